@@ -1,59 +1,201 @@
-use crate::events::{Event, EventBus};
-use anyhow::Result;
-use std::fs;
+//! Backups of files kargo rewrites
+//!
+//! Every run that modifies files records them in a [`Snapshot`] named after
+//! the run and its operation, with the contents stored by hash under
+//! `<config dir>/kargo/snapshots/` (see [`kargo_plugin_api::snapshot`]).
+//! Snapshots outlive the run, so `kargo restore` can preview and restore them
+//! later; only the newest [`KEEP_SNAPSHOTS`] are kept.
+
+use crate::events::{EventBus, EventKind, Source};
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use kargo_plugin_api::snapshot::{Snapshot, SnapshotFile};
+use similar::TextDiff;
 use std::path::{Path, PathBuf};
-use tempfile::TempDir;
 
-#[derive(Debug)]
-pub struct Change {
-    path: PathBuf,
-    backup_path: PathBuf,
+pub use kargo_plugin_api::snapshot::{KEEP_SNAPSHOTS, SnapshotSummary};
+
+/// Unified diff from a file's current contents to its backed-up contents,
+/// i.e. what restoring it would change
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    /// Empty when the file already matches its backup
+    pub unified: String,
+}
+
+/// Selects files by glob. Patterns match the absolute path, or the path
+/// relative to the current directory; no patterns select everything.
+pub struct PathFilter {
+    globs: Option<GlobSet>,
+    base: PathBuf,
+}
+
+impl PathFilter {
+    pub fn new<I, S>(patterns: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+        let mut any = false;
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob '{}'", pattern))?);
+            any = true;
+        }
+        Ok(Self {
+            globs: if any { Some(builder.build()?) } else { None },
+            base: std::env::current_dir()?,
+        })
+    }
+
+    pub fn all() -> Self {
+        Self {
+            globs: None,
+            base: PathBuf::new(),
+        }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(globs) = &self.globs else {
+            return true;
+        };
+        globs.is_match(path)
+            || path
+                .strip_prefix(&self.base)
+                .is_ok_and(|relative| globs.is_match(relative))
+    }
 }
 
 pub struct BackupManager {
-    backup_dir: TempDir,
-    changes: Vec<Change>,
+    snapshot: Snapshot,
     events: EventBus,
 }
 
 impl BackupManager {
-    pub fn new(events: EventBus) -> Result<Self> {
+    /// Start a new snapshot of `operation` in the default snapshot directory
+    pub fn new(operation: &str, events: EventBus) -> Result<Self> {
+        Self::create_in(&Self::default_root(), operation, events)
+    }
+
+    pub fn default_root() -> PathBuf {
+        Snapshot::root(
+            &dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("kargo"),
+        )
+    }
+
+    /// Start a new snapshot of `operation` under `root`. Nothing is written
+    /// until the first file is backed up.
+    pub fn create_in(root: &Path, operation: &str, events: EventBus) -> Result<Self> {
         Ok(Self {
-            backup_dir: TempDir::new()?,
-            changes: Vec::new(),
+            snapshot: Snapshot::create_in(root, operation),
             events,
         })
     }
 
-    pub fn backup_file(&mut self, path: &Path) -> Result<()> {
-        let rel_path = path
-            .file_name()
-            .ok_or_else(|| anyhow::anyhow!("Path has no file name: {}", path.display()))?;
-        let backup_path = self.backup_dir.path().join(rel_path);
+    /// Open the snapshot `id` under `root`, or the newest one
+    pub fn open(root: &Path, id: Option<&str>, events: EventBus) -> Result<Self> {
+        Ok(Self {
+            snapshot: Snapshot::open(root, id)?,
+            events,
+        })
+    }
+
+    /// Snapshots under `root`, oldest first
+    pub fn list(root: &Path) -> Result<Vec<SnapshotSummary>> {
+        Snapshot::list(root)
+    }
+
+    pub fn id(&self) -> &str {
+        self.snapshot.id()
+    }
 
-        fs::copy(path, &backup_path)?;
+    pub fn operation(&self) -> &str {
+        self.snapshot.operation()
+    }
 
-        self.changes.push(Change {
-            path: path.to_owned(),
-            backup_path,
-        });
+    pub fn changes(&self) -> &[SnapshotFile] {
+        self.snapshot.files()
+    }
 
-        Ok(())
+    /// Record `path` in the snapshot. Files already in the snapshot keep their
+    /// first contents, so a restore always goes back to the state before the
+    /// run.
+    pub fn backup_file(&mut self, path: &Path) -> Result<()> {
+        self.snapshot.record(path)
     }
 
+    /// Restore every backed-up file
     pub fn rollback(&self) -> Result<()> {
-        self.events.publish(Event::RollbackStarted {
-            path: self.backup_dir.path().to_owned(),
-        });
+        self.restore_matching(&PathFilter::all()).map(|_| ())
+    }
+
+    /// Restore the backed-up files selected by `filter`, returning their paths
+    pub fn restore_matching(&self, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+        self.events.publish(
+            Source::Backup,
+            EventKind::RollbackStarted {
+                path: self.snapshot.dir(),
+            },
+        );
 
-        for change in &self.changes {
-            fs::copy(&change.backup_path, &change.path)?;
+        let mut restored = Vec::new();
+        for change in self.selected(filter) {
+            self.snapshot.restore(&change.path)?;
+            self.events.publish(
+                Source::Backup,
+                EventKind::FileModified {
+                    path: change.path.clone(),
+                },
+            );
+            restored.push(change.path.clone());
         }
 
-        self.events.publish(Event::RollbackFinished {
-            path: self.backup_dir.path().to_owned(),
-        });
+        self.events.publish(
+            Source::Backup,
+            EventKind::RollbackFinished {
+                path: self.snapshot.dir(),
+            },
+        );
+
+        Ok(restored)
+    }
+
+    /// Diffs of what restoring the files selected by `filter` would change
+    pub fn diffs(&self, filter: &PathFilter) -> Result<Vec<FileDiff>> {
+        self.selected(filter)
+            .map(|change| {
+                let backup =
+                    String::from_utf8_lossy(&self.snapshot.contents(&change.path)?).into_owned();
+                // A file deleted since the backup diffs against nothing
+                let current = std::fs::read_to_string(&change.path).unwrap_or_default();
+                let name = change.path.display().to_string();
+                let unified = TextDiff::from_lines(&current, &backup)
+                    .unified_diff()
+                    .context_radius(3)
+                    .header(
+                        &format!("{} (current)", name),
+                        &format!("{} (backup)", name),
+                    )
+                    .to_string();
+                Ok(FileDiff {
+                    path: change.path.clone(),
+                    unified,
+                })
+            })
+            .collect()
+    }
 
-        Ok(())
+    fn selected<'a>(
+        &'a self,
+        filter: &'a PathFilter,
+    ) -> impl Iterator<Item = &'a SnapshotFile> + 'a {
+        self.snapshot
+            .files()
+            .iter()
+            .filter(move |change| filter.matches(&change.path))
     }
 }
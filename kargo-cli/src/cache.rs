@@ -0,0 +1,176 @@
+//! Cached results of pure plugin invocations
+//!
+//! A plugin that returns a [`CacheSpec`] for an invocation promises that the
+//! same arguments and input contents give the same result. The cache key
+//! hashes the plugin name and version, the arguments, the working directory
+//! and the contents of every input; the stored result is the diagnostics of
+//! the last successful run. Entries live in
+//! `<cache dir>/kargo/plugins/<plugin>/<key>.json` and `--no-cache` bypasses
+//! them.
+
+use anyhow::{Context, Result};
+use kargo_plugin_api::{CacheSpec, Diagnostic, ExecutionContext, WriteGuard};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// FNV-1a, 128-bit: stable across Rust releases, unlike `DefaultHasher`
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    /// Hash `bytes` with its length, so consecutive fields can't run together
+    fn field(&mut self, bytes: &[u8]) {
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRun {
+    /// Unix timestamp (seconds) of the run
+    created: u64,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Where cached results are kept
+#[derive(Debug, Clone)]
+pub struct PluginCache {
+    dir: PathBuf,
+}
+
+/// The cache slot of one invocation
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    path: PathBuf,
+    outputs: Vec<PathBuf>,
+}
+
+impl PluginCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `<cache dir>/kargo/plugins`
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("plugins")
+    }
+
+    /// The entry for running `plugin` as described by `ctx` and `spec`
+    pub fn entry(
+        &self,
+        plugin: &str,
+        spec: &CacheSpec,
+        ctx: &ExecutionContext,
+    ) -> Result<CacheEntry> {
+        let resolve = |path: &PathBuf| ctx.current_dir.join(path);
+        let mut hasher = Fnv128::new();
+        hasher.field(plugin.as_bytes());
+        hasher.field(spec.version.as_bytes());
+        hasher.field(ctx.host_version.as_bytes());
+        hasher.field(ctx.current_dir.to_string_lossy().as_bytes());
+        for arg in &ctx.matched_args {
+            hasher.field(arg.as_bytes());
+        }
+        for input in spec.inputs.iter().map(resolve) {
+            hasher.field(input.to_string_lossy().as_bytes());
+            let mut files = Vec::new();
+            collect_files(&input, &mut files)?;
+            files.sort();
+            for file in files {
+                let content = std::fs::read(&file)
+                    .with_context(|| format!("Failed to read {}", file.display()))?;
+                hasher.field(file.to_string_lossy().as_bytes());
+                hasher.field(&content);
+            }
+        }
+
+        Ok(CacheEntry {
+            path: self
+                .dir
+                .join(plugin)
+                .join(format!("{}.json", hasher.finish())),
+            outputs: spec.outputs.iter().map(resolve).collect(),
+        })
+    }
+}
+
+impl CacheEntry {
+    /// Diagnostics of the cached run, if there is one and its outputs are
+    /// still in place
+    pub fn load(&self) -> Option<Vec<Diagnostic>> {
+        if !self.outputs.iter().all(|output| output.exists()) {
+            return None;
+        }
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str::<CachedRun>(&content) {
+            Ok(run) => Some(run.diagnostics),
+            Err(e) => {
+                log::warn!(
+                    "Ignoring corrupt cache entry {}: {}",
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, diagnostics: &[Diagnostic]) -> Result<()> {
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.path.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        let run = CachedRun {
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            diagnostics: diagnostics.to_vec(),
+        };
+        guard.write(&self.path, serde_json::to_string(&run)?)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Files at or below `path`, skipping `target/` and hidden entries. A
+/// missing input contributes nothing, so creating it changes the key.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    if !path.is_dir() {
+        return Ok(());
+    }
+    for entry in
+        std::fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))?
+    {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        collect_files(&entry.path(), files)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,438 @@
+//! `kargo changelog`: per-member changelogs from conventional commits
+//!
+//! Every workspace member gets its own `CHANGELOG.md`. A commit belongs to
+//! the members whose files it touches (the innermost member, for nested
+//! ones), and only commits since the member's last release tag are
+//! considered: `<name>-v<version>` if the member is tagged on its own, else
+//! the workspace's `v<version>`. Commits are grouped by their
+//! [Conventional Commits](https://www.conventionalcommits.org) type; those
+//! that don't follow it, and types that don't matter to users (`chore`,
+//! `ci`, `test`, ...), are left out.
+//!
+//! The new section is titled with the member's current version, so it's
+//! meant to run after the version bump. Running it again replaces that
+//! section rather than adding another one.
+
+use anyhow::{Context, Result, bail};
+use cargo_metadata::MetadataCommand;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::WriteGuard;
+use regex::Regex;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// Commit types with a changelog section, in the order they're listed
+const SECTIONS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("revert", "Reverts"),
+];
+
+static CONVENTIONAL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<kind>[A-Za-z]+)(?:\((?P<scope>[^)]*)\))?(?P<breaking>!)?: (?P<description>.+)$",
+    )
+    .unwrap()
+});
+
+pub fn command() -> Command {
+    Command::new("changelog")
+        .about("Update each member's CHANGELOG.md from conventional commits")
+        .arg(
+            Arg::new("package")
+                .short('p')
+                .long("package")
+                .value_name("NAME")
+                .help("Member to update (defaults to every member)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("since")
+                .long("since")
+                .value_name("REF")
+                .help("Include commits after this ref instead of each member's last release tag"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print the new sections instead of writing them")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let packages: Vec<String> = matches
+        .get_many::<String>("package")
+        .map(|names| names.cloned().collect())
+        .unwrap_or_default();
+    let since = matches.get_one::<String>("since").map(String::as_str);
+    let dry_run = matches.get_flag("dry-run");
+
+    for changelog in generate(dir, &packages, since)? {
+        let from = changelog.since.as_deref().unwrap_or("the first commit");
+        if changelog.release.commits.is_empty() {
+            println!("{}: no changes since {}", changelog.package, from);
+            continue;
+        }
+        if dry_run {
+            println!("{} ({})", changelog.path.display(), changelog.package);
+            println!("{}", changelog.release.render());
+            continue;
+        }
+        changelog.write()?;
+        println!(
+            "Updated {} ({} {}: {} commit(s) since {})",
+            changelog.path.display(),
+            changelog.package,
+            changelog.release.version,
+            changelog.release.commits.len(),
+            from
+        );
+    }
+    Ok(())
+}
+
+/// A commit following the Conventional Commits format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub hash: String,
+    /// Committer date, `YYYY-MM-DD`
+    pub date: String,
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    /// Parse a commit, or `None` if its subject isn't conventional
+    pub fn parse(hash: &str, date: &str, subject: &str, body: &str) -> Option<Self> {
+        let caps = CONVENTIONAL.captures(subject.trim())?;
+        let breaking = caps.name("breaking").is_some()
+            || body.lines().any(|line| {
+                line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+            });
+        Some(Self {
+            hash: hash.to_string(),
+            date: date.to_string(),
+            kind: caps["kind"].to_lowercase(),
+            scope: caps
+                .name("scope")
+                .map(|s| s.as_str().trim().to_string())
+                .filter(|s| !s.is_empty()),
+            breaking,
+            description: caps["description"].trim().to_string(),
+        })
+    }
+
+    /// Whether the commit is listed in a changelog
+    pub fn is_listed(&self) -> bool {
+        self.breaking || SECTIONS.iter().any(|(kind, _)| *kind == self.kind)
+    }
+
+    fn entry(&self) -> String {
+        let short = &self.hash[..self.hash.len().min(7)];
+        match &self.scope {
+            Some(scope) => format!("- **{}:** {} ({})", scope, self.description, short),
+            None => format!("- {} ({})", self.description, short),
+        }
+    }
+}
+
+/// The changes of one version of a member
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: String,
+    /// Newest commit first
+    pub commits: Vec<ConventionalCommit>,
+}
+
+impl Release {
+    /// The `## <version>` section for the changelog
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        match self.commits.first() {
+            Some(newest) => {
+                let _ = writeln!(out, "## {} - {}", self.version, newest.date);
+            }
+            None => {
+                let _ = writeln!(out, "## {}", self.version);
+            }
+        }
+        let breaking: Vec<&ConventionalCommit> =
+            self.commits.iter().filter(|c| c.breaking).collect();
+        if !breaking.is_empty() {
+            let _ = writeln!(out, "\n### Breaking Changes\n");
+            for commit in breaking {
+                let _ = writeln!(out, "{}", commit.entry());
+            }
+        }
+        for (kind, title) in SECTIONS {
+            let commits: Vec<&ConventionalCommit> =
+                self.commits.iter().filter(|c| c.kind == *kind).collect();
+            if commits.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n### {}\n", title);
+            for commit in commits {
+                let _ = writeln!(out, "{}", commit.entry());
+            }
+        }
+        out
+    }
+}
+
+/// The changelog update of one member
+#[derive(Debug, Clone)]
+pub struct MemberChangelog {
+    pub package: String,
+    pub path: PathBuf,
+    /// Ref the commits were collected after, if any
+    pub since: Option<String>,
+    pub release: Release,
+}
+
+impl MemberChangelog {
+    /// Add the release to the member's changelog, creating it if needed
+    pub fn write(&self) -> Result<()> {
+        let existing = match std::fs::read_to_string(&self.path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+        let updated = insert_release(
+            existing.as_deref(),
+            &self.release.version,
+            &self.release.render(),
+        );
+        WriteGuard::current().write(&self.path, updated)
+    }
+}
+
+/// Collect the release of every member named in `packages` (or every member)
+/// of the workspace in `dir`, without writing anything
+pub fn generate(
+    dir: &Path,
+    packages: &[String],
+    since: Option<&str>,
+) -> Result<Vec<MemberChangelog>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(dir.join("Cargo.toml"))
+        .no_deps()
+        .exec()
+        .with_context(|| format!("cargo metadata failed for {}", dir.display()))?;
+    let toplevel = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+    let toplevel = toplevel.canonicalize().unwrap_or(toplevel);
+
+    // Member directories relative to the repository, to match touched paths
+    let members: Vec<(String, PathBuf)> = metadata
+        .workspace_packages()
+        .iter()
+        .filter_map(|p| {
+            let dir = p.manifest_path.parent()?.as_std_path();
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            let relative = dir.strip_prefix(&toplevel).ok()?.to_path_buf();
+            Some((p.name.to_string(), relative))
+        })
+        .collect();
+    if let Some(missing) = packages
+        .iter()
+        .find(|name| !members.iter().any(|(member, _)| member == *name))
+    {
+        bail!("No workspace member is called {}", missing);
+    }
+
+    let mut changelogs = Vec::new();
+    for package in metadata.workspace_packages() {
+        let name = package.name.to_string();
+        if !packages.is_empty() && !packages.contains(&name) {
+            continue;
+        }
+        let Some((_, relative)) = members.iter().find(|(member, _)| *member == name) else {
+            continue;
+        };
+        let since = match since {
+            Some(since) => Some(since.to_string()),
+            None => last_release_tag(&toplevel, &name)?,
+        };
+        let commits = member_commits(&toplevel, &name, relative, &members, since.as_deref())?;
+        changelogs.push(MemberChangelog {
+            path: toplevel.join(relative).join(CHANGELOG_FILE),
+            package: name,
+            since,
+            release: Release {
+                version: package.version.to_string(),
+                commits,
+            },
+        });
+    }
+    Ok(changelogs)
+}
+
+/// The member owning `path` (relative to the repository): the one with the
+/// longest directory containing it
+pub fn owner<'a>(path: &Path, members: &'a [(String, PathBuf)]) -> Option<&'a str> {
+    members
+        .iter()
+        .filter(|(_, dir)| path.starts_with(dir))
+        .max_by_key(|(_, dir)| dir.components().count())
+        .map(|(name, _)| name.as_str())
+}
+
+/// `existing` changelog with `section` as the entry for `version`: replacing
+/// that version's section if there is one, else above the newest one
+pub fn insert_release(existing: Option<&str>, version: &str, section: &str) -> String {
+    let Some(existing) = existing else {
+        return format!("# Changelog\n\n{}", section);
+    };
+    let is_heading = |line: &str| line.starts_with("## ");
+    let is_version = |line: &str| {
+        line.strip_prefix("## ")
+            .and_then(|rest| rest.split_whitespace().next())
+            .is_some_and(|v| v.trim_start_matches(['v', '[']).trim_end_matches(']') == version)
+    };
+
+    let lines: Vec<&str> = existing.lines().collect();
+    let (start, end) = match lines.iter().position(|line| is_version(line)) {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|line| is_heading(line))
+                .map_or(lines.len(), |i| start + 1 + i);
+            (start, end)
+        }
+        None => {
+            let at = lines
+                .iter()
+                .position(|line| is_heading(line))
+                .unwrap_or(lines.len());
+            (at, at)
+        }
+    };
+
+    let mut out = String::new();
+    for line in &lines[..start] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    out.push_str(section);
+    if end < lines.len() {
+        out.push('\n');
+        for line in &lines[end..] {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Newest tag of the member's releases: `<name>-v*`, else the workspace's `v*`
+fn last_release_tag(toplevel: &Path, name: &str) -> Result<Option<String>> {
+    for pattern in [format!("{}-v*", name), "v*".to_string()] {
+        let output = std::process::Command::new("git")
+            .args(["describe", "--tags", "--abbrev=0", "--match", &pattern])
+            .current_dir(toplevel)
+            .output()
+            .context("Failed to run git describe")?;
+        if output.status.success() {
+            return Ok(Some(
+                String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+/// Listed commits after `since` that touch files `name` owns, newest first
+fn member_commits(
+    toplevel: &Path,
+    name: &str,
+    relative: &Path,
+    members: &[(String, PathBuf)],
+    since: Option<&str>,
+) -> Result<Vec<ConventionalCommit>> {
+    let range = match since {
+        Some(since) => format!("{}..HEAD", since),
+        None => "HEAD".to_string(),
+    };
+    let pathspec = if relative.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        relative.to_string_lossy().into_owned()
+    };
+    // Records are separated by RS and fields by US; the touched files follow
+    // the last field
+    let log = git(
+        toplevel,
+        &[
+            "log",
+            "--no-merges",
+            "--name-only",
+            "--format=%x1e%H%x1f%cs%x1f%s%x1f%b%x1f",
+            &range,
+            "--",
+            &pathspec,
+        ],
+    )?;
+
+    let mut commits = Vec::new();
+    for record in log.split('\x1e').filter(|r| !r.trim().is_empty()) {
+        let fields: Vec<&str> = record.splitn(5, '\x1f').collect();
+        let [hash, date, subject, body, files] = fields[..] else {
+            continue;
+        };
+        let owned = files
+            .lines()
+            .filter(|file| !file.is_empty())
+            .any(|file| owner(Path::new(file), members) == Some(name));
+        if !owned {
+            continue;
+        }
+        match ConventionalCommit::parse(hash, date, subject, body) {
+            Some(commit) if commit.is_listed() => commits.push(commit),
+            _ => log::debug!(
+                "Not listing {} in the changelog of {}: {}",
+                hash,
+                name,
+                subject
+            ),
+        }
+    }
+    Ok(commits)
+}
+
+pub(crate) fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| format!("Failed to run git {}", args[0]))?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,498 @@
+//! Turning a run's events into its exit status and CI output
+//!
+//! [`CiReporter`] listens on the [`EventBus`] for the whole run. Under `--ci`
+//! it annotates failures as GitHub workflow commands and appends a markdown
+//! step summary. Either way it builds the [`StatusReport`] that decides the
+//! exit code, so a reporter that fell behind and missed events fails the run
+//! rather than report a success it can't vouch for.
+
+use anyhow::Result;
+use kargo_plugin_api::Severity as PluginSeverity;
+use kargo_plugin_api::artifacts::CapturedOutput;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::events::{Event, EventBus, EventKind};
+use crate::status::{
+    Diagnostic, ExitStatus, OutdatedDependency, STATUS_REPORT_VERSION, Severity, StatusReport,
+    VulnerableDependency,
+};
+
+/// A diagnostic rendered as a GitHub workflow command
+fn render_annotation(diagnostic: &Diagnostic) -> String {
+    let mut props = Vec::new();
+    if let Some(file) = &diagnostic.file {
+        props.push(format!("file={}", escape_property(&file.to_string_lossy())));
+    }
+    if let Some(line) = diagnostic.line {
+        props.push(format!("line={line}"));
+    }
+    if let Some(col) = diagnostic.col {
+        props.push(format!("col={col}"));
+    }
+    if let Some(title) = &diagnostic.title {
+        props.push(format!("title={}", escape_property(title)));
+    }
+    let props = if props.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", props.join(","))
+    };
+    let command = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    format!(
+        "::{}{}::{}",
+        command,
+        props,
+        escape_data(&diagnostic.message)
+    )
+}
+
+/// Collects events published during a run and turns them into an exit
+/// status and [`StatusReport`]. In `--ci` mode it also prints workflow
+/// annotations and writes a markdown step summary.
+pub struct CiReporter {
+    annotate: bool,
+    summary_path: Option<PathBuf>,
+    started: Instant,
+    command: Option<String>,
+    args: Vec<String>,
+    commands_succeeded: usize,
+    command_failed: bool,
+    annotations: Vec<Diagnostic>,
+    pending: Option<Diagnostic>,
+    outdated: Vec<OutdatedDependency>,
+    vulnerable: Vec<VulnerableDependency>,
+    outputs: Vec<CapturedOutput>,
+    /// Events the reporter fell too far behind to receive
+    events_lost: u64,
+}
+
+impl CiReporter {
+    /// Create a reporter writing its summary to `summary_path`, falling back to
+    /// `$GITHUB_STEP_SUMMARY` when no path is given
+    pub fn new(summary_path: Option<PathBuf>) -> Self {
+        let summary_path =
+            summary_path.or_else(|| std::env::var_os("GITHUB_STEP_SUMMARY").map(PathBuf::from));
+        Self {
+            annotate: true,
+            summary_path,
+            started: Instant::now(),
+            command: None,
+            args: Vec::new(),
+            commands_succeeded: 0,
+            command_failed: false,
+            annotations: Vec::new(),
+            pending: None,
+            outdated: Vec::new(),
+            vulnerable: Vec::new(),
+            outputs: Vec::new(),
+            events_lost: 0,
+        }
+    }
+
+    /// Collect events for the exit status and status report only, without
+    /// annotations or a step summary
+    pub fn silent() -> Self {
+        Self {
+            annotate: false,
+            summary_path: None,
+            ..Self::new(None)
+        }
+    }
+
+    /// Consume events from the bus until every sender has been dropped
+    pub fn spawn(mut self, events: &EventBus) -> JoinHandle<CiReporter> {
+        let mut rx = events.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => self.record(&event),
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("CI reporter missed {} events", skipped);
+                        self.events_lost += skipped;
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            self
+        })
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        match &event.kind {
+            EventKind::KargoCommandStarted { subcommand, args } => {
+                self.command = Some(subcommand.clone());
+                self.args = args.clone();
+            }
+            EventKind::KargoCommandFinished { success: true, .. } => {
+                self.commands_succeeded += 1;
+            }
+            EventKind::KargoCommandFinished { success: false, .. } => {
+                self.command_failed = true;
+            }
+            EventKind::ScheduledJobFinished {
+                exit_code: 0 | 2, ..
+            } => {
+                self.commands_succeeded += 1;
+            }
+            EventKind::ScheduledJobFinished { .. } => {
+                self.command_failed = true;
+            }
+            EventKind::KargoOutputLine { line, .. } => self.record_output_line(line),
+            EventKind::CommandFinished {
+                output: Some(output),
+                ..
+            } => self.outputs.push(output.clone()),
+            EventKind::PluginDiagnostic { plugin, diagnostic } => {
+                let severity = match diagnostic.severity {
+                    PluginSeverity::Error => Severity::Error,
+                    PluginSeverity::Warning => Severity::Warning,
+                    PluginSeverity::Note => return,
+                };
+                self.emit(Diagnostic {
+                    severity,
+                    file: diagnostic.file.clone(),
+                    line: diagnostic.line,
+                    col: diagnostic.column,
+                    title: Some(format!("{plugin}: {}", diagnostic.code)),
+                    message: diagnostic.message.clone(),
+                });
+            }
+            EventKind::Error { message } => self.emit(Diagnostic {
+                severity: Severity::Error,
+                file: None,
+                line: None,
+                col: None,
+                title: None,
+                message: message.clone(),
+            }),
+            EventKind::DependencyOutdated {
+                path,
+                name,
+                current,
+                latest,
+            } => {
+                self.emit(Diagnostic {
+                    severity: Severity::Warning,
+                    file: Some(path.clone()),
+                    line: manifest_line(path, name),
+                    col: None,
+                    title: Some(format!("Outdated dependency: {name}")),
+                    message: format!("{name} {current} can be updated to {latest}"),
+                });
+                self.outdated.push(OutdatedDependency {
+                    path: path.clone(),
+                    name: name.clone(),
+                    current: current.clone(),
+                    latest: latest.clone(),
+                });
+            }
+            EventKind::VulnerableDependency {
+                path,
+                name,
+                version,
+                advisory,
+            } => {
+                self.emit(Diagnostic {
+                    severity: Severity::Error,
+                    file: Some(path.clone()),
+                    line: manifest_line(path, name),
+                    col: None,
+                    title: Some(format!("Vulnerable dependency: {name}")),
+                    message: format!("{name} {version} is affected by {advisory}"),
+                });
+                self.vulnerable.push(VulnerableDependency {
+                    path: path.clone(),
+                    name: name.clone(),
+                    version: version.clone(),
+                    advisory: advisory.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Parse rustc/cargo human-readable output into annotations.
+    ///
+    /// A diagnostic header (`error[E0308]: ...`) is held until the following
+    /// `--> file:line:col` line supplies its location.
+    fn record_output_line(&mut self, line: &str) {
+        if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            if let Some(mut pending) = self.pending.take() {
+                let mut parts = location.rsplitn(3, ':');
+                let col = parts.next().and_then(|c| c.parse().ok());
+                let line_no = parts.next().and_then(|l| l.parse().ok());
+                pending.file = parts.next().map(PathBuf::from);
+                pending.line = line_no;
+                pending.col = col;
+                self.emit(pending);
+            }
+            return;
+        }
+
+        let (severity, rest) = if let Some(rest) = line.strip_prefix("error") {
+            (Severity::Error, rest)
+        } else if let Some(rest) = line.strip_prefix("warning") {
+            (Severity::Warning, rest)
+        } else {
+            return;
+        };
+
+        let (code, message) = match rest.strip_prefix('[') {
+            Some(rest) => match rest.split_once("]: ") {
+                Some((code, message)) => (Some(code.to_string()), message),
+                None => return,
+            },
+            None => match rest.strip_prefix(": ") {
+                Some(message) => (None, message),
+                None => return,
+            },
+        };
+
+        self.flush_pending();
+        if is_summary_line(message) {
+            return;
+        }
+        self.pending = Some(Diagnostic {
+            severity,
+            file: None,
+            line: None,
+            col: None,
+            title: code,
+            message: message.to_string(),
+        });
+    }
+
+    fn flush_pending(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            self.emit(pending);
+        }
+    }
+
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        if self.annotate {
+            println!("{}", render_annotation(&diagnostic));
+        }
+        self.annotations.push(diagnostic);
+    }
+
+    /// Work out the exit status, write the step summary and build the report
+    pub fn finish(mut self, result: &Result<()>) -> Result<StatusReport> {
+        self.flush_pending();
+        // Only annotate the bare failure when nothing more specific was reported
+        if let Err(e) = result
+            && self.count(Severity::Error) == 0
+        {
+            self.emit(Diagnostic {
+                severity: Severity::Error,
+                file: None,
+                line: None,
+                col: None,
+                title: None,
+                message: format!("{:#}", e),
+            });
+        }
+        // A missed event may have been the failure
+        if self.events_lost > 0 {
+            self.emit(Diagnostic {
+                severity: Severity::Error,
+                file: None,
+                line: None,
+                col: None,
+                title: None,
+                message: format!(
+                    "Missed {} events, so the run can't be reported as successful",
+                    self.events_lost
+                ),
+            });
+        }
+
+        // Vulnerable dependencies are annotated as errors, but like outdated
+        // ones they only mean updates are available
+        let failures = self
+            .count(Severity::Error)
+            .saturating_sub(self.vulnerable.len());
+        let status = match result {
+            Err(e) => match ExitStatus::from_error(e) {
+                ExitStatus::Failure if self.commands_succeeded > 0 => ExitStatus::PartialFailure,
+                status => status,
+            },
+            Ok(()) if self.command_failed || failures > 0 => ExitStatus::PartialFailure,
+            Ok(()) if !self.outdated.is_empty() || !self.vulnerable.is_empty() => {
+                ExitStatus::UpdatesAvailable
+            }
+            Ok(()) => ExitStatus::Success,
+        };
+
+        if let Some(path) = &self.summary_path {
+            let summary = self.summary(status);
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(summary.as_bytes())?;
+        }
+
+        Ok(StatusReport {
+            version: STATUS_REPORT_VERSION,
+            status,
+            exit_code: status.code(),
+            command: self.command,
+            args: self.args,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+            diagnostics: self.annotations,
+            outdated: self.outdated,
+            vulnerable: self.vulnerable,
+            outputs: self.outputs,
+        })
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.annotations
+            .iter()
+            .filter(|a| a.severity == severity)
+            .count()
+    }
+
+    fn summary(&self, status: ExitStatus) -> String {
+        let mut out = String::new();
+        let command = self.command.as_deref().unwrap_or("run");
+        let _ = writeln!(out, "## kargo {command}\n");
+        let _ = writeln!(out, "**Status:** {}\n", status.label());
+        let _ = writeln!(
+            out,
+            "| Errors | Warnings | Outdated | Vulnerable |\n|---|---|---|---|\n| {} | {} | {} | {} |\n",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.outdated.len(),
+            self.vulnerable.len()
+        );
+
+        if !self.vulnerable.is_empty() {
+            let _ = writeln!(
+                out,
+                "### Vulnerable dependencies\n\n| Crate | Version | Advisory | Manifest |\n|---|---|---|---|"
+            );
+            for dep in &self.vulnerable {
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} | {} | `{}` |",
+                    dep.name,
+                    dep.version,
+                    dep.advisory,
+                    dep.path.display()
+                );
+            }
+            out.push('\n');
+        }
+
+        if !self.outdated.is_empty() {
+            let _ = writeln!(
+                out,
+                "### Outdated dependencies\n\n| Crate | Current | Latest | Manifest |\n|---|---|---|---|"
+            );
+            for dep in &self.outdated {
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} | {} | `{}` |",
+                    dep.name,
+                    dep.current,
+                    dep.latest,
+                    dep.path.display()
+                );
+            }
+            out.push('\n');
+        }
+
+        let failed: Vec<_> = self
+            .outputs
+            .iter()
+            .filter(|output| output.exit_code != Some(0))
+            .collect();
+        if !failed.is_empty() {
+            let _ = writeln!(
+                out,
+                "### Failed commands\n\n| Command | Exit code | Output |\n|---|---|---|"
+            );
+            for output in failed {
+                let exit_code = output
+                    .exit_code
+                    .map_or_else(|| "killed".to_string(), |code| code.to_string());
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} | `{}` |",
+                    output.command,
+                    exit_code,
+                    output.stderr.display()
+                );
+            }
+            out.push('\n');
+        }
+
+        let located: Vec<_> = self
+            .annotations
+            .iter()
+            .filter(|a| a.severity == Severity::Error && a.file.is_some())
+            .collect();
+        if !located.is_empty() {
+            let _ = writeln!(out, "### Errors\n");
+            for a in located {
+                let file = a.file.as_ref().map(|f| f.display().to_string());
+                let _ = writeln!(
+                    out,
+                    "- `{}:{}` {}",
+                    file.unwrap_or_default(),
+                    a.line.unwrap_or(0),
+                    a.message
+                );
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+/// Cargo's trailing "could not compile"/"aborting due to" lines repeat the
+/// diagnostics above them and would double-count errors
+fn is_summary_line(message: &str) -> bool {
+    message.starts_with("could not compile")
+        || message.starts_with("aborting due to")
+        || (message.starts_with('`') && message.contains("generated"))
+}
+
+/// Best-effort lookup of the line declaring `name` in a manifest
+fn manifest_line(path: &Path, name: &str) -> Option<usize> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .position(|line| {
+            let line = line.trim_start();
+            line.strip_prefix(name)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+                || line.ends_with(&format!(".{name}]"))
+        })
+        .map(|idx| idx + 1)
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
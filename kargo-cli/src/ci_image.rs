@@ -0,0 +1,196 @@
+//! `kargo ci-image`: container images pinning kargo, its plugins and config
+//! for CI (see [`crate::distribution`])
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, requested_profile};
+use crate::distribution::{self, CONFIG_FILE, ImageSpec, find_plugin_artifacts, select_plugins};
+use crate::plugins::manager::PluginManager;
+use crate::status::ConfigError;
+
+pub fn command() -> Command {
+    Command::new("ci-image")
+        .about("Build and verify container images for reproducible CI runs")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("build")
+                .about("Write an OCI image with this kargo, its plugins and config")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Directory to write the OCI image layout to")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value("kargo-image"),
+                )
+                .arg(Arg::new("tag").long("tag").value_name("TAG").help(
+                    "Reference name of the image in the layout (defaults to the kargo version)",
+                ))
+                .arg(
+                    Arg::new("plugin")
+                        .long("plugin")
+                        .value_name("NAME")
+                        .help(
+                            "Plugin to include; defaults to every built plugin the config enables",
+                        )
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .value_name("FILE")
+                        .help("Config to bake (defaults to the active config file)")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("no-config")
+                        .long("no-config")
+                        .help("Don't bake a config; the image runs with the defaults")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("config"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check that this runner is the image with the expected content digest")
+                .arg(
+                    Arg::new("digest")
+                        .long("digest")
+                        .value_name("DIGEST")
+                        .help("Content digest printed by `kargo ci-image build`")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .help("Where the image installs kargo (defaults to the running binary's)")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+}
+
+pub fn run(pm: &PluginManager, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("build", sub)) => build(pm, sub),
+        Some(("verify", sub)) => verify(sub),
+        _ => unreachable!("subcommand_required"),
+    }
+}
+
+fn build(pm: &PluginManager, matches: &ArgMatches) -> Result<()> {
+    if !cfg!(target_os = "linux") {
+        bail!("Images run this kargo binary, so they have to be built on Linux");
+    }
+    let output = matches.get_one::<PathBuf>("output").expect("has default");
+    let version = env!("CARGO_PKG_VERSION");
+
+    // The profile selected with `kargo --profile` is baked in as the default
+    let config_path = match matches.get_one::<PathBuf>("config") {
+        Some(path) => Some(path.clone()),
+        None if matches.get_flag("no-config") => None,
+        None => Some(Config::path()).filter(|path| path.exists()),
+    };
+    let (config, profile, enabled) = match &config_path {
+        Some(path) => {
+            let mut config = Config::from_file(path)?;
+            let profile = requested_profile().or_else(|| config.default_profile.clone());
+            // The config as the image will see it, to pick its plugins
+            let enabled = match &profile {
+                Some(name) => Config::from_file(path)?
+                    .with_profile(name)
+                    .map_err(|e| ConfigError::new(path, e))?,
+                None => Config::from_file(path)?,
+            };
+            config.default_profile = profile.clone();
+            (Some(config), profile, Some(enabled))
+        }
+        None => (None, None, None),
+    };
+
+    let wanted: Vec<String> = matches
+        .get_many::<String>("plugin")
+        .map(|names| names.cloned().collect())
+        .unwrap_or_default();
+    let available = find_plugin_artifacts(pm.search_paths());
+    let plugins = select_plugins(&available, &wanted, |name| {
+        enabled
+            .as_ref()
+            .is_none_or(|config| config.allows_plugin(name))
+    })?;
+
+    let spec = ImageSpec {
+        kargo: std::env::current_exe().context("Failed to locate the kargo binary")?,
+        kargo_version: version.to_string(),
+        plugins,
+        config: config.as_ref().map(serde_yaml::to_string).transpose()?,
+        profile,
+        tag: matches
+            .get_one::<String>("tag")
+            .cloned()
+            .unwrap_or_else(|| version.to_string()),
+    };
+    let image = spec.write_layout(output)?;
+
+    println!("Image written to {} (OCI layout)", output.display());
+    println!("  kargo    {}", version);
+    for plugin in &spec.plugins {
+        println!("  plugin   {} ({})", plugin.name, plugin.path.display());
+    }
+    if let Some(path) = &config_path {
+        match &spec.profile {
+            Some(profile) => println!("  config   {} (profile {})", path.display(), profile),
+            None => println!("  config   {}", path.display()),
+        }
+    }
+    println!("  image    {}", image.manifest_digest);
+    println!("  content  {}", image.contents.digest());
+    println!();
+    println!(
+        "Push it with `skopeo copy oci:{}:{} docker://REGISTRY/kargo:{}`, and check runners with \
+         `kargo ci-image verify --digest {}`",
+        output.display(),
+        spec.tag,
+        spec.tag,
+        image.contents.digest()
+    );
+    Ok(())
+}
+
+fn verify(matches: &ArgMatches) -> Result<()> {
+    let expected = matches.get_one::<String>("digest").expect("required");
+    let dir = match matches.get_one::<PathBuf>("dir") {
+        Some(dir) => dir.clone(),
+        // The binary is installed as <dir>/bin/kargo
+        None => std::env::current_exe()?
+            .parent()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .context("Can't tell where kargo is installed; pass --dir")?,
+    };
+
+    let mut problems = distribution::verify(&dir, env!("CARGO_PKG_VERSION"), expected)?;
+    let baked = dir.join(CONFIG_FILE);
+    if baked.exists() && Config::path() != baked {
+        problems.push(format!(
+            "the config is read from {}, not the image's {}",
+            Config::path().display(),
+            baked.display()
+        ));
+    }
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("✗ {}", problem);
+        }
+        bail!(
+            "This runner doesn't match image {} ({} difference(s))",
+            expected,
+            problems.len()
+        );
+    }
+    println!("✓ This runner matches image {}", expected);
+    Ok(())
+}
@@ -1,10 +1,55 @@
 use anyhow::Result;
+use clap::error::{ContextKind, ContextValue};
 use clap::{ArgMatches, Command};
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Instant,
+};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tracing::Instrument;
 use which::which;
 
+use crate::cache::PluginCache;
+use crate::changelog;
+use crate::ci_image;
+use crate::config::Config;
+use crate::cost;
+use crate::daemon;
+use crate::diff_manifests;
+use crate::digest;
+use crate::doctor;
+use crate::events::{EventBus, EventKind, Source};
+use crate::explain;
+use crate::features;
+use crate::fleet;
+use crate::graph;
+use crate::history;
+use crate::import_config;
+use crate::logging::{LogFormat, plugin_target};
+use crate::manifest;
+use crate::metrics::{self, Metrics, Phase, PluginSample};
+use crate::migrate_edition;
+use crate::plugin;
+use crate::plugins::hooks;
 use crate::plugins::manager::PluginManager;
-use kargo_plugin_api::ExecutionContext;
+use crate::publish_check;
+use crate::remote_config;
+use crate::restore;
+use crate::run_log;
+use crate::schedule;
+use crate::secret;
+use crate::shared_target::SharedTarget;
+use crate::size_report;
+use crate::stale;
+use crate::verify_vendored;
+use crate::why;
+use kargo_plugin_api::profile::{self, Profiler};
+use kargo_plugin_api::{
+    Diagnostic, DiagnosticSink, Example, ExecutionContext, NetworkConfig, PluginArgs,
+    PluginCommand, WriteGuard,
+};
 
 pub fn build_root_cli(pm: &PluginManager) -> Command {
     let mut root = Command::new("kargo")
@@ -17,24 +62,299 @@ pub fn build_root_cli(pm: &PluginManager) -> Command {
                 .action(clap::ArgAction::SetTrue)
                 .conflicts_with("help"),
         )
+        .arg(
+            clap::Arg::new("ci")
+                .long("ci")
+                .help("CI mode: emit GitHub annotations and write a step summary")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("summary-file")
+                .long("summary-file")
+                .value_name("PATH")
+                .help("Where to write the markdown step summary (defaults to $GITHUB_STEP_SUMMARY)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .requires("ci"),
+        )
+        .arg(
+            clap::Arg::new("status-file")
+                .long("status-file")
+                .value_name("PATH")
+                .help("Write a JSON summary of the run to PATH when the command finishes")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("profile-run")
+                .long("profile-run")
+                .value_name("PATH")
+                .help("Print where the command spent its time; with =PATH, also write folded stacks for a flamegraph")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value(""),
+        )
+        .arg(
+            clap::Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Config profile to use (defaults to KARGO_PROFILE, then default_profile)"),
+        )
+        .arg(
+            clap::Arg::new("read-only")
+                .long("read-only")
+                .help("Refuse every file write, for audits and evaluating untrusted plugins")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("offline")
+                .long("offline")
+                .help("Fail instead of sending network requests (also CARGO_NET_OFFLINE=true)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no-cache")
+                .long("no-cache")
+                .help("Run plugins even when a cached result for unchanged inputs exists")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .help("Log output format; the filter comes from KARGO_LOG or RUST_LOG")
+                .value_parser(clap::value_parser!(LogFormat))
+                .default_value("text"),
+        )
+        .after_help(
+            "Exit codes: 0 success, 1 failure, 2 updates available, 3 partial failure, \
+             4 configuration error, 5 usage error",
+        )
         .subcommand_required(false) // Don't require subcommand when using --alias
         .arg_required_else_help(true)
         .allow_external_subcommands(true);
 
-    root = root.subcommand(
-        Command::new("cargo")
-            .about("Forward arbitrary cargo sub-commands")
-            .trailing_var_arg(true)
-            .allow_external_subcommands(true),
-    );
-
+    let mut commands: Vec<(Command, Option<String>)> = vec![
+        (fleet::command(), Some("fleet".to_string())),
+        (schedule::command(), Some("fleet".to_string())),
+        (digest::command(), Some("fleet".to_string())),
+        (ci_image::command(), Some("fleet".to_string())),
+        (migrate_edition::command(), Some("fleet".to_string())),
+        (stale::command(), Some("fleet".to_string())),
+        (restore::command(), Some("deps".to_string())),
+        (history::command(), Some("deps".to_string())),
+        (why::command(), Some("deps".to_string())),
+        (graph::command(), Some("deps".to_string())),
+        (features::command(), Some("deps".to_string())),
+        (import_config::command(), Some("deps".to_string())),
+        (remote_config::command(), None),
+        (cost::command(), Some("deps".to_string())),
+        (manifest::command(), Some("deps".to_string())),
+        (diff_manifests::command(), Some("deps".to_string())),
+        (publish_check::command(), Some("deps".to_string())),
+        (verify_vendored::command(), Some("deps".to_string())),
+        (changelog::command(), Some("docs".to_string())),
+        (size_report::command(), None),
+        (doctor::command(), None),
+        (run_log::command(), None),
+        (explain::command(), None),
+        (plugin::command(), None),
+        (daemon::command(), None),
+        (secret::command(), None),
+        (
+            Command::new("cargo")
+                .about("Forward arbitrary cargo sub-commands")
+                .trailing_var_arg(true)
+                .allow_external_subcommands(true),
+            None,
+        ),
+    ];
     for (_, plugin) in pm.plugins_iter() {
-        root = root.subcommand(plugin.clap());
+        let help = plugin.help_info().unwrap_or_default();
+        commands.push((with_examples(plugin.clap(), &help.examples), help.category));
+    }
+
+    root = hooks::root_flags(pm, root);
+    let template = grouped_help_template(&root, &commands);
+    root = root.help_template(template);
+    for (command, _) in commands {
+        root = root.subcommand(command);
     }
     root
 }
 
-async fn proxy_to_cargo(command: &str, args: &ArgMatches) -> Result<()> {
+/// Headings of the command groups in `kargo --help`, in order. Commands in
+/// other categories follow under their category's name; uncategorized ones
+/// come last, under "Commands".
+const CATEGORIES: &[(&str, &str)] = &[
+    ("deps", "Dependencies"),
+    ("docs", "Documentation"),
+    ("agents", "Agents"),
+    ("fleet", "Fleet"),
+];
+
+/// Clap lists subcommands in a single section, so the root help template
+/// spells out the grouped list itself
+fn grouped_help_template(root: &Command, commands: &[(Command, Option<String>)]) -> String {
+    let mut groups: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    let visible = commands
+        .iter()
+        .filter(|(command, _)| !command.is_hide_set());
+    let entries = visible
+        .map(|(command, category)| {
+            let about = command
+                .get_about()
+                .map(|a| a.to_string())
+                .unwrap_or_default();
+            (command.get_name().to_string(), about, category.as_deref())
+        })
+        .chain(std::iter::once((
+            "help".to_string(),
+            "Print this message or the help of the given subcommand(s)".to_string(),
+            None,
+        )));
+    for (name, about, category) in entries {
+        let heading = category_heading(category);
+        match groups.iter_mut().find(|(h, _)| *h == heading) {
+            Some((_, members)) => members.push((name, about)),
+            None => groups.push((heading, vec![(name, about)])),
+        }
+    }
+    let rank = |heading: &str| {
+        CATEGORIES
+            .iter()
+            .position(|(_, h)| *h == heading)
+            .unwrap_or(if heading == "Commands" {
+                usize::MAX
+            } else {
+                CATEGORIES.len()
+            })
+    };
+    groups.sort_by_key(|(heading, _)| rank(heading));
+
+    let styles = root.get_styles();
+    let (header, literal) = (styles.get_header(), styles.get_literal());
+    let width = groups
+        .iter()
+        .flat_map(|(_, members)| members.iter().map(|(name, _)| name.len()))
+        .max()
+        .unwrap_or(0);
+    let mut template =
+        String::from("{before-help}{about-with-newline}\n{usage-heading} {usage}\n\n");
+    for (heading, members) in &groups {
+        template.push_str(&format!("{header}{heading}:{header:#}\n"));
+        for (name, about) in members {
+            let padding = " ".repeat(width - name.len());
+            template.push_str(&format!("  {literal}{name}{literal:#}{padding}  {about}\n"));
+        }
+        template.push('\n');
+    }
+    template.push_str(&format!(
+        "{header}Options:{header:#}\n{{options}}{{after-help}}"
+    ));
+    template
+}
+
+fn category_heading(category: Option<&str>) -> String {
+    match category {
+        None => "Commands".to_string(),
+        Some(category) => match CATEGORIES.iter().find(|(c, _)| *c == category) {
+            Some((_, heading)) => heading.to_string(),
+            None => {
+                let mut chars = category.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+        },
+    }
+}
+
+/// Append a plugin's usage examples to its long help (`--help`)
+fn with_examples(command: Command, examples: &[Example]) -> Command {
+    if examples.is_empty() {
+        return command;
+    }
+    let mut text = command
+        .get_after_long_help()
+        .map(|help| format!("{}\n\n", help))
+        .unwrap_or_default();
+    let header = command.get_styles().get_header();
+    text.push_str(&format!("{header}Examples:{header:#}"));
+    for example in examples {
+        text.push_str(&format!(
+            "\n  {}\n      {}",
+            example.command, example.description
+        ));
+    }
+    command.after_long_help(text)
+}
+
+/// Value of a root `--flag VALUE`/`--flag=VALUE` taken from the raw arguments,
+/// for settings needed before the root command can be built or parsed
+pub fn prescan_flag(args: impl IntoIterator<Item = String>, flag: &str) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some(rest) = arg.strip_prefix(flag) else {
+            continue;
+        };
+        if rest.is_empty() {
+            return args.next();
+        }
+        if let Some(value) = rest.strip_prefix('=') {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// `args` with `default` put in front of what the root command has no use
+/// for: no arguments at all, an unknown flag or an unknown command. Help, the
+/// version and known commands and plugins are left as they are; cargo
+/// commands stay available as `kargo cargo <command>`.
+pub fn with_default_command(root: &Command, mut args: Vec<String>, default: &str) -> Vec<String> {
+    if root.find_subcommand(default).is_none() {
+        log::warn!(
+            "default_command '{}' is not a kargo command or plugin",
+            default
+        );
+        return args;
+    }
+    let mut position = 1;
+    while let Some(arg) = args.get(position) {
+        if matches!(
+            arg.as_str(),
+            "-h" | "--help" | "-V" | "--version" | "--alias"
+        ) {
+            return args;
+        }
+        let Some(long) = arg.strip_prefix("--") else {
+            // A command (or an unknown short flag, the root has none)
+            if arg == "help" || root.find_subcommand(arg).is_some() {
+                return args;
+            }
+            break;
+        };
+        let (name, value) = match long.split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (long, false),
+        };
+        let Some(flag) = root.get_arguments().find(|a| a.get_long() == Some(name)) else {
+            break;
+        };
+        let separate_value =
+            !value && flag.get_action().takes_values() && !flag.is_require_equals_set();
+        position += if separate_value { 2 } else { 1 };
+    }
+    args.insert(position.min(args.len()), default.to_string());
+    args
+}
+
+async fn proxy_to_cargo(
+    command: &str,
+    args: &ArgMatches,
+    events: &EventBus,
+    capture: bool,
+) -> Result<()> {
     // Find cargo binary in PATH
     let cargo_path = which("cargo")
         .map_err(|e| anyhow::anyhow!("Failed to find cargo binary in PATH: {}", e))?;
@@ -43,15 +363,61 @@ async fn proxy_to_cargo(command: &str, args: &ArgMatches) -> Result<()> {
 
     // Gather additional arguments
     if let Some((_, sub_args)) = args.subcommand() {
-        cargo_args.extend(gather_raw_args(sub_args));
+        cargo_args.extend(gather_raw_args(command, sub_args));
     } else {
-        cargo_args.extend(gather_raw_args(args));
+        cargo_args.extend(gather_raw_args(command, args));
     }
 
-    let status = tokio::process::Command::new(&cargo_path)
-        .args(cargo_args)
-        .status()
-        .await?;
+    run_cargo(&cargo_path, cargo_args, events, capture).await
+}
+
+/// Run cargo, optionally capturing its output line by line onto the event bus
+/// (used by `--ci` to turn compiler diagnostics into annotations)
+async fn run_cargo(
+    cargo_path: &Path,
+    args: Vec<String>,
+    events: &EventBus,
+    capture: bool,
+) -> Result<()> {
+    let subcommand = args.first().cloned().unwrap_or_default();
+    events.publish(
+        Source::Cli,
+        EventKind::KargoCommandStarted {
+            subcommand: subcommand.clone(),
+            args: args.clone(),
+        },
+    );
+
+    let status = if capture {
+        let mut child = tokio::process::Command::new(cargo_path)
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let (out, err) = tokio::join!(
+            forward_lines(stdout, events, false),
+            forward_lines(stderr, events, true)
+        );
+        out?;
+        err?;
+        child.wait().await?
+    } else {
+        tokio::process::Command::new(cargo_path)
+            .args(&args)
+            .status()
+            .await?
+    };
+
+    events.publish(
+        Source::Cli,
+        EventKind::KargoCommandFinished {
+            subcommand,
+            success: status.success(),
+            summary: format!("cargo exited with {:?}", status.code()),
+        },
+    );
 
     if !status.success() {
         anyhow::bail!("cargo exited with {:?}", status.code());
@@ -60,7 +426,31 @@ async fn proxy_to_cargo(command: &str, args: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
-pub async fn dispatch(pm: &PluginManager, matches: &ArgMatches) -> Result<()> {
+async fn forward_lines<R: AsyncRead + Unpin>(
+    reader: R,
+    events: &EventBus,
+    is_error: bool,
+) -> Result<()> {
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if is_error {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        events.publish(Source::Cli, EventKind::KargoOutputLine { line, is_error });
+    }
+    Ok(())
+}
+
+pub async fn dispatch(
+    pm: &mut PluginManager,
+    matches: &ArgMatches,
+    events: &EventBus,
+) -> Result<()> {
+    let capture = matches.get_flag("ci");
+    let cache =
+        (!matches.get_flag("no-cache")).then(|| PluginCache::new(PluginCache::default_dir()));
     match matches.subcommand() {
         Some(("cargo", sub)) => {
             // Find cargo binary in PATH
@@ -73,35 +463,61 @@ pub async fn dispatch(pm: &PluginManager, matches: &ArgMatches) -> Result<()> {
                 if let Some(values) = ext_args.get_many::<std::ffi::OsString>("") {
                     args.extend(values.map(|s| s.to_string_lossy().to_string()));
                 }
-                let status = tokio::process::Command::new(&cargo_path)
-                    .args(args)
-                    .status()
-                    .await?;
-                if !status.success() {
-                    anyhow::bail!("cargo exited with {:?}", status.code());
-                }
+                run_cargo(&cargo_path, args, events, capture).await?;
             } else {
                 anyhow::bail!("No cargo subcommand provided");
             }
         }
+        Some(("fleet", sub)) => fleet::run(pm, sub, events, cache.as_ref()).await?,
+        Some(("schedule", sub)) => schedule::run(sub, events).await?,
+        Some(("digest", sub)) => digest::run(sub).await?,
+        Some(("stale", sub)) => stale::run(sub)?,
+        Some(("ci-image", sub)) => ci_image::run(pm, sub)?,
+        Some(("migrate-edition", sub)) => migrate_edition::run(sub, events).await?,
+        Some(("restore", sub)) => restore::run(sub, events)?,
+        Some(("history", sub)) => history::run(sub)?,
+        Some(("why", sub)) => why::run(sub)?,
+        Some(("graph", sub)) => graph::run(sub)?,
+        Some(("features", sub)) => features::run(sub)?,
+        Some(("import-config", sub)) => import_config::run(sub)?,
+        Some(("config", sub)) => remote_config::run(sub).await?,
+        Some(("cost", sub)) => cost::run(sub)?,
+        Some(("explain", sub)) => explain::run(pm, sub)?,
+        Some(("manifest", sub)) => manifest::run(sub)?,
+        Some(("diff-manifests", sub)) => diff_manifests::run(sub)?,
+        Some(("publish-check", sub)) => publish_check::run(sub)?,
+        Some(("verify-vendored", sub)) => verify_vendored::run(sub)?,
+        Some(("changelog", sub)) => changelog::run(sub)?,
+        Some(("size-report", sub)) => size_report::run(sub)?,
+        Some(("doctor", sub)) => doctor::run(sub).await?,
+        Some(("log", sub)) => run_log::run(sub).await?,
+        Some(("plugin", sub)) => match sub.subcommand() {
+            Some(("watch", watch)) => plugin::watch(pm, watch, events).await?,
+            _ => plugin::run(sub)?,
+        },
+        Some(("daemon", sub)) => daemon::run(pm, sub, events).await?,
+        Some(("secret", sub)) => secret::run(sub)?,
         Some((name, sub)) => {
             // Check if this is a known plugin
             if let Some(plugin) = pm.get(name) {
                 // Run the plugin
                 let mut args = vec![name.to_string()];
-                args.extend(gather_raw_args(sub));
-
-                let ctx = ExecutionContext {
-                    matched_args: args,
-                    current_dir: env::current_dir()?,
-                    config_dir: dirs::config_dir()
-                        .unwrap_or_else(|| PathBuf::from("."))
-                        .join("kargo"),
-                };
-                plugin.run(ctx).await?;
+                args.extend(gather_raw_args(name, sub));
+
+                let (result, diagnostics) = run_plugin(
+                    name,
+                    plugin.as_ref(),
+                    args,
+                    env::current_dir()?,
+                    events,
+                    cache.as_ref(),
+                )
+                .await;
+                publish_diagnostics(name, diagnostics, events).await;
+                result?;
             } else {
                 // Not a plugin, proxy to cargo
-                proxy_to_cargo(name, sub).await?;
+                proxy_to_cargo(name, sub, events, capture).await?;
             }
         }
         None => unreachable!(),
@@ -109,10 +525,201 @@ pub async fn dispatch(pm: &PluginManager, matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn gather_raw_args(m: &ArgMatches) -> Vec<String> {
-    // Get the original command line arguments, excluding the program name and subcommand
+/// Run a plugin with `args` in `current_dir`, bracketed by command events,
+/// returning the diagnostics it reported alongside its result. With a
+/// `cache`, pure invocations whose inputs are unchanged are not run again.
+pub async fn run_plugin(
+    name: &str,
+    plugin: &dyn PluginCommand,
+    args: Vec<String>,
+    current_dir: PathBuf,
+    events: &EventBus,
+    cache: Option<&PluginCache>,
+) -> (Result<()>, Vec<Diagnostic>) {
+    let parsed = match parse_plugin_args(plugin, &args) {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => return (Ok(()), Vec::new()),
+        Err(e) => return (Err(e), Vec::new()),
+    };
+    let diagnostics = DiagnosticSink::default();
+    let mut ctx = execution_context(name, args, current_dir, &diagnostics, events);
+    ctx.args = parsed;
+    events.publish(
+        Source::Plugin,
+        EventKind::KargoCommandStarted {
+            subcommand: name.to_string(),
+            args: ctx.matched_args.clone(),
+        },
+    );
+
+    let entry = cache.and_then(|cache| {
+        let spec = plugin.cache_spec(&ctx)?;
+        match cache.entry(name, &spec, &ctx) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::warn!(target: &plugin_target(name), "Not caching {}: {:#}", name, e);
+                None
+            }
+        }
+    });
+    if let Some(entry) = &entry
+        && let Some(cached) = entry.load()
+    {
+        println!(
+            "{}: inputs unchanged, using the cached result (--no-cache to run again)",
+            name
+        );
+        log::debug!(target: &plugin_target(name), "Cache hit: {}", entry.path().display());
+        events.publish(
+            Source::Plugin,
+            EventKind::KargoCommandFinished {
+                subcommand: name.to_string(),
+                success: true,
+                summary: "cached".to_string(),
+            },
+        );
+        return (Ok(()), cached);
+    }
+
+    let span = tracing::info_span!("plugin_exec", plugin = name);
+    log::info!(target: &plugin_target(name), "Running plugin {}", name);
+    let peak_before = metrics::peak_memory_kb();
+    let started = Instant::now();
+    let profiled = Profiler::current().span(profile::Phase::Plugin, name);
+    let result = plugin.run(ctx).instrument(span).await;
+    drop(profiled);
+    if let Err(e) = &result {
+        log::error!(target: &plugin_target(name), "Plugin {} failed: {:#}", name, e);
+    }
+    let mut sample = PluginSample::new(name, Phase::Run, started.elapsed(), result.is_ok());
+    sample.memory_kb = peak_before
+        .zip(metrics::peak_memory_kb())
+        .map(|(before, after)| after.saturating_sub(before));
+    if let Err(e) = Metrics::new(Metrics::default_dir()).record(&[sample]) {
+        log::debug!("Plugin metrics not recorded: {:#}", e);
+    }
+    events.publish(
+        Source::Plugin,
+        EventKind::KargoCommandFinished {
+            subcommand: name.to_string(),
+            success: result.is_ok(),
+            summary: String::new(),
+        },
+    );
+    let diagnostics = diagnostics.take();
+    if result.is_ok()
+        && !WriteGuard::current().is_read_only()
+        && let Some(entry) = &entry
+        && let Err(e) = entry.store(&diagnostics)
+    {
+        log::warn!(target: &plugin_target(name), "Failed to cache result of {}: {:#}", name, e);
+    }
+    (result, diagnostics)
+}
+
+/// The context plugin `name` runs or serves in, with the session's settings
+pub(crate) fn execution_context(
+    name: &str,
+    args: Vec<String>,
+    current_dir: PathBuf,
+    diagnostics: &DiagnosticSink,
+    events: &EventBus,
+) -> ExecutionContext {
+    // Config problems were reported at startup; fall back to the defaults here
+    let config = Config::active().ok();
+    ExecutionContext {
+        matched_args: args,
+        // Filled in by `run_plugin`, which checks the arguments
+        args: PluginArgs::default(),
+        current_dir,
+        config_dir: dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo"),
+        data_dir: dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("plugins")
+            .join(name),
+        cache_dir: PluginCache::default_dir().join(name),
+        diagnostics: diagnostics.clone(),
+        registry: config.as_ref().and_then(|config| config.registry.clone()),
+        target_dir: config
+            .as_ref()
+            .and_then(SharedTarget::from_config)
+            .map(|target| target.dir),
+        host_version: env!("CARGO_PKG_VERSION").to_string(),
+        write_guard: WriteGuard::current(),
+        events: events.plugin_events(),
+        network: NetworkConfig::current(),
+        profiler: Profiler::current().nested(profile::Phase::Plugin, name),
+        settings: config
+            .as_ref()
+            .map(|config| config.settings_for(name))
+            .unwrap_or_default(),
+    }
+}
+
+/// Parse `args`, the plugin name followed by its arguments, with the command
+/// the plugin declares. Usage mistakes come back as the [`clap::Error`], with
+/// the usage line and help hint, instead of reaching the plugin. `Ok(false)`
+/// means help or the version was asked for and has been printed.
+pub fn check_plugin_args(plugin: &dyn PluginCommand, args: &[String]) -> Result<bool> {
+    Ok(parse_plugin_args(plugin, args)?.is_some())
+}
+
+/// [`check_plugin_args`], keeping what was parsed for the plugin's
+/// [`ExecutionContext::args`]; `None` when help or the version was printed
+pub fn parse_plugin_args(
+    plugin: &dyn PluginCommand,
+    args: &[String],
+) -> Result<Option<PluginArgs>> {
+    let Some(name) = args.first() else {
+        anyhow::bail!("Missing plugin name");
+    };
+    let mut command = plugin.clap().bin_name(format!("kargo {}", name));
+    match command.try_get_matches_from_mut(args) {
+        Ok(matches) => Ok(Some(PluginArgs::from_matches(&command, &matches))),
+        Err(e) if !e.use_stderr() => {
+            e.print()?;
+            Ok(None)
+        }
+        Err(mut e) => {
+            // clap leaves the usage out of some errors, e.g. invalid values
+            if e.get(ContextKind::Usage).is_none() {
+                e.insert(
+                    ContextKind::Usage,
+                    ContextValue::StyledStr(command.render_usage()),
+                );
+            }
+            Err(e.into())
+        }
+    }
+}
+
+/// Forward plugin diagnostics onto the bus (and from there to `--ci`),
+/// together with the dependency findings plugins published as events
+pub async fn publish_diagnostics(plugin: &str, diagnostics: Vec<Diagnostic>, events: &EventBus) {
+    events.forward_plugin_events().await;
+    for diagnostic in diagnostics {
+        events.publish(
+            Source::Plugin,
+            EventKind::PluginDiagnostic {
+                plugin: plugin.to_string(),
+                diagnostic,
+            },
+        );
+        // Give subscribers a chance to drain before the channel fills up
+        tokio::task::yield_now().await;
+    }
+}
+
+pub(crate) fn gather_raw_args(name: &str, m: &ArgMatches) -> Vec<String> {
+    // Get the original command line arguments after the subcommand, skipping the
+    // program name and any root flags (e.g. `kargo --ci mddoc ...`)
     let args: Vec<String> = std::env::args()
-        .skip(2) // Skip "kargo" and "mddoc" (or whatever subcommand)
+        .skip(1)
+        .skip_while(|arg| arg != name)
+        .skip(1)
         .collect();
 
     // If no args were captured from env, fall back to reconstructing from ArgMatches
@@ -1,6 +1,7 @@
-use crate::events::{Event, EventBus};
+use crate::events::{EventBus, EventKind, Source};
 use anyhow::Result;
 use futures::future::Future;
+use kargo_plugin_api::artifacts::RunArtifacts;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::process::Command;
@@ -25,9 +26,12 @@ impl<'a> Future for CommandExecution<'a> {
         let this = self.get_mut();
 
         for cmd in &this.commands {
-            this.runner.events.publish(Event::CommandStarted {
-                command: cmd.clone(),
-            });
+            this.runner.events.publish(
+                Source::Commands,
+                EventKind::CommandStarted {
+                    command: cmd.clone(),
+                },
+            );
 
             let parts: Vec<_> = cmd.split_whitespace().collect();
             let program = parts[0];
@@ -48,16 +52,38 @@ impl<'a> Future for CommandExecution<'a> {
                 }
             };
 
+            // Kept so a failure can be looked into after the run
+            let captured = match RunArtifacts::for_project(&this.working_dir).capture(
+                cmd,
+                output.status.code(),
+                &output.stdout,
+                &output.stderr,
+            ) {
+                Ok(captured) => Some(captured),
+                Err(e) => {
+                    log::warn!("{:#}", e);
+                    None
+                }
+            };
+
             let success = output.status.success();
-            this.runner.events.publish(Event::CommandFinished {
-                command: cmd.clone(),
-                success,
-            });
+            this.runner.events.publish(
+                Source::Commands,
+                EventKind::CommandFinished {
+                    command: cmd.clone(),
+                    success,
+                    output: captured.clone(),
+                },
+            );
 
             if !success {
+                let saved = captured
+                    .map(|captured| format!(" (saved to {})", captured.stderr.display()))
+                    .unwrap_or_default();
                 return std::task::Poll::Ready(Err(anyhow::anyhow!(
-                    "Command failed: {}\nStderr: {}",
+                    "Command failed: {}\nStderr{}: {}",
                     cmd,
+                    saved,
                     String::from_utf8_lossy(&output.stderr)
                 )));
             }
@@ -1,11 +1,27 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use kargo_plugin_api::{NetworkConfig, PluginSettings, WriteGuard};
+
+use crate::plugins::permissions::Capabilities;
+use crate::plugins::trust::TrustPolicy;
+use crate::remote_config::{self, Include, IncludeCache};
+use crate::schedule::ScheduledJob;
+use crate::shared_target::SharedTargetConfig;
+use crate::status::ConfigError;
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
-    /// Directories to scan (overridden by KRATER_SCAN)
+    /// Shared config files this one is laid over, see [`remote_config`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<Include>,
+    /// Directories to scan (overridden by KARGO_SCAN_DIRS or KRATER_SCAN)
     pub scan_dirs: Vec<PathBuf>,
     /// Commands to run after dependency consolidation
     pub post_commands: Vec<String>,
@@ -13,9 +29,147 @@ pub struct Config {
     pub rollback_on_failure: bool,
     /// Whether to vendor dependencies
     pub vendor: VendorConfig,
+    /// Recurring jobs run by `kargo schedule run`
+    #[serde(default)]
+    pub schedule: Vec<ScheduledJob>,
+    /// Sparse index URL for version lookups (crates.io when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Plugins to load; every discovered plugin when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugins: Option<Vec<String>>,
+    /// Named overrides, selected with `--profile` or KARGO_PROFILE
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Profile used when none is selected explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// What each WASM plugin may do outside its sandbox, keyed by the plugin's
+    /// file name without `.wasm`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub wasm_permissions: BTreeMap<String, WasmPermissions>,
+    /// Milliseconds a plugin may take to load before startup warns that it
+    /// keeps exceeding the budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_load_budget_ms: Option<u64>,
+    /// Instances of each WASM plugin kept warm and run in parallel, as by
+    /// `kargo fleet --jobs`; the number of CPUs when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_pool_size: Option<usize>,
+    /// Whether plugins newly found in a plugin directory load: `allow`,
+    /// `deny` or `prompt` (the default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_trust: Option<TrustPolicy>,
+    /// Plugins whose root-level flags and hooks around every command take
+    /// effect, in the order the hooks run; no plugin's when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugin_hooks: Vec<String>,
+    /// One target directory for the cargo builds plugins run themselves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_target: Option<SharedTargetConfig>,
+    /// Proxy, certificates, retries, rate limits and offline mode for every
+    /// request kargo and its plugins send
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkConfig>,
+    /// Command `kargo` runs when invoked bare or with arguments it has no
+    /// command for, such as `sap` for agent-focused setups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_command: Option<String>,
+    /// Each plugin's own section, keyed by plugin name; handed to the plugin
+    /// as [`ExecutionContext::settings`](kargo_plugin_api::ExecutionContext::settings)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugin_settings: BTreeMap<String, serde_yaml::Value>,
+    /// Profile applied by [`Config::active`]
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+    /// Settings that came from `include`, left out by [`Config::save`]
+    #[serde(skip)]
+    pub included: Option<serde_yaml::Value>,
+    /// The `.kargo.toml` applied by [`Config::with_project`]
+    #[serde(skip)]
+    pub project_file: Option<PathBuf>,
+    /// Where each plugin's section was last set, as `file:line` when known
+    #[serde(skip)]
+    pub settings_origin: BTreeMap<String, String>,
+}
+
+/// Settings for one machine context (work, personal, ci, ...). Anything left
+/// out falls back to the top-level value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub scan_dirs: Option<Vec<PathBuf>>,
+    pub post_commands: Option<Vec<String>>,
+    pub rollback_on_failure: Option<bool>,
+    pub vendor: Option<VendorConfig>,
+    pub registry: Option<String>,
+    pub plugins: Option<Vec<String>>,
+    pub plugin_trust: Option<TrustPolicy>,
+    pub network: Option<NetworkConfig>,
+    pub default_command: Option<String>,
+    /// Laid over the top-level sections key by key
+    pub plugin_settings: Option<BTreeMap<String, serde_yaml::Value>>,
+}
+
+/// What a project's `.kargo.toml` may set, laid over the config like a
+/// [`Profile`]. The file comes with whatever repository is checked out, so it
+/// can't choose which plugins load or are trusted, where requests go or with
+/// which certificates and tokens, or commands for kargo to run; those are
+/// [`USER_ONLY_SETTINGS`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSettings {
+    pub scan_dirs: Option<Vec<PathBuf>>,
+    pub rollback_on_failure: Option<bool>,
+    pub vendor: Option<VendorConfig>,
+    /// Laid over the top-level sections key by key
+    pub plugin_settings: Option<BTreeMap<String, serde_yaml::Value>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Settings a `.kargo.toml` is refused for, rather than having them ignored
+pub const USER_ONLY_SETTINGS: &[&str] = &[
+    "registry",
+    "plugins",
+    "plugin_trust",
+    "network",
+    "post_commands",
+    "default_command",
+];
+
+impl From<ProjectSettings> for Profile {
+    fn from(project: ProjectSettings) -> Self {
+        Self {
+            scan_dirs: project.scan_dirs,
+            rollback_on_failure: project.rollback_on_failure,
+            vendor: project.vendor,
+            plugin_settings: project.plugin_settings,
+            ..Self::default()
+        }
+    }
+}
+
+/// Capabilities granted to a WASM plugin. Plugins get none by default; what
+/// they declare is added when the user approves it on first run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WasmPermissions {
+    /// Hosts the plugin may send HTTP requests to; `*` wildcards allowed, as
+    /// in `*.github.com`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_hosts: Vec<String>,
+    /// Files and directories the plugin may read, with everything below them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_paths: Vec<PathBuf>,
+    /// Environment variables the plugin may read
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_env: Vec<String>,
+    /// What the plugin declared when the user last answered for it; it is
+    /// asked about again only for capabilities beyond these
+    #[serde(skip_serializing_if = "Capabilities::is_empty")]
+    pub reviewed: Capabilities,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct VendorConfig {
     /// Enable vendoring
     pub enabled: bool,
@@ -28,17 +182,57 @@ pub struct VendorConfig {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            include: Vec::new(),
             scan_dirs: vec![std::env::var("HOME").map(PathBuf::from).unwrap_or_default()],
             post_commands: vec!["cargo fmt".to_string()],
             rollback_on_failure: true,
             vendor: VendorConfig::default(),
+            schedule: Vec::new(),
+            registry: None,
+            plugins: None,
+            profiles: BTreeMap::new(),
+            default_profile: None,
+            wasm_permissions: BTreeMap::new(),
+            plugin_load_budget_ms: None,
+            wasm_pool_size: None,
+            plugin_trust: None,
+            plugin_hooks: Vec::new(),
+            shared_target: None,
+            network: None,
+            default_command: None,
+            plugin_settings: BTreeMap::new(),
+            active_profile: None,
+            included: None,
+            project_file: None,
+            settings_origin: BTreeMap::new(),
         }
     }
 }
 
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Select the profile for this process (from `--profile`), taking precedence
+/// over KARGO_PROFILE and the config's `default_profile`
+pub fn select_profile(name: impl Into<String>) {
+    let _ = SELECTED_PROFILE.set(name.into());
+}
+
+/// File a project keeps its own settings in, looked up from the current
+/// directory upwards
+pub const PROJECT_FILE: &str = ".kargo.toml";
+
+pub(crate) fn requested_profile() -> Option<String> {
+    SELECTED_PROFILE.get().cloned().or_else(|| {
+        std::env::var("KARGO_PROFILE")
+            .ok()
+            .filter(|name| !name.is_empty())
+    })
+}
+
 impl Config {
-    pub fn load() -> anyhow::Result<Self> {
-        let config_paths = vec![
+    fn candidate_paths() -> Vec<PathBuf> {
+        [
+            std::env::var_os("KARGO_CONFIG").map(PathBuf::from),
             std::env::var("HOME")
                 .map(|h| PathBuf::from(h).join(".krater.yaml"))
                 .ok(),
@@ -46,14 +240,323 @@ impl Config {
             std::env::var("HOME")
                 .map(|h| PathBuf::from(h).join(".config/krater.yaml"))
                 .ok(),
-        ];
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
 
-        for path in config_paths.into_iter().flatten() {
+    pub fn load() -> anyhow::Result<Self> {
+        for path in Self::candidate_paths() {
             if path.exists() {
-                return Ok(serde_yaml::from_str(&std::fs::read_to_string(path)?)?);
+                return Self::from_file(&path);
             }
         }
 
         Ok(Self::default())
     }
+
+    /// Load the config file at `path` over its cached includes, without
+    /// applying a profile
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        Self::from_file_with(path, &IncludeCache::new(IncludeCache::default_dir()))
+    }
+
+    /// Load the config file at `path` over its includes as cached in `cache`
+    pub fn from_file_with(path: &Path, cache: &IncludeCache) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| ConfigError::new(path, e))?;
+        let local: serde_yaml::Value =
+            serde_yaml::from_str(&content).map_err(|e| ConfigError::new(path, e))?;
+        let (value, included) =
+            remote_config::resolve(local, cache).map_err(|e| ConfigError::new(path, e))?;
+        let mut config: Self = serde_yaml::from_value(value).map_err(|e| {
+            // Parsed from the text, the file's own mistakes come with a line
+            match serde_yaml::from_str::<Self>(&content) {
+                Err(located) => {
+                    let line = located.location().map(|l| l.line());
+                    let error = ConfigError::new(path, located);
+                    match line {
+                        Some(line) => error.at_line(line),
+                        None => error,
+                    }
+                }
+                Ok(_) => ConfigError::new(path, e),
+            }
+        })?;
+        config.included = included;
+        for name in config.plugin_settings.keys() {
+            let origin = match yaml_key_line(&content, &["plugin_settings", name]) {
+                Some(line) => format!("{}:{}", path.display(), line),
+                None => path.display().to_string(),
+            };
+            config.settings_origin.insert(name.clone(), origin);
+        }
+        Ok(config)
+    }
+
+    /// Load the config in layers: the config file with the selected profile
+    /// applied, then the project's `.kargo.toml`, then `KARGO_*` variables
+    pub fn active() -> anyhow::Result<Self> {
+        let mut config = Self::load()?;
+        if let Some(name) = requested_profile().or_else(|| config.default_profile.clone()) {
+            config = config
+                .with_profile(&name)
+                .map_err(|e| ConfigError::new(Self::path(), e))?;
+        }
+        if let Ok(dir) = std::env::current_dir() {
+            config = config.with_project(&dir)?;
+        }
+        Ok(config.with_env(std::env::vars())?)
+    }
+
+    /// Apply the profile `name` over the top-level settings
+    pub fn with_profile(mut self, name: &str) -> Result<Self, String> {
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            format!(
+                "unknown profile '{}' (defined: {})",
+                name,
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            )
+        })?;
+        self.apply(profile, &format!("profile '{}'", name));
+        self.active_profile = Some(name.to_string());
+        Ok(self)
+    }
+
+    /// Lay the nearest `.kargo.toml` in `dir` or above over this config; see
+    /// [`ProjectSettings`] for what it may set
+    pub fn with_project(mut self, dir: &Path) -> Result<Self, ConfigError> {
+        let Some(path) = dir
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(self);
+        };
+        let content = std::fs::read_to_string(&path).map_err(|e| ConfigError::new(&path, e))?;
+        let doc = toml_edit::ImDocument::parse(content.as_str())
+            .map_err(|e| ConfigError::new(&path, e))?;
+        let line = |span: Option<Range<usize>>| {
+            span.map(|span| content[..span.start].matches('\n').count() + 1)
+        };
+
+        // Each setting on its own, so a mistake points at its line
+        let table = doc.as_table();
+        let mut settings = serde_yaml::Mapping::new();
+        let mut origins = BTreeMap::new();
+        for (key, item) in table.iter() {
+            let Some(value) = remote_config::toml_item(item) else {
+                continue;
+            };
+            let key_line = table
+                .get_key_value(key)
+                .and_then(|(key, item)| line(key.span().or_else(|| item.span())));
+            let refused = USER_ONLY_SETTINGS.contains(&key).then(|| {
+                format!(
+                    "`{}` can only be set in your own config, not in a project's {}",
+                    key, PROJECT_FILE
+                )
+            });
+            let single = serde_yaml::Mapping::from_iter([(key.into(), value.clone())]);
+            let invalid = serde_yaml::from_value::<ProjectSettings>(single.into())
+                .err()
+                .map(|e| e.to_string());
+            if let Some(message) = refused.or(invalid) {
+                let error = ConfigError::new(&path, message);
+                return Err(match key_line {
+                    Some(line) => error.at_line(line),
+                    None => error,
+                });
+            }
+            if key == "plugin_settings"
+                && let Some(sections) = item.as_table_like()
+            {
+                for (plugin, section) in sections.iter() {
+                    let plugin_line = sections
+                        .get_key_value(plugin)
+                        .and_then(|(key, _)| line(key.span().or_else(|| section.span())))
+                        .or(key_line);
+                    let origin = match plugin_line {
+                        Some(line) => format!("{}:{}", path.display(), line),
+                        None => path.display().to_string(),
+                    };
+                    origins.insert(plugin.to_string(), origin);
+                }
+            }
+            settings.insert(key.into(), value);
+        }
+        let project: ProjectSettings =
+            serde_yaml::from_value(settings.into()).map_err(|e| ConfigError::new(&path, e))?;
+        self.apply(project.into(), &path.display().to_string());
+        // The project's own lines are more precise than the file name
+        self.settings_origin.extend(origins);
+        self.project_file = Some(path);
+        Ok(self)
+    }
+
+    /// Apply the `KARGO_*` settings among `vars` over this config:
+    /// `KARGO_SCAN_DIRS` (a path list), `KARGO_REGISTRY`, `KARGO_PLUGINS`
+    /// (comma separated), `KARGO_ROLLBACK_ON_FAILURE` and
+    /// `KARGO_DEFAULT_COMMAND`
+    pub fn with_env(
+        mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        for (name, value) in vars {
+            match name.as_str() {
+                "KARGO_SCAN_DIRS" => self.scan_dirs = std::env::split_paths(&value).collect(),
+                "KARGO_REGISTRY" => self.registry = Some(value).filter(|v| !v.is_empty()),
+                "KARGO_DEFAULT_COMMAND" => {
+                    self.default_command = Some(value).filter(|v| !v.is_empty())
+                }
+                "KARGO_PLUGINS" => {
+                    self.plugins = Some(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|plugin| !plugin.is_empty())
+                            .map(String::from)
+                            .collect(),
+                    )
+                }
+                "KARGO_ROLLBACK_ON_FAILURE" => {
+                    self.rollback_on_failure = match value.to_ascii_lowercase().as_str() {
+                        "1" | "true" | "yes" => true,
+                        "0" | "false" | "no" => false,
+                        _ => {
+                            return Err(ConfigError::new(
+                                &name,
+                                format!("expected true or false, found '{}'", value),
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+
+    /// Lay `profile`, set in `origin`, over the top-level settings
+    fn apply(&mut self, profile: Profile, origin: &str) {
+        if let Some(scan_dirs) = profile.scan_dirs {
+            self.scan_dirs = scan_dirs;
+        }
+        if let Some(post_commands) = profile.post_commands {
+            self.post_commands = post_commands;
+        }
+        if let Some(rollback_on_failure) = profile.rollback_on_failure {
+            self.rollback_on_failure = rollback_on_failure;
+        }
+        if let Some(vendor) = profile.vendor {
+            self.vendor = vendor;
+        }
+        if profile.registry.is_some() {
+            self.registry = profile.registry;
+        }
+        if profile.plugins.is_some() {
+            self.plugins = profile.plugins;
+        }
+        if profile.plugin_trust.is_some() {
+            self.plugin_trust = profile.plugin_trust;
+        }
+        if profile.network.is_some() {
+            self.network = profile.network;
+        }
+        if profile.default_command.is_some() {
+            self.default_command = profile.default_command;
+        }
+        for (plugin, section) in profile.plugin_settings.unwrap_or_default() {
+            let merged = match self.plugin_settings.remove(&plugin) {
+                Some(base) => remote_config::merge(base, section),
+                None => section,
+            };
+            self.plugin_settings.insert(plugin.clone(), merged);
+            self.settings_origin.insert(plugin, origin.to_string());
+        }
+    }
+
+    /// The section of the plugin `name`, for its
+    /// [`ExecutionContext`](kargo_plugin_api::ExecutionContext)
+    pub fn settings_for(&self, name: &str) -> PluginSettings {
+        let Some(section) = self.plugin_settings.get(name) else {
+            return PluginSettings::default();
+        };
+        PluginSettings::new(
+            name,
+            serde_json::to_value(section).unwrap_or_default(),
+            self.settings_origin.get(name).cloned(),
+        )
+    }
+
+    /// Whether the plugin `name` is enabled by the `plugins` list
+    pub fn allows_plugin(&self, name: &str) -> bool {
+        self.plugins
+            .as_ref()
+            .is_none_or(|plugins| plugins.iter().any(|p| p == name))
+    }
+
+    /// The config file `load` reads, or where a new one should be created
+    pub fn path() -> PathBuf {
+        Self::candidate_paths()
+            .into_iter()
+            .find(|path| path.exists())
+            .or_else(|| {
+                ProjectDirs::from("rs", "", "krater").map(|p| p.config_dir().join("config.yaml"))
+            })
+            .unwrap_or_else(|| PathBuf::from("krater.yaml"))
+    }
+
+    /// Write the config back as YAML. Comments in an existing file are not kept.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let guard = WriteGuard::current();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            guard.create_dir_all(parent)?;
+        }
+        let mut value = serde_yaml::to_value(self)?;
+        if let Some(included) = &self.included {
+            remote_config::strip(&mut value, included);
+        }
+        guard.write_atomic(path, serde_yaml::to_string(&value)?)
+    }
+}
+
+/// Line of the block-style key at `path` in the YAML `content`
+fn yaml_key_line(content: &str, path: &[&str]) -> Option<usize> {
+    // Indentation of the keys at the current depth, once known
+    let mut level: Option<usize> = Some(0);
+    let mut parent = None;
+    let mut depth = 0;
+    for (number, line) in content.lines().enumerate() {
+        let key = line.trim_start();
+        if key.is_empty() || key.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - key.len();
+        if parent.is_some_and(|parent| indent <= parent) {
+            return None;
+        }
+        match level {
+            Some(level) if indent != level => continue,
+            None => level = Some(indent),
+            _ => {}
+        }
+        let found = key
+            .strip_prefix(path[depth])
+            .is_some_and(|rest| rest.trim_start().starts_with(':'));
+        if found {
+            depth += 1;
+            if depth == path.len() {
+                return Some(number + 1);
+            }
+            parent = Some(indent);
+            level = None;
+        }
+    }
+    None
 }
@@ -0,0 +1,523 @@
+//! `kargo cost <crate>[@version]`: what adding a dependency would cost
+//!
+//! The crate is resolved in a scratch package seeded with the project's
+//! `Cargo.lock`, so versions the project already locks are reused the way
+//! they would be after `cargo add`. Against that lockfile, each crate of the
+//! resolved tree is either already locked, new, or a duplicate: another
+//! semver-incompatible version of a crate the project already builds.
+//!
+//! Download sizes are those of the `.crate` files cargo fetches into its
+//! registry cache. Build time is a rough estimate from the size of the new
+//! crates' Rust sources, unless it was measured with `--measure`, which
+//! builds the scratch package from scratch and remembers the time in
+//! `build-costs.json` in the kargo cache directory.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use jwalk::WalkDir;
+use kargo_plugin_api::WriteGuard;
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+use kargo_plugin_api::metadata::{MetadataMode, ProjectMetadata};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use toml_edit::{Array, DocumentMut, InlineTable, Item, value};
+
+use crate::fleet::deps::compatible_series;
+
+/// Rust source a debug build gets through per second, for estimates
+const SOURCE_BYTES_PER_SECOND: u64 = 200 * 1024;
+const PROBE_PACKAGE: &str = "kargo-cost-probe";
+
+pub fn command() -> Command {
+    Command::new("cost")
+        .about("Estimate what adding a dependency would cost")
+        .arg(
+            Arg::new("crate")
+                .value_name("CRATE")
+                .help("Crate to add, optionally with a version requirement (`name@1.2`)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory whose Cargo.lock to compare against")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("features")
+                .short('F')
+                .long("features")
+                .value_name("FEATURES")
+                .help("Features to enable on the crate (comma separated)")
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("no-default-features")
+                .long("no-default-features")
+                .help("Don't enable the crate's default features")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("measure")
+                .long("measure")
+                .help("Build the crate to measure its build time instead of estimating it")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the report as JSON")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let spec = matches.get_one::<String>("crate").expect("required");
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version.to_string())),
+        None => (spec.as_str(), None),
+    };
+    let dependency = DependencySpec {
+        name: name.to_string(),
+        version,
+        features: matches
+            .get_many::<String>("features")
+            .map(|features| features.cloned().collect())
+            .unwrap_or_default(),
+        default_features: !matches.get_flag("no-default-features"),
+    };
+
+    let report = estimate(dir, &dependency, matches.get_flag("measure"))?;
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render());
+    }
+    Ok(())
+}
+
+/// The dependency to price
+#[derive(Debug, Clone)]
+pub struct DependencySpec {
+    pub name: String,
+    /// Version requirement; the newest version when `None`
+    pub version: Option<String>,
+    pub features: Vec<String>,
+    pub default_features: bool,
+}
+
+impl DependencySpec {
+    /// Key of the dependency's measurements in the build cost cache
+    fn key(&self, version: &str) -> String {
+        let mut features = self.features.clone();
+        features.sort();
+        let mut key = format!("{}@{}", self.name, version);
+        if !self.default_features {
+            key.push_str(" no-default");
+        }
+        if !features.is_empty() {
+            let _ = write!(key, " [{}]", features.join(","));
+        }
+        key
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrateStatus {
+    /// The lockfile has this exact version
+    Locked,
+    New,
+    /// New, while the lockfile has an incompatible version of the crate
+    Duplicate,
+}
+
+/// One crate of the dependency's resolved tree
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateCost {
+    pub name: String,
+    pub version: String,
+    pub status: CrateStatus,
+    /// Versions of the crate the lockfile already has, for duplicates
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub locked_versions: Vec<String>,
+    /// Size of the `.crate` file, when cargo has it cached
+    pub download_bytes: Option<u64>,
+    /// Size of the Rust sources, when cargo has them extracted
+    pub source_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildTime {
+    pub seconds: f64,
+    /// Measured with `--measure` (now or earlier) rather than estimated
+    pub measured: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CostReport {
+    pub name: String,
+    /// Version the dependency resolves to
+    pub version: String,
+    pub crates: Vec<CrateCost>,
+    pub build_time: Option<BuildTime>,
+}
+
+impl CostReport {
+    /// Crates the project would have to download and build
+    pub fn added(&self) -> impl Iterator<Item = &CrateCost> {
+        self.crates
+            .iter()
+            .filter(|c| c.status != CrateStatus::Locked)
+    }
+
+    pub fn duplicates(&self) -> impl Iterator<Item = &CrateCost> {
+        self.crates
+            .iter()
+            .filter(|c| c.status == CrateStatus::Duplicate)
+    }
+
+    /// Download size of the added crates, leaving out those not in the cache
+    pub fn download_bytes(&self) -> u64 {
+        self.added().filter_map(|c| c.download_bytes).sum()
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let added = self.added().count();
+        let _ = writeln!(out, "{} {}", self.name, self.version);
+        let _ = writeln!(
+            out,
+            "  crates      {} ({} new, {} already in Cargo.lock)",
+            self.crates.len(),
+            added,
+            self.crates.len() - added
+        );
+        let unknown = self.added().filter(|c| c.download_bytes.is_none()).count();
+        let _ = write!(out, "  download    {}", format_size(self.download_bytes()));
+        if unknown > 0 {
+            let _ = write!(out, " (size of {} crate(s) unknown)", unknown);
+        }
+        let _ = writeln!(out);
+        match &self.build_time {
+            _ if added == 0 => {
+                let _ = writeln!(out, "  build       nothing new to build");
+            }
+            Some(build) if build.measured => {
+                let _ = writeln!(
+                    out,
+                    "  build       {:.1}s (measured, the whole tree from scratch)",
+                    build.seconds
+                );
+            }
+            Some(build) => {
+                let _ = writeln!(
+                    out,
+                    "  build       ~{:.0}s (estimated from source size; --measure to build it)",
+                    build.seconds.max(1.0)
+                );
+            }
+            None => {
+                let _ = writeln!(out, "  build       unknown");
+            }
+        }
+        let duplicates: Vec<&CrateCost> = self.duplicates().collect();
+        if duplicates.is_empty() {
+            let _ = writeln!(out, "  duplicates  none");
+        }
+        for (i, dup) in duplicates.iter().enumerate() {
+            let label = if i == 0 { "duplicates" } else { "" };
+            let _ = writeln!(
+                out,
+                "  {:<10}  {} {} alongside {}",
+                label,
+                dup.name,
+                dup.version,
+                dup.locked_versions.join(", ")
+            );
+        }
+        out
+    }
+}
+
+/// Resolve `dependency` against the lockfile of the project in `dir` and
+/// price it
+pub fn estimate(dir: &Path, dependency: &DependencySpec, measure: bool) -> Result<CostReport> {
+    let scratch = tempfile::tempdir().context("Failed to create a scratch package")?;
+    let manifest = scratch.path().join("Cargo.toml");
+    std::fs::write(&manifest, probe_manifest(dependency))?;
+    std::fs::create_dir_all(scratch.path().join("src"))?;
+    std::fs::write(scratch.path().join("src/lib.rs"), "")?;
+    let lockfile = dir
+        .ancestors()
+        .map(|d| d.join("Cargo.lock"))
+        .find(|path| path.exists());
+    let locked = match &lockfile {
+        Some(path) => {
+            std::fs::copy(path, scratch.path().join("Cargo.lock"))?;
+            lockfile_packages(&std::fs::read_to_string(path)?)
+        }
+        None => Vec::new(),
+    };
+
+    let metadata = ProjectMetadata::load(&manifest, MetadataMode::Resolved)
+        .with_context(|| format!("Failed to resolve {}", dependency.name))?;
+    let probe = metadata
+        .packages
+        .iter()
+        .find(|p| p.name == PROBE_PACKAGE)
+        .context("The scratch package is missing from cargo metadata")?;
+    let version = metadata
+        .resolved_dependencies(&probe.id)
+        .and_then(|deps| deps.iter().find(|d| d.package == dependency.name))
+        .map(|d| d.version.clone())
+        .with_context(|| format!("{} didn't resolve", dependency.name))?;
+    let resolved: Vec<(String, String)> = metadata
+        .packages
+        .iter()
+        .filter(|p| p.id != probe.id)
+        .map(|p| (p.name.clone(), p.version.clone()))
+        .collect();
+
+    // Fetching fills cargo's cache with the crates to measure
+    let fetch = CargoCommand::new("fetch")
+        .arg("--manifest-path")
+        .arg(manifest.to_string_lossy());
+    let fetched = cargo_runner::run(&fetch)?;
+    if !fetched.success() {
+        log::warn!(
+            "cargo fetch failed; sizes of crates cargo hasn't cached are unknown: {}",
+            fetched.stderr.trim()
+        );
+    }
+    let registry = cargo_home().join("registry");
+    let mut crates = classify(&resolved, &locked);
+    for krate in &mut crates {
+        let file = format!("{}-{}", krate.name, krate.version);
+        krate.download_bytes =
+            find_in_registry(&registry.join("cache"), &format!("{}.crate", file))
+                .and_then(|path| std::fs::metadata(path).ok())
+                .map(|m| m.len());
+        krate.source_bytes =
+            find_in_registry(&registry.join("src"), &file).map(|dir| rust_source_bytes(&dir));
+    }
+
+    let key = dependency.key(&version);
+    let mut costs = BuildCosts::load();
+    let build_time = if measure {
+        let seconds = measure_build(&manifest, &scratch.path().join("target"))?;
+        costs.builds.insert(key, seconds);
+        if let Err(e) = costs.save() {
+            log::warn!("Failed to remember the build time: {:#}", e);
+        }
+        Some(BuildTime {
+            seconds,
+            measured: true,
+        })
+    } else if let Some(seconds) = costs.builds.get(&key) {
+        Some(BuildTime {
+            seconds: *seconds,
+            measured: true,
+        })
+    } else {
+        let added: Vec<&CrateCost> = crates
+            .iter()
+            .filter(|c| c.status != CrateStatus::Locked)
+            .collect();
+        added
+            .iter()
+            .all(|c| c.source_bytes.is_some())
+            .then(|| BuildTime {
+                seconds: estimate_build_seconds(added.iter().filter_map(|c| c.source_bytes).sum()),
+                measured: false,
+            })
+    };
+
+    Ok(CostReport {
+        name: dependency.name.clone(),
+        version,
+        crates,
+        build_time,
+    })
+}
+
+/// `(name, version)` of every package in the contents of a `Cargo.lock`
+pub fn lockfile_packages(lockfile: &str) -> Vec<(String, String)> {
+    let Ok(doc) = lockfile.parse::<DocumentMut>() else {
+        return Vec::new();
+    };
+    let Some(packages) = doc.get("package").and_then(Item::as_array_of_tables) else {
+        return Vec::new();
+    };
+    packages
+        .iter()
+        .filter_map(|p| {
+            let name = p.get("name")?.as_str()?;
+            let version = p.get("version")?.as_str()?;
+            Some((name.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// Sort the resolved crates into locked, new and duplicate ones, sizes
+/// unknown
+pub fn classify(resolved: &[(String, String)], locked: &[(String, String)]) -> Vec<CrateCost> {
+    let mut crates: Vec<CrateCost> = resolved
+        .iter()
+        .map(|(name, version)| {
+            let others: Vec<String> = locked
+                .iter()
+                .filter(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+                .collect();
+            let status = if others.contains(version) {
+                CrateStatus::Locked
+            } else if others
+                .iter()
+                .any(|v| compatible_series(v) != compatible_series(version))
+            {
+                CrateStatus::Duplicate
+            } else {
+                CrateStatus::New
+            };
+            CrateCost {
+                name: name.clone(),
+                version: version.clone(),
+                status,
+                locked_versions: if status == CrateStatus::Duplicate {
+                    others
+                } else {
+                    Vec::new()
+                },
+                download_bytes: None,
+                source_bytes: None,
+            }
+        })
+        .collect();
+    crates.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    crates
+}
+
+/// Rough debug build time of `source_bytes` of Rust
+pub fn estimate_build_seconds(source_bytes: u64) -> f64 {
+    source_bytes as f64 / SOURCE_BYTES_PER_SECOND as f64
+}
+
+/// A manifest depending on nothing but `dependency`
+fn probe_manifest(dependency: &DependencySpec) -> String {
+    let mut doc = DocumentMut::new();
+    doc["package"]["name"] = value(PROBE_PACKAGE);
+    doc["package"]["version"] = value("0.0.0");
+    doc["package"]["edition"] = value("2021");
+    doc["package"]["publish"] = value(false);
+    let mut dep = InlineTable::new();
+    dep.insert(
+        "version",
+        dependency.version.as_deref().unwrap_or("*").into(),
+    );
+    if !dependency.default_features {
+        dep.insert("default-features", false.into());
+    }
+    if !dependency.features.is_empty() {
+        let features: Array = dependency.features.iter().map(String::as_str).collect();
+        dep.insert("features", features.into());
+    }
+    doc["dependencies"][&dependency.name] = value(dep);
+    // Keep the probe out of any workspace it happens to be under
+    doc["workspace"] = toml_edit::table();
+    doc.to_string()
+}
+
+fn cargo_home() -> PathBuf {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+        .unwrap_or_else(|| PathBuf::from(".cargo"))
+}
+
+/// `<dir>/<registry>/<name>`, for any registry cargo has cached
+fn find_in_registry(dir: &Path, name: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(name))
+        .find(|path| path.exists())
+}
+
+fn rust_source_bytes(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "rs"))
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn measure_build(manifest: &Path, target_dir: &Path) -> Result<f64> {
+    if WriteGuard::current().is_read_only() {
+        bail!("Measuring builds the crate, which kargo --read-only doesn't allow; drop --measure");
+    }
+    let started = Instant::now();
+    let command = CargoCommand::new("build")
+        .arg("--manifest-path")
+        .arg(manifest.to_string_lossy())
+        .target_dir(Some(target_dir));
+    cargo_runner::run(&command)?
+        .ensure_success(&command)
+        .context("Building the crate failed")?;
+    Ok(started.elapsed().as_secs_f64())
+}
+
+/// Build times measured with `--measure`, by dependency and features
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildCosts {
+    builds: BTreeMap<String, f64>,
+}
+
+impl BuildCosts {
+    fn path() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("build-costs.json")
+    }
+
+    fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path();
+        let guard = WriteGuard::current();
+        if let Some(dir) = path.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        guard.write(&path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+pub(crate) fn format_size(size: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size as u64, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
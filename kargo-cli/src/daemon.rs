@@ -0,0 +1,107 @@
+//! `kargo daemon`: run the background services of loaded plugins
+//!
+//! Every plugin that declares a service (see [`kargo_plugin_api::service`])
+//! is served concurrently under [`supervise_all`], which restarts services
+//! according to their restart policy. Ctrl-C closes the session's events,
+//! which tells the services to stop; any still running after
+//! [`SHUTDOWN_GRACE`] are dropped.
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::DiagnosticSink;
+use std::env;
+use std::time::Duration;
+
+use crate::cli::execution_context;
+use crate::events::EventBus;
+use crate::plugins::manager::PluginManager;
+use crate::plugins::supervisor::supervise_all;
+
+/// How long services get to stop after Ctrl-C
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+pub fn command() -> Command {
+    Command::new("daemon")
+        .about("Run the background services of plugins until interrupted")
+        .arg(
+            Arg::new("plugin")
+                .long("plugin")
+                .value_name("NAME")
+                .help("Only run this plugin's service (repeatable)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .help("List the plugins with a service and their restart policy")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub async fn run(pm: &PluginManager, matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    let only: Vec<&String> = matches.get_many("plugin").into_iter().flatten().collect();
+    let services: Vec<_> = pm
+        .services()
+        .filter(|(name, _, _)| only.is_empty() || only.iter().any(|o| o == name))
+        .collect();
+
+    if matches.get_flag("list") {
+        for (name, _, spec) in &services {
+            let limit = spec
+                .max_restarts
+                .map_or("no limit".to_string(), |max| format!("at most {}", max));
+            println!("{}: restart {:?} ({} restarts)", name, spec.restart, limit);
+        }
+        return Ok(());
+    }
+    for name in &only {
+        if !services.iter().any(|(service, _, _)| service == name) {
+            bail!("Plugin {} is not loaded or has no background service", name);
+        }
+    }
+    if services.is_empty() {
+        bail!("No loaded plugin has a background service");
+    }
+
+    let names: Vec<&str> = services.iter().map(|(name, _, _)| *name).collect();
+    println!("Serving {} (Ctrl-C to stop)", names.join(", "));
+    let current_dir = env::current_dir()?;
+    let plugin_events = events.plugin_events();
+    let context = |name: &str| {
+        let args = vec![name.to_string()];
+        let diagnostics = DiagnosticSink::default();
+        execution_context(name, args, current_dir.clone(), &diagnostics, events)
+    };
+    let supervised = supervise_all(services, context, &plugin_events);
+    tokio::pin!(supervised);
+
+    let exits = tokio::select! {
+        exits = &mut supervised => exits,
+        _ = tokio::signal::ctrl_c() => {
+            println!("Stopping services...");
+            plugin_events.close();
+            match tokio::time::timeout(SHUTDOWN_GRACE, &mut supervised).await {
+                Ok(exits) => exits,
+                Err(_) => bail!("Services still running after {:?}; dropped them", SHUTDOWN_GRACE),
+            }
+        }
+    };
+
+    let mut failed = 0;
+    for (name, exit) in &exits {
+        match &exit.result {
+            Ok(()) => println!("✓ {} stopped after {} restarts", name, exit.restarts),
+            Err(e) => {
+                failed += 1;
+                eprintln!(
+                    "✗ {} failed after {} restarts: {:#}",
+                    name, exit.restarts, e
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} services failed", failed, exits.len());
+    }
+    Ok(())
+}
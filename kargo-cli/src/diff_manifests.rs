@@ -0,0 +1,749 @@
+//! `kargo diff-manifests <a> [b]`: what changed between two `Cargo.toml`s
+//!
+//! The manifests are compared as TOML, not text, so reformatting, reordering
+//! and moving a dependency between inline and standard tables change
+//! nothing. What's reported:
+//!
+//! - dependencies added, removed or changed in every dependency table,
+//!   including `[target.'cfg(..)'.*]` and `[workspace.dependencies]`. A
+//!   changed requirement is classified by the versions it allows: `1.0` to
+//!   `^1.0.0` is equivalent, `1.0` to `1.2` raises the minimum, and `1` to
+//!   `2` also leaves the old range behind entirely.
+//! - features added or removed, and what each enables
+//! - every other key, such as `package.version` or `profile.release.lto`
+//!
+//! Each side is a file, or a git revision of one as `REV:PATH`. A bare
+//! revision stands for the other side's file at that revision, and the second
+//! side defaults to `Cargo.toml`, so `kargo diff-manifests HEAD~3` shows what
+//! changed in the manifest since then.
+
+use anyhow::{Context, Result, bail};
+use cargo_metadata::semver::{Op, Version, VersionReq};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::manifest::{DEPENDENCY_TABLES, canonical};
+
+pub fn command() -> Command {
+    Command::new("diff-manifests")
+        .about("Compare two Cargo.toml files, or revisions of one, by meaning")
+        .arg(
+            Arg::new("a")
+                .value_name("A")
+                .help("Old manifest: a file, REV:PATH, or a revision of B")
+                .required(true),
+        )
+        .arg(
+            Arg::new("b")
+                .value_name("B")
+                .help("New manifest: a file, REV:PATH, or a revision of A")
+                .default_value("Cargo.toml"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the changes as JSON")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let a = matches.get_one::<String>("a").expect("required");
+    let b = matches.get_one::<String>("b").expect("has default");
+    let (a, b) = sides(a, b)?;
+    let diff = diff(&a.read()?, &b.read()?)?;
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        println!("--- {}\n+++ {}", a, b);
+        print!("{}", diff.render());
+    }
+    Ok(())
+}
+
+/// Where one side of the diff is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Side {
+    File(PathBuf),
+    Revision { rev: String, path: PathBuf },
+}
+
+impl Side {
+    pub fn read(&self) -> Result<String> {
+        match self {
+            Side::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display())),
+            Side::Revision { rev, path } => {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                let name = path.file_name().context("No file name")?.to_string_lossy();
+                // `./` makes git resolve the path from `dir` rather than the
+                // repository root
+                let output = std::process::Command::new("git")
+                    .arg("-C")
+                    .arg(dir)
+                    .arg("show")
+                    .arg(format!("{}:./{}", rev, name))
+                    .output()
+                    .context("Failed to run git show")?;
+                if !output.status.success() {
+                    bail!(
+                        "Failed to read {} at {}: {}",
+                        path.display(),
+                        rev,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+                String::from_utf8(output.stdout)
+                    .with_context(|| format!("{} at {} isn't UTF-8", path.display(), rev))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::File(path) => write!(f, "{}", path.display()),
+            Side::Revision { rev, path } => write!(f, "{}:{}", rev, path.display()),
+        }
+    }
+}
+
+/// Resolve both sides as given on the command line. An argument naming an
+/// existing file is that file, `REV:PATH` a revision of one, and anything
+/// else a revision of the other side's file.
+pub fn sides(a: &str, b: &str) -> Result<(Side, Side)> {
+    enum Arg {
+        Side(Side),
+        Rev(String),
+    }
+    let parse = |arg: &str| {
+        if Path::new(arg).is_file() {
+            return Arg::Side(Side::File(PathBuf::from(arg)));
+        }
+        match arg.split_once(':') {
+            Some((rev, path)) if !rev.is_empty() && !path.is_empty() => Arg::Side(Side::Revision {
+                rev: rev.to_string(),
+                path: PathBuf::from(path),
+            }),
+            _ => Arg::Rev(arg.to_string()),
+        }
+    };
+    let path = |side: &Side| match side {
+        Side::File(path) | Side::Revision { path, .. } => path.clone(),
+    };
+    Ok(match (parse(a), parse(b)) {
+        (Arg::Side(a), Arg::Side(b)) => (a, b),
+        (Arg::Rev(rev), Arg::Side(b)) => (
+            Side::Revision {
+                rev,
+                path: path(&b),
+            },
+            b,
+        ),
+        (Arg::Side(a), Arg::Rev(rev)) => {
+            let path = path(&a);
+            (a, Side::Revision { rev, path })
+        }
+        (Arg::Rev(a), Arg::Rev(b)) => bail!(
+            "Neither {} nor {} is a manifest; name one as a file or REV:PATH",
+            a,
+            b
+        ),
+    })
+}
+
+/// Everything that changed from one manifest to the other
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ManifestDiff {
+    pub dependencies: Vec<DependencyChange>,
+    pub features: Vec<FeatureChange>,
+    /// Every other key, by dotted path
+    pub metadata: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    fn sign(self) -> char {
+        match self {
+            Change::Added => '+',
+            Change::Removed => '-',
+            Change::Changed => '~',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyChange {
+    /// Dotted path of the table, e.g. `dev-dependencies` or
+    /// `target.cfg(unix).dependencies`
+    pub table: String,
+    /// Key of the dependency in the table
+    pub name: String,
+    pub change: Change,
+    /// The version requirement of a changed dependency; the whole entry of
+    /// an added or removed one
+    pub before: Option<String>,
+    pub after: Option<String>,
+    /// How the version requirement moved; `None` unless both sides have one
+    /// and they differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<RequirementChange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features_added: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features_removed: Vec<String>,
+    /// Changes to the rest of the entry: `optional`, `default-features`,
+    /// the source, ...
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+/// How the versions allowed by a requirement changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequirementChange {
+    /// Written differently, allowing the same versions
+    Equivalent,
+    /// The minimum went up, and the maximum if at all too
+    Raised,
+    /// The minimum went down, and the maximum if at all too
+    Lowered,
+    /// Allows more versions on at least one end
+    Widened,
+    /// Allows fewer versions on at least one end
+    Narrowed,
+    /// Allows no version the old requirement did, such as `1` to `2`
+    Disjoint,
+    /// Not both are semver requirements
+    Unknown,
+}
+
+impl RequirementChange {
+    /// Classify the move from `before` to `after`
+    pub fn of(before: &str, after: &str) -> Self {
+        let (Some((old_low, old_high)), Some((new_low, new_high))) =
+            (bounds(before), bounds(after))
+        else {
+            return Self::Unknown;
+        };
+        // `None` is unbounded
+        let above = |a: &Option<Version>, b: &Option<Version>| match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        };
+        let disjoint =
+            |low: &Version, high: &Option<Version>| high.as_ref().is_some_and(|h| low >= h);
+        if disjoint(&new_low, &old_high) || disjoint(&old_low, &new_high) {
+            return Self::Disjoint;
+        }
+        match (new_low.cmp(&old_low), above(&new_high, &old_high)) {
+            (Ordering::Equal, Ordering::Equal) => Self::Equivalent,
+            (Ordering::Greater, Ordering::Greater | Ordering::Equal) => Self::Raised,
+            (Ordering::Less, Ordering::Less | Ordering::Equal) => Self::Lowered,
+            (Ordering::Less | Ordering::Equal, Ordering::Greater) => Self::Widened,
+            (Ordering::Greater | Ordering::Equal, Ordering::Less) => Self::Narrowed,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Equivalent => "equivalent",
+            Self::Raised => "raised",
+            Self::Lowered => "lowered",
+            Self::Widened => "widened",
+            Self::Narrowed => "narrowed",
+            Self::Disjoint => "no version satisfies both",
+            Self::Unknown => "not comparable",
+        }
+    }
+}
+
+/// The versions `requirement` allows, as an inclusive lower and an exclusive
+/// upper bound; `None` for no upper bound. Pre-releases are left out.
+fn bounds(requirement: &str) -> Option<(Version, Option<Version>)> {
+    let req = VersionReq::parse(requirement).ok()?;
+    let mut low = Version::new(0, 0, 0);
+    let mut high: Option<Version> = None;
+    for c in &req.comparators {
+        let (major, minor, patch) = (c.major, c.minor, c.patch);
+        let at = Version::new(major, minor.unwrap_or(0), patch.unwrap_or(0));
+        // The version after everything `c`'s given parts match
+        let next = match (minor, patch) {
+            (None, _) => Version::new(major + 1, 0, 0),
+            (Some(minor), None) => Version::new(major, minor + 1, 0),
+            (Some(minor), Some(patch)) => Version::new(major, minor, patch + 1),
+        };
+        let (from, to) = match c.op {
+            Op::Exact | Op::Wildcard => (Some(at), Some(next)),
+            Op::Greater => (Some(next), None),
+            Op::GreaterEq => (Some(at), None),
+            Op::Less => (None, Some(at)),
+            Op::LessEq => (None, Some(next)),
+            Op::Tilde => match minor {
+                Some(minor) => (Some(at), Some(Version::new(major, minor + 1, 0))),
+                None => (Some(at), Some(Version::new(major + 1, 0, 0))),
+            },
+            Op::Caret => {
+                let to = match (major, minor, patch) {
+                    (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                    (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                    _ => Version::new(major + 1, 0, 0),
+                };
+                (Some(at), Some(to))
+            }
+            _ => return None,
+        };
+        if let Some(from) = from {
+            low = low.max(from);
+        }
+        if let Some(to) = to {
+            high = Some(high.map_or(to.clone(), |high| high.min(to)));
+        }
+    }
+    Some((low, high))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeatureChange {
+    pub name: String,
+    pub change: Change,
+    /// Features and dependencies it enables that it didn't before
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enables_added: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enables_removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub key: String,
+    pub change: Change,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl FieldChange {
+    fn between(key: String, before: Option<String>, after: Option<String>) -> Option<Self> {
+        let change = match (&before, &after) {
+            (None, None) => return None,
+            (Some(before), Some(after)) if before == after => return None,
+            (None, Some(_)) => Change::Added,
+            (Some(_), None) => Change::Removed,
+            (Some(_), Some(_)) => Change::Changed,
+        };
+        Some(Self {
+            key,
+            change,
+            before,
+            after,
+        })
+    }
+
+    fn describe(&self) -> String {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => format!("{} {} -> {}", self.key, before, after),
+            (Some(value), None) | (None, Some(value)) => format!("{} {}", self.key, value),
+            (None, None) => self.key.clone(),
+        }
+    }
+}
+
+/// Compare the manifest `before` to `after`
+pub fn diff(before: &str, after: &str) -> Result<ManifestDiff> {
+    let before: DocumentMut = before.parse().context("Failed to parse the old manifest")?;
+    let after: DocumentMut = after.parse().context("Failed to parse the new manifest")?;
+    let mut diff = ManifestDiff::default();
+
+    let (old_tables, new_tables) = (dependency_tables(&before), dependency_tables(&after));
+    let mut table_names: BTreeSet<&String> = old_tables.iter().map(|(name, _)| name).collect();
+    table_names.extend(new_tables.iter().map(|(name, _)| name));
+    for table in table_names {
+        let (old, new) = (
+            table_named(&old_tables, table),
+            table_named(&new_tables, table),
+        );
+        for name in keys(old, new) {
+            let old_entry = old.and_then(|t| t.get(&name)).map(Entry::of);
+            let new_entry = new.and_then(|t| t.get(&name)).map(Entry::of);
+            if let Some(change) = compare_dependency(table, name, old_entry, new_entry) {
+                diff.dependencies.push(change);
+            }
+        }
+    }
+
+    let (old, new) = (
+        before.get("features").and_then(Item::as_table_like),
+        after.get("features").and_then(Item::as_table_like),
+    );
+    for name in keys(old, new) {
+        let enables = |table: Option<&dyn TableLike>| {
+            table
+                .and_then(|t| t.get(&name))
+                .map(|item| strings(item.as_array()))
+        };
+        let (old_enables, new_enables) = (enables(old), enables(new));
+        let change = match (&old_enables, &new_enables) {
+            (None, Some(_)) => Change::Added,
+            (Some(_), None) => Change::Removed,
+            (Some(old), Some(new)) if old != new => Change::Changed,
+            _ => continue,
+        };
+        let (old_enables, new_enables) = (
+            old_enables.unwrap_or_default(),
+            new_enables.unwrap_or_default(),
+        );
+        diff.features.push(FeatureChange {
+            name,
+            change,
+            enables_added: missing_from(&new_enables, &old_enables),
+            enables_removed: missing_from(&old_enables, &new_enables),
+        });
+    }
+
+    compare_fields(
+        Some(before.as_table()),
+        Some(after.as_table()),
+        "",
+        &mut diff.metadata,
+    );
+    Ok(diff)
+}
+
+/// Dependency tables of a manifest, by dotted path
+fn dependency_tables(doc: &DocumentMut) -> Vec<(String, &dyn TableLike)> {
+    // Tables holding dependency tables, by the prefix of their path
+    let mut parents = vec![(String::new(), doc.as_table() as &dyn TableLike)];
+    if let Some(targets) = doc.get("target").and_then(Item::as_table_like) {
+        for (target, item) in targets.iter() {
+            if let Some(target_table) = item.as_table_like() {
+                parents.push((format!("target.{}.", target), target_table));
+            }
+        }
+    }
+    let mut tables = Vec::new();
+    for (prefix, parent) in parents {
+        for name in DEPENDENCY_TABLES {
+            if let Some(table) = parent.get(name).and_then(Item::as_table_like) {
+                tables.push((format!("{}{}", prefix, name), table));
+            }
+        }
+    }
+    if let Some(deps) = doc
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(Item::as_table_like)
+    {
+        tables.push(("workspace.dependencies".to_string(), deps));
+    }
+    tables
+}
+
+fn table_named<'a>(
+    tables: &[(String, &'a dyn TableLike)],
+    name: &str,
+) -> Option<&'a dyn TableLike> {
+    tables
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, table)| *table)
+}
+
+/// Whether `path` is reported as a dependency table or feature rather than
+/// as metadata
+fn is_compared_separately(path: &str) -> bool {
+    path == "features"
+        || path == "target"
+        || path == "workspace.dependencies"
+        || DEPENDENCY_TABLES.contains(&path)
+}
+
+/// Keys of either table, in order
+fn keys(old: Option<&dyn TableLike>, new: Option<&dyn TableLike>) -> BTreeSet<String> {
+    [old, new]
+        .into_iter()
+        .flatten()
+        .flat_map(|table| table.iter().map(|(key, _)| key.to_string()))
+        .collect()
+}
+
+fn strings(array: Option<&toml_edit::Array>) -> Vec<String> {
+    array
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Items of `from` not in `other`
+fn missing_from(from: &[String], other: &[String]) -> Vec<String> {
+    from.iter()
+        .filter(|item| !other.contains(item))
+        .cloned()
+        .collect()
+}
+
+/// A dependency entry, split into what's compared separately
+struct Entry {
+    version: Option<String>,
+    features: Vec<String>,
+    /// Every other key, canonicalized
+    fields: Vec<(String, String)>,
+}
+
+impl Entry {
+    fn of(item: &Item) -> Self {
+        if let Some(version) = item.as_str() {
+            return Self {
+                version: Some(version.to_string()),
+                features: Vec::new(),
+                fields: Vec::new(),
+            };
+        }
+        let Some(table) = item.as_table_like() else {
+            return Self {
+                version: None,
+                features: Vec::new(),
+                fields: vec![(String::new(), canonical(item))],
+            };
+        };
+        Self {
+            version: table
+                .get("version")
+                .and_then(Item::as_str)
+                .map(str::to_string),
+            features: strings(table.get("features").and_then(Item::as_array)),
+            fields: table
+                .iter()
+                .filter(|(key, _)| *key != "version" && *key != "features")
+                .map(|(key, item)| (key.to_string(), canonical(item)))
+                .collect(),
+        }
+    }
+
+    /// The entry in one line
+    fn describe(&self) -> String {
+        let mut parts: Vec<String> = self.version.iter().cloned().collect();
+        if !self.features.is_empty() {
+            parts.push(format!("features {}", self.features.join(", ")));
+        }
+        parts.extend(self.fields.iter().map(|(key, value)| match key.as_str() {
+            "" => value.clone(),
+            key => format!("{} = {}", key, value),
+        }));
+        parts.join(", ")
+    }
+}
+
+fn compare_dependency(
+    table: &str,
+    name: String,
+    old: Option<Entry>,
+    new: Option<Entry>,
+) -> Option<DependencyChange> {
+    let change = |change, before: Option<&Entry>, after: Option<&Entry>| DependencyChange {
+        table: table.to_string(),
+        name: name.clone(),
+        change,
+        before: before.map(Entry::describe),
+        after: after.map(Entry::describe),
+        requirement: None,
+        features_added: Vec::new(),
+        features_removed: Vec::new(),
+        fields: Vec::new(),
+    };
+    let (old, new) = match (old, new) {
+        (None, None) => return None,
+        (None, Some(new)) => return Some(change(Change::Added, None, Some(&new))),
+        (Some(old), None) => return Some(change(Change::Removed, Some(&old), None)),
+        (Some(old), Some(new)) => (old, new),
+    };
+    let mut fields = Vec::new();
+    let field = |entry: &Entry, key: &str| {
+        entry
+            .fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone())
+    };
+    let mut field_keys: Vec<&String> = old.fields.iter().map(|(key, _)| key).collect();
+    for (key, _) in &new.fields {
+        if !field_keys.contains(&key) {
+            field_keys.push(key);
+        }
+    }
+    for key in field_keys {
+        fields.extend(FieldChange::between(
+            key.clone(),
+            field(&old, key),
+            field(&new, key),
+        ));
+    }
+    let requirement = match (&old.version, &new.version) {
+        (Some(before), Some(after)) if before != after => {
+            Some(RequirementChange::of(before, after))
+        }
+        _ => None,
+    };
+    let features_added = missing_from(&new.features, &old.features);
+    let features_removed = missing_from(&old.features, &new.features);
+    // Feature order and how the requirement is written don't matter
+    let same_versions =
+        old.version == new.version || requirement == Some(RequirementChange::Equivalent);
+    if same_versions
+        && fields.is_empty()
+        && features_added.is_empty()
+        && features_removed.is_empty()
+    {
+        return None;
+    }
+    Some(DependencyChange {
+        before: old.version,
+        after: new.version,
+        requirement,
+        features_added,
+        features_removed,
+        fields,
+        ..change(Change::Changed, None, None)
+    })
+}
+
+/// Compare the keys of two tables outside dependencies and features, leaf
+/// by leaf
+fn compare_fields(
+    old: Option<&dyn TableLike>,
+    new: Option<&dyn TableLike>,
+    path: &str,
+    changes: &mut Vec<FieldChange>,
+) {
+    for key in keys(old, new) {
+        let key_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        if is_compared_separately(&key_path) {
+            continue;
+        }
+        let (old_item, new_item) = (old.and_then(|t| t.get(&key)), new.and_then(|t| t.get(&key)));
+        let (old_table, new_table) = (standard_table(old_item), standard_table(new_item));
+        if old_table.is_some() || new_table.is_some() {
+            compare_fields(old_table, new_table, &key_path, changes);
+            continue;
+        }
+        changes.extend(FieldChange::between(
+            key_path,
+            old_item.map(canonical),
+            new_item.map(canonical),
+        ));
+    }
+}
+
+/// Standard tables are walked; inline ones are values like any other
+fn standard_table(item: Option<&Item>) -> Option<&dyn TableLike> {
+    item.filter(|item| item.is_table())
+        .and_then(Item::as_table_like)
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty() && self.features.is_empty() && self.metadata.is_empty()
+    }
+
+    /// The changes as text, one per line under a heading per kind
+    pub fn render(&self) -> String {
+        if self.is_empty() {
+            return "No changes\n".to_string();
+        }
+        let mut out = String::new();
+        if !self.dependencies.is_empty() {
+            out.push_str("Dependencies:\n");
+            for dependency in &self.dependencies {
+                out.push_str(&format!(
+                    "  {} {}.{}",
+                    dependency.change.sign(),
+                    dependency.table,
+                    dependency.name
+                ));
+                let mut details = Vec::new();
+                match (dependency.change, &dependency.before, &dependency.after) {
+                    (Change::Changed, Some(before), Some(after)) if before != after => {
+                        let moved = dependency
+                            .requirement
+                            .map_or("", RequirementChange::describe);
+                        details.push(format!("{} -> {} ({})", before, after, moved));
+                    }
+                    (Change::Changed, before, after) if before != after => details.push(format!(
+                        "{} -> {}",
+                        before.as_deref().unwrap_or("no version"),
+                        after.as_deref().unwrap_or("no version")
+                    )),
+                    (Change::Changed, _, _) => {}
+                    (_, before, after) => details.extend(before.clone().or(after.clone())),
+                }
+                let mut features: Vec<String> = dependency
+                    .features_added
+                    .iter()
+                    .map(|f| format!("+{}", f))
+                    .collect();
+                features.extend(
+                    dependency
+                        .features_removed
+                        .iter()
+                        .map(|f| format!("-{}", f)),
+                );
+                if !features.is_empty() {
+                    details.push(format!("features {}", features.join(" ")));
+                }
+                details.extend(dependency.fields.iter().map(FieldChange::describe));
+                if !details.is_empty() {
+                    out.push_str(&format!(": {}", details.join("; ")));
+                }
+                out.push('\n');
+            }
+        }
+        if !self.features.is_empty() {
+            out.push_str("Features:\n");
+            for feature in &self.features {
+                let mut enables: Vec<String> = feature
+                    .enables_added
+                    .iter()
+                    .map(|f| format!("+{}", f))
+                    .collect();
+                enables.extend(feature.enables_removed.iter().map(|f| format!("-{}", f)));
+                out.push_str(&format!("  {} {}", feature.change.sign(), feature.name));
+                if !enables.is_empty() {
+                    out.push_str(&format!(": {}", enables.join(" ")));
+                }
+                out.push('\n');
+            }
+        }
+        if !self.metadata.is_empty() {
+            out.push_str("Metadata:\n");
+            for field in &self.metadata {
+                out.push_str(&format!("  {} {}\n", field.change.sign(), field.describe()));
+            }
+        }
+        out
+    }
+}
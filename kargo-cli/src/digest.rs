@@ -0,0 +1,310 @@
+//! `kargo digest`: what got worse across the fleet since the last digest
+//!
+//! The digest reads the same inputs as `kargo fleet report` (see
+//! [`crate::fleet::report`]) and compares them with the [`Snapshot`] the
+//! previous digest left behind: dependencies that became outdated or
+//! vulnerable, and projects that broke, since then. Problems that went away
+//! are counted too. The snapshot is then replaced, so running the digest from
+//! a weekly scheduled job reports one week at a time:
+//!
+//! ```yaml
+//! schedule:
+//!   - name: digest
+//!     command: digest --webhook https://hooks.slack.com/services/...
+//!     every: weekly
+//! ```
+//!
+//! With `--webhook`, the summary is posted as `{"text": ...}`, the payload
+//! Slack, Mattermost and most chat incoming webhooks accept.
+
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::{HttpClient, WriteGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::fleet::report::{self, Dashboard};
+use crate::schedule::{ago, unix_now};
+use crate::status::{OutdatedDependency, VulnerableDependency};
+
+pub fn command() -> Command {
+    report::input_args(
+        Command::new("digest")
+            .about("Summarize what became outdated, vulnerable or broken since the last digest")
+            .arg(
+                Arg::new("state")
+                    .long("state")
+                    .value_name("FILE")
+                    .help("Snapshot left by the previous digest (defaults to the kargo data directory)")
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("webhook")
+                    .long("webhook")
+                    .value_name("URL")
+                    .help("Post the summary to this incoming webhook"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the digest as JSON")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("dry-run")
+                    .long("dry-run")
+                    .help("Keep the previous snapshot, so the next digest covers this period again")
+                    .action(ArgAction::SetTrue),
+            ),
+    )
+}
+
+/// The problems of the fleet at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unix timestamp (seconds) the snapshot was taken at
+    pub taken: u64,
+    /// Paths of the broken projects
+    pub broken: BTreeSet<PathBuf>,
+    /// Manifest and crate of each outdated dependency
+    pub outdated: BTreeSet<(PathBuf, String)>,
+    /// Manifest, crate and advisory of each vulnerable dependency
+    pub vulnerable: BTreeSet<(PathBuf, String, String)>,
+}
+
+impl Snapshot {
+    pub fn of(dashboard: &Dashboard, taken: u64) -> Self {
+        let outdated = outdated(dashboard).map(outdated_key).collect();
+        let vulnerable = vulnerable(dashboard).map(vulnerable_key).collect();
+        Self {
+            taken,
+            broken: broken(dashboard)
+                .map(|(path, _)| path.to_path_buf())
+                .collect(),
+            outdated,
+            vulnerable,
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("digest")
+            .join("snapshot.json")
+    }
+
+    /// The snapshot at `path`, or `None` before the first digest
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                Ok(Some(serde_json::from_str(&content).with_context(|| {
+                    format!("Invalid digest snapshot {}", path.display())
+                })?))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read digest snapshot {}", path.display()))
+            }
+        }
+    }
+
+    /// Replace the snapshot at `path`; the digest's own state, so read-only
+    /// runs keep it up to date too
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let guard = WriteGuard::writable();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            guard.create_dir_all(parent)?;
+        }
+        guard
+            .write_atomic(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write digest snapshot {}", path.display()))
+    }
+}
+
+/// A project that broke since the last digest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokenProject {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// What changed between two digests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Digest {
+    /// When the previous digest ran; `None` for the first one, which reports
+    /// every current problem
+    pub since: Option<u64>,
+    pub projects: usize,
+    pub newly_outdated: Vec<OutdatedDependency>,
+    pub newly_vulnerable: Vec<VulnerableDependency>,
+    pub newly_broken: Vec<BrokenProject>,
+    /// Problems of the previous snapshot that are gone
+    pub resolved: usize,
+}
+
+impl Digest {
+    pub fn between(previous: Option<&Snapshot>, dashboard: &Dashboard) -> Self {
+        let empty = Snapshot::default();
+        let before = previous.unwrap_or(&empty);
+        let now = Snapshot::of(dashboard, 0);
+        let resolved = before.broken.difference(&now.broken).count()
+            + before.outdated.difference(&now.outdated).count()
+            + before.vulnerable.difference(&now.vulnerable).count();
+
+        Self {
+            since: previous.map(|snapshot| snapshot.taken),
+            projects: dashboard.projects.len(),
+            newly_outdated: outdated(dashboard)
+                .filter(|dependency| !before.outdated.contains(&outdated_key(dependency)))
+                .cloned()
+                .collect(),
+            newly_vulnerable: vulnerable(dashboard)
+                .filter(|dependency| !before.vulnerable.contains(&vulnerable_key(dependency)))
+                .cloned()
+                .collect(),
+            newly_broken: broken(dashboard)
+                .filter(|(path, _)| !before.broken.contains(*path))
+                .map(|(path, name)| BrokenProject {
+                    name: name.to_string(),
+                    path: path.to_path_buf(),
+                })
+                .collect(),
+            resolved,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newly_outdated.is_empty()
+            && self.newly_vulnerable.is_empty()
+            && self.newly_broken.is_empty()
+    }
+
+    /// The summary as chat-friendly Markdown
+    pub fn render(&self, now: u64) -> String {
+        let mut out = String::new();
+        let period = match self.since {
+            Some(since) => format!("since the last digest ({})", ago(since, now)),
+            None => "in the first digest".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "*kargo digest* — {} projects, {}",
+            self.projects, period
+        );
+        if self.is_empty() {
+            out.push_str("Nothing new to report.\n");
+        }
+        if !self.newly_vulnerable.is_empty() {
+            let _ = writeln!(
+                out,
+                "\n*Newly vulnerable ({})*",
+                self.newly_vulnerable.len()
+            );
+            for dependency in &self.newly_vulnerable {
+                let _ = writeln!(
+                    out,
+                    "• {} {} — {} ({})",
+                    dependency.name,
+                    dependency.version,
+                    dependency.advisory,
+                    dependency.path.display()
+                );
+            }
+        }
+        if !self.newly_broken.is_empty() {
+            let _ = writeln!(out, "\n*Newly broken ({})*", self.newly_broken.len());
+            for project in &self.newly_broken {
+                let _ = writeln!(out, "• {} ({})", project.name, project.path.display());
+            }
+        }
+        if !self.newly_outdated.is_empty() {
+            let _ = writeln!(out, "\n*Newly outdated ({})*", self.newly_outdated.len());
+            for dependency in &self.newly_outdated {
+                let _ = writeln!(
+                    out,
+                    "• {} {} → {} ({})",
+                    dependency.name,
+                    dependency.current,
+                    dependency.latest,
+                    dependency.path.display()
+                );
+            }
+        }
+        if self.resolved > 0 {
+            let _ = writeln!(out, "\n{} earlier problem(s) resolved.", self.resolved);
+        }
+        out
+    }
+}
+
+fn outdated(dashboard: &Dashboard) -> impl Iterator<Item = &OutdatedDependency> {
+    dashboard
+        .projects
+        .iter()
+        .flat_map(|project| &project.outdated)
+        .chain(&dashboard.unattributed_outdated)
+}
+
+fn vulnerable(dashboard: &Dashboard) -> impl Iterator<Item = &VulnerableDependency> {
+    dashboard
+        .projects
+        .iter()
+        .flat_map(|project| &project.vulnerable)
+        .chain(&dashboard.unattributed_vulnerable)
+}
+
+/// Path and name of each broken project
+fn broken(dashboard: &Dashboard) -> impl Iterator<Item = (&Path, &str)> {
+    dashboard
+        .projects
+        .iter()
+        .filter(|project| project.entry.status == "Broken")
+        .map(|project| (project.entry.path.as_path(), project.entry.name.as_str()))
+}
+
+fn outdated_key(dependency: &OutdatedDependency) -> (PathBuf, String) {
+    (dependency.path.clone(), dependency.name.clone())
+}
+
+fn vulnerable_key(dependency: &VulnerableDependency) -> (PathBuf, String, String) {
+    (
+        dependency.path.clone(),
+        dependency.name.clone(),
+        dependency.advisory.clone(),
+    )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<()> {
+    let state = matches
+        .get_one::<PathBuf>("state")
+        .cloned()
+        .unwrap_or_else(Snapshot::default_path);
+    let dashboard = Dashboard::from_matches(matches)?;
+    let previous = Snapshot::load(&state)?;
+    let digest = Digest::between(previous.as_ref(), &dashboard);
+    let now = unix_now();
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&digest)?);
+    } else {
+        print!("{}", digest.render(now));
+    }
+
+    if let Some(url) = matches.get_one::<String>("webhook") {
+        let client = HttpClient::new(concat!("kargo/", env!("CARGO_PKG_VERSION")))?;
+        let payload = serde_json::json!({ "text": digest.render(now) });
+        client
+            .send(client.post(url).json(&payload))
+            .await
+            .and_then(|response| Ok(response.error_for_status()?))
+            .with_context(|| format!("Failed to post the digest to {}", url))?;
+    }
+
+    if !matches.get_flag("dry-run") {
+        Snapshot::of(&dashboard, now).save(&state)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,472 @@
+//! Container images that pin kargo for reproducible fleet runs in CI
+//!
+//! An image holds the kargo binary, a selection of plugin artifacts and the
+//! baked config below [`INSTALL_DIR`], as a single layer on top of nothing:
+//!
+//! ```text
+//! /opt/kargo/bin/kargo
+//! /opt/kargo/plugins/libkargo_upgrade.so
+//! /opt/kargo/config.yaml
+//! /opt/kargo/image.json
+//! ```
+//!
+//! It's written as an OCI image layout directory, which `skopeo` or
+//! `docker buildx` can push or load, and meant to be copied into a runner
+//! image that has a Rust toolchain (`COPY --from=kargo /opt/kargo /opt/kargo`).
+//! The layer is built deterministically, so the same inputs always produce
+//! the same image digest.
+//!
+//! `image.json` lists every installed file with its hash. Its digest, the
+//! content digest, identifies what the image installs independently of how
+//! it's packaged, so a runner can check it matches with
+//! [`verify`] from inside the image.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::plugins::manager::find_existing_lib;
+
+/// Where an image installs kargo
+pub const INSTALL_DIR: &str = "/opt/kargo";
+/// The [`ImageContents`] of an image, relative to [`INSTALL_DIR`]
+pub const CONTENTS_FILE: &str = "image.json";
+/// The baked config, relative to [`INSTALL_DIR`]
+pub const CONFIG_FILE: &str = "config.yaml";
+
+const KARGO_FILE: &str = "bin/kargo";
+const PLUGIN_DIR: &str = "plugins";
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+/// Manifest annotation carrying [`ImageContents::digest`]
+pub const CONTENT_DIGEST_ANNOTATION: &str = "dev.kargo.content-digest";
+
+/// A compiled plugin that can be put into an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginArtifact {
+    /// Name the plugin is selected by: the file stem without a `lib` or
+    /// `kargo` prefix, so `libkargo_upgrade.so` is `upgrade`
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl PluginArtifact {
+    /// The artifact at `path`, if it's a native library or WASM module
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        let stem = match path.extension().and_then(OsStr::to_str)? {
+            "so" | "dylib" => stem.strip_prefix("lib").unwrap_or(stem),
+            "dll" | "wasm" => stem,
+            _ => return None,
+        };
+        let name = stem.replace('_', "-");
+        let name = name.strip_prefix("kargo-").unwrap_or(&name);
+        Some(Self {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Whether the artifact can be loaded on Linux, the images' platform
+    pub fn is_linux(&self) -> bool {
+        matches!(
+            self.path.extension().and_then(OsStr::to_str),
+            Some("so" | "wasm")
+        )
+    }
+}
+
+/// The compiled plugins in `search_paths`, by name. Like plugin discovery,
+/// a directory holding a `Cargo.toml` is a plugin project, represented by
+/// its release library once it's built; other directories hold artifacts.
+/// When several have the same name, the first search path wins.
+pub fn find_plugin_artifacts(search_paths: &[PathBuf]) -> Vec<PluginArtifact> {
+    let mut found: Vec<PluginArtifact> = Vec::new();
+    let mut add = |artifact: PluginArtifact| {
+        if !found.iter().any(|a| a.name == artifact.name) {
+            found.push(artifact);
+        }
+    };
+    for dir in search_paths {
+        if dir.join("Cargo.toml").is_file() {
+            match find_existing_lib(dir) {
+                Ok(Some(lib)) => {
+                    if let Some(artifact) = PluginArtifact::from_path(&lib) {
+                        add(artifact);
+                    }
+                }
+                Ok(None) => log::debug!("Plugin project {} is not built", dir.display()),
+                Err(e) => log::warn!("Skipping plugin project {}: {:#}", dir.display(), e),
+            }
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.is_file()
+                && let Some(artifact) = PluginArtifact::from_path(&path)
+            {
+                add(artifact);
+            }
+        }
+    }
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    found
+}
+
+/// The artifacts of the plugins in `wanted`, or of every plugin `allowed`
+/// accepts when none are named
+pub fn select_plugins(
+    available: &[PluginArtifact],
+    wanted: &[String],
+    allowed: impl Fn(&str) -> bool,
+) -> Result<Vec<PluginArtifact>> {
+    if wanted.is_empty() {
+        return Ok(available
+            .iter()
+            .filter(|a| allowed(&a.name))
+            .cloned()
+            .collect());
+    }
+    wanted
+        .iter()
+        .map(|name| {
+            available
+                .iter()
+                .find(|a| &a.name == name)
+                .cloned()
+                .with_context(|| {
+                    let known: Vec<&str> = available.iter().map(|a| a.name.as_str()).collect();
+                    format!(
+                        "no built plugin named '{}' (found: {})",
+                        name,
+                        if known.is_empty() {
+                            "none".to_string()
+                        } else {
+                            known.join(", ")
+                        }
+                    )
+                })
+        })
+        .collect()
+}
+
+/// What an image installs, written to its [`CONTENTS_FILE`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageContents {
+    pub kargo_version: String,
+    /// Profile the baked config selects by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Every installed file, sorted by path
+    pub files: Vec<ImageFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageFile {
+    /// Path relative to the install directory
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl ImageContents {
+    /// The content digest: `sha256:` of the contents as JSON
+    pub fn digest(&self) -> String {
+        let json = serde_json::to_vec(self).expect("contents serialize");
+        sha256(&json)
+    }
+
+    /// Read the contents of the image installed in `dir`
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(CONTENTS_FILE);
+        let content = std::fs::read_to_string(&path).with_context(|| {
+            format!(
+                "No image contents at {}; is this a kargo image?",
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid {}", path.display()))
+    }
+}
+
+/// Everything that goes into an image
+#[derive(Debug, Clone)]
+pub struct ImageSpec {
+    /// The kargo binary; it has to be built for Linux
+    pub kargo: PathBuf,
+    pub kargo_version: String,
+    pub plugins: Vec<PluginArtifact>,
+    /// The config to bake, as YAML
+    pub config: Option<String>,
+    /// Profile of `config` the image runs with
+    pub profile: Option<String>,
+    /// Reference name the image is tagged with in the layout
+    pub tag: String,
+}
+
+/// An image written by [`ImageSpec::write_layout`]
+#[derive(Debug, Clone)]
+pub struct BuiltImage {
+    /// Digest of the image manifest, as registries report it
+    pub manifest_digest: String,
+    pub contents: ImageContents,
+}
+
+/// A file of the image layer, relative to the install directory
+struct LayerFile {
+    path: String,
+    data: Vec<u8>,
+    mode: u32,
+}
+
+impl ImageSpec {
+    /// Write the image as an OCI image layout in `dir`
+    pub fn write_layout(&self, dir: &Path) -> Result<BuiltImage> {
+        let (files, contents) = self.layer_files()?;
+        let tar = layer_tar(&files)?;
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&tar)?;
+        let layer = gz.finish()?;
+
+        let content_digest = contents.digest();
+        let mut env = vec![format!(
+            "PATH={}/bin:/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+            INSTALL_DIR
+        )];
+        env.push(format!("KARGO_PLUGIN_PATH={}/{}", INSTALL_DIR, PLUGIN_DIR));
+        if self.config.is_some() {
+            env.push(format!("KARGO_CONFIG={}/{}", INSTALL_DIR, CONFIG_FILE));
+        }
+        let config = json!({
+            "architecture": oci_architecture(),
+            "os": "linux",
+            "config": {
+                "Env": env,
+                "Entrypoint": [format!("{}/{}", INSTALL_DIR, KARGO_FILE)],
+                "Labels": {
+                    "org.opencontainers.image.version": self.kargo_version,
+                    CONTENT_DIGEST_ANNOTATION: content_digest,
+                },
+            },
+            "rootfs": {
+                "type": "layers",
+                "diff_ids": [sha256(&tar)],
+            },
+            "history": [{"created_by": "kargo ci-image build"}],
+        });
+        let config = serde_json::to_vec(&config)?;
+        let manifest = json!({
+            "schemaVersion": 2,
+            "mediaType": MANIFEST_MEDIA_TYPE,
+            "config": descriptor(CONFIG_MEDIA_TYPE, &config),
+            "layers": [descriptor(LAYER_MEDIA_TYPE, &layer)],
+            "annotations": {
+                "org.opencontainers.image.version": self.kargo_version,
+                CONTENT_DIGEST_ANNOTATION: content_digest,
+            },
+        });
+        let manifest = serde_json::to_vec(&manifest)?;
+
+        let mut manifest_descriptor = descriptor(MANIFEST_MEDIA_TYPE, &manifest);
+        manifest_descriptor["annotations"] =
+            json!({ "org.opencontainers.image.ref.name": self.tag });
+        let index = json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.index.v1+json",
+            "manifests": [manifest_descriptor],
+        });
+
+        let blobs = dir.join("blobs").join("sha256");
+        std::fs::create_dir_all(&blobs)
+            .with_context(|| format!("Failed to create {}", blobs.display()))?;
+        for blob in [&layer, &config, &manifest] {
+            let digest = sha256(blob);
+            let name = digest.trim_start_matches("sha256:");
+            std::fs::write(blobs.join(name), blob)?;
+        }
+        std::fs::write(dir.join("oci-layout"), r#"{"imageLayoutVersion":"1.0.0"}"#)?;
+        std::fs::write(dir.join("index.json"), serde_json::to_vec(&index)?)?;
+
+        Ok(BuiltImage {
+            manifest_digest: sha256(&manifest),
+            contents,
+        })
+    }
+
+    /// The files the image installs, and their contents list
+    fn layer_files(&self) -> Result<(Vec<LayerFile>, ImageContents)> {
+        let read = |path: &Path| {
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+        let mut files = vec![LayerFile {
+            path: KARGO_FILE.to_string(),
+            data: read(&self.kargo)?,
+            mode: 0o755,
+        }];
+        for plugin in &self.plugins {
+            if !plugin.is_linux() {
+                bail!(
+                    "{} is not a Linux plugin; build it for Linux to put it in an image",
+                    plugin.path.display()
+                );
+            }
+            let file_name = plugin
+                .path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .context("Plugin path has no file name")?;
+            files.push(LayerFile {
+                path: format!("{}/{}", PLUGIN_DIR, file_name),
+                data: read(&plugin.path)?,
+                mode: 0o755,
+            });
+        }
+        if let Some(config) = &self.config {
+            files.push(LayerFile {
+                path: CONFIG_FILE.to_string(),
+                data: config.as_bytes().to_vec(),
+                mode: 0o644,
+            });
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let contents = ImageContents {
+            kargo_version: self.kargo_version.clone(),
+            profile: self.profile.clone(),
+            files: files
+                .iter()
+                .map(|file| ImageFile {
+                    path: file.path.clone(),
+                    sha256: sha256(&file.data),
+                    size: file.data.len() as u64,
+                })
+                .collect(),
+        };
+        files.push(LayerFile {
+            path: CONTENTS_FILE.to_string(),
+            data: serde_json::to_vec_pretty(&contents)?,
+            mode: 0o644,
+        });
+        Ok((files, contents))
+    }
+}
+
+/// Check the image installed in `dir` against the content digest
+/// `expected`, for a runner running kargo `running_version`. Returns what
+/// doesn't match; an empty list means the runner is the expected image.
+pub fn verify(dir: &Path, running_version: &str, expected: &str) -> Result<Vec<String>> {
+    let contents = ImageContents::load(dir)?;
+    let mut problems = Vec::new();
+    let digest = contents.digest();
+    if digest != expected {
+        problems.push(format!(
+            "image contents are {}, expected {}",
+            digest, expected
+        ));
+    }
+    if contents.kargo_version != running_version {
+        problems.push(format!(
+            "kargo {} is running, the image installs {}",
+            running_version, contents.kargo_version
+        ));
+    }
+
+    for file in &contents.files {
+        match std::fs::read(dir.join(&file.path)) {
+            Ok(data) if sha256(&data) == file.sha256 => {}
+            Ok(_) => problems.push(format!("{} differs from the image", file.path)),
+            Err(_) => problems.push(format!("{} is missing", file.path)),
+        }
+    }
+    // Plugins that were added afterwards change what kargo runs
+    let listed: BTreeSet<&str> = contents.files.iter().map(|f| f.path.as_str()).collect();
+    if let Ok(entries) = std::fs::read_dir(dir.join(PLUGIN_DIR)) {
+        let mut extra: Vec<String> = entries
+            .flatten()
+            .map(|e| format!("{}/{}", PLUGIN_DIR, e.file_name().to_string_lossy()))
+            .filter(|path| !listed.contains(path.as_str()))
+            .collect();
+        extra.sort();
+        for path in extra {
+            problems.push(format!("{} is not part of the image", path));
+        }
+    }
+    Ok(problems)
+}
+
+/// A tar of `files` below the install directory that only depends on their
+/// paths, contents and modes
+fn layer_tar(files: &[LayerFile]) -> Result<Vec<u8>> {
+    let root = INSTALL_DIR.trim_start_matches('/');
+    let mut dirs = BTreeSet::new();
+    for dir in Path::new(root)
+        .ancestors()
+        .filter(|d| !d.as_os_str().is_empty())
+    {
+        dirs.insert(dir.to_path_buf());
+    }
+    dirs.insert(Path::new(root).join("bin"));
+    dirs.insert(Path::new(root).join(PLUGIN_DIR));
+
+    let header = |kind: tar::EntryType, size: u64, mode: u32| {
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(kind);
+        header.set_size(size);
+        header.set_mode(mode);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header
+    };
+    let mut tar = tar::Builder::new(Vec::new());
+    for dir in dirs {
+        let mut dir_header = header(tar::EntryType::Directory, 0, 0o755);
+        tar.append_data(&mut dir_header, dir, std::io::empty())?;
+    }
+    for file in files {
+        let mut file_header = header(tar::EntryType::Regular, file.data.len() as u64, file.mode);
+        tar.append_data(
+            &mut file_header,
+            Path::new(root).join(&file.path),
+            file.data.as_slice(),
+        )?;
+    }
+    Ok(tar.into_inner()?)
+}
+
+fn descriptor(media_type: &str, blob: &[u8]) -> serde_json::Value {
+    json!({
+        "mediaType": media_type,
+        "digest": sha256(blob),
+        "size": blob.len(),
+    })
+}
+
+/// `sha256:` and the hex digest of `data`
+fn sha256(data: &[u8]) -> String {
+    let hash = Sha256::digest(data);
+    let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// The OCI name of the architecture kargo was built for
+fn oci_architecture() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "powerpc64" => "ppc64le",
+        other => other,
+    }
+}
@@ -1,8 +1,106 @@
-use std::path::PathBuf;
+//! The session's event bus
+//!
+//! Components publish what they do as [`Event`]s: what happened, stamped
+//! with when, which part of kargo it came from and how much it matters.
+//! The CI reporter and plugins subscribe to them, and with
+//! [`EventBus::persist`] every event of the run is also appended to a JSONL
+//! log under [`EventLog::default_dir`], which `kargo log` reads back.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use kargo_plugin_api::artifacts::{self, CapturedOutput};
+use kargo_plugin_api::{EventSubscription, HostEvent, HostEvents, WriteGuard};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::broadcast;
 
-#[derive(Debug, Clone)]
-pub enum Event {
+/// Number of run logs kept before the oldest are pruned
+pub const KEEP_LOGS: usize = 50;
+
+/// How much an event matters, from routine progress to failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// The part of kargo an event came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Commands kargo runs or forwards to cargo
+    Cli,
+    /// Dependency scanning and updates
+    Updater,
+    /// Post-update commands
+    Commands,
+    Vendor,
+    /// Snapshots and rollbacks
+    Backup,
+    Schedule,
+    Plugin,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Source::Cli => "cli",
+            Source::Updater => "updater",
+            Source::Commands => "commands",
+            Source::Vendor => "vendor",
+            Source::Backup => "backup",
+            Source::Schedule => "schedule",
+            Source::Plugin => "plugin",
+        })
+    }
+}
+
+/// Something that happened during the run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    pub source: Source,
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Event {
+    /// `kind` from `source`, happening now, at the severity of its kind
+    pub fn new(source: Source, kind: EventKind) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        Self {
+            timestamp,
+            source,
+            severity: kind.severity(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
     ScanStarted {
         dirs: Vec<PathBuf>,
     },
@@ -17,15 +115,33 @@ pub enum Event {
     },
     DependencyUpdated {
         path: PathBuf,
+        name: String,
         from: String,
         to: String,
     },
+    FileModified {
+        path: PathBuf,
+    },
+    DependencyOutdated {
+        path: PathBuf,
+        name: String,
+        current: String,
+        latest: String,
+    },
+    VulnerableDependency {
+        path: PathBuf,
+        name: String,
+        version: String,
+        advisory: String,
+    },
     CommandStarted {
         command: String,
     },
     CommandFinished {
         command: String,
         success: bool,
+        /// Where the command's stdout and stderr were saved
+        output: Option<CapturedOutput>,
     },
     KargoOutputLine {
         line: String,
@@ -46,6 +162,18 @@ pub enum Event {
     VendorFinished {
         path: PathBuf,
     },
+    PluginDiagnostic {
+        plugin: String,
+        diagnostic: kargo_plugin_api::Diagnostic,
+    },
+    ScheduledJobStarted {
+        job: String,
+    },
+    ScheduledJobFinished {
+        job: String,
+        exit_code: i32,
+        status_file: PathBuf,
+    },
     Error {
         message: String,
     },
@@ -60,24 +188,360 @@ pub enum Event {
     },
 }
 
+impl EventKind {
+    pub fn severity(&self) -> Severity {
+        use kargo_plugin_api::Severity as PluginSeverity;
+        match self {
+            EventKind::ScanStarted { .. }
+            | EventKind::CargoTomlFound { .. }
+            | EventKind::RustScriptFound { .. }
+            | EventKind::WorkspaceFound { .. }
+            | EventKind::KargoOutputLine { .. } => Severity::Debug,
+            EventKind::DependencyOutdated { .. } | EventKind::RollbackStarted { .. } => {
+                Severity::Warning
+            }
+            EventKind::VulnerableDependency { .. }
+            | EventKind::CommandFinished { success: false, .. }
+            | EventKind::KargoCommandFinished { success: false, .. }
+            | EventKind::Error { .. } => Severity::Error,
+            // Exit code 2 reports updates available
+            EventKind::ScheduledJobFinished { exit_code, .. } if !matches!(exit_code, 0 | 2) => {
+                Severity::Error
+            }
+            EventKind::PluginDiagnostic { diagnostic, .. } => match diagnostic.severity {
+                PluginSeverity::Note => Severity::Info,
+                PluginSeverity::Warning => Severity::Warning,
+                PluginSeverity::Error => Severity::Error,
+            },
+            _ => Severity::Info,
+        }
+    }
+
+    /// The event as plugins see it, if it's one they're told about
+    pub fn for_plugins(&self) -> Option<HostEvent> {
+        let event = match self {
+            EventKind::FileModified { path } => HostEvent::FileModified { path: path.clone() },
+            EventKind::DependencyUpdated {
+                path,
+                name,
+                from,
+                to,
+            } => HostEvent::DependencyUpdated {
+                manifest: path.clone(),
+                name: name.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            },
+            EventKind::RollbackFinished { path } => HostEvent::RolledBack {
+                backup: path.clone(),
+            },
+            EventKind::KargoCommandFinished {
+                subcommand,
+                success,
+                ..
+            } => HostEvent::CommandFinished {
+                command: subcommand.clone(),
+                success: *success,
+            },
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    /// The event a plugin published, if it's one the host reports on. These
+    /// are never passed back to plugins by [`Self::for_plugins`], so
+    /// forwarding them can't loop.
+    pub fn from_plugins(event: &HostEvent) -> Option<Self> {
+        let kind = match event {
+            HostEvent::DependencyOutdated {
+                manifest,
+                name,
+                current,
+                latest,
+            } => EventKind::DependencyOutdated {
+                path: manifest.clone(),
+                name: name.clone(),
+                current: current.clone(),
+                latest: latest.clone(),
+            },
+            HostEvent::VulnerableDependency {
+                manifest,
+                name,
+                version,
+                advisory,
+            } => EventKind::VulnerableDependency {
+                path: manifest.clone(),
+                name: name.clone(),
+                version: version.clone(),
+                advisory: advisory.clone(),
+            },
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// One line describing the event, as `kargo log` prints it
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::ScanStarted { dirs } => write!(f, "Scanning {} director(ies)", dirs.len()),
+            EventKind::CargoTomlFound { path } => write!(f, "Found {}", path.display()),
+            EventKind::RustScriptFound { path } => {
+                write!(f, "Found rust-script {}", path.display())
+            }
+            EventKind::WorkspaceFound { path } => {
+                write!(f, "Found workspace {}", path.display())
+            }
+            EventKind::DependencyUpdated {
+                path,
+                name,
+                from,
+                to,
+            } => write!(
+                f,
+                "Updated {} {} -> {} in {}",
+                name,
+                from,
+                to,
+                path.display()
+            ),
+            EventKind::FileModified { path } => write!(f, "Modified {}", path.display()),
+            EventKind::DependencyOutdated {
+                path,
+                name,
+                current,
+                latest,
+            } => write!(
+                f,
+                "{} {} can be updated to {} in {}",
+                name,
+                current,
+                latest,
+                path.display()
+            ),
+            EventKind::VulnerableDependency {
+                path,
+                name,
+                version,
+                advisory,
+            } => write!(
+                f,
+                "{} {} is affected by {} in {}",
+                name,
+                version,
+                advisory,
+                path.display()
+            ),
+            EventKind::CommandStarted { command } => write!(f, "Running {}", command),
+            EventKind::CommandFinished {
+                command,
+                success,
+                output,
+            } => {
+                let outcome = if *success { "succeeded" } else { "failed" };
+                write!(f, "{} {}", command, outcome)?;
+                match output {
+                    Some(output) => write!(f, " (output in {})", output.stderr.display()),
+                    None => Ok(()),
+                }
+            }
+            EventKind::KargoOutputLine { line, .. } => f.write_str(line),
+            EventKind::KargoCommandStarted { args, .. } => write!(f, "cargo {}", args.join(" ")),
+            EventKind::KargoCommandFinished {
+                subcommand,
+                success,
+                summary,
+            } => {
+                let outcome = if *success { "succeeded" } else { "failed" };
+                write!(f, "{} {}", subcommand, outcome)?;
+                if !summary.is_empty() {
+                    write!(f, " ({})", summary)?;
+                }
+                Ok(())
+            }
+            EventKind::VendorStarted { path } => write!(f, "Vendoring {}", path.display()),
+            EventKind::VendorFinished { path } => write!(f, "Vendored {}", path.display()),
+            EventKind::PluginDiagnostic { plugin, diagnostic } => {
+                write!(f, "{}: {}: {}", plugin, diagnostic.code, diagnostic.message)
+            }
+            EventKind::ScheduledJobStarted { job } => write!(f, "Started job {}", job),
+            EventKind::ScheduledJobFinished { job, exit_code, .. } => {
+                write!(f, "Job {} exited with {}", job, exit_code)
+            }
+            EventKind::Error { message } | EventKind::Info { message } => f.write_str(message),
+            EventKind::RollbackStarted { path } => {
+                write!(f, "Rolling back from {}", path.display())
+            }
+            EventKind::RollbackFinished { path } => {
+                write!(f, "Rolled back from {}", path.display())
+            }
+        }
+    }
+}
+
+/// The logs of past runs, one JSONL file of events per run
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    dir: PathBuf,
+}
+
+impl EventLog {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("logs")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The log of `run`, named after its run id (see
+    /// [`kargo_plugin_api::artifacts::run_id`])
+    pub fn path(&self, run: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", run))
+    }
+
+    /// Ids of the runs with a log, oldest first
+    pub fn runs(&self) -> Result<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut runs: Vec<String> = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.strip_suffix(".jsonl").map(str::to_string)
+            })
+            .collect();
+        runs.sort();
+        Ok(runs)
+    }
+
+    /// The events of `run`, oldest first. Unreadable lines are skipped.
+    pub fn read(&self, run: &str) -> Result<Vec<Event>> {
+        let path = self.path(run);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("No log of run {} ({})", run, path.display()))?;
+        Ok(parse_lines(&content))
+    }
+
+    /// Drop the oldest logs so at most [`KEEP_LOGS`] remain, counting the one
+    /// of `current`
+    fn prune(&self, current: &str) {
+        let Ok(runs) = self.runs() else {
+            return;
+        };
+        let others: Vec<&String> = runs.iter().filter(|run| *run != current).collect();
+        let excess = (others.len() + 1).saturating_sub(KEEP_LOGS);
+        for run in &others[..excess] {
+            if let Err(e) = WriteGuard::current().remove_file(self.path(run)) {
+                log::warn!("Failed to prune the log of run {}: {}", run, e);
+            }
+        }
+    }
+}
+
+/// The events in JSONL `content`, skipping lines that aren't events
+pub fn parse_lines(content: &str) -> Vec<Event> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct EventBus {
     tx: broadcast::Sender<Event>,
+    plugin_events: HostEvents,
+    /// Where [`Self::forward_plugin_events`] continues reading
+    from_plugins: Arc<Mutex<EventSubscription>>,
+    /// Log file every published event is appended to, once persisted
+    log: Arc<Mutex<Option<PathBuf>>>,
 }
 
 impl EventBus {
     pub fn new() -> Self {
         let (tx, _) = broadcast::channel(100);
-        Self { tx }
+        Self::with_sender(tx)
+    }
+
+    fn with_sender(tx: broadcast::Sender<Event>) -> Self {
+        let plugin_events = HostEvents::default();
+        Self {
+            tx,
+            from_plugins: Arc::new(Mutex::new(plugin_events.subscribe())),
+            plugin_events,
+            log: Arc::default(),
+        }
+    }
+
+    /// Append every event published from now on, by this bus or its
+    /// clones, to this run's file in `log`, returning the file's path
+    pub fn persist(&self, log: &EventLog) -> Result<PathBuf> {
+        WriteGuard::current().create_dir_all(log.dir())?;
+        log.prune(artifacts::run_id());
+        let path = log.path(artifacts::run_id());
+        *self.log.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.clone());
+        Ok(path)
+    }
+
+    /// The session's events as plugins receive them in their
+    /// `ExecutionContext`
+    pub fn plugin_events(&self) -> HostEvents {
+        self.plugin_events.clone()
+    }
+
+    /// Publish the outdated and vulnerable dependencies plugins reported on
+    /// their events since the last call (see [`EventKind::from_plugins`])
+    pub async fn forward_plugin_events(&self) {
+        let events: Vec<HostEvent> = {
+            let mut from_plugins = self.from_plugins.lock().unwrap_or_else(|e| e.into_inner());
+            std::iter::from_fn(|| from_plugins.try_recv()).collect()
+        };
+        for kind in events.iter().filter_map(EventKind::from_plugins) {
+            self.publish(Source::Plugin, kind);
+            // Give subscribers a chance to drain before the channel fills up
+            tokio::task::yield_now().await;
+        }
     }
 
     pub fn subscribe(&self) -> broadcast::Receiver<Event> {
         self.tx.subscribe()
     }
 
-    pub fn publish(&self, event: Event) {
+    pub fn publish(&self, source: Source, kind: EventKind) {
+        let event = Event::new(source, kind);
+        // Mirrored into the log so events can be correlated with the active span
+        tracing::debug!(target: "kargo::events", event = ?event);
+        self.append_to_log(&event);
+        if let Some(host_event) = event.kind.for_plugins() {
+            self.plugin_events.publish(host_event);
+        }
         let _ = self.tx.send(event);
     }
+
+    fn append_to_log(&self, event: &Event) {
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(path) = log.as_ref() else {
+            return;
+        };
+        let result = serde_json::to_string(event)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| WriteGuard::current().append(path, line + "\n"));
+        if let Err(e) = result {
+            // One warning rather than one per event
+            log::warn!("Event log disabled: {:#}", e);
+            *log = None;
+        }
+    }
 }
 
 // Add From implementation for broadcast::Receiver<Event>
@@ -95,6 +559,6 @@ impl From<broadcast::Receiver<Event>> for EventBus {
             }
         });
 
-        Self { tx }
+        Self::with_sender(tx)
     }
 }
@@ -0,0 +1,398 @@
+//! `kargo explain`: causes and remediation for an error, like `rustc --explain`
+//!
+//! Accepts any code kargo reports: an exit status by name or number
+//! (`config-error`, `4`), a diagnostic code (`policy::banned`), or the code of
+//! a recognized cargo/rustup failure (`cargo::locked`). Codes declared by
+//! loaded plugins are described from their diagnostics schema. Anything else
+//! is treated as pasted error output and matched against known failures.
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::ReadOnlyError;
+use regex::Regex;
+
+use crate::plugins::manager::PluginManager;
+use crate::status::ExitStatus;
+
+/// What an error means and what to do about it
+#[derive(Debug)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub causes: &'static [&'static str],
+    pub remediation: &'static [&'static str],
+}
+
+/// Explanations of the exit statuses, kargo's own diagnostics and the cargo
+/// and rustup failures in [`PATTERNS`]
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "failure",
+        title: "The command failed (exit code 1)",
+        causes: &[
+            "A plugin returned an error or panicked",
+            "cargo or another external tool exited unsuccessfully",
+            "A file could not be read or written",
+        ],
+        remediation: &[
+            "Read the error chain printed above the exit; the last cause is usually the root one",
+            "Re-run with KARGO_LOG=debug for the steps leading up to it",
+            "Paste the error into `kargo explain \"...\"` to match it against known failures",
+        ],
+    },
+    Explanation {
+        code: "updates-available",
+        title: "Outdated or vulnerable dependencies were found (exit code 2)",
+        causes: &[
+            "The command succeeded, but at least one dependency has a newer or patched release",
+        ],
+        remediation: &[
+            "Run `kargo upgrade` to apply the updates",
+            "Use `--status-file` to get the list as JSON for scripts",
+        ],
+    },
+    Explanation {
+        code: "partial-failure",
+        title: "Some steps failed while others succeeded (exit code 3)",
+        causes: &[
+            "`kargo fleet` ran a plugin in several projects and only some of them failed",
+            "A multi-step command skipped the steps that depended on a failed one",
+        ],
+        remediation: &[
+            "Look for the ❌ entries in the summary to find the failed projects",
+            "Re-run the command in those projects alone to see their full output",
+        ],
+    },
+    Explanation {
+        code: "config-error",
+        title: "Configuration could not be read or is invalid (exit code 4)",
+        causes: &[
+            "The kargo config file is not valid YAML or has a field of the wrong type",
+            "The selected profile (--profile or KARGO_PROFILE) does not exist",
+            "The project's .kargo.toml sets an unknown key or a value of the wrong type",
+            "The project's .kargo.toml sets what only your own config may, such as plugin_trust",
+            "A KARGO_* variable such as KARGO_ROLLBACK_ON_FAILURE has an invalid value",
+            "A policy or inventory file referenced by the config failed to parse",
+        ],
+        remediation: &[
+            "Check the file and line named in the error",
+            "List the profiles defined in the config and check the spelling of --profile",
+            "Move the file aside to confirm kargo runs with the defaults",
+        ],
+    },
+    Explanation {
+        code: "usage-error",
+        title: "Invalid command line (exit code 5)",
+        causes: &[
+            "An unknown flag, a missing argument or a value of the wrong type",
+            "A plugin subcommand that is not loaded in the active profile",
+        ],
+        remediation: &[
+            "Run `kargo <command> --help` for the accepted arguments",
+            "Run `kargo --help` to list the subcommands of the loaded plugins",
+        ],
+    },
+    Explanation {
+        code: "read-only",
+        title: "A write was refused because kargo runs read-only",
+        causes: &["`--read-only` was passed, and the command or a plugin tried to write a file"],
+        remediation: &[
+            "Use the command's dry-run or report mode to see what it would change",
+            "Drop `--read-only` once the plugin or command is trusted to write",
+        ],
+    },
+    Explanation {
+        code: "upgrade::failed",
+        title: "A dependency could not be upgraded",
+        causes: &[
+            "The registry could not be reached or has no matching release",
+            "The manifest could not be parsed or written",
+        ],
+        remediation: &[
+            "Check network access to the registry, or the `registry` setting of the config",
+            "Run `kargo upgrade` again with KARGO_LOG=debug to see the failing request",
+        ],
+    },
+    Explanation {
+        code: "upgrade::breakage",
+        title: "An upgrade broke the build",
+        causes: &["The new version has breaking API changes the project's code doesn't handle yet"],
+        remediation: &[
+            "Read the release notes of the crate for migration steps",
+            "Roll the upgrade back with `kargo restore` and pin the previous version",
+        ],
+    },
+    Explanation {
+        code: "policy::banned",
+        title: "A dependency is banned by policy",
+        causes: &["A manifest depends on a crate listed under [[ban]] in a policy file"],
+        remediation: &[
+            "Replace the crate; the diagnostic carries the reason the policy gives",
+            "Check the policy files in the kargo config directory and .kargo/policy.toml",
+        ],
+    },
+    Explanation {
+        code: "policy::minimum-version",
+        title: "A requirement allows versions below the policy minimum",
+        causes: &[
+            "The version requirement's lowest allowed version is below [minimum] in a policy file",
+        ],
+        remediation: &[
+            "Raise the requirement, e.g. from \"1.20\" to \"1.38\", and update the lockfile",
+        ],
+    },
+    Explanation {
+        code: "policy::git-branch",
+        title: "A git dependency tracks a forbidden branch",
+        causes: &[
+            "A git dependency uses a branch listed in git.forbid_branches",
+            "A git dependency without branch, tag or rev follows the default branch",
+        ],
+        remediation: &["Pin the dependency with `tag = \"...\"` or `rev = \"...\"`"],
+    },
+    Explanation {
+        code: "policy::dependency-count",
+        title: "A manifest has more dependencies than the policy allows",
+        causes: &["The normal and build dependencies exceed max_dependencies in a policy file"],
+        remediation: &[
+            "Remove unused dependencies (e.g. with cargo-machete or cargo-udeps)",
+            "Split the crate, or raise max_dependencies in the project's .kargo/policy.toml",
+        ],
+    },
+    Explanation {
+        code: "cargo::locked",
+        title: "The lockfile is out of date but --locked was passed",
+        causes: &[
+            "A manifest changed without `cargo update` or a build being run afterwards",
+            "Cargo.lock was not committed along with the manifest change",
+        ],
+        remediation: &[
+            "Run `cargo update --workspace` (or a plain build) and commit Cargo.lock",
+            "Drop --locked locally; keep it in CI to catch this",
+        ],
+    },
+    Explanation {
+        code: "cargo::version-conflict",
+        title: "Cargo could not resolve a compatible set of versions",
+        causes: &[
+            "Two dependencies require incompatible versions of the same crate",
+            "A crate with a `links` key is pulled in twice in different versions",
+            "The requirement matches no published version",
+        ],
+        remediation: &[
+            "Run `cargo tree -i <crate>` to find who requires each version",
+            "Loosen or raise the conflicting requirements, then `cargo update -p <crate>`",
+        ],
+    },
+    Explanation {
+        code: "cargo::manifest-missing",
+        title: "No Cargo.toml was found",
+        causes: &["The command ran outside a cargo project"],
+        remediation: &[
+            "Change into the project directory, or pass --manifest-path",
+            "Use `kargo fleet` to run across projects below a directory",
+        ],
+    },
+    Explanation {
+        code: "cargo::registry-unreachable",
+        title: "The registry or a git source could not be reached",
+        causes: &[
+            "No network access, or a proxy that cargo is not configured for",
+            "A private registry whose credentials are missing or expired",
+        ],
+        remediation: &[
+            "Retry with --offline if everything needed is already in the local cache",
+            "Check http.proxy in .cargo/config.toml and `cargo login` for the registry",
+        ],
+    },
+    Explanation {
+        code: "cargo::linker-missing",
+        title: "The linker could not be run",
+        causes: &["No C toolchain is installed, or the configured linker is not on PATH"],
+        remediation: &[
+            "Install one: build-essential on Debian/Ubuntu, Xcode command line tools on macOS",
+            "Check the `linker` setting of the target in .cargo/config.toml",
+        ],
+    },
+    Explanation {
+        code: "rustup::toolchain-missing",
+        title: "The requested toolchain is not installed",
+        causes: &[
+            "rust-toolchain.toml or a +toolchain argument names a toolchain that isn't installed",
+            "A required target or component is missing from the toolchain",
+        ],
+        remediation: &[
+            "Run `rustup toolchain install <toolchain>` (or `rustup target add <target>`)",
+            "Run `rustup show` to see which toolchain the directory selects and why",
+        ],
+    },
+    Explanation {
+        code: "rustup::no-default",
+        title: "rustup has no default toolchain",
+        causes: &["rustup is installed but no toolchain has been set as the default"],
+        remediation: &["Run `rustup default stable`"],
+    },
+];
+
+/// Messages of cargo and rustup failures, and the code that explains them
+const PATTERNS: &[(&str, &str)] = &[
+    (
+        r"lock file .* needs to be updated but --(locked|frozen) was passed",
+        "cargo::locked",
+    ),
+    (
+        r"failed to select a version for|multiple packages link to native library",
+        "cargo::version-conflict",
+    ),
+    (
+        r"could not find `Cargo\.toml` in",
+        "cargo::manifest-missing",
+    ),
+    (
+        r"failed to (download|fetch|get|load source for)|failed to update registry|Couldn't resolve host",
+        "cargo::registry-unreachable",
+    ),
+    (
+        r"linker `[^`]+` not found|error: linking with `[^`]+` failed",
+        "cargo::linker-missing",
+    ),
+    (
+        r"toolchain '[^']+' is not installed|component '[^']+' .* is unavailable|target may not be installed",
+        "rustup::toolchain-missing",
+    ),
+    (
+        r"no (override and no )?default toolchain (is )?(set|configured)",
+        "rustup::no-default",
+    ),
+];
+
+/// The explanation of `code`; exit statuses are also found by number
+pub fn lookup(code: &str) -> Option<&'static Explanation> {
+    let code = code.trim();
+    let code = match code.parse::<i32>() {
+        Ok(number) => status_name(number)?,
+        Err(_) => code,
+    };
+    EXPLANATIONS.iter().find(|e| e.code == code)
+}
+
+fn status_name(code: i32) -> Option<&'static str> {
+    [
+        (ExitStatus::Failure, "failure"),
+        (ExitStatus::UpdatesAvailable, "updates-available"),
+        (ExitStatus::PartialFailure, "partial-failure"),
+        (ExitStatus::ConfigError, "config-error"),
+        (ExitStatus::UsageError, "usage-error"),
+    ]
+    .into_iter()
+    .find(|(status, _)| status.code() == code)
+    .map(|(_, name)| name)
+}
+
+/// Explanations of the known failures found in error output, in the order of
+/// [`PATTERNS`]
+pub fn detect(output: &str) -> Vec<&'static Explanation> {
+    PATTERNS
+        .iter()
+        .filter(|(pattern, _)| Regex::new(pattern).is_ok_and(|re| re.is_match(output)))
+        .filter_map(|(_, code)| lookup(code))
+        .collect()
+}
+
+/// The code to suggest `kargo explain` for after `error` ended a run
+pub fn suggestion(error: &anyhow::Error) -> &'static str {
+    let chain = format!("{:#}", error);
+    if error.chain().any(|cause| cause.is::<ReadOnlyError>()) {
+        return "read-only";
+    }
+    match detect(&chain).first() {
+        Some(explanation) => explanation.code,
+        None => match ExitStatus::from_error(error) {
+            ExitStatus::ConfigError => "config-error",
+            ExitStatus::UsageError => "usage-error",
+            _ => "failure",
+        },
+    }
+}
+
+pub fn command() -> Command {
+    Command::new("explain")
+        .about("Explain an exit status, diagnostic code or cargo/rustup error")
+        .arg(
+            Arg::new("code")
+                .value_name("CODE")
+                .help("Code to explain (e.g. config-error, 4, policy::banned), or pasted error output")
+                .required_unless_present("list"),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .help("List every code with an explanation")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(pm: &PluginManager, matches: &ArgMatches) -> Result<()> {
+    if matches.get_flag("list") {
+        for explanation in EXPLANATIONS {
+            println!("{:<28} {}", explanation.code, explanation.title);
+        }
+        for (name, plugin) in pm.plugins_iter() {
+            for kind in plugin.diagnostics_schema().unwrap_or_default().kinds {
+                println!("{:<28} {} ({})", kind.code, kind.description, name);
+            }
+        }
+        return Ok(());
+    }
+
+    let Some(code) = matches.get_one::<String>("code") else {
+        bail!("No code to explain");
+    };
+    if let Some(explanation) = lookup(code) {
+        print_explanation(explanation);
+        return Ok(());
+    }
+    for (name, plugin) in pm.plugins_iter() {
+        if let Some(kind) = plugin
+            .diagnostics_schema()
+            .and_then(|s| s.kind(code).cloned())
+        {
+            println!("{}: {}", kind.code, kind.description);
+            println!();
+            println!(
+                "Reported by the {} plugin; see `kargo {} --help`.",
+                name, name
+            );
+            return Ok(());
+        }
+    }
+
+    let detected = detect(code);
+    if detected.is_empty() {
+        bail!(
+            "No explanation for '{}'; run `kargo explain --list` for the known codes",
+            code
+        );
+    }
+    for (i, explanation) in detected.into_iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_explanation(explanation);
+    }
+    Ok(())
+}
+
+fn print_explanation(explanation: &Explanation) {
+    println!("{}: {}", explanation.code, explanation.title);
+    println!();
+    println!("Likely causes:");
+    for cause in explanation.causes {
+        println!("  - {}", cause);
+    }
+    println!();
+    println!("What to do:");
+    for step in explanation.remediation {
+        println!("  - {}", step);
+    }
+}
@@ -0,0 +1,614 @@
+//! `kargo features why-unified <crate>`: why a dependency is built with the
+//! features it has
+//!
+//! Cargo builds each dependency once per workspace, with the union of the
+//! features every dependent asks for, so one member asking for a feature turns
+//! it on for all of them. Using the resolved `cargo metadata` graph (see
+//! [`kargo_plugin_api::metadata`]), every enabled feature is traced back to
+//! what activates it: a dependent's declaration, its default features, a
+//! feature forwarded as `dep/feature`, or another feature of the crate. The
+//! features each member gets without asking for them are listed, and where a
+//! member's own declaration is to blame, a diff gating those features behind a
+//! feature of the member is suggested.
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::metadata::{
+    DeclaredDependency, DepKind, MetadataMode, PackageInfo, ProjectMetadata,
+};
+use serde::Serialize;
+use similar::TextDiff;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, value};
+
+pub fn command() -> Command {
+    Command::new("features")
+        .about("Inspect how cargo unifies dependency features across the workspace")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("why-unified")
+                .about("Explain which members and features turn on a dependency's features")
+                .arg(
+                    Arg::new("crate")
+                        .value_name("CRATE")
+                        .help("Dependency to explain, optionally with a version (`name@1.2.3`)")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .value_name("DIR")
+                        .help("Project directory")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value("."),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the explanation as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let Some(("why-unified", sub)) = matches.subcommand() else {
+        bail!("Missing features subcommand");
+    };
+    let spec = sub.get_one::<String>("crate").expect("required");
+    let dir = sub.get_one::<PathBuf>("path").expect("has default");
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (spec.as_str(), None),
+    };
+
+    let metadata = ProjectMetadata::load(&dir.join("Cargo.toml"), MetadataMode::Resolved)?;
+    let unifications = why_unified(&metadata, name, version)?;
+    if sub.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&unifications)?);
+    } else {
+        for (i, unification) in unifications.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("{}", unification.render());
+        }
+    }
+    Ok(())
+}
+
+/// The feature set of one version of a dependency and where it comes from
+#[derive(Debug, Clone, Serialize)]
+pub struct Unification {
+    pub package: String,
+    pub version: String,
+    /// Features cargo enables on the crate
+    pub features: Vec<String>,
+    /// What turns on each enabled feature
+    pub reasons: BTreeMap<String, Vec<Reason>>,
+    /// Per workspace member depending on the crate, the features it asks for
+    /// itself and the ones it gets from the rest of the workspace
+    pub members: BTreeMap<String, MemberFeatures>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemberFeatures {
+    /// Features the member's own dependency graph asks for
+    pub own: Vec<String>,
+    /// Features enabled only because other members ask for them
+    pub unified: Vec<String>,
+}
+
+/// One package turning on a feature
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reason {
+    pub by: String,
+    pub version: String,
+    pub how: Activation,
+    /// Workspace members whose dependency graph contains `by`
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "feature")]
+pub enum Activation {
+    /// Listed in the `features` of the dependency declaration
+    Declared,
+    /// The declaration keeps the default features
+    Default,
+    /// Forwarded as `dep/feature` by this feature of the dependent
+    Forwarded(String),
+    /// Enabled by this other feature of the crate itself
+    Implied(String),
+}
+
+/// A change to a member's manifest that stops it from turning features on
+/// for the whole workspace
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub member: String,
+    pub manifest: PathBuf,
+    /// Features moved behind features of the member
+    pub features: Vec<String>,
+    pub summary: String,
+    /// Unified diff of the manifest
+    pub diff: String,
+}
+
+/// Explain the features of every resolved version of `name` (or just
+/// `version`)
+pub fn why_unified(
+    metadata: &ProjectMetadata,
+    name: &str,
+    version: Option<&str>,
+) -> Result<Vec<Unification>> {
+    let Some(resolve) = &metadata.resolve else {
+        bail!("The dependency graph wasn't resolved");
+    };
+    let mut targets: Vec<&PackageInfo> = metadata
+        .packages
+        .iter()
+        .filter(|p| p.name == name && version.is_none_or(|v| p.version == v))
+        .filter(|p| resolve.contains_key(&p.id))
+        .collect();
+    if targets.is_empty() {
+        match version {
+            Some(version) => bail!("{}@{} is not in the dependency graph", name, version),
+            None => bail!("{} is not in the dependency graph", name),
+        }
+    }
+    targets.sort_by(|a, b| a.version.cmp(&b.version));
+
+    let reach = member_reach(metadata);
+    Ok(targets
+        .into_iter()
+        .map(|target| unification(metadata, &reach, target))
+        .collect())
+}
+
+fn unification(
+    metadata: &ProjectMetadata,
+    reach: &BTreeMap<&str, HashSet<&str>>,
+    target: &PackageInfo,
+) -> Unification {
+    let enabled: BTreeSet<String> = metadata
+        .enabled_features(&target.id)
+        .unwrap_or_default()
+        .iter()
+        .cloned()
+        .collect();
+    let members_of = |id: &str| -> Vec<String> {
+        reach
+            .iter()
+            .filter(|(_, reached)| reached.contains(id))
+            .filter_map(|(member, _)| metadata.package(member).map(|p| p.name.clone()))
+            .collect()
+    };
+
+    // Requests from the packages depending on the crate
+    let mut requests: Vec<(&PackageInfo, String, Activation)> = Vec::new();
+    for dependent in &metadata.packages {
+        let depends = metadata
+            .resolved_dependencies(&dependent.id)
+            .unwrap_or_default()
+            .iter()
+            .any(|dep| dep.package_id == target.id);
+        if depends {
+            for (feature, how) in requested(metadata, dependent, &target.name) {
+                requests.push((dependent, feature, how));
+            }
+        }
+    }
+
+    let mut reasons: BTreeMap<String, Vec<Reason>> = BTreeMap::new();
+    for (dependent, feature, how) in &requests {
+        reasons.entry(feature.clone()).or_default().push(Reason {
+            by: dependent.name.clone(),
+            version: dependent.version.clone(),
+            how: how.clone(),
+            members: members_of(&dependent.id),
+        });
+    }
+    for (feature, implied) in implications(target, &enabled) {
+        reasons.entry(implied).or_default().push(Reason {
+            by: target.name.clone(),
+            version: target.version.clone(),
+            how: Activation::Implied(feature),
+            members: Vec::new(),
+        });
+    }
+    reasons.retain(|feature, _| enabled.contains(feature));
+    for list in reasons.values_mut() {
+        list.sort_by(|a, b| (&a.how, &a.by).cmp(&(&b.how, &b.by)));
+        list.dedup();
+    }
+
+    let mut members = BTreeMap::new();
+    for (member, reached) in reach {
+        if !reached.contains(target.id.as_str()) {
+            continue;
+        }
+        let Some(package) = metadata.package(member) else {
+            continue;
+        };
+        let asked = requests
+            .iter()
+            .filter(|(dependent, _, _)| reached.contains(dependent.id.as_str()))
+            .map(|(_, feature, _)| feature.clone());
+        let own = closure(target, asked.collect(), &enabled);
+        let unified = enabled.difference(&own).cloned().collect();
+        members.insert(
+            package.name.clone(),
+            MemberFeatures {
+                own: own.into_iter().collect(),
+                unified,
+            },
+        );
+    }
+
+    let suggestions = suggestions(metadata, target, &requests, &members);
+    Unification {
+        package: target.name.clone(),
+        version: target.version.clone(),
+        features: enabled.into_iter().collect(),
+        reasons,
+        members,
+        suggestions,
+    }
+}
+
+/// The packages each workspace member's dependency graph contains, itself
+/// included
+fn member_reach(metadata: &ProjectMetadata) -> BTreeMap<&str, HashSet<&str>> {
+    metadata
+        .members
+        .iter()
+        .map(|member| {
+            let mut reached = HashSet::new();
+            let mut queue = VecDeque::from([member.as_str()]);
+            while let Some(id) = queue.pop_front() {
+                if !reached.insert(id) {
+                    continue;
+                }
+                for dep in metadata.resolved_dependencies(id).unwrap_or_default() {
+                    queue.push_back(dep.package_id.as_str());
+                }
+            }
+            (member.as_str(), reached)
+        })
+        .collect()
+}
+
+/// The features `dependent` asks for on the package `name`, through its
+/// active declarations and its enabled features forwarding to them
+fn requested(
+    metadata: &ProjectMetadata,
+    dependent: &PackageInfo,
+    name: &str,
+) -> Vec<(String, Activation)> {
+    let enabled = metadata.enabled_features(&dependent.id).unwrap_or_default();
+    let mut requested = Vec::new();
+    for declared in active_declarations(dependent, name, enabled) {
+        if declared.default_features {
+            requested.push(("default".to_string(), Activation::Default));
+        }
+        for feature in &declared.features {
+            requested.push((feature.clone(), Activation::Declared));
+        }
+        for (own, entries) in &dependent.features {
+            if !enabled.contains(own) {
+                continue;
+            }
+            for feature in entries.iter().filter_map(|e| forwarded(e, declared.key())) {
+                requested.push((feature.to_string(), Activation::Forwarded(own.clone())));
+            }
+        }
+    }
+    requested
+}
+
+/// Declarations of `name` in `dependent` that are in effect: non-optional
+/// ones, and optional ones switched on by an enabled feature
+fn active_declarations<'a>(
+    dependent: &'a PackageInfo,
+    name: &'a str,
+    enabled: &'a [String],
+) -> impl Iterator<Item = &'a DeclaredDependency> + 'a {
+    dependent
+        .dependencies
+        .iter()
+        .filter(move |d| d.package == name)
+        .filter(move |d| {
+            !d.optional
+                || dependent.features.iter().any(|(feature, entries)| {
+                    enabled.contains(feature) && entries.iter().any(|e| switches_on(e, d.key()))
+                })
+        })
+}
+
+/// Whether the feature table entry `entry` turns on the optional dependency
+/// `key`
+fn switches_on(entry: &str, key: &str) -> bool {
+    entry == key
+        || entry.strip_prefix("dep:") == Some(key)
+        || entry
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The feature `entry` forwards to the dependency `key`, for `key/feature`
+/// and `key?/feature`
+fn forwarded<'a>(entry: &'a str, key: &str) -> Option<&'a str> {
+    let rest = entry.strip_prefix(key)?;
+    rest.strip_prefix("?/").or_else(|| rest.strip_prefix('/'))
+}
+
+/// Pairs of an enabled feature of `target` and another of its features it
+/// turns on
+fn implications(target: &PackageInfo, enabled: &BTreeSet<String>) -> Vec<(String, String)> {
+    let mut implied = Vec::new();
+    for (feature, entries) in &target.features {
+        if !enabled.contains(feature) {
+            continue;
+        }
+        for entry in entries {
+            if !entry.contains('/') && !entry.starts_with("dep:") {
+                implied.push((feature.clone(), entry.clone()));
+            }
+        }
+    }
+    implied
+}
+
+/// `asked` plus every feature of `target` it turns on, within `enabled`
+fn closure(
+    target: &PackageInfo,
+    asked: BTreeSet<String>,
+    enabled: &BTreeSet<String>,
+) -> BTreeSet<String> {
+    let mut own = BTreeSet::new();
+    let mut queue: Vec<String> = asked.into_iter().collect();
+    while let Some(feature) = queue.pop() {
+        if !enabled.contains(&feature) || !own.insert(feature.clone()) {
+            continue;
+        }
+        for entry in target.features.get(&feature).into_iter().flatten() {
+            if !entry.contains('/') && !entry.starts_with("dep:") {
+                queue.push(entry.clone());
+            }
+        }
+    }
+    own
+}
+
+/// For each member whose own declaration of `target` turns on features other
+/// members get without asking, a manifest diff gating them behind features
+/// of the member
+fn suggestions(
+    metadata: &ProjectMetadata,
+    target: &PackageInfo,
+    requests: &[(&PackageInfo, String, Activation)],
+    members: &BTreeMap<String, MemberFeatures>,
+) -> Vec<Suggestion> {
+    let mut suggestions = Vec::new();
+    for member in metadata.members() {
+        let pushed: BTreeSet<&str> = members
+            .iter()
+            .filter(|(name, _)| **name != member.name)
+            .flat_map(|(_, features)| features.unified.iter().map(String::as_str))
+            .collect();
+        let features: Vec<String> = requests
+            .iter()
+            .filter(|(dependent, feature, how)| {
+                dependent.id == member.id
+                    && matches!(how, Activation::Declared | Activation::Default)
+                    && pushed.contains(feature.as_str())
+            })
+            .map(|(_, feature, _)| feature.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if features.is_empty() {
+            continue;
+        }
+        // Dev-dependencies can't be gated behind features
+        let Some(declared) = member
+            .dependencies
+            .iter()
+            .find(|d| d.package == target.name && d.kind != DepKind::Dev)
+        else {
+            continue;
+        };
+        let original = match fs::read_to_string(&member.manifest_path) {
+            Ok(original) => original,
+            Err(e) => {
+                log::debug!("Not suggesting changes to {}: {}", member.name, e);
+                continue;
+            }
+        };
+        let Some((edited, gates)) = gate(&original, declared, &features) else {
+            continue;
+        };
+        let path = member
+            .manifest_path
+            .strip_prefix(&metadata.workspace_root)
+            .unwrap_or(&member.manifest_path);
+        let gated: Vec<String> = gates.iter().map(|(feature, _)| feature.clone()).collect();
+        let names: Vec<String> = gates
+            .iter()
+            .map(|(_, gate)| format!("`{}`", gate))
+            .collect();
+        suggestions.push(Suggestion {
+            member: member.name.clone(),
+            manifest: member.manifest_path.clone(),
+            summary: format!(
+                "{} turns on {} of {} for the whole workspace; gate {} behind {} of {}, enabled only where needed",
+                member.name,
+                gated.join(", "),
+                target.name,
+                if gated.len() == 1 { "it" } else { "them" },
+                names.join(", "),
+                member.name
+            ),
+            features: gated,
+            diff: unified_diff(&original, &edited, path),
+        });
+    }
+    suggestions
+}
+
+/// `manifest` with `features` taken off the declaration of `declared` and
+/// each enabled by a new feature of the package instead, with the features
+/// and the names of their gates; `None` when none of them can be moved, as
+/// for features inherited from `[workspace.dependencies]`
+pub fn gate(
+    manifest: &str,
+    declared: &DeclaredDependency,
+    features: &[String],
+) -> Option<(String, Vec<(String, String)>)> {
+    let mut doc: DocumentMut = manifest.parse().ok()?;
+    let key = declared.key().to_string();
+    let section = match declared.kind {
+        DepKind::Normal => "dependencies",
+        DepKind::Build => "build-dependencies",
+        DepKind::Dev => "dev-dependencies",
+    };
+    let table = match &declared.target {
+        Some(target) => doc
+            .get_mut("target")?
+            .get_mut(target.as_str())?
+            .get_mut(section)?,
+        None => doc.get_mut(section)?,
+    };
+    let entry = table.get_mut(&key)?;
+    // `dep = "1"` needs to become a table to carry `default-features`
+    if let Some(version) = entry.as_str() {
+        let mut inline = InlineTable::new();
+        inline.insert("version", version.into());
+        *entry = Item::Value(inline.into());
+    }
+    let dependency = entry.as_table_like_mut()?;
+
+    let mut moved = Vec::new();
+    for feature in features {
+        if feature == "default" {
+            if dependency.contains_key("workspace") {
+                continue;
+            }
+            dependency.insert("default-features", value(false));
+            moved.push(feature.clone());
+        } else if let Some(list) = dependency
+            .get_mut("features")
+            .and_then(|item| item.as_array_mut())
+        {
+            let before = list.len();
+            list.retain(|v| v.as_str() != Some(feature.as_str()));
+            if list.len() < before {
+                moved.push(feature.clone());
+            }
+        }
+    }
+    if dependency
+        .get("features")
+        .and_then(|item| item.as_array())
+        .is_some_and(Array::is_empty)
+    {
+        dependency.remove("features");
+    }
+    // Removing the last key leaves its spacing behind in an inline table
+    if let Some(inline) = entry.as_inline_table_mut() {
+        inline.fmt();
+    }
+    if moved.is_empty() {
+        return None;
+    }
+
+    if doc.get("features").is_none() {
+        doc["features"] = toml_edit::table();
+    }
+    let table = doc["features"].as_table_mut()?;
+    // `?/` leaves an optional dependency switched off unless something else
+    // turns it on
+    let separator = if declared.optional { "?/" } else { "/" };
+    let mut gates = Vec::new();
+    for feature in moved {
+        let name = format!("{}-{}", key, feature);
+        if !table.contains_key(&name) {
+            let mut enables = Array::new();
+            enables.push(format!("{}{}{}", key, separator, feature));
+            table.insert(&name, value(enables));
+        }
+        gates.push((feature, name));
+    }
+    Some((doc.to_string(), gates))
+}
+
+fn unified_diff(original: &str, edited: &str, path: &Path) -> String {
+    TextDiff::from_lines(original, edited)
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &format!("a/{}", path.display()),
+            &format!("b/{}", path.display()),
+        )
+        .to_string()
+}
+
+impl Unification {
+    pub fn render(&self) -> String {
+        let mut out = format!("{} v{}\n", self.package, self.version);
+        if self.features.is_empty() {
+            out.push_str("no features enabled\n");
+            return out;
+        }
+        out.push_str(&format!("features: {}\n", self.features.join(", ")));
+        for feature in &self.features {
+            out.push_str(&format!("\n{}\n", feature));
+            for reason in self.reasons.get(feature).into_iter().flatten() {
+                out.push_str(&format!("  ← {}\n", reason.describe()));
+            }
+        }
+
+        let surprised: Vec<_> = self
+            .members
+            .iter()
+            .filter(|(_, features)| !features.unified.is_empty())
+            .collect();
+        if !surprised.is_empty() {
+            out.push_str("\nunified from other members:\n");
+            for (member, features) in surprised {
+                out.push_str(&format!(
+                    "  {} gets {} without asking\n",
+                    member,
+                    features.unified.join(", ")
+                ));
+            }
+        }
+        for suggestion in &self.suggestions {
+            out.push_str(&format!("\nsuggestion: {}\n", suggestion.summary));
+            out.push_str(&suggestion.diff);
+        }
+        out
+    }
+}
+
+impl Reason {
+    fn describe(&self) -> String {
+        let who = format!("{} v{}", self.by, self.version);
+        let mut line = match &self.how {
+            Activation::Declared => format!("{} asks for it", who),
+            Activation::Default => format!("{} keeps the default features", who),
+            Activation::Forwarded(feature) => {
+                format!("{} forwards it from its feature {}", who, feature)
+            }
+            Activation::Implied(feature) => return format!("implied by feature {}", feature),
+        };
+        if !self.members.is_empty() {
+            line.push_str(&format!(" (members: {})", self.members.join(", ")));
+        }
+        line
+    }
+}
@@ -0,0 +1,426 @@
+//! `kargo fleet`: run a plugin in many projects and aggregate its diagnostics
+//!
+//! The plugin runs once per project with the project as its working directory.
+//! Diagnostics from every run are merged into one report: identical findings
+//! (same code, message and project-relative location) collapse into a single
+//! entry listing the projects they occurred in, and anything below
+//! `--min-severity` is dropped. Each project the report mentions is
+//! attributed to its owners (see [`kargo_plugin_api::owners`]). With
+//! `--jobs`, several projects run at once; WASM plugins then take instances
+//! from their pool (see [`crate::plugins::instance_pool`]) instead of
+//! waiting on a single one.
+//!
+//! `kargo fleet deps` reports dependency usage instead (see [`deps`]), and
+//! `kargo fleet report` renders the recorded state of every project as an
+//! HTML dashboard (see [`report`]).
+
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use futures::stream::{self, StreamExt};
+use jwalk::WalkDir;
+use kargo_plugin_api::owners::{self, Ownership};
+use kargo_plugin_api::{Diagnostic, DiagnosticsSchema, Severity, WriteGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::cache::PluginCache;
+use crate::cli::{check_plugin_args, publish_diagnostics, run_plugin};
+use crate::events::EventBus;
+use crate::plugins::manager::PluginManager;
+
+pub mod deps;
+pub mod report;
+
+pub fn command() -> Command {
+    Command::new("fleet")
+        .about("Run a plugin in every project below the given roots and aggregate its diagnostics")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(deps::command())
+        .subcommand(report::command())
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .value_name("DIR")
+                .help("Directory to search for projects (repeatable; defaults to the current directory)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("min-severity")
+                .long("min-severity")
+                .value_name("LEVEL")
+                .help("Drop diagnostics below this severity")
+                .value_parser(["note", "warning", "error"])
+                .default_value("warning"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .short('j')
+                .value_name("N")
+                .help("Projects to run the plugin in at once")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .help("Write the aggregated report as JSON")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("plugin")
+                .value_name("PLUGIN")
+                .help("Plugin to run in each project")
+                .required(true),
+        )
+        .arg(
+            Arg::new("args")
+                .value_name("ARGS")
+                .help("Arguments passed to the plugin")
+                .num_args(0..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true),
+        )
+}
+
+/// A diagnostic seen in one or more projects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregatedDiagnostic {
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+    /// Description of the code from the plugin's schema
+    pub description: Option<String>,
+    pub projects: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedRun {
+    pub project: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetReport {
+    pub plugin: String,
+    pub projects: usize,
+    pub failed: Vec<FailedRun>,
+    pub diagnostics: Vec<AggregatedDiagnostic>,
+    /// Owners of the projects that failed or reported diagnostics
+    pub owners: BTreeMap<PathBuf, Ownership>,
+}
+
+impl FleetReport {
+    /// Look up the owners of every project the report mentions
+    pub fn attribute_owners(&mut self) {
+        let mentioned: BTreeSet<&PathBuf> = self
+            .failed
+            .iter()
+            .map(|failure| &failure.project)
+            .chain(self.diagnostics.iter().flat_map(|entry| &entry.projects))
+            .collect();
+        self.owners = mentioned
+            .into_iter()
+            .filter_map(|project| Some((project.clone(), owners::project_owners(project)?)))
+            .collect();
+    }
+
+    /// Owners of `projects`, each listed once, in order of appearance
+    pub fn owners_of<'a>(&self, projects: impl IntoIterator<Item = &'a PathBuf>) -> Vec<&str> {
+        let mut owners: Vec<&str> = Vec::new();
+        for owner in projects
+            .into_iter()
+            .filter_map(|project| self.owners.get(project))
+            .flat_map(|ownership| &ownership.owners)
+        {
+            if !owners.contains(&owner.as_str()) {
+                owners.push(owner);
+            }
+        }
+        owners
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DiagnosticKey {
+    code: String,
+    severity: Severity,
+    message: String,
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+/// Merges diagnostics from many runs of the same plugin
+pub struct DiagnosticAggregator {
+    schema: Option<DiagnosticsSchema>,
+    min_severity: Severity,
+    entries: Vec<AggregatedDiagnostic>,
+    index: HashMap<DiagnosticKey, usize>,
+}
+
+impl DiagnosticAggregator {
+    pub fn new(schema: Option<DiagnosticsSchema>, min_severity: Severity) -> Self {
+        Self {
+            schema,
+            min_severity,
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Record a diagnostic reported while running in `project`
+    pub fn add(&mut self, project: &Path, mut diagnostic: Diagnostic) {
+        if diagnostic.severity < self.min_severity {
+            return;
+        }
+        // Paths inside the project are compared relative to it, so the same
+        // finding in two checkouts counts once
+        if let Some(file) = &diagnostic.file
+            && let Ok(relative) = file.strip_prefix(project)
+        {
+            diagnostic.file = Some(relative.to_path_buf());
+        }
+
+        let key = DiagnosticKey {
+            code: diagnostic.code.clone(),
+            severity: diagnostic.severity,
+            message: diagnostic.message.clone(),
+            file: diagnostic.file.clone(),
+            line: diagnostic.line,
+            column: diagnostic.column,
+        };
+        match self.index.get(&key) {
+            Some(&i) => {
+                let projects = &mut self.entries[i].projects;
+                if !projects.iter().any(|p| p == project) {
+                    projects.push(project.to_path_buf());
+                }
+            }
+            None => {
+                let description = self
+                    .schema
+                    .as_ref()
+                    .and_then(|s| s.kind(&diagnostic.code))
+                    .map(|k| k.description.clone());
+                self.index.insert(key, self.entries.len());
+                self.entries.push(AggregatedDiagnostic {
+                    diagnostic,
+                    description,
+                    projects: vec![project.to_path_buf()],
+                });
+            }
+        }
+    }
+
+    /// Most severe first, then by code
+    pub fn finish(mut self) -> Vec<AggregatedDiagnostic> {
+        self.entries.sort_by(|a, b| {
+            b.diagnostic
+                .severity
+                .cmp(&a.diagnostic.severity)
+                .then_with(|| a.diagnostic.code.cmp(&b.diagnostic.code))
+        });
+        self.entries
+    }
+}
+
+pub async fn run(
+    pm: &PluginManager,
+    matches: &ArgMatches,
+    events: &EventBus,
+    cache: Option<&PluginCache>,
+) -> Result<()> {
+    match matches.subcommand() {
+        Some(("deps", sub)) => return deps::run(sub),
+        Some(("report", sub)) => return report::run(sub),
+        _ => {}
+    }
+
+    let name = matches
+        .get_one::<String>("plugin")
+        .context("Missing plugin name")?;
+    let plugin = pm
+        .get(name)
+        .with_context(|| format!("Unknown plugin: {}", name))?;
+    let min_severity: Severity = matches
+        .get_one::<String>("min-severity")
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(anyhow::Error::msg)?
+        .unwrap_or(Severity::Warning);
+    let roots: Vec<PathBuf> = match matches.get_many::<PathBuf>("root") {
+        Some(roots) => roots.cloned().collect(),
+        None => vec![std::env::current_dir()?],
+    };
+
+    let mut args = vec![name.clone()];
+    if let Some(extra) = matches.get_many::<String>("args") {
+        args.extend(extra.cloned());
+    }
+
+    // Catch usage mistakes once rather than in every project
+    if !check_plugin_args(plugin.as_ref(), &args)? {
+        return Ok(());
+    }
+
+    // Before running the plugin everywhere for a report that can't be kept
+    if let Some(path) = matches.get_one::<PathBuf>("report") {
+        WriteGuard::current().check(path)?;
+    }
+
+    let projects = discover_projects(&roots);
+    log::info!("Running {} in {} projects", name, projects.len());
+
+    let jobs = *matches.get_one::<u64>("jobs").expect("has default") as usize;
+    let mut runs = stream::iter(&projects)
+        .map(|project| {
+            let run = run_plugin(
+                name,
+                plugin.as_ref(),
+                args.clone(),
+                project.clone(),
+                events,
+                cache,
+            );
+            async move { (project, run.await) }
+        })
+        .buffered(jobs);
+
+    let mut aggregator = DiagnosticAggregator::new(plugin.diagnostics_schema(), min_severity);
+    let mut failed = Vec::new();
+    while let Some((project, (result, diagnostics))) = runs.next().await {
+        if let Err(e) = result {
+            failed.push(FailedRun {
+                project: project.clone(),
+                error: format!("{:#}", e),
+            });
+        }
+        for diagnostic in diagnostics {
+            aggregator.add(project, diagnostic);
+        }
+    }
+
+    let mut report = FleetReport {
+        plugin: name.clone(),
+        projects: projects.len(),
+        failed,
+        diagnostics: aggregator.finish(),
+        owners: BTreeMap::new(),
+    };
+    report.attribute_owners();
+    print_report(&report);
+
+    // Publish each finding once, located in the first project it was seen in
+    let located = report.diagnostics.iter().map(|entry| {
+        let mut diagnostic = entry.diagnostic.clone();
+        if let (Some(file), Some(project)) = (&diagnostic.file, entry.projects.first()) {
+            diagnostic.file = Some(project.join(file));
+        }
+        diagnostic
+    });
+    publish_diagnostics(name, located.collect(), events).await;
+
+    if let Some(path) = matches.get_one::<PathBuf>("report") {
+        WriteGuard::current().write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+
+    if !report.failed.is_empty() {
+        anyhow::bail!(
+            "{} failed in {} of {} projects",
+            name,
+            report.failed.len(),
+            report.projects
+        );
+    }
+    Ok(())
+}
+
+fn print_report(report: &FleetReport) {
+    for entry in &report.diagnostics {
+        let d = &entry.diagnostic;
+        let severity = match d.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        println!(
+            "{}[{}]: {} ({} project{})",
+            severity,
+            d.code,
+            d.message,
+            entry.projects.len(),
+            if entry.projects.len() == 1 { "" } else { "s" }
+        );
+        if let Some(file) = &d.file {
+            match d.line {
+                Some(line) => println!("  --> {}:{}", file.display(), line),
+                None => println!("  --> {}", file.display()),
+            }
+        }
+        let owners = report.owners_of(&entry.projects);
+        if !owners.is_empty() {
+            println!("  owners: {}", owners.join(", "));
+        }
+    }
+    for failure in &report.failed {
+        match report.owners_of([&failure.project]).as_slice() {
+            [] => eprintln!("✗ {}: {}", failure.project.display(), failure.error),
+            owners => eprintln!(
+                "✗ {} ({}): {}",
+                failure.project.display(),
+                owners.join(", "),
+                failure.error
+            ),
+        }
+    }
+    println!(
+        "{}: {} diagnostic(s) across {} project(s), {} failed",
+        report.plugin,
+        report.diagnostics.len(),
+        report.projects,
+        report.failed.len()
+    );
+}
+
+/// Directories holding a Cargo project, skipping workspace members (the
+/// workspace root covers them) and anything under `target/`
+pub fn discover_projects(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut manifests: Vec<PathBuf> = roots
+        .iter()
+        .flat_map(|root| {
+            WalkDir::new(root)
+                .follow_links(true)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_name.to_string_lossy() == "Cargo.toml")
+                .map(|e| e.path())
+                .filter(|p| !p.components().any(|c| c.as_os_str() == "target"))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    manifests.sort();
+
+    let mut workspaces: Vec<PathBuf> = Vec::new();
+    let mut projects = Vec::new();
+    for manifest in manifests {
+        let Some(dir) = manifest.parent().map(Path::to_path_buf) else {
+            continue;
+        };
+        // Sorted order visits a workspace root before its members
+        if workspaces.iter().any(|ws| dir.starts_with(ws)) {
+            continue;
+        }
+        let is_workspace = std::fs::read_to_string(&manifest)
+            .map(|content| content.contains("[workspace]"))
+            .unwrap_or(false);
+        if is_workspace {
+            workspaces.push(dir.clone());
+        }
+        projects.push(dir);
+    }
+    projects
+}
@@ -0,0 +1,322 @@
+//! `kargo fleet deps`: which projects use a crate, and at which versions
+//!
+//! Every project below the roots is checked for dependencies on the crate:
+//! the requirement written in each manifest (workspace members included) and
+//! the versions resolved in the project's `Cargo.lock`. Projects are then
+//! counted per semver-compatible series, `0.2.x` or `1.x`, preferring the
+//! resolved versions over the requirements when a lockfile exists.
+
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use jwalk::WalkDir;
+use kargo_plugin_api::WriteGuard;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item};
+
+use super::discover_projects;
+
+const DEPENDENCY_TABLES: [(&str, &str); 3] = [
+    ("dependencies", "normal"),
+    ("dev-dependencies", "dev"),
+    ("build-dependencies", "build"),
+];
+
+pub fn command() -> Command {
+    Command::new("deps")
+        .about("Report the projects using a crate and the versions they are on")
+        .arg(
+            Arg::new("crate")
+                .long("crate")
+                .value_name("CRATE")
+                .help("Crate to look for")
+                .required(true),
+        )
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .value_name("DIR")
+                .help("Directory to search for projects (repeatable; defaults to the current directory)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .help("Write the report as JSON")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+}
+
+/// One dependency declaration on the crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Requirement {
+    pub manifest: PathBuf,
+    /// `normal`, `dev`, `build` or `workspace` (a `[workspace.dependencies]`
+    /// entry)
+    pub kind: String,
+    /// Version requirement, `path`/`git` for sources without one, or
+    /// `workspace` for members inheriting it
+    pub requirement: String,
+}
+
+/// A project depending on the crate
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectUsage {
+    pub project: PathBuf,
+    pub requirements: Vec<Requirement>,
+    /// Versions in the project's `Cargo.lock`; empty without one
+    pub resolved: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Projects scanned
+    pub projects: usize,
+    pub users: Vec<ProjectUsage>,
+    /// Number of projects on each compatible series, e.g. `1.x` or `0.2.x`
+    pub series: BTreeMap<String, usize>,
+}
+
+/// Find the projects below `roots` that depend on `krate`
+pub fn scan(roots: &[PathBuf], krate: &str) -> UsageReport {
+    let projects = discover_projects(roots);
+    let users: Vec<ProjectUsage> = projects
+        .iter()
+        .filter_map(|project| project_usage(project, krate))
+        .collect();
+
+    let mut series = BTreeMap::new();
+    for usage in &users {
+        let versions: Vec<&str> = if usage.resolved.is_empty() {
+            usage
+                .requirements
+                .iter()
+                .map(|r| r.requirement.as_str())
+                .collect()
+        } else {
+            usage.resolved.iter().map(String::as_str).collect()
+        };
+        let mut seen: Vec<String> = versions.into_iter().filter_map(compatible_series).collect();
+        seen.sort();
+        seen.dedup();
+        if seen.is_empty() {
+            seen.push("unknown".to_string());
+        }
+        for s in seen {
+            *series.entry(s).or_insert(0) += 1;
+        }
+    }
+
+    UsageReport {
+        krate: krate.to_string(),
+        projects: projects.len(),
+        users,
+        series,
+    }
+}
+
+fn project_usage(project: &Path, krate: &str) -> Option<ProjectUsage> {
+    let mut requirements = Vec::new();
+    for manifest in manifests(project) {
+        let doc = match std::fs::read_to_string(&manifest)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(content.parse::<DocumentMut>()?))
+        {
+            Ok(doc) => doc,
+            Err(e) => {
+                log::warn!("Skipping {}: {}", manifest.display(), e);
+                continue;
+            }
+        };
+        requirements.extend(manifest_requirements(&doc, krate).into_iter().map(
+            |(kind, requirement)| Requirement {
+                manifest: manifest.clone(),
+                kind,
+                requirement,
+            },
+        ));
+    }
+    if requirements.is_empty() {
+        return None;
+    }
+
+    let resolved = std::fs::read_to_string(project.join("Cargo.lock"))
+        .map(|lock| locked_versions(&lock, krate))
+        .unwrap_or_default();
+    Some(ProjectUsage {
+        project: project.to_path_buf(),
+        requirements,
+        resolved,
+    })
+}
+
+/// The project's own manifest, and those of every crate below it when it is
+/// a workspace (`discover_projects` skips workspace members)
+fn manifests(project: &Path) -> Vec<PathBuf> {
+    let root = project.join("Cargo.toml");
+    let is_workspace = std::fs::read_to_string(&root)
+        .map(|content| content.contains("[workspace]"))
+        .unwrap_or(false);
+    if !is_workspace {
+        return vec![root];
+    }
+
+    let mut manifests: Vec<PathBuf> = WalkDir::new(project)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name.to_string_lossy() == "Cargo.toml")
+        .map(|e| e.path())
+        .filter(|p| {
+            !p.strip_prefix(project)
+                .unwrap_or(p)
+                .components()
+                .any(|c| c.as_os_str() == "target")
+        })
+        .collect();
+    manifests.sort();
+    manifests
+}
+
+/// `(kind, requirement)` of each declaration of `krate` in a manifest,
+/// including target-specific tables and renamed dependencies
+pub fn manifest_requirements(doc: &DocumentMut, krate: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut tables = vec![doc.as_item()];
+    if let Some(targets) = doc.get("target").and_then(Item::as_table_like) {
+        tables.extend(targets.iter().map(|(_, target)| target));
+    }
+    for table in tables {
+        for (name, kind) in DEPENDENCY_TABLES {
+            if let Some(deps) = table.get(name).and_then(Item::as_table_like) {
+                found.extend(find_dependency(deps, krate).map(|req| (kind.to_string(), req)));
+            }
+        }
+    }
+    if let Some(deps) = doc
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(Item::as_table_like)
+    {
+        found.extend(find_dependency(deps, krate).map(|req| ("workspace".to_string(), req)));
+    }
+    found
+}
+
+fn find_dependency(deps: &dyn toml_edit::TableLike, krate: &str) -> Option<String> {
+    deps.iter().find_map(|(key, spec)| {
+        if let Some(version) = spec.as_str() {
+            return (key == krate).then(|| version.to_string());
+        }
+        let spec = spec.as_table_like()?;
+        let package = spec.get("package").and_then(Item::as_str).unwrap_or(key);
+        if package != krate {
+            return None;
+        }
+        let requirement = if let Some(version) = spec.get("version").and_then(Item::as_str) {
+            version
+        } else if spec.get("workspace").and_then(Item::as_bool) == Some(true) {
+            "workspace"
+        } else if spec.contains_key("git") {
+            "git"
+        } else if spec.contains_key("path") {
+            "path"
+        } else {
+            "*"
+        };
+        Some(requirement.to_string())
+    })
+}
+
+/// Versions of `krate` in the contents of a `Cargo.lock`
+pub fn locked_versions(lockfile: &str, krate: &str) -> Vec<String> {
+    let Ok(doc) = lockfile.parse::<DocumentMut>() else {
+        return Vec::new();
+    };
+    let Some(packages) = doc.get("package").and_then(Item::as_array_of_tables) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<String> = packages
+        .iter()
+        .filter(|p| p.get("name").and_then(Item::as_str) == Some(krate))
+        .filter_map(|p| p.get("version").and_then(Item::as_str))
+        .map(str::to_string)
+        .collect();
+    versions.sort();
+    versions.dedup();
+    versions
+}
+
+/// Semver-compatible series of a version or requirement: `1.x` for `1.38.0`
+/// or `^1.2`, `0.2.x` for `0.2.17` or `~0.2`
+pub fn compatible_series(version: &str) -> Option<String> {
+    let version = version
+        .split(',')
+        .next()?
+        .trim()
+        .trim_start_matches(['^', '~', '=', '>', '<', ' ']);
+    let mut parts = version.split('.');
+    let major: u64 = parts.next()?.parse().ok()?;
+    if major > 0 {
+        return Some(format!("{}.x", major));
+    }
+    match parts.next().map(str::parse::<u64>) {
+        Some(Ok(minor)) => Some(format!("0.{}.x", minor)),
+        _ => Some("0.x".to_string()),
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let krate = matches
+        .get_one::<String>("crate")
+        .context("Missing crate name")?;
+    let roots: Vec<PathBuf> = match matches.get_many::<PathBuf>("root") {
+        Some(roots) => roots.cloned().collect(),
+        None => vec![std::env::current_dir()?],
+    };
+
+    let report = scan(&roots, krate);
+    print_report(&report);
+
+    if let Some(path) = matches.get_one::<PathBuf>("report") {
+        WriteGuard::current().write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(())
+}
+
+fn print_report(report: &UsageReport) {
+    for usage in &report.users {
+        let resolved = if usage.resolved.is_empty() {
+            "no lockfile".to_string()
+        } else {
+            usage.resolved.join(", ")
+        };
+        println!("{} (resolved: {})", usage.project.display(), resolved);
+        for req in &usage.requirements {
+            let manifest = req
+                .manifest
+                .strip_prefix(&usage.project)
+                .unwrap_or(&req.manifest);
+            println!(
+                "  {} {} ({})",
+                manifest.display(),
+                req.requirement,
+                req.kind
+            );
+        }
+    }
+    println!(
+        "{}: used by {} of {} project(s)",
+        report.krate,
+        report.users.len(),
+        report.projects
+    );
+    for (series, count) in &report.series {
+        println!("  {:<10} {} project(s)", series, count);
+    }
+}
@@ -0,0 +1,691 @@
+//! `kargo fleet report`: the state of every project as a static HTML dashboard
+//!
+//! The dashboard pulls together what other commands already recorded:
+//!
+//! - the inventory of the kargo-walk index (`--index`)
+//! - outdated and vulnerable dependencies from `--status-file` reports
+//!   (`--status`; by default those of the scheduled jobs, see
+//!   [`crate::schedule`])
+//! - findings and documentation coverage from `kargo fleet --report` files
+//!   (`--fleet-report`); coverage comes from the `mddoc::coverage` notes of
+//!   `kargo fleet --min-severity note --report docs.json mddoc coverage .`
+//!
+//! `--html <DIR>` receives `index.html` with the sortable overview tables and
+//! one page per project under `projects/`. Pages carry their own styles and
+//! script and link each other relatively, so the directory can be opened from
+//! disk or published by any static host.
+
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::owners::Ownership;
+use kargo_plugin_api::{Diagnostic, Severity, WriteGuard};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::FleetReport;
+use crate::schedule::Scheduler;
+use crate::status::{OutdatedDependency, VulnerableDependency};
+
+/// Code of the diagnostic mddoc reports a crate's coverage with
+pub const COVERAGE_CODE: &str = "mddoc::coverage";
+
+pub fn command() -> Command {
+    input_args(
+        Command::new("report")
+            .about(
+                "Render the inventory, outdated dependencies, advisories and doc coverage as HTML",
+            )
+            .arg(
+                Arg::new("html")
+                    .long("html")
+                    .value_name("DIR")
+                    .help("Directory to write the dashboard to")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true),
+            ),
+    )
+}
+
+/// The `--index`, `--status` and `--fleet-report` inputs, read back by
+/// [`Dashboard::from_matches`]
+pub fn input_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("index")
+                .long("index")
+                .value_name("FILE")
+                .help("Project index written by kargo-walk")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("index.yaml"),
+        )
+        .arg(
+            Arg::new("status")
+                .long("status")
+                .value_name("FILE")
+                .help("Status file with outdated and vulnerable dependencies (repeatable; defaults to the scheduled jobs' reports)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("fleet-report")
+                .long("fleet-report")
+                .value_name("FILE")
+                .help("Report written by `kargo fleet --report` (repeatable)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(ArgAction::Append),
+        )
+}
+
+/// A project of the kargo-walk index
+#[derive(Debug, Clone, Deserialize)]
+pub struct InventoryEntry {
+    pub path: PathBuf,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub project_type: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub dependents: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub owners: Option<Ownership>,
+}
+
+/// The parts of a status file the dashboard shows
+#[derive(Debug, Default, Deserialize)]
+struct StatusFile {
+    #[serde(default)]
+    outdated: Vec<OutdatedDependency>,
+    #[serde(default)]
+    vulnerable: Vec<VulnerableDependency>,
+}
+
+/// Everything known about one project
+#[derive(Debug, Clone)]
+pub struct ProjectPage {
+    pub entry: InventoryEntry,
+    /// File name below `projects/`
+    pub file: String,
+    pub outdated: Vec<OutdatedDependency>,
+    pub vulnerable: Vec<VulnerableDependency>,
+    /// Plugin and finding, from the fleet reports
+    pub findings: Vec<(String, Diagnostic)>,
+    /// Percentage of the public API with docs
+    pub coverage: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    pub projects: Vec<ProjectPage>,
+    /// Outdated dependencies of manifests outside every indexed project
+    pub unattributed_outdated: Vec<OutdatedDependency>,
+    pub unattributed_vulnerable: Vec<VulnerableDependency>,
+}
+
+impl Dashboard {
+    pub fn new(inventory: Vec<InventoryEntry>) -> Self {
+        let mut taken: HashMap<String, usize> = HashMap::new();
+        let projects = inventory
+            .into_iter()
+            .map(|entry| {
+                let slug = slug(&entry.name);
+                let seen = taken.entry(slug.clone()).or_insert(0);
+                *seen += 1;
+                let file = match *seen {
+                    1 => format!("{}.html", slug),
+                    n => format!("{}-{}.html", slug, n),
+                };
+                ProjectPage {
+                    entry,
+                    file,
+                    outdated: Vec::new(),
+                    vulnerable: Vec::new(),
+                    findings: Vec::new(),
+                    coverage: None,
+                }
+            })
+            .collect();
+        Self {
+            projects,
+            ..Self::default()
+        }
+    }
+
+    /// Read the index and attach the status files and fleet reports to it
+    pub fn load(index: &Path, status: &[PathBuf], fleet_reports: &[PathBuf]) -> Result<Self> {
+        let content = std::fs::read_to_string(index)
+            .with_context(|| format!("Failed to read project index {}", index.display()))?;
+        let inventory: Vec<InventoryEntry> = serde_yaml::from_str(&content)
+            .with_context(|| format!("Invalid project index {}", index.display()))?;
+        let mut dashboard = Self::new(inventory);
+
+        for path in status {
+            let status: StatusFile = read_json(path)?;
+            for dependency in status.outdated {
+                dashboard.add_outdated(dependency);
+            }
+            for dependency in status.vulnerable {
+                dashboard.add_vulnerable(dependency);
+            }
+        }
+        for path in fleet_reports {
+            dashboard.add_fleet_report(read_json(path)?);
+        }
+        Ok(dashboard)
+    }
+
+    /// Load the inputs declared by [`input_args`]
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let index = matches.get_one::<PathBuf>("index").expect("has default");
+        let status: Vec<PathBuf> = match matches.get_many::<PathBuf>("status") {
+            Some(files) => files.cloned().collect(),
+            None => scheduled_status_files(&Scheduler::default_state_dir()),
+        };
+        let fleet_reports: Vec<PathBuf> = matches
+            .get_many::<PathBuf>("fleet-report")
+            .map(|files| files.cloned().collect())
+            .unwrap_or_default();
+        Self::load(index, &status, &fleet_reports)
+    }
+
+    /// The indexed project containing `path`, the innermost when they nest
+    fn project_of(&mut self, path: &Path) -> Option<&mut ProjectPage> {
+        self.projects
+            .iter_mut()
+            .filter(|project| path.starts_with(&project.entry.path))
+            .max_by_key(|project| project.entry.path.components().count())
+    }
+
+    pub fn add_outdated(&mut self, dependency: OutdatedDependency) {
+        match self.project_of(&dependency.path) {
+            Some(project) => project.outdated.push(dependency),
+            None => self.unattributed_outdated.push(dependency),
+        }
+    }
+
+    pub fn add_vulnerable(&mut self, dependency: VulnerableDependency) {
+        match self.project_of(&dependency.path) {
+            Some(project) => project.vulnerable.push(dependency),
+            None => self.unattributed_vulnerable.push(dependency),
+        }
+    }
+
+    /// Attach every finding to the projects it was seen in; coverage notes
+    /// set the project's coverage instead
+    pub fn add_fleet_report(&mut self, report: FleetReport) {
+        for entry in report.diagnostics {
+            let coverage = (entry.diagnostic.code == COVERAGE_CODE)
+                .then(|| parse_coverage(&entry.diagnostic.message))
+                .flatten();
+            for path in &entry.projects {
+                let Some(project) = self.project_of(path) else {
+                    continue;
+                };
+                match coverage {
+                    Some(coverage) => project.coverage = Some(coverage),
+                    None => project
+                        .findings
+                        .push((report.plugin.clone(), entry.diagnostic.clone())),
+                }
+            }
+        }
+    }
+
+    /// Write `index.html` and the project pages to `dir`
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let guard = WriteGuard::current();
+        let pages = dir.join("projects");
+        guard.create_dir_all(&pages)?;
+        guard.write(dir.join("index.html"), self.render_index())?;
+        for project in &self.projects {
+            guard.write(pages.join(&project.file), render_project(project))?;
+        }
+        Ok(())
+    }
+
+    pub fn render_index(&self) -> String {
+        let mut body = String::new();
+        let outdated: usize = self.projects.iter().map(|p| p.outdated.len()).sum();
+        let vulnerable: usize = self.projects.iter().map(|p| p.vulnerable.len()).sum();
+        let broken = self
+            .projects
+            .iter()
+            .filter(|p| p.entry.status == "Broken")
+            .count();
+        let covered: Vec<f64> = self.projects.iter().filter_map(|p| p.coverage).collect();
+        let average = if covered.is_empty() {
+            "–".to_string()
+        } else {
+            format!("{:.1}%", covered.iter().sum::<f64>() / covered.len() as f64)
+        };
+
+        body.push_str("<h1>Fleet dashboard</h1>\n<div class=\"cards\">\n");
+        for (label, value) in [
+            ("Projects", self.projects.len().to_string()),
+            ("Broken", broken.to_string()),
+            ("Outdated dependencies", outdated.to_string()),
+            ("Vulnerable dependencies", vulnerable.to_string()),
+            ("Average doc coverage", average),
+        ] {
+            let _ = writeln!(
+                body,
+                "<div class=\"card\"><span>{}</span><strong>{}</strong></div>",
+                escape(label),
+                escape(&value)
+            );
+        }
+        body.push_str("</div>\n");
+
+        body.push_str("<h2>Inventory</h2>\n");
+        let rows = self.projects.iter().map(|project| {
+            let entry = &project.entry;
+            vec![
+                Cell::link(&entry.name, format!("projects/{}", project.file)),
+                Cell::text(entry.path.display().to_string()),
+                Cell::text(&entry.version),
+                Cell::text(&entry.project_type),
+                Cell::status(&entry.status),
+                Cell::text(entry.tags.join(", ")),
+                Cell::number(
+                    project.outdated.len() as f64,
+                    project.outdated.len().to_string(),
+                ),
+                Cell::number(
+                    project.vulnerable.len() as f64,
+                    project.vulnerable.len().to_string(),
+                ),
+                coverage_cell(project.coverage),
+            ]
+        });
+        table(
+            &mut body,
+            &[
+                "Project",
+                "Path",
+                "Version",
+                "Type",
+                "Status",
+                "Tags",
+                "Outdated",
+                "Vulnerable",
+                "Doc coverage",
+            ],
+            rows,
+        );
+
+        // Crates most often behind, with the newest release seen for them
+        let mut behind: BTreeMap<&str, (usize, &str)> = BTreeMap::new();
+        let all_outdated = self
+            .projects
+            .iter()
+            .flat_map(|p| &p.outdated)
+            .chain(&self.unattributed_outdated);
+        for dependency in all_outdated {
+            let entry = behind.entry(dependency.name.as_str()).or_insert((0, ""));
+            entry.0 += 1;
+            if version_key(entry.1) < version_key(&dependency.latest) {
+                entry.1 = &dependency.latest;
+            }
+        }
+        if !behind.is_empty() {
+            body.push_str("<h2>Outdated crates</h2>\n");
+            let rows = behind.into_iter().map(|(name, (count, latest))| {
+                vec![
+                    Cell::text(name),
+                    Cell::number(count as f64, count.to_string()),
+                    Cell::text(latest),
+                ]
+            });
+            table(&mut body, &["Crate", "Manifests behind", "Latest"], rows);
+        }
+
+        let advisories: Vec<(Option<&ProjectPage>, &VulnerableDependency)> = self
+            .projects
+            .iter()
+            .flat_map(|p| p.vulnerable.iter().map(move |v| (Some(p), v)))
+            .chain(self.unattributed_vulnerable.iter().map(|v| (None, v)))
+            .collect();
+        if !advisories.is_empty() {
+            body.push_str("<h2>Advisories</h2>\n");
+            let rows = advisories.into_iter().map(|(project, dependency)| {
+                vec![
+                    Cell::text(&dependency.advisory),
+                    Cell::text(&dependency.name),
+                    Cell::text(&dependency.version),
+                    match project {
+                        Some(project) => {
+                            Cell::link(&project.entry.name, format!("projects/{}", project.file))
+                        }
+                        None => Cell::text(dependency.path.display().to_string()),
+                    },
+                ]
+            });
+            table(
+                &mut body,
+                &["Advisory", "Crate", "Version", "Project"],
+                rows,
+            );
+        }
+
+        page("Fleet dashboard", "", &body)
+    }
+}
+
+/// The project's drill-down page
+pub fn render_project(project: &ProjectPage) -> String {
+    let entry = &project.entry;
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "<p><a href=\"../index.html\">← Fleet dashboard</a></p>\n<h1>{}</h1>",
+        escape(&entry.name)
+    );
+    if let Some(description) = &entry.description {
+        let _ = writeln!(body, "<p>{}</p>", escape(description));
+    }
+
+    body.push_str("<dl>\n");
+    let owners = entry
+        .owners
+        .as_ref()
+        .map(|ownership| ownership.owners.join(", "))
+        .unwrap_or_default();
+    for (label, value) in [
+        ("Path", entry.path.display().to_string()),
+        ("Version", entry.version.clone()),
+        ("Type", entry.project_type.clone()),
+        ("Status", entry.status.clone()),
+        ("Tags", entry.tags.join(", ")),
+        ("Owners", owners),
+        (
+            "Doc coverage",
+            project
+                .coverage
+                .map(|coverage| format!("{:.1}%", coverage))
+                .unwrap_or_else(|| "–".to_string()),
+        ),
+    ] {
+        let _ = writeln!(
+            body,
+            "<dt>{}</dt><dd>{}</dd>",
+            escape(label),
+            escape(&value)
+        );
+    }
+    body.push_str("</dl>\n");
+
+    let _ = writeln!(
+        body,
+        "<h2>Outdated dependencies ({})</h2>",
+        project.outdated.len()
+    );
+    let rows = project.outdated.iter().map(|dependency| {
+        vec![
+            Cell::text(&dependency.name),
+            Cell::text(&dependency.current),
+            Cell::text(&dependency.latest),
+            Cell::text(dependency.path.display().to_string()),
+        ]
+    });
+    table(&mut body, &["Crate", "Current", "Latest", "Manifest"], rows);
+
+    let _ = writeln!(body, "<h2>Advisories ({})</h2>", project.vulnerable.len());
+    let rows = project.vulnerable.iter().map(|dependency| {
+        vec![
+            Cell::text(&dependency.advisory),
+            Cell::text(&dependency.name),
+            Cell::text(&dependency.version),
+            Cell::text(dependency.path.display().to_string()),
+        ]
+    });
+    table(
+        &mut body,
+        &["Advisory", "Crate", "Version", "Manifest"],
+        rows,
+    );
+
+    let _ = writeln!(body, "<h2>Findings ({})</h2>", project.findings.len());
+    let rows = project.findings.iter().map(|(plugin, diagnostic)| {
+        let location = match (&diagnostic.file, diagnostic.line) {
+            (Some(file), Some(line)) => format!("{}:{}", file.display(), line),
+            (Some(file), None) => file.display().to_string(),
+            _ => String::new(),
+        };
+        vec![
+            Cell::severity(diagnostic.severity),
+            Cell::text(plugin),
+            Cell::text(&diagnostic.code),
+            Cell::text(&diagnostic.message),
+            Cell::text(location),
+        ]
+    });
+    table(
+        &mut body,
+        &["Severity", "Plugin", "Code", "Message", "Location"],
+        rows,
+    );
+
+    for (title, crates) in [
+        ("Dependencies", &entry.dependencies),
+        ("Dependents", &entry.dependents),
+    ] {
+        let _ = writeln!(body, "<h2>{} ({})</h2>", title, crates.len());
+        if !crates.is_empty() {
+            let _ = writeln!(body, "<p>{}</p>", escape(&crates.join(", ")));
+        }
+    }
+
+    page(&entry.name, "../", &body)
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let dir = matches
+        .get_one::<PathBuf>("html")
+        .context("Missing --html")?;
+    let dashboard = Dashboard::from_matches(matches)?;
+    dashboard.write(dir)?;
+    println!(
+        "Wrote the dashboard of {} projects to {}",
+        dashboard.projects.len(),
+        dir.join("index.html").display()
+    );
+    Ok(())
+}
+
+/// The `<job>.json` status files kept in the schedule state directory
+fn scheduled_status_files(state_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(state_dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// The percentage leading a coverage note, e.g. `87.5% of 40 public items
+/// documented`
+pub fn parse_coverage(message: &str) -> Option<f64> {
+    message.split_once('%')?.0.trim().parse().ok()
+}
+
+/// Numeric components of a version, for finding the newest
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid report {}", path.display()))
+}
+
+/// File name for a project page: lowercase alphanumerics and dashes
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    match slug.trim_matches('-') {
+        "" => "project".to_string(),
+        slug => slug.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// One table cell: its markup, and the value the column sorts by
+struct Cell {
+    html: String,
+    sort: String,
+}
+
+impl Cell {
+    fn text(text: impl AsRef<str>) -> Self {
+        let text = text.as_ref();
+        Self {
+            html: escape(text),
+            sort: text.to_lowercase(),
+        }
+    }
+
+    fn link(text: &str, href: String) -> Self {
+        Self {
+            html: format!("<a href=\"{}\">{}</a>", escape(&href), escape(text)),
+            sort: text.to_lowercase(),
+        }
+    }
+
+    fn number(value: f64, text: String) -> Self {
+        Self {
+            html: escape(&text),
+            sort: value.to_string(),
+        }
+    }
+
+    fn status(status: &str) -> Self {
+        Self {
+            html: format!(
+                "<span class=\"status {}\">{}</span>",
+                escape(&status.to_lowercase()),
+                escape(status)
+            ),
+            sort: status.to_lowercase(),
+        }
+    }
+
+    fn severity(severity: Severity) -> Self {
+        let (name, rank) = match severity {
+            Severity::Error => ("error", 2),
+            Severity::Warning => ("warning", 1),
+            Severity::Note => ("note", 0),
+        };
+        Self {
+            html: format!("<span class=\"status {}\">{}</span>", name, name),
+            sort: rank.to_string(),
+        }
+    }
+}
+
+/// Projects without a coverage figure sort below every covered one
+fn coverage_cell(coverage: Option<f64>) -> Cell {
+    match coverage {
+        Some(coverage) => Cell::number(coverage, format!("{:.1}%", coverage)),
+        None => Cell::number(-1.0, "–".to_string()),
+    }
+}
+
+/// A table whose columns sort when their header is clicked
+fn table(out: &mut String, headers: &[&str], rows: impl Iterator<Item = Vec<Cell>>) {
+    out.push_str("<table class=\"sortable\">\n<thead><tr>");
+    for header in headers {
+        let _ = write!(out, "<th>{}</th>", escape(header));
+    }
+    out.push_str("</tr></thead>\n<tbody>\n");
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            let _ = write!(
+                out,
+                "<td data-sort=\"{}\">{}</td>",
+                escape(&cell.sort),
+                cell.html
+            );
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n");
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#222}\
+table{border-collapse:collapse;width:100%;margin-bottom:2rem}\
+th,td{border-bottom:1px solid #ddd;padding:.4rem .6rem;text-align:left}\
+th{cursor:pointer;background:#f4f4f4;user-select:none}\
+th.asc::after{content:' ▲'}th.desc::after{content:' ▼'}\
+.cards{display:flex;gap:1rem;flex-wrap:wrap;margin-bottom:2rem}\
+.card{border:1px solid #ddd;border-radius:6px;padding:.8rem 1.2rem}\
+.card span{display:block;color:#666;font-size:.85rem}.card strong{font-size:1.6rem}\
+.status.broken,.status.error{color:#b00020}.status.working{color:#1b7f3b}\
+.status.warning{color:#a36200}dt{font-weight:bold}dd{margin:0 0 .5rem}";
+
+/// Sorts a table by the `data-sort` values of the clicked column, numerically
+/// when both values are numbers
+const SCRIPT: &str = "document.querySelectorAll('table.sortable th').forEach(function(th){\
+th.addEventListener('click',function(){\
+var table=th.closest('table'),body=table.tBodies[0],i=th.cellIndex,\
+asc=!th.classList.contains('asc');\
+table.querySelectorAll('th').forEach(function(h){h.classList.remove('asc','desc')});\
+th.classList.add(asc?'asc':'desc');\
+Array.from(body.rows).sort(function(a,b){\
+var x=a.cells[i].dataset.sort,y=b.cells[i].dataset.sort,\
+n=parseFloat(x)-parseFloat(y),c=isNaN(n)?x.localeCompare(y):n;\
+return asc?c:-c}).forEach(function(r){body.appendChild(r)})})});";
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<meta name=\"generator\" content=\"kargo fleet report\">\n\
+         <link rel=\"home\" href=\"{}index.html\">\n<style>{}</style>\n</head>\n<body>\n{}\
+         <script>{}</script>\n</body>\n</html>\n",
+        escape(title),
+        root,
+        STYLE,
+        body,
+        SCRIPT
+    )
+}
@@ -0,0 +1,503 @@
+//! `kargo graph`: how the crates below the scan directories depend on each other
+//!
+//! [`DependencyGraph`] is built from manifests alone, without running cargo,
+//! so it covers every project [`DependencyUpdater::find_cargo_tomls`] finds,
+//! whether or not they share a workspace. Each package is a node; path
+//! dependencies link to the package at that path, other dependencies to the
+//! local package of that name when there is exactly one, and to an external
+//! node otherwise.
+//!
+//! [`DependencyGraph::reverse_deps`] answers which local crates a change to
+//! a crate can break, [`DependencyGraph::topo_order`] the order to build or
+//! publish them in, and the graph renders as DOT or Mermaid.
+//!
+//! [`DependencyUpdater::find_cargo_tomls`]: crate::DependencyUpdater::find_cargo_tomls
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::metadata::DepKind;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::DependencyUpdater;
+
+const DEPENDENCY_TABLES: [(&str, DepKind); 3] = [
+    ("dependencies", DepKind::Normal),
+    ("dev-dependencies", DepKind::Dev),
+    ("build-dependencies", DepKind::Build),
+];
+
+pub fn command() -> Command {
+    Command::new("graph")
+        .about("Show how the crates below the scan directories depend on each other")
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .value_name("DIR")
+                .help("Directory to scan (repeatable; defaults to the configured scan directories)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format")
+                .value_parser(["text", "dot", "mermaid", "json"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("external")
+                .long("external")
+                .help("Include dependencies that aren't local crates")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("reverse")
+                .long("reverse")
+                .value_name("CRATE")
+                .help("List the local crates depending on CRATE, directly or not")
+                .conflicts_with("topo"),
+        )
+        .arg(
+            Arg::new("topo")
+                .long("topo")
+                .help("List the local crates with each one after its dependencies")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let mut updater = DependencyUpdater::new();
+    if let Some(roots) = matches.get_many::<PathBuf>("root") {
+        updater = updater.with_scan_dirs(roots.cloned().collect());
+    }
+    let graph = DependencyGraph::build(&updater.find_cargo_tomls());
+    let external = matches.get_flag("external");
+
+    if let Some(name) = matches.get_one::<String>("reverse") {
+        if graph.find(name).is_none() {
+            bail!("No crate called {} in the graph", name);
+        }
+        for package in graph.reverse_deps(name) {
+            println!("{}", package.label());
+        }
+        return Ok(());
+    }
+    if matches.get_flag("topo") {
+        for package in graph.topo_order()? {
+            println!("{}", package.label());
+        }
+        return Ok(());
+    }
+    match matches.get_one::<String>("format").map(String::as_str) {
+        Some("dot") => print!("{}", graph.to_dot(external)),
+        Some("mermaid") => print!("{}", graph.to_mermaid(external)),
+        Some("json") => println!("{}", serde_json::to_string_pretty(&graph)?),
+        _ => print!("{}", graph.render(external)),
+    }
+    Ok(())
+}
+
+/// A crate in the graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Package {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The package's manifest; `None` for dependencies that aren't local
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PathBuf>,
+}
+
+impl Package {
+    pub fn is_local(&self) -> bool {
+        self.manifest.is_some()
+    }
+
+    /// `name version (manifest)`
+    pub fn label(&self) -> String {
+        let mut label = self.name.clone();
+        if let Some(version) = &self.version {
+            let _ = write!(label, " {}", version);
+        }
+        if let Some(manifest) = &self.manifest {
+            let _ = write!(label, " ({})", manifest.display());
+        }
+        label
+    }
+}
+
+/// `from` depends on `to`; indices into [`DependencyGraph::packages`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: DepKind,
+    /// The requirement as written, `path`, `git` or `workspace` without one
+    pub requirement: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyGraph {
+    /// Local packages first, by manifest path, then external ones by name
+    pub packages: Vec<Package>,
+    pub edges: Vec<Edge>,
+}
+
+/// A dependency declaration before it's linked to a package
+struct Declared {
+    name: String,
+    kind: DepKind,
+    requirement: String,
+    path: Option<PathBuf>,
+}
+
+impl DependencyGraph {
+    /// The graph of the packages in `manifests`. Virtual manifests, those
+    /// under `target/` and unreadable ones are left out.
+    pub fn build(manifests: &[PathBuf]) -> Self {
+        let mut manifests: Vec<&PathBuf> = manifests
+            .iter()
+            .filter(|m| !m.components().any(|c| c.as_os_str() == "target"))
+            .collect();
+        manifests.sort();
+        manifests.dedup();
+
+        let mut graph = Self::default();
+        let mut declared = Vec::new();
+        for manifest in manifests {
+            let doc = match std::fs::read_to_string(manifest)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(content.parse::<DocumentMut>()?))
+            {
+                Ok(doc) => doc,
+                Err(e) => {
+                    log::warn!("Skipping {}: {}", manifest.display(), e);
+                    continue;
+                }
+            };
+            let Some(name) = doc
+                .get("package")
+                .and_then(|p| p.get("name"))
+                .and_then(Item::as_str)
+            else {
+                continue;
+            };
+            graph.packages.push(Package {
+                name: name.to_string(),
+                version: doc
+                    .get("package")
+                    .and_then(|p| p.get("version"))
+                    .and_then(Item::as_str)
+                    .map(str::to_string),
+                manifest: Some(manifest.clone()),
+            });
+            let dir = manifest.parent().unwrap_or(Path::new("."));
+            declared.push(dependencies(&doc, dir));
+        }
+
+        let by_dir: HashMap<PathBuf, usize> = graph
+            .packages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| Some((canonical(p.manifest.as_ref()?.parent()?), i)))
+            .collect();
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, package) in graph.packages.iter().enumerate() {
+            by_name.entry(package.name.clone()).or_default().push(i);
+        }
+        let mut external: BTreeMap<String, Vec<(usize, Declared)>> = BTreeMap::new();
+        for (from, dependencies) in declared.into_iter().enumerate() {
+            for dependency in dependencies {
+                let local = match &dependency.path {
+                    Some(path) => by_dir.get(&canonical(path)).copied(),
+                    None => match by_name.get(&dependency.name).map(Vec::as_slice) {
+                        Some([only]) => Some(*only),
+                        _ => None,
+                    },
+                };
+                match local {
+                    Some(to) => graph.edges.push(Edge {
+                        from,
+                        to,
+                        kind: dependency.kind,
+                        requirement: dependency.requirement,
+                    }),
+                    None => external
+                        .entry(dependency.name.clone())
+                        .or_default()
+                        .push((from, dependency)),
+                }
+            }
+        }
+        for (name, dependents) in external {
+            let to = graph.packages.len();
+            graph.packages.push(Package {
+                name,
+                version: None,
+                manifest: None,
+            });
+            for (from, dependency) in dependents {
+                graph.edges.push(Edge {
+                    from,
+                    to,
+                    kind: dependency.kind,
+                    requirement: dependency.requirement,
+                });
+            }
+        }
+        graph
+    }
+
+    /// The local package called `name`, or the external one
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.packages
+            .iter()
+            .position(|p| p.name == name && p.is_local())
+            .or_else(|| self.packages.iter().position(|p| p.name == name))
+    }
+
+    /// Local packages depending on `name` directly or through other local
+    /// packages, by name
+    pub fn reverse_deps(&self, name: &str) -> Vec<&Package> {
+        let targets: Vec<usize> = (0..self.packages.len())
+            .filter(|&i| self.packages[i].name == name)
+            .collect();
+        let mut seen: BTreeSet<usize> = BTreeSet::new();
+        let mut queue: VecDeque<usize> = targets.iter().copied().collect();
+        while let Some(to) = queue.pop_front() {
+            for edge in self.edges.iter().filter(|e| e.to == to) {
+                if !targets.contains(&edge.from) && seen.insert(edge.from) {
+                    queue.push_back(edge.from);
+                }
+            }
+        }
+        let mut dependents: Vec<&Package> = seen.into_iter().map(|i| &self.packages[i]).collect();
+        dependents.sort_by(|a, b| a.name.cmp(&b.name).then(a.manifest.cmp(&b.manifest)));
+        dependents
+    }
+
+    /// Local packages, each after the local packages it needs to build.
+    /// Dev-dependencies don't count: cargo allows cycles through them.
+    pub fn topo_order(&self) -> Result<Vec<&Package>> {
+        let local: Vec<usize> = (0..self.packages.len())
+            .filter(|&i| self.packages[i].is_local())
+            .collect();
+        let mut pending: HashMap<usize, usize> = local.iter().map(|&i| (i, 0)).collect();
+        let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut edges: BTreeSet<(usize, usize)> = BTreeSet::new();
+        for edge in &self.edges {
+            if edge.kind != DepKind::Dev
+                && edge.from != edge.to
+                && self.packages[edge.to].is_local()
+                && edges.insert((edge.from, edge.to))
+            {
+                *pending.entry(edge.from).or_default() += 1;
+                dependents.entry(edge.to).or_default().push(edge.from);
+            }
+        }
+
+        let mut ready: BTreeSet<usize> =
+            local.iter().copied().filter(|i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(local.len());
+        while let Some(next) = ready.pop_first() {
+            order.push(&self.packages[next]);
+            for &dependent in dependents.get(&next).into_iter().flatten() {
+                let count = pending.get_mut(&dependent).expect("local package");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+        if order.len() < local.len() {
+            let mut cycle: Vec<&str> = local
+                .iter()
+                .filter(|i| pending[i] > 0)
+                .map(|&i| self.packages[i].name.as_str())
+                .collect();
+            cycle.sort();
+            bail!("Dependency cycle between {}", cycle.join(", "));
+        }
+        Ok(order)
+    }
+
+    /// Each local package with what it depends on
+    pub fn render(&self, external: bool) -> String {
+        let mut out = String::new();
+        for (i, package) in self.packages.iter().enumerate() {
+            if !package.is_local() {
+                continue;
+            }
+            let _ = writeln!(out, "{}", package.label());
+            for edge in self.shown_edges(external).filter(|e| e.from == i) {
+                let kind = match edge.kind {
+                    DepKind::Normal => String::new(),
+                    kind => format!(" [{}]", kind_name(kind)),
+                };
+                let _ = writeln!(
+                    out,
+                    "  -> {} {}{}",
+                    self.packages[edge.to].name, edge.requirement, kind
+                );
+            }
+        }
+        out
+    }
+
+    /// Graphviz DOT; dev and build dependencies are dashed and dotted
+    pub fn to_dot(&self, external: bool) -> String {
+        let mut out = String::from("digraph dependencies {\n    rankdir=LR;\n");
+        for (i, package) in self.shown_packages(external) {
+            let style = if package.is_local() {
+                ""
+            } else {
+                ", style=dashed"
+            };
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{}\"{}];",
+                i,
+                escape(&package.name),
+                style
+            );
+        }
+        for edge in self.shown_edges(external) {
+            let style = match edge.kind {
+                DepKind::Normal => "",
+                DepKind::Dev => " [style=dashed]",
+                DepKind::Build => " [style=dotted]",
+            };
+            let _ = writeln!(out, "    n{} -> n{}{};", edge.from, edge.to, style);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// A Mermaid flowchart; dev and build dependencies are labelled
+    pub fn to_mermaid(&self, external: bool) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (i, package) in self.shown_packages(external) {
+            let (open, close) = if package.is_local() {
+                ("[", "]")
+            } else {
+                ("([", "])")
+            };
+            let _ = writeln!(
+                out,
+                "    n{}{}\"{}\"{}",
+                i,
+                open,
+                escape(&package.name),
+                close
+            );
+        }
+        for edge in self.shown_edges(external) {
+            match edge.kind {
+                DepKind::Normal => {
+                    let _ = writeln!(out, "    n{} --> n{}", edge.from, edge.to);
+                }
+                kind => {
+                    let _ = writeln!(
+                        out,
+                        "    n{} -.->|{}| n{}",
+                        edge.from,
+                        kind_name(kind),
+                        edge.to
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    fn shown_packages(&self, external: bool) -> impl Iterator<Item = (usize, &Package)> {
+        self.packages
+            .iter()
+            .enumerate()
+            .filter(move |(_, p)| external || p.is_local())
+    }
+
+    fn shown_edges(&self, external: bool) -> impl Iterator<Item = &Edge> {
+        self.edges
+            .iter()
+            .filter(move |e| external || self.packages[e.to].is_local())
+    }
+}
+
+/// Every dependency declared in a manifest, including target-specific ones;
+/// relative paths are resolved against `dir`
+fn dependencies(doc: &DocumentMut, dir: &Path) -> Vec<Declared> {
+    let mut tables = vec![doc.as_item()];
+    if let Some(targets) = doc.get("target").and_then(Item::as_table_like) {
+        tables.extend(targets.iter().map(|(_, target)| target));
+    }
+    let mut declared = Vec::new();
+    for table in tables {
+        for (name, kind) in DEPENDENCY_TABLES {
+            if let Some(deps) = table.get(name).and_then(Item::as_table_like) {
+                declared.extend(table_dependencies(deps, kind, dir));
+            }
+        }
+    }
+    declared
+}
+
+fn table_dependencies(deps: &dyn TableLike, kind: DepKind, dir: &Path) -> Vec<Declared> {
+    deps.iter()
+        .map(|(key, spec)| {
+            let Some(spec) = spec.as_table_like() else {
+                return Declared {
+                    name: key.to_string(),
+                    kind,
+                    requirement: spec.as_str().unwrap_or("*").to_string(),
+                    path: None,
+                };
+            };
+            let requirement = if let Some(version) = spec.get("version").and_then(Item::as_str) {
+                version
+            } else if spec.get("workspace").and_then(Item::as_bool) == Some(true) {
+                "workspace"
+            } else if spec.contains_key("git") {
+                "git"
+            } else if spec.contains_key("path") {
+                "path"
+            } else {
+                "*"
+            };
+            Declared {
+                name: spec
+                    .get("package")
+                    .and_then(Item::as_str)
+                    .unwrap_or(key)
+                    .to_string(),
+                kind,
+                requirement: requirement.to_string(),
+                path: spec
+                    .get("path")
+                    .and_then(Item::as_str)
+                    .map(|path| dir.join(path)),
+            }
+        })
+        .collect()
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn kind_name(kind: DepKind) -> &'static str {
+    match kind {
+        DepKind::Normal => "normal",
+        DepKind::Dev => "dev",
+        DepKind::Build => "build",
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('"', "\\\"")
+}
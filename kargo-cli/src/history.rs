@@ -0,0 +1,98 @@
+//! `kargo history`: when and why dependencies reached their versions
+//!
+//! Reads the `.kargo/history.jsonl` audit trail that upgrades append to (see
+//! [`kargo_plugin_api::history`]). With a crate name it prints every recorded
+//! update of that crate, oldest first; without one, the most recent update of
+//! each crate.
+
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::history::{History, UpgradeRecord};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::schedule::{ago, unix_now};
+
+pub fn command() -> Command {
+    Command::new("history")
+        .about("Show when and why dependencies were upgraded")
+        .arg(
+            Arg::new("crate")
+                .value_name("CRATE")
+                .help("Crate to show the full history of"),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory (defaults to the nearest one with a history)")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the matching records as JSON lines")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let history = match matches.get_one::<PathBuf>("path") {
+        Some(dir) => History::for_project(dir),
+        None => {
+            let cwd = std::env::current_dir()?;
+            History::find(&cwd).with_context(|| {
+                format!("No upgrade history found in {} or above", cwd.display())
+            })?
+        }
+    };
+
+    let records = match matches.get_one::<String>("crate") {
+        Some(name) => {
+            let records = history.for_crate(name)?;
+            if records.is_empty() {
+                println!(
+                    "No recorded upgrades of {} in {}",
+                    name,
+                    history.path().display()
+                );
+            }
+            records
+        }
+        None => latest_per_crate(history.read()?),
+    };
+
+    if matches.get_flag("json") {
+        for record in &records {
+            println!("{}", serde_json::to_string(record)?);
+        }
+        return Ok(());
+    }
+
+    let now = unix_now();
+    for record in &records {
+        println!(
+            "{:<24} {} -> {}  {}  policy {}  by {} (kargo {}, session {})  {}",
+            record.crate_name,
+            record.from,
+            record.to,
+            ago(record.timestamp, now),
+            record.policy,
+            record.user.as_deref().unwrap_or("unknown"),
+            record.kargo_version,
+            record.session,
+            record.manifest.display()
+        );
+    }
+    Ok(())
+}
+
+/// The most recent record of each crate, by crate name
+pub fn latest_per_crate(records: Vec<UpgradeRecord>) -> Vec<UpgradeRecord> {
+    let mut latest = BTreeMap::new();
+    for record in records {
+        // Records are appended in order, so later ones win
+        latest.insert(record.crate_name.clone(), record);
+    }
+    latest.into_values().collect()
+}
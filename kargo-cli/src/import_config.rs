@@ -0,0 +1,784 @@
+//! `kargo import-config`: carry Dependabot and Renovate settings over
+//!
+//! The update configs of those bots translate into what kargo already has:
+//!
+//! - ignore rules become update policies in each project's
+//!   `.kargo/update-policy.yaml`, per crate (with `*` patterns) or per
+//!   dependency kind. Ignoring major updates means `compatible`, major and
+//!   minor `patch`, and everything `pinned`.
+//! - schedules become `kargo schedule` jobs running `kargo upgrade` on the
+//!   project, by interval only; days and times of day are dropped.
+//! - grouping becomes the job's `--git-commit` batching: a group taking in
+//!   every dependency commits the whole run at once, otherwise each crate is
+//!   committed on its own, like the one pull request per dependency the bots
+//!   open.
+//!
+//! Only cargo settings are imported. Whatever has no counterpart, such as
+//! labels, reviewers, version ranges or regex matchers, is listed instead of
+//! silently dropped.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use kargo_plugin_api::WriteGuard;
+use kargo_upgrade::update_policy::UPDATE_POLICY_FILE;
+
+use crate::config::Config;
+use crate::schedule::{Interval, ScheduledJob};
+
+pub fn command() -> Command {
+    Command::new("import-config")
+        .about("Translate a Dependabot or Renovate config into kargo update policies and schedules")
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .value_name("TOOL")
+                .help("Format of FILE")
+                .value_parser(["dependabot", "renovate"])
+                .required(true),
+        )
+        .arg(
+            Arg::new("file")
+                .value_name("FILE")
+                .help("dependabot.yml or renovate.json to import")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Repository root the config's directories are relative to")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Show what would be written without writing it")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let source: Source = matches
+        .get_one::<String>("from")
+        .expect("required")
+        .parse()?;
+    let file = matches.get_one::<PathBuf>("file").expect("required");
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let root = dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let import = import(source, &content, &root)
+        .with_context(|| format!("Failed to import {}", file.display()))?;
+    let dry_run = matches.get_flag("dry-run");
+
+    for (project, draft) in &import.policies {
+        let path = root.join(project).join(UPDATE_POLICY_FILE);
+        let merged = PolicyDraft::load(&path)?.merged(draft);
+        let yaml = serde_yaml::to_string(&merged)?;
+        if dry_run {
+            println!("{}:\n{}", path.display(), yaml);
+            continue;
+        }
+        let guard = WriteGuard::current();
+        if let Some(parent) = path.parent() {
+            guard.create_dir_all(parent)?;
+        }
+        guard.write(&path, yaml)?;
+        println!("Wrote {}", path.display());
+    }
+
+    if !import.jobs.is_empty() {
+        let path = Config::path();
+        let mut config = Config::load()?;
+        for job in &import.jobs {
+            // Importing again replaces the jobs of the last import
+            config.schedule.retain(|existing| existing.name != job.name);
+            config.schedule.push(job.clone());
+            println!(
+                "{} job {} every {}: kargo {}",
+                if dry_run { "Would add" } else { "Added" },
+                job.name,
+                job.every,
+                job.command
+            );
+        }
+        if !dry_run {
+            config.save(&path)?;
+            println!("Saved the schedule to {}", path.display());
+        }
+    }
+
+    if !import.notes.is_empty() {
+        println!("\nNot translated:");
+        for note in &import.notes {
+            println!("  - {}", note);
+        }
+    }
+    Ok(())
+}
+
+/// Tool whose config is imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Dependabot,
+    Renovate,
+}
+
+impl FromStr for Source {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dependabot" => Ok(Self::Dependabot),
+            "renovate" => Ok(Self::Renovate),
+            other => bail!("expected dependabot or renovate, got '{}'", other),
+        }
+    }
+}
+
+/// Which versions a dependency may move to, as in the upgrade plugin's
+/// policy file; later variants are stricter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdatePolicy {
+    Latest,
+    Compatible,
+    Patch,
+    Pinned,
+}
+
+impl UpdatePolicy {
+    /// The policy keeping out the blocked kinds of update, if one does
+    fn blocking(major: bool, minor: bool, patch: bool) -> Option<Self> {
+        match (major, minor, patch) {
+            (false, false, false) => Some(Self::Latest),
+            (true, false, false) => Some(Self::Compatible),
+            (true, true, false) => Some(Self::Patch),
+            (true, true, true) => Some(Self::Pinned),
+            _ => None,
+        }
+    }
+}
+
+/// Contents of an update policy file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDraft {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<UpdatePolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev: Option<UpdatePolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<UpdatePolicy>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub crates: BTreeMap<String, UpdatePolicy>,
+}
+
+impl PolicyDraft {
+    /// The policy file at `path`, empty if there is none
+    fn load(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Invalid update policy in {}", path.display()))
+    }
+
+    /// This file with the kinds and crates `imported` sets replaced
+    fn merged(mut self, imported: &Self) -> Self {
+        self.normal = imported.normal.or(self.normal);
+        self.dev = imported.dev.or(self.dev);
+        self.build = imported.build.or(self.build);
+        self.crates.extend(imported.crates.clone());
+        self
+    }
+
+    /// Apply `policy` to `kind`, keeping a stricter one set by another rule
+    fn restrict_kind(&mut self, kind: Kind, policy: UpdatePolicy) {
+        let slot = match kind {
+            Kind::Normal => &mut self.normal,
+            Kind::Dev => &mut self.dev,
+            Kind::Build => &mut self.build,
+        };
+        *slot = Some(slot.map_or(policy, |current| current.max(policy)));
+    }
+
+    fn restrict_crate(&mut self, name: &str, policy: UpdatePolicy) {
+        let slot = self.crates.entry(name.to_string()).or_insert(policy);
+        *slot = (*slot).max(policy);
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Normal,
+    Dev,
+    Build,
+}
+
+const ALL_KINDS: [Kind; 3] = [Kind::Normal, Kind::Dev, Kind::Build];
+
+/// Result of translating a config
+#[derive(Debug, Clone, Default)]
+pub struct Import {
+    /// Update policies by project directory, relative to the repository root
+    pub policies: BTreeMap<PathBuf, PolicyDraft>,
+    pub jobs: Vec<ScheduledJob>,
+    /// Settings that have no kargo counterpart
+    pub notes: Vec<String>,
+}
+
+/// Translate the `content` of a `source` config for the repository at `root`
+pub fn import(source: Source, content: &str, root: &Path) -> Result<Import> {
+    let mut import = Import::default();
+    match source {
+        Source::Dependabot => {
+            let config: Dependabot =
+                serde_yaml::from_str(content).context("Invalid Dependabot config")?;
+            import_dependabot(config, root, &mut import);
+        }
+        Source::Renovate => {
+            let config: Value = serde_json::from_str(content).context("Invalid Renovate config")?;
+            import_renovate(&config, root, &mut import)?;
+        }
+    }
+    import.policies.retain(|_, draft| !draft.is_empty());
+    if !import.jobs.is_empty() && root.to_string_lossy().contains(char::is_whitespace) {
+        // Scheduled commands are split on whitespace
+        import.notes.push(format!(
+            "schedule: jobs can't be run on {}, whose path contains spaces",
+            root.display()
+        ));
+        import.jobs.clear();
+    }
+    Ok(import)
+}
+
+#[derive(Debug, Deserialize)]
+struct Dependabot {
+    #[serde(default)]
+    updates: Vec<DependabotUpdate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DependabotUpdate {
+    package_ecosystem: String,
+    directory: Option<String>,
+    #[serde(default)]
+    directories: Vec<String>,
+    schedule: Option<DependabotSchedule>,
+    #[serde(default)]
+    ignore: Vec<DependabotIgnore>,
+    #[serde(default)]
+    groups: BTreeMap<String, DependabotGroup>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DependabotSchedule {
+    interval: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DependabotIgnore {
+    dependency_name: String,
+    #[serde(default)]
+    versions: Vec<String>,
+    #[serde(default)]
+    update_types: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DependabotGroup {
+    #[serde(default)]
+    patterns: Vec<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+fn import_dependabot(config: Dependabot, root: &Path, import: &mut Import) {
+    let updates: Vec<DependabotUpdate> = config
+        .updates
+        .into_iter()
+        .filter(|update| update.package_ecosystem == "cargo")
+        .collect();
+    if updates.is_empty() {
+        import
+            .notes
+            .push("dependabot: no updates for the cargo ecosystem".to_string());
+    }
+    let single = updates.len() == 1;
+
+    for update in updates {
+        let mut directories = update.directories.clone();
+        directories.extend(update.directory.clone());
+        if directories.is_empty() {
+            directories.push("/".to_string());
+        }
+        for directory in directories {
+            let project = PathBuf::from(directory.trim_matches('/'));
+            let project_dir = match project.as_os_str().is_empty() {
+                true => root.to_path_buf(),
+                false => root.join(&project),
+            };
+            let label = format!("dependabot {}", directory);
+
+            let draft = import.policies.entry(project.clone()).or_default();
+            for rule in &update.ignore {
+                let name = rule.dependency_name.as_str();
+                if !rule.versions.is_empty() {
+                    import.notes.push(format!(
+                        "{}: ignored versions {} of {} (kargo can't skip single versions)",
+                        label,
+                        rule.versions.join(", "),
+                        name
+                    ));
+                    if rule.update_types.is_empty() {
+                        continue;
+                    }
+                }
+                let blocked = |kind: &str| {
+                    rule.update_types.is_empty()
+                        || rule
+                            .update_types
+                            .iter()
+                            .any(|t| t == &format!("version-update:semver-{}", kind))
+                };
+                let Some(policy) =
+                    UpdatePolicy::blocking(blocked("major"), blocked("minor"), blocked("patch"))
+                else {
+                    import.notes.push(format!(
+                        "{}: ignored {} updates of {} (only major, major and minor, or all updates can be held back)",
+                        label,
+                        rule.update_types.join(", "),
+                        name
+                    ));
+                    continue;
+                };
+                if name == "*" {
+                    for kind in ALL_KINDS {
+                        draft.restrict_kind(kind, policy);
+                    }
+                } else {
+                    draft.restrict_crate(name, policy);
+                }
+            }
+
+            let batching = if update.groups.is_empty() {
+                "crate"
+            } else if update
+                .groups
+                .values()
+                .any(|group| group.other.is_empty() && group.patterns.iter().any(|p| p == "*"))
+            {
+                "session"
+            } else {
+                for name in update.groups.keys() {
+                    import.notes.push(format!(
+                        "{}: group {} (commits are batched per crate, project or session only)",
+                        label, name
+                    ));
+                }
+                "crate"
+            };
+
+            match &update.schedule {
+                Some(schedule) => match dependabot_interval(&schedule.interval) {
+                    Some(every) => import.jobs.push(ScheduledJob {
+                        name: if single {
+                            "dependabot".to_string()
+                        } else {
+                            format!("dependabot-{}", job_suffix(&project))
+                        },
+                        command: upgrade_command(&project_dir, batching),
+                        every,
+                    }),
+                    None => import.notes.push(format!(
+                        "{}: schedule interval {}",
+                        label, schedule.interval
+                    )),
+                },
+                None => import.notes.push(format!("{}: no schedule", label)),
+            }
+
+            let other: Vec<&str> = update
+                .other
+                .keys()
+                .map(String::as_str)
+                .filter(|key| *key != "schedule")
+                .collect();
+            if !other.is_empty() {
+                import
+                    .notes
+                    .push(format!("{}: settings {}", label, other.join(", ")));
+            }
+        }
+    }
+}
+
+fn dependabot_interval(interval: &str) -> Option<Interval> {
+    let every = match interval {
+        "daily" => "daily",
+        "weekly" => "weekly",
+        "monthly" => "30d",
+        "quarterly" => "13w",
+        "semiannually" => "26w",
+        "yearly" => "52w",
+        _ => return None,
+    };
+    every.parse().ok()
+}
+
+/// Keys of a Renovate config that are read
+const RENOVATE_KEYS: &[&str] = &[
+    "$schema",
+    "enabled",
+    "extends",
+    "schedule",
+    "ignoreDeps",
+    "major",
+    "minor",
+    "patch",
+    "packageRules",
+];
+
+/// Keys of a package rule that are read
+const RULE_KEYS: &[&str] = &[
+    "description",
+    "enabled",
+    "groupName",
+    "matchManagers",
+    "matchDatasources",
+    "matchDepTypes",
+    "matchPackageNames",
+    "matchPackagePrefixes",
+    "matchPackagePatterns",
+    "matchUpdateTypes",
+];
+
+fn import_renovate(config: &Value, root: &Path, import: &mut Import) -> Result<()> {
+    let Some(object) = config.as_object() else {
+        bail!("Renovate config must be a JSON object");
+    };
+    let mut draft = PolicyDraft::default();
+    let mut every: Option<Interval> = None;
+    let mut batching = "crate";
+
+    if config.get("enabled") == Some(&Value::Bool(false)) {
+        for kind in ALL_KINDS {
+            draft.restrict_kind(kind, UpdatePolicy::Pinned);
+        }
+    }
+
+    let mut presets = Vec::new();
+    for preset in strings(config.get("extends")) {
+        match preset.as_str() {
+            "group:all" => batching = "session",
+            "group:allNonMajor" => {
+                batching = "session";
+                import.notes.push(
+                    "renovate: group:allNonMajor is committed like group:all, majors included"
+                        .to_string(),
+                );
+            }
+            preset => match preset.strip_prefix("schedule:").and_then(preset_interval) {
+                Some(interval) => every = Some(interval),
+                None => presets.push(preset.to_string()),
+            },
+        }
+    }
+    if !presets.is_empty() {
+        import.notes.push(format!(
+            "renovate: presets {} (not expanded)",
+            presets.join(", ")
+        ));
+    }
+
+    let schedule = strings(config.get("schedule"));
+    if !schedule.is_empty() {
+        every = Some(schedule_interval(&schedule));
+    }
+
+    for name in strings(config.get("ignoreDeps")) {
+        draft.restrict_crate(&name, UpdatePolicy::Pinned);
+    }
+
+    let disabled = |key: &str| {
+        config
+            .get(key)
+            .and_then(|v| v.get("enabled"))
+            .is_some_and(|enabled| enabled == &Value::Bool(false))
+    };
+    let (major, minor, patch) = (disabled("major"), disabled("minor"), disabled("patch"));
+    match UpdatePolicy::blocking(major, minor, patch) {
+        Some(UpdatePolicy::Latest) => {}
+        Some(policy) => {
+            for kind in ALL_KINDS {
+                draft.restrict_kind(kind, policy);
+            }
+        }
+        None => import.notes.push(
+            "renovate: disabled update types (only major, major and minor, or all updates can be held back)"
+                .to_string(),
+        ),
+    }
+
+    let rules = config
+        .get("packageRules")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for (i, rule) in rules.iter().enumerate() {
+        let label = match rule.get("description").and_then(Value::as_str) {
+            Some(description) => format!("renovate packageRules[{}] ({})", i, description),
+            None => format!("renovate packageRules[{}]", i),
+        };
+        if let Some(outcome) = import_rule(rule, &label, &mut draft, import) {
+            batching = outcome;
+        }
+    }
+
+    let other: Vec<&str> = object
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !RENOVATE_KEYS.contains(key))
+        .collect();
+    if !other.is_empty() {
+        import
+            .notes
+            .push(format!("renovate: settings {}", other.join(", ")));
+    }
+
+    import.policies.insert(PathBuf::new(), draft);
+    import.jobs.push(ScheduledJob {
+        name: "renovate".to_string(),
+        command: upgrade_command(root, batching),
+        every: match every {
+            Some(every) => every,
+            None => "daily".parse()?,
+        },
+    });
+    Ok(())
+}
+
+/// Apply one package rule to `draft`; the batching it calls for when it
+/// groups every dependency
+fn import_rule(
+    rule: &Value,
+    label: &str,
+    draft: &mut PolicyDraft,
+    import: &mut Import,
+) -> Option<&'static str> {
+    let applies = |key: &str, wanted: &str| {
+        rule.get(key)
+            .is_none_or(|_| strings(rule.get(key)).iter().any(|v| v == wanted))
+    };
+    if !applies("matchManagers", "cargo") || !applies("matchDatasources", "crate") {
+        return None;
+    }
+
+    let other: Vec<&str> = rule
+        .as_object()
+        .into_iter()
+        .flat_map(|rule| rule.keys())
+        .map(String::as_str)
+        .filter(|key| !RULE_KEYS.contains(key))
+        .collect();
+    if !other.is_empty() {
+        import
+            .notes
+            .push(format!("{}: settings {}", label, other.join(", ")));
+    }
+
+    let mut crates = Vec::new();
+    let mut matches_all = true;
+    for name in strings(rule.get("matchPackageNames")) {
+        matches_all = false;
+        if name.starts_with('/') || name.starts_with('!') {
+            import
+                .notes
+                .push(format!("{}: package matcher {}", label, name));
+        } else {
+            crates.push(name);
+        }
+    }
+    for prefix in strings(rule.get("matchPackagePrefixes")) {
+        matches_all = false;
+        crates.push(format!("{}*", prefix));
+    }
+    for pattern in strings(rule.get("matchPackagePatterns")) {
+        if pattern != "*" && pattern != ".*" {
+            matches_all = false;
+            import
+                .notes
+                .push(format!("{}: package pattern {}", label, pattern));
+        }
+    }
+    let kinds: Vec<Kind> = strings(rule.get("matchDepTypes"))
+        .iter()
+        .filter_map(|dep_type| match dep_type.as_str() {
+            "dependencies" | "normal" => Some(Kind::Normal),
+            "dev-dependencies" | "dev" => Some(Kind::Dev),
+            "build-dependencies" | "build" => Some(Kind::Build),
+            _ => None,
+        })
+        .collect();
+
+    let mut batching = None;
+    if let Some(group) = rule.get("groupName").and_then(Value::as_str) {
+        if matches_all && kinds.is_empty() && rule.get("matchUpdateTypes").is_none() {
+            batching = Some("session");
+        } else {
+            import.notes.push(format!(
+                "{}: group {} (commits are batched per crate, project or session only)",
+                label, group
+            ));
+        }
+    }
+
+    if rule.get("enabled") == Some(&Value::Bool(false)) {
+        let types = strings(rule.get("matchUpdateTypes"));
+        let blocked = |kind: &str| types.is_empty() || types.iter().any(|t| t == kind);
+        let Some(policy) =
+            UpdatePolicy::blocking(blocked("major"), blocked("minor"), blocked("patch"))
+        else {
+            import.notes.push(format!(
+                "{}: disabled {} updates (only major, major and minor, or all updates can be held back)",
+                label,
+                types.join(", ")
+            ));
+            return batching;
+        };
+        if !crates.is_empty() {
+            if !kinds.is_empty() {
+                import.notes.push(format!(
+                    "{}: applied to {} as every kind of dependency",
+                    label,
+                    crates.join(", ")
+                ));
+            }
+            for name in &crates {
+                draft.restrict_crate(name, policy);
+            }
+        } else if matches_all {
+            let kinds = if kinds.is_empty() {
+                ALL_KINDS.to_vec()
+            } else {
+                kinds
+            };
+            for kind in kinds {
+                draft.restrict_kind(kind, policy);
+            }
+        }
+    }
+    batching
+}
+
+/// A string or an array of strings
+fn strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Interval of a `schedule:` preset
+fn preset_interval(preset: &str) -> Option<Interval> {
+    let every = match preset {
+        "daily" | "nonOfficeHours" | "officeHours" => "daily",
+        "weekly" | "weekends" | "weekdays" | "earlyMondays" => "weekly",
+        "monthly" => "30d",
+        "quarterly" => "13w",
+        "yearly" => "52w",
+        _ => return None,
+    };
+    every.parse().ok()
+}
+
+/// How often a Renovate schedule lets updates through: its text or cron
+/// entries are only read for whether they name days of the month or week
+pub fn schedule_interval(schedule: &[String]) -> Interval {
+    const WEEKDAYS: &[&str] = &[
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+    let every = schedule
+        .iter()
+        .map(|entry| {
+            let entry = entry.to_lowercase();
+            let fields: Vec<&str> = entry.split_whitespace().collect();
+            let cron = fields.len() == 5
+                && fields
+                    .iter()
+                    .all(|f| f.chars().all(|c| "0123456789*/,-".contains(c)));
+            if cron {
+                match (fields[2], fields[4]) {
+                    (day, _) if day != "*" => "30d",
+                    (_, weekday) if weekday != "*" => "weekly",
+                    _ => "daily",
+                }
+            } else if entry.contains("month") {
+                "30d"
+            } else if entry.contains("weekday") {
+                "daily"
+            } else if entry.contains("week") || WEEKDAYS.iter().any(|day| entry.contains(day)) {
+                "weekly"
+            } else {
+                "daily"
+            }
+        })
+        // The most frequent entry wins
+        .min_by_key(|every| match *every {
+            "daily" => 0,
+            "weekly" => 1,
+            _ => 2,
+        })
+        .unwrap_or("daily");
+    every.parse().expect("valid interval")
+}
+
+/// `kargo upgrade` of `dir`, committing with `batching`
+fn upgrade_command(dir: &Path, batching: &str) -> String {
+    format!("upgrade {} --git-commit {}", dir.display(), batching)
+}
+
+fn job_suffix(project: &Path) -> String {
+    let suffix: String = project
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    match suffix.trim_matches('-') {
+        "" => "root".to_string(),
+        suffix => suffix.to_string(),
+    }
+}
@@ -1,4 +1,5 @@
 use jwalk::WalkDir;
+use kargo_plugin_api::profile::{Phase, Profiler};
 use log::{info, warn};
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
@@ -7,24 +8,60 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use tokio::sync::broadcast;
 use toml_edit::{DocumentMut, Item};
+use tracing::Instrument;
 
 use crate::backup::BackupManager;
 use crate::commands::CommandRunner;
 use crate::config::Config;
-use crate::events::{Event, EventBus};
+use crate::events::{Event, EventBus, EventKind, Source};
 use crate::vendor::VendorManager;
 
-mod backup;
+pub mod backup;
+pub mod cache;
+pub mod changelog;
+pub mod ci;
+pub mod ci_image;
 pub mod cli;
 mod commands;
 pub mod config;
+pub mod cost;
+pub mod daemon;
+pub mod diff_manifests;
+pub mod digest;
+pub mod distribution;
+pub mod doctor;
 pub mod events;
+pub mod explain;
+pub mod features;
+pub mod fleet;
+pub mod graph;
+pub mod history;
+pub mod import_config;
+pub mod logging;
+pub mod manifest;
+pub mod metrics;
+pub mod migrate_edition;
+pub mod palette;
+pub mod plugin;
 pub mod plugins;
 pub mod project;
+pub mod publish_check;
+pub mod remote_config;
+pub mod restore;
+pub mod run_log;
 pub mod rustscript;
+pub mod schedule;
+pub mod secret;
+pub mod shared_target;
+pub mod size_report;
+pub mod stale;
+pub mod status;
 pub mod vendor;
+pub mod verify_vendored;
+pub mod why;
 
 // Export types for convenience
+pub use kargo_plugin_api::metadata;
 pub use project::{ProjectAnalyzer, ProjectType};
 pub use rustscript::RustScript;
 // These types would come from kargo-upgrade if we were using it
@@ -50,9 +87,12 @@ impl<'a> DependencyUpdateJob<'a> {
 
             if let Err(e) = &result {
                 if let Some(backup) = backup {
-                    self.events.publish(Event::Error {
-                        message: e.to_string(),
-                    });
+                    self.events.publish(
+                        Source::Updater,
+                        EventKind::Error {
+                            message: e.to_string(),
+                        },
+                    );
                     backup.rollback()?;
                 }
             }
@@ -70,25 +110,20 @@ pub struct DependencyUpdater {
 
 impl DependencyUpdater {
     pub fn new() -> Self {
-        let config = Config::load()
+        let config = Config::active()
             .map_err(|e| log::error!("Failed to load config: {}", e))
             .unwrap_or_default();
         let events = EventBus::new();
 
         let scan_dirs = std::env::var("KRATER_SCAN")
             .map(|dirs| dirs.split(':').map(PathBuf::from).collect())
-            .unwrap_or_else(|_| {
-                vec![
-                    std::env::var("HOME")
-                        .map(PathBuf::from)
-                        .unwrap_or_else(|_| {
-                            log::warn!(
-                                "HOME environment variable not set, using current directory"
-                            );
-                            PathBuf::from(".")
-                        }),
-                ]
-            });
+            .unwrap_or_else(|_| config.scan_dirs.clone());
+        let scan_dirs = if scan_dirs.iter().all(|dir| dir.as_os_str().is_empty()) {
+            log::warn!("No scan directories configured, using current directory");
+            vec![PathBuf::from(".")]
+        } else {
+            scan_dirs
+        };
 
         info!("Scanning directories: {:?}", scan_dirs);
 
@@ -99,7 +134,15 @@ impl DependencyUpdater {
         }
     }
 
+    /// Scan `dirs` instead of the configured directories
+    pub fn with_scan_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.scan_dirs = dirs;
+        self
+    }
+
     pub fn find_cargo_tomls(&self) -> Vec<PathBuf> {
+        let _span = tracing::info_span!("scan", dirs = self.scan_dirs.len()).entered();
+        let _profiled = Profiler::current().span(Phase::Discovery, "manifests");
         self.scan_dirs
             .par_iter()
             .flat_map(|dir| {
@@ -122,7 +165,7 @@ impl DependencyUpdater {
     // Non-async interface that returns a domain-specific type
     pub fn run(&self) -> DependencyUpdateJob<'_> {
         let backup = if self.config.rollback_on_failure {
-            match BackupManager::new(self.events.clone()) {
+            match BackupManager::new("update", self.events.clone()) {
                 Ok(bm) => Some(bm),
                 Err(e) => {
                     log::error!("Failed to create backup manager: {}", e);
@@ -149,36 +192,43 @@ impl DependencyUpdater {
             let cargo_tomls = self.find_cargo_tomls();
             info!("Found {} Cargo.toml files", cargo_tomls.len());
 
-            if let Some(backup) = backup {
-                for file_path in &cargo_tomls {
-                    backup.backup_file(file_path)?;
+            // Backups, vendoring and post-commands all mutate the tree
+            let apply = tracing::info_span!("apply", manifests = cargo_tomls.len());
+            async move {
+                if let Some(backup) = backup {
+                    let _profiled = Profiler::current().span(Phase::Writes, "backups");
+                    for file_path in &cargo_tomls {
+                        backup.backup_file(file_path)?;
+                    }
                 }
-            }
 
-            if self.config.vendor.enabled {
-                let vendor = VendorManager::new(
-                    self.config.vendor.path.clone(),
-                    self.config.vendor.dedupe,
-                    self.events.clone(),
-                );
+                if self.config.vendor.enabled {
+                    let vendor = VendorManager::new(
+                        self.config.vendor.path.clone(),
+                        self.config.vendor.dedupe,
+                        self.events.clone(),
+                    );
 
-                let workspaces = vec![PathBuf::from("workspace/path")]; // Example paths
-                for workspace in workspaces {
-                    vendor.vendor_dependencies(&workspace).await?;
+                    let workspaces = vec![PathBuf::from("workspace/path")]; // Example paths
+                    for workspace in workspaces {
+                        vendor.vendor_dependencies(&workspace).await?;
+                    }
                 }
-            }
 
-            // Run post-commands
-            if !self.config.post_commands.is_empty() {
-                let runner = CommandRunner::new(self.events.clone());
-                for dir in &self.scan_dirs {
-                    if let Err(e) = runner.run_commands(&self.config.post_commands, dir).await {
-                        warn!("Post-command failed in {}: {}", dir.display(), e);
+                // Run post-commands
+                if !self.config.post_commands.is_empty() {
+                    let runner = CommandRunner::new(self.events.clone());
+                    for dir in &self.scan_dirs {
+                        if let Err(e) = runner.run_commands(&self.config.post_commands, dir).await {
+                            warn!("Post-command failed in {}: {}", dir.display(), e);
+                        }
                     }
                 }
-            }
 
-            Ok(())
+                Ok(())
+            }
+            .instrument(apply)
+            .await
         }
     }
 
@@ -210,7 +260,13 @@ impl DependencyUpdater {
             }
         }
 
-        fs::write(crate_path, doc.to_string())?;
+        kargo_plugin_api::WriteGuard::current().write(crate_path, doc.to_string())?;
+        self.events.publish(
+            Source::Updater,
+            EventKind::FileModified {
+                path: crate_path.to_path_buf(),
+            },
+        );
         Ok(())
     }
 }
@@ -0,0 +1,73 @@
+//! Tracing setup for the `kargo` binary
+//!
+//! The filter is read from `KARGO_LOG` (falling back to `RUST_LOG`) using the
+//! usual `tracing_subscriber::EnvFilter` syntax. Records from the `log` crate,
+//! including those forwarded from native plugins, are bridged into tracing, so
+//! a noisy plugin can be silenced by its crate name (`KARGO_LOG=info,kargo_sap=off`)
+//! and host-side messages about it by `kargo::plugin::<name>`.
+
+use clap::ValueEnum;
+use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+
+use crate::cli::prescan_flag;
+
+/// Environment variables holding the log filter, in priority order
+const FILTER_ENV: [&str; 2] = ["KARGO_LOG", "RUST_LOG"];
+
+/// Filter used when no environment variable is set
+const DEFAULT_FILTER: &str = "warn";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, including the active span stack
+    Json,
+}
+
+impl LogFormat {
+    /// Pick `--log-format` out of the raw arguments.
+    ///
+    /// Logging has to be set up before plugins are discovered, which is before
+    /// the root command (and therefore clap) exists.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        prescan_flag(args, "--log-format")
+            .and_then(|value| LogFormat::from_str(&value, true).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Install the global subscriber and the `log` bridge
+pub fn init(format: LogFormat) {
+    let filter = FILTER_ENV
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .map(EnvFilter::new)
+        .unwrap_or_else(|| EnvFilter::new(DEFAULT_FILTER));
+
+    let registry = tracing_subscriber::registry().with(filter);
+    let result = match format {
+        LogFormat::Text => registry
+            .with(fmt::layer().with_writer(std::io::stderr))
+            .try_init(),
+        LogFormat::Json => registry
+            .with(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_writer(std::io::stderr),
+            )
+            .try_init(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to initialise logging: {}", e);
+    }
+}
+
+/// Log target for host-side messages about a plugin
+pub fn plugin_target(name: &str) -> String {
+    format!("kargo::plugin::{}", name)
+}
@@ -1,23 +1,192 @@
 use anyhow::Result;
-use env_logger;
 use log::info;
+use std::path::PathBuf;
+use std::time::Instant;
 
-mod cli;
-mod plugins;
-
-use cli::{build_root_cli, dispatch};
-use plugins::manager::PluginManager;
+use kargo_cli::ci::CiReporter;
+use kargo_cli::cli::{build_root_cli, dispatch, prescan_flag, with_default_command};
+use kargo_cli::config::{self, Config};
+use kargo_cli::events::{EventBus, EventLog};
+use kargo_cli::explain;
+use kargo_cli::logging::{self, LogFormat};
+use kargo_cli::metrics;
+use kargo_cli::palette;
+use kargo_cli::plugins::hooks;
+use kargo_cli::plugins::manager::PluginManager;
+use kargo_cli::secret;
+use kargo_cli::shared_target::SharedTarget;
+use kargo_cli::status::{ExitStatus, StatusReport};
+use kargo_plugin_api::{Outcome, Profiler, WriteGuard};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    logging::init(LogFormat::from_args(std::env::args().skip(1)));
     info!("Starting Kargo Flux runtime");
 
+    // The profile decides which plugins exist, so it's needed before clap runs
+    if let Some(profile) = prescan_flag(std::env::args().skip(1), "--profile") {
+        config::select_profile(profile);
+    }
+    let config = match Config::active() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            let status = ExitStatus::from_error(&e);
+            if let Some(path) = prescan_flag(std::env::args().skip(1), "--status-file") {
+                StatusReport::early_exit(status, format!("{:#}", e)).write(&PathBuf::from(path))?;
+            }
+            std::process::exit(status.code());
+        }
+    };
+    if let Some(profile) = &config.active_profile {
+        info!("Using profile {}", profile);
+    }
+
     let mut pm = PluginManager::new();
+    pm.set_wasm_permissions(config.wasm_permissions.clone());
+    if let Some(size) = config.wasm_pool_size {
+        pm.set_wasm_pool_size(size);
+    }
+    // The write guard isn't installed yet, and a read-only run mustn't leave
+    // compiled modules or saved permissions behind either
+    if std::env::args().any(|arg| arg == "--read-only") {
+        pm.set_module_cache(None);
+    } else {
+        pm.set_permissions_file(Some(Config::path()));
+    }
+    pm.set_trust_policy(config.plugin_trust.unwrap_or_default());
+    pm.set_hook_plugins(config.plugin_hooks.clone());
     pm.discover_and_load_plugins()?;
+    pm.retain(|name| config.allows_plugin(name));
 
     let app = build_root_cli(&pm);
-    let matches = app.get_matches();
+    // Bare `kargo` runs the default command if there is one, and in a
+    // terminal opens the command palette otherwise
+    let args: Vec<String> = match &config.default_command {
+        Some(default) => with_default_command(&app, std::env::args().collect(), default),
+        None if std::env::args().len() == 1 && palette::available() => match palette::run(&app)? {
+            Some(args) => args,
+            None => return Ok(()),
+        },
+        None => std::env::args().collect(),
+    };
+    let matches = match app.try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(e) => {
+            // Help and version are successful exits; anything else is a usage error
+            if !e.use_stderr() {
+                e.exit();
+            }
+            let _ = e.print();
+            if let Some(path) = prescan_flag(std::env::args().skip(1), "--status-file") {
+                let rendered = e.render().to_string();
+                let message = rendered.lines().next().unwrap_or_default();
+                StatusReport::usage_error(message.trim_start_matches("error: "))
+                    .write(&PathBuf::from(path))?;
+            }
+            std::process::exit(ExitStatus::UsageError.code());
+        }
+    };
+
+    // Installed before anything else runs, so every guarded write sees it
+    WriteGuard::new(matches.get_flag("read-only")).install();
+    let mut network = config.network.clone().unwrap_or_default();
+    network.offline |= matches.get_flag("offline")
+        || std::env::var("CARGO_NET_OFFLINE").is_ok_and(|v| v == "true" || v == "1");
+    if network.has_secret_references()
+        && let Err(e) = secret::store().and_then(|store| network.resolve_secrets(&store))
+    {
+        log::warn!("Secrets in the network config not resolved: {:#}", e);
+    }
+    network.install();
+    metrics::record_loads(
+        &pm,
+        config
+            .plugin_load_budget_ms
+            .unwrap_or(metrics::DEFAULT_LOAD_BUDGET_MS),
+    );
+
+    let ci = matches.get_flag("ci");
+    let events = EventBus::new();
+    // Kept for `kargo log`, which leaves its own runs out
+    if !WriteGuard::current().is_read_only()
+        && matches.subcommand_name() != Some("log")
+        && let Err(e) = events.persist(&EventLog::new(EventLog::default_dir()))
+    {
+        log::warn!("Events of this run not logged: {:#}", e);
+    }
+    let reporter = if ci {
+        let summary_path = matches.get_one::<PathBuf>("summary-file").cloned();
+        CiReporter::new(summary_path)
+    } else {
+        CiReporter::silent()
+    }
+    .spawn(&events);
+
+    // An empty path only asks for the summary
+    let profile_run = matches.get_one::<String>("profile-run");
+    let profiler = match profile_run {
+        Some(_) => Profiler::new(format!(
+            "kargo {}",
+            matches.subcommand_name().unwrap_or_default()
+        )),
+        None => Profiler::disabled(),
+    };
+    profiler.clone().install();
+
+    let started = Instant::now();
+    let result = match hooks::before(&pm, &matches, &events).await {
+        Ok(()) => dispatch(&mut pm, &matches, &events).await,
+        Err(e) => Err(e),
+    };
+    let outcome = Outcome {
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        elapsed: started.elapsed(),
+    };
+    hooks::after(&pm, &matches, &events, outcome).await;
+    if let Some(path) = profile_run {
+        profiler.finish();
+        eprint!("{}", profiler.summary());
+        if !path.is_empty()
+            && let Err(e) = WriteGuard::current().write(path, profiler.folded())
+        {
+            log::warn!("Profile not written: {:#}", e);
+        }
+    }
+    if !ci && let Err(e) = &result {
+        // Usage errors already end with a pointer to --help
+        if let Some(usage) = e.downcast_ref::<clap::Error>() {
+            let _ = usage.print();
+        } else {
+            eprintln!("Error: {:?}", e);
+            eprintln!(
+                "For more information, try `kargo explain {}`",
+                explain::suggestion(e)
+            );
+        }
+    }
+
+    if let Some(target) = SharedTarget::from_config(&config)
+        && !WriteGuard::current().is_read_only()
+    {
+        match target.evict() {
+            Ok(eviction) if eviction.units > 0 => info!(
+                "Evicted {} unit(s) ({:.1} MB) from the shared target directory",
+                eviction.units,
+                eviction.bytes as f64 / (1024.0 * 1024.0)
+            ),
+            Ok(_) => {}
+            Err(e) => log::warn!("Shared target directory not trimmed: {:#}", e),
+        }
+    }
 
-    dispatch(&pm, &matches).await
+    // Plugins still waiting for events see the session end
+    events.plugin_events().close();
+    // Dropping the last sender lets the reporter drain and finish
+    drop(events);
+    let report = reporter.await?.finish(&result)?;
+    if let Some(path) = matches.get_one::<PathBuf>("status-file") {
+        report.write(path)?;
+    }
+    std::process::exit(report.exit_code);
 }
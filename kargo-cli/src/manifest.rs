@@ -0,0 +1,414 @@
+//! `kargo manifest merge`: a git merge driver for `Cargo.toml`
+//!
+//! Manifests are merged as TOML rather than line by line. Every key is merged
+//! three-way against the common ancestor, so changes to different keys never
+//! conflict. Dependency entries go further: when both sides changed the same
+//! dependency, the higher version requirement wins and features are united, as
+//! long as the rest of the entry (source, `optional`, ...) agrees. The result
+//! keeps the formatting and comments of our side.
+//!
+//! Anything the semantic merge can't settle falls back to `git merge-file`,
+//! leaving the usual conflict markers. To use it for every manifest:
+//!
+//! ```text
+//! git config merge.kargo.name "kargo Cargo.toml merge"
+//! git config merge.kargo.driver "kargo manifest merge %O %A %B"
+//! echo "Cargo.toml merge=kargo" >> .gitattributes
+//! ```
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgMatches, Command};
+use kargo_plugin_api::WriteGuard;
+use kargo_plugin_api::policy::lowest_allowed;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, TableLike, Value};
+
+pub(crate) const DEPENDENCY_TABLES: [&str; 3] =
+    ["dependencies", "dev-dependencies", "build-dependencies"];
+
+pub fn command() -> Command {
+    Command::new("manifest")
+        .about("Work with Cargo.toml manifests")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("merge")
+                .about("Merge three versions of a Cargo.toml (usable as a git merge driver)")
+                .arg(
+                    Arg::new("base")
+                        .value_name("BASE")
+                        .help("Common ancestor (%O)")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("ours")
+                        .value_name("OURS")
+                        .help("Our version (%A); receives the result unless --output is given")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("theirs")
+                        .value_name("THEIRS")
+                        .help("Their version (%B)")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Write the result to FILE instead of OURS")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+}
+
+/// The result of a semantic merge
+#[derive(Debug)]
+pub struct MergeOutcome {
+    /// Our manifest with every settled change applied
+    pub merged: String,
+    /// Dotted paths of keys changed differently on both sides
+    pub conflicts: Vec<String>,
+}
+
+/// Merge `ours` and `theirs`, two descendants of `base`
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Result<MergeOutcome> {
+    let base: DocumentMut = base.parse().context("Failed to parse the base manifest")?;
+    let mut ours: DocumentMut = ours.parse().context("Failed to parse our manifest")?;
+    let theirs: DocumentMut = theirs.parse().context("Failed to parse their manifest")?;
+
+    let mut conflicts = Vec::new();
+    merge_table(
+        Some(base.as_table()),
+        ours.as_table_mut(),
+        theirs.as_table(),
+        "",
+        false,
+        &mut conflicts,
+    );
+    Ok(MergeOutcome {
+        merged: ours.to_string(),
+        conflicts,
+    })
+}
+
+fn merge_table(
+    base: Option<&dyn TableLike>,
+    ours: &mut dyn TableLike,
+    theirs: &dyn TableLike,
+    path: &str,
+    dependencies: bool,
+    conflicts: &mut Vec<String>,
+) {
+    let mut keys: Vec<String> = ours.iter().map(|(key, _)| key.to_string()).collect();
+    for (key, _) in theirs.iter() {
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+
+    for key in keys {
+        let key_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        let base_item = base.and_then(|b| b.get(&key));
+        let their_item = theirs.get(&key);
+        if dependencies {
+            merge_dependency(ours, &key, base_item, their_item, &key_path, conflicts);
+            continue;
+        }
+
+        let nested = ours
+            .get(&key)
+            .is_some_and(|item| item.is_table_like() && !item.is_inline_table());
+        if nested
+            && let Some(their_table) = their_item.and_then(Item::as_table_like)
+            && let Some(our_table) = ours.get_mut(&key).and_then(Item::as_table_like_mut)
+        {
+            merge_table(
+                base_item.and_then(Item::as_table_like),
+                our_table,
+                their_table,
+                &key_path,
+                DEPENDENCY_TABLES.contains(&key.as_str()),
+                conflicts,
+            );
+            continue;
+        }
+        merge_value(ours, &key, base_item, their_item, &key_path, conflicts);
+    }
+}
+
+/// Three-way merge of one key: a side that left it as in `base` takes the
+/// other side's change
+fn merge_value(
+    ours: &mut dyn TableLike,
+    key: &str,
+    base: Option<&Item>,
+    theirs: Option<&Item>,
+    path: &str,
+    conflicts: &mut Vec<String>,
+) {
+    let (b, o, t) = (
+        base.map(canonical),
+        ours.get(key).map(canonical),
+        theirs.map(canonical),
+    );
+    if o == t || t == b {
+        return;
+    }
+    if o == b {
+        take(ours, key, theirs);
+        return;
+    }
+    conflicts.push(path.to_string());
+}
+
+fn take(ours: &mut dyn TableLike, key: &str, theirs: Option<&Item>) {
+    match (theirs, ours.get_mut(key)) {
+        (Some(item), Some(slot)) => *slot = item.clone(),
+        (Some(item), None) => {
+            ours.insert(key, item.clone());
+        }
+        (None, _) => {
+            ours.remove(key);
+        }
+    }
+}
+
+fn merge_dependency(
+    ours: &mut dyn TableLike,
+    key: &str,
+    base: Option<&Item>,
+    theirs: Option<&Item>,
+    path: &str,
+    conflicts: &mut Vec<String>,
+) {
+    let (b, o, t) = (
+        base.map(canonical),
+        ours.get(key).map(canonical),
+        theirs.map(canonical),
+    );
+    if o == t || t == b || o == b {
+        merge_value(ours, key, base, theirs, path, conflicts);
+        return;
+    }
+
+    // Changed on both sides; removed on one of them can't be settled
+    let (Some(our_item), Some(their_item)) = (ours.get(key), theirs) else {
+        conflicts.push(path.to_string());
+        return;
+    };
+    let (our_spec, their_spec) = (Spec::of(our_item), Spec::of(their_item));
+    if our_spec.rest != their_spec.rest {
+        conflicts.push(path.to_string());
+        return;
+    }
+
+    let theirs_higher = match (&our_spec.version, &their_spec.version) {
+        (Some(our_req), Some(their_req)) => lowest_allowed(their_req) > lowest_allowed(our_req),
+        _ => false,
+    };
+    let (mut merged, chosen) = if theirs_higher {
+        (their_item.clone(), &their_spec)
+    } else {
+        (our_item.clone(), &our_spec)
+    };
+    let mut features = our_spec.features.clone();
+    for feature in &their_spec.features {
+        if !features.contains(feature) {
+            features.push(feature.clone());
+        }
+    }
+    if features != chosen.features {
+        set_features(&mut merged, &features);
+    }
+    if let Some(slot) = ours.get_mut(key) {
+        *slot = merged;
+    }
+}
+
+/// A dependency entry split into the parts merged separately
+struct Spec {
+    version: Option<String>,
+    features: Vec<String>,
+    /// Everything else, canonicalized
+    rest: String,
+}
+
+impl Spec {
+    fn of(item: &Item) -> Self {
+        if let Some(version) = item.as_str() {
+            return Self {
+                version: Some(version.to_string()),
+                features: Vec::new(),
+                rest: canonical_entries(Vec::new()),
+            };
+        }
+        let Some(table) = item.as_table_like() else {
+            return Self {
+                version: None,
+                features: Vec::new(),
+                rest: canonical(item),
+            };
+        };
+        Self {
+            version: table
+                .get("version")
+                .and_then(Item::as_str)
+                .map(str::to_string),
+            features: table
+                .get("features")
+                .and_then(Item::as_array)
+                .map(|features| {
+                    features
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            rest: canonical_entries(
+                table
+                    .iter()
+                    .filter(|(key, _)| *key != "version" && *key != "features")
+                    .map(|(key, item)| (key.to_string(), canonical(item)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn set_features(item: &mut Item, features: &[String]) {
+    let features = Array::from_iter(features.iter().map(String::as_str));
+    if let Some(version) = item.as_str() {
+        let mut table = InlineTable::new();
+        table.insert("version", version.into());
+        table.insert("features", Value::Array(features));
+        *item = Item::Value(Value::InlineTable(table));
+    } else if let Some(table) = item.as_table_like_mut() {
+        table.insert("features", toml_edit::value(features));
+    }
+}
+
+/// A representation of `item` that ignores formatting, comments and the
+/// choice between inline and standard tables
+pub(crate) fn canonical(item: &Item) -> String {
+    match item {
+        Item::None => String::new(),
+        Item::Value(value) => canonical_value(value),
+        Item::Table(table) => canonical_entries(
+            table
+                .iter()
+                .map(|(key, item)| (key.to_string(), canonical(item)))
+                .collect(),
+        ),
+        Item::ArrayOfTables(tables) => {
+            let tables: Vec<String> = tables
+                .iter()
+                .map(|table| canonical(&Item::Table(table.clone())))
+                .collect();
+            format!("[{}]", tables.join(","))
+        }
+    }
+}
+
+fn canonical_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s.value()),
+        Value::Integer(i) => i.value().to_string(),
+        Value::Float(f) => f.value().to_string(),
+        Value::Boolean(b) => b.value().to_string(),
+        Value::Datetime(d) => d.value().to_string(),
+        Value::Array(array) => {
+            let values: Vec<String> = array.iter().map(canonical_value).collect();
+            format!("[{}]", values.join(","))
+        }
+        Value::InlineTable(table) => canonical_entries(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), canonical_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn canonical_entries(mut entries: Vec<(String, String)>) -> String {
+    entries.sort();
+    let entries: Vec<String> = entries
+        .into_iter()
+        .map(|(key, value)| format!("{:?}={}", key, value))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("merge", sub)) => run_merge(sub),
+        _ => unreachable!("subcommand_required"),
+    }
+}
+
+fn run_merge(matches: &ArgMatches) -> Result<()> {
+    let path = |id: &str| -> Result<&PathBuf> {
+        matches
+            .get_one::<PathBuf>(id)
+            .with_context(|| format!("Missing {}", id))
+    };
+    let (base, ours, theirs) = (path("base")?, path("ours")?, path("theirs")?);
+    let output = matches.get_one::<PathBuf>("output").unwrap_or(ours);
+    let read = |path: &Path| {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+    };
+
+    let outcome = merge(&read(base)?, &read(ours)?, &read(theirs)?);
+    let conflicts = match outcome {
+        Ok(outcome) if outcome.conflicts.is_empty() => {
+            return WriteGuard::current().write(output, outcome.merged);
+        }
+        Ok(outcome) => outcome.conflicts,
+        Err(e) => {
+            // Usually conflict markers already in one of the sides
+            log::warn!("Semantic merge not possible: {:#}", e);
+            Vec::new()
+        }
+    };
+    for conflict in &conflicts {
+        eprintln!("✗ {}: changed differently on both sides", conflict);
+    }
+
+    let merged = std::process::Command::new("git")
+        .args([
+            "merge-file",
+            "-p",
+            "-L",
+            "ours",
+            "-L",
+            "base",
+            "-L",
+            "theirs",
+        ])
+        .args([ours, base, theirs])
+        .output()
+        .context("Failed to run git merge-file")?;
+    // git merge-file exits with the number of conflicts, negative on errors
+    if merged.status.code().is_none_or(|code| code < 0) {
+        bail!(
+            "git merge-file failed: {}",
+            String::from_utf8_lossy(&merged.stderr).trim()
+        );
+    }
+    WriteGuard::current().write(output, &merged.stdout)?;
+    if merged.status.success() {
+        return Ok(());
+    }
+    bail!(
+        "Conflicts left in {}; resolve the markers and commit",
+        output.display()
+    )
+}
@@ -0,0 +1,288 @@
+//! Per-plugin metrics: load and run times, memory and failure rates
+//!
+//! Every plugin load and every plugin run appends a [`PluginSample`] to
+//! `metrics.jsonl` in the metrics directory. `kargo plugin stats` aggregates
+//! them to point out slow and flaky plugins, and startup warns about plugins
+//! whose recent loads all exceeded the load-time budget
+//! (`plugin_load_budget_ms` in the config file).
+//!
+//! Memory is the growth of the process's peak resident set size during a run,
+//! read from `/proc/self/status`; it is only recorded on Linux. Native
+//! plugins share the kargo process, so this is what the run cost on top of
+//! everything before it.
+
+use anyhow::{Context, Result};
+use kargo_plugin_api::WriteGuard;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::plugins::manager::PluginManager;
+use crate::schedule::unix_now;
+
+/// Load budget applied when the config doesn't set one
+pub const DEFAULT_LOAD_BUDGET_MS: u64 = 1000;
+/// Consecutive loads over budget before startup warns
+pub const OVER_BUDGET_STREAK: usize = 3;
+/// Size of the log beyond which older samples are dropped
+const MAX_LOG_BYTES: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Building (for plugin projects) and loading the plugin at startup
+    Load,
+    Run,
+}
+
+/// One measured plugin load or run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginSample {
+    pub plugin: String,
+    pub phase: Phase,
+    /// Unix timestamp (seconds) of the end of the load or run
+    pub timestamp: u64,
+    pub duration_ms: u64,
+    pub success: bool,
+    /// Growth of peak resident memory, where measurable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_kb: Option<u64>,
+}
+
+impl PluginSample {
+    pub fn new(plugin: impl Into<String>, phase: Phase, duration: Duration, success: bool) -> Self {
+        Self {
+            plugin: plugin.into(),
+            phase,
+            timestamp: unix_now(),
+            duration_ms: duration.as_millis() as u64,
+            success,
+            memory_kb: None,
+        }
+    }
+}
+
+/// Aggregated samples of one plugin
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginStats {
+    pub plugin: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub mean_run_ms: u64,
+    /// 95th percentile run time
+    pub p95_run_ms: u64,
+    pub peak_memory_kb: Option<u64>,
+    pub loads: usize,
+    pub mean_load_ms: u64,
+}
+
+impl PluginStats {
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.runs as f64
+        }
+    }
+
+    /// Whether runs take longer than `threshold_ms` at the 95th percentile
+    pub fn is_slow(&self, threshold_ms: u64) -> bool {
+        self.runs > 0 && self.p95_run_ms > threshold_ms
+    }
+
+    /// Whether runs fail at least `min_rate` of the time without always failing
+    pub fn is_flaky(&self, min_rate: f64) -> bool {
+        self.failures > 0 && self.failures < self.runs && self.failure_rate() >= min_rate
+    }
+}
+
+/// The metrics log
+pub struct Metrics {
+    dir: PathBuf,
+}
+
+impl Metrics {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("metrics")
+    }
+
+    fn log_path(&self) -> PathBuf {
+        self.dir.join("metrics.jsonl")
+    }
+
+    pub fn record(&self, samples: &[PluginSample]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        let guard = WriteGuard::current();
+        guard.create_dir_all(&self.dir)?;
+        let mut lines = String::new();
+        for sample in samples {
+            lines.push_str(&serde_json::to_string(sample)?);
+            lines.push('\n');
+        }
+        guard.append(self.log_path(), lines)?;
+        self.compact()
+    }
+
+    /// Keep the newer half of the log once it outgrows [`MAX_LOG_BYTES`]
+    fn compact(&self) -> Result<()> {
+        let path = self.log_path();
+        if !std::fs::metadata(&path).is_ok_and(|m| m.len() > MAX_LOG_BYTES) {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let lines: Vec<&str> = content.lines().collect();
+        let mut kept = lines[lines.len() / 2..].join("\n");
+        kept.push('\n');
+        WriteGuard::current().write(&path, kept)
+    }
+
+    /// All recorded samples, oldest first. Unreadable lines are skipped.
+    pub fn samples(&self) -> Result<Vec<PluginSample>> {
+        let path = self.log_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Statistics per plugin, by plugin name
+pub fn summarize(samples: &[PluginSample]) -> Vec<PluginStats> {
+    let mut by_plugin: BTreeMap<&str, Vec<&PluginSample>> = BTreeMap::new();
+    for sample in samples {
+        by_plugin.entry(&sample.plugin).or_default().push(sample);
+    }
+    by_plugin
+        .into_iter()
+        .map(|(plugin, samples)| {
+            let mut runs: Vec<u64> = samples
+                .iter()
+                .filter(|s| s.phase == Phase::Run)
+                .map(|s| s.duration_ms)
+                .collect();
+            runs.sort_unstable();
+            let loads: Vec<u64> = samples
+                .iter()
+                .filter(|s| s.phase == Phase::Load)
+                .map(|s| s.duration_ms)
+                .collect();
+            PluginStats {
+                plugin: plugin.to_string(),
+                runs: runs.len(),
+                failures: samples
+                    .iter()
+                    .filter(|s| s.phase == Phase::Run && !s.success)
+                    .count(),
+                mean_run_ms: mean(&runs),
+                p95_run_ms: runs
+                    .get((runs.len() * 95).div_ceil(100).saturating_sub(1))
+                    .copied()
+                    .unwrap_or(0),
+                peak_memory_kb: samples.iter().filter_map(|s| s.memory_kb).max(),
+                loads: loads.len(),
+                mean_load_ms: mean(&loads),
+            }
+        })
+        .collect()
+}
+
+fn mean(values: &[u64]) -> u64 {
+    if values.is_empty() {
+        0
+    } else {
+        values.iter().sum::<u64>() / values.len() as u64
+    }
+}
+
+/// Plugins whose last [`OVER_BUDGET_STREAK`] loads all took longer than
+/// `budget_ms`, with the duration of the latest one
+pub fn over_budget(samples: &[PluginSample], budget_ms: u64) -> Vec<(String, u64)> {
+    let mut loads: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    for sample in samples.iter().filter(|s| s.phase == Phase::Load) {
+        loads
+            .entry(&sample.plugin)
+            .or_default()
+            .push(sample.duration_ms);
+    }
+    loads
+        .into_iter()
+        .filter(|(_, durations)| {
+            durations.len() >= OVER_BUDGET_STREAK
+                && durations
+                    .iter()
+                    .rev()
+                    .take(OVER_BUDGET_STREAK)
+                    .all(|&ms| ms > budget_ms)
+        })
+        .map(|(plugin, durations)| (plugin.to_string(), durations[durations.len() - 1]))
+        .collect()
+}
+
+/// Record how long the plugins in `pm` took to load, and warn about those
+/// that keep exceeding `budget_ms`
+pub fn record_loads(pm: &PluginManager, budget_ms: u64) {
+    let metrics = Metrics::new(Metrics::default_dir());
+    let samples: Vec<PluginSample> = pm
+        .load_times()
+        .iter()
+        .filter(|(name, _)| pm.get(name).is_some())
+        .map(|(name, duration)| PluginSample::new(name, Phase::Load, *duration, true))
+        .collect();
+    if samples.is_empty() {
+        return;
+    }
+    if let Err(e) = metrics.record(&samples) {
+        log::debug!("Plugin metrics not recorded: {:#}", e);
+        return;
+    }
+    let Ok(history) = metrics.samples() else {
+        return;
+    };
+    for (plugin, latest) in over_budget(&history, budget_ms) {
+        if samples.iter().any(|s| s.plugin == plugin) {
+            log::warn!(
+                "Plugin {} exceeded its {} load budget on each of its last {} loads (latest {}); \
+                 see `kargo plugin stats`",
+                plugin,
+                format_ms(budget_ms),
+                OVER_BUDGET_STREAK,
+                format_ms(latest)
+            );
+        }
+    }
+}
+
+/// Peak resident set size of this process in KiB; `None` where it can't be
+/// read
+pub fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+/// `1.2s` or `340ms`
+pub fn format_ms(ms: u64) -> String {
+    if ms >= 1000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}ms", ms)
+    }
+}
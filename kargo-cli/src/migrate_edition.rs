@@ -0,0 +1,541 @@
+//! `kargo migrate-edition`: move every project below the roots to a new edition
+//!
+//! Projects are found like `kargo fleet` finds them. `cargo fix --edition`
+//! only moves code one edition at a time, so a project is migrated in steps:
+//! for each edition up to the target, the packages still on the one before
+//! are fixed, then their `edition` fields (and `[workspace.package]`'s) are
+//! bumped. A final `cargo check` tells the projects that migrated cleanly from
+//! those that need manual work. With `--jobs`, several projects migrate at
+//! once.
+//!
+//! Every file of a project that's migrated is backed up first, all in one
+//! snapshot for the run (see [`crate::backup`]). With `--rollback`, projects
+//! that need manual work are restored as soon as the run is over; otherwise
+//! `kargo restore` puts them back.
+//!
+//! What `cargo` printed for each project is saved to the project's
+//! `.kargo/runs/` (see [`kargo_plugin_api::artifacts`]) and listed in the
+//! report, since only its first errors make it into the details.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use futures::stream::{self, StreamExt};
+use jwalk::WalkDir;
+use kargo_plugin_api::WriteGuard;
+use kargo_plugin_api::artifacts::{CapturedOutput, RunArtifacts};
+use kargo_plugin_api::cargo_runner::{CargoCommand, CargoRunner, ProcessRunner};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use toml_edit::{DocumentMut, Item, value};
+
+use crate::backup::{BackupManager, PathFilter};
+use crate::events::EventBus;
+use crate::fleet::discover_projects;
+
+/// Editions in order; a manifest without one is on the first
+pub const EDITIONS: &[&str] = &["2015", "2018", "2021", "2024"];
+
+/// Lines of `cargo` errors kept per project
+const MAX_DETAILS: usize = 20;
+
+pub fn command() -> Command {
+    Command::new("migrate-edition")
+        .about("Move every project below the given roots to a new Rust edition")
+        .arg(
+            Arg::new("edition")
+                .value_name("EDITION")
+                .help("Edition to migrate to")
+                .value_parser(EDITIONS[1..].to_vec())
+                .required(true),
+        )
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .value_name("DIR")
+                .help("Directory to search for projects (repeatable; defaults to the current directory)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .short('j')
+                .value_name("N")
+                .help("Projects to migrate at once")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("rollback")
+                .long("rollback")
+                .help("Restore the projects that need manual work to how they were")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("List the packages that would be migrated without changing anything")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .help("Write the outcome of every project as JSON")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+}
+
+/// An `edition` field on an older edition than the target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EditionField {
+    pub manifest: PathBuf,
+    /// Package the field belongs to; `None` for `[workspace.package]`
+    pub package: Option<String>,
+    pub edition: String,
+    /// `edition.workspace = true`: the package follows `[workspace.package]`
+    /// and its manifest isn't written
+    pub inherited: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MigrationStatus {
+    /// Every package was already on the edition
+    UpToDate,
+    Migrated,
+    /// `cargo fix` or `cargo check` failed; the details say where
+    NeedsWork,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectMigration {
+    pub project: PathBuf,
+    /// Editions the project's packages were on, oldest first
+    pub from: Vec<String>,
+    pub status: MigrationStatus,
+    /// Errors from `cargo`, when the project needs manual work
+    pub details: Vec<String>,
+    /// Restored from the backup after failing
+    pub rolled_back: bool,
+    /// Where the output of every `cargo` run on the project was saved
+    pub outputs: Vec<CapturedOutput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub edition: String,
+    /// Snapshot the migrated projects were backed up to
+    pub backup: Option<String>,
+    pub projects: Vec<ProjectMigration>,
+}
+
+pub async fn run(matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    let edition = matches
+        .get_one::<String>("edition")
+        .expect("required")
+        .clone();
+    let roots: Vec<PathBuf> = match matches.get_many::<PathBuf>("root") {
+        Some(roots) => roots.cloned().collect(),
+        None => vec![std::env::current_dir()?],
+    };
+    let dry_run = matches.get_flag("dry-run");
+    if !dry_run && WriteGuard::current().is_read_only() {
+        bail!("migrate-edition rewrites sources and manifests, and kargo is read-only");
+    }
+
+    let mut projects = Vec::new();
+    let mut up_to_date = Vec::new();
+    for project in discover_projects(&roots) {
+        let fields = behind(&project, &edition)
+            .with_context(|| format!("Failed to read the editions of {}", project.display()))?;
+        if fields.is_empty() {
+            up_to_date.push(project);
+        } else {
+            projects.push((project, fields));
+        }
+    }
+
+    if dry_run {
+        for (project, behind) in &projects {
+            println!("{}: {}", project.display(), editions(behind).join(", "));
+            for field in behind {
+                let what = match &field.package {
+                    Some(name) if field.inherited => format!("{} (inherited)", name),
+                    Some(name) => name.clone(),
+                    None => "[workspace.package]".to_string(),
+                };
+                println!("    {} {} -> {}", what, field.edition, edition);
+            }
+        }
+        println!(
+            "Dry run: {} project(s) to migrate to {}, {} already on it",
+            projects.len(),
+            edition,
+            up_to_date.len()
+        );
+        return Ok(());
+    }
+
+    // Backed up one after another: the snapshot is shared
+    let mut backup = BackupManager::new("migrate-edition", events.clone())?;
+    for (project, _) in &projects {
+        for file in project_files(project) {
+            backup.backup_file(&file)?;
+        }
+    }
+
+    let jobs = *matches.get_one::<u64>("jobs").expect("has default") as usize;
+    let runner: Arc<dyn CargoRunner> = Arc::new(ProcessRunner::default());
+    let mut migrations: Vec<ProjectMigration> = stream::iter(projects)
+        .map(|(project, behind)| migrate(project, behind, &edition, &runner))
+        .buffered(jobs)
+        .collect()
+        .await;
+
+    if matches.get_flag("rollback") {
+        for migration in &mut migrations {
+            if migration.status != MigrationStatus::NeedsWork {
+                continue;
+            }
+            let dir = migration
+                .project
+                .canonicalize()
+                .unwrap_or_else(|_| migration.project.clone());
+            let filter = PathFilter::new([format!("{}/**", dir.display())])?;
+            match backup.restore_matching(&filter) {
+                Ok(_) => migration.rolled_back = true,
+                Err(e) => migration.details.push(format!("Rollback failed: {:#}", e)),
+            }
+        }
+    }
+    migrations.extend(up_to_date.into_iter().map(|project| ProjectMigration {
+        project,
+        from: Vec::new(),
+        status: MigrationStatus::UpToDate,
+        details: Vec::new(),
+        rolled_back: false,
+        outputs: Vec::new(),
+    }));
+
+    let report = MigrationReport {
+        edition,
+        backup: (!backup.changes().is_empty()).then(|| backup.id().to_string()),
+        projects: migrations,
+    };
+    print_report(&report);
+    if let Some(path) = matches.get_one::<PathBuf>("report") {
+        WriteGuard::current().write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+
+    let failed: Vec<&ProjectMigration> = report
+        .projects
+        .iter()
+        .filter(|p| p.status == MigrationStatus::NeedsWork)
+        .collect();
+    if !failed.is_empty() {
+        let hint = match &report.backup {
+            Some(id) if failed.iter().any(|p| !p.rolled_back) => {
+                format!("; `kargo restore --snapshot {}` puts them back", id)
+            }
+            _ => String::new(),
+        };
+        bail!(
+            "{} of {} project(s) need manual work{}",
+            failed.len(),
+            report.projects.len(),
+            hint
+        );
+    }
+    Ok(())
+}
+
+/// The `edition` fields of the project in `dir` on an older edition than
+/// `target`, the root manifest's first. A workspace's members count as part
+/// of it.
+pub fn behind(dir: &Path, target: &str) -> Result<Vec<EditionField>> {
+    let target = edition_index(target).with_context(|| format!("Unknown edition {}", target))?;
+    let root = dir.join("Cargo.toml");
+    let root_doc = read_manifest(&root)?;
+    let workspace_edition = root_doc
+        .get("workspace")
+        .and_then(|ws| ws.get("package"))
+        .and_then(|package| package.get("edition"))
+        .and_then(Item::as_str)
+        .map(str::to_string);
+
+    let mut manifests = vec![(root, root_doc)];
+    if manifests[0].1.contains_key("workspace") {
+        for member in project_files(dir) {
+            if member.file_name().is_some_and(|name| name == "Cargo.toml")
+                && member != manifests[0].0
+            {
+                let doc = read_manifest(&member)?;
+                manifests.push((member, doc));
+            }
+        }
+    }
+
+    let mut fields = Vec::new();
+    for (manifest, doc) in &manifests {
+        if let Some(edition) = &workspace_edition
+            && manifest == &manifests[0].0
+        {
+            fields.push(EditionField {
+                manifest: manifest.clone(),
+                package: None,
+                edition: edition.clone(),
+                inherited: false,
+            });
+        }
+        let Some(package) = doc.get("package") else {
+            continue;
+        };
+        let name = package
+            .get("name")
+            .and_then(Item::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let (edition, inherited) = match package.get("edition") {
+            None => (EDITIONS[0].to_string(), false),
+            Some(item) => match item.as_str() {
+                Some(edition) => (edition.to_string(), false),
+                // `edition.workspace = true`
+                None => match &workspace_edition {
+                    Some(edition) => (edition.clone(), true),
+                    None => continue,
+                },
+            },
+        };
+        fields.push(EditionField {
+            manifest: manifest.clone(),
+            package: Some(name),
+            edition,
+            inherited,
+        });
+    }
+    fields.retain(|field| edition_index(&field.edition).is_some_and(|index| index < target));
+    Ok(fields)
+}
+
+/// Set the edition of `field` in its manifest
+pub fn set_edition(field: &EditionField, edition: &str) -> Result<()> {
+    if field.inherited {
+        return Ok(());
+    }
+    let mut doc = read_manifest(&field.manifest)?;
+    let table = match field.package {
+        Some(_) => doc.get_mut("package"),
+        None => doc
+            .get_mut("workspace")
+            .and_then(|ws| ws.get_mut("package")),
+    }
+    .with_context(|| format!("{} lost its edition", field.manifest.display()))?;
+    table["edition"] = value(edition);
+    WriteGuard::current().write(&field.manifest, doc.to_string())
+}
+
+pub fn edition_index(edition: &str) -> Option<usize> {
+    EDITIONS.iter().position(|e| *e == edition)
+}
+
+/// Migrate the project in `dir`, whose `behind` fields are older than
+/// `target`, one edition at a time, running cargo through `runner`
+pub async fn migrate(
+    dir: PathBuf,
+    mut behind: Vec<EditionField>,
+    target: &str,
+    runner: &Arc<dyn CargoRunner>,
+) -> ProjectMigration {
+    let mut migration = ProjectMigration {
+        from: editions(&behind),
+        project: dir,
+        status: MigrationStatus::Migrated,
+        details: Vec::new(),
+        rolled_back: false,
+        outputs: Vec::new(),
+    };
+    let manifest = migration.project.join("Cargo.toml");
+    let first = behind
+        .iter()
+        .filter_map(|field| edition_index(&field.edition))
+        .min()
+        .unwrap_or_default();
+    let last = edition_index(target).unwrap_or_default();
+
+    for edition in &EDITIONS[first + 1..=last] {
+        let step = edition_index(edition).unwrap_or_default();
+        let moving: Vec<&mut EditionField> = behind
+            .iter_mut()
+            .filter(|field| edition_index(&field.edition).is_some_and(|index| index < step))
+            .collect();
+        let mut fix = vec![
+            "fix",
+            "--edition",
+            "--allow-dirty",
+            "--allow-staged",
+            "--all-targets",
+        ];
+        let packages: Vec<String> = moving.iter().filter_map(|f| f.package.clone()).collect();
+        for package in &packages {
+            fix.extend(["-p", package.as_str()]);
+        }
+        if !packages.is_empty()
+            && let Err(details) = cargo(runner, &manifest, &fix, &mut migration.outputs).await
+        {
+            log::warn!(
+                "cargo fix --edition failed in {} on the way to {}",
+                migration.project.display(),
+                edition
+            );
+            migration.status = MigrationStatus::NeedsWork;
+            migration.details = details;
+            return migration;
+        }
+        for field in moving {
+            if let Err(e) = set_edition(field, edition) {
+                migration.status = MigrationStatus::NeedsWork;
+                migration.details = vec![format!("{:#}", e)];
+                return migration;
+            }
+            field.edition = edition.to_string();
+        }
+    }
+
+    if let Err(details) = cargo(
+        runner,
+        &manifest,
+        &["check", "--all-targets"],
+        &mut migration.outputs,
+    )
+    .await
+    {
+        migration.status = MigrationStatus::NeedsWork;
+        migration.details = details;
+    }
+    migration
+}
+
+/// Run `cargo <args>` on the project of `manifest` through `runner`, saving
+/// its output to `outputs`; the errors it printed when it fails
+async fn cargo(
+    runner: &Arc<dyn CargoRunner>,
+    manifest: &Path,
+    args: &[&str],
+    outputs: &mut Vec<CapturedOutput>,
+) -> Result<(), Vec<String>> {
+    let command = CargoCommand::new(args[0])
+        .args(args[1..].iter().copied())
+        .arg("--manifest-path")
+        .arg(manifest.to_string_lossy());
+    let runner = Arc::clone(runner);
+    let (command, output) = tokio::task::spawn_blocking(move || {
+        let output = runner.run(&command);
+        (command, output)
+    })
+    .await
+    .map_err(|e| vec![format!("Failed to run cargo {}: {}", args[0], e)])?;
+    let output = output.map_err(|e| vec![format!("{:#}", e)])?;
+    let project = manifest.parent().unwrap_or(Path::new("."));
+    match RunArtifacts::for_project(project).capture_cargo(&command, &output) {
+        Ok(captured) => outputs.push(captured),
+        Err(e) => log::warn!("{:#}", e),
+    }
+    if output.success() {
+        return Ok(());
+    }
+    let mut errors: Vec<String> = output
+        .stderr
+        .lines()
+        .filter(|line| line.starts_with("error") || line.trim_start().starts_with("-->"))
+        .map(|line| line.trim().to_string())
+        .take(MAX_DETAILS)
+        .collect();
+    if errors.is_empty() {
+        let status = output
+            .status
+            .map_or_else(|| "killed".to_string(), |status| status.to_string());
+        errors.push(format!("cargo {} failed ({})", args[0], status));
+    }
+    Err(errors)
+}
+
+/// Distinct editions of `fields`, oldest first
+fn editions(fields: &[EditionField]) -> Vec<String> {
+    let mut editions: Vec<String> = fields.iter().map(|f| f.edition.clone()).collect();
+    editions.sort_by_key(|edition| edition_index(edition));
+    editions.dedup();
+    editions
+}
+
+/// Sources, manifests and the lockfile of the project in `dir`: what
+/// `cargo fix` and the edition bump may rewrite
+fn project_files(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path())
+        .filter(|path| {
+            !path
+                .strip_prefix(dir)
+                .unwrap_or(path)
+                .components()
+                .any(|c| c.as_os_str() == "target")
+        })
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "rs")
+                || path
+                    .file_name()
+                    .is_some_and(|name| name == "Cargo.toml" || name == "Cargo.lock")
+        })
+        .collect()
+}
+
+fn read_manifest(path: &Path) -> Result<DocumentMut> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .parse()
+        .with_context(|| format!("Invalid manifest {}", path.display()))
+}
+
+fn print_report(report: &MigrationReport) {
+    let mut counts = [0; 3];
+    for project in &report.projects {
+        match project.status {
+            MigrationStatus::UpToDate => counts[0] += 1,
+            MigrationStatus::Migrated => {
+                counts[1] += 1;
+                println!(
+                    "✓ {}: {} -> {}",
+                    project.project.display(),
+                    project.from.join(", "),
+                    report.edition
+                );
+            }
+            MigrationStatus::NeedsWork => {
+                counts[2] += 1;
+                let note = if project.rolled_back {
+                    " (rolled back)"
+                } else {
+                    ""
+                };
+                eprintln!("✗ {}: needs manual work{}", project.project.display(), note);
+                for detail in &project.details {
+                    eprintln!("    {}", detail);
+                }
+                if let Some(output) = project.outputs.last() {
+                    eprintln!("    Full output: {}", output.stderr.display());
+                }
+            }
+        }
+    }
+    println!(
+        "Edition {}: {} project(s) migrated cleanly, {} need manual work, {} already on it",
+        report.edition, counts[1], counts[2], counts[0]
+    );
+    if let Some(id) = &report.backup {
+        println!("Backed up as {}", id);
+    }
+}
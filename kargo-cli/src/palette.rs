@@ -0,0 +1,315 @@
+//! Interactive command palette for `kargo` run without arguments
+//!
+//! Lists every command, built-in and plugin, and narrows the list as the user
+//! types. Once a command is picked, its arguments are asked for one by one
+//! from its clap definition. The answers become an ordinary argument vector
+//! that is parsed and dispatched exactly like one typed on the command line,
+//! which is echoed so users learn the direct form.
+
+use anyhow::Result;
+use clap::{Arg, ArgAction, Command};
+use console::{Key, Term, style};
+use std::io::IsTerminal;
+
+/// Commands shown at once; the list scrolls to keep the selection visible
+const VISIBLE: usize = 10;
+
+/// A command offered by the palette
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub about: String,
+}
+
+impl Entry {
+    /// How well `query` matches: fuzzily against the name, literally against
+    /// the description. Name matches rank first.
+    pub fn score(&self, query: &str) -> Option<i64> {
+        let query = query.trim();
+        let described = self.about.to_lowercase().contains(&query.to_lowercase());
+        fuzzy_score(query, &self.name)
+            .map(|score| score + 100)
+            .or(described.then_some(0))
+    }
+}
+
+/// Whether the palette can run: both ends of the conversation are a terminal
+pub fn available() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// The visible subcommands of `command`, in declaration order
+pub fn entries(command: &Command) -> Vec<Entry> {
+    command
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+        .map(|sub| Entry {
+            name: sub.get_name().to_string(),
+            about: sub.get_about().map(|a| a.to_string()).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Score of `query` as a case-insensitive subsequence of `text`; `None` if it
+/// isn't one. Consecutive characters and characters starting a word score
+/// higher, gaps lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0i64;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + text[next..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next).min(10) as i64;
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// Entries matching `query`, best first; ties keep their order
+pub fn filter<'a>(entries: &'a [Entry], query: &str) -> Vec<&'a Entry> {
+    let mut matches: Vec<(i64, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| entry.score(query).map(|score| (score, entry)))
+        .collect();
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    matches.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Run the palette over the subcommands of `root`. Returns the arguments to
+/// parse, starting with the binary name, or `None` if the user cancelled.
+pub fn run(root: &Command) -> Result<Option<Vec<String>>> {
+    let term = Term::stderr();
+    let mut args = vec![root.get_name().to_string()];
+    let mut command = root;
+    loop {
+        let Some(name) = select(&term, &entries(command))? else {
+            return Ok(None);
+        };
+        command = command
+            .find_subcommand(&name)
+            .expect("palette entries are subcommands");
+        args.push(name);
+        args.extend(arguments(command, &mut |prompt| {
+            term.write_str(&format!("{} ", prompt))?;
+            Ok(term.read_line()?)
+        })?);
+        if !command.get_subcommands().any(|sub| !sub.is_hide_set()) {
+            break;
+        }
+    }
+    term.write_line(&format!("{} {}", style("→").cyan(), args.join(" ")))?;
+    Ok(Some(args))
+}
+
+/// Let the user pick one of `entries` by typing to filter it
+fn select(term: &Term, entries: &[Entry]) -> Result<Option<String>> {
+    let width = entries.iter().map(|e| e.name.len()).max().unwrap_or(0);
+    let columns = usize::from(term.size().1);
+    let mut query = String::new();
+    let mut selected = 0;
+    let mut drawn = 0;
+    term.hide_cursor()?;
+    let picked = loop {
+        let matches = filter(entries, &query);
+        selected = selected.min(matches.len().saturating_sub(1));
+        let first = selected.saturating_sub(VISIBLE - 1);
+
+        term.clear_last_lines(drawn)?;
+        let mut lines = vec![format!(
+            "{} {}{}",
+            style("kargo ›").bold(),
+            query,
+            style("  (type to filter, ↑/↓ to move, Enter to pick, Esc to cancel)").dim()
+        )];
+        if matches.is_empty() {
+            lines.push(style("  no matching command").dim().to_string());
+        }
+        for (index, entry) in matches.iter().enumerate().skip(first).take(VISIBLE) {
+            let line = format!("{:width$}  {}", entry.name, entry.about);
+            let line = console::truncate_str(&line, columns.saturating_sub(4), "…");
+            lines.push(if index == selected {
+                format!("{} {}", style("›").cyan(), style(line).cyan().bold())
+            } else {
+                format!("  {}", line)
+            });
+        }
+        for line in &lines {
+            term.write_line(line)?;
+        }
+        drawn = lines.len();
+
+        match term.read_key()? {
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                selected = 0;
+            }
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::ArrowUp | Key::BackTab => selected = selected.saturating_sub(1),
+            Key::ArrowDown | Key::Tab => selected += 1,
+            Key::Enter if !matches.is_empty() => break Some(matches[selected].name.clone()),
+            Key::Escape | Key::CtrlC => break None,
+            _ => {}
+        }
+    };
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    Ok(picked)
+}
+
+/// Ask for the arguments of `command`, options first, and return them as
+/// command-line words. `ask` shows a prompt and returns the user's answer;
+/// empty answers leave optional arguments out.
+pub fn arguments(
+    command: &Command,
+    ask: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let args = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && !arg.is_global_set() && takes_input(arg));
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) = args.partition(|arg| arg.is_positional());
+
+    for arg in options {
+        let flag = match (arg.get_long(), arg.get_short()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => continue,
+        };
+        if !arg.get_action().takes_values() {
+            let answer = ask(&format!("{} [y/N]{}", flag, describe(arg)))?;
+            if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                words.push(flag);
+            }
+            continue;
+        }
+        let values = answer_for(arg, &format!("{} <{}>", flag, value_name(arg)), ask)?;
+        if matches!(arg.get_action(), ArgAction::Append) {
+            for value in values {
+                words.push(flag.clone());
+                words.push(value);
+            }
+        } else if !values.is_empty() {
+            words.push(flag);
+            words.extend(values);
+        }
+    }
+
+    let mut values = Vec::new();
+    for arg in positionals {
+        values.extend(answer_for(arg, &format!("<{}>", value_name(arg)), ask)?);
+    }
+    // Values that look like flags must not be taken for one
+    if values.iter().any(|value| value.starts_with('-')) {
+        words.push("--".to_string());
+    }
+    words.extend(values);
+
+    if command.is_allow_external_subcommands_set() && command.get_positionals().next().is_none() {
+        words.extend(split_words(&ask("Arguments:")?));
+    }
+    Ok(words)
+}
+
+fn takes_input(arg: &Arg) -> bool {
+    !matches!(
+        arg.get_action(),
+        ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version
+    )
+}
+
+/// Ask for the values of `arg` until a required one gets an answer
+fn answer_for(
+    arg: &Arg,
+    label: &str,
+    ask: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<Vec<String>> {
+    let required = if arg.is_required_set() {
+        " (required)"
+    } else {
+        ""
+    };
+    let prompt = format!("{}{}{}", label, required, describe(arg));
+    loop {
+        let answer = ask(&prompt)?;
+        let values = if arg.get_num_args().is_some_and(|n| n.max_values() > 1)
+            || matches!(arg.get_action(), ArgAction::Append)
+        {
+            split_words(&answer)
+        } else {
+            Some(answer.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .into_iter()
+                .collect()
+        };
+        if !values.is_empty() || !arg.is_required_set() {
+            return Ok(values);
+        }
+    }
+}
+
+fn value_name(arg: &Arg) -> String {
+    arg.get_value_names()
+        .and_then(|names| names.first())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| arg.get_id().to_string().to_uppercase())
+}
+
+/// The help, possible values and default of `arg`, for its prompt
+fn describe(arg: &Arg) -> String {
+    let mut text = String::new();
+    if let Some(help) = arg.get_help() {
+        text.push_str(&format!(" {}", style(format!("— {}", help)).dim()));
+    }
+    let possible: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !possible.is_empty() {
+        text.push_str(&format!(" [{}]", possible.join(", ")));
+    }
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().into_owned())
+        .collect();
+    if !defaults.is_empty() {
+        text.push_str(&format!(" (default: {})", defaults.join(" ")));
+    }
+    text.push(':');
+    text
+}
+
+/// Split an answer into words, honouring single and double quotes
+pub fn split_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in input.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
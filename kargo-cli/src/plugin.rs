@@ -0,0 +1,336 @@
+//! `kargo plugin`: information about the installed plugins
+//!
+//! `kargo plugin watch` is the plugin development loop: it runs a plugin,
+//! then reloads it whenever its library or WASM module is rebuilt and runs
+//! it again, without restarting kargo.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgMatches, Command};
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cli::{publish_diagnostics, run_plugin};
+use crate::config::Config;
+use crate::events::EventBus;
+use crate::metrics::{self, Metrics, format_ms};
+use crate::plugins::manager::PluginManager;
+use crate::plugins::module_cache::ModuleCache;
+use crate::plugins::trust::{self, Decision, TrustStore};
+use crate::plugins::watcher::SourceWatcher;
+
+pub fn command() -> Command {
+    Command::new("plugin")
+        .about("Inspect installed plugins")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("stats")
+                .about("Show load and run times, memory and failure rates per plugin")
+                .arg(
+                    Arg::new("slow")
+                        .long("slow")
+                        .value_name("MS")
+                        .help("Flag plugins whose 95th percentile run takes longer than this")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("10000"),
+                )
+                .arg(
+                    Arg::new("flaky")
+                        .long("flaky")
+                        .value_name("RATE")
+                        .help("Flag plugins that fail at least this fraction of their runs, but not all")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0.1"),
+                ),
+        )
+        .subcommand(
+            Command::new("trust")
+                .about("Allow a plugin found in a plugin directory to load, or list decisions")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Plugin library, WASM module or project directory")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("deny")
+                        .long("deny")
+                        .help("Record that the plugin must not load")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("path"),
+                ),
+        )
+        .subcommand(
+            Command::new("untrust")
+                .about("Forget the decision about a plugin, so kargo asks again")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("clear-cache")
+                .about("Delete compiled WASM modules; plugins compile again on their next load"),
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Run a plugin, then reload and run it again whenever it is rebuilt")
+                .arg(
+                    Arg::new("plugin")
+                        .value_name("PLUGIN")
+                        .required(true)
+                        .help("Loaded plugin to watch"),
+                )
+                .arg(
+                    Arg::new("args")
+                        .value_name("ARGS")
+                        .help("Arguments for each run of the plugin")
+                        .num_args(0..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::new("settle")
+                        .long("settle")
+                        .value_name("MS")
+                        .help("How long a changed plugin file must stay unchanged before it is reloaded")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("200"),
+                ),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("stats", sub)) => stats(sub),
+        Some(("trust", sub)) => trust(sub),
+        Some(("untrust", sub)) => untrust(sub),
+        Some(("clear-cache", _)) => clear_cache(),
+        // Needs the plugin manager; see `watch`
+        _ => unreachable!("subcommand_required"),
+    }
+}
+
+/// `kargo plugin watch`: run the plugin on every reload until Ctrl-C
+pub async fn watch(pm: &mut PluginManager, matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    let name = matches.get_one::<String>("plugin").expect("required");
+    let settle = Duration::from_millis(*matches.get_one::<u64>("settle").expect("has default"));
+    let mut args = vec![name.clone()];
+    args.extend(
+        matches
+            .get_many::<String>("args")
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+
+    if pm.get(name).is_none() {
+        bail!("Plugin {} is not loaded", name);
+    }
+    let Some(source) = pm.source(name).map(|path| path.to_path_buf()) else {
+        bail!(
+            "Plugin {} was not loaded from a file; nothing to watch",
+            name
+        );
+    };
+    let mut watcher = SourceWatcher::new(pm.sources())?;
+    println!("Watching {} (Ctrl-C to stop)", source.display());
+
+    let mut run = true;
+    loop {
+        if run && let Some(plugin) = pm.get(name) {
+            let (result, diagnostics) = run_plugin(
+                name,
+                plugin.as_ref(),
+                args.clone(),
+                env::current_dir()?,
+                events,
+                None,
+            )
+            .await;
+            publish_diagnostics(name, diagnostics, events).await;
+            if let Err(e) = result {
+                eprintln!("✗ {} failed: {:#}", name, e);
+            }
+        }
+
+        tokio::select! {
+            changed = watcher.changed(settle) => changed?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        run = false;
+        for reload in pm.reload_changed() {
+            match reload.result {
+                Ok(()) => {
+                    println!(
+                        "↻ Reloaded {} from {}",
+                        reload.plugin,
+                        reload.path.display()
+                    );
+                    run |= reload.plugin == *name;
+                }
+                Err(e) => eprintln!(
+                    "✗ {} not reloaded, keeping the previous version: {:#}",
+                    reload.plugin, e
+                ),
+            }
+        }
+        if run && pm.get(name).is_none() {
+            bail!(
+                "{} no longer provides the {} command",
+                source.display(),
+                name
+            );
+        }
+    }
+}
+
+fn stats(matches: &ArgMatches) -> Result<()> {
+    let slow = *matches.get_one::<u64>("slow").expect("has default");
+    let flaky = *matches.get_one::<f64>("flaky").expect("has default");
+    let budget = Config::active()
+        .ok()
+        .and_then(|config| config.plugin_load_budget_ms)
+        .unwrap_or(metrics::DEFAULT_LOAD_BUDGET_MS);
+
+    let stats = metrics::summarize(&Metrics::new(Metrics::default_dir()).samples()?);
+    if stats.is_empty() {
+        println!("No plugin runs recorded yet");
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:>6} {:>10} {:>8} {:>8} {:>10} {:>6} {:>10}",
+        "PLUGIN", "RUNS", "FAILED", "MEAN", "P95", "PEAK MEM", "LOADS", "MEAN LOAD"
+    );
+    for plugin in &stats {
+        println!(
+            "{:<16} {:>6} {:>10} {:>8} {:>8} {:>10} {:>6} {:>10}",
+            plugin.plugin,
+            plugin.runs,
+            format!(
+                "{} ({:.0}%)",
+                plugin.failures,
+                plugin.failure_rate() * 100.0
+            ),
+            format_ms(plugin.mean_run_ms),
+            format_ms(plugin.p95_run_ms),
+            plugin
+                .peak_memory_kb
+                .map(|kb| format!("{:.1} MB", kb as f64 / 1024.0))
+                .unwrap_or_else(|| "-".to_string()),
+            plugin.loads,
+            format_ms(plugin.mean_load_ms),
+        );
+    }
+
+    let mut findings = Vec::new();
+    for plugin in &stats {
+        if plugin.is_slow(slow) {
+            findings.push(format!(
+                "{} is slow: 95% of runs finish within {}, above {}",
+                plugin.plugin,
+                format_ms(plugin.p95_run_ms),
+                format_ms(slow)
+            ));
+        }
+        if plugin.is_flaky(flaky) {
+            findings.push(format!(
+                "{} is flaky: {} of {} runs failed",
+                plugin.plugin, plugin.failures, plugin.runs
+            ));
+        }
+        if plugin.loads > 0 && plugin.mean_load_ms > budget {
+            findings.push(format!(
+                "{} is slow to load: {} on average, over the {} budget",
+                plugin.plugin,
+                format_ms(plugin.mean_load_ms),
+                format_ms(budget)
+            ));
+        }
+    }
+    if !findings.is_empty() {
+        println!();
+        for finding in findings {
+            println!("⚠ {}", finding);
+        }
+    }
+    Ok(())
+}
+
+fn trust(matches: &ArgMatches) -> Result<()> {
+    let mut store = TrustStore::load(TrustStore::default_path())?;
+    let Some(path) = matches.get_one::<PathBuf>("path") else {
+        if store.records().is_empty() {
+            println!("No plugin trust decisions recorded yet");
+        }
+        for (path, record) in store.records() {
+            println!(
+                "{:<6} {}{}",
+                match record.decision {
+                    Decision::Allow => "allow",
+                    Decision::Deny => "deny",
+                },
+                path.display(),
+                record
+                    .sha256
+                    .as_deref()
+                    .map(|sha| format!(" ({})", sha))
+                    .unwrap_or_default()
+            );
+        }
+        return Ok(());
+    };
+
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("No plugin at {}", path.display()))?;
+    let digest = Some(trust::plugin_digest(&path)?);
+    let decision = if matches.get_flag("deny") {
+        Decision::Deny
+    } else {
+        Decision::Allow
+    };
+    store.record(&path, digest, decision);
+    store.save()?;
+    println!(
+        "{} {}",
+        match decision {
+            Decision::Allow => "Trusted",
+            Decision::Deny => "Denied",
+        },
+        path.display()
+    );
+    Ok(())
+}
+
+fn untrust(matches: &ArgMatches) -> Result<()> {
+    let path = matches.get_one::<PathBuf>("path").expect("required");
+    // The plugin may be gone already
+    let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+    let mut store = TrustStore::load(TrustStore::default_path())?;
+    if store.forget(&path) {
+        store.save()?;
+        println!(
+            "Forgot {}; kargo will ask before loading it again",
+            path.display()
+        );
+    } else {
+        println!("No decision recorded for {}", path.display());
+    }
+    Ok(())
+}
+
+fn clear_cache() -> Result<()> {
+    let cache = ModuleCache::new(ModuleCache::default_dir());
+    let size = cache.size();
+    cache.clear()?;
+    println!(
+        "Removed {:.1} MiB of compiled modules from {}",
+        size as f64 / (1024.0 * 1024.0),
+        cache.dir().display()
+    );
+    Ok(())
+}
@@ -0,0 +1,233 @@
+//! The command line WASM plugins declare, and its conversion to clap
+//!
+//! WASM plugins can't hand the host a `clap::Command`, so they describe their
+//! command as JSON from `_kargo_plugin_get_command_spec_json`. These types
+//! mirror `CommandDefinition` and friends in `kargo-plugin-wasm`; fields added
+//! after the first release are optional, so older plugins still load.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, ArgGroup, Command};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub about: String,
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+    #[serde(default)]
+    pub subcommands: Vec<CommandSpec>,
+    #[serde(default)]
+    pub groups: Vec<GroupSpec>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub subcommand_required: bool,
+    #[serde(default)]
+    pub arg_required_else_help: bool,
+}
+
+/// An argument; without `short` and `long` it's positional
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArgSpec {
+    pub name: String,
+    #[serde(default)]
+    pub short: Option<char>,
+    #[serde(default)]
+    pub long: Option<String>,
+    #[serde(default)]
+    pub help: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub takes_value: bool,
+    #[serde(default)]
+    pub multiple: bool,
+    #[serde(default)]
+    pub last: bool,
+    #[serde(default)]
+    pub value_name: Option<String>,
+    #[serde(default)]
+    pub value_type: Option<ValueType>,
+    #[serde(default)]
+    pub possible_values: Vec<String>,
+    #[serde(default)]
+    pub default_values: Vec<String>,
+    #[serde(default)]
+    pub value_delimiter: Option<char>,
+    #[serde(default)]
+    pub conflicts_with: Vec<String>,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    #[serde(default)]
+    pub required_unless_present: Vec<String>,
+    #[serde(default)]
+    pub global: bool,
+    #[serde(default)]
+    pub hide: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    String,
+    Integer,
+    Unsigned,
+    Float,
+    Bool,
+    Path,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GroupSpec {
+    pub name: String,
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+/// Parse and check the JSON a plugin returned for its command
+pub fn parse(json: &str) -> Result<CommandSpec> {
+    let spec: CommandSpec = serde_json::from_str(json).context("Invalid command spec")?;
+    spec.validate()?;
+    Ok(spec)
+}
+
+impl CommandSpec {
+    /// Catch the mistakes clap would otherwise only report with a panic when
+    /// the command is built
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            bail!("Command spec is missing a name");
+        }
+        let known = |id: &String| {
+            self.args.iter().any(|arg| &arg.name == id)
+                || self.groups.iter().any(|group| &group.name == id)
+        };
+        for (i, arg) in self.args.iter().enumerate() {
+            if arg.name.is_empty() {
+                bail!("Argument {} of `{}` has no name", i + 1, self.name);
+            }
+            if self.args[..i].iter().any(|other| other.name == arg.name) {
+                bail!("`{}` declares `{}` twice", self.name, arg.name);
+            }
+            let references = arg
+                .conflicts_with
+                .iter()
+                .chain(&arg.requires)
+                .chain(&arg.required_unless_present);
+            for id in references {
+                if !known(id) {
+                    bail!("`{}` refers to unknown argument `{}`", arg.name, id);
+                }
+            }
+        }
+        for group in &self.groups {
+            if let Some(id) = group.args.iter().find(|id| !known(id)) {
+                bail!("Group `{}` refers to unknown argument `{}`", group.name, id);
+            }
+        }
+        self.subcommands.iter().try_for_each(CommandSpec::validate)
+    }
+
+    pub fn to_clap(&self) -> Command {
+        let mut cmd = Command::new(self.name.clone())
+            .subcommand_required(self.subcommand_required)
+            .arg_required_else_help(self.arg_required_else_help);
+        if !self.about.is_empty() {
+            cmd = cmd.about(self.about.clone());
+        }
+        if !self.aliases.is_empty() {
+            cmd = cmd.aliases(self.aliases.clone());
+        }
+        cmd = cmd.args(self.args.iter().map(ArgSpec::to_clap));
+        cmd = cmd.groups(self.groups.iter().map(GroupSpec::to_clap));
+        cmd.subcommands(self.subcommands.iter().map(CommandSpec::to_clap))
+    }
+}
+
+impl ArgSpec {
+    fn is_positional(&self) -> bool {
+        self.short.is_none() && self.long.is_none()
+    }
+
+    pub fn to_clap(&self) -> Arg {
+        let mut arg = Arg::new(self.name.clone())
+            .required(self.required)
+            .global(self.global)
+            .hide(self.hide)
+            .last(self.last);
+        if !self.help.is_empty() {
+            arg = arg.help(self.help.clone());
+        }
+        if let Some(short) = self.short {
+            arg = arg.short(short);
+        }
+        if let Some(long) = &self.long {
+            arg = arg.long(long.clone());
+        }
+
+        // Positionals always take a value
+        let takes_value = self.takes_value || self.is_positional();
+        arg = match (takes_value, self.multiple) {
+            (false, false) => arg.action(ArgAction::SetTrue),
+            (false, true) => arg.action(ArgAction::Count),
+            (true, false) => arg.action(ArgAction::Set),
+            (true, true) if self.is_positional() => arg.action(ArgAction::Append).num_args(1..),
+            (true, true) => arg.action(ArgAction::Append),
+        };
+        if takes_value {
+            arg = self.with_values(arg);
+        }
+
+        for id in &self.conflicts_with {
+            arg = arg.conflicts_with(id.clone());
+        }
+        for id in &self.requires {
+            arg = arg.requires(id.clone());
+        }
+        if !self.required_unless_present.is_empty() {
+            arg = arg.required_unless_present_any(self.required_unless_present.clone());
+        }
+        arg
+    }
+
+    fn with_values(&self, mut arg: Arg) -> Arg {
+        if let Some(value_name) = &self.value_name {
+            arg = arg.value_name(value_name.clone());
+        }
+        if let Some(delimiter) = self.value_delimiter {
+            arg = arg.value_delimiter(delimiter);
+        }
+        if !self.default_values.is_empty() {
+            arg = arg.default_values(self.default_values.clone());
+        }
+        // Listed values are matched as strings whatever their type
+        if !self.possible_values.is_empty() {
+            return arg.value_parser(PossibleValuesParser::new(self.possible_values.clone()));
+        }
+        match self.value_type.unwrap_or(ValueType::String) {
+            ValueType::String => arg,
+            ValueType::Integer => arg.value_parser(clap::value_parser!(i64)),
+            ValueType::Unsigned => arg.value_parser(clap::value_parser!(u64)),
+            ValueType::Float => arg.value_parser(clap::value_parser!(f64)),
+            ValueType::Bool => arg.value_parser(clap::value_parser!(bool)),
+            ValueType::Path => arg.value_parser(clap::value_parser!(PathBuf)),
+        }
+    }
+}
+
+impl GroupSpec {
+    pub fn to_clap(&self) -> ArgGroup {
+        ArgGroup::new(self.name.clone())
+            .args(self.args.clone())
+            .required(self.required)
+            .multiple(self.multiple)
+    }
+}
@@ -0,0 +1,121 @@
+//! Plugin hooks around every command, see [`kargo_plugin_api::hooks`]
+//!
+//! Only plugins named in the config's `plugin_hooks` take part, in that
+//! order (see [`PluginManager::hooks`]). [`root_flags`] adds their flags to
+//! `kargo`; [`before`] and [`after`] bracket the command the user ran.
+
+use anyhow::Result;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use kargo_plugin_api::{DiagnosticSink, HookSpec, Invocation, Outcome};
+use log::warn;
+use std::env;
+
+use crate::cli::{execution_context, gather_raw_args, publish_diagnostics};
+use crate::events::EventBus;
+
+use super::manager::PluginManager;
+
+/// `root` with the flags of every hooked plugin that don't clash with flags
+/// already there
+pub fn root_flags(pm: &PluginManager, mut root: Command) -> Command {
+    for (name, _, spec) in pm.hooks() {
+        for flag in spec.flags {
+            let clash = root.get_arguments().find(|existing| {
+                existing.get_id() == flag.get_id()
+                    || (flag.get_long().is_some() && existing.get_long() == flag.get_long())
+                    || (flag.get_short().is_some() && existing.get_short() == flag.get_short())
+            });
+            match clash {
+                Some(existing) => warn!(
+                    "Flag {} of plugin {} clashes with {}; left out",
+                    flag.get_id(),
+                    name,
+                    existing.get_id()
+                ),
+                None => root = root.arg(flag),
+            }
+        }
+    }
+    root
+}
+
+/// Run the `before_command` hooks in order, stopping at the first error
+pub async fn before(pm: &PluginManager, matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    for (name, plugin, spec) in pm.hooks() {
+        let Some(invocation) = invocation(&spec, matches) else {
+            return Ok(());
+        };
+        let diagnostics = DiagnosticSink::default();
+        let ctx = context(name, &diagnostics, events)?;
+        let result = plugin.before_command(ctx, invocation).await;
+        publish_diagnostics(name, diagnostics.take(), events).await;
+        result.map_err(|e| e.context(format!("Hook of plugin {} stopped the command", name)))?;
+    }
+    Ok(())
+}
+
+/// Run the `after_command` hooks in reverse order; their errors are logged
+pub async fn after(pm: &PluginManager, matches: &ArgMatches, events: &EventBus, outcome: Outcome) {
+    for (name, plugin, spec) in pm.hooks().into_iter().rev() {
+        let Some(mut invocation) = invocation(&spec, matches) else {
+            return;
+        };
+        invocation.outcome = Some(outcome.clone());
+        let diagnostics = DiagnosticSink::default();
+        let result = match context(name, &diagnostics, events) {
+            Ok(ctx) => plugin.after_command(ctx, invocation).await,
+            Err(e) => Err(e),
+        };
+        publish_diagnostics(name, diagnostics.take(), events).await;
+        if let Err(e) = result {
+            warn!("Hook of plugin {} failed after the command: {:#}", name, e);
+        }
+    }
+}
+
+/// The command in `matches` as the plugin with `spec` sees it; `None` when
+/// no subcommand runs
+fn invocation(spec: &HookSpec, matches: &ArgMatches) -> Option<Invocation> {
+    let (command, sub) = matches.subcommand()?;
+    let flags = spec
+        .flags
+        .iter()
+        .map(|flag| flag.get_id().as_str())
+        // Flags left out of the root are unknown to the matches
+        .filter(|id| matches.ids().any(|known| known.as_str() == *id))
+        .filter(|id| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        })
+        .filter_map(|id| {
+            let values = matches
+                .get_raw(id)?
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect();
+            Some((id.to_string(), values))
+        })
+        .collect();
+    Some(Invocation {
+        command: command.to_string(),
+        args: gather_raw_args(command, sub),
+        flags,
+        outcome: None,
+    })
+}
+
+fn context(
+    name: &str,
+    diagnostics: &DiagnosticSink,
+    events: &EventBus,
+) -> Result<kargo_plugin_api::ExecutionContext> {
+    Ok(execution_context(
+        name,
+        vec![name.to_string()],
+        env::current_dir()?,
+        diagnostics,
+        events,
+    ))
+}
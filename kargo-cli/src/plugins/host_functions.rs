@@ -1,9 +1,15 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicI64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
 use extism::*;
 use tokio::sync::{mpsc, oneshot};
 
+use crate::config::WasmPermissions;
+
 #[derive(Debug)]
 pub enum HostFunctionRequest {
     ReadFile {
@@ -14,6 +20,10 @@ pub enum HostFunctionRequest {
         msg: String,
         reply: oneshot::Sender<HostFunctionResponse>,
     },
+    GetEnv {
+        name: String,
+        reply: oneshot::Sender<HostFunctionResponse>,
+    },
 }
 
 #[derive(Debug)]
@@ -61,12 +71,61 @@ host_fn!(read_file_fn(user_data: mpsc::Sender<HostFunctionRequest>; path: String
     }
 });
 
+// Host function for reading environment variables; unset ones are empty
+host_fn!(get_env_fn(user_data: mpsc::Sender<HostFunctionRequest>; name: String) -> String {
+    let tx = user_data.get()?;
+    let tx = match tx.lock() {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Failed to lock tx mutex: {}", e);
+            return Err(Error::msg(format!("Failed to lock tx mutex: {}", e)));
+        }
+    };
+    let (sx, rx) = oneshot::channel();
+    let _ = tx.blocking_send(HostFunctionRequest::GetEnv { name, reply: sx });
+    match rx.blocking_recv() {
+        Ok(HostFunctionResponse::Text(t)) => Ok(t),
+        Ok(HostFunctionResponse::Error(e)) => Err(Error::msg(e)),
+        _ => Err(Error::msg("get_env failed")),
+    }
+});
+
+/// Largest buffer `random_bytes` fills in one call
+const MAX_RANDOM_BYTES: i64 = 1 << 16;
+
+/// Latest time `clock_ms` returned
+static LAST_CLOCK_MS: AtomicI64 = AtomicI64::new(0);
+
+// Clock for plugins built without WASI: milliseconds since the Unix epoch,
+// never going back when the system clock is set back
+host_fn!(clock_ms_fn() -> i64 {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(Error::msg)?;
+    let now = elapsed.as_millis() as i64;
+    Ok(LAST_CLOCK_MS.fetch_max(now, Ordering::Relaxed).max(now))
+});
+
+// Random bytes for plugins built without WASI, from the OS generator
+host_fn!(random_bytes_fn(len: i64) -> Vec<u8> {
+    if !(0..=MAX_RANDOM_BYTES).contains(&len) {
+        return Err(Error::msg(format!(
+            "random_bytes: length must be between 0 and {}",
+            MAX_RANDOM_BYTES
+        )));
+    }
+    let mut bytes = vec![0; len as usize];
+    getrandom::getrandom(&mut bytes).map_err(|e| Error::msg(e.to_string()))?;
+    Ok(bytes)
+});
+
 pub fn register_host_functions(
     tx: mpsc::Sender<HostFunctionRequest>,
     manifest: Manifest,
-) -> Result<Plugin> {
+) -> PluginBuilder<'static> {
     let tx_log = UserData::new(tx.clone());
-    let tx_read = UserData::new(tx);
+    let tx_read = UserData::new(tx.clone());
+    let tx_env = UserData::new(tx);
 
     PluginBuilder::new(manifest)
         .with_wasi(true)
@@ -84,12 +143,33 @@ pub fn register_host_functions(
             tx_read,
             read_file_fn,
         )
-        .build()
+        .with_function(
+            "get_env",
+            [ValType::I64], // variable name string pointer
+            [ValType::I64], // returns string pointer
+            tx_env,
+            get_env_fn,
+        )
+        .with_function(
+            "clock_ms",
+            [],             // no arguments
+            [ValType::I64], // returns pointer to an i64
+            UserData::new(()),
+            clock_ms_fn,
+        )
+        .with_function(
+            "random_bytes",
+            [ValType::I64], // pointer to the requested length
+            [ValType::I64], // returns pointer to the bytes
+            UserData::new(()),
+            random_bytes_fn,
+        )
 }
 
+/// Answer the plugin's requests, with only the access `permissions` grant
 pub async fn handle_requests(
-    _: Arc<std::sync::Mutex<Plugin>>,
     mut rx: mpsc::Receiver<HostFunctionRequest>,
+    permissions: WasmPermissions,
 ) -> Result<()> {
     while let Some(req) = rx.recv().await {
         match req {
@@ -98,13 +178,104 @@ pub async fn handle_requests(
                 let _ = reply.send(HostFunctionResponse::Ok);
             }
             HostFunctionRequest::ReadFile { path, reply } => {
+                if !permissions.allows_path(&path) {
+                    let _ = reply.send(HostFunctionResponse::Error(format!(
+                        "Permission denied: the plugin may not read {}",
+                        path.display()
+                    )));
+                    continue;
+                }
                 let res = tokio::fs::read_to_string(&path).await;
                 let _ = reply.send(match res {
                     Ok(t) => HostFunctionResponse::Text(t),
                     Err(e) => HostFunctionResponse::Error(e.to_string()),
                 });
             }
+            HostFunctionRequest::GetEnv { name, reply } => {
+                let _ = reply.send(if permissions.allows_env(&name) {
+                    HostFunctionResponse::Text(std::env::var(&name).unwrap_or_default())
+                } else {
+                    HostFunctionResponse::Error(format!(
+                        "Permission denied: the plugin may not read ${}",
+                        name
+                    ))
+                });
+            }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::register_host_functions;
+    use extism::{Manifest, Plugin, Wasm};
+    use tokio::sync::mpsc;
+
+    /// A plugin reading the clock twice and asking for 32 random bytes through
+    /// the host shims, as plugins built without WASI do
+    const SHIMS: &str = r#"
+    (module
+      (import "extism:host/user" "clock_ms" (func $clock_ms (result i64)))
+      (import "extism:host/user" "random_bytes" (func $random_bytes (param i64) (result i64)))
+      (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+      (import "extism:host/env" "length" (func $length (param i64) (result i64)))
+      (import "extism:host/env" "load_u64" (func $load_u64 (param i64) (result i64)))
+      (import "extism:host/env" "store_u64" (func $store_u64 (param i64 i64)))
+      (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+      (memory 1)
+      (func (export "clocks") (result i32)
+        (local $first i64)
+        (local $out i64)
+        (local.set $first (call $load_u64 (call $clock_ms)))
+        (local.set $out (call $alloc (i64.const 16)))
+        (call $store_u64 (local.get $out) (local.get $first))
+        (call $store_u64
+          (i64.add (local.get $out) (i64.const 8))
+          (call $load_u64 (call $clock_ms)))
+        (call $output_set (local.get $out) (i64.const 16))
+        (i32.const 0))
+      (func (export "random") (result i32)
+        (local $len i64)
+        (local $bytes i64)
+        (local.set $len (call $alloc (i64.const 8)))
+        (call $store_u64 (local.get $len) (i64.const 32))
+        (local.set $bytes (call $random_bytes (local.get $len)))
+        (call $output_set (local.get $bytes) (call $length (local.get $bytes)))
+        (i32.const 0)))
+    "#;
+
+    fn shims_plugin() -> Plugin {
+        let (tx, _rx) = mpsc::channel(1);
+        let manifest = Manifest::new([Wasm::data(SHIMS)]);
+        register_host_functions(tx, manifest).build().unwrap()
+    }
+
+    #[test]
+    fn test_clock_shim_never_goes_back() {
+        let mut plugin = shims_plugin();
+        let mut last = 0;
+        for _ in 0..3 {
+            let output = plugin.call::<&str, Vec<u8>>("clocks", "").unwrap();
+            assert_eq!(output.len(), 16);
+            let first = u64::from_le_bytes(output[..8].try_into().unwrap());
+            let second = u64::from_le_bytes(output[8..].try_into().unwrap());
+            assert!(first >= last, "{} after {}", first, last);
+            assert!(second >= first, "{} after {}", second, first);
+            last = second;
+        }
+        // Milliseconds since the Unix epoch, not since some other start
+        assert!(last > 1_600_000_000_000);
+    }
+
+    #[test]
+    fn test_random_shim_returns_fresh_bytes() {
+        let mut plugin = shims_plugin();
+        let first = plugin.call::<&str, Vec<u8>>("random", "").unwrap();
+        let second = plugin.call::<&str, Vec<u8>>("random", "").unwrap();
+        assert_eq!(first.len(), 32);
+        assert_eq!(second.len(), 32);
+        assert_ne!(first, second);
+        assert!(first.iter().any(|&b| b != 0));
+    }
+}
@@ -0,0 +1,144 @@
+//! A bounded pool of reusable plugin instances
+//!
+//! WASM plugins are compiled once when they load; running one still needs an
+//! instance, which is cheap to make from the compiled module but not free,
+//! and a single instance can only run one call at a time. The pool hands out
+//! up to `size` instances at once, creating them as demand grows and keeping
+//! them warm for the next call. An instance whose call failed may be left in
+//! a bad state, so the caller [`Pooled::discard`]s it instead of returning it.
+
+use anyhow::{Result, anyhow};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+type Factory<T> = Box<dyn Fn() -> Result<T> + Send + Sync>;
+
+struct Inner<T> {
+    create: Factory<T>,
+    idle: Mutex<Vec<T>>,
+    permits: Arc<Semaphore>,
+    size: usize,
+}
+
+/// Up to `size` instances of `T`, shared by every clone of the pool
+pub struct InstancePool<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for InstancePool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: Send + 'static> InstancePool<T> {
+    /// A pool of at most `size` (at least one) instances made by `create`
+    pub fn new(size: usize, create: impl Fn() -> Result<T> + Send + Sync + 'static) -> Self {
+        let size = size.max(1);
+        Self {
+            inner: Arc::new(Inner {
+                create: Box::new(create),
+                idle: Mutex::new(Vec::new()),
+                permits: Arc::new(Semaphore::new(size)),
+                size,
+            }),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.inner.size
+    }
+
+    /// Instances created and waiting for a call
+    pub fn idle(&self) -> usize {
+        self.idle_instances().len()
+    }
+
+    /// Create instances until `count` (up to the pool size) are idle, so the
+    /// first calls of a burst don't pay for instantiation
+    pub fn warm(&self, count: usize) -> Result<()> {
+        while self.idle() < count.min(self.inner.size) {
+            let instance = (self.inner.create)()?;
+            self.idle_instances().push(instance);
+        }
+        Ok(())
+    }
+
+    /// An instance for one call, waiting while all of them are busy
+    pub async fn checkout(&self) -> Result<Pooled<T>> {
+        let permit = Arc::clone(&self.inner.permits)
+            .acquire_owned()
+            .await
+            .map_err(|e| anyhow!("Instance pool closed: {}", e))?;
+        self.take(permit)
+    }
+
+    /// An instance for one call, or `None` while all of them are busy
+    pub fn try_checkout(&self) -> Result<Option<Pooled<T>>> {
+        match Arc::clone(&self.inner.permits).try_acquire_owned() {
+            Ok(permit) => self.take(permit).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn take(&self, permit: OwnedSemaphorePermit) -> Result<Pooled<T>> {
+        let idle = self.idle_instances().pop();
+        let instance = match idle {
+            Some(instance) => instance,
+            None => (self.inner.create)()?,
+        };
+        Ok(Pooled {
+            instance: Some(instance),
+            pool: Arc::clone(&self.inner),
+            _permit: permit,
+        })
+    }
+
+    fn idle_instances(&self) -> std::sync::MutexGuard<'_, Vec<T>> {
+        self.inner.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A checked-out instance; returns to the pool when dropped
+pub struct Pooled<T> {
+    instance: Option<T>,
+    pool: Arc<Inner<T>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<T> Pooled<T> {
+    /// Drop the instance instead of returning it; the pool makes a fresh one
+    /// when it is needed again
+    pub fn discard(mut self) {
+        self.instance = None;
+    }
+}
+
+impl<T> Deref for Pooled<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.instance.as_ref().expect("only taken on drop")
+    }
+}
+
+impl<T> DerefMut for Pooled<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.instance.as_mut().expect("only taken on drop")
+    }
+}
+
+impl<T> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        if let Some(instance) = self.instance.take() {
+            self.pool
+                .idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(instance);
+        }
+    }
+}
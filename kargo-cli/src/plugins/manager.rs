@@ -1,26 +1,59 @@
 use std::{
-    collections::HashMap,
-    ffi::OsStr,
+    collections::{BTreeMap, HashMap},
+    ffi::{CStr, OsStr},
     fs,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
 use anyhow::{Context, Result};
 use libloading::{Library, Symbol};
-use log::info;
-use std::process::Command;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
 
-use kargo_plugin_api::{CreateFn, PluginCommand};
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+use kargo_plugin_api::compat::{self, ApiVersionFn, Incompatible, RequiresKargoFn};
+use kargo_plugin_api::{CreateFn, HookSpec, InitLoggerFn, PluginCommand, ServiceSpec};
 
+use crate::config::WasmPermissions;
+
+use super::module_cache::ModuleCache;
+use super::permissions;
+use super::trust::{self, TrustPolicy, TrustStore};
 use super::{trait_scanner, wasm_adapter::WasmPluginAdapter};
 
+/// Version plugins' kargo requirements are checked against
+const KARGO_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 pub struct PluginManager {
     search_paths: Vec<PathBuf>,
     plugins: HashMap<String, Box<dyn PluginCommand>>,
+    wasm_permissions: BTreeMap<String, WasmPermissions>,
+    wasm_pool_size: usize,
+    module_cache: Option<ModuleCache>,
+    trust_policy: TrustPolicy,
+    trust_store: Option<TrustStore>,
+    permissions_file: Option<PathBuf>,
+    load_times: Vec<(String, Duration)>,
+    sources: HashMap<String, PluginSource>,
+    hook_plugins: Vec<String>,
     _native_libs: Vec<Arc<Library>>, // keep libs alive
 }
 
+/// The file a plugin was loaded from, to notice when it is rebuilt
+struct PluginSource {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// The outcome of reloading one plugin, see [`PluginManager::reload_changed`]
+pub struct Reload {
+    pub plugin: String,
+    pub path: PathBuf,
+    pub result: Result<()>,
+}
+
 impl PluginManager {
     pub fn new() -> Self {
         // 1) optional env override
@@ -88,10 +121,69 @@ impl PluginManager {
         Self {
             search_paths: sp,
             plugins: HashMap::new(),
+            load_times: Vec::new(),
+            sources: HashMap::new(),
+            hook_plugins: Vec::new(),
+            wasm_permissions: BTreeMap::new(),
+            wasm_pool_size: default_wasm_pool_size(),
+            module_cache: Some(ModuleCache::new(ModuleCache::default_dir())),
+            trust_policy: TrustPolicy::default(),
+            trust_store: None,
+            permissions_file: None,
             _native_libs: vec![],
         }
     }
 
+    /// Directories plugins are discovered in, in search order
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// Discover plugins in `paths` only, instead of the default directories
+    pub fn set_search_paths(&mut self, paths: Vec<PathBuf>) {
+        self.search_paths = paths;
+    }
+
+    /// Permissions for WASM plugins loaded from now on, keyed by file stem
+    pub fn set_wasm_permissions(&mut self, permissions: BTreeMap<String, WasmPermissions>) {
+        self.wasm_permissions = permissions;
+    }
+
+    /// Instances of each WASM plugin loaded from now on that may run at once;
+    /// defaults to the number of CPUs
+    pub fn set_wasm_pool_size(&mut self, size: usize) {
+        self.wasm_pool_size = size;
+    }
+
+    /// Where WASM plugins loaded from now on keep their compiled modules;
+    /// `None` compiles them on every load
+    pub fn set_module_cache(&mut self, cache: Option<ModuleCache>) {
+        self.module_cache = cache;
+    }
+
+    /// What to do with plugins found in a plugin directory that have no
+    /// recorded trust decision yet
+    pub fn set_trust_policy(&mut self, policy: TrustPolicy) {
+        self.trust_policy = policy;
+    }
+
+    /// Plugins whose root flags and command hooks take effect, in the order
+    /// their hooks run before a command; none by default
+    pub fn set_hook_plugins(&mut self, plugins: Vec<String>) {
+        self.hook_plugins = plugins;
+    }
+
+    /// Record trust decisions in `store` instead of the user's default one
+    pub fn set_trust_store(&mut self, store: TrustStore) {
+        self.trust_store = Some(store);
+    }
+
+    /// Config file the answers about WASM plugins' capabilities are saved
+    /// in; `None` keeps them for this run only
+    pub fn set_permissions_file(&mut self, path: Option<PathBuf>) {
+        self.permissions_file = path;
+    }
+
     pub fn discover_and_load_plugins(&mut self) -> Result<()> {
         let search_paths = self.search_paths.clone();
         for d in &search_paths {
@@ -102,9 +194,9 @@ impl PluginManager {
             // Check if this directory itself is a plugin (for workspace siblings)
             if d.join("Cargo.toml").is_file() {
                 info!("Loading plugin project: {}", d.display());
-                match self.build_and_load_rust_project(&d) {
+                match self.timed(|pm| pm.build_and_load_rust_project(d, None)) {
                     Ok(_) => info!("Successfully loaded plugin from {}", d.display()),
-                    Err(e) => info!("Failed to load plugin from {}: {}", d.display(), e),
+                    Err(e) => report_failure(d, &e),
                 }
                 continue;
             }
@@ -114,21 +206,31 @@ impl PluginManager {
             for entry in fs::read_dir(d)? {
                 let path = entry?.path();
                 if path.is_dir() && path.join("Cargo.toml").is_file() {
-                    self.build_and_load_rust_project(&path)
-                        .with_context(|| format!("Rust plugin {}", path.display()))?;
-                } else {
-                    match path.extension().and_then(OsStr::to_str) {
-                        Some("so" | "dylib" | "dll") => match self.load_native(&path) {
-                            Ok(_) => info!("Successfully loaded native plugin: {}", path.display()),
-                            Err(e) => {
-                                info!("Failed to load native plugin {}: {}", path.display(), e)
-                            }
-                        },
-                        Some("wasm") => match self.load_wasm(&path) {
-                            Ok(_) => info!("Successfully loaded WASM plugin: {}", path.display()),
-                            Err(e) => info!("Failed to load WASM plugin {}: {}", path.display(), e),
-                        },
-                        _ => {}
+                    match self.is_trusted(&path) {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(e) => {
+                            warn!("Skipping plugin {}: {:#}", path.display(), e);
+                            continue;
+                        }
+                    }
+                    // Built where a file dropped into the project can't stand in
+                    let target_dir = plugin_target_dir(&path);
+                    match self.timed(|pm| pm.build_and_load_rust_project(&path, Some(&target_dir)))
+                    {
+                        Err(e) if e.is::<Incompatible>() => report_failure(&path, &e),
+                        result => {
+                            result.with_context(|| format!("Rust plugin {}", path.display()))?
+                        }
+                    }
+                } else if matches!(
+                    path.extension().and_then(OsStr::to_str),
+                    Some("so" | "dylib" | "dll" | "wasm")
+                ) {
+                    match self.timed(|pm| pm.load_file(&path, true)) {
+                        Ok(Some(_)) => info!("Successfully loaded plugin: {}", path.display()),
+                        Ok(None) => {}
+                        Err(e) => report_failure(&path, &e),
                     }
                 }
             }
@@ -142,21 +244,206 @@ impl PluginManager {
         Ok(())
     }
 
+    /// Whether the plugin project `dir`, found by scanning a plugin
+    /// directory, may be built and loaded. Directories named directly in the
+    /// search path are trusted already.
+    fn is_trusted(&mut self, dir: &Path) -> Result<bool> {
+        let digest = trust::source_digest(dir)?;
+        self.check_trust(dir, digest)
+    }
+
+    /// Whether the plugin at `path` may be loaded with contents `digest`
+    fn check_trust(&mut self, path: &Path, digest: String) -> Result<bool> {
+        let path = path.canonicalize()?;
+        let store = match &mut self.trust_store {
+            Some(store) => store,
+            None => self.trust_store.insert(
+                TrustStore::load(TrustStore::default_path()).unwrap_or_else(|e| {
+                    warn!("{:#}; asking about every plugin again", e);
+                    TrustStore::default()
+                }),
+            ),
+        };
+        Ok(store.check(&path, Some(digest), self.trust_policy))
+    }
+
+    /// How long each plugin loaded by [`Self::discover_and_load_plugins`]
+    /// took, including building plugin projects
+    pub fn load_times(&self) -> &[(String, Duration)] {
+        &self.load_times
+    }
+
+    /// Run `load` and attribute its duration to the plugins it added
+    fn timed<T>(&mut self, load: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let before: Vec<String> = self.plugins.keys().cloned().collect();
+        let started = Instant::now();
+        let result = load(self);
+        let elapsed = started.elapsed();
+        for name in self.plugins.keys() {
+            if !before.contains(name) {
+                self.load_times.push((name.clone(), elapsed));
+            }
+        }
+        result
+    }
+
     pub fn get(&self, name: &str) -> Option<&Box<dyn PluginCommand>> {
         self.plugins.get(name)
     }
 
+    /// Drop every loaded plugin for which `keep` returns false
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.plugins.retain(|name, plugin| {
+            let kept = keep(name);
+            if !kept {
+                plugin.on_unload();
+            }
+            kept
+        });
+    }
+
+    /// Add `plugin` under its command name once its `on_load` hook succeeds,
+    /// unloading a plugin of the same name it replaces
+    pub fn register(&mut self, plugin: Box<dyn PluginCommand>) -> Result<()> {
+        let name = plugin.clap().get_name().to_owned();
+        plugin
+            .on_load()
+            .with_context(|| format!("Plugin {} failed to load", name))?;
+        if let Some(replaced) = self.plugins.insert(name, plugin) {
+            replaced.on_unload();
+        }
+        Ok(())
+    }
+
+    /// [`Self::register`] a plugin loaded from `path`, returning its name
+    fn register_from(&mut self, plugin: Box<dyn PluginCommand>, path: &Path) -> Result<String> {
+        let name = plugin.clap().get_name().to_owned();
+        self.register(plugin)?;
+        self.sources.insert(
+            name.clone(),
+            PluginSource {
+                path: path.to_path_buf(),
+                modified: modified(path),
+            },
+        );
+        Ok(name)
+    }
+
+    /// The library or WASM module `name` was loaded from; `None` for plugins
+    /// registered directly
+    pub fn source(&self, name: &str) -> Option<&Path> {
+        self.sources.get(name).map(|source| source.path.as_path())
+    }
+
+    /// The libraries and WASM modules plugins were loaded from
+    pub fn sources(&self) -> impl Iterator<Item = &Path> {
+        self.sources.values().map(|source| source.path.as_path())
+    }
+
+    /// Plugins whose library or WASM module changed on disk since they were
+    /// loaded
+    pub fn changed_plugins(&self) -> Vec<String> {
+        let mut changed: Vec<String> = self
+            .sources
+            .iter()
+            .filter(|(_, source)| modified(&source.path) != source.modified)
+            .map(|(name, _)| name.clone())
+            .collect();
+        changed.sort();
+        changed
+    }
+
+    /// Load the new version of every plugin whose file changed on disk,
+    /// unloading the old one. A plugin whose new version fails to load keeps
+    /// running the old one until its file changes again.
+    pub fn reload_changed(&mut self) -> Vec<Reload> {
+        let mut reloads = Vec::new();
+        for name in self.changed_plugins() {
+            let Some(source) = self.sources.get_mut(&name) else {
+                continue;
+            };
+            // Don't retry a broken build on every check
+            source.modified = modified(&source.path);
+            let path = source.path.clone();
+            let result = self.reload(&name, &path);
+            match &result {
+                Ok(()) => info!("Reloaded plugin {} from {}", name, path.display()),
+                Err(e) => warn!("Plugin {} not reloaded: {:#}", name, e),
+            }
+            reloads.push(Reload {
+                plugin: name,
+                path,
+                result,
+            });
+        }
+        reloads
+    }
+
+    fn reload(&mut self, name: &str, path: &Path) -> Result<()> {
+        if !path.is_file() {
+            anyhow::bail!("{} is gone", path.display());
+        }
+        // Libraries found by scanning a plugin directory are trusted by
+        // digest, which a rebuild changes
+        let scanned = self.search_paths.iter().any(|d| path.parent() == Some(d));
+        let Some(loaded) = self.load_file(path, scanned)? else {
+            anyhow::bail!("{} is no longer trusted", path.display());
+        };
+        if loaded != name {
+            // The new version renamed its command
+            if let Some(old) = self.plugins.remove(name) {
+                old.on_unload();
+            }
+            self.sources.remove(name);
+        }
+        Ok(())
+    }
+
+    /// Loaded plugins with a background service, and how to supervise it
+    pub fn services(&self) -> impl Iterator<Item = (&str, &dyn PluginCommand, ServiceSpec)> {
+        self.plugins
+            .iter()
+            .filter_map(|(name, plugin)| Some((name.as_str(), plugin.as_ref(), plugin.service()?)))
+    }
+
+    /// Loaded plugins allowed to hook commands that declare hooks, in hook
+    /// order
+    pub fn hooks(&self) -> Vec<(&str, &dyn PluginCommand, HookSpec)> {
+        self.hook_plugins
+            .iter()
+            .filter_map(|name| {
+                let plugin = self.plugins.get(name)?;
+                Some((name.as_str(), plugin.as_ref(), plugin.hooks()?))
+            })
+            .collect()
+    }
+
     pub fn plugins_iter(&self) -> impl Iterator<Item = (&String, &Box<dyn PluginCommand>)> {
         self.plugins.iter()
     }
 
     /* -------- raw Rust project -------- */
-    fn build_and_load_rust_project(&mut self, dir: &Path) -> Result<()> {
+    /// Build the plugin project in `dir` and load it. With `target_dir`, it
+    /// is built there every time, from `dir` so cargo applies the config its
+    /// trust digest covers (see [`trust::source_digest`]); otherwise an
+    /// existing library newer than the sources is loaded as it is.
+    fn build_and_load_rust_project(&mut self, dir: &Path, target_dir: Option<&Path>) -> Result<()> {
         info!("Compiling plugin at {}", dir.display());
 
         // First, verify the plugin implements the required traits
         self.verify_plugin_traits(dir)?;
 
+        if let Some(target_dir) = target_dir {
+            let command = CargoCommand::new("build")
+                .args(["--release", "--lib"])
+                .current_dir(dir)
+                .target_dir(Some(target_dir));
+            cargo_runner::run(&command)?.ensure_success(&command)?;
+            let lib = lib_in(&target_dir.join("release"), dir)?
+                .ok_or_else(|| anyhow::anyhow!("built lib not found for {}", dir.display()))?;
+            return self.load_native(&lib);
+        }
+
         let needs_build = {
             let artifact = find_existing_lib(dir)?;
             match artifact {
@@ -177,16 +464,10 @@ impl PluginManager {
         };
 
         if needs_build {
-            let status = Command::new("cargo")
-                .arg("build")
-                .arg("--release")
-                .arg("--lib")
-                .arg("--manifest-path")
-                .arg(dir.join("Cargo.toml"))
-                .status()?;
-            if !status.success() {
-                anyhow::bail!("cargo build failed for {}", dir.display());
-            }
+            let command = CargoCommand::new("build")
+                .args(["--release", "--lib", "--manifest-path"])
+                .arg(dir.join("Cargo.toml").to_string_lossy());
+            cargo_runner::run(&command)?.ensure_success(&command)?;
         }
 
         let lib = find_existing_lib(dir)?
@@ -227,26 +508,137 @@ impl PluginManager {
 
     /* -------- existing native lib -------- */
     fn load_native(&mut self, file: &Path) -> Result<()> {
-        let lib = unsafe { Library::new(file) }?;
+        self.load_native_image(file, file).map(drop)
+    }
+
+    /// Load the library or WASM module at `file` from a private copy. With
+    /// `check_trust` the copy is what is checked, so rewriting `file` after
+    /// the check can't slip in a plugin nobody trusted. `None` when the copy
+    /// isn't trusted.
+    fn load_file(&mut self, file: &Path, check_trust: bool) -> Result<Option<String>> {
+        let copy = snapshot(file)?;
+        if check_trust && !self.check_trust(file, trust::file_digest(copy.path())?)? {
+            return Ok(None);
+        }
+        let name = match file.extension().and_then(OsStr::to_str) {
+            Some("wasm") => self.load_wasm(file, copy.path())?,
+            _ => self.load_native_image(file, copy.path())?,
+        };
+        // Removing the copy afterwards leaves the loaded image mapped
+        Ok(Some(name))
+    }
+
+    /// Load the library image at `image`, attributing it to `file`
+    fn load_native_image(&mut self, file: &Path, image: &Path) -> Result<String> {
+        let lib = unsafe { Library::new(image) }?;
         let arc = Arc::new(lib);
+        // Before anything is called through a vtable that may not match
+        let api_version = unsafe { arc.get::<ApiVersionFn>(b"kargo_plugin_api_version") }
+            .ok()
+            .map(|version| version());
+        compat::check_api(api_version)?;
+        if let Ok(requires) = unsafe { arc.get::<RequiresKargoFn>(b"kargo_plugin_requires_kargo") }
+        {
+            let requires = unsafe { CStr::from_ptr(requires()) }.to_string_lossy();
+            compat::check_kargo(&requires, KARGO_VERSION)?;
+        }
         let ctor: Symbol<CreateFn> = unsafe { arc.get(b"kargo_plugin_create") }?;
-        let plugin = ctor();
-        self.plugins
-            .insert(plugin.clap().get_name().to_owned(), plugin);
+        // Plugins that opt in log through the host's subscriber
+        if let Ok(init_logger) = unsafe { arc.get::<InitLoggerFn>(b"kargo_plugin_init_logger") } {
+            init_logger(log::logger(), log::max_level());
+        }
+        // Plugins catch their own panics, which can't unwind into kargo
+        let plugin = ctor().with_context(|| format!("Failed to create {}", file.display()))?;
+        let name = self.register_from(plugin, file)?;
         self._native_libs.push(arc);
-        Ok(())
+        Ok(name)
     }
 
-    fn load_wasm(&mut self, file: &Path) -> Result<()> {
-        let adapt = WasmPluginAdapter::new(file)?;
-        self.plugins
-            .insert(adapt.clap().get_name().to_owned(), Box::new(adapt));
-        Ok(())
+    /// Load the WASM module at `image`, attributing it to `file`
+    fn load_wasm(&mut self, file: &Path, image: &Path) -> Result<String> {
+        let stem = file
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_string();
+        let current = self
+            .wasm_permissions
+            .get(&stem)
+            .cloned()
+            .unwrap_or_default();
+        let mut adapt = WasmPluginAdapter::new(
+            image,
+            &current,
+            self.wasm_pool_size,
+            self.module_cache.as_ref(),
+        )?;
+        // Before asking the user about capabilities it won't get to use
+        if let Some(requires) = adapt.requires_kargo() {
+            compat::check_kargo(requires, KARGO_VERSION)?;
+        }
+        let requested = adapt.capabilities().clone();
+        if let Some(updated) = permissions::review(&stem, &requested, &current, self.trust_policy) {
+            if let Some(path) = &self.permissions_file
+                && let Err(e) = permissions::record(path, &stem, &updated)
+            {
+                warn!("Failed to record permissions of {}: {:#}", stem, e);
+            }
+            // Instances get their permissions when they are made
+            if updated.granted() != current.granted() {
+                adapt = WasmPluginAdapter::new(
+                    image,
+                    &updated,
+                    self.wasm_pool_size,
+                    self.module_cache.as_ref(),
+                )?;
+            }
+            self.wasm_permissions.insert(stem, updated);
+        }
+        self.register_from(Box::new(adapt), file)
+    }
+}
+
+impl Drop for PluginManager {
+    fn drop(&mut self) {
+        // Before `_native_libs` unmaps their code
+        for plugin in self.plugins.values() {
+            plugin.on_unload();
+        }
     }
 }
 
+/// Log a plugin that failed to load. Plugins skipped as incompatible are
+/// worth a warning: they load fine once rebuilt or upgraded.
+fn report_failure(path: &Path, e: &anyhow::Error) {
+    match e.downcast_ref::<Incompatible>() {
+        Some(reason) => warn!("Skipping plugin {}: {}", path.display(), reason),
+        None => info!("Failed to load plugin {}: {:#}", path.display(), e),
+    }
+}
+
+/// A fresh copy of the plugin file at `file`. The dynamic loader hands back
+/// the image it already has for a path it has seen, and the old image must
+/// stay mapped while code from it may still run.
+fn snapshot(file: &Path) -> Result<tempfile::NamedTempFile> {
+    let extension = file.extension().and_then(OsStr::to_str).unwrap_or("so");
+    let copy = tempfile::Builder::new()
+        .prefix("kargo-plugin-")
+        .suffix(&format!(".{}", extension))
+        .tempfile()?;
+    fs::copy(file, copy.path()).with_context(|| format!("Failed to copy {}", file.display()))?;
+    Ok(copy)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn default_wasm_pool_size() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
 /* ---------- helper: locate compiled library ---------- */
-fn find_existing_lib(dir: &Path) -> Result<Option<PathBuf>> {
+pub(crate) fn find_existing_lib(dir: &Path) -> Result<Option<PathBuf>> {
     // First try the local target directory
     let mut release = dir.join("target").join("release");
 
@@ -263,6 +655,24 @@ fn find_existing_lib(dir: &Path) -> Result<Option<PathBuf>> {
         release = workspace_root.join("target").join("release");
     }
 
+    lib_in(&release, dir)
+}
+
+/// Where the plugin project in `dir`, found by scanning a plugin directory,
+/// is built: below kargo's cache, one directory per project
+fn plugin_target_dir(dir: &Path) -> PathBuf {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let hash = Sha256::digest(dir.to_string_lossy().as_bytes());
+    let name: String = hash[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("kargo")
+        .join("plugin-builds")
+        .join(name)
+}
+
+/// The library of the project in `dir` in the profile directory `release`
+fn lib_in(release: &Path, dir: &Path) -> Result<Option<PathBuf>> {
     if !release.is_dir() {
         return Ok(None);
     }
@@ -2,7 +2,15 @@
 // for the kargo CLI tool. This includes both native Rust library plugins and WASM plugins
 // via the Extism framework.
 
-mod host_functions;
+pub mod command_spec;
+pub mod hooks;
+pub(crate) mod host_functions;
+pub mod instance_pool;
 pub mod manager;
+pub mod module_cache;
+pub mod permissions;
+pub mod supervisor;
 mod trait_scanner;
-mod wasm_adapter;
+pub mod trust;
+pub mod watcher;
+pub mod wasm_adapter;
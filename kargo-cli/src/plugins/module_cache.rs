@@ -0,0 +1,77 @@
+//! Compiled WASM modules kept between runs
+//!
+//! Compiling a large WASM plugin takes far longer than instantiating it, and
+//! without a cache every kargo invocation compiles every WASM plugin again.
+//! Wasmtime can store the compiled artifacts itself: each is keyed by a hash
+//! of the module together with the wasmtime version and compiler settings, so
+//! a changed plugin or a kargo built against another engine compiles afresh
+//! instead of loading a stale artifact. This module points that cache at
+//! `<cache dir>/kargo/wasm` through the config file wasmtime reads.
+
+use anyhow::Result;
+use kargo_plugin_api::WriteGuard;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table, value};
+
+/// Name of the wasmtime cache config kept next to the artifacts
+const CONFIG_FILE: &str = "wasmtime-cache.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("wasm")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The wasmtime cache config storing artifacts in this cache, written
+    /// when missing or pointing elsewhere
+    pub fn config_file(&self) -> Result<PathBuf> {
+        let path = self.dir.join(CONFIG_FILE);
+        let mut cache = Table::new();
+        cache["enabled"] = value(true);
+        cache["directory"] = value(self.dir.join("modules").display().to_string());
+        let mut config = DocumentMut::new();
+        config["cache"] = Item::Table(cache);
+        let config = config.to_string();
+        if fs::read_to_string(&path).ok().as_deref() != Some(config.as_str()) {
+            let guard = WriteGuard::current();
+            guard.create_dir_all(&self.dir)?;
+            guard.write(&path, config)?;
+        }
+        Ok(path)
+    }
+
+    /// Bytes taken by compiled modules
+    pub fn size(&self) -> u64 {
+        jwalk::WalkDir::new(self.dir.join("modules"))
+            .into_iter()
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Delete every compiled module; plugins compile again on their next load
+    pub fn clear(&self) -> Result<()> {
+        let modules = self.dir.join("modules");
+        if !modules.exists() {
+            return Ok(());
+        }
+        WriteGuard::current().remove_dir_all(&modules)
+    }
+}
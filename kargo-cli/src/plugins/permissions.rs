@@ -0,0 +1,206 @@
+//! Capabilities WASM plugins ask for, and the user's approval of them
+//!
+//! A WASM plugin declares in the metadata it returns from
+//! `_kargo_plugin_metadata` which files it reads, which hosts it sends
+//! requests to and which environment variables it reads. Only what the
+//! plugin's [`WasmPermissions`] grant is allowed: `read_file` and `get_env`
+//! refuse anything else, and Extism blocks requests to other hosts.
+//!
+//! The first time a plugin declares something it hasn't been granted, the
+//! [`TrustPolicy`] decides whether it is, asking on the terminal by default.
+//! The answer is saved with the plugin's `wasm_permissions` in the config,
+//! so the plugin is only asked about again when it declares more. Under
+//! `--read-only` it holds for that run only.
+
+use anyhow::Result;
+use kargo_plugin_api::WriteGuard;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{BufRead, IsTerminal, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, WasmPermissions};
+
+use super::trust::TrustPolicy;
+
+/// Access a plugin declares; mirrors `Capabilities` in `kargo-plugin-wasm`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    /// Files and directories, with everything below them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fs_read: Vec<PathBuf>,
+    /// Hosts, with `*` wildcards
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<String>,
+    /// Environment variable names
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+}
+
+impl Capabilities {
+    pub fn is_empty(&self) -> bool {
+        self.fs_read.is_empty() && self.network.is_empty() && self.env.is_empty()
+    }
+
+    /// What `self` has that `other` hasn't
+    pub fn beyond(&self, other: &Capabilities) -> Capabilities {
+        Capabilities {
+            fs_read: missing(&self.fs_read, &other.fs_read),
+            network: missing(&self.network, &other.network),
+            env: missing(&self.env, &other.env),
+        }
+    }
+}
+
+/// One line per capability, as listed when asking for approval
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for path in &self.fs_read {
+            writeln!(f, "  read files in {}", path.display())?;
+        }
+        for host in &self.network {
+            writeln!(f, "  send requests to {}", host)?;
+        }
+        for name in &self.env {
+            writeln!(f, "  read the environment variable {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+fn missing<T: Clone + PartialEq>(wanted: &[T], have: &[T]) -> Vec<T> {
+    wanted
+        .iter()
+        .filter(|item| !have.contains(item))
+        .cloned()
+        .collect()
+}
+
+impl WasmPermissions {
+    /// Everything granted, in the shape plugins declare it
+    pub fn granted(&self) -> Capabilities {
+        Capabilities {
+            fs_read: self.allowed_paths.clone(),
+            network: self.allowed_hosts.clone(),
+            env: self.allowed_env.clone(),
+        }
+    }
+
+    pub fn grant(&mut self, capabilities: &Capabilities) {
+        let new = capabilities.beyond(&self.granted());
+        self.allowed_paths.extend(new.fs_read);
+        self.allowed_hosts.extend(new.network);
+        self.allowed_env.extend(new.env);
+    }
+
+    /// Whether the plugin may read `path`: it is one of the allowed paths or
+    /// below one, once symlinks and relative paths are resolved
+    pub fn allows_path(&self, path: &Path) -> bool {
+        let Ok(path) = path.canonicalize() else {
+            return false;
+        };
+        self.allowed_paths
+            .iter()
+            .filter_map(|allowed| allowed.canonicalize().ok())
+            .any(|allowed| path.starts_with(allowed))
+    }
+
+    pub fn allows_env(&self, name: &str) -> bool {
+        self.allowed_env.iter().any(|allowed| allowed == name)
+    }
+}
+
+/// Settle the permissions of the plugin `name`, which has `permissions` and
+/// declares `requested`. Capabilities it hasn't been granted or asked about
+/// before are granted or declined as `policy` says; the permissions to
+/// record are returned when there was a new answer.
+pub fn review(
+    name: &str,
+    requested: &Capabilities,
+    permissions: &WasmPermissions,
+    policy: TrustPolicy,
+) -> Option<WasmPermissions> {
+    let new = requested
+        .beyond(&permissions.granted())
+        .beyond(&permissions.reviewed);
+    if new.is_empty() {
+        return None;
+    }
+    let grant = match policy {
+        TrustPolicy::Allow => true,
+        TrustPolicy::Deny => {
+            warn!(
+                "WASM plugin {} runs without access it asks for; grant it under \
+                 `wasm_permissions.{}` in the config:\n{}",
+                name,
+                name,
+                new.to_string().trim_end()
+            );
+            return None;
+        }
+        TrustPolicy::Prompt => match ask(name, &new) {
+            Some(grant) => grant,
+            None => {
+                warn!(
+                    "WASM plugin {} runs without access it asks for, with no terminal to \
+                     approve it; grant it under `wasm_permissions.{}` in the config:\n{}",
+                    name,
+                    name,
+                    new.to_string().trim_end()
+                );
+                return None;
+            }
+        },
+    };
+    let mut updated = permissions.clone();
+    if grant {
+        updated.grant(&new);
+    }
+    updated.reviewed = requested.clone();
+    Some(updated)
+}
+
+/// Save `permissions` as those of the plugin `name` in the config file at
+/// `path`, leaving the rest of the file's settings as they are. Read-only
+/// runs keep them for the run and write nothing.
+pub fn record(path: &Path, name: &str, permissions: &WasmPermissions) -> Result<()> {
+    if WriteGuard::current().is_read_only() {
+        info!(
+            "Not saving the permissions of {} to {}: kargo is running with --read-only",
+            name,
+            path.display()
+        );
+        return Ok(());
+    }
+    let mut config = if path.exists() {
+        Config::from_file(path)?
+    } else {
+        Config::default()
+    };
+    config
+        .wasm_permissions
+        .insert(name.to_string(), permissions.clone());
+    config.save(path)
+}
+
+/// Ask on the terminal; `None` when not attached to one
+fn ask(name: &str, capabilities: &Capabilities) -> Option<bool> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return None;
+    }
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "WASM plugin {} asks to:\n{}", name, capabilities);
+    let question = if WriteGuard::current().is_read_only() {
+        "Allow it for this run? [y/N] "
+    } else {
+        "Allow it? [y/N] "
+    };
+    let _ = write!(stderr, "{}", question);
+    let _ = stderr.flush();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok()?;
+    Some(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,74 @@
+//! Supervision of plugin background services
+//!
+//! [`supervise`] runs one plugin's [`PluginCommand::serve`] and starts it
+//! again whenever it stops, as long as its [`ServiceSpec`] allows. Shutdown
+//! goes through the session's [`HostEvents`]: once they are closed, a
+//! service that stops is not restarted and a pending restart is abandoned.
+
+use anyhow::Result;
+use futures::future;
+use kargo_plugin_api::{ExecutionContext, HostEvents, PluginCommand, ServiceSpec};
+
+use crate::logging::plugin_target;
+
+/// How a supervised service ended
+#[derive(Debug)]
+pub struct ServiceExit {
+    /// Times the service was started again after stopping
+    pub restarts: u32,
+    /// What its last run returned
+    pub result: Result<()>,
+}
+
+/// Serve `plugin` in a fresh `context()` until it stops for good or `events`
+/// are closed
+pub async fn supervise(
+    name: &str,
+    plugin: &dyn PluginCommand,
+    spec: &ServiceSpec,
+    context: impl Fn() -> ExecutionContext,
+    events: &HostEvents,
+) -> ServiceExit {
+    let target = plugin_target(name);
+    let mut restarts = 0;
+    loop {
+        log::info!(target: &target, "Starting service {}", name);
+        let result = plugin.serve(context()).await;
+        if events.is_closed() {
+            return ServiceExit { restarts, result };
+        }
+        match &result {
+            Ok(()) => log::info!(target: &target, "Service {} stopped", name),
+            Err(e) => log::error!(target: &target, "Service {} failed: {:#}", name, e),
+        }
+        let Some(delay) = spec.restart_delay(result.is_err(), restarts) else {
+            return ServiceExit { restarts, result };
+        };
+        log::info!(target: &target, "Restarting service {} in {:?}", name, delay);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = closed(events) => return ServiceExit { restarts, result },
+        }
+        restarts += 1;
+    }
+}
+
+/// Supervise every service in `services` at once; see [`supervise`]
+pub async fn supervise_all<'a>(
+    services: impl IntoIterator<Item = (&'a str, &'a dyn PluginCommand, ServiceSpec)>,
+    context: impl Fn(&str) -> ExecutionContext,
+    events: &HostEvents,
+) -> Vec<(String, ServiceExit)> {
+    let context = &context;
+    future::join_all(services.into_iter().map(|(name, plugin, spec)| async move {
+        let exit = supervise(name, plugin, &spec, || context(name), events).await;
+        (name.to_string(), exit)
+    }))
+    .await
+}
+
+/// Resolves once `events` are closed
+async fn closed(events: &HostEvents) {
+    let mut subscription = events.subscribe();
+    while subscription.recv().await.is_some() {}
+}
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use log::info;
 use std::path::Path;
-use syn::{Item, ItemFn, ItemImpl, ItemStatic, parse_file};
+use syn::{Attribute, Item, ItemFn, ItemImpl, ItemStatic, parse_file};
 
 /// Scan a plugin's source to verify it implements the required trait
 pub fn verify_native_plugin(source_path: &Path) -> Result<PluginInfo> {
@@ -43,8 +43,19 @@ pub fn verify_native_plugin(source_path: &Path) -> Result<PluginInfo> {
                     info!("Found plugin declaration");
                 }
             }
+            // #[plugin] generates the PluginCommand and the create function
+            Item::Struct(struct_item) if has_plugin_attribute(&struct_item.attrs) => {
+                plugin_info.implements_plugin_command = true;
+                plugin_info.has_create_function = true;
+                plugin_info.impl_type = Some(struct_item.ident.to_string());
+                info!("Found #[plugin] struct: {}", struct_item.ident);
+            }
             Item::Fn(fn_item) => {
-                if is_plugin_create_function(&fn_item) {
+                if has_plugin_attribute(&fn_item.attrs) {
+                    plugin_info.implements_plugin_command = true;
+                    plugin_info.has_create_function = true;
+                    info!("Found #[plugin] function: {}", fn_item.sig.ident);
+                } else if is_plugin_create_function(&fn_item) {
                     plugin_info.has_create_function = true;
                     info!("Found kargo_plugin_create function");
                 }
@@ -101,6 +112,15 @@ fn is_plugin_create_function(fn_item: &ItemFn) -> bool {
     fn_item.sig.ident == "kargo_plugin_create"
 }
 
+fn has_plugin_attribute(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "plugin")
+    })
+}
+
 fn extract_self_type(impl_item: &ItemImpl) -> Option<String> {
     match &*impl_item.self_ty {
         syn::Type::Path(type_path) => type_path
@@ -0,0 +1,294 @@
+//! Trust-on-first-use for plugins found in the plugin directories
+//!
+//! Anything that shows up in a plugin directory is loaded into the kargo
+//! process, so on a shared machine a dropped-in library runs with the
+//! permissions of whoever runs kargo next. The first time a plugin is seen
+//! the [`TrustPolicy`] decides whether it loads, and the decision is
+//! recorded together with the artifact's hash; a library whose hash changed
+//! since counts as new again. Plugin projects, which kargo builds itself, are
+//! hashed by their sources instead (see [`source_digest`]), so editing one
+//! asks again while rebuilding it doesn't. A project's `.cargo/` and the
+//! cargo config of the directories above it are hashed with the sources, as
+//! they can change what the build runs, and a scanned project is always
+//! built into a target directory of kargo's own rather than loaded from its
+//! `target/`, which the hash leaves out.
+//!
+//! Decisions are kept in their own `trusted-plugins.json` rather than in the
+//! kargo config. Config is layered from project directories, profiles and
+//! remote sources (see [`crate::config`]), any of which could then mark a
+//! plugin as trusted on the user's behalf; the trust file is only ever
+//! written by kargo for the user running it, and only read from their
+//! config directory.
+
+use anyhow::{Context, Result};
+use kargo_plugin_api::WriteGuard;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write as _};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What to do with a plugin kargo hasn't recorded a decision for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustPolicy {
+    /// Load it and remember it as trusted
+    Allow,
+    /// Skip it
+    Deny,
+    /// Ask on the terminal; skip it when there is no terminal to ask on
+    #[default]
+    Prompt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+/// A recorded decision about one plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustRecord {
+    pub decision: Decision,
+    /// `sha256:` digest of the library or module, or of a plugin project's
+    /// sources; `None` in decisions recorded before projects were hashed,
+    /// which are asked about again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Seconds since the Unix epoch
+    pub decided_at: u64,
+}
+
+/// Decisions about plugins, keyed by path
+#[derive(Debug, Default)]
+pub struct TrustStore {
+    path: PathBuf,
+    records: BTreeMap<PathBuf, TrustRecord>,
+}
+
+impl TrustStore {
+    /// `trusted-plugins.json` next to the user's plugin directory
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("trusted-plugins.json")
+    }
+
+    /// Read the decisions stored at `path`; none when it doesn't exist yet
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let records = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self { path, records })
+    }
+
+    pub fn records(&self) -> &BTreeMap<PathBuf, TrustRecord> {
+        &self.records
+    }
+
+    /// The recorded decision for `plugin`, unless its hash changed since
+    pub fn decision(&self, plugin: &Path, sha256: Option<&str>) -> Option<Decision> {
+        self.records
+            .get(plugin)
+            .filter(|record| record.sha256.as_deref() == sha256)
+            .map(|record| record.decision)
+    }
+
+    pub fn record(&mut self, plugin: &Path, sha256: Option<String>, decision: Decision) {
+        let decided_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.records.insert(
+            plugin.to_path_buf(),
+            TrustRecord {
+                decision,
+                sha256,
+                decided_at,
+            },
+        );
+    }
+
+    /// Drop the decision for `plugin`, so it is asked about again
+    pub fn forget(&mut self, plugin: &Path) -> bool {
+        self.records.remove(plugin).is_some()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.path.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        guard.write(&self.path, serde_json::to_string_pretty(&self.records)?)
+    }
+
+    /// Whether the plugin at `plugin` may be loaded, asking on the terminal
+    /// or applying `policy` when there is no decision for it yet. New
+    /// decisions are saved right away.
+    pub fn check(&mut self, plugin: &Path, sha256: Option<String>, policy: TrustPolicy) -> bool {
+        self.check_with(plugin, sha256, policy, ask)
+    }
+
+    /// [`Self::check`], asking `prompt` instead of the terminal under
+    /// [`TrustPolicy::Prompt`]. It gets the plugin, its digest and whether a
+    /// decision about an earlier version exists, and returns `None` when
+    /// there is nobody to ask.
+    pub fn check_with(
+        &mut self,
+        plugin: &Path,
+        sha256: Option<String>,
+        policy: TrustPolicy,
+        prompt: impl FnOnce(&Path, Option<&str>, bool) -> Option<Decision>,
+    ) -> bool {
+        if let Some(decision) = self.decision(plugin, sha256.as_deref()) {
+            return decision == Decision::Allow;
+        }
+        let changed = self.records.contains_key(plugin);
+        let decision = match policy {
+            TrustPolicy::Allow => Decision::Allow,
+            TrustPolicy::Deny => {
+                warn!(
+                    "Not loading untrusted plugin {}; run `kargo plugin trust {}` to allow it",
+                    plugin.display(),
+                    plugin.display()
+                );
+                return false;
+            }
+            TrustPolicy::Prompt => match prompt(plugin, sha256.as_deref(), changed) {
+                Some(decision) => decision,
+                None => {
+                    warn!(
+                        "Not loading new plugin {} without a terminal to confirm it; run \
+                         `kargo plugin trust {}` or set `plugin_trust: allow`",
+                        plugin.display(),
+                        plugin.display()
+                    );
+                    return false;
+                }
+            },
+        };
+        self.record(plugin, sha256, decision);
+        if let Err(e) = self.save() {
+            warn!("Failed to record plugin trust decision: {:#}", e);
+        }
+        decision == Decision::Allow
+    }
+}
+
+/// `sha256:` digest of the file at `path`
+pub fn file_digest(path: &Path) -> Result<String> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format_digest(&Sha256::digest(&data)))
+}
+
+/// Cargo config files, in a `.cargo` directory, that cargo reads
+const CARGO_CONFIGS: &[&str] = &["config.toml", "config"];
+
+/// `sha256:` digest of the plugin project in `dir`: the path and content of
+/// every file below it, leaving out `target` and hidden directories other
+/// than `.cargo` (such as `.git`), so building the project doesn't change it,
+/// and of the cargo config in the directories above it
+pub fn source_digest(dir: &Path) -> Result<String> {
+    let mut files = Vec::new();
+    collect_sources(dir, Path::new(""), &mut files)?;
+    files.sort();
+    let mut hasher = Sha256::new();
+    for relative in files {
+        hash_file(&mut hasher, &relative, &dir.join(&relative))?;
+    }
+    // Cargo also applies the config of every directory above the project
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+    for (up, ancestor) in dir.ancestors().enumerate().skip(1) {
+        for name in CARGO_CONFIGS {
+            let path = ancestor.join(".cargo").join(name);
+            if path.is_file() {
+                let relative: PathBuf = std::iter::repeat_n("..", up)
+                    .chain([".cargo", name])
+                    .collect();
+                hash_file(&mut hasher, &relative, &path)?;
+            }
+        }
+    }
+    Ok(format_digest(&hasher.finalize()))
+}
+
+/// Add the file at `path` to `hasher` as `relative`
+fn hash_file(hasher: &mut Sha256, relative: &Path, path: &Path) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    hasher.update(relative.to_string_lossy().replace('\\', "/").as_bytes());
+    hasher.update([0]);
+    hasher.update((data.len() as u64).to_le_bytes());
+    hasher.update(&data);
+    Ok(())
+}
+
+/// Digest of the plugin at `path`, a library, module or plugin project
+pub fn plugin_digest(path: &Path) -> Result<String> {
+    if path.is_dir() {
+        source_digest(path)
+    } else {
+        file_digest(path)
+    }
+}
+
+fn collect_sources(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = root.join(relative);
+    let entries =
+        std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let path = relative.join(&name);
+        if entry.file_type()?.is_dir() {
+            let hidden = name.to_string_lossy().starts_with('.') && name != ".cargo";
+            if name != "target" && !hidden {
+                collect_sources(root, &path, files)?;
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn format_digest(digest: &[u8]) -> String {
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// Ask on the terminal; `None` when not attached to one
+fn ask(plugin: &Path, sha256: Option<&str>, changed: bool) -> Option<Decision> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return None;
+    }
+    let mut stderr = std::io::stderr();
+    let _ = writeln!(
+        stderr,
+        "{} plugin: {}",
+        if changed { "Changed" } else { "New" },
+        plugin.display()
+    );
+    if let Some(sha256) = sha256 {
+        let _ = writeln!(stderr, "  {}", sha256);
+    }
+    let _ = write!(stderr, "Load it? [y/N] ");
+    let _ = stderr.flush();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok()?;
+    Some(match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Decision::Allow,
+        _ => Decision::Deny,
+    })
+}
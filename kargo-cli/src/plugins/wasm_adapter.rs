@@ -1,85 +1,241 @@
-use std::{
-    path::Path,
-    sync::{Arc, Mutex},
-};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, mpsc as std_mpsc};
+use std::thread;
 
 use anyhow::{Context, Result};
-use extism::{Manifest, Plugin, Wasm};
+use extism::{CompiledPlugin, Manifest, Plugin, Wasm};
 use tokio::sync::mpsc;
 
-use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+use kargo_plugin_api::{BoxFuture, DiagnosticsSchema, ExecutionContext, HelpInfo, PluginCommand};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
 
+use crate::config::WasmPermissions;
+
+use super::command_spec;
 use super::host_functions::{HostFunctionRequest, handle_requests, register_host_functions};
+use super::instance_pool::InstancePool;
+use super::module_cache::ModuleCache;
+use super::permissions::Capabilities;
+
+/// Optional export returning the plugin's [`DiagnosticsSchema`] as JSON
+const DIAGNOSTICS_SCHEMA_FN: &str = "_kargo_plugin_diagnostics_schema";
+/// Optional export returning the plugin's [`HelpInfo`] as JSON
+const HELP_INFO_FN: &str = "_kargo_plugin_help_info";
+/// Optional export returning the plugin's metadata as JSON, with the
+/// capabilities it needs and the kargo versions it works with
+const METADATA_FN: &str = "_kargo_plugin_metadata";
+
+/// The part of a plugin's metadata the host uses
+#[derive(Debug, Default, Deserialize)]
+struct Metadata {
+    #[serde(default)]
+    capabilities: Capabilities,
+    #[serde(default)]
+    requires_kargo: Option<String>,
+}
 
 pub struct WasmPluginAdapter {
-    plugin: Arc<Mutex<Plugin>>,
+    /// Instances of the module, compiled once at load
+    pool: InstancePool<Plugin>,
+    schema: Option<DiagnosticsSchema>,
+    help: Option<HelpInfo>,
+    capabilities: Capabilities,
+    requires_kargo: Option<String>,
     _sender: mpsc::Sender<HostFunctionRequest>,
 }
 
 impl WasmPluginAdapter {
-    pub fn new(file: &Path) -> Result<Self> {
+    /// Load the plugin at `file`, compiling it once for up to `pool_size`
+    /// instances that run calls in parallel. With a `module_cache`, the
+    /// compiled module is stored there and reused by later loads.
+    ///
+    /// Modules targeting WASI preview 1 get the clock and random number
+    /// generator from the WASI context. Outbound HTTP goes through Extism's
+    /// `http_request`, which only reaches the hosts in `permissions`; the
+    /// component-model wasi-http interface isn't supported by Extism.
+    /// Files and environment variables are only readable through the host
+    /// functions, within `permissions` as well.
+    pub fn new(
+        file: &Path,
+        permissions: &WasmPermissions,
+        pool_size: usize,
+        module_cache: Option<&ModuleCache>,
+    ) -> Result<Self> {
         let (tx, rx) = mpsc::channel(32);
 
         // Create manifest with the WASM file
         let wasm = Wasm::file(file);
-        let manifest = Manifest::new([wasm]);
+        let manifest =
+            Manifest::new([wasm]).with_allowed_hosts(permissions.allowed_hosts.iter().cloned());
+        if !permissions.allowed_hosts.is_empty() {
+            log::info!(
+                "{} may send HTTP requests to {}",
+                file.display(),
+                permissions.allowed_hosts.join(", ")
+            );
+        }
 
-        // Build plugin with host functions
-        let plugin = register_host_functions(tx.clone(), manifest)
-            .with_context(|| format!("Failed to create Extism plugin from: {}", file.display()))?;
+        // Compile with host functions; instances are made from the result
+        let cache_config = module_cache.and_then(|cache| match cache.config_file() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                log::warn!("Compiling {} without a cache: {:#}", file.display(), e);
+                None
+            }
+        });
+        let instantiate = Instantiator::spawn(file, manifest, tx.clone(), cache_config)?;
+        let pool = InstancePool::new(pool_size, move || instantiate.instance());
+        // The instance that answers the queries below stays warm for the
+        // first run
+        pool.warm(1)?;
 
-        let plugin = Arc::new(Mutex::new(plugin));
-        let plugin_clone = Arc::clone(&plugin);
-        tokio::spawn(handle_requests(plugin_clone, rx));
-        Ok(Self {
-            plugin,
+        tokio::spawn(handle_requests(rx, permissions.clone()));
+        let mut adapter = Self {
+            pool,
+            schema: None,
+            help: None,
+            capabilities: Capabilities::default(),
+            requires_kargo: None,
             _sender: tx,
-        })
+        };
+        // WASM plugins can't share a `DiagnosticSink`, so their diagnostics
+        // are parsed from output with the patterns they export
+        adapter.schema = adapter.load_optional(DIAGNOSTICS_SCHEMA_FN, "diagnostics schema");
+        adapter.help = adapter.load_optional(HELP_INFO_FN, "help info");
+        let metadata = adapter
+            .load_optional::<Metadata>(METADATA_FN, "metadata")
+            .unwrap_or_default();
+        adapter.capabilities = metadata.capabilities;
+        adapter.requires_kargo = metadata.requires_kargo;
+        Ok(adapter)
+    }
+
+    /// What the plugin declares it needs, which may be more than it was
+    /// granted
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// The kargo versions the plugin declares it works with
+    pub fn requires_kargo(&self) -> Option<&str> {
+        self.requires_kargo.as_deref()
+    }
+
+    /// Call the optional export `func`, if the plugin has it, and parse the
+    /// JSON it returns
+    fn load_optional<T: DeserializeOwned>(&self, func: &str, what: &str) -> Option<T> {
+        let exported = matches!(
+            self.pool.try_checkout(),
+            Ok(Some(plugin)) if plugin.function_exists(func)
+        );
+        if !exported {
+            return None;
+        }
+        match self
+            .json_call(func, "{}")
+            .and_then(|json| serde_json::from_str(&json).map_err(Into::into))
+        {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::warn!("Ignoring invalid {}: {:#}", what, e);
+                None
+            }
+        }
     }
 
     fn json_call(&self, func: &str, input: &str) -> Result<String> {
         let mut plugin = self
-            .plugin
+            .pool
+            .try_checkout()?
+            .context("Every instance of the plugin is busy")?;
+        match plugin.call::<&str, String>(func, input) {
+            Ok(output) => Ok(output),
+            Err(e) => {
+                plugin.discard();
+                Err(e).with_context(|| format!("Failed to call WASM function: {}", func))
+            }
+        }
+    }
+}
+
+/// Makes instances of a compiled module for the pool. Neither a
+/// `CompiledPlugin` nor the builder with its host functions can be shared
+/// between threads, while the pool makes instances on whichever thread checks
+/// one out, so the module is compiled and kept on a thread of its own that
+/// instantiates it on request. The thread exits once the pool is dropped.
+struct Instantiator {
+    requests: Mutex<std_mpsc::Sender<std_mpsc::SyncSender<Result<Plugin>>>>,
+}
+
+impl Instantiator {
+    /// Compile the module in `manifest` with the host functions on a new
+    /// thread, failing if it doesn't compile
+    fn spawn(
+        file: &Path,
+        manifest: Manifest,
+        host: mpsc::Sender<HostFunctionRequest>,
+        cache_config: Option<PathBuf>,
+    ) -> Result<Self> {
+        let (requests, incoming) = std_mpsc::channel::<std_mpsc::SyncSender<Result<Plugin>>>();
+        let (compiled_tx, compiled_rx) = std_mpsc::sync_channel(1);
+        let name = file.display().to_string();
+        thread::Builder::new()
+            .name(format!("wasm {}", name))
+            .spawn(move || {
+                let builder = register_host_functions(host, manifest);
+                let builder = match cache_config {
+                    Some(config) => builder.with_cache_config(config),
+                    None => builder,
+                };
+                let compiled = match CompiledPlugin::new(builder) {
+                    Ok(compiled) => {
+                        let _ = compiled_tx.send(Ok(()));
+                        compiled
+                    }
+                    Err(e) => {
+                        let _ = compiled_tx.send(Err(e));
+                        return;
+                    }
+                };
+                for reply in incoming {
+                    let instance = Plugin::new_from_compiled(&compiled)
+                        .context("Failed to instantiate WASM plugin");
+                    let _ = reply.send(instance);
+                }
+            })
+            .context("Failed to start the WASM compiler thread")?;
+        compiled_rx
+            .recv()
+            .context("WASM compiler thread exited")?
+            .with_context(|| format!("Failed to create Extism plugin from: {}", name))?;
+        Ok(Self {
+            requests: Mutex::new(requests),
+        })
+    }
+
+    /// A new instance of the module
+    fn instance(&self) -> Result<Plugin> {
+        let (reply, instance) = std_mpsc::sync_channel(1);
+        self.requests
             .lock()
-            .map_err(|e| anyhow::anyhow!("Failed to lock plugin mutex: {}", e))?;
-        let output = plugin
-            .call::<&str, String>(func, input)
-            .with_context(|| format!("Failed to call WASM function: {}", func))?;
-        Ok(output)
+            .unwrap_or_else(|e| e.into_inner())
+            .send(reply)
+            .context("WASM compiler thread exited")?;
+        instance.recv().context("WASM compiler thread exited")?
     }
 }
 
 impl PluginCommand for WasmPluginAdapter {
     fn clap(&self) -> clap::Command {
         match self.json_call("_kargo_plugin_get_command_spec_json", "{}") {
-            Ok(json) => {
-                // Parse the JSON into command name and about
-                match serde_json::from_str::<serde_json::Value>(&json) {
-                    Ok(val) => {
-                        let name = match val.get("name").and_then(|v| v.as_str()) {
-                            Some(n) => n.to_string(),
-                            None => {
-                                eprintln!("Plugin command missing 'name' field");
-                                return clap::Command::new("wasm-missing-name");
-                            }
-                        };
-                        let about = val
-                            .get("about")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string());
-
-                        let mut cmd = clap::Command::new(name);
-                        if let Some(about) = about {
-                            cmd = cmd.about(about);
-                        }
-                        cmd
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to parse command spec: {}", e);
-                        clap::Command::new("wasm-bad-spec")
-                    }
+            Ok(json) => match command_spec::parse(&json) {
+                Ok(spec) => spec.to_clap(),
+                Err(e) => {
+                    eprintln!("Failed to parse command spec: {:#}", e);
+                    clap::Command::new("wasm-bad-spec")
                 }
-            }
+            },
             Err(e) => {
                 eprintln!("{e}");
                 clap::Command::new("wasm-error")
@@ -88,15 +244,40 @@ impl PluginCommand for WasmPluginAdapter {
     }
 
     fn run(&self, ctx: ExecutionContext) -> BoxFuture {
-        let plugin = Arc::clone(&self.plugin);
+        let pool = self.pool.clone();
+        let schema = self.schema.clone();
         Box::pin(async move {
             let input = serde_json::to_string(&ctx.matched_args)?;
-            let mut plugin = plugin
-                .lock()
-                .map_err(|e| anyhow::anyhow!("Failed to lock plugin mutex: {}", e))?;
-            let output = plugin.call::<&str, String>("_kargo_plugin_execute", &input)?;
+            let mut plugin = pool.checkout().await?;
+            // Calls block, including on host functions that are answered by
+            // the runtime, so they run off it; other runs take other instances
+            let (plugin, output) = tokio::task::spawn_blocking(move || {
+                let output = plugin.call::<&str, String>("_kargo_plugin_execute", &input);
+                (plugin, output)
+            })
+            .await?;
+            let output = match output {
+                Ok(output) => output,
+                Err(e) => {
+                    plugin.discard();
+                    return Err(e);
+                }
+            };
             println!("{}", output);
+            if let Some(schema) = &schema {
+                for diagnostic in schema.parse_output(&output) {
+                    ctx.diagnostics.report(diagnostic);
+                }
+            }
             Ok(())
         })
     }
+
+    fn diagnostics_schema(&self) -> Option<DiagnosticsSchema> {
+        self.schema.clone()
+    }
+
+    fn help_info(&self) -> Option<HelpInfo> {
+        self.help.clone()
+    }
 }
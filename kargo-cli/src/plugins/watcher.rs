@@ -0,0 +1,94 @@
+//! Noticing when plugin files are rebuilt
+//!
+//! [`SourceWatcher`] subscribes to file system events for the directories
+//! the watched files are in rather than for the files themselves: a build
+//! usually replaces a library instead of writing to it, which would end a
+//! watch on the old file. Events arrive while a file is still being written,
+//! so [`SourceWatcher::changed`] waits for them to settle.
+
+use anyhow::{Context, Result, bail};
+use log::warn;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+pub struct SourceWatcher {
+    files: Vec<PathBuf>,
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl SourceWatcher {
+    /// Watch `files` for changes
+    pub fn new<'a>(files: impl IntoIterator<Item = &'a Path>) -> Result<Self> {
+        let (tx, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .context("Failed to start watching plugin files")?;
+
+        let mut dirs = Vec::new();
+        let mut watched = Vec::new();
+        for file in files {
+            let (Some(dir), Some(name)) = (file.parent(), file.file_name()) else {
+                bail!("{} is not a file", file.display());
+            };
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            // Events name files below the directory as it was watched
+            let dir = dir
+                .canonicalize()
+                .with_context(|| format!("Failed to watch {}", file.display()))?;
+            if !dirs.contains(&dir) {
+                watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .with_context(|| format!("Failed to watch {}", dir.display()))?;
+                dirs.push(dir.clone());
+            }
+            watched.push(dir.join(name));
+        }
+        Ok(Self {
+            files: watched,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Wait until a watched file changes and then goes `settle` without
+    /// changing again
+    pub async fn changed(&mut self, settle: Duration) -> Result<()> {
+        self.next_change().await?;
+        while let Ok(result) = tokio::time::timeout(settle, self.next_change()).await {
+            result?;
+        }
+        Ok(())
+    }
+
+    async fn next_change(&mut self) -> Result<()> {
+        while !self.next_is_change().await? {}
+        Ok(())
+    }
+
+    /// Whether the next event changed a watched file
+    async fn next_is_change(&mut self) -> Result<bool> {
+        let Some(event) = self.events.recv().await else {
+            bail!("Stopped watching plugin files");
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Watching plugin files: {}", e);
+                return Ok(false);
+            }
+        };
+        // Loading a plugin reads its file
+        if matches!(event.kind, EventKind::Access(_)) {
+            return Ok(false);
+        }
+        Ok(event.paths.iter().any(|path| self.files.contains(path)))
+    }
+}
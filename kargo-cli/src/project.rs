@@ -1,4 +1,5 @@
 use anyhow::{Result, anyhow};
+use kargo_plugin_api::metadata::{MetadataMode, PackageInfo, ProjectMetadata};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -203,24 +204,23 @@ impl ProjectAnalyzer {
     async fn analyze_cargo_toml(&self, path: &Path) -> Result<ProjectType> {
         let content = fs::read_to_string(path).await?;
         let document = content.parse::<DocumentMut>()?;
+        let metadata = load_metadata(path).await;
 
         // Check if this is a workspace
         if document.get("workspace").is_some() {
-            return self.analyze_workspace(path, document).await;
+            return self
+                .analyze_workspace(path, document, metadata.as_ref())
+                .await;
         }
 
         // Determine the project type and configuration
-        let is_binary = path
-            .parent()
-            .map_or(false, |parent| parent.join("src/main.rs").exists());
-        let is_library = path
-            .parent()
-            .map_or(false, |parent| parent.join("src/lib.rs").exists());
-        let is_proc_macro = document
-            .get("lib")
-            .and_then(|lib| lib.get("proc-macro"))
-            .and_then(|proc_macro| proc_macro.as_bool())
-            == Some(true);
+        let targets = match metadata.as_ref().and_then(|m| m.member_at(path)) {
+            Some(package) => Targets::from_package(package),
+            None => Targets::guess(path, &document),
+        };
+        let is_binary = targets.bin.is_some();
+        let is_library = targets.lib.is_some();
+        let is_proc_macro = targets.proc_macro;
 
         let name = document
             .get("package")
@@ -229,12 +229,12 @@ impl ProjectAnalyzer {
             .ok_or_else(|| anyhow::anyhow!("Missing package name in Cargo.toml"))?
             .to_string();
 
-        let has_build_script = path
-            .parent()
-            .map_or(false, |parent| parent.join("build.rs").exists());
+        let has_build_script = targets.build_script;
 
         // Check if this is a workspace member
-        let workspace_info = self.extract_workspace_info(path, &document).await;
+        let workspace_info = self
+            .extract_workspace_info(path, &document, metadata.as_ref())
+            .await;
 
         // Handle workspace member
         if let Some((workspace_root, inherited_fields, workspace_deps)) = workspace_info {
@@ -249,22 +249,22 @@ impl ProjectAnalyzer {
                 ProjectType::Hybrid(HybridConfig {
                     name: name.clone(),
                     path: path.to_path_buf(),
-                    bin_path: path.parent().map(|p| p.join("src/main.rs")),
-                    lib_path: path.parent().map(|p| p.join("src/lib.rs")),
+                    bin_path: targets.bin.clone(),
+                    lib_path: targets.lib.clone(),
                     has_build_script,
                 })
             } else if is_binary {
                 ProjectType::Binary(BinaryConfig {
                     name: name.clone(),
                     path: path.to_path_buf(),
-                    bin_path: path.parent().map(|p| p.join("src/main.rs")),
+                    bin_path: targets.bin.clone(),
                     has_build_script,
                 })
             } else if is_library {
                 ProjectType::Library(LibraryConfig {
                     name: name.clone(),
                     path: path.to_path_buf(),
-                    lib_path: path.parent().map(|p| p.join("src/lib.rs")),
+                    lib_path: targets.lib.clone(),
                     has_build_script,
                 })
             } else {
@@ -292,22 +292,22 @@ impl ProjectAnalyzer {
             Ok(ProjectType::Hybrid(HybridConfig {
                 name,
                 path: path.to_path_buf(),
-                bin_path: path.parent().map(|p| p.join("src/main.rs")),
-                lib_path: path.parent().map(|p| p.join("src/lib.rs")),
+                bin_path: targets.bin.clone(),
+                lib_path: targets.lib.clone(),
                 has_build_script,
             }))
         } else if is_binary {
             Ok(ProjectType::Binary(BinaryConfig {
                 name,
                 path: path.to_path_buf(),
-                bin_path: path.parent().map(|p| p.join("src/main.rs")),
+                bin_path: targets.bin.clone(),
                 has_build_script,
             }))
         } else if is_library {
             Ok(ProjectType::Library(LibraryConfig {
                 name,
                 path: path.to_path_buf(),
-                lib_path: path.parent().map(|p| p.join("src/lib.rs")),
+                lib_path: targets.lib.clone(),
                 has_build_script,
             }))
         } else {
@@ -316,7 +316,12 @@ impl ProjectAnalyzer {
     }
 
     /// Analyze a workspace Cargo.toml
-    async fn analyze_workspace(&self, path: &Path, document: DocumentMut) -> Result<ProjectType> {
+    async fn analyze_workspace(
+        &self,
+        path: &Path,
+        document: DocumentMut,
+        metadata: Option<&ProjectMetadata>,
+    ) -> Result<ProjectType> {
         let workspace = document
             .get("workspace")
             .ok_or_else(|| anyhow::anyhow!("No [workspace] section found in Cargo.toml"))?;
@@ -326,11 +331,19 @@ impl ProjectAnalyzer {
             .parent()
             .ok_or_else(|| anyhow::anyhow!("Cargo.toml has no parent directory"))?;
 
-        let members = match workspace
-            .get("members")
-            .and_then(|members| members.as_array())
-        {
-            Some(members) => members
+        // Cargo expands member globs and applies `exclude`; the TOML only has the patterns
+        let members = match (
+            metadata,
+            workspace
+                .get("members")
+                .and_then(|members| members.as_array()),
+        ) {
+            (Some(metadata), _) => metadata
+                .members()
+                .map(|package| package.dir().to_path_buf())
+                .filter(|dir| !same_dir(dir, parent_dir))
+                .collect(),
+            (None, Some(members)) => members
                 .iter()
                 .filter_map(|m| m.as_str())
                 .map(|m| {
@@ -341,7 +354,7 @@ impl ProjectAnalyzer {
                     }
                 })
                 .collect::<Vec<_>>(),
-            None => Vec::new(),
+            (None, None) => Vec::new(),
         };
 
         let default_members = match workspace
@@ -440,6 +453,7 @@ impl ProjectAnalyzer {
         &self,
         path: &Path,
         document: &DocumentMut,
+        metadata: Option<&ProjectMetadata>,
     ) -> Option<(PathBuf, HashMap<String, bool>, Vec<String>)> {
         // Check if this is explicitly a workspace member
         let workspace_path = document
@@ -449,7 +463,14 @@ impl ProjectAnalyzer {
 
         let parent_dir = path.parent()?;
 
-        let workspace_root = if let Some(workspace_path) = workspace_path {
+        let workspace_root = if let Some(metadata) = metadata {
+            // Cargo reports the workspace the package really belongs to, which
+            // is the package itself when it's standalone or excluded
+            if same_dir(&metadata.workspace_root, parent_dir) {
+                return None;
+            }
+            metadata.workspace_root.join("Cargo.toml")
+        } else if let Some(workspace_path) = workspace_path {
             // Explicit workspace path
             if workspace_path.starts_with("/") {
                 PathBuf::from(workspace_path)
@@ -549,6 +570,148 @@ impl ProjectAnalyzer {
     }
 }
 
+/// Targets of a package, from cargo metadata when available
+struct Targets {
+    bin: Option<PathBuf>,
+    lib: Option<PathBuf>,
+    proc_macro: bool,
+    build_script: bool,
+}
+
+impl Targets {
+    fn from_package(package: &PackageInfo) -> Self {
+        let src = |kinds: &[&str]| {
+            package
+                .targets
+                .iter()
+                .find(|t| t.kinds.iter().any(|k| kinds.contains(&k.as_str())))
+                .map(|t| t.src_path.clone())
+        };
+        Self {
+            bin: src(&["bin"]),
+            lib: src(&["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"]),
+            proc_macro: package.has_target_kind("proc-macro"),
+            build_script: package.has_target_kind("custom-build"),
+        }
+    }
+
+    /// The target tables of the manifest plus cargo's autodiscovery, for
+    /// manifests cargo can't load
+    fn guess(path: &Path, document: &DocumentMut) -> Self {
+        let root = path.parent().unwrap_or(Path::new("."));
+        let existing = |file: &str| Some(root.join(file)).filter(|file| file.exists());
+        let package = document.get("package");
+        let auto = |key: &str| {
+            package
+                .and_then(|package| package.get(key))
+                .and_then(|auto| auto.as_bool())
+                != Some(false)
+        };
+        let lib = document.get("lib");
+
+        let lib_path = match lib
+            .and_then(|lib| lib.get("path"))
+            .and_then(|path| path.as_str())
+        {
+            Some(path) => Some(root.join(path)),
+            None if lib.is_some() || auto("autolib") => existing("src/lib.rs"),
+            None => None,
+        };
+
+        // Declared binaries come first, as in cargo's target list; an entry
+        // without a path is found where autodiscovery would have put it
+        let package_name = package
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str());
+        let declared = document
+            .get("bin")
+            .and_then(|bins| bins.as_array_of_tables())
+            .into_iter()
+            .flat_map(|bins| bins.iter());
+        let mut bins = Vec::new();
+        for bin in declared {
+            if let Some(path) = bin.get("path").and_then(|path| path.as_str()) {
+                bins.push(root.join(path));
+                continue;
+            }
+            let Some(name) = bin.get("name").and_then(|name| name.as_str()) else {
+                continue;
+            };
+            let mut candidates = vec![
+                format!("src/bin/{}.rs", name),
+                format!("src/bin/{}/main.rs", name),
+            ];
+            if Some(name) == package_name {
+                candidates.insert(0, "src/main.rs".to_string());
+            }
+            bins.extend(candidates.iter().find_map(|file| existing(file)));
+        }
+        if auto("autobins") {
+            bins.extend(existing("src/main.rs"));
+            bins.extend(discover_bins(&root.join("src/bin")));
+        }
+
+        // `build = false` turns the build script off, a string moves it
+        let build_script = match package.and_then(|package| package.get("build")) {
+            Some(build) if build.as_bool() == Some(false) => false,
+            Some(build) => match build.as_str() {
+                Some(script) => root.join(script).exists(),
+                None => existing("build.rs").is_some(),
+            },
+            None => existing("build.rs").is_some(),
+        };
+
+        Self {
+            bin: bins.into_iter().next(),
+            lib: lib_path,
+            proc_macro: lib
+                .and_then(|lib| lib.get("proc-macro"))
+                .and_then(|proc_macro| proc_macro.as_bool())
+                == Some(true),
+            build_script,
+        }
+    }
+}
+
+/// Binaries cargo discovers under `src/bin`: `<name>.rs` files and
+/// `<name>/main.rs` directories, in name order
+fn discover_bins(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut bins: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            if path.is_dir() {
+                Some(path.join("main.rs")).filter(|main| main.exists())
+            } else {
+                (path.extension().is_some_and(|ext| ext == "rs")).then_some(path)
+            }
+        })
+        .collect();
+    bins.sort();
+    bins
+}
+
+/// `cargo metadata --no-deps` for the manifest, or `None` when cargo can't
+/// load it and the TOML heuristics have to do
+async fn load_metadata(path: &Path) -> Option<ProjectMetadata> {
+    let manifest = path.to_path_buf();
+    tokio::task::spawn_blocking(move || ProjectMetadata::load(&manifest, MetadataMode::Declared))
+        .await
+        .ok()?
+        .map_err(|e| log::debug!("Falling back to manifest heuristics: {:#}", e))
+        .ok()
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 /// Extract version from a TOML value
 fn extract_version_from_toml(value: &Item) -> Option<String> {
     match value {
@@ -0,0 +1,422 @@
+//! `kargo publish-check`: what would stop `cargo publish`, found in one pass
+//!
+//! `cargo publish` stops at the first problem, and some only show up once
+//! the upload is rejected: a path dependency without a version, a wildcard
+//! requirement, a missing README or license, a crate over the size limit or a
+//! version that's already taken. [`preflight`] checks all of them for every
+//! publishable member and reports them together; `kargo publish-check`
+//! fails unless every report [`passed`](PublishReport::passed), so it can gate
+//! a `cargo publish` in scripts and CI.
+
+use anyhow::{Context, Result, bail};
+use cargo_metadata::semver::VersionReq;
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::WriteGuard;
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+use serde::Serialize;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+/// Largest `.crate` file crates.io accepts
+pub const MAX_CRATE_SIZE: u64 = 10 * 1024 * 1024;
+
+pub fn command() -> Command {
+    Command::new("publish-check")
+        .about("Check that crates are ready to publish to their registry")
+        .arg(
+            Arg::new("package")
+                .short('p')
+                .long("package")
+                .value_name("NAME")
+                .help("Member to check (defaults to every publishable member)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("allow-dirty")
+                .long("allow-dirty")
+                .help("Package even with uncommitted changes")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-verify")
+                .long("no-verify")
+                .help("Don't build the packaged crate")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .help("Don't ask the registry whether the version is taken")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the reports as JSON")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let packages: Vec<String> = matches
+        .get_many::<String>("package")
+        .map(|names| names.cloned().collect())
+        .unwrap_or_default();
+    let options = PreflightOptions {
+        allow_dirty: matches.get_flag("allow-dirty"),
+        no_verify: matches.get_flag("no-verify"),
+        offline: matches.get_flag("offline"),
+    };
+
+    let reports = preflight(&dir.join("Cargo.toml"), &packages, &options)?;
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for (i, report) in reports.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("{}", report.render());
+        }
+    }
+
+    let failed = reports.iter().filter(|r| !r.passed()).count();
+    if failed > 0 {
+        bail!(
+            "{} of {} crate(s) can't be published as they are",
+            failed,
+            reports.len()
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// The check couldn't run; it doesn't fail the report
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// What's wrong, or why the check was skipped
+    pub details: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, problems: Vec<String>) -> Self {
+        let status = if problems.is_empty() {
+            CheckStatus::Pass
+        } else {
+            CheckStatus::Fail
+        };
+        Self {
+            name,
+            status,
+            details: problems,
+        }
+    }
+
+    fn skipped(name: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skipped,
+            details: vec![reason.into()],
+        }
+    }
+}
+
+/// The checks of one package
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishReport {
+    pub package: String,
+    pub version: String,
+    pub checks: Vec<Check>,
+}
+
+impl PublishReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let verdict = if self.passed() {
+            "ready to publish"
+        } else {
+            "not ready to publish"
+        };
+        let _ = writeln!(out, "{} {}: {}", self.package, self.version, verdict);
+        for check in &self.checks {
+            let mark = match check.status {
+                CheckStatus::Pass => "✓",
+                CheckStatus::Fail => "✗",
+                CheckStatus::Skipped => "-",
+            };
+            let _ = writeln!(out, "  {} {}", mark, check.name);
+            for detail in &check.details {
+                let _ = writeln!(out, "      {}", detail);
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PreflightOptions {
+    /// Pass `--allow-dirty` to `cargo package`
+    pub allow_dirty: bool,
+    /// Pass `--no-verify` to `cargo package`
+    pub no_verify: bool,
+    /// Skip the registry lookup
+    pub offline: bool,
+}
+
+/// Check the members named in `packages` of the workspace owning
+/// `manifest_path`. Without names, that's the member `manifest_path`
+/// belongs to, or every member that can be published for the workspace root.
+pub fn preflight(
+    manifest_path: &Path,
+    packages: &[String],
+    options: &PreflightOptions,
+) -> Result<Vec<PublishReport>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .with_context(|| format!("cargo metadata failed for {}", manifest_path.display()))?;
+    let members = metadata.workspace_packages();
+    // In a member's directory, that member is the one to check
+    let in_member = manifest_path
+        .canonicalize()
+        .ok()
+        .filter(|path| path.parent() != Some(metadata.workspace_root.as_std_path()))
+        .and_then(|path| members.iter().find(|p| p.manifest_path == path));
+    let packages: Vec<String> = match in_member {
+        Some(member) if packages.is_empty() => vec![member.name.to_string()],
+        _ => packages.to_vec(),
+    };
+    if let Some(missing) = packages
+        .iter()
+        .find(|name| !members.iter().any(|p| p.name.as_str() == name.as_str()))
+    {
+        bail!("No workspace member is called {}", missing);
+    }
+
+    let mut reports = Vec::new();
+    for package in members {
+        let unpublishable = package.publish.as_ref().is_some_and(Vec::is_empty);
+        if packages.is_empty() && unpublishable {
+            continue;
+        }
+        if !packages.is_empty() && !packages.iter().any(|n| n == package.name.as_str()) {
+            continue;
+        }
+        log::info!("Checking {} {}", package.name, package.version);
+
+        let mut checks = Vec::new();
+        if unpublishable {
+            checks.push(Check::new(
+                "publish",
+                vec!["The manifest sets `publish = false`".to_string()],
+            ));
+        }
+        checks.extend(manifest_checks(package));
+        checks.extend(package_checks(&metadata, package, options));
+        checks.push(if options.offline {
+            Check::skipped("version", "Not looked up with --offline")
+        } else {
+            version_check(package)
+        });
+        reports.push(PublishReport {
+            package: package.name.to_string(),
+            version: package.version.to_string(),
+            checks,
+        });
+    }
+    Ok(reports)
+}
+
+/// The checks that only need the manifest
+pub fn manifest_checks(package: &Package) -> Vec<Check> {
+    let mut unversioned = Vec::new();
+    let mut wildcards = Vec::new();
+    for dep in &package.dependencies {
+        if dep.req != VersionReq::STAR {
+            continue;
+        }
+        let local = dep.path.is_some()
+            || dep
+                .source
+                .as_ref()
+                .is_some_and(|s| s.repr.starts_with("git+"));
+        if !local {
+            wildcards.push(format!("{} = \"*\"", dep.name));
+        } else if dep.kind != DependencyKind::Development {
+            // cargo drops dev-dependencies without a version when packaging
+            let source = match &dep.path {
+                Some(path) => format!("path {}", path),
+                None => "git".to_string(),
+            };
+            unversioned.push(format!("{} ({}) has no version", dep.name, source));
+        }
+    }
+
+    let readme = match package.readme() {
+        Some(path) if path.exists() => Vec::new(),
+        Some(path) => vec![format!("{} doesn't exist", path)],
+        None => vec!["No README; add README.md or set `readme`".to_string()],
+    };
+    let license = match (&package.license, package.license_file()) {
+        (Some(_), _) => Vec::new(),
+        (None, Some(path)) if path.exists() => Vec::new(),
+        (None, Some(path)) => vec![format!("{} doesn't exist", path)],
+        (None, None) => vec!["Neither `license` nor `license-file` is set".to_string()],
+    };
+    let description = match &package.description {
+        Some(description) if !description.trim().is_empty() => Vec::new(),
+        _ => vec!["`description` is required by crates.io".to_string()],
+    };
+
+    vec![
+        Check::new("path-dependencies", unversioned),
+        Check::new("wildcards", wildcards),
+        Check::new("readme", readme),
+        Check::new("license", license),
+        Check::new("description", description),
+    ]
+}
+
+/// Lines of stderr shown when `cargo package` fails without an `error` line
+const STDERR_TAIL: usize = 10;
+
+/// The failed `package` check for a `cargo package` that exited with
+/// `status`: its errors, or the end of `stderr` if it printed none
+pub fn package_failure(status: impl fmt::Display, stderr: &str) -> Check {
+    let lines: Vec<String> = stderr
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    // Warnings about metadata are already covered by the other checks
+    let errors = match lines.iter().position(|line| line.starts_with("error")) {
+        Some(first) => &lines[first..],
+        None => &lines[lines.len().saturating_sub(STDERR_TAIL)..],
+    };
+    let mut problems = vec![format!("cargo package failed ({})", status)];
+    problems.extend_from_slice(errors);
+    Check::new("package", problems)
+}
+
+/// Run `cargo package` and check the size of the `.crate` it writes
+fn package_checks(
+    metadata: &Metadata,
+    package: &Package,
+    options: &PreflightOptions,
+) -> [Check; 2] {
+    if WriteGuard::current().is_read_only() {
+        let reason = "cargo package writes to the target directory, and kargo is read-only";
+        return [
+            Check::skipped("package", reason),
+            Check::skipped("size", reason),
+        ];
+    }
+
+    let mut command = CargoCommand::new("package")
+        .arg("--manifest-path")
+        .arg(package.manifest_path.as_str());
+    if options.allow_dirty {
+        command = command.arg("--allow-dirty");
+    }
+    if options.no_verify {
+        command = command.arg("--no-verify");
+    }
+    let output = match cargo_runner::run(&command) {
+        Ok(output) => output,
+        Err(e) => {
+            return [
+                Check::new("package", vec![format!("{:#}", e)]),
+                Check::skipped("size", "cargo package didn't run"),
+            ];
+        }
+    };
+    if let Some(status) = output.status.filter(|status| !status.success()) {
+        return [
+            package_failure(status, &output.stderr),
+            Check::skipped("size", "cargo package failed"),
+        ];
+    }
+
+    let crate_file = metadata
+        .target_directory
+        .join("package")
+        .join(format!("{}-{}.crate", package.name, package.version));
+    let size = match std::fs::metadata(&crate_file) {
+        Ok(file) if file.len() > MAX_CRATE_SIZE => Check::new(
+            "size",
+            vec![format!(
+                "{} is {:.1} MiB; crates.io accepts up to {} MiB",
+                crate_file,
+                file.len() as f64 / (1024.0 * 1024.0),
+                MAX_CRATE_SIZE / (1024 * 1024)
+            )],
+        ),
+        Ok(_) => Check::new("size", Vec::new()),
+        Err(e) => Check::skipped("size", format!("Can't read {}: {}", crate_file, e)),
+    };
+    [Check::new("package", Vec::new()), size]
+}
+
+/// Ask the registry the package publishes to whether its version exists
+fn version_check(package: &Package) -> Check {
+    let mut command =
+        CargoCommand::new("info").arg(format!("{}@{}", package.name, package.version));
+    if let Some(registry) = package.publish.as_ref().and_then(|r| r.first()) {
+        command = command.args(["--registry", registry]);
+    }
+    // Registry config applies per directory
+    if let Some(dir) = package.manifest_path.parent() {
+        command = command.current_dir(dir);
+    }
+    let output = match cargo_runner::run(&command) {
+        Ok(output) => output,
+        Err(e) => return Check::skipped("version", format!("{:#}", e)),
+    };
+    if output.success() {
+        return Check::new(
+            "version",
+            vec![format!(
+                "{} {} is already published; bump the version",
+                package.name, package.version
+            )],
+        );
+    }
+    let stderr = &output.stderr;
+    if stderr.contains("could not find") {
+        Check::new("version", Vec::new())
+    } else {
+        let reason = stderr
+            .lines()
+            .rfind(|line| !line.trim().is_empty())
+            .unwrap_or("cargo info failed");
+        Check::skipped(
+            "version",
+            format!("Couldn't ask the registry: {}", reason.trim()),
+        )
+    }
+}
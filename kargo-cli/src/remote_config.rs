@@ -0,0 +1,511 @@
+//! Config shared by a team from a URL or git repository
+//!
+//! The config file can pull settings in from elsewhere, so a platform team
+//! keeps registries, plugin sets, trust and network settings in one place:
+//!
+//! ```yaml
+//! include:
+//!   - https://config.example.com/kargo-org.yaml
+//!   - url: https://config.example.com/kargo-build.toml
+//!     public_key: RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3
+//!   - git: https://github.com/example/kargo-config
+//!     ref: main
+//!     path: kargo.yaml
+//! ```
+//!
+//! Included files are YAML, or TOML when their name ends in `.toml`. Later
+//! includes override earlier ones, and the local file overrides them all;
+//! tables are merged key by key, anything else is replaced. Settings saved
+//! back to the local file leave out whatever an include already provides.
+//!
+//! Includes are only fetched by `kargo config sync`, never while the config
+//! loads, and kept in the cache directory. HTTP includes are revalidated
+//! with their `ETag`, git ones against the commit `ref` points to. With a
+//! `public_key`, the file must come with a minisign signature, `<url>.minisig`
+//! or `<path>.minisig` in the repository, or it isn't accepted.
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::{HttpClient, WriteGuard};
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::schedule::unix_now;
+
+/// Age after which a cached include is reported as due for a sync
+const STALE_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// File read from a git include without a `path`
+const DEFAULT_GIT_PATH: &str = "kargo.yaml";
+
+pub fn command() -> Command {
+    Command::new("config")
+        .about("Inspect the config and sync the shared parts it includes")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("sync")
+                .about("Fetch the config's includes into the cache")
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Download every include again, even if unchanged")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("show").about("Print the effective config, includes and profile applied"),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("sync", sub)) => sync(sub.get_flag("force")).await,
+        Some(("show", _)) => {
+            print!("{}", serde_yaml::to_string(&Config::active()?)?);
+            Ok(())
+        }
+        _ => unreachable!("subcommand_required"),
+    }
+}
+
+async fn sync(force: bool) -> Result<()> {
+    // Read the include list alone, the rest of the file may need the includes
+    // to be complete
+    let path = Config::path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let local: Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Invalid config {}", path.display()))?;
+    let includes = includes(&local)?;
+    if includes.is_empty() {
+        println!("{} includes nothing", path.display());
+        return Ok(());
+    }
+    let cache = IncludeCache::new(IncludeCache::default_dir());
+    let client = HttpClient::new(concat!("kargo/", env!("CARGO_PKG_VERSION")))?;
+    let mut failed = 0;
+    for include in &includes {
+        match cache.sync(include, &client, force).await {
+            Ok(Synced::Updated) => println!("✓ {} updated", include),
+            Ok(Synced::Unchanged) => println!("✓ {} unchanged", include),
+            Err(e) => {
+                failed += 1;
+                eprintln!("✗ {}: {:#}", include, e);
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} includes failed to sync", failed, includes.len());
+    }
+    Ok(())
+}
+
+/// A config file included from elsewhere
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Include {
+    Url(String),
+    Source(IncludeSource),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncludeSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Repository to read `path` from, instead of a URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Branch or tag of `git`; its default branch when unset
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// File in `git`, `kargo.yaml` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Minisign public key the file must be signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl Include {
+    pub fn source(&self) -> IncludeSource {
+        match self {
+            Self::Url(url) => IncludeSource {
+                url: Some(url.clone()),
+                ..IncludeSource::default()
+            },
+            Self::Source(source) => source.clone(),
+        }
+    }
+
+    /// Name of the included file, for parsing it as TOML or YAML
+    fn file_name(&self) -> String {
+        let source = self.source();
+        match (&source.url, &source.path) {
+            (Some(url), _) => url.clone(),
+            (None, Some(path)) => path.clone(),
+            (None, None) => DEFAULT_GIT_PATH.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Include {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = self.source();
+        match (&source.url, &source.git) {
+            (Some(url), _) => f.write_str(url),
+            (None, Some(git)) => {
+                write!(f, "{}", git)?;
+                if let Some(reference) = &source.reference {
+                    write!(f, "#{}", reference)?;
+                }
+                write!(f, ":{}", source.path.as_deref().unwrap_or(DEFAULT_GIT_PATH))
+            }
+            (None, None) => f.write_str("(include without url or git)"),
+        }
+    }
+}
+
+/// How a cached include was fetched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheMeta {
+    /// `ETag` of an HTTP include, commit of a git one
+    pub version: Option<String>,
+    /// Unix timestamp (seconds) of the last sync
+    pub fetched_at: u64,
+    /// `sha256:` of the file as fetched
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synced {
+    Updated,
+    Unchanged,
+}
+
+/// Includes as last synced, one YAML file and its [`CacheMeta`] each
+pub struct IncludeCache {
+    dir: PathBuf,
+}
+
+impl IncludeCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `<cache dir>/kargo/config-includes`
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("config-includes")
+    }
+
+    fn paths(&self, include: &Include) -> (PathBuf, PathBuf) {
+        let key = sha256(include.to_string().as_bytes());
+        let key = key.trim_start_matches("sha256:");
+        (
+            self.dir.join(format!("{}.yaml", key)),
+            self.dir.join(format!("{}.json", key)),
+        )
+    }
+
+    /// The settings of `include` as last synced, `None` before the first sync
+    pub fn load(&self, include: &Include) -> Result<Option<(Value, CacheMeta)>> {
+        let (content, meta) = self.paths(include);
+        if !content.is_file() || !meta.is_file() {
+            return Ok(None);
+        }
+        let value = serde_yaml::from_str(&std::fs::read_to_string(&content)?)
+            .with_context(|| format!("Corrupt cached include {}", content.display()))?;
+        let meta = serde_json::from_str(&std::fs::read_to_string(&meta)?)
+            .with_context(|| format!("Corrupt cached include {}", meta.display()))?;
+        Ok(Some((value, meta)))
+    }
+
+    /// Check `content`, fetched as `version` of `include`, against the
+    /// include's key and format, and cache it
+    pub fn store(
+        &self,
+        include: &Include,
+        content: &str,
+        signature: Option<&str>,
+        version: Option<String>,
+    ) -> Result<()> {
+        if let Some(public_key) = &include.source().public_key {
+            let signature = signature.context("Not signed, but a public_key is configured")?;
+            verify_signature(content.as_bytes(), signature, public_key)?;
+        }
+        let mut value = parse_settings(content, &include.file_name())?;
+        if let Some(mapping) = value.as_mapping_mut()
+            && mapping.remove("include").is_some()
+        {
+            log::warn!("Ignoring the includes of included config {}", include);
+        }
+
+        let (content_path, meta_path) = self.paths(include);
+        let guard = WriteGuard::current();
+        guard.create_dir_all(&self.dir)?;
+        let meta = CacheMeta {
+            version,
+            fetched_at: unix_now(),
+            sha256: sha256(content.as_bytes()),
+        };
+        guard.write(&content_path, serde_yaml::to_string(&value)?)?;
+        guard.write(&meta_path, serde_json::to_string_pretty(&meta)?)
+    }
+
+    /// Fetch `include` unless the cached copy is still current, or always
+    /// with `force`
+    pub async fn sync(
+        &self,
+        include: &Include,
+        client: &HttpClient,
+        force: bool,
+    ) -> Result<Synced> {
+        // Syncing only fills the cache, so don't fetch what can't be kept
+        WriteGuard::current().check(&self.dir)?;
+        let cached = match force {
+            true => None,
+            false => self.load(include).ok().flatten(),
+        };
+        let cached_version = cached.as_ref().and_then(|(_, meta)| meta.version.clone());
+        let source = include.source();
+        match (&source.url, &source.git) {
+            (Some(url), _) => {
+                let mut request = client.get(url);
+                if let Some(etag) = &cached_version {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                let response = client.send(request).await?;
+                if response.status() == StatusCode::NOT_MODIFIED {
+                    self.touch(include, cached)?;
+                    return Ok(Synced::Unchanged);
+                }
+                let response = response.error_for_status()?;
+                let etag = response
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                let content = response.text().await?;
+                let signature = match source.public_key {
+                    Some(_) => {
+                        let url = format!("{}.minisig", url);
+                        let response = client.send(client.get(&url)).await?;
+                        Some(
+                            response
+                                .error_for_status()
+                                .with_context(|| format!("No signature at {}", url))?
+                                .text()
+                                .await?,
+                        )
+                    }
+                    None => None,
+                };
+                self.store(include, &content, signature.as_deref(), etag)?;
+                Ok(Synced::Updated)
+            }
+            (None, Some(git)) => {
+                let reference = source.reference.as_deref().unwrap_or("HEAD");
+                let commit = remote_commit(git, reference).await?;
+                if cached_version.as_deref() == Some(commit.as_str()) {
+                    self.touch(include, cached)?;
+                    return Ok(Synced::Unchanged);
+                }
+                let checkout = tempfile::tempdir()?;
+                let mut clone = tokio::process::Command::new("git");
+                clone.args(["clone", "--quiet", "--depth", "1"]);
+                if let Some(reference) = &source.reference {
+                    clone.args(["--branch", reference]);
+                }
+                let status = clone.arg(git).arg(checkout.path()).status().await?;
+                if !status.success() {
+                    bail!("git clone of {} failed", git);
+                }
+                let path = checkout
+                    .path()
+                    .join(source.path.as_deref().unwrap_or(DEFAULT_GIT_PATH));
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("No {} in {}", path.display(), git))?;
+                let signature_path = PathBuf::from(format!("{}.minisig", path.display()));
+                let signature = std::fs::read_to_string(signature_path).ok();
+                self.store(include, &content, signature.as_deref(), Some(commit))?;
+                Ok(Synced::Updated)
+            }
+            (None, None) => bail!("An include needs a url or git repository"),
+        }
+    }
+
+    /// Record that the cached copy was found current
+    fn touch(&self, include: &Include, cached: Option<(Value, CacheMeta)>) -> Result<()> {
+        let Some((_, mut meta)) = cached else {
+            return Ok(());
+        };
+        meta.fetched_at = unix_now();
+        let (_, meta_path) = self.paths(include);
+        WriteGuard::current().write(&meta_path, serde_json::to_string_pretty(&meta)?)
+    }
+}
+
+/// The commit `reference` of the repository `git` points to
+async fn remote_commit(git: &str, reference: &str) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(["ls-remote", git, reference])
+        .output()
+        .await
+        .context("Failed to run git ls-remote")?;
+    if !output.status.success() {
+        bail!(
+            "git ls-remote {} failed: {}",
+            git,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("{} has no {}", git, reference))
+}
+
+/// Check the minisign `signature` of `content` against `public_key`
+pub fn verify_signature(content: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key.trim())
+        .map_err(|e| anyhow!("Invalid public_key: {}", e))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| anyhow!("Invalid signature: {}", e))?;
+    public_key
+        .verify(content, &signature, false)
+        .map_err(|e| anyhow!("Signature check failed: {}", e))
+}
+
+/// The settings in an included file, TOML if `name` ends in `.toml` and YAML
+/// otherwise
+pub fn parse_settings(content: &str, name: &str) -> Result<Value> {
+    let value = if name.ends_with(".toml") {
+        let doc: toml_edit::DocumentMut = content.parse().context("Invalid TOML")?;
+        toml_table(doc.as_table())
+    } else {
+        serde_yaml::from_str(content).context("Invalid YAML")?
+    };
+    if !value.is_mapping() {
+        bail!("An included config must be a table of settings");
+    }
+    Ok(value)
+}
+
+fn toml_table(table: &dyn toml_edit::TableLike) -> Value {
+    let mut mapping = Mapping::new();
+    for (key, item) in table.iter() {
+        if let Some(value) = toml_item(item) {
+            mapping.insert(Value::String(key.to_string()), value);
+        }
+    }
+    Value::Mapping(mapping)
+}
+
+pub(crate) fn toml_item(item: &toml_edit::Item) -> Option<Value> {
+    match item {
+        toml_edit::Item::None => None,
+        toml_edit::Item::Value(value) => Some(toml_value(value)),
+        toml_edit::Item::Table(table) => Some(toml_table(table)),
+        toml_edit::Item::ArrayOfTables(tables) => Some(Value::Sequence(
+            tables.iter().map(|table| toml_table(table)).collect(),
+        )),
+    }
+}
+
+fn toml_value(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(s) => Value::String(s.value().clone()),
+        toml_edit::Value::Integer(n) => Value::Number((*n.value()).into()),
+        toml_edit::Value::Float(n) => Value::Number((*n.value()).into()),
+        toml_edit::Value::Boolean(b) => Value::Bool(*b.value()),
+        toml_edit::Value::Datetime(d) => Value::String(d.value().to_string()),
+        toml_edit::Value::Array(items) => Value::Sequence(items.iter().map(toml_value).collect()),
+        toml_edit::Value::InlineTable(table) => toml_table(table),
+    }
+}
+
+/// The local settings `local` laid over its includes, with the settings
+/// coming from includes alone; `None` for those when nothing is included
+pub fn resolve(local: Value, cache: &IncludeCache) -> Result<(Value, Option<Value>)> {
+    let includes = includes(&local)?;
+    if includes.is_empty() {
+        return Ok((local, None));
+    }
+    let mut base = Value::Mapping(Mapping::new());
+    for include in &includes {
+        match cache.load(include)? {
+            Some((value, meta)) => {
+                if unix_now().saturating_sub(meta.fetched_at) > STALE_AFTER.as_secs() {
+                    log::info!(
+                        "Config include {} is out of date; run `kargo config sync`",
+                        include
+                    );
+                }
+                base = merge(base, value);
+            }
+            None => log::warn!(
+                "Config include {} hasn't been fetched; run `kargo config sync`",
+                include
+            ),
+        }
+    }
+    Ok((merge(base.clone(), local), Some(base)))
+}
+
+/// The `include` list of the config `local`
+fn includes(local: &Value) -> Result<Vec<Include>> {
+    match local.get("include") {
+        Some(includes) => serde_yaml::from_value(includes.clone()).context("Invalid include"),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// `overlay` laid over `base`: tables are merged key by key, anything else in
+/// `overlay` replaces what `base` has
+pub fn merge(base: Value, overlay: Value) -> Value {
+    match (base, overlay) {
+        (Value::Mapping(mut base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                let merged = match base.remove(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+            Value::Mapping(base)
+        }
+        (_, overlay) => overlay,
+    }
+}
+
+/// Remove from `value` the settings `base` has the same, so `value` keeps
+/// only what differs from it
+pub fn strip(value: &mut Value, base: &Value) {
+    let (Some(mapping), Some(base)) = (value.as_mapping_mut(), base.as_mapping()) else {
+        return;
+    };
+    mapping.retain(|key, value| match base.get(key) {
+        Some(base) if base == value => false,
+        Some(base) => {
+            strip(value, base);
+            !value.as_mapping().is_some_and(Mapping::is_empty)
+        }
+        None => true,
+    });
+}
+
+fn sha256(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
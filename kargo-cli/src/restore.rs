@@ -0,0 +1,161 @@
+//! `kargo restore`: inspect and restore the snapshots taken by earlier runs
+//!
+//! `--list` shows each run's snapshot with the operation it performed, and
+//! `--snapshot ID` picks one to roll back to instead of the most recent.
+//!
+//! `--preview` prints the diff restoring would apply — current contents on
+//! the `-` side, backed-up contents on the `+` side — without touching any
+//! file. Glob arguments limit both the preview and the restore to matching
+//! paths, so a single manifest can be rolled back while the rest of a run's
+//! changes are kept.
+
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::io::{IsTerminal, Write as _};
+use std::process::Stdio;
+
+use crate::backup::{BackupManager, FileDiff, PathFilter};
+use crate::events::EventBus;
+use crate::schedule::{ago, unix_now};
+
+pub fn command() -> Command {
+    Command::new("restore")
+        .about("Restore files from the snapshot of an earlier run")
+        .arg(
+            Arg::new("paths")
+                .value_name("GLOB")
+                .help("Only restore files matching these globs (absolute or relative to the current directory)")
+                .num_args(0..),
+        )
+        .arg(
+            Arg::new("preview")
+                .long("preview")
+                .help("Show what restoring would change without restoring anything")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .help("List the available snapshots")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["preview", "paths"]),
+        )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .alias("backup")
+                .value_name("ID")
+                .help("Snapshot to restore from (defaults to the most recent)"),
+        )
+        .arg(
+            Arg::new("no-pager")
+                .long("no-pager")
+                .help("Print the preview directly instead of through a pager")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    let root = BackupManager::default_root();
+
+    if matches.get_flag("list") {
+        let snapshots = BackupManager::list(&root)?;
+        if snapshots.is_empty() {
+            println!("No snapshots in {}", root.display());
+        }
+        let now = unix_now();
+        for snapshot in snapshots.iter().rev() {
+            println!(
+                "{:<40} {:<16} {:<10} {} file(s)",
+                snapshot.id,
+                snapshot.operation,
+                ago(snapshot.created, now),
+                snapshot.files
+            );
+        }
+        return Ok(());
+    }
+
+    let backup = BackupManager::open(
+        &root,
+        matches.get_one::<String>("snapshot").map(String::as_str),
+        events.clone(),
+    )?;
+    let filter = PathFilter::new(matches.get_many::<String>("paths").into_iter().flatten())?;
+
+    if matches.get_flag("preview") {
+        let diffs = backup.diffs(&filter)?;
+        if diffs.is_empty() {
+            println!("No backed-up files match in {}", backup.id());
+            return Ok(());
+        }
+        show(&render(&diffs), !matches.get_flag("no-pager"));
+        return Ok(());
+    }
+
+    let restored = backup.restore_matching(&filter)?;
+    if restored.is_empty() {
+        println!("No backed-up files match in {}", backup.id());
+    }
+    for path in &restored {
+        println!("Restored {}", path.display());
+    }
+    Ok(())
+}
+
+fn render(diffs: &[FileDiff]) -> String {
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let mut out = String::new();
+    for diff in diffs {
+        if diff.unified.is_empty() {
+            out.push_str(&format!("{}: unchanged\n", diff.path.display()));
+            continue;
+        }
+        for line in diff.unified.lines() {
+            let style = match line {
+                _ if !color => None,
+                l if l.starts_with("---") || l.starts_with("+++") => Some("1"),
+                l if l.starts_with("@@") => Some("36"),
+                l if l.starts_with('+') => Some("32"),
+                l if l.starts_with('-') => Some("31"),
+                _ => None,
+            };
+            match style {
+                Some(code) => out.push_str(&format!("\x1b[{}m{}\x1b[0m\n", code, line)),
+                None => {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Write `text` through `KARGO_PAGER`, `PAGER` or `less` when stdout is a
+/// terminal, falling back to printing it directly
+fn show(text: &str, use_pager: bool) {
+    if use_pager && std::io::stdout().is_terminal() && page(text).is_ok() {
+        return;
+    }
+    print!("{}", text);
+}
+
+fn page(text: &str) -> Result<()> {
+    let pager = std::env::var("KARGO_PAGER")
+        .or_else(|_| std::env::var("PAGER"))
+        .unwrap_or_else(|_| "less -FRX".to_string());
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().ok_or_else(|| anyhow::anyhow!("Empty pager"))?;
+
+    let mut child = std::process::Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may exit before reading everything (e.g. `q` in less)
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
@@ -0,0 +1,242 @@
+//! `kargo log`: the events of past runs
+//!
+//! Every run appends its events to a JSONL file under
+//! [`EventLog::default_dir`] (see [`crate::events`]). Without arguments the
+//! most recent run's events of info severity and above are printed, each
+//! with the time since the run's first event; `--list` shows the runs that
+//! have a log, and a run id picks one of them.
+//!
+//! `--severity`, `--source` and `--grep` narrow the events down and `--tail`
+//! keeps the last few. `--follow` goes on printing events as a run still in
+//! progress writes them, until interrupted.
+
+use anyhow::{Context, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::fs::File;
+use std::io::{Read as _, Seek as _, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::events::{Event, EventKind, EventLog, Severity, Source, parse_lines};
+use crate::schedule::{ago, unix_now};
+
+/// How often `--follow` checks the log for new events
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn command() -> Command {
+    Command::new("log")
+        .about("Show, filter and follow the events of past runs")
+        .arg(
+            Arg::new("run")
+                .value_name("RUN")
+                .help("Run to show (defaults to the most recent; see --list)"),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .help("List the runs with a log")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["run", "tail", "follow"]),
+        )
+        .arg(
+            Arg::new("severity")
+                .long("severity")
+                .value_name("LEVEL")
+                .help("Only show events at least this severe")
+                .value_parser(clap::value_parser!(Severity))
+                .default_value("info"),
+        )
+        .arg(
+            Arg::new("source")
+                .long("source")
+                .value_name("SOURCE")
+                .help("Only show events from this part of kargo (repeatable)")
+                .value_parser(clap::value_parser!(Source))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("grep")
+                .long("grep")
+                .value_name("TEXT")
+                .help("Only show events whose description contains TEXT"),
+        )
+        .arg(
+            Arg::new("tail")
+                .short('n')
+                .long("tail")
+                .value_name("N")
+                .help("Only show the last N matching events")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("follow")
+                .short('f')
+                .long("follow")
+                .help("Keep printing events as the run writes them")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the matching events as JSON lines")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Which events `kargo log` prints
+#[derive(Debug, Clone)]
+pub struct EventFilter {
+    pub severity: Severity,
+    /// Sources to show; all of them when empty
+    pub sources: Vec<Source>,
+    pub grep: Option<String>,
+}
+
+impl EventFilter {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            severity: matches
+                .get_one::<Severity>("severity")
+                .copied()
+                .unwrap_or(Severity::Info),
+            sources: matches
+                .get_many::<Source>("source")
+                .map(|sources| sources.copied().collect())
+                .unwrap_or_default(),
+            grep: matches.get_one::<String>("grep").cloned(),
+        }
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        event.severity >= self.severity
+            && (self.sources.is_empty() || self.sources.contains(&event.source))
+            && self
+                .grep
+                .as_ref()
+                .is_none_or(|text| event.kind.to_string().contains(text.as_str()))
+    }
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<()> {
+    let log = EventLog::new(EventLog::default_dir());
+    if matches.get_flag("list") {
+        return list(&log);
+    }
+
+    let run = match matches.get_one::<String>("run") {
+        Some(run) => run.clone(),
+        None => log
+            .runs()?
+            .pop()
+            .with_context(|| format!("No run logs found in {}", log.dir().display()))?,
+    };
+    let path = log.path(&run);
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("No log of run {} ({})", run, path.display()))?;
+    // A line still being written is picked up by --follow
+    let complete = content.rfind('\n').map_or(0, |end| end + 1);
+    let events = parse_lines(&content[..complete]);
+
+    let filter = EventFilter::from_matches(matches);
+    let json = matches.get_flag("json");
+    let started = events.first().map_or(0, |event| event.timestamp);
+    let matching: Vec<&Event> = events
+        .iter()
+        .filter(|event| filter.matches(event))
+        .collect();
+    let skip = matches
+        .get_one::<usize>("tail")
+        .map_or(0, |tail| matching.len().saturating_sub(*tail));
+    for event in &matching[skip..] {
+        print_event(event, started, json)?;
+    }
+
+    if matches.get_flag("follow") {
+        follow(&path, complete as u64, started, &filter, json).await?;
+    }
+    Ok(())
+}
+
+fn list(log: &EventLog) -> Result<()> {
+    let runs = log.runs()?;
+    if runs.is_empty() {
+        println!("No run logs found in {}", log.dir().display());
+        return Ok(());
+    }
+    let now = unix_now();
+    for run in runs {
+        let events = log.read(&run)?;
+        let Some(first) = events.first() else {
+            continue;
+        };
+        let errors = events
+            .iter()
+            .filter(|event| event.severity == Severity::Error)
+            .count();
+        let command = events
+            .iter()
+            .find_map(|event| match &event.kind {
+                EventKind::KargoCommandStarted { subcommand, .. } => Some(subcommand.as_str()),
+                _ => None,
+            })
+            .unwrap_or("-");
+        println!(
+            "{}  {:<10} {:>6} events  {:>4} errors  {}",
+            run,
+            ago(first.timestamp / 1000, now),
+            events.len(),
+            errors,
+            command
+        );
+    }
+    Ok(())
+}
+
+/// Print the events appended to `path` after `offset` as they come
+async fn follow(
+    path: &Path,
+    mut offset: u64,
+    mut started: u64,
+    filter: &EventFilter,
+    json: bool,
+) -> Result<()> {
+    loop {
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut appended = String::new();
+        file.read_to_string(&mut appended)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let Some(end) = appended.rfind('\n') else {
+            continue;
+        };
+        offset += end as u64 + 1;
+        for event in parse_lines(&appended[..end]) {
+            if started == 0 {
+                started = event.timestamp;
+            }
+            if filter.matches(&event) {
+                print_event(&event, started, json)?;
+            }
+        }
+    }
+}
+
+fn print_event(event: &Event, started: u64, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(event)?);
+        return Ok(());
+    }
+    let elapsed = event.timestamp.saturating_sub(started);
+    println!(
+        "{:>4}:{:02}.{:03}  {:<7}  {:<8}  {}",
+        elapsed / 60_000,
+        elapsed / 1000 % 60,
+        elapsed % 1000,
+        event.severity,
+        event.source,
+        event.kind
+    );
+    Ok(())
+}
@@ -0,0 +1,485 @@
+//! `kargo schedule`: recurring jobs driven by a single cron or systemd timer
+//!
+//! Jobs live in the `schedule` section of the config file:
+//!
+//! ```yaml
+//! schedule:
+//!   - name: upgrade-check
+//!     command: upgrade --dry-run
+//!     every: nightly
+//!   - name: inventory
+//!     command: inventory
+//!     every: weekly
+//! ```
+//!
+//! `kargo schedule run` starts every job whose interval has elapsed since its
+//! last run, so the timer only needs to fire more often than the shortest
+//! interval. Each job runs as its own `kargo` process; its
+//! [`StatusReport`](crate::status::StatusReport) is kept as `<job>.json` and
+//! the run is appended to `history.jsonl`, both in the schedule state
+//! directory.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, requested_profile};
+use crate::events::{EventBus, EventKind, Source};
+
+/// How often a job runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Interval(Duration);
+
+impl Interval {
+    pub fn duration(self) -> Duration {
+        self.0
+    }
+}
+
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+
+    /// `hourly`, `daily`/`nightly`, `weekly`, or a count with a unit:
+    /// `30m`, `6h`, `2d`, `1w`
+    fn from_str(s: &str) -> Result<Self> {
+        const MINUTE: u64 = 60;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+        const WEEK: u64 = 7 * DAY;
+
+        let secs = match s.trim() {
+            "hourly" => HOUR,
+            "daily" | "nightly" => DAY,
+            "weekly" => WEEK,
+            spec => {
+                let split = spec
+                    .find(|c: char| !c.is_ascii_digit())
+                    .with_context(|| format!("Interval '{}' is missing a unit", spec))?;
+                let (count, unit) = spec.split_at(split);
+                let count: u64 = count
+                    .parse()
+                    .with_context(|| format!("Invalid interval '{}'", spec))?;
+                let unit = match unit {
+                    "m" => MINUTE,
+                    "h" => HOUR,
+                    "d" => DAY,
+                    "w" => WEEK,
+                    other => bail!("Unknown interval unit '{}' (use m, h, d or w)", other),
+                };
+                count * unit
+            }
+        };
+        if secs == 0 {
+            bail!("Interval '{}' must be greater than zero", s);
+        }
+        Ok(Interval(Duration::from_secs(secs)))
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        for (unit, size) in [("w", 604_800), ("d", 86_400), ("h", 3_600)] {
+            if secs.is_multiple_of(size) {
+                return write!(f, "{}{}", secs / size, unit);
+            }
+        }
+        write!(f, "{}m", secs / 60)
+    }
+}
+
+impl TryFrom<String> for Interval {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Interval> for String {
+    fn from(interval: Interval) -> Self {
+        interval.to_string()
+    }
+}
+
+/// A recurring job from the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub name: String,
+    /// `kargo` arguments, e.g. `upgrade --dry-run`
+    pub command: String,
+    pub every: Interval,
+}
+
+/// One execution of a job, as recorded in the history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+    pub job: String,
+    /// Unix timestamp (seconds) the run started at
+    pub started: u64,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    /// `status` from the job's status file, when it wrote one
+    pub status: Option<String>,
+}
+
+impl JobRun {
+    /// Finished cleanly; updates being available still counts as success
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == 0 || self.exit_code == 2
+    }
+}
+
+/// Run history and per-job status files
+pub struct Scheduler {
+    state_dir: PathBuf,
+}
+
+impl Scheduler {
+    pub fn new(state_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            state_dir: state_dir.into(),
+        }
+    }
+
+    pub fn default_state_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("kargo")
+            .join("schedule")
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.state_dir.join("history.jsonl")
+    }
+
+    /// Where the latest run of `job` leaves its status report
+    pub fn status_file(&self, job: &str) -> PathBuf {
+        self.state_dir.join(format!("{}.json", job))
+    }
+
+    /// All recorded runs, oldest first. Unreadable lines are skipped.
+    pub fn history(&self) -> Result<Vec<JobRun>> {
+        let path = self.history_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn record(&self, run: &JobRun) -> Result<()> {
+        std::fs::create_dir_all(&self.state_dir)
+            .with_context(|| format!("Failed to create {}", self.state_dir.display()))?;
+        let path = self.history_path();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(run)?)?;
+        Ok(())
+    }
+
+    pub fn last_run(&self, history: &[JobRun], job: &str) -> Option<u64> {
+        history
+            .iter()
+            .filter(|run| run.job == job)
+            .map(|run| run.started)
+            .max()
+    }
+
+    /// Whether `job` hasn't run within its interval as of `now` (unix seconds).
+    ///
+    /// A twentieth of the interval is allowed as slack, so a timer firing at
+    /// the same time every night isn't skipped because last night's run
+    /// started a few seconds late.
+    pub fn is_due(&self, history: &[JobRun], job: &ScheduledJob, now: u64) -> bool {
+        let interval = job.every.duration().as_secs();
+        match self.last_run(history, &job.name) {
+            Some(last) => now.saturating_sub(last) >= interval - interval / 20,
+            None => true,
+        }
+    }
+
+    /// Run `job` as a child `kargo` process and record the outcome
+    pub async fn execute(&self, job: &ScheduledJob, events: &EventBus) -> Result<JobRun> {
+        let exe = std::env::current_exe().context("Failed to locate the kargo executable")?;
+        let status_file = self.status_file(&job.name);
+        std::fs::create_dir_all(&self.state_dir)
+            .with_context(|| format!("Failed to create {}", self.state_dir.display()))?;
+        let _ = std::fs::remove_file(&status_file);
+
+        events.publish(
+            Source::Schedule,
+            EventKind::ScheduledJobStarted {
+                job: job.name.clone(),
+            },
+        );
+        let started = unix_now();
+        let timer = Instant::now();
+        let mut command = tokio::process::Command::new(&exe);
+        command.arg("--status-file").arg(&status_file);
+        // Jobs run under the same profile as the scheduler itself
+        if let Some(profile) = requested_profile() {
+            command.arg("--profile").arg(profile);
+        }
+        let exit = command
+            .args(job.command.split_whitespace())
+            .status()
+            .await
+            .with_context(|| format!("Failed to start job {}", job.name))?;
+
+        let run = JobRun {
+            job: job.name.clone(),
+            started,
+            duration_ms: timer.elapsed().as_millis() as u64,
+            // Killed by a signal: report it as a plain failure
+            exit_code: exit.code().unwrap_or(1),
+            status: read_status(&status_file),
+        };
+        self.record(&run)?;
+        events.publish(
+            Source::Schedule,
+            EventKind::ScheduledJobFinished {
+                job: job.name.clone(),
+                exit_code: run.exit_code,
+                status_file,
+            },
+        );
+        Ok(run)
+    }
+}
+
+fn read_status(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let report: serde_json::Value = serde_json::from_str(&content).ok()?;
+    report.get("status")?.as_str().map(str::to_string)
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub(crate) fn ago(then: u64, now: u64) -> String {
+    let secs = now.saturating_sub(then);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+pub fn command() -> Command {
+    let name = || Arg::new("name").value_name("NAME").required(true);
+    Command::new("schedule")
+        .about("Manage recurring jobs; point a cron entry or systemd timer at `kargo schedule run`")
+        .subcommand_required(true)
+        .subcommand(Command::new("list").about("Show configured jobs and when they last ran"))
+        .subcommand(
+            Command::new("add")
+                .about("Add a job to the config file")
+                .arg(name())
+                .arg(
+                    Arg::new("every")
+                        .long("every")
+                        .value_name("INTERVAL")
+                        .help("hourly, daily, nightly, weekly, or e.g. 30m, 6h, 2d")
+                        .value_parser(clap::value_parser!(Interval))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("command")
+                        .value_name("COMMAND")
+                        .help("kargo arguments to run, e.g. upgrade --dry-run")
+                        .num_args(1..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a job from the config file")
+                .arg(name()),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Run every job that is due")
+                .arg(
+                    Arg::new("jobs")
+                        .value_name("NAME")
+                        .help("Only consider these jobs")
+                        .num_args(0..),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Run the selected jobs even if they are not due")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("history").about("Show recorded runs").arg(
+                Arg::new("name")
+                    .value_name("NAME")
+                    .help("Only show this job"),
+            ),
+        )
+}
+
+pub async fn run(matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    let scheduler = Scheduler::new(Scheduler::default_state_dir());
+    match matches.subcommand() {
+        Some(("list", _)) => list(&scheduler),
+        Some(("add", sub)) => add(sub),
+        Some(("remove", sub)) => remove(sub),
+        Some(("run", sub)) => run_due(&scheduler, sub, events).await,
+        Some(("history", sub)) => history(&scheduler, sub.get_one::<String>("name")),
+        _ => unreachable!("subcommand_required"),
+    }
+}
+
+fn list(scheduler: &Scheduler) -> Result<()> {
+    let config = Config::load()?;
+    if config.schedule.is_empty() {
+        println!("No scheduled jobs");
+        return Ok(());
+    }
+    let history = scheduler.history()?;
+    let now = unix_now();
+    for job in &config.schedule {
+        let last = scheduler
+            .last_run(&history, &job.name)
+            .map(|t| ago(t, now))
+            .unwrap_or_else(|| "never".to_string());
+        let due = if scheduler.is_due(&history, job, now) {
+            " (due)"
+        } else {
+            ""
+        };
+        println!(
+            "{:<20} every {:<5} last run {}{}  kargo {}",
+            job.name, job.every, last, due, job.command
+        );
+    }
+    Ok(())
+}
+
+fn add(matches: &ArgMatches) -> Result<()> {
+    let name = matches.get_one::<String>("name").expect("required");
+    let every = *matches.get_one::<Interval>("every").expect("required");
+    let command: Vec<&str> = matches
+        .get_many::<String>("command")
+        .expect("required")
+        .map(String::as_str)
+        .collect();
+
+    let path = Config::path();
+    let mut config = Config::load()?;
+    if config.schedule.iter().any(|job| &job.name == name) {
+        bail!("A job named '{}' already exists", name);
+    }
+    config.schedule.push(ScheduledJob {
+        name: name.clone(),
+        command: command.join(" "),
+        every,
+    });
+    config.save(&path)?;
+    println!("Added {} to {}", name, path.display());
+    Ok(())
+}
+
+fn remove(matches: &ArgMatches) -> Result<()> {
+    let name = matches.get_one::<String>("name").expect("required");
+    let path = Config::path();
+    let mut config = Config::load()?;
+    let before = config.schedule.len();
+    config.schedule.retain(|job| &job.name != name);
+    if config.schedule.len() == before {
+        bail!("No job named '{}'", name);
+    }
+    config.save(&path)?;
+    println!("Removed {} from {}", name, path.display());
+    Ok(())
+}
+
+async fn run_due(scheduler: &Scheduler, matches: &ArgMatches, events: &EventBus) -> Result<()> {
+    let config = Config::load()?;
+    let selected: Vec<&String> = matches
+        .get_many::<String>("jobs")
+        .map(|names| names.collect())
+        .unwrap_or_default();
+    for name in &selected {
+        if !config.schedule.iter().any(|job| &&job.name == name) {
+            bail!("No job named '{}'", name);
+        }
+    }
+    let force = matches.get_flag("force");
+
+    let history = scheduler.history()?;
+    let now = unix_now();
+    let due: Vec<&ScheduledJob> = config
+        .schedule
+        .iter()
+        .filter(|job| selected.is_empty() || selected.contains(&&job.name))
+        .filter(|job| force || scheduler.is_due(&history, job, now))
+        .collect();
+    if due.is_empty() {
+        log::info!("No scheduled jobs are due");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for job in &due {
+        log::info!("Running scheduled job {}: kargo {}", job.name, job.command);
+        let run = scheduler.execute(job, events).await?;
+        if !run.succeeded() {
+            failed += 1;
+            events.publish(
+                Source::Schedule,
+                EventKind::Error {
+                    message: format!(
+                        "Scheduled job {} failed with exit code {}",
+                        job.name, run.exit_code
+                    ),
+                },
+            );
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} scheduled jobs failed", failed, due.len());
+    }
+    Ok(())
+}
+
+fn history(scheduler: &Scheduler, name: Option<&String>) -> Result<()> {
+    let now = unix_now();
+    let runs = scheduler.history()?;
+    for run in runs.iter().filter(|run| name.is_none_or(|n| &run.job == n)) {
+        println!(
+            "{:<20} {:<10} exit {} ({}) in {:.1}s",
+            run.job,
+            ago(run.started, now),
+            run.exit_code,
+            run.status.as_deref().unwrap_or("no status"),
+            run.duration_ms as f64 / 1000.0
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! `kargo secret`: API tokens kept in the OS keychain
+//!
+//! Secrets stored here are referenced from the config as `keyring:<name>`
+//! (see [`kargo_plugin_api::secrets`]). Without a reachable keychain, or with
+//! `KARGO_SECRETS_BACKEND=file`, they go to an encrypted file instead
+//! (`KARGO_SECRETS_FILE`, by default `<config dir>/kargo/secrets.enc`)
+//! sealed with `KARGO_SECRETS_PASSPHRASE`, asked for on a terminal when
+//! unset.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgMatches, Command};
+use console::Term;
+use kargo_plugin_api::SecretStore;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+pub fn command() -> Command {
+    let name = || {
+        Arg::new("name")
+            .value_name("NAME")
+            .help("Name the config refers to as keyring:NAME")
+            .required(true)
+    };
+    Command::new("secret")
+        .about("Store API tokens in the OS keychain for keyring: references in the config")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set")
+                .about("Store a secret, read from the terminal or the first line of stdin")
+                .arg(name()),
+        )
+        .subcommand(Command::new("get").about("Print a secret").arg(name()))
+        .subcommand(Command::new("rm").about("Delete a secret").arg(name()))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let store = store()?;
+    let (action, sub) = matches.subcommand().context("Missing secret subcommand")?;
+    let name = sub
+        .get_one::<String>("name")
+        .context("Missing secret name")?;
+    match action {
+        "set" => {
+            let secret = read_secret(&format!("Secret {}: ", name))?;
+            if secret.is_empty() {
+                bail!("Not storing an empty secret");
+            }
+            store.set(name, &secret)?;
+            println!("Stored {}; refer to it as keyring:{}", name, name);
+        }
+        "get" => match store.get(name)? {
+            Some(secret) => println!("{}", secret),
+            None => bail!("Secret {} is not set", name),
+        },
+        "rm" => {
+            if !store.remove(name)? {
+                bail!("Secret {} is not set", name);
+            }
+            println!("Deleted {}", name);
+        }
+        other => bail!("Unknown secret subcommand {}", other),
+    }
+    Ok(())
+}
+
+/// The store selected by the environment: the OS keychain when reachable,
+/// the encrypted file otherwise
+pub fn store() -> Result<SecretStore> {
+    let backend = std::env::var("KARGO_SECRETS_BACKEND").ok();
+    match backend.as_deref() {
+        Some("keychain") => return Ok(SecretStore::keychain()),
+        Some("file") => {}
+        Some(other) => bail!(
+            "Unknown KARGO_SECRETS_BACKEND {}; expected keychain or file",
+            other
+        ),
+        None if SecretStore::keychain_available() => return Ok(SecretStore::keychain()),
+        None => log::info!("No OS keychain available; using the encrypted secrets file"),
+    }
+    let path = std::env::var_os("KARGO_SECRETS_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("kargo")
+                .join("secrets.enc")
+        });
+    let passphrase = match std::env::var("KARGO_SECRETS_PASSPHRASE") {
+        Ok(passphrase) => Some(passphrase),
+        Err(_) if Term::stderr().is_term() => {
+            Some(read_secret("Passphrase for the kargo secrets file: ")?)
+        }
+        Err(_) => None,
+    };
+    Ok(SecretStore::file(path, passphrase))
+}
+
+/// A line typed without echo on a terminal, or the first line of stdin
+fn read_secret(prompt: &str) -> Result<String> {
+    let term = Term::stderr();
+    if term.is_term() {
+        term.write_str(prompt)?;
+        return Ok(term.read_secure_line()?);
+    }
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
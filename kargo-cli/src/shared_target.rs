@@ -0,0 +1,213 @@
+//! Shared target directory for the cargo builds kargo runs itself
+//!
+//! Plugins build on their own behalf: mddoc runs rustdoc in a scratch project
+//! for every package it documents, and upgrade checks each project it bumped.
+//! Across a fleet of similar projects, each of those rebuilds the same
+//! dependencies from scratch. With
+//!
+//! ```yaml
+//! shared_target:
+//!   max_size_mb: 20480
+//!   # dir: /fast/disk/kargo-target  (defaults to <cache dir>/kargo/target)
+//! ```
+//!
+//! plugins receive one `CARGO_TARGET_DIR` for all of them in
+//! [`ExecutionContext::target_dir`](kargo_plugin_api::ExecutionContext),
+//! so each dependency is built once. Builds the user asks for (`kargo cargo
+//! build`, proxied cargo commands) keep using the project's own `target/`.
+//!
+//! After each command the directory is trimmed back under its size limit by
+//! evicting the least recently built compilation units: a unit's fingerprint,
+//! build script output and artifacts go together, so cargo simply rebuilds it
+//! when it's needed again.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::Config;
+
+const DEFAULT_MAX_SIZE_MB: u64 = 10 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SharedTargetConfig {
+    /// Defaults to `<cache dir>/kargo/target`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Size the directory is trimmed back to after each command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size_mb: Option<u64>,
+}
+
+/// The shared target directory and its size limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTarget {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+}
+
+/// One compilation unit (`<crate>-<hash>`) of a profile directory, or one
+/// incremental compilation session
+#[derive(Debug)]
+struct Unit {
+    paths: Vec<PathBuf>,
+    bytes: u64,
+    last_built: SystemTime,
+}
+
+/// What [`SharedTarget::evict`] removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Eviction {
+    pub units: usize,
+    pub bytes: u64,
+}
+
+impl SharedTarget {
+    /// The shared target of `config`, if it enables one
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let shared = config.shared_target.as_ref()?;
+        Some(Self {
+            dir: shared.dir.clone().unwrap_or_else(Self::default_dir),
+            max_bytes: shared.max_size_mb.unwrap_or(DEFAULT_MAX_SIZE_MB) * 1024 * 1024,
+        })
+    }
+
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from(".cache"))
+            .join("kargo")
+            .join("target")
+    }
+
+    /// Remove the least recently built units until the directory fits in
+    /// `max_bytes`
+    pub fn evict(&self) -> Result<Eviction> {
+        let mut units = Vec::new();
+        for profile in profile_dirs(&self.dir) {
+            units.extend(profile_units(&profile)?);
+        }
+        let mut total: u64 = units.iter().map(|unit| unit.bytes).sum();
+        let mut eviction = Eviction::default();
+        if total <= self.max_bytes {
+            return Ok(eviction);
+        }
+
+        units.sort_by_key(|unit| unit.last_built);
+        for unit in units {
+            if total <= self.max_bytes {
+                break;
+            }
+            for path in &unit.paths {
+                let removed = if path.is_dir() {
+                    std::fs::remove_dir_all(path)
+                } else {
+                    std::fs::remove_file(path)
+                };
+                removed.with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            total -= unit.bytes;
+            eviction.units += 1;
+            eviction.bytes += unit.bytes;
+        }
+        Ok(eviction)
+    }
+}
+
+/// Directories holding a cargo profile's output (`debug`, `release`, and the
+/// same below a target triple)
+fn profile_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut profiles = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if path.join(".fingerprint").is_dir() {
+                profiles.push(path);
+            } else if depth == 0 {
+                pending.push((path, depth + 1));
+            }
+        }
+    }
+    profiles
+}
+
+/// Units of one profile directory, keyed by their fingerprint directory
+fn profile_units(profile: &Path) -> Result<Vec<Unit>> {
+    let mut units: BTreeMap<String, Unit> = BTreeMap::new();
+    for entry in read_dir(&profile.join(".fingerprint"))? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let mut unit = Unit {
+            paths: Vec::new(),
+            bytes: 0,
+            last_built: SystemTime::UNIX_EPOCH,
+        };
+        add(&mut unit, entry.path());
+        let build = profile.join("build").join(&name);
+        if build.exists() {
+            add(&mut unit, build);
+        }
+        units.insert(name, unit);
+    }
+
+    for entry in read_dir(&profile.join("deps"))? {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let stem = file_name.split('.').next().unwrap_or(&file_name);
+        let key = stem
+            .strip_prefix("lib")
+            .filter(|key| units.contains_key(*key));
+        if let Some(unit) = units.get_mut(key.unwrap_or(stem)) {
+            add(unit, entry.path());
+        }
+    }
+
+    let mut units: Vec<Unit> = units.into_values().collect();
+    for entry in read_dir(&profile.join("incremental"))? {
+        let mut unit = Unit {
+            paths: Vec::new(),
+            bytes: 0,
+            last_built: SystemTime::UNIX_EPOCH,
+        };
+        add(&mut unit, entry.path());
+        units.push(unit);
+    }
+    Ok(units)
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<std::fs::DirEntry>> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => Ok(entries.flatten().collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    }
+}
+
+/// Add `path` and everything below it to `unit`
+fn add(unit: &mut Unit, path: PathBuf) {
+    measure(unit, &path);
+    unit.paths.push(path);
+}
+
+fn measure(unit: &mut Unit, path: &Path) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return;
+    };
+    if let Ok(modified) = metadata.modified() {
+        unit.last_built = unit.last_built.max(modified);
+    }
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            measure(unit, &entry.path());
+        }
+    } else {
+        unit.bytes += metadata.len();
+    }
+}
@@ -0,0 +1,693 @@
+//! `kargo size-report`: release binary sizes tracked across runs
+//!
+//! Builds the selected binaries with `cargo build --release` and records each
+//! one's size, its section breakdown and the features it was built with in
+//! `.kargo/size-history.jsonl`. Every run is compared with a baseline: the
+//! last recorded run with the same features, or the run for the git ref given
+//! with `--baseline`, which is built in a temporary worktree when the history
+//! has no run for that commit yet. A binary growing by more than
+//! `--threshold` percent fails the command, so CI can use it as a size gate.
+//!
+//! Sections are read from ELF and Mach-O binaries; other formats are tracked
+//! by total size only.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::WriteGuard;
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::changelog::git;
+use crate::cost::format_size;
+use crate::schedule::unix_now;
+
+/// Location of the size history relative to the project root
+pub const HISTORY_FILE: &str = ".kargo/size-history.jsonl";
+/// Section changes smaller than this are left out of the report
+const SECTION_NOISE_BYTES: u64 = 1024;
+/// Sections listed per binary, largest changes first
+const SECTIONS_SHOWN: usize = 5;
+
+pub fn command() -> Command {
+    Command::new("size-report")
+        .about("Build release binaries and report how their sizes changed")
+        .arg(
+            Arg::new("bin")
+                .long("bin")
+                .value_name("NAME")
+                .help("Binary to build and track (default: every binary in the workspace)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("features")
+                .short('F')
+                .long("features")
+                .value_name("FEATURES")
+                .help("Features to build with (comma separated)")
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("all-features")
+                .long("all-features")
+                .help("Build with every feature enabled")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-default-features")
+                .long("no-default-features")
+                .help("Build without the default features")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("baseline")
+                .long("baseline")
+                .value_name("REF")
+                .help(
+                    "Compare with this git ref instead of the previous run, building it if needed",
+                ),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .value_name("PERCENT")
+                .help("Fail when a binary grows by more than this percentage")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("5"),
+        )
+        .arg(
+            Arg::new("no-record")
+                .long("no-record")
+                .help("Compare without adding this run to the history")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the comparison as JSON")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let bins: Vec<String> = matches
+        .get_many::<String>("bin")
+        .map(|bins| bins.cloned().collect())
+        .unwrap_or_default();
+    let mut features: Vec<String> = matches
+        .get_many::<String>("features")
+        .map(|features| features.cloned().collect())
+        .unwrap_or_default();
+    features.sort();
+    features.dedup();
+    let features = FeatureConfig {
+        features,
+        all_features: matches.get_flag("all-features"),
+        no_default_features: matches.get_flag("no-default-features"),
+    };
+    let threshold = *matches.get_one::<f64>("threshold").expect("has default");
+    if WriteGuard::current().is_read_only() {
+        bail!("size-report builds the binaries, which kargo --read-only doesn't allow");
+    }
+    let history = SizeHistory::for_project(dir);
+    let record = !matches.get_flag("no-record");
+
+    let runs = history.runs()?;
+    let baseline = match matches.get_one::<String>("baseline") {
+        Some(reference) => {
+            let commit = git(
+                dir,
+                &[
+                    "rev-parse",
+                    "--verify",
+                    &format!("{}^{{commit}}", reference),
+                ],
+            )
+            .with_context(|| format!("Unknown git ref {}", reference))?;
+            let commit = commit.trim();
+            let recorded = runs
+                .iter()
+                .rev()
+                .find(|run| run.commit.as_deref() == Some(commit) && run.features == features);
+            match recorded {
+                Some(run) => Some(run.clone()),
+                None => {
+                    eprintln!(
+                        "Building {} ({}) for the baseline",
+                        reference,
+                        short(commit)
+                    );
+                    let run = build_at_commit(dir, commit, &bins, &features)?;
+                    if record {
+                        history.append(&run)?;
+                    }
+                    Some(run)
+                }
+            }
+        }
+        None => runs
+            .iter()
+            .rev()
+            .find(|run| run.features == features)
+            .cloned(),
+    };
+
+    let binaries = build(dir, &bins, &features, None)?;
+    let current = SizeRun {
+        commit: git(dir, &["rev-parse", "HEAD"])
+            .ok()
+            .map(|commit| commit.trim().to_string()),
+        timestamp: unix_now(),
+        features,
+        binaries,
+    };
+    if record {
+        history.append(&current)?;
+    }
+
+    let comparison = SizeComparison::new(baseline.as_ref(), &current);
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+    } else {
+        print!("{}", comparison.render());
+    }
+
+    let regressions = comparison.regressions(threshold);
+    if !regressions.is_empty() {
+        bail!(
+            "{} binary(ies) grew by more than {}%: {}",
+            regressions.len(),
+            threshold,
+            regressions
+                .iter()
+                .map(|change| change.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Features a run was built with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureConfig {
+    /// Sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub all_features: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_default_features: bool,
+}
+
+impl FeatureConfig {
+    fn cargo_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        args
+    }
+}
+
+impl fmt::Display for FeatureConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.all_features {
+            return f.write_str("all features");
+        }
+        let mut parts = Vec::new();
+        if self.no_default_features {
+            parts.push("no default features".to_string());
+        } else {
+            parts.push("default features".to_string());
+        }
+        if !self.features.is_empty() {
+            parts.push(self.features.join(", "));
+        }
+        f.write_str(&parts.join(" + "))
+    }
+}
+
+/// Size of one release binary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinarySize {
+    pub name: String,
+    pub bytes: u64,
+    /// Bytes per section, for ELF and Mach-O binaries
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sections: BTreeMap<String, u64>,
+}
+
+impl BinarySize {
+    pub fn read(name: impl Into<String>, path: &Path) -> Result<Self> {
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self {
+            name: name.into(),
+            bytes: data.len() as u64,
+            sections: section_sizes(&data),
+        })
+    }
+}
+
+/// One `kargo size-report` run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeRun {
+    /// Commit the binaries were built from, when in a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Unix timestamp (seconds)
+    pub timestamp: u64,
+    #[serde(default)]
+    pub features: FeatureConfig,
+    pub binaries: Vec<BinarySize>,
+}
+
+/// The size history of one project
+#[derive(Debug, Clone)]
+pub struct SizeHistory {
+    path: PathBuf,
+}
+
+impl SizeHistory {
+    pub fn for_project(root: &Path) -> Self {
+        Self {
+            path: root.join(HISTORY_FILE),
+        }
+    }
+
+    /// Recorded runs, oldest first
+    pub fn runs(&self) -> Result<Vec<SizeRun>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("{}:{}: invalid run", self.path.display(), i + 1))
+            })
+            .collect()
+    }
+
+    pub fn append(&self, run: &SizeRun) -> Result<()> {
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.path.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(run)?;
+        line.push('\n');
+        guard.append(&self.path, line)
+    }
+}
+
+/// How one binary changed against the baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BinaryChange {
+    pub name: String,
+    /// `None` when the baseline doesn't have the binary
+    pub before: Option<u64>,
+    pub after: u64,
+    /// Changed sections, largest change first
+    pub sections: Vec<SectionChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SectionChange {
+    pub name: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl SectionChange {
+    pub fn delta(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+impl BinaryChange {
+    pub fn delta(&self) -> Option<i64> {
+        self.before.map(|before| self.after as i64 - before as i64)
+    }
+
+    /// Growth in percent of the baseline size
+    pub fn percent(&self) -> Option<f64> {
+        self.before
+            .filter(|&before| before > 0)
+            .map(|before| (self.after as f64 - before as f64) * 100.0 / before as f64)
+    }
+}
+
+/// A run compared with its baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeComparison {
+    /// Commit of the baseline run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_commit: Option<String>,
+    /// Whether there was a baseline run at all
+    pub has_baseline: bool,
+    pub features: FeatureConfig,
+    pub binaries: Vec<BinaryChange>,
+    /// Binaries of the baseline that are gone
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+impl SizeComparison {
+    pub fn new(baseline: Option<&SizeRun>, current: &SizeRun) -> Self {
+        let before = |name: &str| {
+            baseline.and_then(|run| run.binaries.iter().find(|binary| binary.name == name))
+        };
+        let binaries = current
+            .binaries
+            .iter()
+            .map(|binary| {
+                let previous = before(&binary.name);
+                let mut sections = Vec::new();
+                if let Some(previous) = previous {
+                    let names: std::collections::BTreeSet<&String> = previous
+                        .sections
+                        .keys()
+                        .chain(binary.sections.keys())
+                        .collect();
+                    for name in names {
+                        let change = SectionChange {
+                            name: name.clone(),
+                            before: previous.sections.get(name).copied().unwrap_or(0),
+                            after: binary.sections.get(name).copied().unwrap_or(0),
+                        };
+                        if change.delta().unsigned_abs() >= SECTION_NOISE_BYTES {
+                            sections.push(change);
+                        }
+                    }
+                    sections.sort_by_key(|change| std::cmp::Reverse(change.delta().unsigned_abs()));
+                }
+                BinaryChange {
+                    name: binary.name.clone(),
+                    before: previous.map(|previous| previous.bytes),
+                    after: binary.bytes,
+                    sections,
+                }
+            })
+            .collect();
+        let removed = baseline
+            .map(|run| {
+                run.binaries
+                    .iter()
+                    .filter(|binary| !current.binaries.iter().any(|b| b.name == binary.name))
+                    .map(|binary| binary.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            baseline_commit: baseline.and_then(|run| run.commit.clone()),
+            has_baseline: baseline.is_some(),
+            features: current.features.clone(),
+            binaries,
+            removed,
+        }
+    }
+
+    /// Binaries that grew by more than `threshold` percent
+    pub fn regressions(&self, threshold: f64) -> Vec<&BinaryChange> {
+        self.binaries
+            .iter()
+            .filter(|change| change.percent().is_some_and(|percent| percent > threshold))
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(out, "Release binary sizes ({})", self.features);
+        match (&self.baseline_commit, self.has_baseline) {
+            (Some(commit), _) => {
+                let _ = writeln!(out, " against {}", short(commit));
+            }
+            (None, true) => out.push_str(" against the previous run\n"),
+            (None, false) => out.push_str(", no earlier run to compare with\n"),
+        }
+        let width = self
+            .binaries
+            .iter()
+            .map(|change| change.name.len())
+            .max()
+            .unwrap_or(0);
+        for change in &self.binaries {
+            let _ = write!(
+                out,
+                "  {:<width$}  {:>10}",
+                change.name,
+                format_size(change.after),
+                width = width
+            );
+            match (change.delta(), change.percent()) {
+                (Some(0), _) => out.push_str("  unchanged"),
+                (Some(delta), Some(percent)) => {
+                    let _ = write!(out, "  {} ({:+.1}%)", format_delta(delta), percent);
+                }
+                (Some(delta), None) => {
+                    let _ = write!(out, "  {}", format_delta(delta));
+                }
+                (None, _) if self.has_baseline => out.push_str("  new"),
+                (None, _) => {}
+            }
+            out.push('\n');
+            // Section deltas line up with the binary's
+            for section in change.sections.iter().take(SECTIONS_SHOWN) {
+                let _ = writeln!(
+                    out,
+                    "    {:<width$}  {}",
+                    section.name,
+                    format_delta(section.delta()),
+                    width = width + 10
+                );
+            }
+        }
+        for name in &self.removed {
+            let _ = writeln!(out, "  {}  removed", name);
+        }
+        out
+    }
+}
+
+/// Build the binaries in release mode and measure them. `target_dir`
+/// overrides cargo's target directory.
+pub fn build(
+    dir: &Path,
+    bins: &[String],
+    features: &FeatureConfig,
+    target_dir: Option<&Path>,
+) -> Result<Vec<BinarySize>> {
+    let mut command = CargoCommand::new("build")
+        .args(["--release", "--message-format=json-render-diagnostics"])
+        .args(features.cargo_args())
+        .current_dir(dir)
+        .target_dir(target_dir);
+    if bins.is_empty() {
+        command = command.args(["--workspace", "--bins"]);
+    } else {
+        for bin in bins {
+            command = command.args(["--bin", bin]);
+        }
+    }
+
+    let output = cargo_runner::run(&command)?;
+    output.ensure_success(&command)?;
+    let mut executables = BTreeMap::new();
+    for message in cargo_metadata::Message::parse_stream(output.stdout.as_slice()) {
+        if let cargo_metadata::Message::CompilerArtifact(artifact) = message?
+            && let Some(executable) = artifact.executable
+        {
+            executables.insert(artifact.target.name.to_string(), PathBuf::from(executable));
+        }
+    }
+    if executables.is_empty() {
+        bail!("No binaries were built in {}", dir.display());
+    }
+    executables
+        .iter()
+        .map(|(name, path)| BinarySize::read(name, path))
+        .collect()
+}
+
+/// Build `commit` of the repository containing `dir` in a temporary worktree
+fn build_at_commit(
+    dir: &Path,
+    commit: &str,
+    bins: &[String],
+    features: &FeatureConfig,
+) -> Result<SizeRun> {
+    let top = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+    let prefix = git(dir, &["rev-parse", "--show-prefix"])?;
+    let prefix = prefix.trim();
+    let scratch = tempfile::tempdir()?;
+    let worktree = scratch.path().join("tree");
+    let worktree_arg = worktree.to_string_lossy().into_owned();
+    git(
+        &top,
+        &["worktree", "add", "--detach", &worktree_arg, commit],
+    )?;
+    // Rebuilding the baseline every time would be slow, so it keeps its own
+    // target directory next to the project's
+    let target_dir = top.join("target").join("kargo-size-baseline");
+    let binaries = build(&worktree.join(prefix), bins, features, Some(&target_dir));
+    if let Err(e) = git(&top, &["worktree", "remove", "--force", &worktree_arg]) {
+        log::warn!("Failed to remove the baseline worktree: {:#}", e);
+    }
+    Ok(SizeRun {
+        commit: Some(commit.to_string()),
+        timestamp: unix_now(),
+        features: features.clone(),
+        binaries: binaries?,
+    })
+}
+
+/// File bytes per section of an ELF or Mach-O binary; empty for other
+/// formats or when the headers don't parse. Sections taking no space in the
+/// file, like `.bss`, are left out.
+pub fn section_sizes(data: &[u8]) -> BTreeMap<String, u64> {
+    if data.starts_with(b"\x7fELF") {
+        elf_sections(data)
+    } else if data.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]) {
+        macho_sections(data)
+    } else {
+        None
+    }
+    .unwrap_or_default()
+}
+
+/// Read a `len`-byte unsigned integer at `offset`
+fn uint(data: &[u8], offset: usize, len: usize, big_endian: bool) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(len)?)?;
+    let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+    Some(if big_endian {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    })
+}
+
+fn elf_sections(data: &[u8]) -> Option<BTreeMap<String, u64>> {
+    const SHT_NOBITS: u64 = 8;
+    let wide = *data.get(4)? == 2;
+    let big_endian = *data.get(5)? == 2;
+    let read = |offset: usize, len: usize| uint(data, offset, len, big_endian);
+    let (shoff, shentsize, shnum, shstrndx) = if wide {
+        (
+            read(0x28, 8)?,
+            read(0x3a, 2)?,
+            read(0x3c, 2)?,
+            read(0x3e, 2)?,
+        )
+    } else {
+        (
+            read(0x20, 4)?,
+            read(0x2e, 2)?,
+            read(0x30, 2)?,
+            read(0x32, 2)?,
+        )
+    };
+    // Name offset, type, file offset and size of a section header
+    let header = |index: u64| -> Option<(u64, u64, u64, u64)> {
+        let at = usize::try_from(shoff + index * shentsize).ok()?;
+        if wide {
+            Some((
+                read(at, 4)?,
+                read(at + 4, 4)?,
+                read(at + 24, 8)?,
+                read(at + 32, 8)?,
+            ))
+        } else {
+            Some((
+                read(at, 4)?,
+                read(at + 4, 4)?,
+                read(at + 16, 4)?,
+                read(at + 20, 4)?,
+            ))
+        }
+    };
+    let (_, _, names_offset, _) = header(shstrndx)?;
+
+    let mut sections = BTreeMap::new();
+    for index in 1..shnum {
+        let (name, kind, _, size) = header(index)?;
+        if kind == SHT_NOBITS || size == 0 {
+            continue;
+        }
+        let start = usize::try_from(names_offset + name).ok()?;
+        let name = data.get(start..)?.split(|&b| b == 0).next()?;
+        *sections
+            .entry(String::from_utf8_lossy(name).into_owned())
+            .or_default() += size;
+    }
+    Some(sections)
+}
+
+fn macho_sections(data: &[u8]) -> Option<BTreeMap<String, u64>> {
+    const LC_SEGMENT_64: u64 = 0x19;
+    // S_ZEROFILL, S_GB_ZEROFILL and S_THREAD_LOCAL_ZEROFILL take no file space
+    const ZEROFILL: [u64; 3] = [0x1, 0xc, 0x12];
+    let read = |offset: usize, len: usize| uint(data, offset, len, false);
+    let name = |offset: usize| -> Option<String> {
+        let raw = data.get(offset..offset + 16)?;
+        let raw = raw.split(|&b| b == 0).next()?;
+        Some(String::from_utf8_lossy(raw).into_owned())
+    };
+
+    let commands = read(16, 4)?;
+    let mut at = 32;
+    let mut sections = BTreeMap::new();
+    for _ in 0..commands {
+        let (command, size) = (read(at, 4)?, read(at + 4, 4)? as usize);
+        if command == LC_SEGMENT_64 {
+            for i in 0..read(at + 64, 4)? as usize {
+                let section = at + 72 + i * 80;
+                let bytes = read(section + 40, 8)?;
+                if bytes == 0 || ZEROFILL.contains(&(read(section + 64, 4)? & 0xff)) {
+                    continue;
+                }
+                let key = format!("{},{}", name(section + 16)?, name(section)?);
+                *sections.entry(key).or_default() += bytes;
+            }
+        }
+        if size == 0 {
+            break;
+        }
+        at += size;
+    }
+    Some(sections)
+}
+
+fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{}{}", sign, format_size(delta.unsigned_abs()))
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
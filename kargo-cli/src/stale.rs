@@ -0,0 +1,43 @@
+//! `kargo stale`: projects of the kargo-walk index that look abandoned
+//!
+//! Lists the projects that have had no commits for `--months`, fail
+//! `cargo check` and that no other indexed project depends on, with the
+//! evidence for each (see [`kargo_walk::stale`]). Same as
+//! `kargo-walk --stale MONTHS`.
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use kargo_walk::{index, stale};
+use std::path::PathBuf;
+
+pub fn command() -> Command {
+    Command::new("stale")
+        .about("List indexed projects that are idle, broken and unused: candidates for archiving")
+        .arg(
+            Arg::new("months")
+                .long("months")
+                .value_name("MONTHS")
+                .help("Months without commits before a project counts as idle")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("12"),
+        )
+        .arg(
+            Arg::new("index")
+                .long("index")
+                .value_name("FILE")
+                .help("Project index written by kargo-walk")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("index.yaml"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let months = matches.get_one::<u32>("months").copied().unwrap_or(12);
+    let index = matches
+        .get_one::<PathBuf>("index")
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("index.yaml"));
+    let projects = index::read(&index)?;
+    print!("{}", stale::report(&projects, months.into(), stale::now()));
+    Ok(())
+}
@@ -0,0 +1,222 @@
+//! Exit code contract and the `--status-file` report
+//!
+//! Every `kargo` invocation ends with one of the [`ExitStatus`] codes below.
+//! The codes are stable so wrapper scripts and CI jobs can branch on them:
+//!
+//! | Code | Status              | Meaning                                                  |
+//! |------|---------------------|----------------------------------------------------------|
+//! | 0    | `success`           | The command succeeded and found nothing to act on        |
+//! | 1    | `failure`           | The command failed                                       |
+//! | 2    | `updates-available` | The command succeeded but found outdated or vulnerable dependencies |
+//! | 3    | `partial-failure`   | Some steps failed while others succeeded                 |
+//! | 4    | `config-error`      | Configuration could not be read or is invalid            |
+//! | 5    | `usage-error`       | Invalid command line (unknown flag, missing argument)    |
+//!
+//! `kargo upgrade --dry-run` exits with `2` when it finds updates, as does
+//! any plugin that reports outdated or vulnerable dependencies on its
+//! [`HostEvents`](kargo_plugin_api::HostEvents).
+//!
+//! With `--status-file <PATH>` the same outcome is written as a JSON
+//! [`StatusReport`] once the command has finished.
+
+use anyhow::{Context, Result};
+use kargo_plugin_api::WriteGuard;
+use kargo_plugin_api::artifacts::CapturedOutput;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Version of the [`StatusReport`] schema, bumped on incompatible changes
+pub const STATUS_REPORT_VERSION: u32 = 1;
+
+/// Final result of a run, mapped onto the process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitStatus {
+    Success,
+    Failure,
+    UpdatesAvailable,
+    PartialFailure,
+    ConfigError,
+    UsageError,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Failure => 1,
+            ExitStatus::UpdatesAvailable => 2,
+            ExitStatus::PartialFailure => 3,
+            ExitStatus::ConfigError => 4,
+            ExitStatus::UsageError => 5,
+        }
+    }
+
+    /// Classify an error that ended the run
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if error.chain().any(|cause| cause.is::<ConfigError>()) {
+            ExitStatus::ConfigError
+        } else if error.chain().any(|cause| cause.is::<clap::Error>()) {
+            // Plugin arguments checked against the plugin's declared command
+            ExitStatus::UsageError
+        } else {
+            ExitStatus::Failure
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ExitStatus::Success => "✅ clean",
+            ExitStatus::Failure => "❌ failed",
+            ExitStatus::UpdatesAvailable => "⬆️ updates available",
+            ExitStatus::PartialFailure => "⚠️ partially failed",
+            ExitStatus::ConfigError => "❌ configuration error",
+            ExitStatus::UsageError => "❌ usage error",
+        }
+    }
+}
+
+/// Configuration that could not be read or parsed; exits with
+/// [`ExitStatus::ConfigError`]
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    line: Option<usize>,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl ConfigError {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            line: None,
+            source: source.into(),
+        }
+    }
+
+    /// Point the error at `line` (1-based) of the file
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl fmt::Display for ConfigError {
+    // The cause is exposed through `source()`, so `{:#}` doesn't repeat it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration in {}", self.path.display())?;
+        match self.line {
+            Some(line) => write!(f, ":{}", line),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A diagnostic reported during the run, optionally tied to a source location
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+    pub title: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutdatedDependency {
+    pub path: PathBuf,
+    pub name: String,
+    pub current: String,
+    pub latest: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VulnerableDependency {
+    pub path: PathBuf,
+    pub name: String,
+    pub version: String,
+    pub advisory: String,
+}
+
+/// Machine-readable summary of a run, written by `--status-file`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub version: u32,
+    pub status: ExitStatus,
+    pub exit_code: i32,
+    /// Subcommand that ran, if one got as far as starting
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub duration_ms: u64,
+    /// The error that ended the run, if any
+    pub error: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub outdated: Vec<OutdatedDependency>,
+    pub vulnerable: Vec<VulnerableDependency>,
+    /// Where the output of the commands the run executed was saved, under
+    /// each project's `.kargo/runs/`
+    pub outputs: Vec<CapturedOutput>,
+}
+
+impl StatusReport {
+    /// Report for a run that never got past argument parsing
+    pub fn usage_error(message: impl Into<String>) -> Self {
+        Self::early_exit(ExitStatus::UsageError, message)
+    }
+
+    /// Report for a run that stopped before any command started
+    pub fn early_exit(status: ExitStatus, message: impl Into<String>) -> Self {
+        Self {
+            version: STATUS_REPORT_VERSION,
+            status,
+            exit_code: status.code(),
+            command: None,
+            args: Vec::new(),
+            duration_ms: 0,
+            error: Some(message.into()),
+            diagnostics: Vec::new(),
+            outdated: Vec::new(),
+            vulnerable: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Write the report as JSON, replacing the file atomically so readers never
+    /// see a partial document. The report is the run's own output, asked for
+    /// on the command line, so read-only runs write it too.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let guard = WriteGuard::writable();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            guard.create_dir_all(parent)?;
+        }
+        guard
+            .write_atomic(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write status file {}", path.display()))
+    }
+}
@@ -1,4 +1,4 @@
-use crate::events::{Event, EventBus};
+use crate::events::{EventBus, EventKind, Source};
 use anyhow::Result;
 use cargo_metadata::{MetadataCommand, Package};
 use std::collections::HashMap;
@@ -20,9 +20,12 @@ impl VendorManager {
     }
 
     pub async fn vendor_dependencies(&self, workspace_path: &Path) -> Result<()> {
-        self.events.publish(Event::VendorStarted {
-            path: workspace_path.to_owned(),
-        });
+        self.events.publish(
+            Source::Vendor,
+            EventKind::VendorStarted {
+                path: workspace_path.to_owned(),
+            },
+        );
 
         // Get metadata for the workspace
         let metadata = MetadataCommand::new()
@@ -57,9 +60,12 @@ impl VendorManager {
             }
         }
 
-        self.events.publish(Event::VendorFinished {
-            path: workspace_path.to_owned(),
-        });
+        self.events.publish(
+            Source::Vendor,
+            EventKind::VendorFinished {
+                path: workspace_path.to_owned(),
+            },
+        );
 
         Ok(())
     }
@@ -0,0 +1,374 @@
+//! `kargo verify-vendored`: is the vendor directory what Cargo.lock says?
+//!
+//! Every vendored crate carries a `.cargo-checksum.json` with the digest of
+//! the `.crate` it was unpacked from and of each of its files. [`verify`]
+//! checks the package digest against the `checksum` Cargo.lock pins (a
+//! crate that drifted to another release or registry), hashes every file
+//! against the recorded digests (a crate edited in place), and lists locked
+//! packages with no vendored copy. Both `cargo vendor`'s flat layout and the
+//! `<name>/<version>` layout of [`VendorManager`](crate::vendor::VendorManager)
+//! are understood.
+
+use anyhow::{Context, Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use jwalk::WalkDir;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item};
+
+use crate::config::Config;
+
+/// Written by cargo next to an unpacked crate; never part of its contents
+const CHECKSUM_FILE: &str = ".cargo-checksum.json";
+const UNPACK_MARKER: &str = ".cargo-ok";
+
+pub fn command() -> Command {
+    Command::new("verify-vendored")
+        .about("Check vendored crates against Cargo.lock and for local modifications")
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory holding Cargo.lock")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("vendor-dir")
+                .long("vendor-dir")
+                .value_name("DIR")
+                .help("Vendor directory (defaults to vendor.path from the config, else `vendor`), relative to the project")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the report as JSON")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let vendor_dir = match matches.get_one::<PathBuf>("vendor-dir") {
+        Some(vendor_dir) => vendor_dir.clone(),
+        None => {
+            let configured = Config::load()?.vendor.path;
+            if configured.as_os_str().is_empty() {
+                PathBuf::from("vendor")
+            } else {
+                configured
+            }
+        }
+    };
+
+    let report = verify(&dir.join(vendor_dir), &dir.join("Cargo.lock"))?;
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render());
+    }
+
+    let failed = report.failed();
+    if failed > 0 {
+        bail!(
+            "{} vendored crate(s) don't match Cargo.lock or were modified",
+            failed
+        );
+    }
+    Ok(())
+}
+
+/// Something wrong with a vendored crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Problem {
+    /// Cargo.lock doesn't lock this version
+    NotLocked,
+    /// The crate was unpacked from another `.crate` than the locked one
+    ChecksumMismatch {
+        locked: String,
+        vendored: Option<String>,
+    },
+    /// Without `.cargo-checksum.json`, nothing can be verified
+    NoChecksumFile,
+    /// A file's contents differ from its recorded digest
+    Modified { file: String },
+    /// A file that wasn't in the crate
+    Added { file: String },
+    /// A file of the crate that's gone
+    Removed { file: String },
+}
+
+impl Problem {
+    fn describe(&self) -> String {
+        match self {
+            Self::NotLocked => "not locked in Cargo.lock".to_string(),
+            Self::ChecksumMismatch { locked, vendored } => format!(
+                "package checksum {} instead of the locked {}",
+                vendored.as_deref().unwrap_or("missing"),
+                locked
+            ),
+            Self::NoChecksumFile => format!("no {}, can't be verified", CHECKSUM_FILE),
+            Self::Modified { file } => format!("modified {}", file),
+            Self::Added { file } => format!("added {}", file),
+            Self::Removed { file } => format!("removed {}", file),
+        }
+    }
+}
+
+/// One vendored crate
+#[derive(Debug, Clone, Serialize)]
+pub struct VendoredCrate {
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Missing {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VendorReport {
+    pub crates: Vec<VendoredCrate>,
+    /// Locked packages from a registry or git with no vendored copy
+    pub missing: Vec<Missing>,
+}
+
+impl VendorReport {
+    /// Crates with problems, and locked ones that aren't vendored
+    pub fn failed(&self) -> usize {
+        self.crates
+            .iter()
+            .filter(|c| !c.problems.is_empty())
+            .count()
+            + self.missing.len()
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let clean = self.crates.iter().filter(|c| c.problems.is_empty()).count();
+        let _ = writeln!(
+            out,
+            "{} of {} vendored crate(s) verified",
+            clean,
+            self.crates.len()
+        );
+        for krate in self.crates.iter().filter(|c| !c.problems.is_empty()) {
+            let _ = writeln!(
+                out,
+                "  ✗ {} {} ({})",
+                krate.name,
+                krate.version,
+                krate.path.display()
+            );
+            for problem in &krate.problems {
+                let _ = writeln!(out, "      {}", problem.describe());
+            }
+        }
+        for missing in &self.missing {
+            let _ = writeln!(
+                out,
+                "  ✗ {} {}: locked but not vendored",
+                missing.name, missing.version
+            );
+        }
+        out
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChecksumFile {
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+    package: Option<String>,
+}
+
+/// A package in Cargo.lock that comes from a registry or git
+struct Locked {
+    name: String,
+    version: String,
+    checksum: Option<String>,
+}
+
+/// Check every crate in `vendor_dir` against `lockfile`
+pub fn verify(vendor_dir: &Path, lockfile: &Path) -> Result<VendorReport> {
+    let content = std::fs::read_to_string(lockfile)
+        .with_context(|| format!("Failed to read {}", lockfile.display()))?;
+    let locked = locked_packages(&content)
+        .with_context(|| format!("Failed to parse {}", lockfile.display()))?;
+    if !vendor_dir.is_dir() {
+        bail!("{} is not a directory", vendor_dir.display());
+    }
+
+    let mut crates = Vec::new();
+    let mut vendored = HashSet::new();
+    for path in crate_dirs(vendor_dir)? {
+        let (name, version) = package_id(&path)?;
+        let lock = locked
+            .iter()
+            .find(|l| l.name == name && l.version == version);
+        let mut problems = Vec::new();
+        match std::fs::read_to_string(path.join(CHECKSUM_FILE)) {
+            Ok(content) => {
+                let checksums: ChecksumFile =
+                    serde_json::from_str(&content).with_context(|| {
+                        format!("Failed to parse {}", path.join(CHECKSUM_FILE).display())
+                    })?;
+                if let Some(locked) = lock.and_then(|l| l.checksum.as_ref())
+                    && checksums.package.as_ref() != Some(locked)
+                {
+                    problems.push(Problem::ChecksumMismatch {
+                        locked: locked.clone(),
+                        vendored: checksums.package.clone(),
+                    });
+                }
+                problems.extend(file_problems(&path, &checksums.files)?);
+            }
+            Err(_) => problems.push(Problem::NoChecksumFile),
+        }
+        if lock.is_none() {
+            problems.insert(0, Problem::NotLocked);
+        }
+        vendored.insert((name.clone(), version.clone()));
+        crates.push(VendoredCrate {
+            name,
+            version,
+            path,
+            problems,
+        });
+    }
+
+    let missing = locked
+        .into_iter()
+        .filter(|l| !vendored.contains(&(l.name.clone(), l.version.clone())))
+        .map(|l| Missing {
+            name: l.name,
+            version: l.version,
+        })
+        .collect();
+    Ok(VendorReport { crates, missing })
+}
+
+fn locked_packages(lockfile: &str) -> Result<Vec<Locked>> {
+    let doc = lockfile.parse::<DocumentMut>()?;
+    let Some(packages) = doc.get("package").and_then(Item::as_array_of_tables) else {
+        return Ok(Vec::new());
+    };
+    Ok(packages
+        .iter()
+        // Workspace members and path dependencies have no source
+        .filter(|p| p.get("source").is_some())
+        .filter_map(|p| {
+            Some(Locked {
+                name: p.get("name")?.as_str()?.to_string(),
+                version: p.get("version")?.as_str()?.to_string(),
+                checksum: p.get("checksum").and_then(Item::as_str).map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+/// Directories holding a vendored crate: `<name>` or `<name>/<version>`
+fn crate_dirs(vendor_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    let mut pending = vec![(vendor_dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let entries =
+            std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            // Anything below a crate name is a version, vendored or not
+            if depth == 1 || path.join("Cargo.toml").is_file() || path.join(CHECKSUM_FILE).is_file()
+            {
+                dirs.push(path);
+            } else {
+                pending.push((path, depth + 1));
+            }
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// `(name, version)` from the crate's manifest, or from the directory names
+/// of the `<name>/<version>` layout when it has none
+fn package_id(dir: &Path) -> Result<(String, String)> {
+    let manifest = dir.join("Cargo.toml");
+    if let Ok(content) = std::fs::read_to_string(&manifest) {
+        let doc = content
+            .parse::<DocumentMut>()
+            .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+        let package = &doc["package"];
+        if let (Some(name), Some(version)) = (package["name"].as_str(), package["version"].as_str())
+        {
+            return Ok((name.to_string(), version.to_string()));
+        }
+    }
+    let version = dir.file_name().map(|n| n.to_string_lossy().into_owned());
+    let name = dir
+        .parent()
+        .and_then(Path::file_name)
+        .map(|n| n.to_string_lossy().into_owned());
+    match (name, version) {
+        (Some(name), Some(version)) => Ok((name, version)),
+        _ => bail!("Can't tell which crate {} holds", dir.display()),
+    }
+}
+
+/// Files of the crate in `dir` that differ from `recorded`
+fn file_problems(dir: &Path, recorded: &BTreeMap<String, String>) -> Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+    let mut present = BTreeSet::new();
+    for entry in WalkDir::new(dir).skip_hidden(false).sort(true) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let file = path
+            .strip_prefix(dir)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if file == CHECKSUM_FILE || file == UNPACK_MARKER {
+            continue;
+        }
+        match recorded.get(&file) {
+            Some(digest) => {
+                let content = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                if sha256_hex(&content) != *digest {
+                    problems.push(Problem::Modified { file: file.clone() });
+                }
+            }
+            None => problems.push(Problem::Added { file: file.clone() }),
+        }
+        present.insert(file);
+    }
+    problems.extend(
+        recorded
+            .keys()
+            .filter(|file| !present.contains(*file))
+            .map(|file| Problem::Removed { file: file.clone() }),
+    );
+    Ok(problems)
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,417 @@
+//! `kargo why <crate>`: why a crate is in the build graph
+//!
+//! Walks the resolved `cargo metadata` graph (see
+//! [`kargo_plugin_api::metadata`]) back from every version of the crate to the
+//! workspace members that pull it in. Each chain notes how every package
+//! depends on the next: the dependency kind, the features it requests, and,
+//! for optional dependencies, which of its own enabled features switch it on.
+//! The graphs the crate ends up in tell whether it is compiled into the
+//! shipped artifacts or only for tests and build scripts.
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use kargo_plugin_api::metadata::{DepKind, MetadataMode, ProjectMetadata};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+/// Chains listed per crate version before the rest are summarized
+const MAX_CHAINS: usize = 32;
+
+pub fn command() -> Command {
+    Command::new("why")
+        .about("Explain why a crate is in the dependency graph")
+        .arg(
+            Arg::new("crate")
+                .value_name("CRATE")
+                .help("Crate to explain, optionally with a version (`name@1.2.3`)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .help("Project directory")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the explanation as JSON")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let spec = matches.get_one::<String>("crate").expect("required");
+    let dir = matches.get_one::<PathBuf>("path").expect("has default");
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (spec.as_str(), None),
+    };
+
+    let metadata = ProjectMetadata::load(&dir.join("Cargo.toml"), MetadataMode::Resolved)?;
+    let explanations = explain(&metadata, name, version)?;
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&explanations)?);
+    } else {
+        for (i, why) in explanations.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("{}", why.render());
+        }
+    }
+    Ok(())
+}
+
+/// Why one version of a crate is in the graph
+#[derive(Debug, Clone, Serialize)]
+pub struct Why {
+    pub package: String,
+    pub version: String,
+    /// Graphs the crate is part of: `normal` when it ends up in the built
+    /// artifacts, `build` when only build scripts and proc macros need it,
+    /// `dev` when only tests, examples and benches do
+    pub graphs: Vec<DepKind>,
+    /// Features cargo enables on the crate
+    pub features: Vec<String>,
+    /// Dependency chains from a workspace member down to the crate
+    pub chains: Vec<Vec<Link>>,
+    /// Whether there are more chains than the ones listed
+    pub truncated: bool,
+}
+
+/// One package of a chain
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Link {
+    pub package: String,
+    pub version: String,
+    /// How the previous package of the chain depends on this one; `None` for
+    /// the workspace member starting the chain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge: Option<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Edge {
+    pub kinds: Vec<DepKind>,
+    /// Features the dependent asks for on top of the defaults
+    pub features: Vec<String>,
+    pub default_features: bool,
+    pub optional: bool,
+    /// The dependent's enabled features that turn on an optional dependency
+    pub enabled_by: Vec<String>,
+}
+
+/// Explain every resolved version of `name` (or just `version`)
+pub fn explain(metadata: &ProjectMetadata, name: &str, version: Option<&str>) -> Result<Vec<Why>> {
+    let Some(resolve) = &metadata.resolve else {
+        bail!("The dependency graph wasn't resolved");
+    };
+    let mut targets: Vec<_> = metadata
+        .packages
+        .iter()
+        .filter(|p| p.name == name && version.is_none_or(|v| p.version == v))
+        .filter(|p| resolve.contains_key(&p.id))
+        .collect();
+    if targets.is_empty() {
+        match version {
+            Some(version) => bail!("{}@{} is not in the dependency graph", name, version),
+            None => bail!("{} is not in the dependency graph", name),
+        }
+    }
+    targets.sort_by(|a, b| a.version.cmp(&b.version));
+
+    // Edges from each package to the packages depending on it
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (id, node) in resolve {
+        for dep in &node.dependencies {
+            dependents
+                .entry(dep.package_id.as_str())
+                .or_default()
+                .push(id.as_str());
+        }
+    }
+    for edges in dependents.values_mut() {
+        edges.sort_unstable();
+    }
+
+    let graphs = graph_kinds(metadata);
+    Ok(targets
+        .into_iter()
+        .map(|target| {
+            let mut chains = Vec::new();
+            let mut truncated = false;
+            let mut path = vec![target.id.as_str()];
+            collect_chains(
+                metadata,
+                &dependents,
+                &mut path,
+                &mut chains,
+                &mut truncated,
+            );
+            Why {
+                package: target.name.clone(),
+                version: target.version.clone(),
+                graphs: graphs.get(target.id.as_str()).cloned().unwrap_or_default(),
+                features: metadata
+                    .enabled_features(&target.id)
+                    .unwrap_or_default()
+                    .to_vec(),
+                chains,
+                truncated,
+            }
+        })
+        .collect())
+}
+
+/// Walk up from the last package of `path` to the workspace members, until
+/// [`MAX_CHAINS`] chains are found
+fn collect_chains<'a>(
+    metadata: &ProjectMetadata,
+    dependents: &HashMap<&str, Vec<&'a str>>,
+    path: &mut Vec<&'a str>,
+    chains: &mut Vec<Vec<Link>>,
+    truncated: &mut bool,
+) {
+    let id = *path.last().expect("path starts at the target");
+    if metadata.members.iter().any(|member| member == id) {
+        if chains.len() < MAX_CHAINS {
+            chains.push(chain(metadata, path));
+        } else {
+            *truncated = true;
+        }
+        return;
+    }
+    let Some(edges) = dependents.get(id) else {
+        return;
+    };
+    for dependent in edges {
+        if *truncated {
+            return;
+        }
+        if !path.contains(dependent) {
+            path.push(dependent);
+            collect_chains(metadata, dependents, path, chains, truncated);
+            path.pop();
+        }
+    }
+}
+
+/// The links of `path`, which runs from the crate up to a workspace member
+fn chain(metadata: &ProjectMetadata, path: &[&str]) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut previous: Option<&str> = None;
+    for id in path.iter().rev() {
+        let package = metadata.package(id);
+        links.push(Link {
+            package: package.map(|p| p.name.clone()).unwrap_or_default(),
+            version: package.map(|p| p.version.clone()).unwrap_or_default(),
+            edge: previous.and_then(|dependent| edge(metadata, dependent, id)),
+        });
+        previous = Some(id);
+    }
+    links
+}
+
+/// How `dependent` depends on `id`
+fn edge(metadata: &ProjectMetadata, dependent: &str, id: &str) -> Option<Edge> {
+    let resolved = metadata
+        .resolved_dependencies(dependent)?
+        .iter()
+        .find(|dep| dep.package_id == id)?;
+    let package = metadata.package(dependent)?;
+    let enabled = metadata.enabled_features(dependent).unwrap_or_default();
+
+    let mut kinds: Vec<DepKind> = resolved.kinds.iter().map(|(kind, _)| *kind).collect();
+    kinds.sort_by_key(|kind| rank(*kind));
+    kinds.dedup();
+    let declared: Vec<_> = package
+        .dependencies
+        .iter()
+        .filter(|d| d.package == resolved.package)
+        .collect();
+    let mut features = BTreeSet::new();
+    let mut default_features = false;
+    let mut enabled_by = BTreeSet::new();
+    for declared in &declared {
+        features.extend(declared.features.iter().cloned());
+        default_features |= declared.default_features;
+        if declared.optional {
+            let key = declared.key();
+            for (feature, enables) in &package.features {
+                let switches_on = enables.iter().any(|entry| {
+                    entry == key
+                        || entry.strip_prefix("dep:") == Some(key)
+                        || entry
+                            .strip_prefix(key)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                });
+                if switches_on && enabled.contains(feature) {
+                    enabled_by.insert(feature.clone());
+                }
+            }
+        }
+    }
+    Some(Edge {
+        kinds,
+        features: features.into_iter().collect(),
+        default_features,
+        optional: !declared.is_empty() && declared.iter().all(|d| d.optional),
+        enabled_by: enabled_by.into_iter().collect(),
+    })
+}
+
+/// Normal edges keep a package in the artifacts, build edges confine it to
+/// the host, dev edges to tests
+fn rank(kind: DepKind) -> u8 {
+    match kind {
+        DepKind::Normal => 0,
+        DepKind::Build => 1,
+        DepKind::Dev => 2,
+    }
+}
+
+/// The graphs each package is reachable in from the workspace members
+fn graph_kinds(metadata: &ProjectMetadata) -> HashMap<&str, Vec<DepKind>> {
+    let mut reached: HashMap<&str, Vec<DepKind>> = HashMap::new();
+    let mut seen: HashSet<(&str, u8)> = HashSet::new();
+    let mut queue: VecDeque<(&str, DepKind)> = metadata
+        .members
+        .iter()
+        .map(|id| (id.as_str(), DepKind::Normal))
+        .collect();
+    while let Some((id, graph)) = queue.pop_front() {
+        if !seen.insert((id, rank(graph))) {
+            continue;
+        }
+        let graphs = reached.entry(id).or_default();
+        graphs.push(graph);
+        graphs.sort_by_key(|kind| rank(*kind));
+        for dep in metadata.resolved_dependencies(id).unwrap_or_default() {
+            let Some(kind) = dep
+                .kinds
+                .iter()
+                .map(|(kind, _)| *kind)
+                .min_by_key(|k| rank(*k))
+            else {
+                continue;
+            };
+            // A dependency stays in the most restricted graph on its path
+            let next = if rank(kind) > rank(graph) {
+                kind
+            } else {
+                graph
+            };
+            queue.push_back((dep.package_id.as_str(), next));
+        }
+    }
+    reached
+}
+
+impl Why {
+    /// The chains as a tree hanging off the crate, `cargo tree -i` style
+    pub fn render(&self) -> String {
+        let mut out = format!("{} v{}", self.package, self.version);
+        if !self.features.is_empty() {
+            out.push_str(&format!(" (features: {})", self.features.join(", ")));
+        }
+        out.push('\n');
+        let graphs: Vec<&str> = self.graphs.iter().map(|kind| graph_name(*kind)).collect();
+        out.push_str(&format!("graphs: {}\n", graphs.join(", ")));
+        if self.graphs == [DepKind::Dev] {
+            out.push_str("only needed for tests, examples and benches\n");
+        } else if !self.graphs.contains(&DepKind::Normal) {
+            out.push_str("only needed at build time\n");
+        }
+
+        // Chains inverted, sharing common prefixes
+        let mut tree = Tree::default();
+        for chain in &self.chains {
+            let mut node = &mut tree;
+            for i in (0..chain.len() - 1).rev() {
+                let line = describe(&chain[i], chain[i + 1].edge.as_ref());
+                node = node.children.entry(line).or_default();
+            }
+        }
+        tree.render("", &mut out);
+        if self.truncated {
+            out.push_str(&format!(
+                "… only the first {} chains are shown\n",
+                MAX_CHAINS
+            ));
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct Tree {
+    children: BTreeMap<String, Tree>,
+}
+
+impl Tree {
+    fn render(&self, prefix: &str, out: &mut String) {
+        let count = self.children.len();
+        for (i, (line, child)) in self.children.iter().enumerate() {
+            let last = i + 1 == count;
+            out.push_str(&format!(
+                "{}{}{}\n",
+                prefix,
+                if last { "└── " } else { "├── " },
+                line
+            ));
+            child.render(
+                &format!("{}{}", prefix, if last { "    " } else { "│   " }),
+                out,
+            );
+        }
+    }
+}
+
+/// `package`, depending on the line above it through `edge`
+fn describe(package: &Link, edge: Option<&Edge>) -> String {
+    let mut line = format!("{} v{}", package.package, package.version);
+    let Some(edge) = edge else {
+        return line;
+    };
+    let mut notes = Vec::new();
+    if edge
+        .kinds
+        .first()
+        .is_some_and(|kind| *kind != DepKind::Normal)
+    {
+        let kinds: Vec<&str> = edge.kinds.iter().map(|kind| graph_name(*kind)).collect();
+        notes.push(format!("[{}]", kinds.join(", ")));
+    }
+    if edge.optional {
+        if edge.enabled_by.is_empty() {
+            notes.push("optional".to_string());
+        } else {
+            notes.push(format!(
+                "optional, enabled by feature {}",
+                edge.enabled_by.join(", ")
+            ));
+        }
+    }
+    if !edge.features.is_empty() {
+        notes.push(format!("features: {}", edge.features.join(", ")));
+    }
+    if !edge.default_features {
+        notes.push("no default features".to_string());
+    }
+    if !notes.is_empty() {
+        line.push_str(&format!(" ({})", notes.join("; ")));
+    }
+    line
+}
+
+fn graph_name(kind: DepKind) -> &'static str {
+    match kind {
+        DepKind::Normal => "normal",
+        DepKind::Build => "build",
+        DepKind::Dev => "dev",
+    }
+}
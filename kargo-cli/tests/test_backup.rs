@@ -0,0 +1,62 @@
+use kargo_cli::backup::{BackupManager, PathFilter};
+use kargo_cli::events::EventBus;
+use std::fs;
+
+#[test]
+fn test_preview_and_selective_restore() {
+    let work = tempfile::tempdir().unwrap();
+    let store = tempfile::tempdir().unwrap();
+    let app = work.path().join("app/Cargo.toml");
+    let lib = work.path().join("lib/Cargo.toml");
+    for manifest in [&app, &lib] {
+        fs::create_dir_all(manifest.parent().unwrap()).unwrap();
+        fs::write(manifest, "[dependencies]\nserde = \"1.0\"\n").unwrap();
+    }
+
+    let mut backup = BackupManager::create_in(store.path(), "update", EventBus::new()).unwrap();
+    backup.backup_file(&app).unwrap();
+    backup.backup_file(&lib).unwrap();
+    // A second backup of the same file keeps the original contents
+    fs::write(&app, "[dependencies]\nserde = \"1.0.200\"\n").unwrap();
+    backup.backup_file(&app).unwrap();
+    fs::write(&lib, "[dependencies]\nserde = \"1.0.200\"\n").unwrap();
+
+    let backup = BackupManager::open(store.path(), None, EventBus::new()).unwrap();
+    assert_eq!(backup.changes().len(), 2);
+
+    let app_only = PathFilter::new([format!("{}/app/*", work.path().display())]).unwrap();
+    let diffs = backup.diffs(&app_only).unwrap();
+    assert_eq!(diffs.len(), 1);
+    assert!(diffs[0].unified.contains("-serde = \"1.0.200\""));
+    assert!(diffs[0].unified.contains("+serde = \"1.0\""));
+
+    let restored = backup.restore_matching(&app_only).unwrap();
+    assert_eq!(restored.len(), 1);
+    assert_eq!(
+        fs::read_to_string(&app).unwrap(),
+        "[dependencies]\nserde = \"1.0\"\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&lib).unwrap(),
+        "[dependencies]\nserde = \"1.0.200\"\n"
+    );
+}
+
+#[test]
+fn test_snapshots_are_listed_newest_last() {
+    let work = tempfile::tempdir().unwrap();
+    let store = tempfile::tempdir().unwrap();
+    let manifest = work.path().join("Cargo.toml");
+    fs::write(&manifest, "[package]\nname = \"demo\"\n").unwrap();
+
+    // Nothing is written until a file is backed up
+    BackupManager::create_in(store.path(), "update", EventBus::new()).unwrap();
+    assert!(BackupManager::list(store.path()).unwrap().is_empty());
+
+    let mut backup = BackupManager::create_in(store.path(), "update", EventBus::new()).unwrap();
+    backup.backup_file(&manifest).unwrap();
+    let snapshots = BackupManager::list(store.path()).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].id, backup.id());
+    assert_eq!(snapshots[0].files, 1);
+}
@@ -0,0 +1,128 @@
+use kargo_cli::changelog::{ConventionalCommit, Release, generate, insert_release, owner};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[test]
+fn test_release_sections_are_inserted_or_replaced() {
+    let commits = vec![
+        ConventionalCommit::parse("aaaaaaaaaa", "2026-01-02", "feat(cli)!: drop --legacy", "")
+            .unwrap(),
+        ConventionalCommit::parse(
+            "bbbbbbbbbb",
+            "2026-01-01",
+            "fix: handle empty manifests",
+            "",
+        )
+        .unwrap(),
+    ];
+    assert!(ConventionalCommit::parse("c", "2026-01-01", "Update readme", "").is_none());
+    assert!(
+        !ConventionalCommit::parse("c", "2026-01-01", "chore: bump deps", "")
+            .unwrap()
+            .is_listed()
+    );
+    let section = Release {
+        version: "1.1.0".to_string(),
+        commits,
+    }
+    .render();
+    assert_eq!(
+        section,
+        "## 1.1.0 - 2026-01-02\n\n### Breaking Changes\n\n- **cli:** drop --legacy (aaaaaaa)\n\n\
+         ### Features\n\n- **cli:** drop --legacy (aaaaaaa)\n\n\
+         ### Bug Fixes\n\n- handle empty manifests (bbbbbbb)\n"
+    );
+
+    let existing = "# Changelog\n\nNotable changes.\n\n## 1.0.0 - 2025-12-01\n\n- First release\n";
+    let updated = insert_release(Some(existing), "1.1.0", "## 1.1.0\n\n- New\n");
+    assert_eq!(
+        updated,
+        "# Changelog\n\nNotable changes.\n\n## 1.1.0\n\n- New\n\n## 1.0.0 - 2025-12-01\n\n- First release\n"
+    );
+    // Running again replaces the section
+    let again = insert_release(Some(&updated), "1.1.0", "## 1.1.0\n\n- Newer\n");
+    assert_eq!(again, updated.replace("- New\n", "- Newer\n"));
+    assert_eq!(
+        insert_release(None, "0.1.0", "## 0.1.0\n"),
+        "# Changelog\n\n## 0.1.0\n"
+    );
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn package(dir: &Path, name: &str, version: &str) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/lib.rs"), "").unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!("[package]\nname = \"{name}\"\nversion = \"{version}\"\nedition = \"2021\"\n"),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_commits_are_mapped_to_the_members_they_touch() {
+    let root = tempfile::tempdir().unwrap();
+    let root = root.path();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"core\", \"core/macros\"]\n",
+    )
+    .unwrap();
+    package(&root.join("core"), "core", "0.2.0");
+    package(&root.join("core/macros"), "core-macros", "0.1.1");
+    git(root, &["init", "-q"]);
+    git(root, &["add", "-A"]);
+    git(root, &["commit", "-qm", "feat: initial"]);
+    git(root, &["tag", "core-v0.1.0"]);
+
+    fs::write(root.join("core/src/lib.rs"), "pub fn a() {}").unwrap();
+    git(root, &["commit", "-qam", "feat(core): add a"]);
+    fs::write(root.join("core/macros/src/lib.rs"), "// fixed").unwrap();
+    git(root, &["commit", "-qam", "fix: macro hygiene"]);
+
+    assert_eq!(
+        owner(
+            Path::new("core/macros/src/lib.rs"),
+            &[
+                ("core".to_string(), PathBuf::from("core")),
+                ("core-macros".to_string(), PathBuf::from("core/macros")),
+            ]
+        ),
+        Some("core-macros")
+    );
+
+    let changelogs = generate(root, &[], None).unwrap();
+    let core = changelogs.iter().find(|c| c.package == "core").unwrap();
+    assert_eq!(core.since.as_deref(), Some("core-v0.1.0"));
+    let descriptions: Vec<&str> = core
+        .release
+        .commits
+        .iter()
+        .map(|c| c.description.as_str())
+        .collect();
+    assert_eq!(descriptions, ["add a"]);
+
+    // Untagged, so everything that touched it counts
+    let macros = changelogs
+        .iter()
+        .find(|c| c.package == "core-macros")
+        .unwrap();
+    assert_eq!(macros.since, None);
+    assert_eq!(macros.release.commits.len(), 2);
+
+    core.write().unwrap();
+    let written = fs::read_to_string(root.join("core/CHANGELOG.md")).unwrap();
+    assert!(written.starts_with("# Changelog\n\n## 0.2.0 - "));
+    assert!(written.contains("- **core:** add a"));
+}
@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+use kargo_cli::ci::CiReporter;
+use kargo_cli::events::{Event, EventBus, EventKind, Source};
+use kargo_cli::status::ExitStatus;
+use kargo_plugin_api::artifacts::CapturedOutput;
+
+#[test]
+fn test_rustc_diagnostics_fail_the_run() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let summary = dir.path().join("summary.md");
+
+    let mut reporter = CiReporter::new(Some(summary.clone()));
+    for line in [
+        "error[E0308]: mismatched types",
+        " --> src/main.rs:1:25",
+        "error: could not compile `x` (bin \"x\") due to 1 previous error",
+    ] {
+        reporter.record(&Event::new(
+            Source::Cli,
+            EventKind::KargoOutputLine {
+                line: line.to_string(),
+                is_error: true,
+            },
+        ));
+    }
+
+    reporter.record(&Event::new(
+        Source::Cli,
+        EventKind::KargoCommandFinished {
+            subcommand: "build".to_string(),
+            success: false,
+            summary: String::new(),
+        },
+    ));
+
+    let result = Err(anyhow::anyhow!("cargo exited with Some(101)"));
+    let report = reporter.finish(&result).expect("Failed to finish report");
+    assert_eq!(report.status, ExitStatus::Failure);
+    assert_eq!(report.exit_code, 1);
+    assert_eq!(report.diagnostics.len(), 1);
+
+    let summary = std::fs::read_to_string(summary).expect("Failed to read summary");
+    assert!(summary.contains("`src/main.rs:1` mismatched types"));
+    assert!(summary.contains("| 1 | 0 | 0 | 0 |"));
+}
+
+#[test]
+fn test_outdated_dependencies_report_updates_available() {
+    let mut reporter = CiReporter::new(None);
+    reporter.record(&Event::new(
+        Source::Updater,
+        EventKind::DependencyOutdated {
+            path: PathBuf::from("Cargo.toml"),
+            name: "serde".to_string(),
+            current: "1.0.0".to_string(),
+            latest: "1.0.200".to_string(),
+        },
+    ));
+
+    let report = reporter.finish(&Ok(())).expect("Failed to finish report");
+    assert_eq!(report.status, ExitStatus::UpdatesAvailable);
+    assert_eq!(report.exit_code, 2);
+}
+
+#[test]
+fn test_vulnerable_dependencies_report_updates_available() {
+    let mut reporter = CiReporter::silent();
+    reporter.record(&Event::new(
+        Source::Plugin,
+        EventKind::VulnerableDependency {
+            path: PathBuf::from("Cargo.toml"),
+            name: "time".to_string(),
+            version: "0.1.0".to_string(),
+            advisory: "RUSTSEC-2020-0071".to_string(),
+        },
+    ));
+
+    let report = reporter.finish(&Ok(())).expect("Failed to finish report");
+    assert_eq!(report.diagnostics.len(), 1);
+    assert_eq!(report.status, ExitStatus::UpdatesAvailable);
+    assert_eq!(report.exit_code, 2);
+}
+
+#[test]
+fn test_errors_after_successful_steps_are_partial_failures() {
+    let mut reporter = CiReporter::silent();
+    reporter.record(&Event::new(
+        Source::Cli,
+        EventKind::KargoCommandFinished {
+            subcommand: "upgrade".to_string(),
+            success: true,
+            summary: String::new(),
+        },
+    ));
+    reporter.record(&Event::new(
+        Source::Updater,
+        EventKind::Error {
+            message: "Failed to update crates/a/Cargo.toml".to_string(),
+        },
+    ));
+
+    let report = reporter.finish(&Ok(())).expect("Failed to finish report");
+    assert_eq!(report.status, ExitStatus::PartialFailure);
+    assert_eq!(report.exit_code, 3);
+}
+
+#[test]
+fn test_config_errors_have_their_own_exit_code() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let status_file = dir.path().join("status.json");
+
+    let error = kargo_cli::status::ConfigError::new("krater.yaml", "expected a mapping");
+    let result = Err(anyhow::Error::new(error).context("Failed to start"));
+    let report = CiReporter::silent()
+        .finish(&result)
+        .expect("Failed to finish report");
+    assert_eq!(report.status, ExitStatus::ConfigError);
+
+    report
+        .write(&status_file)
+        .expect("Failed to write status file");
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(status_file).unwrap()).unwrap();
+    assert_eq!(json["status"], "config-error");
+    assert_eq!(json["exit_code"], 4);
+}
+
+#[test]
+fn test_saved_command_output_is_referenced_from_the_report() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let summary = dir.path().join("summary.md");
+    let output = CapturedOutput {
+        command: "cargo deny check".to_string(),
+        exit_code: Some(1),
+        stdout: PathBuf::from(".kargo/runs/1-1/001-cargo-deny-check.stdout"),
+        stderr: PathBuf::from(".kargo/runs/1-1/001-cargo-deny-check.stderr"),
+    };
+
+    let mut reporter = CiReporter::new(Some(summary.clone()));
+    reporter.record(&Event::new(
+        Source::Commands,
+        EventKind::CommandFinished {
+            command: output.command.clone(),
+            success: false,
+            output: Some(output.clone()),
+        },
+    ));
+
+    let report = reporter.finish(&Ok(())).expect("Failed to finish report");
+    assert_eq!(report.outputs, [output]);
+    let summary = std::fs::read_to_string(summary).expect("Failed to read summary");
+    assert!(
+        summary
+            .contains("| `cargo deny check` | 1 | `.kargo/runs/1-1/001-cargo-deny-check.stderr` |")
+    );
+}
+
+#[tokio::test]
+async fn test_missed_events_fail_the_run() {
+    let events = EventBus::new();
+    let reporter = CiReporter::silent().spawn(&events);
+    // The reporter doesn't get to run until the test yields, so it falls
+    // behind and misses the oldest events
+    for _ in 0..500 {
+        events.publish(
+            Source::Cli,
+            EventKind::KargoCommandFinished {
+                subcommand: "build".to_string(),
+                success: true,
+                summary: String::new(),
+            },
+        );
+    }
+    drop(events);
+
+    let reporter = reporter.await.expect("CI reporter panicked");
+    let report = reporter.finish(&Ok(())).expect("Failed to finish report");
+    assert_eq!(report.status, ExitStatus::PartialFailure);
+    assert!(
+        report
+            .diagnostics
+            .iter()
+            .any(|d| d.message.starts_with("Missed "))
+    );
+}
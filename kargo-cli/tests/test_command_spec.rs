@@ -0,0 +1,101 @@
+use kargo_cli::cli::check_plugin_args;
+use kargo_cli::plugins::command_spec::{self, CommandSpec};
+use kargo_cli::status::ExitStatus;
+use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+use std::path::PathBuf;
+
+const SPEC: &str = r#"{
+    "name": "bench",
+    "about": "Run benchmarks",
+    "args": [
+        {"name": "verbose", "short": "v", "long": "verbose", "help": "", "required": false,
+         "takes_value": false, "multiple": true, "global": true}
+    ],
+    "subcommands": [{
+        "name": "run",
+        "about": "Run a suite",
+        "args": [
+            {"name": "suite", "short": null, "long": null, "help": "Suite file", "required": true,
+             "takes_value": true, "value_type": "path"},
+            {"name": "iterations", "short": "n", "long": "iterations", "help": "", "required": false,
+             "takes_value": true, "value_type": "unsigned", "default_values": ["10"]},
+            {"name": "format", "short": null, "long": "format", "help": "", "required": false,
+             "takes_value": true, "possible_values": ["text", "json"]},
+            {"name": "json", "short": null, "long": "json", "help": "", "required": false,
+             "takes_value": false}
+        ],
+        "groups": [{"name": "output", "args": ["format", "json"]}]
+    }],
+    "subcommand_required": true
+}"#;
+
+#[test]
+fn test_spec_converts_to_full_clap_command() {
+    let cmd = command_spec::parse(SPEC).unwrap().to_clap();
+
+    let matches = cmd
+        .clone()
+        .try_get_matches_from(["bench", "-vv", "run", "suite.toml", "--format", "json"])
+        .unwrap();
+    assert_eq!(matches.get_count("verbose"), 2);
+    let run = matches.subcommand_matches("run").unwrap();
+    assert_eq!(
+        run.get_one::<PathBuf>("suite"),
+        Some(&PathBuf::from("suite.toml"))
+    );
+    assert_eq!(run.get_one::<u64>("iterations"), Some(&10));
+
+    let parse = |args: &[&str]| cmd.clone().try_get_matches_from(args);
+    assert!(parse(&["bench"]).is_err());
+    assert!(parse(&["bench", "run"]).is_err());
+    assert!(parse(&["bench", "run", "s", "-n", "many"]).is_err());
+    assert!(parse(&["bench", "run", "s", "--format", "xml"]).is_err());
+    assert!(parse(&["bench", "run", "s", "--format", "text", "--json"]).is_err());
+}
+
+#[test]
+fn test_old_specs_load_and_bad_references_are_rejected() {
+    let old = r#"{"name": "hello", "about": "Say hello", "args": [
+        {"name": "name", "short": "n", "long": "name", "help": "Who", "required": false, "takes_value": true}
+    ]}"#;
+    let matches = command_spec::parse(old)
+        .unwrap()
+        .to_clap()
+        .try_get_matches_from(["hello", "--name", "kargo"])
+        .unwrap();
+    assert_eq!(matches.get_one::<String>("name").unwrap(), "kargo");
+
+    let dangling = r#"{"name": "x", "about": "", "args": [
+        {"name": "a", "short": null, "long": "a", "help": "", "required": false, "takes_value": false,
+         "requires": ["b"]}
+    ]}"#;
+    assert!(command_spec::parse(dangling).is_err());
+}
+
+/// A plugin with a declared command that must never be run
+struct Declared(CommandSpec);
+
+impl PluginCommand for Declared {
+    fn clap(&self) -> clap::Command {
+        self.0.to_clap()
+    }
+
+    fn run(&self, _ctx: ExecutionContext) -> BoxFuture {
+        unreachable!("arguments are checked before the plugin runs")
+    }
+}
+
+#[test]
+fn test_bad_plugin_arguments_are_usage_errors() {
+    let plugin = Declared(command_spec::parse(SPEC).unwrap());
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+    assert!(check_plugin_args(&plugin, &args(&["bench", "run", "suite.toml"])).unwrap());
+
+    let error =
+        check_plugin_args(&plugin, &args(&["bench", "run", "s", "-n", "many"])).unwrap_err();
+    assert_eq!(ExitStatus::from_error(&error), ExitStatus::UsageError);
+    let rendered = error.to_string();
+    assert!(rendered.contains("invalid value 'many'"));
+    assert!(rendered.contains("Usage: kargo bench"));
+}
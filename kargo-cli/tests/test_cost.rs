@@ -0,0 +1,67 @@
+use kargo_cli::cost::{BuildTime, CostReport, CrateStatus, classify, lockfile_packages};
+
+#[test]
+fn test_resolved_crates_are_compared_with_the_lockfile() {
+    let lockfile = r#"
+version = 4
+
+[[package]]
+name = "itoa"
+version = "0.4.8"
+
+[[package]]
+name = "ryu"
+version = "1.0.18"
+
+[[package]]
+name = "memchr"
+version = "2.7.1"
+"#;
+    let locked = lockfile_packages(lockfile);
+    assert_eq!(locked.len(), 3);
+
+    let resolved: Vec<(String, String)> = [
+        ("serde_json", "1.0.128"),
+        ("itoa", "1.0.11"),
+        ("ryu", "1.0.18"),
+        ("memchr", "2.7.4"),
+    ]
+    .iter()
+    .map(|(n, v)| (n.to_string(), v.to_string()))
+    .collect();
+    let mut crates = classify(&resolved, &locked);
+    let statuses: Vec<(&str, CrateStatus)> =
+        crates.iter().map(|c| (c.name.as_str(), c.status)).collect();
+    // memchr 2.7.4 would replace the locked 2.7.1 rather than duplicate it
+    assert_eq!(
+        statuses,
+        [
+            ("itoa", CrateStatus::Duplicate),
+            ("memchr", CrateStatus::New),
+            ("ryu", CrateStatus::Locked),
+            ("serde_json", CrateStatus::New),
+        ]
+    );
+    assert_eq!(crates[0].locked_versions, ["0.4.8"]);
+
+    crates[0].download_bytes = Some(10 * 1024);
+    crates[3].download_bytes = Some(150 * 1024);
+    let report = CostReport {
+        name: "serde_json".to_string(),
+        version: "1.0.128".to_string(),
+        crates,
+        build_time: Some(BuildTime {
+            seconds: 2.4,
+            measured: false,
+        }),
+    };
+    assert_eq!(report.download_bytes(), 160 * 1024);
+    assert_eq!(
+        report.render(),
+        "serde_json 1.0.128\n\
+         \x20 crates      4 (3 new, 1 already in Cargo.lock)\n\
+         \x20 download    160.0 KiB (size of 1 crate(s) unknown)\n\
+         \x20 build       ~2s (estimated from source size; --measure to build it)\n\
+         \x20 duplicates  itoa 1.0.11 alongside 0.4.8\n"
+    );
+}
@@ -0,0 +1,49 @@
+use kargo_cli::cli::{build_root_cli, with_default_command};
+use kargo_cli::config::Config;
+use kargo_cli::plugins::manager::PluginManager;
+
+fn rewrite(args: &[&str], default: &str) -> Vec<String> {
+    let root = build_root_cli(&PluginManager::new());
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    with_default_command(&root, args, default)
+}
+
+#[test]
+fn test_default_command_takes_what_kargo_has_no_command_for() {
+    assert_eq!(rewrite(&["kargo"], "doctor"), ["kargo", "doctor"]);
+    assert_eq!(
+        rewrite(&["kargo", "--offline", "--log-format", "json"], "doctor"),
+        ["kargo", "--offline", "--log-format", "json", "doctor"]
+    );
+    assert_eq!(
+        rewrite(
+            &["kargo", "--profile-run", "where", "is", "retry"],
+            "doctor"
+        ),
+        ["kargo", "--profile-run", "doctor", "where", "is", "retry"]
+    );
+    assert_eq!(
+        rewrite(&["kargo", "--limit", "5"], "doctor"),
+        ["kargo", "doctor", "--limit", "5"]
+    );
+}
+
+#[test]
+fn test_known_commands_and_help_are_left_alone() {
+    for args in [
+        &["kargo", "why", "serde"][..],
+        &["kargo", "--offline", "help"],
+        &["kargo", "--help"],
+        &["kargo", "-V"],
+        &["kargo", "--alias"],
+    ] {
+        assert_eq!(rewrite(args, "doctor"), args);
+    }
+    // Not a command, so nothing is redirected to it
+    assert_eq!(rewrite(&["kargo"], "nope"), ["kargo"]);
+
+    let config = Config::default()
+        .with_env([("KARGO_DEFAULT_COMMAND".to_string(), "sap".to_string())])
+        .unwrap();
+    assert_eq!(config.default_command.as_deref(), Some("sap"));
+}
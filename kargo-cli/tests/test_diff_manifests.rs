@@ -0,0 +1,187 @@
+use kargo_cli::diff_manifests::{self, Change, RequirementChange, Side};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const BEFORE: &str = r#"[package]
+name = "app"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+tokio = "1"
+log = "0.4"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[features]
+default = ["std"]
+std = []
+"#;
+
+const AFTER: &str = r#"[package]
+name = "app"
+version = "0.2.0"
+edition = "2021"
+rust-version = "1.80"
+
+[dependencies]
+tokio = { version = "2", optional = true }
+log = "^0.4.0"
+anyhow = "1"
+
+[dependencies.serde]
+features = ["rc", "derive"]
+version = "1.0.200"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[features]
+default = ["std", "async"]
+std = []
+async = ["dep:tokio"]
+"#;
+
+#[test]
+fn test_changes_are_reported_by_meaning() {
+    let diff = diff_manifests::diff(BEFORE, AFTER).unwrap();
+
+    let deps: Vec<(&str, Change)> = diff
+        .dependencies
+        .iter()
+        .map(|d| (d.name.as_str(), d.change))
+        .collect();
+    // log only changed how its requirement is written; libc not at all
+    assert_eq!(
+        deps,
+        [
+            ("anyhow", Change::Added),
+            ("serde", Change::Changed),
+            ("tokio", Change::Changed),
+        ]
+    );
+    let serde = &diff.dependencies[1];
+    assert_eq!(serde.requirement, Some(RequirementChange::Raised));
+    assert_eq!(serde.features_added, ["rc"]);
+    assert!(serde.features_removed.is_empty());
+    let tokio = &diff.dependencies[2];
+    assert_eq!(tokio.requirement, Some(RequirementChange::Disjoint));
+    assert_eq!(tokio.fields[0].key, "optional");
+    assert_eq!(tokio.fields[0].after.as_deref(), Some("true"));
+
+    let features: Vec<(&str, Change)> = diff
+        .features
+        .iter()
+        .map(|f| (f.name.as_str(), f.change))
+        .collect();
+    assert_eq!(
+        features,
+        [("async", Change::Added), ("default", Change::Changed)]
+    );
+    assert_eq!(diff.features[1].enables_added, ["async"]);
+
+    let metadata: Vec<&str> = diff.metadata.iter().map(|f| f.key.as_str()).collect();
+    assert_eq!(metadata, ["package.rust-version", "package.version"]);
+
+    let text = diff.render();
+    assert!(text.contains("  ~ dependencies.serde: 1.0 -> 1.0.200 (raised); features +rc\n"));
+    assert!(text.contains("  + dependencies.anyhow: 1\n"));
+    assert!(text.contains("  ~ package.version \"0.1.0\" -> \"0.2.0\"\n"));
+}
+
+#[test]
+fn test_reformatting_is_no_change() {
+    let reformatted = r#"[features]
+std = []
+default = ["std"]
+
+[dependencies]
+log = "0.4"
+tokio = { version = "1" }
+
+[dependencies.serde]
+version = "1.0"
+features = ["derive"]
+
+[package]
+edition = "2021"
+name = "app"
+version = "0.1.0"
+
+[target."cfg(unix)".dependencies]
+libc = "0.2"
+"#;
+    let diff = diff_manifests::diff(BEFORE, reformatted).unwrap();
+    assert!(diff.is_empty(), "{:?}", diff);
+    assert_eq!(diff.render(), "No changes\n");
+}
+
+#[test]
+fn test_requirement_moves_are_classified() {
+    let cases = [
+        ("1.0", "^1.0.0", RequirementChange::Equivalent),
+        ("1.*", "1", RequirementChange::Equivalent),
+        ("1.2", "1.4", RequirementChange::Raised),
+        ("1.4", "1.2", RequirementChange::Lowered),
+        ("~1.2", "1.2", RequirementChange::Widened),
+        ("1.2", "~1.2", RequirementChange::Narrowed),
+        ("0.4", "0.5", RequirementChange::Disjoint),
+        ("=1.2.3", ">=1.2.3", RequirementChange::Widened),
+        ("1", "latest", RequirementChange::Unknown),
+    ];
+    for (before, after, expected) in cases {
+        assert_eq!(
+            RequirementChange::of(before, after),
+            expected,
+            "{} -> {}",
+            before,
+            after
+        );
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn test_bare_revision_reads_the_other_sides_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("crates/app");
+    fs::create_dir_all(&app).unwrap();
+    let manifest = app.join("Cargo.toml");
+    fs::write(&manifest, BEFORE).unwrap();
+    git(dir.path(), &["init", "-q"]);
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-qm", "init"]);
+    fs::write(&manifest, AFTER).unwrap();
+
+    let path = manifest.to_str().unwrap();
+    let (old, new) = diff_manifests::sides("HEAD", path).unwrap();
+    assert_eq!(
+        old,
+        Side::Revision {
+            rev: "HEAD".to_string(),
+            path: manifest.clone(),
+        }
+    );
+    assert_eq!(new, Side::File(manifest.clone()));
+    assert_eq!(old.read().unwrap(), BEFORE);
+    assert!(
+        !diff_manifests::diff(&old.read().unwrap(), &new.read().unwrap())
+            .unwrap()
+            .is_empty()
+    );
+
+    assert!(diff_manifests::sides("HEAD", "HEAD~1").is_err());
+}
@@ -0,0 +1,79 @@
+use kargo_cli::digest::{Digest, Snapshot};
+use kargo_cli::fleet::report::{Dashboard, InventoryEntry};
+use kargo_cli::status::{OutdatedDependency, VulnerableDependency};
+use std::path::PathBuf;
+
+fn project(name: &str, status: &str) -> InventoryEntry {
+    InventoryEntry {
+        path: PathBuf::from("/repos").join(name),
+        name: name.to_string(),
+        version: "0.1.0".to_string(),
+        description: None,
+        project_type: "Library".to_string(),
+        status: status.to_string(),
+        dependencies: Vec::new(),
+        dependents: Vec::new(),
+        tags: Vec::new(),
+        owners: None,
+    }
+}
+
+fn outdated(project: &str, name: &str) -> OutdatedDependency {
+    OutdatedDependency {
+        path: PathBuf::from("/repos").join(project).join("Cargo.toml"),
+        name: name.to_string(),
+        current: "1.0.0".to_string(),
+        latest: "2.0.0".to_string(),
+    }
+}
+
+#[test]
+fn test_digest_reports_only_new_problems() {
+    let mut last_week = Dashboard::new(vec![project("api", "Working"), project("cli", "Broken")]);
+    last_week.add_outdated(outdated("api", "serde"));
+    last_week.add_outdated(outdated("cli", "rand"));
+    let snapshot = Snapshot::of(&last_week, 1_000);
+
+    let mut today = Dashboard::new(vec![project("api", "Broken"), project("cli", "Working")]);
+    today.add_outdated(outdated("api", "serde"));
+    today.add_outdated(outdated("api", "tokio"));
+    today.add_vulnerable(VulnerableDependency {
+        path: PathBuf::from("/repos/api/Cargo.lock"),
+        name: "time".to_string(),
+        version: "0.1.0".to_string(),
+        advisory: "RUSTSEC-2020-0071".to_string(),
+    });
+
+    let digest = Digest::between(Some(&snapshot), &today);
+    assert_eq!(digest.since, Some(1_000));
+    assert_eq!(digest.newly_outdated.len(), 1);
+    assert_eq!(digest.newly_outdated[0].name, "tokio");
+    assert_eq!(digest.newly_vulnerable[0].advisory, "RUSTSEC-2020-0071");
+    assert_eq!(digest.newly_broken.len(), 1);
+    assert_eq!(digest.newly_broken[0].name, "api");
+    // cli works again and no longer lags on rand
+    assert_eq!(digest.resolved, 2);
+
+    let text = digest.render(1_000 + 7 * 86_400);
+    assert!(text.contains("since the last digest (7d ago)"));
+    assert!(text.contains("• tokio 1.0.0 → 2.0.0"));
+    assert!(!text.contains("serde"));
+}
+
+#[test]
+fn test_snapshot_round_trips_and_first_digest_reports_everything() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("digest/snapshot.json");
+    assert_eq!(Snapshot::load(&path).unwrap(), None);
+
+    let mut dashboard = Dashboard::new(vec![project("api", "Working")]);
+    dashboard.add_outdated(outdated("api", "serde"));
+    let snapshot = Snapshot::of(&dashboard, 42);
+    snapshot.save(&path).unwrap();
+    assert_eq!(Snapshot::load(&path).unwrap(), Some(snapshot.clone()));
+
+    let first = Digest::between(None, &dashboard);
+    assert_eq!(first.newly_outdated.len(), 1);
+    assert!(first.render(42).contains("in the first digest"));
+    assert!(Digest::between(Some(&snapshot), &dashboard).is_empty());
+}
@@ -0,0 +1,114 @@
+use kargo_cli::distribution::{
+    CONTENT_DIGEST_ANNOTATION, ImageSpec, PluginArtifact, find_plugin_artifacts, select_plugins,
+    verify,
+};
+use std::path::Path;
+
+fn spec(dir: &Path) -> ImageSpec {
+    let kargo = dir.join("kargo");
+    std::fs::write(&kargo, "#!/bin/sh\n").unwrap();
+    let plugins = dir.join("plugins");
+    std::fs::create_dir_all(&plugins).unwrap();
+    std::fs::write(plugins.join("libkargo_upgrade.so"), "upgrade").unwrap();
+    std::fs::write(plugins.join("hello.wasm"), "hello").unwrap();
+    std::fs::write(plugins.join("notes.txt"), "not a plugin").unwrap();
+
+    let available = find_plugin_artifacts(&[plugins]);
+    ImageSpec {
+        kargo,
+        kargo_version: "1.2.3".to_string(),
+        plugins: select_plugins(&available, &[], |name| name != "hello").unwrap(),
+        config: Some("scan_dirs: [/src]\n".to_string()),
+        profile: None,
+        tag: "1.2.3".to_string(),
+    }
+}
+
+#[test]
+fn test_plugin_artifacts_are_named_and_selected() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = spec(dir.path());
+    assert_eq!(spec.plugins.len(), 1);
+    assert_eq!(spec.plugins[0].name, "upgrade");
+
+    let available = find_plugin_artifacts(&[dir.path().join("plugins")]);
+    let names: Vec<&str> = available.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, ["hello", "upgrade"]);
+    let error = select_plugins(&available, &["mddoc".to_string()], |_| true).unwrap_err();
+    assert!(error.to_string().contains("found: hello, upgrade"));
+    assert!(
+        !PluginArtifact::from_path(Path::new("kargo_walk.dll"))
+            .unwrap()
+            .is_linux()
+    );
+}
+
+#[test]
+fn test_image_layout_is_reproducible_and_verifiable() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = spec(dir.path());
+
+    let first = spec.write_layout(&dir.path().join("a")).unwrap();
+    let second = spec.write_layout(&dir.path().join("b")).unwrap();
+    assert_eq!(first.manifest_digest, second.manifest_digest);
+
+    let layout = dir.path().join("a");
+    let index: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(layout.join("index.json")).unwrap()).unwrap();
+    assert_eq!(index["manifests"][0]["digest"], first.manifest_digest);
+    let manifest_blob = layout
+        .join("blobs/sha256")
+        .join(first.manifest_digest.trim_start_matches("sha256:"));
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(manifest_blob).unwrap()).unwrap();
+    let content_digest = first.contents.digest();
+    assert_eq!(
+        manifest["annotations"][CONTENT_DIGEST_ANNOTATION],
+        content_digest
+    );
+    let paths: Vec<&str> = first
+        .contents
+        .files
+        .iter()
+        .map(|f| f.path.as_str())
+        .collect();
+    assert_eq!(
+        paths,
+        ["bin/kargo", "config.yaml", "plugins/libkargo_upgrade.so"]
+    );
+
+    // Install the image's files as a runner would have them
+    let installed = dir.path().join("opt/kargo");
+    std::fs::create_dir_all(installed.join("bin")).unwrap();
+    std::fs::create_dir_all(installed.join("plugins")).unwrap();
+    std::fs::copy(&spec.kargo, installed.join("bin/kargo")).unwrap();
+    std::fs::copy(
+        &spec.plugins[0].path,
+        installed.join("plugins/libkargo_upgrade.so"),
+    )
+    .unwrap();
+    std::fs::write(installed.join("config.yaml"), spec.config.as_ref().unwrap()).unwrap();
+    std::fs::write(
+        installed.join("image.json"),
+        serde_json::to_vec_pretty(&first.contents).unwrap(),
+    )
+    .unwrap();
+    assert!(
+        verify(&installed, "1.2.3", &content_digest)
+            .unwrap()
+            .is_empty()
+    );
+
+    std::fs::write(installed.join("plugins/libkargo_upgrade.so"), "patched").unwrap();
+    std::fs::write(installed.join("plugins/extra.wasm"), "extra").unwrap();
+    let problems = verify(&installed, "1.2.4", &content_digest).unwrap();
+    assert_eq!(
+        problems,
+        [
+            "kargo 1.2.4 is running, the image installs 1.2.3",
+            "plugins/libkargo_upgrade.so differs from the image",
+            "plugins/extra.wasm is not part of the image",
+        ]
+    );
+    assert_eq!(verify(&installed, "1.2.3", "sha256:00").unwrap().len(), 3);
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use kargo_cli::events::{EventBus, EventKind, EventLog, KEEP_LOGS, Severity, Source};
+use kargo_cli::run_log::EventFilter;
+use kargo_plugin_api::artifacts::run_id;
+
+#[tokio::test]
+async fn test_events_are_logged_per_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = EventLog::new(dir.path());
+    // Runs left behind by earlier processes, the oldest to be pruned
+    for run in 0..KEEP_LOGS {
+        std::fs::write(log.path(&format!("{:012}-1", run)), "").unwrap();
+    }
+
+    let bus = EventBus::new();
+    bus.publish(
+        Source::Updater,
+        EventKind::Info {
+            message: "not logged yet".to_string(),
+        },
+    );
+    let path = bus.persist(&log).unwrap();
+    let clone = bus.clone();
+    clone.publish(
+        Source::Cli,
+        EventKind::KargoCommandStarted {
+            subcommand: "upgrade".to_string(),
+            args: vec!["upgrade".to_string()],
+        },
+    );
+    bus.publish(
+        Source::Updater,
+        EventKind::CargoTomlFound {
+            path: PathBuf::from("a/Cargo.toml"),
+        },
+    );
+    bus.publish(
+        Source::Commands,
+        EventKind::CommandFinished {
+            command: "cargo test".to_string(),
+            success: false,
+            output: None,
+        },
+    );
+
+    assert_eq!(path, log.path(run_id()));
+    let runs = log.runs().unwrap();
+    assert_eq!(runs.len(), KEEP_LOGS);
+    assert!(!runs.contains(&format!("{:012}-1", 0)));
+    assert_eq!(runs.last().map(String::as_str), Some(run_id()));
+
+    let events = log.read(run_id()).unwrap();
+    assert_eq!(events.len(), 3);
+    assert!(events.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert_eq!(events[0].source, Source::Cli);
+    assert_eq!(events[0].severity, Severity::Info);
+    assert_eq!(events[1].severity, Severity::Debug);
+    assert_eq!(events[2].severity, Severity::Error);
+    assert_eq!(events[2].kind.to_string(), "cargo test failed");
+
+    let failures = EventFilter {
+        severity: Severity::Warning,
+        sources: Vec::new(),
+        grep: None,
+    };
+    let from_updater = EventFilter {
+        severity: Severity::Debug,
+        sources: vec![Source::Updater],
+        grep: Some("a/Cargo".to_string()),
+    };
+    let matching = |filter: &EventFilter| events.iter().filter(|e| filter.matches(e)).count();
+    assert_eq!(matching(&failures), 1);
+    assert_eq!(matching(&from_updater), 1);
+}
@@ -0,0 +1,35 @@
+use kargo_cli::explain::{EXPLANATIONS, detect, lookup, suggestion};
+use kargo_cli::status::ConfigError;
+use kargo_plugin_api::ReadOnlyError;
+
+#[test]
+fn test_codes_resolve_by_name_and_exit_code() {
+    assert_eq!(lookup("4").unwrap().code, "config-error");
+    assert_eq!(lookup("policy::banned").unwrap().code, "policy::banned");
+    assert!(lookup("0").is_none());
+    assert!(lookup("nonsense").is_none());
+    assert!(
+        EXPLANATIONS
+            .iter()
+            .all(|e| !e.causes.is_empty() && !e.remediation.is_empty())
+    );
+}
+
+#[test]
+fn test_cargo_and_rustup_failures_are_detected() {
+    let output = "\
+error: the lock file /work/app/Cargo.lock needs to be updated but --locked was passed to prevent this
+error: toolchain 'nightly-2024-01-01-x86_64-unknown-linux-gnu' is not installed";
+    let codes: Vec<&str> = detect(output).iter().map(|e| e.code).collect();
+    assert_eq!(codes, ["cargo::locked", "rustup::toolchain-missing"]);
+
+    let error = anyhow::Error::new(ConfigError::new("kargo.yaml", "bad indentation"));
+    assert_eq!(suggestion(&error), "config-error");
+    let error = anyhow::anyhow!("failed to select a version for `tokio`");
+    assert_eq!(suggestion(&error), "cargo::version-conflict");
+    let error = anyhow::Error::new(ReadOnlyError {
+        path: "Cargo.toml".into(),
+    })
+    .context("Failed to upgrade");
+    assert_eq!(suggestion(&error), "read-only");
+}
@@ -0,0 +1,168 @@
+use kargo_cli::features::{Activation, why_unified};
+use kargo_plugin_api::metadata::{
+    DeclaredDependency, DepKind, DependencySource, PackageInfo, ProjectMetadata,
+    ResolvedDependency, ResolvedNode,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// A package, the features of its feature table it has enabled, and its
+/// dependencies with the features they ask for and whether they keep the
+/// defaults
+struct Spec {
+    name: &'static str,
+    features: &'static [(&'static str, &'static [&'static str])],
+    enabled: &'static [&'static str],
+    deps: &'static [(&'static str, &'static [&'static str], bool)],
+}
+
+fn id(name: &str) -> String {
+    format!("path+file:///ws/{}#{}@1.0.0", name, name)
+}
+
+fn metadata(root: &Path, members: &[&str], specs: &[Spec]) -> ProjectMetadata {
+    let mut packages = Vec::new();
+    let mut resolve = HashMap::new();
+    for spec in specs {
+        packages.push(PackageInfo {
+            id: id(spec.name),
+            name: spec.name.to_string(),
+            version: "1.0.0".to_string(),
+            manifest_path: root.join(spec.name).join("Cargo.toml"),
+            edition: "2021".to_string(),
+            targets: Vec::new(),
+            features: spec
+                .features
+                .iter()
+                .map(|(name, enables)| {
+                    (
+                        name.to_string(),
+                        enables.iter().map(|e| e.to_string()).collect(),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>(),
+            dependencies: spec
+                .deps
+                .iter()
+                .map(|(name, features, default_features)| DeclaredDependency {
+                    package: name.to_string(),
+                    rename: None,
+                    req: "1".to_string(),
+                    kind: DepKind::Normal,
+                    target: None,
+                    optional: false,
+                    default_features: *default_features,
+                    features: features.iter().map(|f| f.to_string()).collect(),
+                    source: DependencySource::Registry(None),
+                })
+                .collect(),
+        });
+        let node = ResolvedNode {
+            dependencies: spec
+                .deps
+                .iter()
+                .map(|(name, _, _)| ResolvedDependency {
+                    name: name.to_string(),
+                    package_id: id(name),
+                    package: name.to_string(),
+                    version: "1.0.0".to_string(),
+                    kinds: vec![(DepKind::Normal, None)],
+                })
+                .collect(),
+            features: spec.enabled.iter().map(|f| f.to_string()).collect(),
+        };
+        resolve.insert(id(spec.name), node);
+    }
+    ProjectMetadata {
+        workspace_root: root.to_path_buf(),
+        members: members.iter().map(|name| id(name)).collect(),
+        packages,
+        resolve: Some(resolve),
+    }
+}
+
+#[test]
+fn test_why_unified_traces_features_and_suggests_gates() {
+    let root = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(root.path().join("cli")).unwrap();
+    std::fs::write(
+        root.path().join("cli/Cargo.toml"),
+        "[package]\nname = \"cli\"\nversion = \"1.0.0\"\n\n\
+         [dependencies]\nregex = { version = \"1\", features = [\"unicode\"] }\n",
+    )
+    .unwrap();
+
+    let metadata = metadata(
+        root.path(),
+        &["app", "cli"],
+        &[
+            Spec {
+                name: "app",
+                features: &[("fancy", &["web/pattern"])],
+                enabled: &["fancy"],
+                deps: &[("web", &[], true)],
+            },
+            Spec {
+                name: "cli",
+                features: &[],
+                enabled: &[],
+                deps: &[("regex", &["unicode"], false)],
+            },
+            Spec {
+                name: "web",
+                features: &[("pattern", &["regex/perf"])],
+                enabled: &["pattern"],
+                deps: &[("regex", &["std"], false)],
+            },
+            Spec {
+                name: "regex",
+                features: &[
+                    ("std", &[]),
+                    ("perf", &["std"]),
+                    ("unicode", &["unicode-case"]),
+                    ("unicode-case", &[]),
+                ],
+                enabled: &["perf", "std", "unicode", "unicode-case"],
+                deps: &[],
+            },
+        ],
+    );
+
+    let regex = &why_unified(&metadata, "regex", None).unwrap()[0];
+    assert_eq!(regex.features, ["perf", "std", "unicode", "unicode-case"]);
+    let perf = &regex.reasons["perf"][0];
+    assert_eq!(perf.by, "web");
+    assert_eq!(perf.how, Activation::Forwarded("pattern".to_string()));
+    assert_eq!(perf.members, ["app"]);
+    let std = &regex.reasons["std"];
+    assert_eq!(std[0].how, Activation::Declared);
+    assert_eq!(std[1].how, Activation::Implied("perf".to_string()));
+    assert_eq!(
+        regex.reasons["unicode-case"][0].how,
+        Activation::Implied("unicode".to_string())
+    );
+
+    assert_eq!(regex.members["app"].unified, ["unicode", "unicode-case"]);
+    assert_eq!(regex.members["cli"].own, ["unicode", "unicode-case"]);
+    assert_eq!(regex.members["cli"].unified, ["perf", "std"]);
+
+    // cli asks for unicode itself, so that is where the gate goes; web is not
+    // a member and gets no suggestion
+    assert_eq!(regex.suggestions.len(), 1);
+    let suggestion = &regex.suggestions[0];
+    assert_eq!(suggestion.member, "cli");
+    assert_eq!(suggestion.features, ["unicode"]);
+    assert!(suggestion.diff.contains("--- a/cli/Cargo.toml"));
+    assert!(suggestion.diff.contains("+regex = { version = \"1\" }"));
+    assert!(
+        suggestion
+            .diff
+            .contains("+regex-unicode = [\"regex/unicode\"]")
+    );
+
+    let report = regex.render();
+    assert!(report.contains("← web v1.0.0 forwards it from its feature pattern (members: app)"));
+    assert!(report.contains("app gets unicode, unicode-case without asking"));
+
+    assert!(why_unified(&metadata, "regex", Some("2.0.0")).is_err());
+}
@@ -0,0 +1,151 @@
+use kargo_cli::events::EventBus;
+use kargo_cli::fleet::{self, DiagnosticAggregator};
+use kargo_cli::plugins::manager::PluginManager;
+use kargo_plugin_api::{
+    BoxFuture, Diagnostic, DiagnosticKind, DiagnosticsSchema, ExecutionContext, PluginCommand,
+    ReadOnlyError, Severity, WriteGuard,
+};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+fn schema() -> DiagnosticsSchema {
+    DiagnosticsSchema {
+        kinds: vec![DiagnosticKind {
+            code: "lint::todo".to_string(),
+            severity: Severity::Warning,
+            description: "Leftover TODO".to_string(),
+        }],
+        output_patterns: vec![r"^(?P<file>[^:]+):(?P<line>\d+): (?P<message>.+)$".to_string()],
+    }
+}
+
+#[test]
+fn test_output_patterns_fill_in_declared_kind() {
+    let parsed = schema().parse_output("src/lib.rs:12: TODO: remove\nunrelated line\n");
+
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(parsed[0].code, "lint::todo");
+    assert_eq!(parsed[0].severity, Severity::Warning);
+    assert_eq!(parsed[0].line, Some(12));
+}
+
+#[test]
+fn test_identical_findings_merge_across_projects() {
+    let mut aggregator = DiagnosticAggregator::new(Some(schema()), Severity::Warning);
+    for project in ["/repos/a", "/repos/b"] {
+        let project = Path::new(project);
+        aggregator.add(
+            project,
+            Diagnostic::new("lint::todo", Severity::Warning, "TODO: remove").at(
+                project.join("src/lib.rs"),
+                Some(12),
+                None,
+            ),
+        );
+        aggregator.add(
+            project,
+            Diagnostic::new("lint::todo", Severity::Note, "below threshold"),
+        );
+    }
+
+    let entries = aggregator.finish();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].projects.len(), 2);
+    assert_eq!(entries[0].description.as_deref(), Some("Leftover TODO"));
+    assert_eq!(
+        entries[0].diagnostic.file.as_deref(),
+        Some(Path::new("src/lib.rs"))
+    );
+}
+
+#[test]
+fn test_deps_report_counts_projects_per_series() {
+    let root = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = root.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = [\"app\"]\n\n[workspace.dependencies]\ntokio = \"1.38\"\n",
+    );
+    write(
+        "ws/app/Cargo.toml",
+        "[package]\nname = \"app\"\n\n[dependencies]\ntokio = { workspace = true }\n",
+    );
+    write(
+        "ws/Cargo.lock",
+        "[[package]]\nname = \"tokio\"\nversion = \"1.45.0\"\n",
+    );
+    write(
+        "legacy/Cargo.toml",
+        "[package]\nname = \"legacy\"\n\n[target.'cfg(unix)'.dev-dependencies]\nrt = { package = \"tokio\", version = \"0.2\" }\n",
+    );
+    write("other/Cargo.toml", "[package]\nname = \"other\"\n");
+
+    let report = kargo_cli::fleet::deps::scan(&[root.path().to_path_buf()], "tokio");
+
+    assert_eq!(report.projects, 3);
+    assert_eq!(report.users.len(), 2);
+    let ws = report
+        .users
+        .iter()
+        .find(|u| u.project.ends_with("ws"))
+        .unwrap();
+    assert_eq!(ws.resolved, ["1.45.0"]);
+    assert_eq!(ws.requirements.len(), 2);
+    let legacy = report
+        .users
+        .iter()
+        .find(|u| u.project.ends_with("legacy"))
+        .unwrap();
+    assert_eq!(legacy.requirements[0].kind, "dev");
+    assert_eq!(legacy.requirements[0].requirement, "0.2");
+    assert_eq!(report.series.get("1.x"), Some(&1));
+    assert_eq!(report.series.get("0.2.x"), Some(&1));
+}
+
+/// Notes that it ran
+struct Probe(Arc<AtomicBool>);
+
+impl PluginCommand for Probe {
+    fn clap(&self) -> clap::Command {
+        clap::Command::new("probe")
+    }
+
+    fn run(&self, _ctx: ExecutionContext) -> BoxFuture {
+        self.0.store(true, Ordering::SeqCst);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[tokio::test]
+async fn test_read_only_fleet_runs_refuse_to_write_the_report() {
+    let root = tempfile::tempdir().unwrap();
+    std::fs::write(root.path().join("Cargo.toml"), "[package]\nname = \"a\"\n").unwrap();
+    let report = root.path().join("report.json");
+    let ran = Arc::new(AtomicBool::new(false));
+    let mut pm = PluginManager::new();
+    pm.register(Box::new(Probe(ran.clone()))).unwrap();
+    let matches = fleet::command().get_matches_from([
+        OsStr::new("fleet"),
+        OsStr::new("--root"),
+        root.path().as_os_str(),
+        OsStr::new("--report"),
+        report.as_os_str(),
+        OsStr::new("probe"),
+    ]);
+
+    WriteGuard::read_only().install();
+    let error = fleet::run(&pm, &matches, &EventBus::new(), None)
+        .await
+        .unwrap_err();
+    WriteGuard::writable().install();
+    assert_eq!(error.downcast_ref::<ReadOnlyError>().unwrap().path, report);
+    assert!(!report.exists());
+    // Refused before the plugin ran in every project for nothing
+    assert!(!ran.load(Ordering::SeqCst));
+}
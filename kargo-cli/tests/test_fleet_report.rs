@@ -0,0 +1,106 @@
+use kargo_cli::fleet::report::{Dashboard, parse_coverage};
+use std::path::Path;
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_dashboard_attributes_findings_and_writes_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(
+        root,
+        "index.yaml",
+        r#"- path: /repos/api
+  name: api
+  version: 1.2.0
+  description: Public <API> server
+  project_type: Binary
+  status: Working
+  dependencies: [serde]
+  tags: [service]
+  is_workspace: false
+  workspace_members: []
+  indicators: {}
+- path: /repos/api/tools
+  name: api-tools
+  version: 0.1.0
+  project_type: Library
+  status: Broken
+  dependencies: []
+  tags: []
+  is_workspace: false
+  workspace_members: []
+  indicators: {}
+"#,
+    );
+    write(
+        root,
+        "status.json",
+        r#"{
+  "outdated": [
+    {"path": "/repos/api/Cargo.toml", "name": "serde", "current": "1.0.100", "latest": "1.0.219"},
+    {"path": "/repos/api/tools/Cargo.toml", "name": "serde", "current": "1.0.9", "latest": "1.0.30"},
+    {"path": "/elsewhere/Cargo.toml", "name": "rand", "current": "0.7.0", "latest": "0.9.0"}
+  ],
+  "vulnerable": [
+    {"path": "/repos/api/Cargo.lock", "name": "time", "version": "0.1.0", "advisory": "RUSTSEC-2020-0071"}
+  ]
+}"#,
+    );
+    write(
+        root,
+        "docs.json",
+        r#"{
+  "plugin": "mddoc",
+  "projects": 2,
+  "failed": [],
+  "owners": {},
+  "diagnostics": [
+    {"code": "mddoc::coverage", "severity": "note", "message": "87.5% of 40 public items documented", "projects": ["/repos/api"]},
+    {"code": "lint::todo", "severity": "warning", "message": "TODO left", "file": "src/lib.rs", "line": 3, "projects": ["/repos/api/tools"]}
+  ]
+}"#,
+    );
+
+    let dashboard = Dashboard::load(
+        &root.join("index.yaml"),
+        &[root.join("status.json")],
+        &[root.join("docs.json")],
+    )
+    .unwrap();
+
+    let api = &dashboard.projects[0];
+    assert_eq!(api.outdated.len(), 1);
+    assert_eq!(api.vulnerable.len(), 1);
+    assert_eq!(api.coverage, Some(87.5));
+    assert!(api.findings.is_empty());
+    // Nested projects claim their own manifests
+    let tools = &dashboard.projects[1];
+    assert_eq!(tools.outdated[0].current, "1.0.9");
+    assert_eq!(tools.findings[0].1.code, "lint::todo");
+    assert_eq!(dashboard.unattributed_outdated[0].name, "rand");
+
+    let out = root.join("out");
+    dashboard.write(&out).unwrap();
+    let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+    assert!(index.contains("<a href=\"projects/api.html\">api</a>"));
+    assert!(index.contains("RUSTSEC-2020-0071"));
+    assert!(index.contains("1.0.219"));
+    let page = std::fs::read_to_string(out.join("projects/api.html")).unwrap();
+    assert!(page.contains("Public &lt;API&gt; server"));
+    assert!(page.contains("87.5%"));
+    assert!(out.join("projects/api-tools.html").exists());
+}
+
+#[test]
+fn test_parse_coverage() {
+    assert_eq!(
+        parse_coverage("87.5% of 40 public items documented"),
+        Some(87.5)
+    );
+    assert_eq!(parse_coverage("no figure"), None);
+}
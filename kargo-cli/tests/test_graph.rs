@@ -0,0 +1,111 @@
+use kargo_cli::graph::DependencyGraph;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn package(root: &Path, dir: &str, manifest: &str) -> PathBuf {
+    let path = root.join(dir).join("Cargo.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, manifest).unwrap();
+    path
+}
+
+fn names<'a>(packages: impl IntoIterator<Item = &'a kargo_cli::graph::Package>) -> Vec<&'a str> {
+    packages.into_iter().map(|p| p.name.as_str()).collect()
+}
+
+#[test]
+fn test_graph_links_local_crates_across_projects() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let manifests = vec![
+        package(
+            root,
+            "app",
+            r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+core = { path = "../lib/core" }
+serde = "1"
+
+[dev-dependencies]
+testkit = { path = "../testkit" }
+"#,
+        ),
+        package(
+            root,
+            "lib/core",
+            r#"[package]
+name = "core"
+version = "0.2.0"
+
+[target.'cfg(unix)'.dependencies]
+util = "0.3"
+
+[build-dependencies]
+cc = "1"
+"#,
+        ),
+        package(
+            root,
+            "util",
+            "[package]\nname = \"util\"\nversion = \"0.3.0\"\n",
+        ),
+        // Depends back on app, which only needs it for tests
+        package(
+            root,
+            "testkit",
+            "[package]\nname = \"testkit\"\n\n[dependencies]\napp = { path = \"../app\" }\n",
+        ),
+        package(root, "ws", "[workspace]\nmembers = []\n"),
+        package(
+            root,
+            "app/target/package/app-0.1.0",
+            "[package]\nname = \"app\"\n",
+        ),
+    ];
+
+    let graph = DependencyGraph::build(&manifests);
+    let local: Vec<&str> = names(graph.packages.iter().filter(|p| p.is_local()));
+    assert_eq!(local, ["app", "core", "testkit", "util"]);
+    assert_eq!(
+        names(graph.reverse_deps("util")),
+        ["app", "core", "testkit"]
+    );
+    // testkit reaches serde through app
+    assert_eq!(names(graph.reverse_deps("serde")), ["app", "testkit"]);
+    assert_eq!(
+        names(graph.topo_order().unwrap()),
+        ["util", "core", "app", "testkit"]
+    );
+
+    let text = graph.render(false);
+    assert!(text.contains("  -> util 0.3\n"));
+    assert!(!text.contains("serde"));
+    let dot = graph.to_dot(true);
+    assert!(dot.contains("[label=\"serde\", style=dashed]"));
+    assert!(dot.contains("[style=dotted]"));
+    let mermaid = graph.to_mermaid(false);
+    assert!(mermaid.starts_with("flowchart LR\n"));
+    assert!(mermaid.contains("-.->|dev|"));
+}
+
+#[test]
+fn test_topo_order_reports_cycles() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = vec![
+        package(
+            dir.path(),
+            "a",
+            "[package]\nname = \"a\"\n\n[dependencies]\nb = { path = \"../b\" }\n",
+        ),
+        package(
+            dir.path(),
+            "b",
+            "[package]\nname = \"b\"\n\n[build-dependencies]\na = { path = \"../a\" }\n",
+        ),
+    ];
+    let error = DependencyGraph::build(&manifests).topo_order().unwrap_err();
+    assert_eq!(error.to_string(), "Dependency cycle between a, b");
+}
@@ -0,0 +1,22 @@
+use kargo_cli::cli::build_root_cli;
+use kargo_cli::plugins::manager::PluginManager;
+
+#[test]
+fn test_root_help_groups_commands_by_category() {
+    let help = build_root_cli(&PluginManager::new())
+        .render_help()
+        .to_string();
+
+    let position = |text: &str| {
+        help.find(text)
+            .unwrap_or_else(|| panic!("{text:?} missing from:\n{help}"))
+    };
+    assert!(position("Dependencies:") < position("  manifest"));
+    assert!(position("  manifest") < position("Fleet:"));
+    assert!(position("Fleet:") < position("  schedule"));
+    assert!(position("  schedule") < position("Commands:"));
+    assert!(position("Commands:") < position("  explain"));
+    assert!(position("  help") < position("Options:"));
+    assert!(position("Options:") < position("--log-format"));
+    assert!(help.contains("Exit codes:"));
+}
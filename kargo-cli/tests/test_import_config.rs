@@ -0,0 +1,111 @@
+use kargo_cli::import_config::{Source, UpdatePolicy, import, schedule_interval};
+use std::path::{Path, PathBuf};
+
+const DEPENDABOT: &str = r#"
+version: 2
+updates:
+  - package-ecosystem: cargo
+    directory: /
+    schedule:
+      interval: weekly
+      day: monday
+    labels: [deps]
+    groups:
+      everything:
+        patterns: ["*"]
+    ignore:
+      - dependency-name: "*"
+        update-types: ["version-update:semver-major"]
+      - dependency-name: openssl
+      - dependency-name: "aws-*"
+        update-types: ["version-update:semver-major", "version-update:semver-minor"]
+      - dependency-name: tokio
+        versions: ["1.40.x"]
+  - package-ecosystem: cargo
+    directory: /tools/xtask
+    schedule:
+      interval: monthly
+    groups:
+      serde:
+        patterns: ["serde*"]
+  - package-ecosystem: github-actions
+    directory: /
+    schedule:
+      interval: daily
+"#;
+
+const RENOVATE: &str = r#"{
+  "$schema": "https://docs.renovatebot.com/renovate-schema.json",
+  "extends": ["config:recommended", "schedule:weekly"],
+  "schedule": ["before 4am on monday"],
+  "ignoreDeps": ["openssl"],
+  "major": { "enabled": false },
+  "automerge": true,
+  "packageRules": [
+    { "matchManagers": ["cargo"], "matchDepTypes": ["dev-dependencies"], "enabled": false },
+    { "matchPackagePrefixes": ["tokio"], "matchUpdateTypes": ["minor", "major"], "enabled": false },
+    { "matchPackagePatterns": ["^serde"], "groupName": "serde" },
+    { "matchManagers": ["npm"], "enabled": false },
+    { "groupName": "all dependencies" }
+  ]
+}"#;
+
+#[test]
+fn test_imports_dependabot_ignores_schedules_and_groups() {
+    let root = Path::new("/repo");
+    let import = import(Source::Dependabot, DEPENDABOT, root).unwrap();
+
+    let policy = &import.policies[&PathBuf::new()];
+    assert_eq!(policy.normal, Some(UpdatePolicy::Compatible));
+    assert_eq!(policy.build, Some(UpdatePolicy::Compatible));
+    assert_eq!(policy.crates["openssl"], UpdatePolicy::Pinned);
+    assert_eq!(policy.crates["aws-*"], UpdatePolicy::Patch);
+    assert!(!policy.crates.contains_key("tokio"));
+    assert!(!import.policies.contains_key(Path::new("tools/xtask")));
+
+    assert_eq!(import.jobs.len(), 2);
+    assert_eq!(import.jobs[0].name, "dependabot-root");
+    assert_eq!(import.jobs[0].every.to_string(), "1w");
+    assert_eq!(import.jobs[0].command, "upgrade /repo --git-commit session");
+    assert_eq!(import.jobs[1].name, "dependabot-tools-xtask");
+    assert_eq!(import.jobs[1].every.to_string(), "30d");
+    assert_eq!(
+        import.jobs[1].command,
+        "upgrade /repo/tools/xtask --git-commit crate"
+    );
+
+    let notes = import.notes.join("\n");
+    assert!(notes.contains("ignored versions 1.40.x of tokio"));
+    assert!(notes.contains("dependabot /: settings labels"));
+    assert!(notes.contains("dependabot /tools/xtask: group serde"));
+}
+
+#[test]
+fn test_imports_renovate_rules_and_schedule() {
+    let import = import(Source::Renovate, RENOVATE, Path::new("/repo")).unwrap();
+
+    let policy = &import.policies[&PathBuf::new()];
+    assert_eq!(policy.normal, Some(UpdatePolicy::Compatible));
+    assert_eq!(policy.dev, Some(UpdatePolicy::Pinned));
+    assert_eq!(policy.crates["openssl"], UpdatePolicy::Pinned);
+    assert_eq!(policy.crates["tokio*"], UpdatePolicy::Patch);
+
+    assert_eq!(import.jobs.len(), 1);
+    assert_eq!(import.jobs[0].every.to_string(), "1w");
+    assert_eq!(import.jobs[0].command, "upgrade /repo --git-commit session");
+
+    let notes = import.notes.join("\n");
+    assert!(notes.contains("presets config:recommended"));
+    assert!(notes.contains("renovate: settings automerge"));
+    assert!(notes.contains("package pattern ^serde"));
+    assert!(notes.contains("group serde"));
+
+    let every = |entries: &[&str]| {
+        let entries: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        schedule_interval(&entries).to_string()
+    };
+    assert_eq!(every(&["after 10pm every weekday"]), "1d");
+    assert_eq!(every(&["on the first day of the month"]), "30d");
+    assert_eq!(every(&["* 0-3 * * 1"]), "1w");
+    assert_eq!(every(&["every weekend", "at any time"]), "1d");
+}
@@ -0,0 +1,34 @@
+use kargo_cli::plugins::instance_pool::InstancePool;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[tokio::test]
+async fn test_instances_are_reused_and_bounded() {
+    let created = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&created);
+    let pool = InstancePool::new(2, move || Ok(counter.fetch_add(1, Ordering::SeqCst)));
+    pool.warm(1).unwrap();
+    assert_eq!(pool.idle(), 1);
+
+    // Calls one after another keep using the warm instance
+    for _ in 0..3 {
+        assert_eq!(*pool.checkout().await.unwrap(), 0);
+    }
+    assert_eq!(created.load(Ordering::SeqCst), 1);
+
+    let first = pool.checkout().await.unwrap();
+    let second = pool.checkout().await.unwrap();
+    assert_eq!((*first, *second), (0, 1));
+    assert!(pool.try_checkout().unwrap().is_none());
+
+    // A discarded instance is replaced on demand; a dropped one is reused
+    second.discard();
+    drop(first);
+    assert_eq!(pool.idle(), 1);
+    let (a, b) = (
+        pool.checkout().await.unwrap(),
+        pool.checkout().await.unwrap(),
+    );
+    assert_eq!((*a, *b), (0, 2));
+    assert_eq!(created.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,31 @@
+use kargo_cli::logging::{LogFormat, plugin_target};
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn log_format_is_read_before_clap_runs() {
+    assert_eq!(
+        LogFormat::from_args(args(&["sap", "--help"])),
+        LogFormat::Text
+    );
+    assert_eq!(
+        LogFormat::from_args(args(&["--log-format", "json", "sap"])),
+        LogFormat::Json
+    );
+    assert_eq!(
+        LogFormat::from_args(args(&["--ci", "--log-format=JSON", "sap"])),
+        LogFormat::Json
+    );
+    // Unknown values fall back to text; clap reports the error later
+    assert_eq!(
+        LogFormat::from_args(args(&["--log-format=yaml"])),
+        LogFormat::Text
+    );
+}
+
+#[test]
+fn plugin_targets_are_namespaced() {
+    assert_eq!(plugin_target("sap"), "kargo::plugin::sap");
+}
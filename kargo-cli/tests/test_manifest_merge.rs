@@ -0,0 +1,67 @@
+use kargo_cli::manifest::merge;
+
+const BASE: &str = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+serde = "1.0.100"
+tokio = "1.30"
+log = "0.4"
+"#;
+
+#[test]
+fn test_dependency_changes_on_both_sides_merge() {
+    let ours = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+# Keep in sync with the server
+serde = "1.0.200"
+tokio = "1.30"
+log = "0.4"
+anyhow = "1"
+"#;
+    let theirs = r#"[package]
+name = "app"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+serde = { version = "1.0.210", features = ["derive"] }
+log = "0.4"
+regex = "1.11"
+"#;
+
+    let outcome = merge(BASE, ours, theirs).unwrap();
+
+    assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+    let merged = outcome.merged;
+    assert!(merged.contains("# Keep in sync with the server"));
+    assert!(merged.contains(r#"serde = { version = "1.0.210", features = ["derive"] }"#));
+    assert!(merged.contains(r#"anyhow = "1""#));
+    assert!(merged.contains(r#"regex = "1.11""#));
+    assert!(merged.contains(r#"edition = "2024""#));
+    assert!(!merged.contains("tokio"));
+}
+
+#[test]
+fn test_diverging_changes_are_reported_as_conflicts() {
+    let ours = BASE
+        .replace("version = \"0.1.0\"", "version = \"0.2.0\"")
+        .replace(
+            "log = \"0.4\"",
+            "log = { version = \"0.4\", optional = true }",
+        );
+    let theirs = BASE
+        .replace("version = \"0.1.0\"", "version = \"0.1.1\"")
+        .replace(
+            "log = \"0.4\"",
+            "log = { version = \"0.4.22\", features = [\"std\"] }",
+        );
+
+    let outcome = merge(BASE, &ours, &theirs).unwrap();
+
+    assert_eq!(outcome.conflicts, ["package.version", "dependencies.log"]);
+}
@@ -0,0 +1,135 @@
+use kargo_cli::metadata::{DepKind, DependencySource, MetadataMode, ProjectMetadata};
+use kargo_cli::{ProjectAnalyzer, ProjectType};
+use std::path::Path;
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn workspace(root: &Path) {
+    write(
+        root,
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    );
+    write(
+        root,
+        "crates/app/Cargo.toml",
+        r#"[package]
+name = "app"
+version = "0.1.0"
+edition = "2021"
+
+[[bin]]
+name = "app"
+path = "src/entry.rs"
+
+[dependencies]
+json = { package = "serde_json", version = "1" }
+util = { path = "../util", version = "0.1" }
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#,
+    );
+    write(root, "crates/app/src/entry.rs", "fn main() {}\n");
+    write(
+        root,
+        "crates/util/Cargo.toml",
+        "[package]\nname = \"util\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    );
+    write(root, "crates/util/src/lib.rs", "");
+}
+
+#[test]
+fn test_declared_dependencies_follow_cargo() {
+    let dir = tempfile::tempdir().unwrap();
+    workspace(dir.path());
+
+    let metadata =
+        ProjectMetadata::load(&dir.path().join("Cargo.toml"), MetadataMode::Declared).unwrap();
+    let app = metadata
+        .member_at(&dir.path().join("crates/app/Cargo.toml"))
+        .unwrap();
+
+    let json = app.dependencies.iter().find(|d| d.key() == "json").unwrap();
+    assert_eq!(json.package, "serde_json");
+    assert!(json.is_registry());
+    let util = app.dependencies.iter().find(|d| d.key() == "util").unwrap();
+    assert!(matches!(util.source, DependencySource::Path(_)));
+    let libc = app.dependencies.iter().find(|d| d.key() == "libc").unwrap();
+    assert_eq!(libc.kind, DepKind::Normal);
+    assert_eq!(libc.target.as_deref(), Some("cfg(unix)"));
+}
+
+#[tokio::test]
+async fn test_analyzer_uses_cargo_targets_and_member_globs() {
+    let dir = tempfile::tempdir().unwrap();
+    workspace(dir.path());
+    let analyzer = ProjectAnalyzer::new();
+
+    let ProjectType::Workspace(ws) = analyzer.analyze(dir.path()).await.unwrap() else {
+        panic!("expected a workspace");
+    };
+    assert_eq!(ws.members.len(), 2);
+
+    let ProjectType::WorkspaceMember(member) = analyzer
+        .analyze(dir.path().join("crates/app"))
+        .await
+        .unwrap()
+    else {
+        panic!("expected a workspace member");
+    };
+    let ProjectType::Binary(bin) = *member.project_type else {
+        panic!("expected a binary");
+    };
+    assert!(bin.bin_path.unwrap().ends_with("src/entry.rs"));
+}
+
+#[tokio::test]
+async fn test_analyzer_reads_target_tables_without_cargo() {
+    let dir = tempfile::tempdir().unwrap();
+    // An edition this cargo doesn't know, so the manifest heuristics apply
+    write(
+        dir.path(),
+        "tool/Cargo.toml",
+        r#"[package]
+name = "tool"
+version = "0.1.0"
+edition = "2099"
+
+[lib]
+path = "core/mod.rs"
+
+[[bin]]
+name = "helper"
+"#,
+    );
+    write(dir.path(), "tool/core/mod.rs", "");
+    write(dir.path(), "tool/src/bin/helper/main.rs", "fn main() {}\n");
+    write(dir.path(), "tool/examples/demo.rs", "fn main() {}\n");
+
+    let analyzer = ProjectAnalyzer::new();
+    let ProjectType::Hybrid(hybrid) = analyzer.analyze(dir.path().join("tool")).await.unwrap()
+    else {
+        panic!("expected a hybrid crate");
+    };
+    assert!(hybrid.lib_path.unwrap().ends_with("core/mod.rs"));
+    assert!(hybrid.bin_path.unwrap().ends_with("src/bin/helper/main.rs"));
+
+    // Examples are no binaries, and `autobins = false` hides src/main.rs
+    write(
+        dir.path(),
+        "lib/Cargo.toml",
+        "[package]\nname = \"lib\"\nversion = \"0.1.0\"\nedition = \"2099\"\nautobins = false\n",
+    );
+    write(dir.path(), "lib/src/lib.rs", "");
+    write(dir.path(), "lib/src/main.rs", "fn main() {}\n");
+    write(dir.path(), "lib/examples/demo.rs", "fn main() {}\n");
+    let ProjectType::Library(lib) = analyzer.analyze(dir.path().join("lib")).await.unwrap() else {
+        panic!("expected a library");
+    };
+    assert!(lib.lib_path.unwrap().ends_with("src/lib.rs"));
+}
@@ -0,0 +1,48 @@
+use kargo_cli::metrics::{Metrics, Phase, PluginSample, over_budget, summarize};
+use std::time::Duration;
+
+fn sample(plugin: &str, phase: Phase, ms: u64, success: bool) -> PluginSample {
+    PluginSample::new(plugin, phase, Duration::from_millis(ms), success)
+}
+
+#[test]
+fn test_samples_round_trip_and_summarize() {
+    let dir = tempfile::tempdir().unwrap();
+    let metrics = Metrics::new(dir.path());
+    let mut samples: Vec<PluginSample> = (1..=20)
+        .map(|i| sample("upgrade", Phase::Run, i * 100, i % 5 != 0))
+        .collect();
+    samples[0].memory_kb = Some(2048);
+    samples.push(sample("upgrade", Phase::Load, 300, true));
+    samples.push(sample("sap", Phase::Run, 50, false));
+    metrics.record(&samples).unwrap();
+    assert_eq!(metrics.samples().unwrap(), samples);
+
+    let stats = summarize(&metrics.samples().unwrap());
+    let (sap, upgrade) = (&stats[0], &stats[1]);
+    assert_eq!(upgrade.runs, 20);
+    assert_eq!(upgrade.failures, 4);
+    assert_eq!(upgrade.mean_run_ms, 1050);
+    assert_eq!(upgrade.p95_run_ms, 1900);
+    assert_eq!(upgrade.peak_memory_kb, Some(2048));
+    assert_eq!((upgrade.loads, upgrade.mean_load_ms), (1, 300));
+    assert!(upgrade.is_slow(1500) && !upgrade.is_slow(2000));
+    assert!(upgrade.is_flaky(0.2) && !upgrade.is_flaky(0.25));
+    // Always failing is broken, not flaky
+    assert!(!sap.is_flaky(0.1));
+}
+
+#[test]
+fn test_only_consecutive_slow_loads_exceed_the_budget() {
+    let loads = |plugin: &str, durations: &[u64]| -> Vec<PluginSample> {
+        durations
+            .iter()
+            .map(|&ms| sample(plugin, Phase::Load, ms, true))
+            .collect()
+    };
+    let mut samples = loads("mddoc", &[100, 1500, 1200, 1800]);
+    samples.extend(loads("sap", &[1500, 1500, 200, 1500]));
+    samples.extend(loads("upgrade", &[1500, 1500]));
+
+    assert_eq!(over_budget(&samples, 1000), [("mddoc".to_string(), 1800)]);
+}
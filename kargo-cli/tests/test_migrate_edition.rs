@@ -0,0 +1,223 @@
+use anyhow::Result;
+use kargo_cli::migrate_edition::{self, EditionField, MigrationStatus};
+use kargo_plugin_api::cargo_runner::{CargoCommand, CargoOutput, CargoRunner};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_finds_every_edition_behind_the_target() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(
+        root,
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nedition = \"2021\"\n",
+    );
+    write(
+        root,
+        "crates/core/Cargo.toml",
+        "[package]\nname = \"core\"\nedition.workspace = true\n",
+    );
+    write(
+        root,
+        "crates/legacy/Cargo.toml",
+        "[package]\nname = \"legacy\"\nversion = \"0.1.0\"\n",
+    );
+    write(
+        root,
+        "crates/modern/Cargo.toml",
+        "[package]\nname = \"modern\"\nedition = \"2024\"\n",
+    );
+    // Build output isn't part of the project
+    write(
+        root,
+        "target/package/stale/Cargo.toml",
+        "[package]\nname = \"stale\"\n",
+    );
+
+    let mut fields = migrate_edition::behind(root, "2024").unwrap();
+    fields.sort_by(|a, b| a.manifest.cmp(&b.manifest));
+    let found: Vec<(Option<&str>, &str, bool)> = fields
+        .iter()
+        .map(|f| (f.package.as_deref(), f.edition.as_str(), f.inherited))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (None, "2021", false),
+            (Some("core"), "2021", true),
+            (Some("legacy"), "2015", false),
+        ]
+    );
+    assert_eq!(migrate_edition::behind(root, "2021").unwrap().len(), 1);
+}
+
+#[test]
+fn test_set_edition_writes_only_declared_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    fs::write(
+        &manifest,
+        "[package]\nname = \"legacy\"\nversion = \"0.1.0\" # keep\n\n[dependencies]\n",
+    )
+    .unwrap();
+
+    let field = EditionField {
+        manifest: manifest.clone(),
+        package: Some("legacy".to_string()),
+        edition: "2015".to_string(),
+        inherited: false,
+    };
+    migrate_edition::set_edition(&field, "2018").unwrap();
+    let content = fs::read_to_string(&manifest).unwrap();
+    assert!(content.starts_with(
+        "[package]\nname = \"legacy\"\nversion = \"0.1.0\" # keep\nedition = \"2018\"\n"
+    ));
+
+    // Inheriting packages move with the workspace
+    let inherited = EditionField {
+        inherited: true,
+        ..field
+    };
+    migrate_edition::set_edition(&inherited, "2021").unwrap();
+    assert_eq!(fs::read_to_string(&manifest).unwrap(), content);
+}
+
+/// Records the commands it gets; those starting with the first part of
+/// `fail` exit with 101 and print the second part
+#[derive(Default)]
+struct FakeCargo {
+    commands: Mutex<Vec<String>>,
+    fail: Option<(&'static str, &'static str)>,
+}
+
+impl CargoRunner for FakeCargo {
+    fn run(&self, command: &CargoCommand) -> Result<CargoOutput> {
+        let line = command.args.join(" ");
+        self.commands.lock().unwrap().push(line.clone());
+        let failed = self.fail.filter(|(prefix, _)| line.starts_with(prefix));
+        Ok(CargoOutput {
+            status: Some(exit_status(if failed.is_some() { 101 } else { 0 })),
+            stderr: failed
+                .map(|(_, stderr)| stderr.to_string())
+                .unwrap_or_default(),
+            ..CargoOutput::default()
+        })
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> std::process::ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    std::process::ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> std::process::ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    std::process::ExitStatus::from_raw(code as u32)
+}
+
+/// A 2015 package, as the fields `behind` finds for it
+fn legacy_project(root: &Path) -> Vec<EditionField> {
+    write(
+        root,
+        "Cargo.toml",
+        "[package]\nname = \"legacy\"\nversion = \"0.1.0\"\n",
+    );
+    write(root, "src/lib.rs", "");
+    migrate_edition::behind(root, "2021").unwrap()
+}
+
+#[tokio::test]
+async fn test_migrates_one_edition_at_a_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let behind = legacy_project(dir.path());
+    let fake = Arc::new(FakeCargo::default());
+    let runner: Arc<dyn CargoRunner> = fake.clone();
+
+    let migration =
+        migrate_edition::migrate(dir.path().to_path_buf(), behind, "2021", &runner).await;
+    assert_eq!(migration.status, MigrationStatus::Migrated);
+    assert_eq!(migration.from, ["2015"]);
+    assert!(migration.details.is_empty());
+    // What cargo printed is kept for every run
+    assert_eq!(migration.outputs.len(), 3);
+
+    let manifest = dir.path().join("Cargo.toml");
+    let manifest_arg = format!("--manifest-path {}", manifest.display());
+    let fix = format!(
+        "fix --edition --allow-dirty --allow-staged --all-targets -p legacy {}",
+        manifest_arg
+    );
+    assert_eq!(
+        *fake.commands.lock().unwrap(),
+        [
+            fix.clone(),
+            fix,
+            format!("check --all-targets {}", manifest_arg),
+        ]
+    );
+    assert!(
+        fs::read_to_string(&manifest)
+            .unwrap()
+            .contains("edition = \"2021\"")
+    );
+}
+
+#[tokio::test]
+async fn test_failing_cargo_leaves_the_project_for_manual_work() {
+    let dir = tempfile::tempdir().unwrap();
+    let behind = legacy_project(dir.path());
+    let fake = Arc::new(FakeCargo {
+        fail: Some((
+            "check",
+            "    Checking legacy v0.1.0\n\
+             error[E0412]: cannot find type `dyn` in this scope\n\
+             \x20--> src/lib.rs:1:12\n\
+             \x20 |\n\
+             error: could not compile `legacy` (lib) due to 1 previous error\n",
+        )),
+        ..FakeCargo::default()
+    });
+    let runner: Arc<dyn CargoRunner> = fake.clone();
+
+    let migration =
+        migrate_edition::migrate(dir.path().to_path_buf(), behind, "2021", &runner).await;
+    assert_eq!(migration.status, MigrationStatus::NeedsWork);
+    assert_eq!(
+        migration.details,
+        [
+            "error[E0412]: cannot find type `dyn` in this scope",
+            "--> src/lib.rs:1:12",
+            "error: could not compile `legacy` (lib) due to 1 previous error",
+        ]
+    );
+
+    // A failed fix stops the migration before the edition is bumped
+    let dir = tempfile::tempdir().unwrap();
+    let behind = legacy_project(dir.path());
+    let fake = Arc::new(FakeCargo {
+        fail: Some(("fix", "")),
+        ..FakeCargo::default()
+    });
+    let runner: Arc<dyn CargoRunner> = fake.clone();
+
+    let migration =
+        migrate_edition::migrate(dir.path().to_path_buf(), behind, "2021", &runner).await;
+    assert_eq!(migration.status, MigrationStatus::NeedsWork);
+    assert_eq!(migration.details, ["cargo fix failed (exit status: 101)"]);
+    assert_eq!(fake.commands.lock().unwrap().len(), 1);
+    assert!(
+        !fs::read_to_string(dir.path().join("Cargo.toml"))
+            .unwrap()
+            .contains("edition")
+    );
+}
@@ -0,0 +1,25 @@
+use kargo_cli::plugins::module_cache::ModuleCache;
+use std::fs;
+
+#[test]
+fn test_cache_config_points_wasmtime_at_the_cache_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ModuleCache::new(dir.path().join("wasm"));
+    let config = cache.config_file().unwrap();
+    let modules = dir.path().join("wasm").join("modules");
+    assert_eq!(
+        fs::read_to_string(&config).unwrap(),
+        format!(
+            "[cache]\nenabled = true\ndirectory = {}\n",
+            toml_edit::value(modules.display().to_string())
+        )
+    );
+
+    fs::create_dir_all(modules.join("ab")).unwrap();
+    fs::write(modules.join("ab").join("module"), [0u8; 100]).unwrap();
+    assert_eq!(cache.size(), 100);
+    cache.clear().unwrap();
+    assert_eq!(cache.size(), 0);
+    // The config stays for the next compile
+    assert!(config.is_file());
+}
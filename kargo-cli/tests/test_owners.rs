@@ -0,0 +1,72 @@
+use kargo_plugin_api::owners::{self, CodeOwners, OwnerSource};
+use std::fs;
+
+#[test]
+fn test_last_matching_codeowners_rule_wins() {
+    let codeowners = CodeOwners::parse(
+        "# Default owners\n\
+         *                @org/platform-team\n\
+         /crates/billing/ @org/payments @alice # money\n\
+         docs/**/*.md     @org/docs\n\
+         /crates/billing/vendor/\n",
+    );
+    assert_eq!(
+        codeowners.owners("crates/walk/Cargo.toml").unwrap(),
+        ["@org/platform-team"]
+    );
+    assert_eq!(
+        codeowners.owners("crates/billing/Cargo.toml").unwrap(),
+        ["@org/payments", "@alice"]
+    );
+    // Anchored patterns only match from the root
+    assert_eq!(
+        codeowners
+            .owners("vendor/crates/billing/Cargo.toml")
+            .unwrap(),
+        ["@org/platform-team"]
+    );
+    assert_eq!(
+        codeowners.owners("docs/guide/intro.md").unwrap(),
+        ["@org/docs"]
+    );
+    assert!(
+        codeowners
+            .owners("crates/billing/vendor/Cargo.toml")
+            .unwrap()
+            .is_empty()
+    );
+    assert!(CodeOwners::parse("").owners("Cargo.toml").is_none());
+}
+
+#[test]
+fn test_project_owners_falls_back_to_authors() {
+    let repo = tempfile::tempdir().unwrap();
+    fs::create_dir_all(repo.path().join(".git")).unwrap();
+    fs::create_dir_all(repo.path().join(".github")).unwrap();
+    fs::write(
+        repo.path().join(".github/CODEOWNERS"),
+        "/services/ @Platform-Team\n",
+    )
+    .unwrap();
+    for (dir, authors) in [
+        ("services/api", "[]"),
+        ("tools/cli", r#"["Ada <ada@example.com>"]"#),
+    ] {
+        fs::create_dir_all(repo.path().join(dir)).unwrap();
+        fs::write(
+            repo.path().join(dir).join("Cargo.toml"),
+            format!("[package]\nname = \"x\"\nauthors = {authors}\n"),
+        )
+        .unwrap();
+    }
+
+    let api = owners::project_owners(&repo.path().join("services/api")).unwrap();
+    assert_eq!(api.source, OwnerSource::Codeowners);
+    assert!(api.includes("@platform-team"));
+
+    let cli = owners::project_owners(&repo.path().join("tools/cli")).unwrap();
+    assert_eq!(cli.source, OwnerSource::Authors);
+    assert!(cli.includes("Ada"));
+    assert!(cli.includes("ada@example.com"));
+    assert!(!cli.includes("@platform-team"));
+}
@@ -0,0 +1,79 @@
+use clap::{Arg, ArgAction, Command};
+use kargo_cli::palette::{Entry, arguments, filter, fuzzy_score, split_words};
+
+fn entry(name: &str, about: &str) -> Entry {
+    Entry {
+        name: name.to_string(),
+        about: about.to_string(),
+    }
+}
+
+#[test]
+fn test_filter_ranks_name_matches_first() {
+    let entries = [
+        entry("fleet", "Run a plugin in every project"),
+        entry("upgrade", "Upgrade dependencies in every Cargo.toml"),
+        entry("sap", "Directory listing for agents"),
+    ];
+
+    let names = |query| -> Vec<&str> {
+        filter(&entries, query)
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect()
+    };
+    assert_eq!(names(""), ["fleet", "upgrade", "sap"]);
+    assert_eq!(names("upg"), ["upgrade"]);
+    // Names match fuzzily, descriptions literally
+    assert_eq!(names("every"), ["fleet", "upgrade"]);
+    assert_eq!(names("sp"), ["sap"]);
+    assert!(fuzzy_score("ugd", "upgrade").is_some());
+    assert!(fuzzy_score("xyz", "upgrade").is_none());
+    assert!(fuzzy_score("up", "upgrade") > fuzzy_score("ud", "upgrade"));
+}
+
+#[test]
+fn test_arguments_are_prompted_from_the_clap_definition() {
+    let command = Command::new("upgrade")
+        .arg(Arg::new("path").index(1).required(true))
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("policy")
+                .long("policy")
+                .value_name("FILE")
+                .action(ArgAction::Append),
+        )
+        .arg(Arg::new("rules").long("rules").value_name("FILE"));
+
+    let mut answers = ["yes", "a.toml 'b c.toml'", "", "", "-crate"].into_iter();
+    let mut prompts = Vec::new();
+    let words = arguments(&command, &mut |prompt| {
+        prompts.push(prompt.to_string());
+        Ok(answers.next().unwrap().to_string())
+    })
+    .unwrap();
+
+    assert_eq!(
+        words,
+        [
+            "--dry-run",
+            "--policy",
+            "a.toml",
+            "--policy",
+            "b c.toml",
+            "--",
+            "-crate"
+        ]
+    );
+    // The required positional is asked again after an empty answer
+    assert_eq!(prompts.len(), 5);
+    assert!(prompts[3].starts_with("<PATH> (required)"));
+    assert_eq!(
+        split_words(r#"build --features "a b""#),
+        ["build", "--features", "a b"]
+    );
+}
@@ -0,0 +1,58 @@
+use kargo_cli::cache::PluginCache;
+use kargo_plugin_api::{CacheSpec, Diagnostic, DiagnosticSink, ExecutionContext, Severity};
+use std::path::PathBuf;
+
+fn context(dir: PathBuf) -> ExecutionContext {
+    ExecutionContext {
+        matched_args: vec!["mddoc".to_string(), "tokio@=1.45.0".to_string()],
+        args: Default::default(),
+        current_dir: dir.clone(),
+        config_dir: dir.clone(),
+        data_dir: dir.clone(),
+        cache_dir: dir,
+        diagnostics: DiagnosticSink::default(),
+        registry: None,
+        target_dir: None,
+        host_version: "0.1.0".to_string(),
+        write_guard: Default::default(),
+        events: Default::default(),
+        network: Default::default(),
+        profiler: Default::default(),
+        settings: Default::default(),
+    }
+}
+
+#[test]
+fn test_cached_result_is_reused_until_inputs_change() {
+    let project = tempfile::tempdir().unwrap();
+    let cache = PluginCache::new(project.path().join("cache"));
+    std::fs::create_dir_all(project.path().join("templates/.git")).unwrap();
+    std::fs::write(project.path().join("templates/footer.hbs"), "v1").unwrap();
+    std::fs::create_dir(project.path().join("docs")).unwrap();
+    let spec = CacheSpec {
+        version: "0.1.0".to_string(),
+        inputs: vec![PathBuf::from("templates")],
+        outputs: vec![PathBuf::from("docs")],
+    };
+    let ctx = context(project.path().to_path_buf());
+
+    let entry = cache.entry("mddoc", &spec, &ctx).unwrap();
+    assert!(entry.load().is_none());
+    let diagnostic = Diagnostic::new("mddoc::broken-link", Severity::Warning, "dangling");
+    entry.store(std::slice::from_ref(&diagnostic)).unwrap();
+    assert_eq!(entry.load(), Some(vec![diagnostic]));
+
+    // Hidden entries don't count; content changes and missing outputs do
+    std::fs::write(project.path().join("templates/.git/HEAD"), "x").unwrap();
+    assert_eq!(
+        cache.entry("mddoc", &spec, &ctx).unwrap().path(),
+        entry.path()
+    );
+    std::fs::remove_dir(project.path().join("docs")).unwrap();
+    assert!(entry.load().is_none());
+    std::fs::write(project.path().join("templates/footer.hbs"), "v2").unwrap();
+    assert_ne!(
+        cache.entry("mddoc", &spec, &ctx).unwrap().path(),
+        entry.path()
+    );
+}
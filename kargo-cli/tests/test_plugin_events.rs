@@ -0,0 +1,98 @@
+use kargo_cli::events::{EventBus, EventKind, Source};
+use kargo_plugin_api::HostEvent;
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn test_plugins_receive_host_events_until_the_session_ends() {
+    let bus = EventBus::new();
+    let events = bus.plugin_events();
+    bus.publish(
+        Source::Updater,
+        EventKind::FileModified {
+            path: PathBuf::from("a/Cargo.toml"),
+        },
+    );
+    let mut subscription = events.subscribe();
+
+    let waiting = tokio::spawn(async move {
+        let mut received = Vec::new();
+        while let Some(event) = subscription.recv().await {
+            received.push(event);
+        }
+        received
+    });
+    // Not forwarded to plugins
+    bus.publish(
+        Source::Updater,
+        EventKind::Info {
+            message: "scanning".to_string(),
+        },
+    );
+    bus.publish(
+        Source::Updater,
+        EventKind::DependencyUpdated {
+            path: PathBuf::from("b/Cargo.toml"),
+            name: "serde".to_string(),
+            from: "1.0".to_string(),
+            to: "1.1".to_string(),
+        },
+    );
+    // What a plugin publishes reaches the others
+    events.publish(HostEvent::FileModified {
+        path: PathBuf::from("b/README.md"),
+    });
+    events.close();
+
+    assert_eq!(
+        waiting.await.unwrap(),
+        [
+            HostEvent::DependencyUpdated {
+                manifest: PathBuf::from("b/Cargo.toml"),
+                name: "serde".to_string(),
+                from: "1.0".to_string(),
+                to: "1.1".to_string(),
+            },
+            HostEvent::FileModified {
+                path: PathBuf::from("b/README.md"),
+            },
+        ]
+    );
+    assert_eq!(events.history().len(), 3);
+    // Closed streams take no more events
+    bus.publish(
+        Source::Updater,
+        EventKind::FileModified {
+            path: PathBuf::from("c/Cargo.toml"),
+        },
+    );
+    assert_eq!(events.history().len(), 3);
+}
+
+#[tokio::test]
+async fn test_dependency_findings_of_plugins_reach_the_bus_once() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let events = bus.plugin_events();
+    events.publish(HostEvent::FileModified {
+        path: PathBuf::from("a/Cargo.toml"),
+    });
+    events.publish(HostEvent::DependencyOutdated {
+        manifest: PathBuf::from("a/Cargo.toml"),
+        name: "serde".to_string(),
+        current: "1.0".to_string(),
+        latest: "1.1".to_string(),
+    });
+
+    bus.forward_plugin_events().await;
+    bus.forward_plugin_events().await;
+
+    let forwarded = rx.try_recv().unwrap();
+    assert_eq!(forwarded.source, Source::Plugin);
+    assert!(matches!(
+        forwarded.kind,
+        EventKind::DependencyOutdated { ref name, .. } if name == "serde"
+    ));
+    assert!(rx.try_recv().is_err());
+    // Plugins aren't told about it a second time
+    assert_eq!(events.history().len(), 2);
+}
@@ -0,0 +1,111 @@
+use kargo_cli::cli::build_root_cli;
+use kargo_cli::events::EventBus;
+use kargo_cli::plugins::hooks;
+use kargo_cli::plugins::manager::PluginManager;
+use kargo_plugin_api::{BoxFuture, ExecutionContext, HookSpec, Invocation, Outcome, PluginCommand};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Records the hooks it sees as `<name>:before:<command>` and
+/// `<name>:after:<command>`; refuses to run `publish-check`
+struct Recorder {
+    name: &'static str,
+    flag: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl PluginCommand for Recorder {
+    fn clap(&self) -> clap::Command {
+        clap::Command::new(self.name)
+    }
+
+    fn run(&self, _ctx: ExecutionContext) -> BoxFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn hooks(&self) -> Option<HookSpec> {
+        Some(HookSpec {
+            flags: vec![clap::Arg::new(self.flag).long(self.flag)],
+        })
+    }
+
+    fn before_command(&self, _ctx: ExecutionContext, invocation: Invocation) -> BoxFuture {
+        let entry = format!("{}:before:{}", self.name, invocation.command);
+        self.log.lock().unwrap().push(entry);
+        Box::pin(async move {
+            anyhow::ensure!(invocation.command != "publish-check", "not on my watch");
+            Ok(())
+        })
+    }
+
+    fn after_command(&self, _ctx: ExecutionContext, invocation: Invocation) -> BoxFuture {
+        let outcome = invocation.outcome.as_ref().unwrap();
+        let entry = format!(
+            "{}:after:{}:{}:{:?}",
+            self.name,
+            invocation.command,
+            outcome.success(),
+            invocation.flags.get(self.flag)
+        );
+        self.log.lock().unwrap().push(entry);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[tokio::test]
+async fn test_hooks_run_in_configured_order_with_their_flags() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut pm = PluginManager::new();
+    for (name, flag) in [
+        ("timing", "timing-out"),
+        ("audit", "audit-tag"),
+        ("stray", "ci"),
+    ] {
+        let plugin = Recorder {
+            name,
+            flag,
+            log: Arc::clone(&log),
+        };
+        pm.register(Box::new(plugin)).unwrap();
+    }
+    // `stray` isn't allowed to hook, and `ci` would clash anyway
+    pm.set_hook_plugins(vec!["audit".to_string(), "timing".to_string()]);
+
+    let root = build_root_cli(&pm);
+    assert!(root.get_arguments().any(|arg| arg.get_id() == "audit-tag"));
+    assert_eq!(
+        root.get_arguments()
+            .filter(|arg| arg.get_id() == "ci")
+            .count(),
+        1
+    );
+
+    let matches = root
+        .try_get_matches_from(["kargo", "--audit-tag", "release", "history"])
+        .unwrap();
+    let events = EventBus::new();
+    hooks::before(&pm, &matches, &events).await.unwrap();
+    let outcome = Outcome {
+        error: None,
+        elapsed: Duration::from_millis(5),
+    };
+    hooks::after(&pm, &matches, &events, outcome).await;
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "audit:before:history",
+            "timing:before:history",
+            "timing:after:history:true:None",
+            "audit:after:history:true:Some([\"release\"])",
+        ]
+    );
+
+    // An error before the command stops the remaining hooks
+    log.lock().unwrap().clear();
+    let matches = build_root_cli(&pm)
+        .try_get_matches_from(["kargo", "publish-check"])
+        .unwrap();
+    let error = hooks::before(&pm, &matches, &events).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("not on my watch"));
+    assert_eq!(*log.lock().unwrap(), ["audit:before:publish-check"]);
+}
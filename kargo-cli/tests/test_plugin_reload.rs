@@ -0,0 +1,114 @@
+use kargo_cli::plugins::manager::PluginManager;
+use kargo_cli::plugins::trust::{Decision, TrustPolicy, TrustStore, file_digest};
+use kargo_cli::plugins::watcher::SourceWatcher;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// A plugin declaring the `greet` command with `about` as its description
+fn greet(about: &str) -> String {
+    let spec = format!(r#"{{"name":"greet","about":"{}"}}"#, about);
+    format!(
+        r#"
+(module
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+  (memory 1)
+  (data (i32.const 0) "{}")
+  (func (export "_kargo_plugin_get_command_spec_json") (result i32)
+    (local $offset i64)
+    (local $i i32)
+    (local.set $offset (call $alloc (i64.const {len})))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (i32.const {len})))
+        (call $store_u8
+          (i64.add (local.get $offset) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (local.get $i)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $offset) (i64.const {len}))
+    (i32.const 0))
+  (func (export "_kargo_plugin_execute") (result i32)
+    (i32.const 0)))
+"#,
+        spec.replace('"', "\\\""),
+        len = spec.len()
+    )
+}
+
+/// Replace the plugin at `file` the way a rebuild does
+fn rebuild(file: &Path, about: &str) {
+    fs::write(file, greet(about)).unwrap();
+    // Coarse file times could otherwise hide a rewrite this soon after the
+    // last one
+    fs::File::options()
+        .write(true)
+        .open(file)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+}
+
+fn about(pm: &PluginManager) -> String {
+    pm.get("greet")
+        .expect("greet is loaded")
+        .clap()
+        .get_about()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_rebuilt_wasm_plugins_are_reloaded_while_trusted() {
+    let dir = tempfile::tempdir().unwrap();
+    let plugins = dir.path().join("plugins");
+    fs::create_dir(&plugins).unwrap();
+    let file = plugins.join("greet.wasm");
+    fs::write(&file, greet("Say hello")).unwrap();
+    let store_path = dir.path().join("trusted-plugins.json");
+
+    let mut pm = PluginManager::new();
+    pm.set_search_paths(vec![plugins.clone()]);
+    pm.set_module_cache(None);
+    pm.set_trust_store(TrustStore::load(&store_path).unwrap());
+    pm.set_trust_policy(TrustPolicy::Allow);
+    pm.discover_and_load_plugins().unwrap();
+    assert_eq!(about(&pm), "Say hello");
+    assert_eq!(pm.source("greet"), Some(file.as_path()));
+
+    let mut watcher = SourceWatcher::new(pm.sources()).unwrap();
+    rebuild(&file, "Say hi");
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        watcher.changed(Duration::from_millis(50)),
+    )
+    .await
+    .expect("the rewrite is noticed")
+    .unwrap();
+    assert_eq!(pm.changed_plugins(), ["greet"]);
+    let reloads = pm.reload_changed();
+    assert_eq!(reloads.len(), 1);
+    reloads.into_iter().next().unwrap().result.unwrap();
+    assert_eq!(about(&pm), "Say hi");
+    // What was trusted is the new version
+    let digest = file_digest(&file).unwrap();
+    assert_eq!(
+        TrustStore::load(&store_path)
+            .unwrap()
+            .decision(&file.canonicalize().unwrap(), Some(&digest)),
+        Some(Decision::Allow)
+    );
+
+    // A replacement nobody trusted is rejected, and the old version stays
+    pm.set_trust_policy(TrustPolicy::Deny);
+    rebuild(&file, "Something else");
+    let reloads = pm.reload_changed();
+    assert_eq!(reloads.len(), 1);
+    let error = reloads.into_iter().next().unwrap().result.unwrap_err();
+    assert!(format!("{:#}", error).contains("no longer trusted"));
+    assert_eq!(about(&pm), "Say hi");
+    // Until it changes again
+    assert!(pm.changed_plugins().is_empty());
+}
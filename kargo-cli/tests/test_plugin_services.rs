@@ -0,0 +1,140 @@
+use kargo_cli::plugins::manager::PluginManager;
+use kargo_cli::plugins::supervisor::supervise;
+use kargo_plugin_api::{
+    BoxFuture, DiagnosticSink, ExecutionContext, HostEvents, PluginCommand, RestartPolicy,
+    ServiceSpec,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// A service that fails its first `failures` runs, then succeeds
+struct Flaky {
+    starts: Arc<AtomicU32>,
+    failures: u32,
+    unloads: Arc<AtomicU32>,
+}
+
+impl PluginCommand for Flaky {
+    fn clap(&self) -> clap::Command {
+        clap::Command::new("flaky")
+    }
+
+    fn run(&self, _ctx: ExecutionContext) -> BoxFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn on_load(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.failures < 10, "refusing to load");
+        Ok(())
+    }
+
+    fn on_unload(&self) {
+        self.unloads.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn service(&self) -> Option<ServiceSpec> {
+        Some(ServiceSpec::default())
+    }
+
+    fn serve(&self, _ctx: ExecutionContext) -> BoxFuture {
+        let start = self.starts.fetch_add(1, Ordering::SeqCst);
+        let failures = self.failures;
+        Box::pin(async move {
+            anyhow::ensure!(start >= failures, "run {} failed", start);
+            Ok(())
+        })
+    }
+}
+
+fn flaky(failures: u32) -> Flaky {
+    Flaky {
+        starts: Arc::default(),
+        failures,
+        unloads: Arc::default(),
+    }
+}
+
+fn context() -> ExecutionContext {
+    ExecutionContext {
+        matched_args: vec!["flaky".to_string()],
+        args: Default::default(),
+        current_dir: ".".into(),
+        config_dir: ".".into(),
+        data_dir: ".".into(),
+        cache_dir: ".".into(),
+        diagnostics: DiagnosticSink::default(),
+        registry: None,
+        target_dir: None,
+        host_version: "0.1.0".to_string(),
+        write_guard: Default::default(),
+        events: Default::default(),
+        network: Default::default(),
+        profiler: Default::default(),
+        settings: Default::default(),
+    }
+}
+
+#[test]
+fn test_restart_delay_follows_policy() {
+    let spec = ServiceSpec::new(RestartPolicy::OnFailure)
+        .with_backoff(Duration::from_secs(1))
+        .with_max_restarts(Some(3));
+    assert_eq!(spec.restart_delay(false, 0), None);
+    assert_eq!(spec.restart_delay(true, 2), Some(Duration::from_secs(4)));
+    assert_eq!(spec.restart_delay(true, 3), None);
+    let always = ServiceSpec::new(RestartPolicy::Always).with_max_restarts(None);
+    assert_eq!(
+        always.restart_delay(false, 20),
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(
+        ServiceSpec::new(RestartPolicy::Never).restart_delay(true, 0),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_failed_service_restarts_until_it_succeeds() {
+    let plugin = flaky(2);
+    let spec = ServiceSpec::default().with_backoff(Duration::from_millis(1));
+    let exit = supervise("flaky", &plugin, &spec, context, &HostEvents::default()).await;
+    assert!(exit.result.is_ok());
+    assert_eq!(exit.restarts, 2);
+
+    // Giving up keeps the last error
+    let plugin = flaky(5);
+    let spec = spec.with_max_restarts(Some(1));
+    let exit = supervise("flaky", &plugin, &spec, context, &HostEvents::default()).await;
+    assert!(exit.result.is_err());
+    assert_eq!(plugin.starts.load(Ordering::SeqCst), 2);
+
+    // Closed events stop a service waiting to restart
+    let events = HostEvents::default();
+    let spec = ServiceSpec::new(RestartPolicy::Always)
+        .with_max_restarts(None)
+        .with_backoff(Duration::from_secs(60));
+    let plugin = flaky(0);
+    let (exit, ()) = tokio::join!(
+        supervise("flaky", &plugin, &spec, context, &events),
+        async { events.close() }
+    );
+    assert_eq!(exit.restarts, 0);
+}
+
+#[test]
+fn test_manager_runs_lifecycle_hooks() {
+    let unloads = Arc::new(AtomicU32::new(0));
+    let mut pm = PluginManager::new();
+    assert!(pm.register(Box::new(flaky(10))).is_err());
+    assert!(pm.get("flaky").is_none());
+
+    let plugin = Flaky {
+        unloads: Arc::clone(&unloads),
+        ..flaky(0)
+    };
+    pm.register(Box::new(plugin)).unwrap();
+    assert_eq!(pm.services().count(), 1);
+    drop(pm);
+    assert_eq!(unloads.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,114 @@
+use kargo_cli::plugins::trust::{Decision, TrustPolicy, TrustStore, file_digest, source_digest};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_decisions_are_recorded_with_the_artifact_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    let plugin = dir.path().join("libdropped.so");
+    fs::write(&plugin, b"first build").unwrap();
+    let store_path = dir.path().join("trusted-plugins.json");
+    let digest = file_digest(&plugin).unwrap();
+    assert!(digest.starts_with("sha256:"));
+
+    // Policy deny skips without recording anything
+    let mut store = TrustStore::load(&store_path).unwrap();
+    assert!(!store.check(&plugin, Some(digest.clone()), TrustPolicy::Deny));
+    assert!(store.records().is_empty());
+
+    // Policy allow loads it and remembers, so prompting won't ask later
+    assert!(store.check(&plugin, Some(digest.clone()), TrustPolicy::Allow));
+    let store = TrustStore::load(&store_path).unwrap();
+    assert_eq!(
+        store.decision(&plugin, Some(&digest)),
+        Some(Decision::Allow)
+    );
+    let mut store = store;
+    assert!(store.check_with(&plugin, Some(digest), TrustPolicy::Prompt, unreachable));
+
+    // A replaced library is new again, and skipped with nobody to ask
+    fs::write(&plugin, b"something else").unwrap();
+    let changed = file_digest(&plugin).unwrap();
+    assert_eq!(store.decision(&plugin, Some(&changed)), None);
+    let mut asked = None;
+    assert!(!store.check_with(
+        &plugin,
+        Some(changed.clone()),
+        TrustPolicy::Prompt,
+        |_, sha256, changed| {
+            asked = Some((sha256.map(str::to_string), changed));
+            None
+        }
+    ));
+    assert_eq!(asked, Some((Some(changed.clone()), true)));
+    // Saying no is remembered
+    assert!(!store.check_with(
+        &plugin,
+        Some(changed.clone()),
+        TrustPolicy::Prompt,
+        |_, _, _| Some(Decision::Deny)
+    ));
+    assert_eq!(
+        store.decision(&plugin, Some(&changed)),
+        Some(Decision::Deny)
+    );
+
+    assert!(store.forget(&plugin));
+    assert!(!store.forget(&plugin));
+}
+
+#[test]
+fn test_plugin_projects_are_trusted_by_their_sources() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("greet");
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join("Cargo.toml"), "[package]\nname = \"greet\"\n").unwrap();
+    fs::write(project.join("src/lib.rs"), "pub fn greet() {}\n").unwrap();
+    let digest = source_digest(&project).unwrap();
+
+    let mut store = TrustStore::load(dir.path().join("trusted-plugins.json")).unwrap();
+    assert!(store.check(&project, Some(digest.clone()), TrustPolicy::Allow));
+
+    // Building it doesn't change what was trusted
+    fs::create_dir_all(project.join("target/debug")).unwrap();
+    fs::write(project.join("target/debug/libgreet.so"), b"built").unwrap();
+    fs::create_dir_all(project.join(".git")).unwrap();
+    fs::write(project.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    assert_eq!(source_digest(&project).unwrap(), digest);
+    assert!(store.check_with(
+        &project,
+        Some(digest.clone()),
+        TrustPolicy::Prompt,
+        unreachable
+    ));
+
+    // Editing it does, and with nobody to ask it is skipped
+    fs::write(project.join("src/lib.rs"), "pub fn greet() { evil() }\n").unwrap();
+    let changed = source_digest(&project).unwrap();
+    assert!(!store.check_with(&project, Some(changed), TrustPolicy::Prompt, |_, _, _| None));
+    fs::write(project.join("src/lib.rs"), "pub fn greet() {}\n").unwrap();
+    assert_eq!(source_digest(&project).unwrap(), digest);
+
+    // So does cargo config that changes what the build runs, the project's
+    // own or that of a directory above it
+    fs::create_dir_all(project.join(".cargo")).unwrap();
+    fs::write(
+        project.join(".cargo/config.toml"),
+        "[build]\nrustc-wrapper = \"/tmp/evil\"\n",
+    )
+    .unwrap();
+    assert_ne!(source_digest(&project).unwrap(), digest);
+    fs::remove_dir_all(project.join(".cargo")).unwrap();
+    fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+    fs::write(
+        dir.path().join(".cargo/config"),
+        "[target.x86_64-unknown-linux-gnu]\nlinker = \"/tmp/evil\"\n",
+    )
+    .unwrap();
+    assert_ne!(source_digest(&project).unwrap(), digest);
+}
+
+/// For checks that must be answered from the recorded decision
+fn unreachable(plugin: &Path, _: Option<&str>, _: bool) -> Option<Decision> {
+    panic!("asked about {}", plugin.display())
+}
@@ -0,0 +1,43 @@
+use kargo_cli::config::Config;
+use std::path::PathBuf;
+
+const CONFIG: &str = r#"
+scan_dirs: [/home/me]
+post_commands: [cargo fmt]
+rollback_on_failure: true
+vendor: { enabled: false, path: '', dedupe: false }
+profiles:
+  work:
+    scan_dirs: [/home/me/work]
+    registry: sparse+https://registry.example.com/index/
+    plugins: [upgrade]
+  personal:
+    rollback_on_failure: false
+"#;
+
+#[test]
+fn test_profile_overrides_only_what_it_sets() {
+    let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+
+    let work = config.with_profile("work").unwrap();
+    assert_eq!(work.scan_dirs, vec![PathBuf::from("/home/me/work")]);
+    assert_eq!(work.post_commands, vec!["cargo fmt".to_string()]);
+    assert!(work.allows_plugin("upgrade"));
+    assert!(!work.allows_plugin("mddoc"));
+    assert_eq!(work.active_profile.as_deref(), Some("work"));
+
+    let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+    let personal = config.with_profile("personal").unwrap();
+    assert_eq!(personal.scan_dirs, vec![PathBuf::from("/home/me")]);
+    assert!(!personal.rollback_on_failure);
+    assert!(personal.allows_plugin("mddoc"));
+    assert!(personal.registry.is_none());
+}
+
+#[test]
+fn test_unknown_profile_lists_defined_ones() {
+    let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+
+    let error = config.with_profile("ci").unwrap_err();
+    assert!(error.contains("personal, work"), "{}", error);
+}
@@ -0,0 +1,119 @@
+use kargo_cli::publish_check::{CheckStatus, PublishReport, manifest_checks, package_failure};
+use std::fs;
+
+#[test]
+fn test_manifest_checks_find_every_problem() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+    fs::create_dir_all(dir.path().join("helper/src")).unwrap();
+    fs::write(dir.path().join("helper/src/lib.rs"), "").unwrap();
+    fs::write(
+        dir.path().join("helper/Cargo.toml"),
+        "[package]\nname = \"helper\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        r#"[package]
+name = "demo"
+version = "0.1.0"
+edition = "2021"
+license-file = "LICENSE"
+
+[dependencies]
+helper = { path = "helper" }
+serde = "*"
+
+[dev-dependencies]
+helper = { path = "helper" }
+"#,
+    )
+    .unwrap();
+
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(dir.path().join("Cargo.toml"))
+        .no_deps()
+        .exec()
+        .unwrap();
+    let package = metadata
+        .packages
+        .iter()
+        .find(|p| p.name.as_str() == "demo")
+        .unwrap();
+    let report = PublishReport {
+        package: "demo".to_string(),
+        version: "0.1.0".to_string(),
+        checks: manifest_checks(package),
+    };
+    assert!(!report.passed());
+
+    let failed: Vec<(&str, usize)> = report
+        .checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .map(|c| (c.name, c.details.len()))
+        .collect();
+    // The dev-dependency on helper needs no version
+    assert_eq!(
+        failed,
+        [
+            ("path-dependencies", 1),
+            ("wildcards", 1),
+            ("readme", 1),
+            ("license", 1),
+            ("description", 1),
+        ]
+    );
+    assert!(report.render().contains("✗ wildcards\n      serde = \"*\""));
+
+    fs::write(dir.path().join("README.md"), "# demo\n").unwrap();
+    fs::write(dir.path().join("LICENSE"), "MIT\n").unwrap();
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(dir.path().join("Cargo.toml"))
+        .no_deps()
+        .exec()
+        .unwrap();
+    let package = metadata
+        .packages
+        .iter()
+        .find(|p| p.name.as_str() == "demo")
+        .unwrap();
+    let passed: Vec<&str> = manifest_checks(package)
+        .into_iter()
+        .filter(|c| c.status == CheckStatus::Pass)
+        .map(|c| c.name)
+        .collect();
+    assert_eq!(passed, ["readme", "license"]);
+}
+
+#[test]
+fn test_failed_packaging_never_passes() {
+    let check = package_failure(
+        "exit status: 101",
+        "warning: manifest has no documentation\nerror: failed to verify package tarball\n\nCaused by:\n  oops\n",
+    );
+    assert_eq!(check.status, CheckStatus::Fail);
+    assert_eq!(
+        check.details,
+        [
+            "cargo package failed (exit status: 101)",
+            "error: failed to verify package tarball",
+            "Caused by:",
+            "oops",
+        ]
+    );
+
+    // Without an error line, the end of stderr says what happened
+    let check = package_failure("signal: 9 (SIGKILL)", "   Packaging demo v0.1.0\n");
+    assert_eq!(check.status, CheckStatus::Fail);
+    assert_eq!(
+        check.details,
+        [
+            "cargo package failed (signal: 9 (SIGKILL))",
+            "Packaging demo v0.1.0"
+        ]
+    );
+    let check = package_failure("exit status: 1", "");
+    assert_eq!(check.status, CheckStatus::Fail);
+}
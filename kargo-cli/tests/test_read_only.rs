@@ -0,0 +1,53 @@
+use kargo_cli::backup::BackupManager;
+use kargo_cli::config::{Config, WasmPermissions};
+use kargo_cli::events::EventBus;
+use kargo_cli::plugins::permissions;
+use kargo_cli::remote_config::{Include, IncludeCache};
+use kargo_plugin_api::{ReadOnlyError, WriteGuard};
+use std::fs;
+
+#[test]
+fn test_read_only_refuses_writes_with_a_typed_error() {
+    let work = tempfile::tempdir().unwrap();
+    let store = work.path().join("backups");
+    let manifest = work.path().join("Cargo.toml");
+    fs::write(&manifest, "[dependencies]\n").unwrap();
+
+    WriteGuard::read_only().install();
+    let error = BackupManager::create_in(&store, "update", EventBus::new())
+        .and_then(|mut backup| backup.backup_file(&manifest))
+        .unwrap_err();
+    let refused = error.downcast_ref::<ReadOnlyError>().unwrap();
+    assert!(refused.path.starts_with(&store));
+    assert!(!store.exists());
+
+    let error = WriteGuard::current()
+        .write_atomic(&manifest, "[dependencies]\nserde = \"1\"\n")
+        .unwrap_err();
+    assert!(error.is::<ReadOnlyError>());
+    assert_eq!(fs::read_to_string(&manifest).unwrap(), "[dependencies]\n");
+
+    // WASM plugins' permissions are granted for this run only
+    let config = work.path().join("krater.yaml");
+    let granted = WasmPermissions {
+        allowed_env: vec!["GITHUB_TOKEN".to_string()],
+        ..WasmPermissions::default()
+    };
+    permissions::record(&config, "fetcher", &granted).unwrap();
+    assert!(!config.exists());
+    let error = Config::default().save(&config).unwrap_err();
+    assert!(error.is::<ReadOnlyError>());
+
+    // Nor are config includes cached
+    let cache = work.path().join("includes");
+    let include = Include::Url("https://config.example.com/kargo-org.yaml".to_string());
+    let error = IncludeCache::new(&cache)
+        .store(&include, "plugins: [audit]\n", None, None)
+        .unwrap_err();
+    assert!(error.is::<ReadOnlyError>());
+    assert!(!cache.exists());
+
+    // Explicitly writable guards still write, e.g. for the status report
+    WriteGuard::writable().write(&manifest, "").unwrap();
+    WriteGuard::writable().install();
+}
@@ -0,0 +1,87 @@
+use kargo_cli::config::Config;
+use kargo_cli::remote_config::{Include, IncludeCache, IncludeSource};
+
+#[test]
+fn test_local_settings_override_includes_and_stay_local_on_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = IncludeCache::new(dir.path().join("cache"));
+    let org = Include::Url("https://config.example.com/kargo-org.toml".to_string());
+    let team = Include::Source(IncludeSource {
+        git: Some("https://git.example.com/team/config".to_string()),
+        reference: Some("main".to_string()),
+        ..IncludeSource::default()
+    });
+    cache
+        .store(
+            &org,
+            "post_commands = [\"cargo fmt\", \"cargo deny check\"]\n\
+             rollback_on_failure = false\n\
+             plugins = [\"audit\", \"outdated\"]\n\
+             [vendor]\nenabled = true\npath = \"vendor\"\ndedupe = true\n",
+            None,
+            Some("\"v1\"".to_string()),
+        )
+        .unwrap();
+    cache
+        .store(
+            &team,
+            "plugins: [audit]\n",
+            None,
+            Some("abc123".to_string()),
+        )
+        .unwrap();
+
+    let path = dir.path().join("krater.yaml");
+    std::fs::write(
+        &path,
+        "include:\n\
+         - https://config.example.com/kargo-org.toml\n\
+         - git: https://git.example.com/team/config\n  ref: main\n\
+         - https://config.example.com/not-synced.yaml\n\
+         scan_dirs: [/src]\n\
+         vendor:\n  dedupe: false\n",
+    )
+    .unwrap();
+
+    let config = Config::from_file_with(&path, &cache).unwrap();
+    assert_eq!(config.include.len(), 3);
+    assert_eq!(config.scan_dirs, [std::path::PathBuf::from("/src")]);
+    assert_eq!(config.post_commands, ["cargo fmt", "cargo deny check"]);
+    assert!(!config.rollback_on_failure);
+    // the later include wins, the local file wins over both, key by key
+    assert_eq!(config.plugins.as_deref(), Some(&["audit".to_string()][..]));
+    assert!(config.vendor.enabled);
+    assert!(!config.vendor.dedupe);
+
+    config.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("not-synced.yaml"));
+    assert!(saved.contains("dedupe: false"));
+    assert!(!saved.contains("cargo deny check"));
+    assert!(!saved.contains("plugins"));
+    assert!(!saved.contains("enabled"));
+    let reloaded = Config::from_file_with(&path, &cache).unwrap();
+    assert_eq!(reloaded.post_commands, config.post_commands);
+    assert!(reloaded.vendor.enabled);
+}
+
+#[test]
+fn test_signed_include_requires_a_valid_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = IncludeCache::new(dir.path());
+    let include = Include::Source(IncludeSource {
+        url: Some("https://config.example.com/kargo-org.yaml".to_string()),
+        public_key: Some("RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3".to_string()),
+        ..IncludeSource::default()
+    });
+
+    let unsigned = cache.store(&include, "plugins: [audit]\n", None, None);
+    assert!(unsigned.unwrap_err().to_string().contains("Not signed"));
+    let signature = "untrusted comment: signature\n\
+        RWQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=\n\
+        trusted comment: timestamp:1633700835\tfile:test\n\
+        wLMDjy9FLAuxZ3q4NlEvkgtyhrr0gtTu6KC4KBJdITbbOeAi1zBIYo0v4iTgt8jJpIidRJnp94ABQkJAgAooBQ==\n";
+    let forged = cache.store(&include, "plugins: [audit]\n", Some(signature), None);
+    assert!(forged.is_err());
+    assert!(cache.load(&include).unwrap().is_none());
+}
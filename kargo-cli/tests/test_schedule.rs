@@ -0,0 +1,46 @@
+use kargo_cli::schedule::{Interval, JobRun, ScheduledJob, Scheduler};
+use std::time::Duration;
+
+fn job(every: &str) -> ScheduledJob {
+    ScheduledJob {
+        name: "upgrade-check".to_string(),
+        command: "upgrade --dry-run".to_string(),
+        every: every.parse().unwrap(),
+    }
+}
+
+#[test]
+fn test_interval_parsing() {
+    let nightly: Interval = "nightly".parse().unwrap();
+    assert_eq!(nightly.duration(), Duration::from_secs(86_400));
+    assert_eq!("6h".parse::<Interval>().unwrap().to_string(), "6h");
+    assert_eq!("90m".parse::<Interval>().unwrap().to_string(), "90m");
+    assert!("6".parse::<Interval>().is_err());
+    assert!("0h".parse::<Interval>().is_err());
+    assert!("3y".parse::<Interval>().is_err());
+}
+
+#[test]
+fn test_due_jobs_follow_recorded_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let scheduler = Scheduler::new(dir.path());
+    let job = job("daily");
+    let start = 1_700_000_000;
+
+    assert!(scheduler.is_due(&scheduler.history().unwrap(), &job, start));
+
+    scheduler
+        .record(&JobRun {
+            job: job.name.clone(),
+            started: start,
+            duration_ms: 1200,
+            exit_code: 0,
+            status: Some("success".to_string()),
+        })
+        .unwrap();
+    let history = scheduler.history().unwrap();
+
+    assert!(!scheduler.is_due(&history, &job, start + 3_600));
+    // A timer firing slightly earlier the next day still picks the job up
+    assert!(scheduler.is_due(&history, &job, start + 86_400 - 30));
+}
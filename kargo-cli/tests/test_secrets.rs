@@ -0,0 +1,71 @@
+use kargo_plugin_api::secrets::{self, SecretStore};
+use kargo_plugin_api::{HttpClient, NetworkConfig};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+
+#[test]
+fn test_encrypted_file_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secrets.enc");
+    let store = SecretStore::file(path.clone(), Some("correct horse".to_string()));
+    store.set("github", "ghp_example").unwrap();
+    store.set("registry", "cio_example").unwrap();
+    assert!(!String::from_utf8_lossy(&std::fs::read(&path).unwrap()).contains("ghp_example"));
+
+    assert_eq!(store.resolve("keyring:github").unwrap(), "ghp_example");
+    assert_eq!(store.resolve("plain-value").unwrap(), "plain-value");
+    assert!(store.remove("github").unwrap());
+    assert!(!store.remove("github").unwrap());
+    assert!(store.resolve("keyring:github").is_err());
+
+    let wrong = SecretStore::file(path.clone(), Some("battery staple".to_string()));
+    assert!(wrong.get("registry").is_err());
+    assert!(SecretStore::file(path, None).get("registry").is_err());
+    assert_eq!(secrets::reference("keyring: ci "), Some("ci"));
+}
+
+#[test]
+fn test_network_tokens_resolve_and_authorize_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SecretStore::file(dir.path().join("secrets.enc"), Some("pass".to_string()));
+    store.set("local", "s3cret").unwrap();
+
+    let mut network = NetworkConfig {
+        retries: 0,
+        ..NetworkConfig::default()
+    };
+    network
+        .tokens
+        .insert("127.0.0.1".to_string(), "keyring:local".to_string());
+    network
+        .tokens
+        .insert("example.com".to_string(), "keyring:missing".to_string());
+    assert!(network.has_secret_references());
+    // The missing secret is reported and dropped, the other one resolved
+    assert!(network.resolve_secrets(&store).is_err());
+    assert_eq!(network.tokens.len(), 1);
+    assert!(!network.has_secret_references());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let read = stream.read(&mut request).unwrap();
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        String::from_utf8_lossy(&request[..read]).to_lowercase()
+    });
+    let client = HttpClient::with_config(reqwest::Client::builder(), network).unwrap();
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(client.send(client.get(&url)))
+        .unwrap();
+    assert!(
+        server
+            .join()
+            .unwrap()
+            .contains("authorization: bearer s3cret")
+    );
+}
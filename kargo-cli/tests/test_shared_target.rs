@@ -0,0 +1,57 @@
+use kargo_cli::shared_target::{Eviction, SharedTarget};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Write a unit of `bytes` bytes to `profile`, last built `age_secs` ago
+fn unit(profile: &Path, key: &str, bytes: usize, age_secs: u64) {
+    let fingerprint = profile.join(".fingerprint").join(key);
+    let build = profile.join("build").join(key);
+    let rlib = profile.join("deps").join(format!("lib{}.rlib", key));
+    fs::create_dir_all(&fingerprint).unwrap();
+    fs::create_dir_all(&build).unwrap();
+    fs::create_dir_all(rlib.parent().unwrap()).unwrap();
+    fs::write(fingerprint.join("lib"), "fp").unwrap();
+    fs::write(build.join("output"), "").unwrap();
+    fs::write(&rlib, vec![0u8; bytes]).unwrap();
+
+    let built = SystemTime::now() - Duration::from_secs(age_secs);
+    for path in [fingerprint.join("lib"), build.join("output"), rlib] {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(built)
+            .unwrap();
+    }
+    for dir in [fingerprint, build] {
+        File::open(dir).unwrap().set_modified(built).unwrap();
+    }
+}
+
+#[test]
+fn test_evicts_least_recently_built_units_until_under_the_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let debug = dir.path().join("debug");
+    let release = dir.path().join("x86_64-unknown-linux-gnu").join("release");
+    unit(&debug, "serde-1a", 4000, 300);
+    unit(&debug, "libc-2b", 4000, 100);
+    unit(&release, "tokio-3c", 4000, 200);
+    unit(&debug, "anyhow-4d", 4000, 10);
+
+    let target = SharedTarget {
+        dir: dir.path().to_path_buf(),
+        max_bytes: 9000,
+    };
+    let eviction = target.evict().unwrap();
+
+    assert_eq!(eviction.units, 2);
+    assert!(!debug.join(".fingerprint/serde-1a").exists());
+    assert!(!debug.join("build/serde-1a").exists());
+    assert!(!debug.join("deps/libserde-1a.rlib").exists());
+    assert!(!release.join("deps/libtokio-3c.rlib").exists());
+    assert!(debug.join("deps/liblibc-2b.rlib").exists());
+    assert!(debug.join(".fingerprint/anyhow-4d").exists());
+
+    assert_eq!(target.evict().unwrap(), Eviction::default());
+}
@@ -0,0 +1,105 @@
+use kargo_cli::size_report::{
+    BinarySize, FeatureConfig, SizeComparison, SizeHistory, SizeRun, section_sizes,
+};
+use std::collections::BTreeMap;
+
+/// A binary's name and size, with the sizes of its sections
+type Binary<'a> = (&'a str, u64, &'a [(&'a str, u64)]);
+
+fn run(commit: &str, binaries: &[Binary]) -> SizeRun {
+    SizeRun {
+        commit: Some(commit.to_string()),
+        timestamp: 0,
+        features: FeatureConfig::default(),
+        binaries: binaries
+            .iter()
+            .map(|(name, bytes, sections)| BinarySize {
+                name: name.to_string(),
+                bytes: *bytes,
+                sections: sections.iter().map(|(s, b)| (s.to_string(), *b)).collect(),
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_runs_are_compared_with_the_baseline() {
+    let dir = tempfile::tempdir().unwrap();
+    let history = SizeHistory::for_project(dir.path());
+    let baseline = run(
+        "0123456789abcdef",
+        &[
+            (
+                "kargo",
+                1_000_000,
+                &[(".text", 600_000), (".rodata", 100_000)],
+            ),
+            ("old-tool", 50_000, &[]),
+        ],
+    );
+    history.append(&baseline).unwrap();
+    assert_eq!(history.runs().unwrap(), std::slice::from_ref(&baseline));
+
+    let current = run(
+        "fedcba9876543210",
+        &[
+            (
+                "kargo",
+                1_080_000,
+                &[(".text", 680_000), (".rodata", 100_500)],
+            ),
+            ("helper", 20_000, &[]),
+        ],
+    );
+    let comparison = SizeComparison::new(Some(&baseline), &current);
+    assert_eq!(comparison.binaries[0].percent(), Some(8.0));
+    // Below 1 KiB of change, .rodata is noise
+    assert_eq!(comparison.binaries[0].sections.len(), 1);
+    assert_eq!(comparison.removed, ["old-tool"]);
+    assert_eq!(comparison.regressions(10.0).len(), 0);
+    assert_eq!(comparison.regressions(5.0).len(), 1);
+    assert_eq!(
+        comparison.render(),
+        "Release binary sizes (default features) against 0123456\n\
+         \x20 kargo      1.0 MiB  +78.1 KiB (+8.0%)\n\
+         \x20   .text             +78.1 KiB\n\
+         \x20 helper    19.5 KiB  new\n\
+         \x20 old-tool  removed\n"
+    );
+}
+
+#[test]
+fn test_elf_sections_are_read() {
+    // A 64-bit little-endian ELF header with 4 section headers: null,
+    // .text (0x300 bytes), .bss (no file bytes) and the name table
+    let names = b"\0.text\0.bss\0.shstrtab\0";
+    let mut elf = vec![0u8; 0x40];
+    elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+    let shoff = 0x40 + names.len() as u64;
+    elf[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+    elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+    elf[0x3c..0x3e].copy_from_slice(&4u16.to_le_bytes());
+    elf[0x3e..0x40].copy_from_slice(&3u16.to_le_bytes());
+    elf.extend_from_slice(names);
+    let header = |name: u32, kind: u32, offset: u64, size: u64| {
+        let mut header = vec![0u8; 64];
+        header[0..4].copy_from_slice(&name.to_le_bytes());
+        header[4..8].copy_from_slice(&kind.to_le_bytes());
+        header[24..32].copy_from_slice(&offset.to_le_bytes());
+        header[32..40].copy_from_slice(&size.to_le_bytes());
+        header
+    };
+    elf.extend(header(0, 0, 0, 0));
+    elf.extend(header(1, 1, 0, 0x300));
+    elf.extend(header(7, 8, 0, 0x100));
+    elf.extend(header(12, 3, 0x40, names.len() as u64));
+
+    assert_eq!(
+        section_sizes(&elf),
+        BTreeMap::from([
+            (".shstrtab".to_string(), names.len() as u64),
+            (".text".to_string(), 0x300),
+        ])
+    );
+    assert!(section_sizes(b"MZ not a supported format").is_empty());
+}
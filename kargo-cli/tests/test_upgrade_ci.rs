@@ -0,0 +1,91 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use kargo_cli::ci::CiReporter;
+use kargo_cli::cli::{publish_diagnostics, run_plugin};
+use kargo_cli::events::EventBus;
+use kargo_cli::status::{ExitStatus, Severity};
+use kargo_upgrade::plugin::UpgradePlugin;
+use kargo_upgrade::registry::use_index;
+
+const MANIFEST: &str =
+    "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\ndemo = \"1.0.0\"\n";
+
+const INDEX_FILE: &str = concat!(
+    r#"{"name":"demo","vers":"1.0.0","yanked":false}"#,
+    "\n",
+    r#"{"name":"demo","vers":"1.1.0","yanked":false}"#,
+    "\n",
+);
+
+/// Serve `INDEX_FILE` for every request, as a sparse registry with one crate
+fn serve_index() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 && line.trim() != "" {
+                line.clear();
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                INDEX_FILE.len(),
+                INDEX_FILE
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_dry_run_reports_outdated_dependencies_to_ci() {
+    assert!(use_index(&serve_index()));
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    std::fs::write(&manifest, MANIFEST).unwrap();
+    let summary = dir.path().join("summary.md");
+
+    // `kargo --ci upgrade --dry-run <dir>`
+    let events = EventBus::new();
+    let reporter = CiReporter::new(Some(summary.clone())).spawn(&events);
+    let args = ["upgrade", "--dry-run", &dir.path().to_string_lossy()].map(String::from);
+    let (result, diagnostics) = run_plugin(
+        "upgrade",
+        &UpgradePlugin,
+        args.to_vec(),
+        dir.path().to_path_buf(),
+        &events,
+        None,
+    )
+    .await;
+    publish_diagnostics("upgrade", diagnostics, &events).await;
+    drop(events);
+    let report = reporter.await.unwrap().finish(&result).unwrap();
+
+    assert!(result.is_ok());
+    assert_eq!(report.status, ExitStatus::UpdatesAvailable);
+    assert_eq!(report.exit_code, 2);
+    assert_eq!(report.outdated.len(), 1);
+    assert_eq!(report.outdated[0].name, "demo");
+    assert_eq!(report.outdated[0].current, "1.0.0");
+    assert_eq!(report.outdated[0].latest, "1.1.0");
+
+    let annotation = &report.diagnostics[0];
+    assert_eq!(report.diagnostics.len(), 1);
+    assert_eq!(annotation.severity, Severity::Warning);
+    assert_eq!(annotation.file.as_deref(), Some(manifest.as_path()));
+    assert_eq!(annotation.line, Some(6));
+    assert_eq!(
+        annotation.title.as_deref(),
+        Some("Outdated dependency: demo")
+    );
+
+    let summary = std::fs::read_to_string(summary).unwrap();
+    assert!(summary.contains("| `demo` | 1.0.0 | 1.1.0 |"));
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), MANIFEST);
+}
@@ -0,0 +1,141 @@
+use kargo_cli::verify_vendored::{Problem, sha256_hex, verify};
+use std::fs;
+use std::path::Path;
+
+/// Vendor `files` as `name` `version` the way `cargo vendor` does, recording
+/// `package` as the digest of the `.crate`
+fn vendor(dir: &Path, name: &str, version: &str, package: &str, files: &[(&str, &str)]) {
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"{}\"\n",
+        name, version
+    );
+    let mut recorded = serde_json::Map::new();
+    for (file, content) in files
+        .iter()
+        .copied()
+        .chain([("Cargo.toml", manifest.as_str())])
+    {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        recorded.insert(file.to_string(), sha256_hex(content.as_bytes()).into());
+    }
+    let checksums = serde_json::json!({ "files": recorded, "package": package });
+    fs::write(dir.join(".cargo-checksum.json"), checksums.to_string()).unwrap();
+}
+
+#[test]
+fn test_tampered_and_drifted_crates_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let lockfile = dir.path().join("Cargo.lock");
+    fs::write(
+        &lockfile,
+        r#"version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "clean"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "aaaa"
+
+[[package]]
+name = "edited"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbbb"
+
+[[package]]
+name = "drifted"
+version = "3.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "cccc"
+
+[[package]]
+name = "absent"
+version = "4.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "dddd"
+"#,
+    )
+    .unwrap();
+    let vendor_dir = dir.path().join("vendor");
+    vendor(
+        &vendor_dir.join("clean"),
+        "clean",
+        "1.0.0",
+        "aaaa",
+        &[("src/lib.rs", "pub fn f() {}")],
+    );
+    vendor(
+        &vendor_dir.join("edited"),
+        "edited",
+        "2.0.0",
+        "bbbb",
+        &[("src/lib.rs", "pub fn f() {}"), ("README.md", "# edited")],
+    );
+    fs::write(
+        vendor_dir.join("edited/src/lib.rs"),
+        "pub fn f() { evil() }",
+    )
+    .unwrap();
+    fs::write(vendor_dir.join("edited/build.rs"), "fn main() {}").unwrap();
+    fs::remove_file(vendor_dir.join("edited/README.md")).unwrap();
+    // The `<name>/<version>` layout works too
+    vendor(
+        &vendor_dir.join("drifted/3.0.0"),
+        "drifted",
+        "3.0.0",
+        "ffff",
+        &[],
+    );
+    fs::create_dir_all(vendor_dir.join("stray/0.1.0")).unwrap();
+
+    let report = verify(&vendor_dir, &lockfile).unwrap();
+    let problems = |name: &str| {
+        report
+            .crates
+            .iter()
+            .find(|c| c.name == name)
+            .unwrap()
+            .problems
+            .clone()
+    };
+    assert_eq!(problems("clean"), []);
+    assert_eq!(
+        problems("edited"),
+        [
+            Problem::Added {
+                file: "build.rs".to_string()
+            },
+            Problem::Modified {
+                file: "src/lib.rs".to_string()
+            },
+            Problem::Removed {
+                file: "README.md".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        problems("drifted"),
+        [Problem::ChecksumMismatch {
+            locked: "cccc".to_string(),
+            vendored: Some("ffff".to_string())
+        }]
+    );
+    assert_eq!(
+        problems("stray"),
+        [Problem::NotLocked, Problem::NoChecksumFile]
+    );
+    let missing: Vec<&str> = report.missing.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(missing, ["absent"]);
+    assert_eq!(report.failed(), 4);
+    assert!(
+        report
+            .render()
+            .contains("1 of 4 vendored crate(s) verified")
+    );
+}
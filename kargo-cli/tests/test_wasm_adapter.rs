@@ -0,0 +1,62 @@
+use kargo_cli::config::WasmPermissions;
+use kargo_cli::plugins::wasm_adapter::WasmPluginAdapter;
+use kargo_plugin_api::PluginCommand;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A plugin declaring the `greet` command and printing a greeting when run,
+/// through the Extism kernel as plugins built with the PDK do
+const GREET: &str = r#"
+(module
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+  (memory 1)
+  (data (i32.const 0) "{\"name\":\"greet\",\"about\":\"Say hello\"}")
+  (data (i32.const 64) "hello")
+  (func $output (param $at i32) (param $len i32)
+    (local $offset i64)
+    (local $i i32)
+    (local.set $offset (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $offset) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $at) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $offset) (i64.extend_i32_u (local.get $len))))
+  (func (export "_kargo_plugin_get_command_spec_json") (result i32)
+    (call $output (i32.const 0) (i32.const 36))
+    (i32.const 0))
+  (func (export "_kargo_plugin_execute") (result i32)
+    (call $output (i32.const 64) (i32.const 5))
+    (i32.const 0)))
+"#;
+
+fn greet_plugin(dir: &Path) -> PathBuf {
+    let file = dir.join("greet.wasm");
+    fs::write(&file, GREET).unwrap();
+    file
+}
+
+#[tokio::test]
+async fn test_wasm_plugins_load_into_a_pool_of_instances() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = greet_plugin(dir.path());
+    let adapter = WasmPluginAdapter::new(&file, &WasmPermissions::default(), 2, None).unwrap();
+
+    let command = adapter.clap();
+    assert_eq!(command.get_name(), "greet");
+    assert_eq!(command.get_about().unwrap().to_string(), "Say hello");
+    assert!(adapter.diagnostics_schema().is_none());
+    assert!(adapter.requires_kargo().is_none());
+
+    // What doesn't compile fails to load instead of failing each call
+    fs::write(&file, "(module (func (export \"broken\") (result i32)))").unwrap();
+    let error = WasmPluginAdapter::new(&file, &WasmPermissions::default(), 2, None)
+        .err()
+        .unwrap();
+    assert!(format!("{:#}", error).contains("Failed to create Extism plugin"));
+}
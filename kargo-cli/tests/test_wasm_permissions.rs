@@ -0,0 +1,89 @@
+use kargo_cli::config::{Config, WasmPermissions};
+use kargo_cli::plugins::permissions::{self, Capabilities};
+use kargo_cli::plugins::trust::TrustPolicy;
+use std::fs;
+
+fn requested() -> Capabilities {
+    serde_json::from_str(
+        r#"{"fs_read": ["data"], "network": ["api.github.com"], "env": ["GITHUB_TOKEN"]}"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_declared_capabilities_are_granted_once_and_saved() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("krater.yaml");
+    fs::write(
+        &config_path,
+        "scan_dirs: [/src]\nwasm_permissions:\n  fetcher:\n    allowed_hosts: [crates.io]\n",
+    )
+    .unwrap();
+    let current = Config::from_file(&config_path).unwrap().wasm_permissions["fetcher"].clone();
+
+    // Deny leaves the plugin with what it had, and asks again next time
+    assert_eq!(
+        permissions::review("fetcher", &requested(), &current, TrustPolicy::Deny),
+        None
+    );
+    // So does prompting without a terminal
+    assert_eq!(
+        permissions::review("fetcher", &requested(), &current, TrustPolicy::Prompt),
+        None
+    );
+
+    let granted =
+        permissions::review("fetcher", &requested(), &current, TrustPolicy::Allow).unwrap();
+    assert_eq!(granted.allowed_hosts, ["crates.io", "api.github.com"]);
+    assert_eq!(granted.allowed_env, ["GITHUB_TOKEN"]);
+    assert_eq!(granted.reviewed, requested());
+    permissions::record(&config_path, "fetcher", &granted).unwrap();
+
+    let config = Config::from_file(&config_path).unwrap();
+    assert_eq!(config.scan_dirs, [std::path::PathBuf::from("/src")]);
+    let saved = &config.wasm_permissions["fetcher"];
+    assert_eq!(saved, &granted);
+    // Nothing new to ask about
+    assert_eq!(
+        permissions::review("fetcher", &requested(), saved, TrustPolicy::Prompt),
+        None
+    );
+
+    // A declined capability isn't asked about again, a newly declared one is
+    let declined = WasmPermissions {
+        reviewed: requested(),
+        ..WasmPermissions::default()
+    };
+    assert_eq!(
+        permissions::review("fetcher", &requested(), &declined, TrustPolicy::Allow),
+        None
+    );
+    let mut more = requested();
+    more.env.push("HOME".to_string());
+    let updated = permissions::review("fetcher", &more, &declined, TrustPolicy::Allow).unwrap();
+    assert_eq!(updated.allowed_env, ["HOME"]);
+    assert!(updated.allowed_hosts.is_empty());
+}
+
+#[test]
+fn test_granted_paths_and_variables_bound_what_plugins_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("data");
+    fs::create_dir_all(data.join("nested")).unwrap();
+    fs::write(data.join("nested/input.txt"), "input").unwrap();
+    fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+
+    let permissions = WasmPermissions {
+        allowed_paths: vec![data.clone()],
+        allowed_env: vec!["GITHUB_TOKEN".to_string()],
+        ..WasmPermissions::default()
+    };
+    assert!(permissions.allows_path(&data.join("nested/input.txt")));
+    assert!(!permissions.allows_path(&dir.path().join("secret.txt")));
+    // No way out through `..`
+    assert!(!permissions.allows_path(&data.join("../secret.txt")));
+    assert!(!permissions.allows_path(&data.join("missing.txt")));
+    assert!(permissions.allows_env("GITHUB_TOKEN"));
+    assert!(!permissions.allows_env("AWS_SECRET_ACCESS_KEY"));
+    assert!(WasmPermissions::default().granted().is_empty());
+}
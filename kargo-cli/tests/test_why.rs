@@ -0,0 +1,161 @@
+use kargo_cli::why::explain;
+use kargo_plugin_api::metadata::{
+    DeclaredDependency, DepKind, DependencySource, PackageInfo, ProjectMetadata,
+    ResolvedDependency, ResolvedNode,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// A package and the packages it depends on, with their kinds and whether
+/// they're optional
+struct Spec {
+    name: &'static str,
+    features: &'static [(&'static str, &'static [&'static str])],
+    enabled: &'static [&'static str],
+    deps: &'static [(&'static str, DepKind, bool)],
+}
+
+fn id(name: &str) -> String {
+    format!(
+        "registry+https://github.com/rust-lang/crates.io-index#{}@1.0.0",
+        name
+    )
+}
+
+fn metadata(specs: &[Spec]) -> ProjectMetadata {
+    let mut packages = Vec::new();
+    let mut resolve = HashMap::new();
+    for spec in specs {
+        packages.push(PackageInfo {
+            id: id(spec.name),
+            name: spec.name.to_string(),
+            version: "1.0.0".to_string(),
+            manifest_path: PathBuf::from(format!("/ws/{}/Cargo.toml", spec.name)),
+            edition: "2021".to_string(),
+            targets: Vec::new(),
+            features: spec
+                .features
+                .iter()
+                .map(|(name, enables)| {
+                    (
+                        name.to_string(),
+                        enables.iter().map(|e| e.to_string()).collect(),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>(),
+            dependencies: spec
+                .deps
+                .iter()
+                .map(|(name, kind, optional)| DeclaredDependency {
+                    package: name.to_string(),
+                    rename: None,
+                    req: "1".to_string(),
+                    kind: *kind,
+                    target: None,
+                    optional: *optional,
+                    default_features: true,
+                    features: vec!["std".to_string()],
+                    source: DependencySource::Registry(None),
+                })
+                .collect(),
+        });
+        let node = ResolvedNode {
+            dependencies: spec
+                .deps
+                .iter()
+                .map(|(name, kind, _)| ResolvedDependency {
+                    name: name.to_string(),
+                    package_id: id(name),
+                    package: name.to_string(),
+                    version: "1.0.0".to_string(),
+                    kinds: vec![(*kind, None)],
+                })
+                .collect(),
+            features: spec.enabled.iter().map(|f| f.to_string()).collect(),
+        };
+        resolve.insert(id(spec.name), node);
+    }
+    ProjectMetadata {
+        workspace_root: PathBuf::from("/ws"),
+        members: vec![id("app")],
+        packages,
+        resolve: Some(resolve),
+    }
+}
+
+#[test]
+fn test_explains_chains_features_and_graphs() {
+    let metadata = metadata(&[
+        Spec {
+            name: "app",
+            features: &[],
+            enabled: &[],
+            deps: &[
+                ("web", DepKind::Normal, false),
+                ("testkit", DepKind::Dev, false),
+                ("cc", DepKind::Build, false),
+            ],
+        },
+        Spec {
+            name: "web",
+            features: &[("default", &["pattern"]), ("pattern", &["dep:regex"])],
+            enabled: &["default", "pattern"],
+            deps: &[("regex", DepKind::Normal, true)],
+        },
+        Spec {
+            name: "testkit",
+            features: &[],
+            enabled: &[],
+            deps: &[
+                ("regex", DepKind::Normal, false),
+                ("fake", DepKind::Normal, false),
+            ],
+        },
+        Spec {
+            name: "regex",
+            features: &[],
+            enabled: &["std"],
+            deps: &[],
+        },
+        Spec {
+            name: "cc",
+            features: &[],
+            enabled: &[],
+            deps: &[],
+        },
+        Spec {
+            name: "fake",
+            features: &[],
+            enabled: &[],
+            deps: &[],
+        },
+    ]);
+
+    let regex = &explain(&metadata, "regex", None).unwrap()[0];
+    assert_eq!(regex.graphs, [DepKind::Normal, DepKind::Dev]);
+    assert_eq!(regex.features, ["std"]);
+    assert_eq!(regex.chains.len(), 2);
+    let via_testkit = &regex.chains[0];
+    assert_eq!(via_testkit[1].package, "testkit");
+    assert_eq!(via_testkit[1].edge.as_ref().unwrap().kinds, [DepKind::Dev]);
+    let edge = regex.chains[1][2].edge.as_ref().unwrap();
+    assert!(edge.optional);
+    assert_eq!(edge.enabled_by, ["pattern"]);
+    assert_eq!(edge.features, ["std"]);
+
+    let tree = regex.render();
+    assert!(
+        tree.contains(
+            "├── testkit v1.0.0 (features: std)\n│   └── app v1.0.0 ([dev]; features: std)"
+        )
+    );
+    assert!(tree.contains("└── web v1.0.0 (optional, enabled by feature pattern; features: std)"));
+
+    let cc = &explain(&metadata, "cc", None).unwrap()[0];
+    assert_eq!(cc.graphs, [DepKind::Build]);
+    assert!(cc.render().contains("only needed at build time"));
+    let fake = &explain(&metadata, "fake", None).unwrap()[0];
+    assert_eq!(fake.graphs, [DepKind::Dev]);
+
+    assert!(explain(&metadata, "regex", Some("2.0.0")).is_err());
+}
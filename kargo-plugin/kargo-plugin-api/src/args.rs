@@ -0,0 +1,177 @@
+//! A plugin's arguments as the host parsed them
+//!
+//! The host checks a plugin's arguments against the command the plugin
+//! declares before running it, and hands over what it parsed in
+//! [`ExecutionContext::args`]: the values of each argument keyed by its id,
+//! and the subcommand given with its own arguments. Plugins read them with
+//! the getters below instead of parsing
+//! [`ExecutionContext::matched_args`] again.
+//!
+//! ```
+//! # use kargo_plugin_api::args::PluginArgs;
+//! # use clap::{Arg, ArgAction, Command};
+//! let command = Command::new("lint")
+//!     .arg(Arg::new("files").num_args(0..))
+//!     .arg(Arg::new("quiet").long("quiet").action(ArgAction::SetTrue))
+//!     .arg(Arg::new("jobs").long("jobs").default_value("4"));
+//! let args = PluginArgs::parse(command, ["lint", "README.md", "--quiet"])?;
+//! assert_eq!(args.values("files"), ["README.md"]);
+//! assert!(args.flag("quiet"));
+//! assert_eq!(args.get::<usize>("jobs")?, Some(4));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Values are kept as the strings they were given as, so they cross the
+//! plugin boundary the same way whichever version of clap either side links.
+//!
+//! [`ExecutionContext::args`]: crate::ExecutionContext::args
+//! [`ExecutionContext::matched_args`]: crate::ExecutionContext::matched_args
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::str::FromStr;
+
+/// Id the values of an external subcommand are kept under, as in clap
+const EXTERNAL: &str = "";
+
+/// Parsed arguments of a plugin or one of its subcommands
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginArgs {
+    /// Values of the arguments given or defaulted, keyed by id; switches have
+    /// `true` or `false` and counters their count
+    pub values: BTreeMap<String, Vec<String>>,
+    /// The subcommand given, with its own arguments
+    pub subcommand: Option<(String, Box<PluginArgs>)>,
+}
+
+impl PluginArgs {
+    /// Parse `args`, the plugin name followed by its arguments, with `command`
+    pub fn parse<I, T>(command: Command, args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = command.clone().try_get_matches_from(args)?;
+        Ok(Self::from_matches(&command, &matches))
+    }
+
+    /// The arguments in `matches`, which `command` parsed
+    pub fn from_matches(command: &Command, matches: &ArgMatches) -> Self {
+        let mut values = BTreeMap::new();
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            if matches.value_source(id).is_none() {
+                continue;
+            }
+            let value = match arg.get_action() {
+                ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong => continue,
+                ArgAction::Version => continue,
+                ArgAction::SetTrue | ArgAction::SetFalse => {
+                    vec![matches.get_flag(id).to_string()]
+                }
+                ArgAction::Count => vec![matches.get_count(id).to_string()],
+                _ => raw(matches, id),
+            };
+            values.insert(id.to_string(), value);
+        }
+        let subcommand = matches.subcommand().map(|(name, sub)| {
+            let args = match command.find_subcommand(name) {
+                Some(command) => Self::from_matches(command, sub),
+                None => Self {
+                    values: BTreeMap::from([(EXTERNAL.to_string(), raw(sub, EXTERNAL))]),
+                    subcommand: None,
+                },
+            };
+            (name.to_string(), Box::new(args))
+        });
+        Self { values, subcommand }
+    }
+
+    /// Whether the argument `id` was given or has a default
+    pub fn contains(&self, id: &str) -> bool {
+        self.values.contains_key(id)
+    }
+
+    /// Whether the switch `id` is on; counters are on from one
+    pub fn flag(&self, id: &str) -> bool {
+        self.value(id)
+            .is_some_and(|value| value == "true" || value.parse::<u8>().is_ok_and(|n| n > 0))
+    }
+
+    /// How often the counter `id` was given
+    pub fn count(&self, id: &str) -> u8 {
+        self.get(id).ok().flatten().unwrap_or(0)
+    }
+
+    /// The first value of `id`
+    pub fn value(&self, id: &str) -> Option<&str> {
+        self.values(id).first().map(String::as_str)
+    }
+
+    /// Every value of `id`, in the order given
+    pub fn values(&self, id: &str) -> &[String] {
+        self.values.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The first value of `id` parsed as `T`
+    pub fn get<T>(&self, id: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.value(id)
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid value '{}' for {}", value, id))
+            })
+            .transpose()
+    }
+
+    /// The first value of `id` parsed as `T`, failing when there is none
+    pub fn require<T>(&self, id: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.get(id)?
+            .with_context(|| format!("Missing a value for {}", id))
+    }
+
+    /// Every value of `id` parsed as `T`
+    pub fn get_many<T>(&self, id: &str) -> Result<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.values(id)
+            .iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid value '{}' for {}", value, id))
+            })
+            .collect()
+    }
+
+    /// The subcommand given and its arguments
+    pub fn subcommand(&self) -> Option<(&str, &PluginArgs)> {
+        self.subcommand
+            .as_ref()
+            .map(|(name, args)| (name.as_str(), args.as_ref()))
+    }
+}
+
+fn raw(matches: &ArgMatches, id: &str) -> Vec<String> {
+    matches
+        .get_raw(id)
+        .map(|values| {
+            values
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
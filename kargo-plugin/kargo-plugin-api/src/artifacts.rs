@@ -0,0 +1,159 @@
+//! Output of the commands a run executes, kept for later
+//!
+//! Post-commands, verification builds and project checks print far more than
+//! a report can hold, and what they print is gone with the terminal
+//! scrollback. Their stdout and stderr are written instead to the project's
+//! `.kargo/runs/<run id>/`, one pair of files per command, and reports refer
+//! to them with a [`CapturedOutput`]. Every command of one kargo process
+//! shares the run id, so a run's output is found in one directory per
+//! project; only the newest [`KEEP_RUNS`] of those are kept.
+//!
+//! ```no_run
+//! # use kargo_plugin_api::artifacts::RunArtifacts;
+//! # use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+//! # fn check(project: &std::path::Path) -> anyhow::Result<()> {
+//! let command = CargoCommand::new("check").current_dir(project);
+//! let output = cargo_runner::run(&command)?;
+//! let captured = RunArtifacts::for_project(project).capture_cargo(&command, &output)?;
+//! println!("cargo check printed {}", captured.stderr.display());
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::WriteGuard;
+use crate::cargo_runner::{CargoCommand, CargoOutput};
+
+/// Location of the runs directory relative to the project root
+pub const RUNS_DIR: &str = ".kargo/runs";
+
+/// Number of runs kept per project before the oldest are pruned
+pub const KEEP_RUNS: usize = 20;
+
+/// Longest command name kept in the file names of its output
+const MAX_NAME_LEN: usize = 40;
+
+/// Identifies this process's run; zero-padded so runs sort chronologically
+static RUN_ID: LazyLock<String> = LazyLock::new(|| {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    format!("{:012}-{}", now, std::process::id())
+});
+
+/// Commands captured by this process, numbering their files in order
+static CAPTURED: AtomicUsize = AtomicUsize::new(0);
+
+/// The id of this process's run
+pub fn run_id() -> &'static str {
+    &RUN_ID
+}
+
+/// Where the output of one command was saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedOutput {
+    pub command: String,
+    /// Exit code; `None` when the command was killed or couldn't start
+    pub exit_code: Option<i32>,
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+}
+
+/// The artifacts directory of this run in one project
+#[derive(Debug, Clone)]
+pub struct RunArtifacts {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl RunArtifacts {
+    pub fn for_project(root: &Path) -> Self {
+        let root = root.join(RUNS_DIR);
+        Self {
+            dir: root.join(run_id()),
+            root,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Save what `command` printed. The first capture of the run creates
+    /// its directory and prunes the oldest runs of the project.
+    pub fn capture(
+        &self,
+        command: &str,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        stderr: &[u8],
+    ) -> Result<CapturedOutput> {
+        let guard = WriteGuard::current();
+        if !self.dir.is_dir() {
+            guard.create_dir_all(&self.dir)?;
+            prune(&self.root);
+        }
+        let number = CAPTURED.fetch_add(1, Ordering::Relaxed) + 1;
+        let base = format!("{:03}-{}", number, file_name(command));
+        let captured = CapturedOutput {
+            command: command.to_string(),
+            exit_code,
+            stdout: self.dir.join(format!("{}.stdout", base)),
+            stderr: self.dir.join(format!("{}.stderr", base)),
+        };
+        guard
+            .write(&captured.stdout, stdout)
+            .and_then(|()| guard.write(&captured.stderr, stderr))
+            .with_context(|| format!("Failed to save the output of {}", command))?;
+        Ok(captured)
+    }
+
+    /// Save what a cargo invocation printed
+    pub fn capture_cargo(
+        &self,
+        command: &CargoCommand,
+        output: &CargoOutput,
+    ) -> Result<CapturedOutput> {
+        self.capture(
+            &command.to_string(),
+            output.status.and_then(|status| status.code()),
+            &output.stdout,
+            output.stderr.as_bytes(),
+        )
+    }
+}
+
+/// `cargo check --all-targets` as `cargo-check---all-targets`, cut short
+fn file_name(command: &str) -> String {
+    command
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(MAX_NAME_LEN)
+        .collect()
+}
+
+/// Drop the oldest runs so at most [`KEEP_RUNS`] remain
+fn prune(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let mut runs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    runs.sort();
+    let excess = runs.len().saturating_sub(KEEP_RUNS);
+    for run in &runs[..excess] {
+        if let Err(e) = WriteGuard::current().remove_dir_all(run) {
+            log::warn!("Failed to prune {}: {}", run.display(), e);
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Running `cargo` on a plugin's behalf
+//!
+//! Plugins build, check and document projects they don't own: kargo-walk
+//! checks every indexed project, mddoc runs rustdoc and kargo-upgrade
+//! verifies bumped requirements. A [`CargoCommand`] describes one such
+//! invocation — subcommand, toolchain, environment, target directory and
+//! timeout — and a [`CargoRunner`] executes it. With
+//! [`CargoCommand::json_messages`] the compiler diagnostics cargo prints with
+//! `--message-format=json` come back parsed as [`CompilerMessage`]s.
+//!
+//! [`ProcessRunner`] spawns the real cargo. Code that accepts an
+//! `Arc<dyn CargoRunner>` can be tested with a runner returning canned
+//! output instead.
+
+use anyhow::{Context, Result, bail};
+use cargo_metadata::Message;
+use cargo_metadata::diagnostic::DiagnosticLevel;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::Severity;
+
+/// How often a command with a timeout is polled for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// One `cargo` invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoCommand {
+    /// Rustup toolchain passed as `+<toolchain>`
+    pub toolchain: Option<String>,
+    /// The subcommand followed by its arguments
+    pub args: Vec<String>,
+    /// Where cargo runs; the current directory when `None`
+    pub current_dir: Option<PathBuf>,
+    /// Variables set for cargo on top of the inherited environment
+    pub env: Vec<(String, OsString)>,
+    /// Inherited variables removed before cargo starts
+    pub env_remove: Vec<String>,
+    /// Kill cargo once it ran this long
+    pub timeout: Option<Duration>,
+    /// Whether cargo prints JSON messages, which are parsed into
+    /// [`CargoOutput::messages`]
+    pub json_messages: bool,
+}
+
+impl CargoCommand {
+    pub fn new(subcommand: impl Into<String>) -> Self {
+        Self {
+            toolchain: None,
+            args: vec![subcommand.into()],
+            current_dir: None,
+            env: Vec::new(),
+            env_remove: Vec::new(),
+            timeout: None,
+            json_messages: false,
+        }
+    }
+
+    /// The subcommand, e.g. `check`
+    pub fn subcommand(&self) -> &str {
+        &self.args[0]
+    }
+
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.toolchain = Some(toolchain.into());
+        self
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<OsString>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn env_remove(mut self, key: impl Into<String>) -> Self {
+        self.env_remove.push(key.into());
+        self
+    }
+
+    /// Build in `target_dir` through `CARGO_TARGET_DIR`; `None` keeps cargo's
+    /// own choice
+    pub fn target_dir(self, target_dir: Option<&Path>) -> Self {
+        match target_dir {
+            Some(target_dir) => self.env("CARGO_TARGET_DIR", target_dir),
+            None => self,
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Ask for `--message-format=json` and parse the compiler messages
+    pub fn json_messages(mut self) -> Self {
+        if !self.json_messages {
+            self.json_messages = true;
+            self.args.push("--message-format=json".to_string());
+        }
+        self
+    }
+
+    /// Where cargo runs, for messages
+    fn location(&self) -> String {
+        self.current_dir
+            .as_deref()
+            .map(|dir| format!(" in {}", dir.display()))
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for CargoCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cargo")?;
+        if let Some(toolchain) = &self.toolchain {
+            write!(f, " +{}", toolchain)?;
+        }
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a finished (or killed) cargo invocation left behind
+#[derive(Debug, Clone, Default)]
+pub struct CargoOutput {
+    /// Exit status; `None` when cargo was killed after its timeout
+    pub status: Option<ExitStatus>,
+    pub stdout: Vec<u8>,
+    pub stderr: String,
+    /// Compiler messages, for commands with [`CargoCommand::json_messages`]
+    pub messages: Vec<CompilerMessage>,
+    pub timed_out: bool,
+}
+
+impl CargoOutput {
+    pub fn success(&self) -> bool {
+        self.status.is_some_and(|status| status.success())
+    }
+
+    /// The compiler messages of error severity
+    pub fn errors(&self) -> impl Iterator<Item = &CompilerMessage> {
+        self.messages
+            .iter()
+            .filter(|message| message.level == Severity::Error)
+    }
+
+    /// Turn a failed or timed out run of `command` into an error carrying
+    /// cargo's stderr
+    pub fn ensure_success(&self, command: &CargoCommand) -> Result<()> {
+        if self.timed_out {
+            bail!(
+                "cargo {} timed out{} after {}s",
+                command.subcommand(),
+                command.location(),
+                command.timeout.unwrap_or_default().as_secs()
+            );
+        }
+        if !self.success() {
+            bail!(
+                "cargo {} failed{}: {}",
+                command.subcommand(),
+                command.location(),
+                self.stderr.trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A diagnostic rustc reported while cargo built a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerMessage {
+    /// Package id of the crate being compiled
+    pub package_id: String,
+    pub level: Severity,
+    /// Lint or error code, e.g. `E0433`
+    pub code: Option<String>,
+    pub message: String,
+    /// The message as rustc would have printed it
+    pub rendered: Option<String>,
+    /// Primary span, relative to the workspace root
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl CompilerMessage {
+    /// The rendered message, or the bare one when rustc didn't render it
+    pub fn text(&self) -> &str {
+        self.rendered.as_deref().unwrap_or(&self.message)
+    }
+}
+
+/// Parse the compiler messages out of cargo's `--message-format=json` output,
+/// skipping artifacts, build script output and lines that aren't JSON
+pub fn parse_messages(stdout: &[u8]) -> Vec<CompilerMessage> {
+    Message::parse_stream(stdout)
+        .filter_map(|message| match message.ok()? {
+            Message::CompilerMessage(message) => Some(message),
+            _ => None,
+        })
+        .map(|message| {
+            let diagnostic = message.message;
+            let span = diagnostic.spans.iter().find(|span| span.is_primary);
+            CompilerMessage {
+                package_id: message.package_id.repr,
+                level: match diagnostic.level {
+                    DiagnosticLevel::Ice | DiagnosticLevel::Error => Severity::Error,
+                    DiagnosticLevel::Warning => Severity::Warning,
+                    _ => Severity::Note,
+                },
+                code: diagnostic.code.as_ref().map(|code| code.code.clone()),
+                message: diagnostic.message.clone(),
+                rendered: diagnostic.rendered.clone(),
+                file: span.map(|span| PathBuf::from(&span.file_name)),
+                line: span.map(|span| span.line_start),
+                column: span.map(|span| span.column_start),
+            }
+        })
+        .collect()
+}
+
+/// Executes [`CargoCommand`]s
+pub trait CargoRunner: Send + Sync {
+    fn run(&self, command: &CargoCommand) -> Result<CargoOutput>;
+}
+
+/// Runs cargo as a child process
+#[derive(Debug, Clone)]
+pub struct ProcessRunner {
+    program: OsString,
+}
+
+impl Default for ProcessRunner {
+    /// The cargo named by `CARGO`, as set when running under cargo, or the
+    /// one on the `PATH`
+    fn default() -> Self {
+        Self {
+            program: std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()),
+        }
+    }
+}
+
+impl ProcessRunner {
+    /// Run `program` in place of cargo
+    pub fn with_program(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+}
+
+impl CargoRunner for ProcessRunner {
+    fn run(&self, command: &CargoCommand) -> Result<CargoOutput> {
+        let mut process = Command::new(&self.program);
+        if let Some(toolchain) = &command.toolchain {
+            process.arg(format!("+{}", toolchain));
+        }
+        process
+            .args(&command.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &command.current_dir {
+            process.current_dir(dir);
+        }
+        for key in &command.env_remove {
+            process.env_remove(key);
+        }
+        process.envs(command.env.iter().map(|(key, value)| (key, value)));
+
+        log::debug!("Running {}{}", command, command.location());
+        let mut child = process
+            .spawn()
+            .with_context(|| format!("Failed to run {}{}", command, command.location()))?;
+        // Drain both pipes while waiting so a chatty build can't fill one
+        // and block
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+        let (status, timed_out) = wait(&mut child, command.timeout)
+            .with_context(|| format!("Failed to wait for {}", command))?;
+
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&stderr.join().unwrap_or_default()).into_owned();
+        let messages = if command.json_messages {
+            parse_messages(&stdout)
+        } else {
+            Vec::new()
+        };
+        Ok(CargoOutput {
+            status,
+            stdout,
+            stderr,
+            messages,
+            timed_out,
+        })
+    }
+}
+
+/// Run `command` with the cargo on the `PATH`
+pub fn run(command: &CargoCommand) -> Result<CargoOutput> {
+    ProcessRunner::default().run(command)
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// Wait for `child`, killing it once `timeout` passed
+fn wait(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<(Option<ExitStatus>, bool)> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(|status| (Some(status), false));
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((Some(status), false));
+        }
+        if Instant::now() >= deadline {
+            // Already exited between the poll and the kill is fine
+            let _ = child.kill();
+            child.wait()?;
+            return Ok((None, true));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
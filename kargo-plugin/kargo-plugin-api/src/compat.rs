@@ -0,0 +1,135 @@
+//! Version constraints between kargo and its plugins
+//!
+//! The host calls native plugins through [`PluginCommand`]'s vtable, which
+//! changes shape as the trait grows. A plugin built against an older copy of
+//! this crate doesn't fail to load, it crashes on the first call that lands in
+//! the wrong slot. Native plugins therefore export the [`API_VERSION`] they
+//! were built against with [`export_plugin_compat!`], and the host refuses
+//! those outside [`MIN_API_VERSION`]`..=`[`API_VERSION`] before creating them.
+//!
+//! The other way round, a plugin may name the kargo versions it works with as
+//! a semver requirement, for features it relies on the host for:
+//!
+//! ```ignore
+//! kargo_plugin_api::export_plugin_compat!(kargo = ">=0.4, <0.6");
+//! ```
+//!
+//! WASM plugins talk to the host in JSON rather than through the vtable, so
+//! they only declare the kargo requirement, as `requires_kargo` in their
+//! metadata. Either way, a plugin that doesn't fit is skipped with an
+//! [`Incompatible`] saying why.
+//!
+//! [`PluginCommand`]: crate::PluginCommand
+
+use semver::{Prerelease, Version, VersionReq};
+use std::ffi::c_char;
+use std::fmt;
+
+/// Version of the plugin API in this crate; bumped whenever
+/// [`PluginCommand`](crate::PluginCommand) or the types it passes change
+/// layout
+pub const API_VERSION: u32 = 2;
+
+/// Oldest API version the host still loads plugins built against
+pub const MIN_API_VERSION: u32 = 2;
+
+/// Export `kargo_plugin_api_version`, returning [`API_VERSION`]
+pub type ApiVersionFn = extern "C" fn() -> u32;
+
+/// Optional export `kargo_plugin_requires_kargo`, returning the kargo
+/// requirement as a NUL-terminated string
+pub type RequiresKargoFn = extern "C" fn() -> *const c_char;
+
+/// Why the host refuses a plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatible {
+    /// Built before plugins exported their API version
+    Unversioned,
+    /// Built against an API older than [`MIN_API_VERSION`]
+    OldApi(u32),
+    /// Built against an API newer than the host's
+    NewApi(u32),
+    /// The plugin's kargo requirement doesn't match the running kargo
+    Kargo { requires: String, host: String },
+    /// The plugin's kargo requirement isn't a semver requirement
+    InvalidRequirement(String),
+}
+
+impl fmt::Display for Incompatible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unversioned => write!(
+                f,
+                "built against a kargo-plugin-api without API versions; rebuild it against plugin API {}",
+                API_VERSION
+            ),
+            Self::OldApi(version) => write!(
+                f,
+                "built against plugin API {}, but this kargo loads {} to {}; rebuild it against the current kargo-plugin-api",
+                version, MIN_API_VERSION, API_VERSION
+            ),
+            Self::NewApi(version) => write!(
+                f,
+                "built against plugin API {}, newer than this kargo's {}; upgrade kargo",
+                version, API_VERSION
+            ),
+            Self::Kargo { requires, host } => {
+                write!(f, "requires kargo {}, but this is kargo {}", requires, host)
+            }
+            Self::InvalidRequirement(requires) => {
+                write!(f, "declares an invalid kargo requirement `{}`", requires)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Incompatible {}
+
+/// Check the API version a native plugin exports; `None` if it exports none
+pub fn check_api(version: Option<u32>) -> Result<(), Incompatible> {
+    match version {
+        None => Err(Incompatible::Unversioned),
+        Some(version) if version < MIN_API_VERSION => Err(Incompatible::OldApi(version)),
+        Some(version) if version > API_VERSION => Err(Incompatible::NewApi(version)),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Check a plugin's kargo requirement against the `host` version
+pub fn check_kargo(requires: &str, host: &str) -> Result<(), Incompatible> {
+    let req = VersionReq::parse(requires)
+        .map_err(|_| Incompatible::InvalidRequirement(requires.to_string()))?;
+    let incompatible = || Incompatible::Kargo {
+        requires: requires.to_string(),
+        host: host.to_string(),
+    };
+    let mut version = Version::parse(host).map_err(|_| incompatible())?;
+    // Development builds satisfy what the release they lead to does
+    version.pre = Prerelease::EMPTY;
+    if req.matches(&version) {
+        Ok(())
+    } else {
+        Err(incompatible())
+    }
+}
+
+/// Export the plugin API version this plugin is built against, and
+/// optionally the kargo versions it works with, for the host to check before
+/// it loads the plugin
+#[macro_export]
+macro_rules! export_plugin_compat {
+    () => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn kargo_plugin_api_version() -> u32 {
+            $crate::compat::API_VERSION
+        }
+    };
+    (kargo = $requires:literal) => {
+        $crate::export_plugin_compat!();
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn kargo_plugin_requires_kargo() -> *const ::std::ffi::c_char {
+            concat!($requires, "\0").as_ptr().cast()
+        }
+    };
+}
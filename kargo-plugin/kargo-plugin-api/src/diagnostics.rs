@@ -0,0 +1,171 @@
+//! Structured diagnostics reported by plugins
+//!
+//! A plugin declares what it can report through
+//! [`PluginCommand::diagnostics_schema`](crate::PluginCommand::diagnostics_schema)
+//! and reports findings into the [`DiagnosticSink`] on its
+//! [`ExecutionContext`](crate::ExecutionContext). Plugins that only print text
+//! can instead declare output patterns, which the host applies to their output.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "note" | "info" | "help" => Ok(Severity::Note),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            other => Err(format!("unknown severity '{}'", other)),
+        }
+    }
+}
+
+/// One finding reported by a plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Stable identifier, declared in the plugin's schema (e.g. `upgrade::breakage`)
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub line: Option<usize>,
+    #[serde(default)]
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(code: impl Into<String>, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            severity,
+            message: message.into(),
+            file: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Attach a source location
+    pub fn at(
+        mut self,
+        file: impl Into<PathBuf>,
+        line: Option<usize>,
+        column: Option<usize>,
+    ) -> Self {
+        self.file = Some(file.into());
+        self.line = line;
+        self.column = column;
+        self
+    }
+}
+
+/// A kind of diagnostic a plugin may report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticKind {
+    pub code: String,
+    /// Severity used when a diagnostic parsed from output doesn't carry one
+    pub severity: Severity,
+    pub description: String,
+}
+
+/// What a plugin reports, used by the host to aggregate runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiagnosticsSchema {
+    #[serde(default)]
+    pub kinds: Vec<DiagnosticKind>,
+    /// Regexes matched against each line of text output. Named groups
+    /// `code`, `severity`, `file`, `line`, `column` and `message` fill in the
+    /// diagnostic; `code` may be omitted when the schema declares one kind.
+    #[serde(default)]
+    pub output_patterns: Vec<String>,
+}
+
+impl DiagnosticsSchema {
+    pub fn kind(&self, code: &str) -> Option<&DiagnosticKind> {
+        self.kinds.iter().find(|k| k.code == code)
+    }
+
+    /// Parse diagnostics out of plugin output using the declared patterns.
+    ///
+    /// Invalid patterns are skipped; lines matching no pattern are ignored.
+    pub fn parse_output(&self, output: &str) -> Vec<Diagnostic> {
+        let patterns: Vec<Regex> = self
+            .output_patterns
+            .iter()
+            .filter_map(|p| Regex::new(p).ok())
+            .collect();
+        if patterns.is_empty() {
+            return Vec::new();
+        }
+
+        output
+            .lines()
+            .filter_map(|line| {
+                patterns
+                    .iter()
+                    .find_map(|re| re.captures(line))
+                    .and_then(|caps| self.diagnostic_from(&caps, line))
+            })
+            .collect()
+    }
+
+    fn diagnostic_from(&self, caps: &regex::Captures<'_>, line: &str) -> Option<Diagnostic> {
+        let group = |name| caps.name(name).map(|m| m.as_str());
+        let code = match group("code") {
+            Some(code) => code.to_string(),
+            None if self.kinds.len() == 1 => self.kinds[0].code.clone(),
+            None => return None,
+        };
+        let severity = group("severity")
+            .and_then(|s| s.parse().ok())
+            .or_else(|| self.kind(&code).map(|k| k.severity))
+            .unwrap_or(Severity::Warning);
+        Some(Diagnostic {
+            severity,
+            message: group("message").unwrap_or(line).trim().to_string(),
+            file: group("file").map(PathBuf::from),
+            line: group("line").and_then(|l| l.parse().ok()),
+            column: group("column").and_then(|c| c.parse().ok()),
+            code,
+        })
+    }
+}
+
+/// Collects the diagnostics a plugin reports during one run.
+///
+/// Clones share the same buffer, so the host keeps one and hands the other to
+/// the plugin through its [`ExecutionContext`](crate::ExecutionContext).
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticSink {
+    inner: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl DiagnosticSink {
+    pub fn report(&self, diagnostic: Diagnostic) {
+        self.lock().push(diagnostic);
+    }
+
+    /// Remove and return everything reported so far
+    pub fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Diagnostic>> {
+        // A plugin panicking mid-report leaves the Vec intact, so keep going
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,188 @@
+//! Host events plugins can react to during their run
+//!
+//! The host forwards what happens in a kargo session — files it wrote,
+//! dependencies bumped, rollbacks, commands finishing — into the
+//! [`HostEvents`] on every plugin's
+//! [`ExecutionContext`](crate::ExecutionContext). Plugins can publish there
+//! too, so a plugin that changes files tells the ones running after or
+//! alongside it. The outdated and vulnerable dependencies a plugin publishes
+//! also go into the host's `--ci` annotations and `--status-file` report:
+//!
+//! ```no_run
+//! # use kargo_plugin_api::{ExecutionContext, HostEvent};
+//! # async fn run(ctx: ExecutionContext) {
+//! // Manifests changed earlier in this session
+//! let changed: Vec<_> = ctx
+//!     .events
+//!     .history()
+//!     .into_iter()
+//!     .filter_map(|event| match event {
+//!         HostEvent::DependencyUpdated { manifest, .. } => Some(manifest),
+//!         _ => None,
+//!     })
+//!     .collect();
+//!
+//! // And those changed from now on, until the session ends
+//! let mut subscription = ctx.events.subscribe();
+//! while let Some(event) = subscription.recv().await {
+//!     println!("{:?}", event);
+//! }
+//! # }
+//! ```
+//!
+//! [`EventSubscription::recv`] waits until the host closes the stream at the
+//! end of the session, so only await it while other work is still running;
+//! [`EventSubscription::try_recv`] never waits.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// Something that happened in the session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum HostEvent {
+    /// A file was written
+    FileModified { path: PathBuf },
+    /// A dependency requirement changed in a manifest
+    DependencyUpdated {
+        manifest: PathBuf,
+        name: String,
+        from: String,
+        to: String,
+    },
+    /// Files were restored from the backup in `backup`; each restored file
+    /// is also a [`HostEvent::FileModified`]
+    RolledBack { backup: PathBuf },
+    /// A kargo command or plugin finished
+    CommandFinished { command: String, success: bool },
+    /// A newer release of a dependency was found but not applied, e.g. by a
+    /// dry run
+    DependencyOutdated {
+        manifest: PathBuf,
+        name: String,
+        current: String,
+        latest: String,
+    },
+    /// A dependency is affected by a security advisory
+    VulnerableDependency {
+        manifest: PathBuf,
+        name: String,
+        version: String,
+        advisory: String,
+    },
+}
+
+#[derive(Debug, Default)]
+struct EventLog {
+    events: Vec<HostEvent>,
+    closed: bool,
+    wakers: Vec<Waker>,
+}
+
+/// The session's event stream, shared by the host and every plugin
+#[derive(Debug, Clone, Default)]
+pub struct HostEvents {
+    inner: Arc<Mutex<EventLog>>,
+}
+
+impl HostEvents {
+    /// Add an event for every subscriber; ignored once the stream is closed
+    pub fn publish(&self, event: HostEvent) {
+        let wakers = {
+            let mut log = self.lock();
+            if log.closed {
+                return;
+            }
+            log.events.push(event);
+            std::mem::take(&mut log.wakers)
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Every event published in the session so far
+    pub fn history(&self) -> Vec<HostEvent> {
+        self.lock().events.clone()
+    }
+
+    /// Receive the events published from now on
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription {
+            events: self.clone(),
+            next: self.lock().events.len(),
+        }
+    }
+
+    /// End the stream: pending and later [`EventSubscription::recv`] calls
+    /// return `None` once the events published before are received
+    pub fn close(&self) {
+        let wakers = {
+            let mut log = self.lock();
+            log.closed = true;
+            std::mem::take(&mut log.wakers)
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Whether [`Self::close`] was called
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    fn lock(&self) -> MutexGuard<'_, EventLog> {
+        // Events are only ever appended, so a poisoned log is still whole
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A reader of [`HostEvents`], from where it subscribed
+#[derive(Debug, Clone)]
+pub struct EventSubscription {
+    events: HostEvents,
+    next: usize,
+}
+
+impl EventSubscription {
+    /// The next event if one was published, without waiting
+    pub fn try_recv(&mut self) -> Option<HostEvent> {
+        let event = self.events.lock().events.get(self.next).cloned();
+        if event.is_some() {
+            self.next += 1;
+        }
+        event
+    }
+
+    /// Wait for the next event; `None` once the stream is closed
+    pub fn recv(&mut self) -> Recv<'_> {
+        Recv { subscription: self }
+    }
+}
+
+/// Future returned by [`EventSubscription::recv`]
+#[derive(Debug)]
+pub struct Recv<'a> {
+    subscription: &'a mut EventSubscription,
+}
+
+impl Future for Recv<'_> {
+    type Output = Option<HostEvent>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let EventSubscription { events, next } = &mut *self.get_mut().subscription;
+        let mut log = events.lock();
+        if let Some(event) = log.events.get(*next).cloned() {
+            *next += 1;
+            return Poll::Ready(Some(event));
+        }
+        if log.closed {
+            return Poll::Ready(None);
+        }
+        if !log.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            log.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
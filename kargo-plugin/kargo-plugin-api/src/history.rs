@@ -0,0 +1,108 @@
+//! Audit trail of applied dependency updates
+//!
+//! Each project keeps an append-only `.kargo/history.jsonl` with one
+//! [`UpgradeRecord`] per update written to one of its manifests. Plugins append
+//! to it; the host reads it back for `kargo history`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::WriteGuard;
+
+/// Location of the history file relative to the project root
+pub const HISTORY_FILE: &str = ".kargo/history.jsonl";
+
+/// One applied update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeRecord {
+    /// Registry name of the crate
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub from: String,
+    pub to: String,
+    /// Manifest the update was written to, relative to the project root
+    pub manifest: PathBuf,
+    /// Unix timestamp (seconds) of the write
+    pub timestamp: u64,
+    pub kargo_version: String,
+    /// Identifies the run that wrote the update; shared by all its records
+    pub session: String,
+    /// Rule that selected the new version, e.g. `latest`
+    pub policy: String,
+    /// User that ran kargo, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// The history file of one project
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn for_project(root: &Path) -> Self {
+        Self {
+            path: root.join(HISTORY_FILE),
+        }
+    }
+
+    /// History of the nearest project at or above `dir` that has one
+    pub fn find(dir: &Path) -> Option<Self> {
+        dir.ancestors()
+            .map(Self::for_project)
+            .find(|history| history.path.is_file())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, records: &[UpgradeRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.path.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        let mut lines = String::new();
+        for record in records {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        guard.append(&self.path, lines)
+    }
+
+    /// All records, oldest first. Lines that don't parse are skipped.
+    pub fn read(&self) -> Result<Vec<UpgradeRecord>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+        Ok(content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(n, line)| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::warn!("Skipping {}:{}: {}", self.path.display(), n + 1, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Records for `crate_name`, oldest first
+    pub fn for_crate(&self, crate_name: &str) -> Result<Vec<UpgradeRecord>> {
+        let mut records = self.read()?;
+        records.retain(|record| record.crate_name == crate_name);
+        Ok(records)
+    }
+}
@@ -0,0 +1,63 @@
+//! Behavior plugins add around every command
+//!
+//! A plugin that times commands or keeps an audit log returns a [`HookSpec`]
+//! from [`PluginCommand::hooks`](crate::PluginCommand::hooks). The host then
+//! adds the plugin's flags to `kargo` itself and calls
+//! [`PluginCommand::before_command`](crate::PluginCommand::before_command)
+//! and [`PluginCommand::after_command`](crate::PluginCommand::after_command)
+//! around each command:
+//!
+//! ```no_run
+//! # use kargo_plugin_api::{BoxFuture, ExecutionContext, Invocation};
+//! fn after_command(ctx: ExecutionContext, invocation: Invocation) -> BoxFuture {
+//!     Box::pin(async move {
+//!         if let Some(outcome) = &invocation.outcome {
+//!             println!("{} took {:?}", invocation.command, outcome.elapsed);
+//!         }
+//!         Ok(())
+//!     })
+//! }
+//! ```
+//!
+//! Hooks only take effect for plugins listed under `plugin_hooks` in the
+//! config. They run in that order before a command and in reverse order after
+//! it. An error from a hook before the command keeps it from running; errors
+//! after it are logged.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// What a plugin contributes to every command
+#[derive(Debug, Clone, Default)]
+pub struct HookSpec {
+    /// Flags added to `kargo` itself, such as `--audit-log <PATH>`. Flags
+    /// that clash with the host's or another plugin's are left out.
+    pub flags: Vec<clap::Arg>,
+}
+
+/// The command a hook runs around
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invocation {
+    /// Subcommand of `kargo` being run, such as `upgrade` or `build`
+    pub command: String,
+    /// Its arguments, after the subcommand name
+    pub args: Vec<String>,
+    /// Values of the plugin's own root flags that were given, keyed by flag
+    /// id; switches have the value `true`
+    pub flags: BTreeMap<String, Vec<String>>,
+    /// How the command ended; `None` before it runs
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The error the command failed with; `None` when it succeeded
+    pub error: Option<String>,
+    pub elapsed: Duration,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        self.error.is_none()
+    }
+}
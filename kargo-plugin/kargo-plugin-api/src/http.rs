@@ -0,0 +1,239 @@
+//! The HTTP client for everything kargo and its plugins fetch
+//!
+//! [`HttpClient`] applies the [`NetworkConfig`]: requests go through the
+//! configured proxy and trust its certificates, wait for their turn on
+//! rate-limited hosts, are retried with exponential backoff after connection
+//! errors, timeouts, 429 and 5xx responses (honoring `Retry-After`) until
+//! their retries or the run's retry budget are spent, and fail with an
+//! [`OfflineError`] without being sent when kargo is offline, unless they are
+//! for a loopback address.
+//!
+//! ```no_run
+//! # use kargo_plugin_api::http::HttpClient;
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = HttpClient::new(concat!("my-plugin/", env!("CARGO_PKG_VERSION")))?;
+//! let body = client
+//!     .send(client.get("https://crates.io/api/v1/crates/serde"))
+//!     .await?
+//!     .error_for_status()?
+//!     .text()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only available with the `http` feature.
+
+use anyhow::{Context, Result, bail};
+use reqwest::header::{AUTHORIZATION, HeaderValue, RETRY_AFTER};
+use reqwest::{
+    Certificate, ClientBuilder, IntoUrl, NoProxy, Proxy, RequestBuilder, Response, StatusCode,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::network::{NetworkConfig, OfflineError};
+use crate::profile::{Phase, Profiler};
+
+/// When each rate-limited host may next be sent a request, shared by every
+/// client in the process so the limit holds across subsystems
+static NEXT_SLOT: Mutex<Option<HashMap<String, Instant>>> = Mutex::new(None);
+
+/// Retries made by every client in the process, against
+/// [`NetworkConfig::retry_budget`]
+static RETRIES_SPENT: AtomicU32 = AtomicU32::new(0);
+static BUDGET_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// A pooled HTTP client configured by a [`NetworkConfig`]
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    config: NetworkConfig,
+}
+
+impl HttpClient {
+    /// A client for the [`NetworkConfig::current`] settings
+    pub fn new(user_agent: &str) -> Result<Self> {
+        Self::from_builder(reqwest::Client::builder().user_agent(user_agent))
+    }
+
+    /// Apply the [`NetworkConfig::current`] settings to `builder`, for
+    /// callers that tune the client further (pool sizes, default headers)
+    pub fn from_builder(builder: ClientBuilder) -> Result<Self> {
+        Self::with_config(builder, NetworkConfig::current())
+    }
+
+    /// Apply `config` instead of the installed one
+    pub fn with_config(mut builder: ClientBuilder, config: NetworkConfig) -> Result<Self> {
+        builder = builder.timeout(Duration::from_secs(config.timeout_secs));
+        if let Some(proxy) = &config.proxy {
+            let proxy = Proxy::all(proxy)
+                .with_context(|| format!("Invalid network.proxy {}", proxy))?
+                .no_proxy(match &config.no_proxy {
+                    Some(no_proxy) => NoProxy::from_string(no_proxy),
+                    None => NoProxy::from_env(),
+                });
+            builder = builder.proxy(proxy);
+        }
+        for path in &config.ca_certificates {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read certificate {}", path.display()))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid certificate {}", path.display()))?;
+            if certificates.is_empty() {
+                bail!("No certificates in {}", path.display());
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(Self {
+            client: builder.build().context("Failed to create HTTP client")?,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &NetworkConfig {
+        &self.config
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Send `request`, retrying transient failures. Error statuses that
+    /// aren't retried, or still fail after the last retry, are returned as
+    /// responses like reqwest does.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let mut request = request.build().context("Invalid request")?;
+        let url = request.url().to_string();
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let _span = Profiler::current().span(Phase::Network, &host);
+        if self.config.offline && !is_loopback(&host) {
+            return Err(OfflineError { url }.into());
+        }
+        if let Some(token) = self.config.tokens.get(&host)
+            && (request.url().scheme() == "https" || is_loopback(&host))
+            && !request.headers().contains_key(AUTHORIZATION)
+        {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .with_context(|| format!("Invalid network.tokens.{}", host))?;
+            value.set_sensitive(true);
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+
+        let mut attempt = 0;
+        loop {
+            // Streamed bodies can't be sent twice, so those get one attempt
+            let next = request
+                .try_clone()
+                .filter(|_| attempt < self.config.retries);
+            wait_for_slot(&host, &self.config).await;
+            let result = self.client.execute(request).await;
+            let backoff = || self.backoff(attempt);
+            let retry_in = match &result {
+                Ok(response) if is_transient(response.status()) => retry_after(response)
+                    .map(|delay| delay.min(self.max_backoff()))
+                    .unwrap_or_else(backoff),
+                Err(e) if e.is_connect() || e.is_timeout() => backoff(),
+                _ => return result.with_context(|| format!("Request to {} failed", url)),
+            };
+            let Some(next) = next.filter(|_| self.spend_retry()) else {
+                return result.with_context(|| format!("Request to {} failed", url));
+            };
+            log::debug!(
+                "Retrying {} in {:?} after {}",
+                url,
+                retry_in,
+                match &result {
+                    Ok(response) => response.status().to_string(),
+                    Err(e) => e.to_string(),
+                }
+            );
+            tokio::time::sleep(retry_in).await;
+            request = next;
+            attempt += 1;
+        }
+    }
+
+    /// Delay before retry number `attempt + 1`
+    fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_millis(self.config.backoff_ms)
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff())
+    }
+
+    fn max_backoff(&self) -> Duration {
+        Duration::from_secs(self.config.max_backoff_secs)
+    }
+
+    /// Take one retry from the run's budget; false once it's spent
+    fn spend_retry(&self) -> bool {
+        let budget = self.config.retry_budget.unwrap_or(u32::MAX);
+        let spent = RETRIES_SPENT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |spent| {
+            (spent < budget).then_some(spent + 1)
+        });
+        if spent.is_ok() {
+            return true;
+        }
+        if !BUDGET_WARNED.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "All {} retries of network.retry_budget spent; failing requests are no longer retried",
+                budget
+            );
+        }
+        false
+    }
+}
+
+/// Retries made so far by every client in the process
+pub fn retries_spent() -> u32 {
+    RETRIES_SPENT.load(Ordering::Relaxed)
+}
+
+/// Local services such as an embedding server stay reachable offline
+fn is_loopback(host: &str) -> bool {
+    host == "localhost"
+        || host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The delay a 429 or 503 response asks for, when given in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    Some(Duration::from_secs(seconds.trim().parse().ok()?))
+}
+
+/// Reserve the next request slot for `host` and sleep until it comes
+async fn wait_for_slot(host: &str, config: &NetworkConfig) {
+    let Some(limit) = config.rate_limit(host) else {
+        return;
+    };
+    let interval = Duration::from_secs_f64(1.0 / limit);
+    let now = Instant::now();
+    let slot = {
+        let mut slots = NEXT_SLOT.lock().unwrap_or_else(|e| e.into_inner());
+        let next = slots
+            .get_or_insert_with(HashMap::new)
+            .entry(host.to_string())
+            .or_insert(now);
+        let slot = (*next).max(now);
+        *next = slot + interval;
+        slot
+    };
+    if slot > now {
+        tokio::time::sleep(slot - now).await;
+    }
+}
@@ -0,0 +1,321 @@
+//! Panic isolation for native plugins
+//!
+//! A native plugin is a library with its own copy of the standard library,
+//! so a panic unwinding out of it reaches kargo as a foreign exception that
+//! kargo can't catch, and the process aborts. Plugins therefore catch their
+//! panics before they leave the library: `kargo_plugin_create`, as exported
+//! by [`export_plugin!`](crate::export_plugin) or `#[plugin]`, creates the
+//! plugin through [`create`] under `catch_unwind` and hands the host an
+//! [`Isolated`] plugin, which runs every call into the plugin (including each
+//! poll of the futures it returns) the same way.
+//!
+//! Each call that gets a context installs the context's
+//! [`WriteGuard`](crate::WriteGuard) first, so the plugin's own copy of
+//! [`WriteGuard::current`](crate::WriteGuard::current) follows the host's
+//! `--read-only` without every plugin installing it itself.
+//!
+//! A caught panic becomes an ordinary error for the user, and the details —
+//! plugin, version, arguments, panic message and backtrace — go to a crash
+//! report in `crashes/` below the context's
+//! [`config_dir`](crate::ExecutionContext::config_dir).
+
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Write as _};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Once;
+use std::task::{Context as TaskContext, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use clap::Command;
+
+use crate::{
+    BoxFuture, CacheSpec, DiagnosticsSchema, ExecutionContext, HelpInfo, HookSpec, Invocation,
+    PluginCommand, ServiceSpec,
+};
+
+thread_local! {
+    /// Set while this thread is executing plugin code
+    static IN_PLUGIN: Cell<bool> = const { Cell::new(false) };
+    /// Details of the last plugin panic on this thread, filled in by the hook
+    static LAST_PANIC: RefCell<Option<PluginPanic>> = const { RefCell::new(None) };
+}
+
+/// A panic caught in plugin code
+#[derive(Debug, Clone)]
+pub struct PluginPanic {
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+impl PluginPanic {
+    fn from_hook(info: &PanicHookInfo<'_>) -> Self {
+        Self {
+            message: payload_message(info.payload()),
+            location: info.location().map(|l| l.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for PluginPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} at {}", self.message, location),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for PluginPanic {}
+
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// Route panics raised in plugin code to [`LAST_PANIC`] instead of stderr;
+/// everything else still goes to the previous hook
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if IN_PLUGIN.get() {
+                LAST_PANIC.set(Some(PluginPanic::from_hook(info)));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Run `f` as plugin code, turning a panic into its details
+fn guarded<T>(f: impl FnOnce() -> T) -> Result<T, PluginPanic> {
+    install_hook();
+    LAST_PANIC.take();
+    let outer = IN_PLUGIN.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    IN_PLUGIN.set(outer);
+    result.map_err(|payload| {
+        LAST_PANIC.take().unwrap_or_else(|| PluginPanic {
+            message: payload_message(payload.as_ref()),
+            location: None,
+            backtrace: String::new(),
+        })
+    })
+}
+
+/// Create a plugin with `create` as an [`Isolated`] one; what
+/// `kargo_plugin_create` returns
+pub fn create(create: impl FnOnce() -> Box<dyn PluginCommand>) -> Result<Box<dyn PluginCommand>> {
+    let plugin = Isolated::new(create).context("Plugin panicked while it was created")?;
+    Ok(Box::new(plugin))
+}
+
+/// Polls a plugin future under [`guarded`]
+struct GuardedFuture {
+    inner: BoxFuture,
+    crash: CrashContext,
+}
+
+impl Future for GuardedFuture {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.inner;
+        match guarded(|| inner.as_mut().poll(cx)) {
+            Ok(poll) => poll,
+            Err(panic) => Poll::Ready(Err(self.crash.report(panic))),
+        }
+    }
+}
+
+/// Everything a crash report needs besides the panic itself
+#[derive(Debug, Clone)]
+struct CrashContext {
+    plugin: String,
+    version: String,
+    host_version: String,
+    args: Vec<String>,
+    crash_dir: Option<PathBuf>,
+}
+
+impl CrashContext {
+    /// Turn a panic creating or polling the future into a reported error
+    fn guard(self, future: Result<BoxFuture, PluginPanic>) -> BoxFuture {
+        match future {
+            Ok(inner) => Box::pin(GuardedFuture { inner, crash: self }),
+            Err(panic) => {
+                let error = self.report(panic);
+                Box::pin(async move { Err(error) })
+            }
+        }
+    }
+
+    /// Write the crash report and build the error shown to the user
+    fn report(&self, panic: PluginPanic) -> anyhow::Error {
+        let Some(crash_dir) = &self.crash_dir else {
+            return anyhow::anyhow!("Plugin {} crashed: {}", self.plugin, panic);
+        };
+        match self.write(crash_dir, &panic) {
+            Ok(path) => anyhow::anyhow!(
+                "Plugin {} crashed: {}\nA crash report was written to {}",
+                self.plugin,
+                panic,
+                path.display()
+            ),
+            Err(e) => anyhow::anyhow!(
+                "Plugin {} crashed: {} (failed to write crash report: {:#})",
+                self.plugin,
+                panic,
+                e
+            ),
+        }
+    }
+
+    fn write(&self, crash_dir: &Path, panic: &PluginPanic) -> Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        std::fs::create_dir_all(crash_dir)
+            .with_context(|| format!("Failed to create {}", crash_dir.display()))?;
+        let path = crash_dir.join(format!("{}-{}.txt", self.plugin, timestamp));
+
+        let mut report = String::new();
+        let _ = writeln!(report, "kargo plugin crash report");
+        let _ = writeln!(report, "plugin:   {}", self.plugin);
+        let _ = writeln!(report, "version:  {}", self.version);
+        let _ = writeln!(report, "kargo:    {}", self.host_version);
+        let _ = writeln!(report, "args:     {:?}", self.args);
+        let _ = writeln!(report, "panic:    {}", panic.message);
+        if let Some(location) = &panic.location {
+            let _ = writeln!(report, "location: {}", location);
+        }
+        let _ = writeln!(report, "\nbacktrace:\n{}", panic.backtrace);
+
+        std::fs::write(&path, report)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// A plugin whose panics are caught and reported before they leave it
+pub struct Isolated {
+    inner: Box<dyn PluginCommand>,
+    /// Described once, when the plugin is created
+    command: Command,
+}
+
+impl Isolated {
+    /// Create the plugin with `create` and describe its command, failing if
+    /// either panics
+    pub fn new(create: impl FnOnce() -> Box<dyn PluginCommand>) -> Result<Self, PluginPanic> {
+        guarded(|| {
+            let inner = create();
+            let command = inner.clap();
+            Self { inner, command }
+        })
+    }
+
+    fn crash_context(&self, ctx: Option<&ExecutionContext>) -> CrashContext {
+        CrashContext {
+            plugin: self.command.get_name().to_string(),
+            version: self
+                .command
+                .get_version()
+                .map(str::to_string)
+                .unwrap_or_else(|| "unknown".to_string()),
+            host_version: ctx.map(|ctx| ctx.host_version.clone()).unwrap_or_default(),
+            args: ctx.map(|ctx| ctx.matched_args.clone()).unwrap_or_default(),
+            crash_dir: ctx.map(|ctx| ctx.config_dir.join("crashes")),
+        }
+    }
+
+    /// The result of `f`, or `None` with a warning if it panics
+    fn describe<T>(&self, what: &str, f: impl FnOnce() -> Option<T>) -> Option<T> {
+        guarded(f).unwrap_or_else(|panic| {
+            log::warn!(
+                "Plugin {} panicked describing its {}: {}",
+                self.command.get_name(),
+                what,
+                panic
+            );
+            None
+        })
+    }
+}
+
+impl PluginCommand for Isolated {
+    fn clap(&self) -> Command {
+        self.command.clone()
+    }
+
+    fn run(&self, ctx: ExecutionContext) -> BoxFuture {
+        let crash = self.crash_context(Some(&ctx));
+        ctx.write_guard.install();
+        crash.guard(guarded(|| self.inner.run(ctx)))
+    }
+
+    fn diagnostics_schema(&self) -> Option<DiagnosticsSchema> {
+        self.describe("diagnostics", || self.inner.diagnostics_schema())
+    }
+
+    fn cache_spec(&self, ctx: &ExecutionContext) -> Option<CacheSpec> {
+        self.describe("cache inputs", || self.inner.cache_spec(ctx))
+    }
+
+    fn help_info(&self) -> Option<HelpInfo> {
+        self.describe("help", || self.inner.help_info())
+    }
+
+    fn on_load(&self) -> Result<()> {
+        let crash = self.crash_context(None);
+        guarded(|| self.inner.on_load()).unwrap_or_else(|panic| Err(crash.report(panic)))
+    }
+
+    fn on_unload(&self) {
+        if let Err(panic) = guarded(|| self.inner.on_unload()) {
+            log::warn!(
+                "Plugin {} panicked while unloading: {}",
+                self.command.get_name(),
+                panic
+            );
+        }
+    }
+
+    fn service(&self) -> Option<ServiceSpec> {
+        self.describe("service", || self.inner.service())
+    }
+
+    fn serve(&self, ctx: ExecutionContext) -> BoxFuture {
+        let crash = self.crash_context(Some(&ctx));
+        ctx.write_guard.install();
+        crash.guard(guarded(|| self.inner.serve(ctx)))
+    }
+
+    fn hooks(&self) -> Option<HookSpec> {
+        self.describe("hooks", || self.inner.hooks())
+    }
+
+    fn before_command(&self, ctx: ExecutionContext, invocation: Invocation) -> BoxFuture {
+        let crash = self.crash_context(Some(&ctx));
+        ctx.write_guard.install();
+        crash.guard(guarded(|| self.inner.before_command(ctx, invocation)))
+    }
+
+    fn after_command(&self, ctx: ExecutionContext, invocation: Invocation) -> BoxFuture {
+        let crash = self.crash_context(Some(&ctx));
+        ctx.write_guard.install();
+        crash.guard(guarded(|| self.inner.after_command(ctx, invocation)))
+    }
+}
@@ -1,19 +1,249 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::{future::Future, path::PathBuf, pin::Pin};
 
+pub mod args;
+pub mod artifacts;
+pub mod cargo_runner;
+pub mod compat;
+mod diagnostics;
+pub mod events;
+pub mod history;
+pub mod hooks;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod isolation;
+pub mod metadata;
+pub mod network;
+pub mod owners;
+pub mod plugin;
+pub mod policy;
+pub mod profile;
+pub mod resume;
+#[cfg(feature = "secrets")]
+pub mod secrets;
+pub mod service;
+pub mod settings;
+pub mod snapshot;
+pub mod write_guard;
+
+pub use args::PluginArgs;
+pub use cargo_runner::{CargoCommand, CargoOutput, CargoRunner, CompilerMessage, ProcessRunner};
+pub use compat::Incompatible;
+pub use diagnostics::*;
+pub use events::{EventSubscription, HostEvent, HostEvents};
+pub use hooks::{HookSpec, Invocation, Outcome};
+#[cfg(feature = "http")]
+pub use http::HttpClient;
+pub use network::{NetworkConfig, OfflineError};
+pub use plugin::{Plugin, PluginDefinition, StructPlugin};
+pub use profile::Profiler;
+#[cfg(feature = "secrets")]
+pub use secrets::SecretStore;
+pub use service::{RestartPolicy, ServiceSpec};
+pub use settings::PluginSettings;
+pub use write_guard::{ReadOnlyError, WriteGuard};
+
 pub type BoxFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
 
 #[derive(Debug, Clone)]
 pub struct ExecutionContext {
+    /// The plugin name followed by its arguments, as given
     pub matched_args: Vec<String>,
+    /// The arguments parsed with the plugin's command; see [`args`]
+    pub args: PluginArgs,
     pub current_dir: PathBuf,
     pub config_dir: PathBuf,
+    /// The plugin's own directory for state kept between runs; created on
+    /// first use
+    pub data_dir: PathBuf,
+    /// The plugin's directory for data it can rebuild, such as indexes
+    pub cache_dir: PathBuf,
+    /// Where the plugin reports structured diagnostics for the host
+    pub diagnostics: DiagnosticSink,
+    /// Sparse index URL from the active profile; `None` means crates.io
+    pub registry: Option<String>,
+    /// Shared `CARGO_TARGET_DIR` for the cargo builds a plugin runs on its own
+    /// behalf (checks, rustdoc, verification builds); `None` builds in each
+    /// project's own `target/`
+    pub target_dir: Option<PathBuf>,
+    /// Version of the kargo binary running the plugin
+    pub host_version: String,
+    /// Whether the plugin may write files; see [`write_guard`]
+    pub write_guard: WriteGuard,
+    /// Events of the session the plugin runs in; see [`events`]
+    pub events: HostEvents,
+    /// Proxy, retry and offline settings; see [`network`]
+    pub network: NetworkConfig,
+    /// Records the plugin's phases under its span with `--profile-run`;
+    /// see [`profile`]
+    pub profiler: Profiler,
+    /// The plugin's section of the config; see [`settings`]
+    pub settings: PluginSettings,
+}
+
+/// What a pure plugin invocation depends on, so the host can cache its result
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheSpec {
+    /// Plugin version; results cached by other versions are not reused
+    pub version: String,
+    /// Files and directories the result depends on besides the arguments.
+    /// Directories are read recursively, skipping `target/` and hidden
+    /// entries. Relative paths are resolved against the current directory.
+    pub inputs: Vec<PathBuf>,
+    /// What the invocation writes; a cached result is only reused while all
+    /// of these still exist
+    pub outputs: Vec<PathBuf>,
+}
+
+/// How a plugin presents itself in `kargo --help`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HelpInfo {
+    /// Group the command is listed under: `deps`, `docs`, `agents`, `fleet`,
+    /// or any other name for a group of its own. `None` lists it with the
+    /// uncategorized commands.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Shown in `kargo <command> --help`
+    #[serde(default)]
+    pub examples: Vec<Example>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Example {
+    /// Full command line, starting with `kargo`
+    pub command: String,
+    pub description: String,
+}
+
+impl Example {
+    pub fn new(command: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            description: description.into(),
+        }
+    }
 }
 
 pub trait PluginCommand: Send + Sync {
     fn clap(&self) -> clap::Command;
     fn run(&self, ctx: ExecutionContext) -> BoxFuture;
+
+    /// Diagnostics this plugin reports, so the host can aggregate and filter
+    /// them across runs. Plugins without structured output return `None`.
+    fn diagnostics_schema(&self) -> Option<DiagnosticsSchema> {
+        None
+    }
+
+    /// Declare the invocation in `ctx` pure over its inputs: the same
+    /// arguments and input contents always produce the same diagnostics and
+    /// outputs. The host then skips runs whose inputs are unchanged and
+    /// replays the diagnostics of the last one. `None` always runs the plugin.
+    fn cache_spec(&self, _ctx: &ExecutionContext) -> Option<CacheSpec> {
+        None
+    }
+
+    /// Category and usage examples for `kargo --help`
+    fn help_info(&self) -> Option<HelpInfo> {
+        None
+    }
+
+    /// Called once after the host loads the plugin, before it runs or
+    /// serves. An error unloads the plugin again.
+    fn on_load(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once before the host drops the plugin
+    fn on_unload(&self) {}
+
+    /// How `kargo daemon` supervises the plugin's background service; `None`
+    /// for plugins without one. See [`service`].
+    fn service(&self) -> Option<ServiceSpec> {
+        None
+    }
+
+    /// Run the background service until `ctx.events` is closed. Only called
+    /// for plugins whose [`Self::service`] returns a spec.
+    fn serve(&self, _ctx: ExecutionContext) -> BoxFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Root-level flags and hooks around every command; `None` for plugins
+    /// without any. See [`hooks`].
+    fn hooks(&self) -> Option<HookSpec> {
+        None
+    }
+
+    /// Called before each command; an error keeps the command from running.
+    /// Only called for plugins whose [`Self::hooks`] returns a spec.
+    fn before_command(&self, _ctx: ExecutionContext, _invocation: Invocation) -> BoxFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called after each command, with [`Invocation::outcome`] set
+    fn after_command(&self, _ctx: ExecutionContext, _invocation: Invocation) -> BoxFuture {
+        Box::pin(async { Ok(()) })
+    }
 }
 
+/// Export `kargo_plugin_create`, through which the host creates a native
+/// plugin; fails rather than unwinding into the host if creating it panics
+/// (see [`isolation`])
 #[allow(improper_ctypes_definitions)]
-pub type CreateFn = extern "C" fn() -> Box<dyn PluginCommand>;
+pub type CreateFn = extern "C" fn() -> Result<Box<dyn PluginCommand>>;
+
+/// Export `kargo_plugin_create`, creating the plugin `$plugin` evaluates to
+/// with its panics caught inside the plugin; see [`isolation`]
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:expr) => {
+        #[unsafe(no_mangle)]
+        #[allow(improper_ctypes_definitions)]
+        pub extern "C" fn kargo_plugin_create()
+        -> $crate::__private::anyhow::Result<::std::boxed::Box<dyn $crate::PluginCommand>> {
+            $crate::isolation::create(|| ::std::boxed::Box::new($plugin))
+        }
+    };
+}
+
+pub use log;
+
+/// What the code `#[plugin]` generates refers to, so plugins don't need to
+/// depend on the same versions themselves
+#[doc(hidden)]
+pub mod __private {
+    pub use anyhow;
+    pub use clap;
+}
+
+/// Optional export through which the host hands its logger to a native plugin.
+///
+/// A dynamically loaded plugin links its own copy of the `log` crate, so
+/// without this hook its `log::info!` calls go nowhere. Plugins opt in with
+/// [`export_host_logger!`]; records keep the plugin's module path as their
+/// target, which lets operators filter a single plugin by crate name.
+#[allow(improper_ctypes_definitions)]
+pub type InitLoggerFn = extern "C" fn(&'static dyn log::Log, log::LevelFilter);
+
+/// Install the host's logger as this plugin's global logger
+pub fn install_host_logger(logger: &'static dyn log::Log, level: log::LevelFilter) {
+    // Only fails if the plugin already installed its own logger, which wins
+    let _ = log::set_logger(logger);
+    log::set_max_level(level);
+}
+
+/// Export `kargo_plugin_init_logger` so the host can route this plugin's logs
+#[macro_export]
+macro_rules! export_host_logger {
+    () => {
+        #[unsafe(no_mangle)]
+        #[allow(improper_ctypes_definitions)]
+        pub extern "C" fn kargo_plugin_init_logger(
+            logger: &'static dyn $crate::log::Log,
+            level: $crate::log::LevelFilter,
+        ) {
+            $crate::install_host_logger(logger, level);
+        }
+    };
+}
@@ -0,0 +1,298 @@
+//! Typed view of `cargo metadata --format-version 1`
+//!
+//! Manifests say what a package asks for; cargo decides what that means.
+//! Renamed dependencies, `[target.'cfg(..)'.dependencies]`, workspace member
+//! globs, inherited dependencies and default features are all resolved by
+//! cargo here rather than guessed from the TOML, so the host and plugins see
+//! the same answer cargo itself would give.
+
+use anyhow::{Context, Result};
+use cargo_metadata::{DependencyKind, MetadataCommand};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// How much work `cargo metadata` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataMode {
+    /// Workspace packages and their declared dependencies only (`--no-deps`).
+    /// Fast and works offline.
+    #[default]
+    Declared,
+    /// Also resolve the full dependency graph and enabled features. May update
+    /// the lockfile or hit the network.
+    Resolved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepKind {
+    Normal,
+    Dev,
+    Build,
+}
+
+impl From<DependencyKind> for DepKind {
+    fn from(kind: DependencyKind) -> Self {
+        match kind {
+            DependencyKind::Development => DepKind::Dev,
+            DependencyKind::Build => DepKind::Build,
+            _ => DepKind::Normal,
+        }
+    }
+}
+
+/// Where a declared dependency comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "location")]
+pub enum DependencySource {
+    /// A registry; `None` is crates.io
+    Registry(Option<String>),
+    Git(String),
+    Path(PathBuf),
+}
+
+/// A dependency as declared in a package's manifest, after cargo has applied
+/// workspace inheritance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclaredDependency {
+    /// Name of the package in its registry
+    pub package: String,
+    /// Key used in the manifest (and `extern` name) when it differs from `package`
+    pub rename: Option<String>,
+    pub req: String,
+    pub kind: DepKind,
+    /// `cfg(..)` expression or target triple for target-specific dependencies
+    pub target: Option<String>,
+    pub optional: bool,
+    pub default_features: bool,
+    pub features: Vec<String>,
+    pub source: DependencySource,
+}
+
+impl DeclaredDependency {
+    /// The key this dependency has in the manifest
+    pub fn key(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.package)
+    }
+
+    pub fn is_registry(&self) -> bool {
+        matches!(self.source, DependencySource::Registry(_))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetInfo {
+    pub name: String,
+    /// `lib`, `bin`, `proc-macro`, `test`, `example`, `bench`, `custom-build`, ...
+    pub kinds: Vec<String>,
+    pub src_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub manifest_path: PathBuf,
+    pub edition: String,
+    pub targets: Vec<TargetInfo>,
+    /// Feature table, with `default` included when declared
+    pub features: BTreeMap<String, Vec<String>>,
+    pub dependencies: Vec<DeclaredDependency>,
+}
+
+impl PackageInfo {
+    pub fn has_target_kind(&self, kind: &str) -> bool {
+        self.targets
+            .iter()
+            .any(|target| target.kinds.iter().any(|k| k == kind))
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(Path::new("."))
+    }
+}
+
+/// An edge of the resolved graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedDependency {
+    /// Name the dependent uses in code (the rename, if any)
+    pub name: String,
+    pub package_id: String,
+    pub package: String,
+    pub version: String,
+    /// How the dependency is used, with the target it applies to, if any
+    pub kinds: Vec<(DepKind, Option<String>)>,
+}
+
+/// A package in the resolved graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedNode {
+    pub dependencies: Vec<ResolvedDependency>,
+    /// Features enabled on this package across the workspace
+    pub features: Vec<String>,
+}
+
+/// Result of one `cargo metadata` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectMetadata {
+    pub workspace_root: PathBuf,
+    /// IDs of the workspace members
+    pub members: Vec<String>,
+    /// Workspace members, plus every dependency when resolved
+    pub packages: Vec<PackageInfo>,
+    /// Resolved graph keyed by package ID; `None` in [`MetadataMode::Declared`]
+    pub resolve: Option<HashMap<String, ResolvedNode>>,
+}
+
+impl ProjectMetadata {
+    /// Run `cargo metadata` for the project owning `manifest_path`
+    pub fn load(manifest_path: &Path, mode: MetadataMode) -> Result<Self> {
+        let mut command = MetadataCommand::new();
+        command.manifest_path(manifest_path);
+        if mode == MetadataMode::Declared {
+            command.no_deps();
+        }
+        let metadata = command
+            .exec()
+            .with_context(|| format!("cargo metadata failed for {}", manifest_path.display()))?;
+        Ok(Self::from_metadata(metadata))
+    }
+
+    fn from_metadata(metadata: cargo_metadata::Metadata) -> Self {
+        let packages: Vec<PackageInfo> = metadata.packages.iter().map(package_info).collect();
+        let versions: HashMap<&str, (&str, &str)> = packages
+            .iter()
+            .map(|p| (p.id.as_str(), (p.name.as_str(), p.version.as_str())))
+            .collect();
+
+        let resolve = metadata.resolve.as_ref().map(|resolve| {
+            resolve
+                .nodes
+                .iter()
+                .map(|node| {
+                    let dependencies = node
+                        .deps
+                        .iter()
+                        .map(|dep| {
+                            let id = dep.pkg.repr.clone();
+                            let (package, version) =
+                                versions.get(id.as_str()).copied().unwrap_or_default();
+                            ResolvedDependency {
+                                name: dep.name.to_string(),
+                                package: package.to_string(),
+                                version: version.to_string(),
+                                kinds: dep
+                                    .dep_kinds
+                                    .iter()
+                                    .map(|k| {
+                                        (k.kind.into(), k.target.as_ref().map(|t| t.to_string()))
+                                    })
+                                    .collect(),
+                                package_id: id,
+                            }
+                        })
+                        .collect();
+                    let node_info = ResolvedNode {
+                        dependencies,
+                        features: node.features.iter().map(|f| f.to_string()).collect(),
+                    };
+                    (node.id.repr.clone(), node_info)
+                })
+                .collect()
+        });
+
+        Self {
+            workspace_root: metadata.workspace_root.into_std_path_buf(),
+            members: metadata
+                .workspace_members
+                .iter()
+                .map(|id| id.repr.clone())
+                .collect(),
+            packages,
+            resolve,
+        }
+    }
+
+    pub fn package(&self, id: &str) -> Option<&PackageInfo> {
+        self.packages.iter().find(|p| p.id == id)
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = &PackageInfo> {
+        self.members.iter().filter_map(|id| self.package(id))
+    }
+
+    /// The workspace member whose manifest is `manifest_path`
+    pub fn member_at(&self, manifest_path: &Path) -> Option<&PackageInfo> {
+        let wanted = manifest_path.canonicalize().ok();
+        self.members().find(|p| {
+            p.manifest_path == manifest_path || p.manifest_path.canonicalize().ok() == wanted
+        })
+    }
+
+    /// Resolved dependencies of `id`, or `None` without a resolved graph
+    pub fn resolved_dependencies(&self, id: &str) -> Option<&[ResolvedDependency]> {
+        self.resolve
+            .as_ref()?
+            .get(id)
+            .map(|node| node.dependencies.as_slice())
+    }
+
+    /// Features cargo enables on `id`, or `None` without a resolved graph
+    pub fn enabled_features(&self, id: &str) -> Option<&[String]> {
+        self.resolve
+            .as_ref()?
+            .get(id)
+            .map(|node| node.features.as_slice())
+    }
+}
+
+fn package_info(package: &cargo_metadata::Package) -> PackageInfo {
+    PackageInfo {
+        id: package.id.repr.clone(),
+        name: package.name.to_string(),
+        version: package.version.to_string(),
+        manifest_path: package.manifest_path.clone().into_std_path_buf(),
+        edition: package.edition.to_string(),
+        targets: package
+            .targets
+            .iter()
+            .map(|target| TargetInfo {
+                name: target.name.clone(),
+                kinds: target.kind.iter().map(|k| k.to_string()).collect(),
+                src_path: target.src_path.clone().into_std_path_buf(),
+            })
+            .collect(),
+        features: package
+            .features
+            .iter()
+            .map(|(name, enables)| (name.to_string(), enables.clone()))
+            .collect(),
+        dependencies: package
+            .dependencies
+            .iter()
+            .map(declared_dependency)
+            .collect(),
+    }
+}
+
+fn declared_dependency(dep: &cargo_metadata::Dependency) -> DeclaredDependency {
+    let source = dep.source.as_ref().map(|s| s.to_string());
+    let source = match (&dep.path, source) {
+        (Some(path), _) => DependencySource::Path(path.clone().into_std_path_buf()),
+        (None, Some(source)) if source.starts_with("git+") => DependencySource::Git(source),
+        _ => DependencySource::Registry(dep.registry.clone()),
+    };
+    DeclaredDependency {
+        package: dep.name.to_string(),
+        rename: dep.rename.clone(),
+        req: dep.req.to_string(),
+        kind: dep.kind.into(),
+        target: dep.target.as_ref().map(|t| t.to_string()),
+        optional: dep.optional,
+        default_features: dep.uses_default_features,
+        features: dep.features.clone(),
+        source,
+    }
+}
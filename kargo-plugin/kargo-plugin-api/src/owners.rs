@@ -0,0 +1,226 @@
+//! Who owns a project
+//!
+//! Triage across a fleet needs someone to ask about each broken or outdated
+//! project. [`project_owners`] takes the first of these that names anyone:
+//!
+//! 1. the `CODEOWNERS` file of the git repository the project is in
+//!    (`CODEOWNERS`, `.github/`, `.gitlab/` or `docs/`), where the last rule
+//!    matching the project's `Cargo.toml` wins, as on GitHub
+//! 2. the package's `authors`
+//! 3. the most frequent committers to the project directory, by
+//!    `git shortlog`
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where GitHub and GitLab look for the file, in their order
+const CODEOWNERS_PATHS: [&str; 4] = [
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+/// Committers listed when ownership comes from history
+const MAX_COMMITTERS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnerSource {
+    Codeowners,
+    Authors,
+    Git,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ownership {
+    /// `@user`, `@org/team` or email for CODEOWNERS; `Name <email>` otherwise
+    pub owners: Vec<String>,
+    pub source: OwnerSource,
+}
+
+impl Ownership {
+    /// Whether `owner` is one of the owners, ignoring case. For a
+    /// `Name <email>` owner, the name or the email alone match too.
+    pub fn includes(&self, owner: &str) -> bool {
+        self.owners
+            .iter()
+            .any(|candidate| owner_matches(candidate, owner))
+    }
+}
+
+/// Whether `candidate`, as listed by [`Ownership::owners`], is `owner`
+pub fn owner_matches(candidate: &str, owner: &str) -> bool {
+    let owner = owner.trim();
+    if candidate.eq_ignore_ascii_case(owner) {
+        return true;
+    }
+    match candidate.split_once('<') {
+        Some((name, email)) => {
+            name.trim().eq_ignore_ascii_case(owner)
+                || email.trim_end_matches('>').eq_ignore_ascii_case(owner)
+        }
+        None => false,
+    }
+}
+
+/// Owners of the project in `dir`, or `None` when nothing names any
+pub fn project_owners(dir: &Path) -> Option<Ownership> {
+    let repo = repository_root(dir);
+    let from_codeowners = repo.as_deref().and_then(|repo| {
+        let codeowners = CodeOwners::find(repo)?;
+        let relative = dir.strip_prefix(repo).ok()?.join("Cargo.toml");
+        let owners = codeowners.owners(&relative.to_string_lossy().replace('\\', "/"))?;
+        Some(owners.to_vec())
+    });
+    if let Some(owners) = from_codeowners.filter(|owners| !owners.is_empty()) {
+        return Some(Ownership {
+            owners,
+            source: OwnerSource::Codeowners,
+        });
+    }
+
+    let authors = package_authors(&dir.join("Cargo.toml"));
+    if !authors.is_empty() {
+        return Some(Ownership {
+            owners: authors,
+            source: OwnerSource::Authors,
+        });
+    }
+
+    let committers = repo.and_then(|_| top_committers(dir)).unwrap_or_default();
+    (!committers.is_empty()).then_some(Ownership {
+        owners: committers,
+        source: OwnerSource::Git,
+    })
+}
+
+/// The rules of a `CODEOWNERS` file
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<(Regex, Vec<String>)>,
+}
+
+impl CodeOwners {
+    /// The `CODEOWNERS` file of the repository rooted at `repo`, if any
+    pub fn find(repo: &Path) -> Option<Self> {
+        CODEOWNERS_PATHS
+            .iter()
+            .find_map(|path| fs::read_to_string(repo.join(path)).ok())
+            .map(|content| Self::parse(&content))
+    }
+
+    /// Parse `content`, skipping comments and patterns that can't be matched
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(|line| line.split_once(" #").map_or(line, |(rule, _)| rule).trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            // GitLab section headers, e.g. `[Docs]` or `^[Optional][2] @docs`
+            .filter(|line| !line.starts_with('[') && !line.starts_with("^["))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pattern = pattern_regex(fields.next()?)?;
+                Some((pattern, fields.map(str::to_string).collect()))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Owners of `path` (relative to the repository root, `/`-separated):
+    /// those of the last matching rule. A matching rule without owners
+    /// leaves the path unowned.
+    pub fn owners(&self, path: &str) -> Option<&[String]> {
+        let path = path.trim_start_matches('/');
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.is_match(path))
+            .map(|(_, owners)| owners.as_slice())
+    }
+}
+
+/// Translate a gitignore-style pattern into a regex over repository paths.
+/// Matching a directory matches everything below it.
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    // A slash anywhere but at the end anchors the pattern to the root
+    let anchored = pattern.trim_end_matches('/').contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = pattern.trim_end_matches('/').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str("(?:/.*)?$");
+    Regex::new(&regex).ok()
+}
+
+/// The enclosing git work tree's root
+fn repository_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+fn package_authors(manifest: &Path) -> Vec<String> {
+    let Some(manifest) = fs::read_to_string(manifest)
+        .ok()
+        .and_then(|content| content.parse::<toml_edit::DocumentMut>().ok())
+    else {
+        return Vec::new();
+    };
+    manifest
+        .get("package")
+        .and_then(|package| package.get("authors"))
+        .and_then(|authors| authors.as_array())
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(|author| author.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn top_committers(dir: &Path) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args([
+            "shortlog",
+            "--summary",
+            "--numbered",
+            "--email",
+            "HEAD",
+            "--",
+            ".",
+        ])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().split_once('\t'))
+            .map(|(_, author)| author.trim().to_string())
+            .take(MAX_COMMITTERS)
+            .collect(),
+    )
+}
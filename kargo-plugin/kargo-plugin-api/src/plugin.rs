@@ -0,0 +1,110 @@
+//! Plugins declared as structs
+//!
+//! Rather than building a [`clap::Command`] and reading each argument back
+//! out of [`ExecutionContext::args`], a plugin can be a struct whose fields
+//! are its arguments. `#[plugin]` from kargo-plugin-macros implements
+//! [`PluginDefinition`] for it: every field becomes an argument documented by
+//! its doc comment, and the command takes its description, version and
+//! authors from the crate's `Cargo.toml`. The plugin then only implements
+//! [`Plugin::run`], and receives its arguments already parsed:
+//!
+//! ```ignore
+//! use kargo_plugin_api::{ExecutionContext, Plugin};
+//!
+//! /// Greet whoever is named
+//! #[kargo_plugin_macros::plugin(name = "greet", category = "docs")]
+//! pub struct Greet {
+//!     /// Who to greet
+//!     #[arg(short = 'n', default = "world")]
+//!     name: String,
+//!     /// Greet in capitals
+//!     loud: bool,
+//!     /// Greet this many times
+//!     times: Option<u32>,
+//!     /// Files to greet too
+//!     #[arg(positional)]
+//!     files: Vec<String>,
+//! }
+//!
+//! impl Plugin for Greet {
+//!     async fn run(self, _ctx: ExecutionContext) -> anyhow::Result<()> {
+//!         println!("Hello, {}!", self.name);
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! `#[plugin]` also exports `kargo_plugin_create`, creating a
+//! [`StructPlugin`] of the type with its panics caught (see
+//! [`crate::isolation`]), and the plugin's API version (see
+//! [`crate::compat`]), so nothing else is needed for the host to load it.
+
+use anyhow::Result;
+use std::future::Future;
+use std::marker::PhantomData;
+
+use crate::{BoxFuture, ExecutionContext, HelpInfo, PluginArgs, PluginCommand};
+
+/// How a struct plugin is declared to the host and read from its arguments;
+/// implemented by `#[plugin]`
+pub trait PluginDefinition: Sized {
+    /// The command the host parses the plugin's arguments with
+    fn command() -> clap::Command;
+
+    /// The plugin with its fields set from `args`, parsed with
+    /// [`Self::command`]
+    fn from_args(args: &PluginArgs) -> Result<Self>;
+
+    /// Category and usage examples for `kargo --help`
+    fn help_info() -> Option<HelpInfo> {
+        None
+    }
+}
+
+/// A plugin whose arguments are its fields
+pub trait Plugin: PluginDefinition + Send + 'static {
+    /// Run the plugin with its fields set from the arguments it was given
+    fn run(self, ctx: ExecutionContext) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// The [`PluginCommand`] of a [`Plugin`], which the host loads
+pub struct StructPlugin<P> {
+    plugin: PhantomData<fn() -> P>,
+}
+
+impl<P: Plugin> StructPlugin<P> {
+    pub fn new() -> Self {
+        Self {
+            plugin: PhantomData,
+        }
+    }
+}
+
+impl<P: Plugin> Default for StructPlugin<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Plugin> PluginCommand for StructPlugin<P> {
+    fn clap(&self) -> clap::Command {
+        P::command()
+    }
+
+    fn run(&self, mut ctx: ExecutionContext) -> BoxFuture {
+        // Hosts that only pass the raw arguments get them parsed here
+        let parsed = (ctx.args == PluginArgs::default() && !ctx.matched_args.is_empty())
+            .then(|| PluginArgs::parse(P::command(), &ctx.matched_args));
+        Box::pin(async move {
+            if let Some(parsed) = parsed {
+                ctx.args = parsed?;
+            }
+            let plugin = P::from_args(&ctx.args)?;
+            plugin.run(ctx).await
+        })
+    }
+
+    fn help_info(&self) -> Option<HelpInfo> {
+        P::help_info()
+    }
+}
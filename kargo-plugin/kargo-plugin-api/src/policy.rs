@@ -0,0 +1,353 @@
+//! Organizational rules for dependencies
+//!
+//! A policy is a TOML file, kept in the kargo config directory for rules that
+//! apply everywhere and in a project's `.kargo/policy.toml` for its own:
+//!
+//! ```toml
+//! # Direct dependencies allowed per manifest (dev-dependencies not counted)
+//! max_dependencies = 60
+//!
+//! [[ban]]
+//! crate = "openssl"
+//! reason = "use rustls"
+//!
+//! [minimum]
+//! tokio = "1.38"
+//!
+//! [git]
+//! forbid_branches = ["main", "master"]
+//! ```
+//!
+//! Commands that touch dependencies check manifests against the policy and
+//! report each violation as a [`Diagnostic`] with a `policy::` code, so they
+//! are aggregated like any other finding. A git dependency without `branch`,
+//! `tag` or `rev` follows the repository's default branch and violates
+//! `forbid_branches` whenever that list is non-empty.
+
+use anyhow::{Context, Result, anyhow, bail};
+use semver::{Op, Version, VersionReq};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::{Diagnostic, DiagnosticKind, Severity};
+
+/// Location of a project's policy relative to its root
+pub const POLICY_FILE: &str = ".kargo/policy.toml";
+
+const BANNED: &str = "policy::banned";
+const MINIMUM_VERSION: &str = "policy::minimum-version";
+const GIT_BRANCH: &str = "policy::git-branch";
+const DEPENDENCY_COUNT: &str = "policy::dependency-count";
+
+/// A crate that must not be depended on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ban {
+    pub crate_name: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    pub bans: Vec<Ban>,
+    /// Lowest version each crate's requirement may allow
+    pub minimum: BTreeMap<String, Version>,
+    /// Branches git dependencies must not track
+    pub forbid_branches: Vec<String>,
+    pub max_dependencies: Option<usize>,
+}
+
+/// A dependency as declared in a manifest
+#[derive(Debug, Clone)]
+struct Declared {
+    /// Registry name, after `package = ...` renames
+    name: String,
+    /// Counts towards `max_dependencies`
+    counted: bool,
+    requirement: Option<String>,
+    git: Option<GitRef>,
+}
+
+#[derive(Debug, Clone)]
+struct GitRef {
+    branch: Option<String>,
+    pinned: bool,
+}
+
+impl Policy {
+    /// Parse the contents of a policy file
+    pub fn parse(content: &str) -> Result<Self> {
+        let doc: DocumentMut = content.parse()?;
+        let mut policy = Self::default();
+
+        for (key, value) in doc.iter() {
+            match key {
+                "max_dependencies" => {
+                    let max = value
+                        .as_integer()
+                        .and_then(|n| usize::try_from(n).ok())
+                        .ok_or_else(|| anyhow!("max_dependencies must be a positive integer"))?;
+                    policy.max_dependencies = Some(max);
+                }
+                "ban" => {
+                    let bans = value
+                        .as_array_of_tables()
+                        .ok_or_else(|| anyhow!("ban must be an array of tables ([[ban]])"))?;
+                    for ban in bans {
+                        let crate_name = ban
+                            .get("crate")
+                            .and_then(Item::as_str)
+                            .ok_or_else(|| anyhow!("[[ban]] entry without a crate"))?;
+                        policy.bans.push(Ban {
+                            crate_name: crate_name.to_string(),
+                            reason: ban.get("reason").and_then(Item::as_str).map(str::to_string),
+                        });
+                    }
+                }
+                "minimum" => {
+                    let table = value
+                        .as_table_like()
+                        .ok_or_else(|| anyhow!("minimum must be a table"))?;
+                    for (name, version) in table.iter() {
+                        let version = version
+                            .as_str()
+                            .ok_or_else(|| anyhow!("minimum.{} must be a version string", name))?;
+                        policy
+                            .minimum
+                            .insert(name.to_string(), parse_version(version)?);
+                    }
+                }
+                "git" => {
+                    if let Some(branches) = value.get("forbid_branches") {
+                        let branches = branches
+                            .as_array()
+                            .ok_or_else(|| anyhow!("git.forbid_branches must be an array"))?;
+                        policy.forbid_branches.extend(
+                            branches
+                                .iter()
+                                .filter_map(|b| b.as_str())
+                                .map(str::to_string),
+                        );
+                    }
+                }
+                other => bail!("unknown policy setting '{}'", other),
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Load and merge the policy files in `paths`, skipping missing ones.
+    /// Merged rules keep the stricter setting: all bans, the higher minimum,
+    /// the lower dependency cap.
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        let mut policy = Self::default();
+        for path in paths.iter().filter(|p| p.is_file()) {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let parsed = Self::parse(&content)
+                .with_context(|| format!("Invalid policy in {}", path.display()))?;
+            log::debug!("Loaded policy from {}", path.display());
+            policy.merge(parsed);
+        }
+        Ok(policy)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.bans.extend(other.bans);
+        for (name, version) in other.minimum {
+            let minimum = self.minimum.entry(name).or_insert_with(|| version.clone());
+            if version > *minimum {
+                *minimum = version;
+            }
+        }
+        self.forbid_branches.extend(other.forbid_branches);
+        self.max_dependencies = match (self.max_dependencies, other.max_dependencies) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Violations in the manifest at `path`
+    pub fn check_manifest(&self, path: &Path) -> Result<Vec<Diagnostic>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        self.check(path, &content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Violations in `content`, the manifest at `path`
+    pub fn check(&self, path: &Path, content: &str) -> Result<Vec<Diagnostic>> {
+        let doc: DocumentMut = content.parse()?;
+        let declared = declared_dependencies(&doc);
+        let mut violations = Vec::new();
+        let mut violation = |code: &str, message: String| {
+            violations.push(Diagnostic::new(code, Severity::Error, message).at(path, None, None));
+        };
+
+        for dep in &declared {
+            if let Some(ban) = self.bans.iter().find(|b| b.crate_name == dep.name) {
+                violation(
+                    BANNED,
+                    match &ban.reason {
+                        Some(reason) => format!("{} is banned: {}", dep.name, reason),
+                        None => format!("{} is banned", dep.name),
+                    },
+                );
+            }
+            if let (Some(minimum), Some(requirement)) =
+                (self.minimum.get(&dep.name), &dep.requirement)
+                && let Some(lowest) = lowest_allowed(requirement)
+                && lowest < *minimum
+            {
+                violation(
+                    MINIMUM_VERSION,
+                    format!(
+                        "{} {} allows versions below the required minimum {}",
+                        dep.name, requirement, minimum
+                    ),
+                );
+            }
+            if let Some(git) = &dep.git
+                && !git.pinned
+                && !self.forbid_branches.is_empty()
+            {
+                match &git.branch {
+                    Some(branch) if self.forbid_branches.contains(branch) => violation(
+                        GIT_BRANCH,
+                        format!(
+                            "{} tracks the {} branch of its repository",
+                            dep.name, branch
+                        ),
+                    ),
+                    Some(_) => {}
+                    None => violation(
+                        GIT_BRANCH,
+                        format!(
+                            "{} tracks the default branch of its repository; pin a tag or rev",
+                            dep.name
+                        ),
+                    ),
+                }
+            }
+        }
+
+        if let Some(max) = self.max_dependencies {
+            let count = declared.iter().filter(|dep| dep.counted).count();
+            if count > max {
+                violation(
+                    DEPENDENCY_COUNT,
+                    format!("{} dependencies exceed the limit of {}", count, max),
+                );
+            }
+        }
+        Ok(violations)
+    }
+}
+
+/// Kinds of the diagnostics policy checks report, for plugin schemas
+pub fn diagnostic_kinds() -> Vec<DiagnosticKind> {
+    [
+        (BANNED, "Depends on a banned crate"),
+        (MINIMUM_VERSION, "Allows versions below the policy minimum"),
+        (GIT_BRANCH, "Git dependency tracks a forbidden branch"),
+        (DEPENDENCY_COUNT, "More dependencies than the policy allows"),
+    ]
+    .into_iter()
+    .map(|(code, description)| DiagnosticKind {
+        code: code.to_string(),
+        severity: Severity::Error,
+        description: description.to_string(),
+    })
+    .collect()
+}
+
+/// Every dependency in the normal, dev and build tables, including
+/// target-specific ones. `[workspace.dependencies]` only defines versions for
+/// members, so it is checked for bans, minimums and git refs but not counted.
+fn declared_dependencies(doc: &DocumentMut) -> Vec<Declared> {
+    let mut tables = vec![doc.as_item()];
+    if let Some(targets) = doc.get("target").and_then(Item::as_table_like) {
+        tables.extend(targets.iter().map(|(_, target)| target));
+    }
+
+    let mut declared = Vec::new();
+    for table in tables {
+        for (name, counted) in [
+            ("dependencies", true),
+            ("build-dependencies", true),
+            ("dev-dependencies", false),
+        ] {
+            if let Some(deps) = table.get(name).and_then(Item::as_table_like) {
+                collect(deps, counted, &mut declared);
+            }
+        }
+    }
+    if let Some(deps) = doc
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(Item::as_table_like)
+    {
+        collect(deps, false, &mut declared);
+    }
+    declared
+}
+
+fn collect(deps: &dyn TableLike, counted: bool, declared: &mut Vec<Declared>) {
+    for (key, spec) in deps.iter() {
+        let dep = match spec.as_table_like() {
+            Some(spec) => {
+                let get = |field| spec.get(field).and_then(Item::as_str).map(str::to_string);
+                Declared {
+                    name: get("package").unwrap_or_else(|| key.to_string()),
+                    counted,
+                    requirement: get("version"),
+                    git: spec.contains_key("git").then(|| GitRef {
+                        branch: get("branch"),
+                        pinned: spec.contains_key("tag") || spec.contains_key("rev"),
+                    }),
+                }
+            }
+            None => Declared {
+                name: key.to_string(),
+                counted,
+                requirement: spec.as_str().map(str::to_string),
+                git: None,
+            },
+        };
+        declared.push(dep);
+    }
+}
+
+/// `1.38` or `1.38.0` as a full version
+fn parse_version(version: &str) -> Result<Version> {
+    let mut parts = version.trim().split('.');
+    let mut next = || -> Result<u64> {
+        Ok(match parts.next() {
+            Some(part) => part
+                .parse()
+                .with_context(|| format!("invalid version '{}'", version))?,
+            None => 0,
+        })
+    };
+    Ok(Version::new(next()?, next()?, next()?))
+}
+
+/// The lowest version a requirement accepts, ignoring pre-releases
+pub fn lowest_allowed(requirement: &str) -> Option<Version> {
+    let req = VersionReq::parse(requirement).ok()?;
+    req.comparators
+        .iter()
+        .filter(|c| {
+            matches!(
+                c.op,
+                Op::Exact | Op::Greater | Op::GreaterEq | Op::Tilde | Op::Caret | Op::Wildcard
+            )
+        })
+        .map(|c| Version::new(c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0)))
+        .max()
+        .or(Some(Version::new(0, 0, 0)))
+}
@@ -0,0 +1,279 @@
+//! Where a command spends its time, for `kargo --profile-run`
+//!
+//! The host creates a [`Profiler`] for the command it runs and installs it;
+//! code on the hot paths opens a [`Profiler::span`] per phase, such as
+//! [`HttpClient::send`](crate::HttpClient::send) for every request. Without
+//! `--profile-run` the installed profiler is disabled and spans cost nothing
+//! but a clock read.
+//!
+//! ```no_run
+//! # use kargo_plugin_api::profile::{Phase, Profiler};
+//! # fn find_manifests() {}
+//! let manifests = {
+//!     let _span = Profiler::current().span(Phase::Discovery, "manifests");
+//!     find_manifests()
+//! };
+//! ```
+//!
+//! Spans form stacks (`kargo upgrade;plugin;upgrade;network;index.crates.io`)
+//! that [`Profiler::folded`] writes in the folded format `inferno` and
+//! `flamegraph.pl` read. Native plugins link their own copy of this crate, so
+//! they [`install`](Profiler::install) the profiler from
+//! [`ExecutionContext::profiler`] when they start, as with the network
+//! config; their spans then nest under the host's span for the plugin.
+//!
+//! [`ExecutionContext::profiler`]: crate::ExecutionContext::profiler
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+static CURRENT: RwLock<Option<Profiler>> = RwLock::new(None);
+
+/// What a span was spent on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Finding manifests, projects and files to work on
+    Discovery,
+    /// Reading and parsing manifests and lockfiles
+    Parsing,
+    /// HTTP requests
+    Network,
+    /// Writing files
+    Writes,
+    /// A plugin running, outside the phases it reports itself
+    Plugin,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Discovery => "discovery",
+            Self::Parsing => "parsing",
+            Self::Network => "network",
+            Self::Writes => "writes",
+            Self::Plugin => "plugin",
+        }
+    }
+}
+
+/// A finished span
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// Frames from the command down to the span
+    pub stack: Vec<String>,
+    /// `None` for the command itself
+    pub phase: Option<Phase>,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+struct Recording {
+    started: Instant,
+    spans: Vec<Span>,
+}
+
+/// Records spans below a stack of frames; disabled by default
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    frames: Vec<String>,
+    recording: Option<Arc<Mutex<Recording>>>,
+}
+
+impl Profiler {
+    /// A profiler for the command `root`, recording from now
+    pub fn new(root: impl Into<String>) -> Self {
+        Self {
+            frames: vec![frame(root.into())],
+            recording: Some(Arc::new(Mutex::new(Recording {
+                started: Instant::now(),
+                spans: Vec::new(),
+            }))),
+        }
+    }
+
+    /// A profiler that records nothing
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// The profiler installed for this process (or native plugin); disabled
+    /// when none was
+    pub fn current() -> Self {
+        CURRENT
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Make this the profiler [`Profiler::current`] returns
+    pub fn install(self) {
+        *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(self);
+    }
+
+    /// Time `phase` until the returned guard is dropped, as `label`
+    pub fn span(&self, phase: Phase, label: impl Into<String>) -> SpanGuard {
+        SpanGuard {
+            profiler: self.nested(phase, label),
+            phase,
+            started: Instant::now(),
+        }
+    }
+
+    /// A profiler whose spans nest under the span `phase`/`label` of this one
+    pub fn nested(&self, phase: Phase, label: impl Into<String>) -> Self {
+        let mut frames = self.frames.clone();
+        frames.push(phase.name().to_string());
+        frames.push(frame(label.into()));
+        Self {
+            frames,
+            recording: self.recording.clone(),
+        }
+    }
+
+    /// Record the command itself, from [`Profiler::new`] until now
+    pub fn finish(&self) {
+        if let Some(recording) = &self.recording {
+            let mut recording = recording.lock().unwrap_or_else(|e| e.into_inner());
+            let elapsed = recording.started.elapsed();
+            recording.spans.push(Span {
+                stack: self.frames[..1].to_vec(),
+                phase: None,
+                elapsed,
+            });
+        }
+    }
+
+    /// Every span recorded so far, in the order they ended
+    pub fn spans(&self) -> Vec<Span> {
+        self.recording
+            .as_ref()
+            .map(|r| r.lock().unwrap_or_else(|e| e.into_inner()).spans.clone())
+            .unwrap_or_default()
+    }
+
+    /// The recorded stacks in the folded format, one `stack microseconds`
+    /// line per stack with its self time
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for (stack, (_, time)) in self_times(&self.spans()) {
+            if !time.is_zero() {
+                let _ = writeln!(out, "{} {}", stack.join(";"), time.as_micros());
+            }
+        }
+        out
+    }
+
+    /// Self time per phase, and of the command outside every phase under
+    /// `None`
+    pub fn phase_totals(&self) -> BTreeMap<Option<Phase>, Duration> {
+        let mut totals = BTreeMap::new();
+        for (_, (phase, time)) in self_times(&self.spans()) {
+            *totals.entry(phase).or_default() += time;
+        }
+        totals
+    }
+
+    /// The phase totals as a table, longest first
+    pub fn summary(&self) -> String {
+        let total: Duration = self
+            .spans()
+            .iter()
+            .filter(|s| s.phase.is_none())
+            .map(|s| s.elapsed)
+            .sum();
+        let mut totals: Vec<(Option<Phase>, Duration)> = self.phase_totals().into_iter().collect();
+        totals.sort_by_key(|t| std::cmp::Reverse(t.1));
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<12} {:>10} {:>6}", "phase", "time", "share");
+        for (phase, time) in totals {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            let _ = writeln!(
+                out,
+                "{:<12} {:>8.1}ms {:>5.1}%",
+                phase.map_or("other", |p| p.name()),
+                time.as_secs_f64() * 1000.0,
+                share
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{:<12} {:>8.1}ms",
+            "total",
+            total.as_secs_f64() * 1000.0
+        );
+        out
+    }
+}
+
+/// Times one span; records it when dropped
+#[must_use = "the span ends when the guard is dropped"]
+pub struct SpanGuard {
+    profiler: Profiler,
+    phase: Phase,
+    started: Instant,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if let Some(recording) = &self.profiler.recording {
+            let span = Span {
+                stack: self.profiler.frames.clone(),
+                phase: Some(self.phase),
+                elapsed: self.started.elapsed(),
+            };
+            recording
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .spans
+                .push(span);
+        }
+    }
+}
+
+/// Time of each stack outside the stacks nested in it. Spans that overlap,
+/// like concurrent requests, can add up to more than the span they're in;
+/// that span's self time is then zero.
+fn self_times(spans: &[Span]) -> BTreeMap<Vec<String>, (Option<Phase>, Duration)> {
+    let mut totals: BTreeMap<Vec<String>, (Option<Phase>, Duration)> = BTreeMap::new();
+    for span in spans {
+        let entry = totals
+            .entry(span.stack.clone())
+            .or_insert((span.phase, Duration::ZERO));
+        entry.1 += span.elapsed;
+    }
+    let mut nested: BTreeMap<Vec<String>, Duration> = BTreeMap::new();
+    for (stack, (_, time)) in &totals {
+        let parent = (1..stack.len())
+            .rev()
+            .map(|len| &stack[..len])
+            .find(|prefix| totals.contains_key(*prefix));
+        if let Some(parent) = parent {
+            *nested.entry(parent.to_vec()).or_default() += *time;
+        }
+    }
+    totals
+        .into_iter()
+        .map(|(stack, (phase, time))| {
+            let inner = nested.get(&stack).copied().unwrap_or_default();
+            (stack, (phase, time.saturating_sub(inner)))
+        })
+        .collect()
+}
+
+/// `;` separates frames in the folded format
+fn frame(name: String) -> String {
+    name.replace(';', ",")
+}
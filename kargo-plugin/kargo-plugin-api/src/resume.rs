@@ -0,0 +1,137 @@
+//! Progress of interrupted runs
+//!
+//! A run that fetches something for every crate of a large workspace loses
+//! all of it when it's interrupted, or when the network gives out half way.
+//! Such runs keep a [`Journal`] in their cache directory instead: each result
+//! is recorded as it comes in, a rerun takes the recorded ones rather than
+//! fetching them again, and the journal is removed once the run completes.
+//!
+//! ```no_run
+//! # use kargo_plugin_api::resume::Journal;
+//! # fn fetch(name: &str) -> Option<String> { None }
+//! # let cache_dir = std::path::Path::new(".");
+//! let mut journal = Journal::open(cache_dir, "repositories");
+//! for name in ["serde", "tokio"] {
+//!     let repository = match journal.get::<String>(name) {
+//!         Some(repository) => repository,
+//!         None => match fetch(name) {
+//!             Some(repository) => {
+//!                 journal.record(name, &repository);
+//!                 repository
+//!             }
+//!             None => continue,
+//!         },
+//!     };
+//! }
+//! journal.finish();
+//! ```
+//!
+//! Keys name everything a result depends on (the crate and its versions,
+//! say), so a rerun with other inputs simply doesn't find them. Journals
+//! older than [`MAX_AGE`] are ignored.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::WriteGuard;
+
+/// How long an interrupted run can be resumed
+pub const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Stored {
+    /// When the interrupted run started, in seconds since the epoch
+    started: u64,
+    entries: BTreeMap<String, serde_json::Value>,
+}
+
+/// The recorded results of one task
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    stored: Stored,
+    resumed: usize,
+}
+
+impl Journal {
+    /// Open the journal of `task` in `dir`; one that is unreadable or older
+    /// than [`MAX_AGE`] starts over
+    pub fn open(dir: &Path, task: &str) -> Self {
+        let path = dir.join(format!("{}.resume.json", task));
+        let now = now();
+        let stored = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Stored>(&content).ok())
+            .filter(|stored| now.saturating_sub(stored.started) < MAX_AGE.as_secs())
+            .unwrap_or(Stored {
+                started: now,
+                entries: BTreeMap::new(),
+            });
+        let resumed = stored.entries.len();
+        if resumed > 0 {
+            log::info!(
+                "Resuming {}: {} result(s) kept from an interrupted run",
+                task,
+                resumed
+            );
+        }
+        Self {
+            path,
+            stored,
+            resumed,
+        }
+    }
+
+    /// Results carried over from an interrupted run
+    pub fn resumed(&self) -> usize {
+        self.resumed
+    }
+
+    /// The result recorded for `key`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_value(self.stored.entries.get(key)?.clone()).ok()
+    }
+
+    /// Record the result for `key`, saved at once so it survives the run
+    /// being interrupted. Failing to save only costs the resume, so it's
+    /// logged and ignored.
+    pub fn record<T: Serialize>(&mut self, key: &str, value: &T) {
+        let Ok(value) = serde_json::to_value(value) else {
+            return;
+        };
+        self.stored.entries.insert(key.to_string(), value);
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save progress to {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// The run completed: forget its progress
+    pub fn finish(self) {
+        if self.path.exists() && !WriteGuard::current().is_read_only() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        if WriteGuard::current().is_read_only() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so an interruption mid-write leaves the
+        // previous progress rather than a torn file
+        let partial = self.path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_string(&self.stored)?)?;
+        std::fs::rename(&partial, &self.path)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
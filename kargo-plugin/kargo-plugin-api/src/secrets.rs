@@ -0,0 +1,268 @@
+//! API tokens kept out of plaintext config
+//!
+//! Secrets live in the OS keychain (macOS Keychain, Windows Credential
+//! Manager, the Secret Service on Linux) under the service `kargo`. Where no
+//! keychain is reachable — headless machines, containers, CI — they go to an
+//! encrypted file instead, sealed with a key derived from a passphrase by
+//! Argon2id and encrypted with XChaCha20-Poly1305.
+//!
+//! Config values name a secret as `keyring:<name>`, and are swapped for the
+//! secret when the config is loaded:
+//!
+//! ```yaml
+//! network:
+//!   proxy: keyring:corp-proxy
+//!   tokens:
+//!     api.github.com: keyring:github
+//! ```
+//!
+//! Only available with the `secrets` feature.
+
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::WriteGuard;
+use crate::network::NetworkConfig;
+
+/// Prefix of config values naming a secret
+pub const REFERENCE_PREFIX: &str = "keyring:";
+/// Keychain service the secrets are stored under
+const SERVICE: &str = "kargo";
+/// Start of the encrypted file, followed by the salt, nonce and ciphertext
+const FILE_MAGIC: &[u8] = b"KSEC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// The name of the secret `value` refers to, if it's a `keyring:` reference
+pub fn reference(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(REFERENCE_PREFIX)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Where secrets are stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Keychain,
+    /// Encrypted file, with its passphrase when one was provided
+    File {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretStore {
+    backend: Backend,
+}
+
+impl SecretStore {
+    pub fn keychain() -> Self {
+        Self {
+            backend: Backend::Keychain,
+        }
+    }
+
+    pub fn file(path: PathBuf, passphrase: Option<String>) -> Self {
+        Self {
+            backend: Backend::File { path, passphrase },
+        }
+    }
+
+    /// Whether the OS keychain can be reached from this process
+    pub fn keychain_available() -> bool {
+        match keyring::Entry::new(SERVICE, "kargo-probe").and_then(|entry| entry.get_password()) {
+            Ok(_) | Err(keyring::Error::NoEntry) => true,
+            Err(e) => {
+                log::debug!("OS keychain unavailable: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        match &self.backend {
+            Backend::Keychain => match entry(name)?.get_password() {
+                Ok(secret) => Ok(Some(secret)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(anyhow!(e).context(format!("Failed to read secret {}", name))),
+            },
+            Backend::File { .. } => Ok(self.read_file()?.remove(name)),
+        }
+    }
+
+    pub fn set(&self, name: &str, secret: &str) -> Result<()> {
+        match &self.backend {
+            Backend::Keychain => {
+                WriteGuard::current().check(Path::new(SERVICE))?;
+                entry(name)?
+                    .set_password(secret)
+                    .with_context(|| format!("Failed to store secret {}", name))
+            }
+            Backend::File { .. } => {
+                let mut secrets = self.read_file()?;
+                secrets.insert(name.to_string(), secret.to_string());
+                self.write_file(&secrets)
+            }
+        }
+    }
+
+    /// Delete the secret; `false` if there was none
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        match &self.backend {
+            Backend::Keychain => {
+                WriteGuard::current().check(Path::new(SERVICE))?;
+                match entry(name)?.delete_credential() {
+                    Ok(()) => Ok(true),
+                    Err(keyring::Error::NoEntry) => Ok(false),
+                    Err(e) => Err(anyhow!(e).context(format!("Failed to delete secret {}", name))),
+                }
+            }
+            Backend::File { .. } => {
+                let mut secrets = self.read_file()?;
+                if secrets.remove(name).is_none() {
+                    return Ok(false);
+                }
+                self.write_file(&secrets)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// `value`, or the secret it refers to if it's a `keyring:` reference
+    pub fn resolve(&self, value: &str) -> Result<String> {
+        let Some(name) = reference(value) else {
+            return Ok(value.to_string());
+        };
+        self.get(name)?.with_context(|| {
+            format!(
+                "Secret {} is not set; run `kargo secret set {}`",
+                name, name
+            )
+        })
+    }
+
+    fn passphrase(&self) -> Result<&str> {
+        match &self.backend {
+            Backend::File {
+                passphrase: Some(passphrase),
+                ..
+            } => Ok(passphrase),
+            _ => bail!(
+                "No OS keychain is available; set KARGO_SECRETS_PASSPHRASE to keep secrets in an encrypted file"
+            ),
+        }
+    }
+
+    fn read_file(&self) -> Result<BTreeMap<String, String>> {
+        let Backend::File { path, .. } = &self.backend else {
+            unreachable!("only called for the file backend");
+        };
+        let sealed = match fs::read(path) {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let header = FILE_MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if sealed.len() < header || !sealed.starts_with(FILE_MAGIC) {
+            bail!("{} is not a kargo secrets file", path.display());
+        }
+        let (salt, rest) = sealed[FILE_MAGIC.len()..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let plaintext = cipher(self.passphrase()?, salt)?
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Wrong passphrase for {}", path.display()))?;
+        serde_json::from_slice(&plaintext)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn write_file(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let Backend::File { path, .. } = &self.backend else {
+            unreachable!("only called for the file backend");
+        };
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut salt)
+            .and_then(|()| getrandom::getrandom(&mut nonce))
+            .map_err(|e| anyhow!("No randomness for the secrets file: {}", e))?;
+        let ciphertext = cipher(self.passphrase()?, &salt)?
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                serde_json::to_vec(secrets)?.as_slice(),
+            )
+            .map_err(|_| anyhow!("Failed to encrypt {}", path.display()))?;
+
+        let guard = WriteGuard::current();
+        if let Some(parent) = path.parent() {
+            guard.create_dir_all(parent)?;
+        }
+        guard.write_atomic(path, [FILE_MAGIC, &salt, &nonce, &ciphertext].concat())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to restrict {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl NetworkConfig {
+    /// Replace `keyring:` references in the proxy and tokens with the
+    /// secrets they name. References that can't be resolved are dropped, so
+    /// requests go out without them, and reported together.
+    pub fn resolve_secrets(&mut self, store: &SecretStore) -> Result<()> {
+        let mut failures = Vec::new();
+        if let Some(proxy) = self.proxy.take() {
+            match store.resolve(&proxy) {
+                Ok(proxy) => self.proxy = Some(proxy),
+                Err(e) => failures.push(format!("network.proxy: {:#}", e)),
+            }
+        }
+        self.tokens
+            .retain(|host, token| match store.resolve(token) {
+                Ok(secret) => {
+                    *token = secret;
+                    true
+                }
+                Err(e) => {
+                    failures.push(format!("network.tokens.{}: {:#}", host, e));
+                    false
+                }
+            });
+        if !failures.is_empty() {
+            bail!("{}", failures.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Whether the proxy or any token is a `keyring:` reference
+    pub fn has_secret_references(&self) -> bool {
+        self.proxy
+            .iter()
+            .chain(self.tokens.values())
+            .any(|value| reference(value).is_some())
+    }
+}
+
+fn entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name).with_context(|| format!("Invalid secret name {}", name))
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the secrets key: {}", e))?;
+    Ok(XChaCha20Poly1305::new(Key::from_slice(&key)))
+}
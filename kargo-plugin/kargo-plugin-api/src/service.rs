@@ -0,0 +1,99 @@
+//! Background services plugins run under `kargo daemon`
+//!
+//! A plugin with work that should keep happening between commands — keeping
+//! an index fresh, syncing an advisory database — returns a [`ServiceSpec`]
+//! from [`PluginCommand::service`](crate::PluginCommand::service) and does the
+//! work in [`PluginCommand::serve`](crate::PluginCommand::serve). The daemon
+//! runs every service concurrently and restarts one that stops according to
+//! its [`RestartPolicy`]. When the daemon shuts down it closes
+//! [`ExecutionContext::events`](crate::ExecutionContext::events), so a
+//! service waiting for events sees the end of the stream and returns:
+//!
+//! ```no_run
+//! # use kargo_plugin_api::ExecutionContext;
+//! # async fn serve(ctx: ExecutionContext) -> anyhow::Result<()> {
+//! let mut events = ctx.events.subscribe();
+//! while let Some(event) = events.recv().await {
+//!     // React to files changing, dependencies bumped, ...
+//!     println!("{:?}", event);
+//! }
+//! Ok(())
+//! # }
+//! ```
+//!
+//! A service that still runs a while after the shutdown is dropped.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Longest wait between restarts, however often the service failed
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// When a service that stopped is started again
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Never; the service runs once
+    Never,
+    /// When it returned an error or panicked
+    #[default]
+    OnFailure,
+    /// Whenever it stops before the daemon shuts down
+    Always,
+}
+
+/// How the daemon supervises a plugin's service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceSpec {
+    pub restart: RestartPolicy,
+    /// Restarts before the daemon gives up on the service; `None` restarts
+    /// it for as long as the daemon runs
+    pub max_restarts: Option<u32>,
+    /// Wait before the first restart, doubled for each one after it up to
+    /// [`MAX_BACKOFF`]
+    pub backoff: Duration,
+}
+
+impl Default for ServiceSpec {
+    fn default() -> Self {
+        Self {
+            restart: RestartPolicy::default(),
+            max_restarts: Some(5),
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ServiceSpec {
+    pub fn new(restart: RestartPolicy) -> Self {
+        Self {
+            restart,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_restarts(mut self, max_restarts: Option<u32>) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// How long to wait before restarting a service that stopped, `failed`
+    /// or not, after `restarts` earlier restarts; `None` leaves it stopped
+    pub fn restart_delay(&self, failed: bool, restarts: u32) -> Option<Duration> {
+        let restart = match self.restart {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => failed,
+            RestartPolicy::Always => true,
+        };
+        if !restart || self.max_restarts.is_some_and(|max| restarts >= max) {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(restarts);
+        Some(self.backoff.saturating_mul(factor).min(MAX_BACKOFF))
+    }
+}
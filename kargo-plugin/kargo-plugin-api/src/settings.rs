@@ -0,0 +1,110 @@
+//! A plugin's own section of the kargo config
+//!
+//! Users configure a plugin under its name in `plugin_settings`, in the
+//! global config, a profile or a project's `.kargo.toml`:
+//!
+//! ```toml
+//! [plugin_settings.upgrade]
+//! exclude = ["tokio"]
+//! ```
+//!
+//! The host lays those over each other and hands the plugin its section in
+//! [`ExecutionContext::settings`]; the plugin reads it into a type of its own
+//! with [`PluginSettings::get`].
+//!
+//! ```
+//! # use kargo_plugin_api::settings::PluginSettings;
+//! #[derive(Default, serde::Deserialize)]
+//! #[serde(default)]
+//! struct UpgradeSettings {
+//!     exclude: Vec<String>,
+//! }
+//!
+//! let settings = PluginSettings::new(
+//!     "upgrade",
+//!     serde_json::json!({ "exclude": ["tokio"] }),
+//!     Some(".kargo.toml:2".to_string()),
+//! );
+//! let upgrade: UpgradeSettings = settings.get()?;
+//! assert_eq!(upgrade.exclude, ["tokio"]);
+//! # Ok::<(), kargo_plugin_api::settings::SettingsError>(())
+//! ```
+//!
+//! [`ExecutionContext::settings`]: crate::ExecutionContext::settings
+
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// The settings section of one plugin; empty when the user configured none
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginSettings {
+    plugin: String,
+    value: Option<serde_json::Value>,
+    origin: Option<String>,
+}
+
+impl PluginSettings {
+    /// The section `value` of `plugin`, read from `origin` (`file:line`)
+    pub fn new(
+        plugin: impl Into<String>,
+        value: serde_json::Value,
+        origin: Option<String>,
+    ) -> Self {
+        Self {
+            plugin: plugin.into(),
+            value: Some(value),
+            origin,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Where the section was configured, for messages about it
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// The section as the host read it
+    pub fn raw(&self) -> Option<&serde_json::Value> {
+        self.value.as_ref()
+    }
+
+    /// The section as `T`; `T::default()` when there is none
+    pub fn get<T: DeserializeOwned + Default>(&self) -> Result<T, SettingsError> {
+        match &self.value {
+            Some(value) => T::deserialize(value).map_err(|source| SettingsError {
+                plugin: self.plugin.clone(),
+                origin: self.origin.clone(),
+                source,
+            }),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+/// A plugin's section doesn't match the settings it takes
+#[derive(Debug)]
+pub struct SettingsError {
+    plugin: String,
+    origin: Option<String>,
+    source: serde_json::Error,
+}
+
+impl fmt::Display for SettingsError {
+    // The cause is exposed through `source()`, so `{:#}` doesn't repeat it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid settings for plugin '{}'", self.plugin)?;
+        match &self.origin {
+            Some(origin) => write!(f, " in {}", origin),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
@@ -0,0 +1,302 @@
+//! Snapshots of files taken before kargo rewrites them
+//!
+//! Every run that modifies files, whether kargo's own commands or a plugin's,
+//! records them in a snapshot under `<config dir>/snapshots/`. A snapshot is
+//! named after when its run started, to the millisecond, and the operation
+//! it performed, e.g. `001718000000042-upgrade`, and its `index.json` lists
+//! each file it covers with the SHA-256 of the contents the file had before
+//! the run's first change to it. The contents themselves are stored once, by that hash,
+//! under `snapshots/objects/`, so the same manifest backed up by many runs
+//! takes the space of one copy.
+//!
+//! `kargo restore` lists the snapshots and rolls files back to any of them.
+//! Only the newest [`KEEP_SNAPSHOTS`] are kept; contents no kept snapshot
+//! refers to are removed along with the oldest.
+//!
+//! ```no_run
+//! # use kargo_plugin_api::snapshot::Snapshot;
+//! # fn run(ctx: kargo_plugin_api::ExecutionContext) -> anyhow::Result<()> {
+//! let mut snapshot = Snapshot::create_in(&Snapshot::root(&ctx.config_dir), "upgrade");
+//! let manifest = ctx.current_dir.join("Cargo.toml");
+//! snapshot.record(&manifest)?;
+//! // ... rewrite the manifest; on failure:
+//! snapshot.restore(&manifest)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::WriteGuard;
+
+/// Directory of the snapshots, below kargo's config directory
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Number of snapshots kept before the oldest are pruned
+pub const KEEP_SNAPSHOTS: usize = 20;
+
+const INDEX_FILE: &str = "index.json";
+
+/// Directory of the stored contents, below the snapshots
+const OBJECTS_DIR: &str = "objects";
+
+/// Millisecond timestamp of the last snapshot this process started
+static LAST_STARTED: AtomicU64 = AtomicU64::new(0);
+
+/// A file covered by a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub path: PathBuf,
+    /// SHA-256 of the contents the file had, in hex
+    pub sha256: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    created: u64,
+    operation: String,
+    files: Vec<SnapshotFile>,
+}
+
+/// A snapshot as shown by `kargo restore --list`
+#[derive(Debug, Clone)]
+pub struct SnapshotSummary {
+    pub id: String,
+    /// Unix timestamp (seconds) the snapshot was taken at
+    pub created: u64,
+    /// What the run did, e.g. `upgrade`
+    pub operation: String,
+    pub files: usize,
+}
+
+/// The files one run changed, as they were before
+#[derive(Debug)]
+pub struct Snapshot {
+    root: PathBuf,
+    id: String,
+    index: Index,
+}
+
+impl Snapshot {
+    /// The snapshots directory of kargo's `config_dir`
+    pub fn root(config_dir: &Path) -> PathBuf {
+        config_dir.join(SNAPSHOTS_DIR)
+    }
+
+    /// Start a snapshot of `operation` under `root`. Nothing is written until
+    /// the first file is recorded.
+    pub fn create_in(root: &Path, operation: &str) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        // Strictly increasing, so a run's own snapshots stay apart and in order
+        let started =
+            match LAST_STARTED.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                Some(now.max(last + 1))
+            }) {
+                Ok(last) | Err(last) => now.max(last + 1),
+            };
+        let created = started / 1000;
+        let operation: String = operation
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        // Zero-padded so ids sort chronologically
+        let id = format!("{:015}-{}", started, operation);
+        Self {
+            root: root.to_path_buf(),
+            id,
+            index: Index {
+                created,
+                operation,
+                files: Vec::new(),
+            },
+        }
+    }
+
+    /// Open the snapshot `id` under `root`, or the newest one
+    pub fn open(root: &Path, id: Option<&str>) -> Result<Self> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => snapshot_ids(root)?
+                .pop()
+                .with_context(|| format!("No snapshots found in {}", root.display()))?,
+        };
+        let path = root.join(&id).join(INDEX_FILE);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("No snapshot named {} ({})", id, path.display()))?;
+        let index = serde_json::from_str(&content)
+            .with_context(|| format!("Corrupt snapshot index {}", path.display()))?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            id,
+            index,
+        })
+    }
+
+    /// Snapshots under `root`, oldest first
+    pub fn list(root: &Path) -> Result<Vec<SnapshotSummary>> {
+        let mut summaries = Vec::new();
+        for id in snapshot_ids(root)? {
+            let Some(index) = read_index(&root.join(&id)) else {
+                continue;
+            };
+            summaries.push(SnapshotSummary {
+                id,
+                created: index.created,
+                operation: index.operation,
+                files: index.files.len(),
+            });
+        }
+        Ok(summaries)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn operation(&self) -> &str {
+        &self.index.operation
+    }
+
+    /// Directory of the snapshot's index
+    pub fn dir(&self) -> PathBuf {
+        self.root.join(&self.id)
+    }
+
+    pub fn files(&self) -> &[SnapshotFile] {
+        &self.index.files
+    }
+
+    /// Record the contents of `path`. Files already recorded keep their
+    /// first contents, so restoring always goes back to the state before the
+    /// run.
+    pub fn record(&mut self, path: &Path) -> Result<()> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Cannot back up {}", path.display()))?;
+        if self.index.files.iter().any(|file| file.path == path) {
+            return Ok(());
+        }
+        let contents =
+            fs::read(&path).with_context(|| format!("Cannot back up {}", path.display()))?;
+        let sha256 = format!("{:x}", Sha256::digest(&contents));
+
+        let guard = WriteGuard::current();
+        if self.index.files.is_empty() {
+            guard.create_dir_all(self.dir())?;
+            prune(&self.root, &self.id);
+        }
+        let object = self.object(&sha256);
+        if !object.is_file() {
+            if let Some(dir) = object.parent() {
+                guard.create_dir_all(dir)?;
+            }
+            guard
+                .write_atomic(&object, &contents)
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+        }
+        self.index.files.push(SnapshotFile { path, sha256 });
+        guard.write_atomic(
+            self.dir().join(INDEX_FILE),
+            serde_json::to_string_pretty(&self.index)?,
+        )
+    }
+
+    /// The recorded contents of `path`
+    pub fn contents(&self, path: &Path) -> Result<Vec<u8>> {
+        let file = self
+            .file(path)
+            .with_context(|| format!("{} is not in snapshot {}", path.display(), self.id))?;
+        let object = self.object(&file.sha256);
+        fs::read(&object).with_context(|| format!("Failed to read {}", object.display()))
+    }
+
+    /// Put the recorded contents of `path` back
+    pub fn restore(&self, path: &Path) -> Result<()> {
+        let contents = self.contents(path)?;
+        WriteGuard::current()
+            .write_atomic(path, contents)
+            .with_context(|| format!("Failed to restore {}", path.display()))
+    }
+
+    fn file(&self, path: &Path) -> Option<&SnapshotFile> {
+        let canonical = path.canonicalize().ok();
+        self.index
+            .files
+            .iter()
+            .find(|file| file.path == path || Some(&file.path) == canonical.as_ref())
+    }
+
+    fn object(&self, sha256: &str) -> PathBuf {
+        self.root.join(OBJECTS_DIR).join(&sha256[..2]).join(sha256)
+    }
+}
+
+fn read_index(dir: &Path) -> Option<Index> {
+    let content = fs::read_to_string(dir.join(INDEX_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn snapshot_ids(root: &Path) -> Result<Vec<String>> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids: Vec<String> = fs::read_dir(root)
+        .with_context(|| format!("Failed to read {}", root.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(INDEX_FILE).is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+/// Drop the oldest snapshots so at most [`KEEP_SNAPSHOTS`] remain, counting
+/// the one being started, and the contents only they referred to
+fn prune(root: &Path, current: &str) {
+    let Ok(ids) = snapshot_ids(root) else {
+        return;
+    };
+    let others: Vec<&String> = ids.iter().filter(|id| *id != current).collect();
+    let excess = (others.len() + 1).saturating_sub(KEEP_SNAPSHOTS);
+    if excess == 0 {
+        return;
+    }
+    let guard = WriteGuard::current();
+    for id in &others[..excess] {
+        if let Err(e) = guard.remove_dir_all(root.join(id)) {
+            log::warn!("Failed to prune snapshot {}: {}", id, e);
+        }
+    }
+
+    let kept: HashSet<String> = others[excess..]
+        .iter()
+        .filter_map(|id| read_index(&root.join(id)))
+        .flat_map(|index| index.files)
+        .map(|file| file.sha256)
+        .collect();
+    let Ok(prefixes) = fs::read_dir(root.join(OBJECTS_DIR)) else {
+        return;
+    };
+    for object in prefixes
+        .filter_map(|prefix| fs::read_dir(prefix.ok()?.path()).ok())
+        .flatten()
+        .filter_map(|object| object.ok())
+    {
+        let name = object.file_name();
+        if kept.contains(name.to_string_lossy().as_ref()) {
+            continue;
+        }
+        if let Err(e) = guard.remove_file(object.path()) {
+            log::warn!("Failed to prune {}: {}", object.path().display(), e);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Read-only mode for audits and evaluating untrusted plugins
+//!
+//! With `kargo --read-only`, file-writing utilities refuse to touch the disk
+//! and fail with a [`ReadOnlyError`] instead, which callers can tell apart
+//! from ordinary I/O failures:
+//!
+//! ```no_run
+//! # use kargo_plugin_api::{ExecutionContext, ReadOnlyError};
+//! # fn run(ctx: ExecutionContext) -> anyhow::Result<()> {
+//! if let Err(e) = ctx.write_guard.write(ctx.current_dir.join("out.md"), "# Report") {
+//!     if e.is::<ReadOnlyError>() {
+//!         println!("Read-only run; not writing the report");
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The host passes its guard in [`ExecutionContext::write_guard`]. Native
+//! plugins link their own copy of this crate; the wrapper they are exported
+//! in (see [`isolation`](crate::isolation)) [`WriteGuard::install`]s it before
+//! each call, for [`WriteGuard::current`], and the utilities built on it, to
+//! see the mode. The guard stops writes that go through it; it's not
+//! a sandbox for code that writes with `std::fs` directly.
+//!
+//! [`ExecutionContext::write_guard`]: crate::ExecutionContext::write_guard
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// A write refused because kargo is running read-only
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyError {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Refusing to write {}: kargo is running with --read-only",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
+/// Whether file writes are allowed, and the writes that check it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteGuard {
+    read_only: bool,
+}
+
+impl WriteGuard {
+    pub const fn new(read_only: bool) -> Self {
+        Self { read_only }
+    }
+
+    /// A guard that allows every write
+    pub const fn writable() -> Self {
+        Self::new(false)
+    }
+
+    /// A guard that refuses every write
+    pub const fn read_only() -> Self {
+        Self::new(true)
+    }
+
+    /// The guard installed for this process (or native plugin)
+    pub fn current() -> Self {
+        Self::new(READ_ONLY.load(Ordering::Relaxed))
+    }
+
+    /// Make this the guard [`WriteGuard::current`] returns
+    pub fn install(self) {
+        READ_ONLY.store(self.read_only, Ordering::Relaxed);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail with a [`ReadOnlyError`] if `path` may not be written
+    pub fn check(&self, path: impl AsRef<Path>) -> Result<(), ReadOnlyError> {
+        if self.read_only {
+            return Err(ReadOnlyError {
+                path: path.as_ref().to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = path.as_ref();
+        self.check(path)?;
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Write `contents` to a temporary file next to `path` and rename it
+    /// over `path`, so readers see the old or the new file, never a partial
+    /// one
+    pub fn write_atomic(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = path.as_ref();
+        self.check(path)?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, contents).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    /// Append `contents` to `path`, creating it if needed
+    pub fn append(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = path.as_ref();
+        self.check(path)?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_ref()))
+            .with_context(|| format!("Failed to append to {}", path.display()))
+    }
+
+    pub fn copy(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.check(to)?;
+        fs::copy(from, to)
+            .map(|_| ())
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))
+    }
+
+    pub fn create_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(());
+        }
+        self.check(path)?;
+        fs::create_dir_all(path).with_context(|| format!("Failed to create {}", path.display()))
+    }
+
+    pub fn remove_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        self.check(path)?;
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+    }
+
+    pub fn remove_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        self.check(path)?;
+        fs::remove_dir_all(path).with_context(|| format!("Failed to remove {}", path.display()))
+    }
+}
@@ -0,0 +1,67 @@
+use clap::{Arg, ArgAction, Command};
+use kargo_plugin_api::args::PluginArgs;
+use std::path::PathBuf;
+
+fn command() -> Command {
+    Command::new("upgrade")
+        .arg(Arg::new("verbose").short('v').action(ArgAction::Count))
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(Arg::new("jobs").long("jobs").default_value("4"))
+        .subcommand(
+            Command::new("pin").arg(
+                Arg::new("crates")
+                    .num_args(1..)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            ),
+        )
+        .subcommand(Command::new("exec").allow_external_subcommands(true))
+}
+
+#[test]
+fn test_parsed_values_are_read_back_typed() {
+    let args = PluginArgs::parse(
+        command(),
+        ["upgrade", "-vv", "--jobs", "8", "pin", "a", "b"],
+    )
+    .unwrap();
+    assert_eq!(args.count("verbose"), 2);
+    assert!(args.flag("verbose"));
+    // Switches that weren't given are off rather than missing
+    assert!(args.contains("dry-run"));
+    assert!(!args.flag("dry-run"));
+    assert_eq!(args.get::<usize>("jobs").unwrap(), Some(8));
+    assert_eq!(args.value("missing"), None);
+
+    let (name, pin) = args.subcommand().unwrap();
+    assert_eq!(name, "pin");
+    assert_eq!(
+        pin.get_many::<PathBuf>("crates").unwrap(),
+        [PathBuf::from("a"), PathBuf::from("b")]
+    );
+
+    let defaulted = PluginArgs::parse(command(), ["upgrade"]).unwrap();
+    assert_eq!(defaulted.value("jobs"), Some("4"));
+    assert!(defaulted.subcommand().is_none());
+    let error = PluginArgs::parse(command(), ["upgrade", "--jobs", "many"])
+        .unwrap()
+        .get::<usize>("jobs")
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Invalid value 'many' for jobs");
+}
+
+#[test]
+fn test_external_subcommands_and_round_trip() {
+    let args = PluginArgs::parse(command(), ["upgrade", "exec", "cargo", "test", "--all"]).unwrap();
+    let (_, exec) = args.subcommand().unwrap();
+    let (name, external) = exec.subcommand().unwrap();
+    assert_eq!(name, "cargo");
+    assert_eq!(external.values(""), ["test", "--all"]);
+
+    let json = serde_json::to_string(&args).unwrap();
+    assert_eq!(serde_json::from_str::<PluginArgs>(&json).unwrap(), args);
+    assert!(PluginArgs::parse(command(), ["upgrade", "--bogus"]).is_err());
+}
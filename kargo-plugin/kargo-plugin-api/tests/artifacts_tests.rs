@@ -0,0 +1,44 @@
+use kargo_plugin_api::artifacts::{KEEP_RUNS, RUNS_DIR, RunArtifacts, run_id};
+use std::fs;
+
+#[test]
+fn test_captured_output_is_kept_per_run() {
+    let project = tempfile::tempdir().unwrap();
+    let runs = project.path().join(RUNS_DIR);
+    // Runs left behind by earlier processes, the oldest to be pruned
+    for run in 0..KEEP_RUNS {
+        fs::create_dir_all(runs.join(format!("{:012}-1", run))).unwrap();
+    }
+
+    let artifacts = RunArtifacts::for_project(project.path());
+    let fmt = artifacts.capture("cargo fmt", Some(0), b"", b"").unwrap();
+    let check = artifacts
+        .capture(
+            "cargo check --all-targets",
+            Some(101),
+            b"",
+            b"error[E0433]: failed to resolve\n",
+        )
+        .unwrap();
+
+    assert_eq!(artifacts.dir(), runs.join(run_id()));
+    assert!(check.stderr.starts_with(artifacts.dir()));
+    assert_ne!(fmt.stderr, check.stderr);
+    assert!(
+        check
+            .stderr
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with("cargo-check---all-targets.stderr")
+    );
+    assert_eq!(check.exit_code, Some(101));
+    assert_eq!(
+        fs::read_to_string(&check.stderr).unwrap(),
+        "error[E0433]: failed to resolve\n"
+    );
+    assert_eq!(fs::read_to_string(&check.stdout).unwrap(), "");
+
+    assert_eq!(fs::read_dir(&runs).unwrap().count(), KEEP_RUNS);
+    assert!(!runs.join(format!("{:012}-1", 0)).exists());
+}
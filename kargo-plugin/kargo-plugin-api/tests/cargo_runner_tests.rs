@@ -0,0 +1,85 @@
+use kargo_plugin_api::Severity;
+use kargo_plugin_api::cargo_runner::{CargoCommand, CargoRunner, ProcessRunner, parse_messages};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CHECK_OUTPUT: &str = r#"{"reason":"compiler-artifact","package_id":"path+file:///w/dep#0.1.0","manifest_path":"/w/dep/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"dep","src_path":"/w/dep/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":false},"features":[],"filenames":[],"executable":null,"fresh":true}
+not json at all
+{"reason":"compiler-message","package_id":"path+file:///w/app#0.1.0","manifest_path":"/w/app/Cargo.toml","target":{"kind":["bin"],"crate_types":["bin"],"name":"app","src_path":"/w/app/src/main.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"$message_type":"diagnostic","message":"failed to resolve: could not find `thread_rng` in `rand`","code":{"code":"E0433","explanation":null},"level":"error","spans":[{"file_name":"src/main.rs","byte_start":10,"byte_end":20,"line_start":3,"line_end":3,"column_start":15,"column_end":25,"is_primary":true,"text":[],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[],"rendered":"error[E0433]: failed to resolve: could not find `thread_rng` in `rand`\n"}}
+{"reason":"compiler-message","package_id":"path+file:///w/app#0.1.0","manifest_path":"/w/app/Cargo.toml","target":{"kind":["bin"],"crate_types":["bin"],"name":"app","src_path":"/w/app/src/main.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"$message_type":"diagnostic","message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[],"children":[],"rendered":null}}
+{"reason":"build-finished","success":false}
+"#;
+
+#[test]
+fn test_parse_messages_keeps_compiler_messages_only() {
+    let messages = parse_messages(CHECK_OUTPUT.as_bytes());
+    assert_eq!(messages.len(), 2);
+
+    let error = &messages[0];
+    assert_eq!(error.level, Severity::Error);
+    assert_eq!(error.code.as_deref(), Some("E0433"));
+    assert_eq!(error.file.as_deref(), Some(Path::new("src/main.rs")));
+    assert_eq!((error.line, error.column), (Some(3), Some(15)));
+    assert!(error.text().starts_with("error[E0433]"));
+
+    let warning = &messages[1];
+    assert_eq!(warning.level, Severity::Warning);
+    assert_eq!(warning.file, None);
+    assert_eq!(warning.text(), "unused variable: `x`");
+}
+
+#[test]
+fn test_command_display_and_json_flag() {
+    let command = CargoCommand::new("rustdoc")
+        .toolchain("nightly")
+        .arg("--lib")
+        .json_messages()
+        .json_messages()
+        .target_dir(Some(Path::new("/tmp/target")));
+    assert_eq!(
+        command.to_string(),
+        "cargo +nightly rustdoc --lib --message-format=json"
+    );
+    assert_eq!(command.subcommand(), "rustdoc");
+    assert_eq!(
+        command.env,
+        vec![(
+            "CARGO_TARGET_DIR".to_string(),
+            PathBuf::from("/tmp/target").into()
+        )]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_process_runner_kills_after_timeout() {
+    // `sleep` stands in for cargo, with the "subcommand" as its argument
+    let runner = ProcessRunner::with_program("sleep");
+    let command = CargoCommand::new("5").timeout(Duration::from_millis(100));
+    let output = runner.run(&command).unwrap();
+    assert!(output.timed_out);
+    assert!(!output.success());
+    assert!(
+        output
+            .ensure_success(&command)
+            .unwrap_err()
+            .to_string()
+            .contains("timed out")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_process_runner_reports_failure_with_stderr() {
+    let runner = ProcessRunner::with_program("sh");
+    let command = CargoCommand::new("-c")
+        .arg("echo broken >&2; exit 3")
+        .env_remove("CARGO_TARGET_DIR");
+    let output = runner.run(&command).unwrap();
+    assert!(!output.timed_out);
+    assert_eq!(output.status.and_then(|status| status.code()), Some(3));
+    assert_eq!(
+        output.ensure_success(&command).unwrap_err().to_string(),
+        "cargo -c failed: broken"
+    );
+}
@@ -0,0 +1,51 @@
+use kargo_plugin_api::compat::{self, API_VERSION, Incompatible, MIN_API_VERSION};
+
+#[test]
+fn test_api_version_must_be_within_what_the_host_loads() {
+    assert_eq!(compat::check_api(Some(API_VERSION)), Ok(()));
+    assert_eq!(compat::check_api(Some(MIN_API_VERSION)), Ok(()));
+    assert_eq!(compat::check_api(None), Err(Incompatible::Unversioned));
+    assert_eq!(
+        compat::check_api(Some(API_VERSION + 1)),
+        Err(Incompatible::NewApi(API_VERSION + 1))
+    );
+    assert_eq!(
+        compat::check_api(Some(MIN_API_VERSION - 1)),
+        Err(Incompatible::OldApi(MIN_API_VERSION - 1))
+    );
+}
+
+#[test]
+fn test_kargo_requirement_is_checked_against_the_host() {
+    assert_eq!(compat::check_kargo(">=0.4, <0.6", "0.5.2"), Ok(()));
+    // Development builds count as the release they lead to
+    assert_eq!(compat::check_kargo("^0.5", "0.5.0-dev"), Ok(()));
+
+    let err = compat::check_kargo(">=0.4, <0.6", "0.6.0").unwrap_err();
+    assert_eq!(
+        err,
+        Incompatible::Kargo {
+            requires: ">=0.4, <0.6".to_string(),
+            host: "0.6.0".to_string(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "requires kargo >=0.4, <0.6, but this is kargo 0.6.0"
+    );
+    assert_eq!(
+        compat::check_kargo("newest please", "0.5.0"),
+        Err(Incompatible::InvalidRequirement(
+            "newest please".to_string()
+        ))
+    );
+}
+
+kargo_plugin_api::export_plugin_compat!(kargo = ">=0.4");
+
+#[test]
+fn test_exports_report_what_the_plugin_was_built_against() {
+    assert_eq!(kargo_plugin_api_version(), API_VERSION);
+    let requires = unsafe { std::ffi::CStr::from_ptr(kargo_plugin_requires_kargo()) };
+    assert_eq!(requires.to_str(), Ok(">=0.4"));
+}
@@ -0,0 +1,27 @@
+//! A native plugin that panics, loaded by the isolation tests
+
+use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+
+/// Panics before returning its future for `--early`, otherwise while polled
+struct Panicking;
+
+impl PluginCommand for Panicking {
+    fn clap(&self) -> clap::Command {
+        clap::Command::new("panicking").version("1.2.3")
+    }
+
+    fn run(&self, ctx: ExecutionContext) -> BoxFuture {
+        if ctx.matched_args.iter().any(|a| a == "--early") {
+            panic!("bad arguments");
+        }
+        Box::pin(async {
+            let items: Vec<u32> = Vec::new();
+            let _ = items[3];
+            Ok(())
+        })
+    }
+}
+
+kargo_plugin_api::export_plugin!(Panicking);
+
+kargo_plugin_api::export_plugin_compat!();
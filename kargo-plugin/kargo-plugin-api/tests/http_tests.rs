@@ -0,0 +1,49 @@
+#![cfg(feature = "http")]
+
+use kargo_plugin_api::NetworkConfig;
+use kargo_plugin_api::http::{self, HttpClient};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Answer `count` requests with 503, then stop listening
+fn unavailable(count: usize) -> (String, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        for _ in 0..count {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            stream
+                .write_all(
+                    b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+        }
+    });
+    (url, handle)
+}
+
+#[tokio::test]
+async fn test_retries_stop_once_the_budget_is_spent() {
+    // Three attempts for the first request, one for the second
+    let (url, server) = unavailable(4);
+    let config = NetworkConfig {
+        retries: 5,
+        backoff_ms: 0,
+        retry_budget: Some(2),
+        ..Default::default()
+    };
+    let client = HttpClient::with_config(reqwest::Client::builder(), config).unwrap();
+
+    for _ in 0..2 {
+        let response = client.send(client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), 503);
+    }
+    server.join().unwrap();
+    assert_eq!(http::retries_spent(), 2);
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::task::{Context, Poll, Waker};
+
+use kargo_plugin_api::compat::{API_VERSION, ApiVersionFn};
+use kargo_plugin_api::{BoxFuture, CreateFn, ExecutionContext, PluginCommand, isolation};
+use libloading::{Library, Symbol};
+
+/// Panics before returning its future for `--early`, otherwise while polled
+struct Crashy;
+
+impl PluginCommand for Crashy {
+    fn clap(&self) -> clap::Command {
+        clap::Command::new("crashy").version("1.2.3")
+    }
+
+    fn run(&self, ctx: ExecutionContext) -> BoxFuture {
+        if ctx.matched_args.iter().any(|a| a == "--early") {
+            panic!("bad arguments");
+        }
+        Box::pin(async {
+            let items: Vec<u32> = Vec::new();
+            let _ = items[3];
+            Ok(())
+        })
+    }
+}
+
+fn context(config_dir: &Path, args: &[&str]) -> ExecutionContext {
+    ExecutionContext {
+        matched_args: args.iter().map(|a| a.to_string()).collect(),
+        args: Default::default(),
+        current_dir: ".".into(),
+        config_dir: config_dir.to_path_buf(),
+        data_dir: ".".into(),
+        cache_dir: ".".into(),
+        diagnostics: Default::default(),
+        registry: None,
+        target_dir: None,
+        host_version: "9.9.9".to_string(),
+        write_guard: Default::default(),
+        events: Default::default(),
+        network: Default::default(),
+        profiler: Default::default(),
+        settings: Default::default(),
+    }
+}
+
+/// The plugin futures here finish on their first poll
+fn ready(mut future: BoxFuture) -> anyhow::Result<()> {
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(result) => result,
+        Poll::Pending => panic!("plugin future is pending"),
+    }
+}
+
+fn crash_reports(config_dir: &Path) -> Vec<String> {
+    std::fs::read_dir(config_dir.join("crashes"))
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect()
+}
+
+/// Build the panicking fixture plugin and return its library
+fn build_panicking_plugin() -> PathBuf {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output = Command::new(env!("CARGO"))
+        .current_dir(&workspace)
+        .args([
+            "build",
+            "-p",
+            "kargo-panicking-plugin",
+            "--message-format=json",
+        ])
+        .arg("--target-dir")
+        .arg(Path::new(env!("CARGO_TARGET_TMPDIR")).join("panicking-plugin"))
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "building the fixture plugin failed"
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|message| {
+            message["reason"] == "compiler-artifact"
+                && message["target"]["name"] == "kargo_panicking_plugin"
+        })
+        .flat_map(|message| message["filenames"].as_array().cloned().unwrap_or_default())
+        .filter_map(|file| file.as_str().map(PathBuf::from))
+        .find(|file| {
+            file.extension()
+                .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION)
+        })
+        .expect("no fixture plugin library was built")
+}
+
+#[test]
+fn test_plugin_panics_become_errors_with_crash_reports() {
+    let config = tempfile::tempdir().unwrap();
+    let plugin = isolation::create(|| Box::new(Crashy)).unwrap();
+    assert_eq!(plugin.clap().get_name(), "crashy");
+
+    let error = ready(plugin.run(context(config.path(), &["crashy", "--all"])))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Plugin crashy crashed: index out of bounds"));
+
+    let error = ready(plugin.run(context(config.path(), &["crashy", "--early"])))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("bad arguments"));
+
+    let reports = crash_reports(config.path());
+    assert_eq!(reports.len(), 2);
+    let report = reports.iter().find(|r| r.contains("--all")).unwrap();
+    assert!(report.contains("version:  1.2.3"));
+    assert!(report.contains("kargo:    9.9.9"));
+    assert!(report.contains("backtrace:"));
+}
+
+#[test]
+fn test_panics_creating_a_plugin_become_errors() {
+    let error = isolation::create(|| panic!("no config")).err().unwrap();
+    assert!(format!("{:#}", error).contains("panicked while it was created: no config"));
+}
+
+#[test]
+fn test_panics_in_a_native_plugin_do_not_unwind_into_the_host() {
+    let library = build_panicking_plugin();
+    let config = tempfile::tempdir().unwrap();
+    let library = unsafe { Library::new(&library) }.unwrap();
+    let api_version: Symbol<ApiVersionFn> =
+        unsafe { library.get(b"kargo_plugin_api_version") }.unwrap();
+    assert_eq!(api_version(), API_VERSION);
+    let create: Symbol<CreateFn> = unsafe { library.get(b"kargo_plugin_create") }.unwrap();
+    let plugin = create().unwrap();
+
+    let error = ready(plugin.run(context(config.path(), &["panicking"])))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Plugin panicking crashed: index out of bounds"));
+    let error = ready(plugin.run(context(config.path(), &["panicking", "--early"])))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Plugin panicking crashed: bad arguments"));
+    assert_eq!(crash_reports(config.path()).len(), 2);
+
+    // Still running, and the plugin still answers
+    assert_eq!(plugin.clap().get_version(), Some("1.2.3"));
+    drop(plugin);
+}
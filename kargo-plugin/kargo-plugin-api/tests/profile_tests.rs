@@ -0,0 +1,45 @@
+use kargo_plugin_api::profile::{Phase, Profiler};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn test_nested_spans_fold_into_self_times() {
+    let profiler = Profiler::new("kargo upgrade");
+    {
+        let _plugin = profiler.span(Phase::Plugin, "upgrade");
+        // What the plugin records through the profiler in its context
+        let inside = profiler.nested(Phase::Plugin, "upgrade");
+        sleep(Duration::from_millis(5));
+        let _request = inside.span(Phase::Network, "index.crates.io;sparse");
+        sleep(Duration::from_millis(20));
+    }
+    profiler.finish();
+
+    let folded = profiler.folded();
+    let stacks: Vec<&str> = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert!(stacks.contains(&"kargo upgrade;plugin;upgrade"));
+    assert!(stacks.contains(&"kargo upgrade;plugin;upgrade;network;index.crates.io,sparse"));
+
+    let totals = profiler.phase_totals();
+    let network = totals[&Some(Phase::Network)];
+    let plugin = totals[&Some(Phase::Plugin)];
+    assert!(network >= Duration::from_millis(20));
+    // The request isn't counted twice
+    assert!(plugin >= Duration::from_millis(5) && plugin < network);
+    let summary = profiler.summary();
+    assert!(summary.lines().nth(1).unwrap().starts_with("network"));
+    assert!(summary.contains("total"));
+}
+
+#[test]
+fn test_disabled_profiler_records_nothing() {
+    let profiler = Profiler::disabled();
+    drop(profiler.span(Phase::Writes, "Cargo.toml"));
+    profiler.finish();
+    assert!(!profiler.is_enabled());
+    assert!(profiler.spans().is_empty());
+    assert_eq!(profiler.folded(), "");
+}
@@ -0,0 +1,32 @@
+use kargo_plugin_api::resume::Journal;
+
+#[test]
+fn test_recorded_results_survive_until_the_run_finishes() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut journal = Journal::open(dir.path(), "lookups");
+    assert_eq!(journal.resumed(), 0);
+    journal.record(
+        "repository:serde",
+        &Some("https://github.com/serde-rs/serde"),
+    );
+    journal.record("repository:anyhow", &None::<String>);
+    // Interrupted here: the next run picks up what was recorded
+    drop(journal);
+
+    let journal = Journal::open(dir.path(), "lookups");
+    assert_eq!(journal.resumed(), 2);
+    assert_eq!(
+        journal.get::<Option<String>>("repository:serde"),
+        Some(Some("https://github.com/serde-rs/serde".to_string()))
+    );
+    assert_eq!(
+        journal.get::<Option<String>>("repository:anyhow"),
+        Some(None)
+    );
+    assert_eq!(journal.get::<Option<String>>("repository:tokio"), None);
+    assert_eq!(Journal::open(dir.path(), "other").resumed(), 0);
+
+    journal.finish();
+    assert_eq!(Journal::open(dir.path(), "lookups").resumed(), 0);
+}
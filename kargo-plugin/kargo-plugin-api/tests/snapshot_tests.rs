@@ -0,0 +1,77 @@
+use kargo_plugin_api::snapshot::{KEEP_SNAPSHOTS, Snapshot};
+use std::fs;
+use std::path::Path;
+
+fn objects(root: &Path) -> usize {
+    fs::read_dir(root.join("objects"))
+        .unwrap()
+        .map(|prefix| fs::read_dir(prefix.unwrap().path()).unwrap().count())
+        .sum()
+}
+
+#[test]
+fn test_snapshots_share_stored_contents() {
+    let work = tempfile::tempdir().unwrap();
+    let root = work.path().join("snapshots");
+    let app = work.path().join("app.toml");
+    let lib = work.path().join("lib.toml");
+    fs::write(&app, "[dependencies]\nserde = \"1.0\"\n").unwrap();
+    fs::write(&lib, "[dependencies]\nserde = \"1.0\"\n").unwrap();
+
+    let mut first = Snapshot::create_in(&root, "upgrade");
+    first.record(&app).unwrap();
+    first.record(&lib).unwrap();
+    let mut second = Snapshot::create_in(&root, "migrate edition");
+    second.record(&app).unwrap();
+    assert_eq!(objects(&root), 1);
+
+    fs::write(&app, "[dependencies]\nserde = \"1.0.200\"\n").unwrap();
+    // Recorded once per snapshot, with the contents from before the change
+    second.record(&app).unwrap();
+    assert_eq!(second.files().len(), 1);
+
+    let listed = Snapshot::list(&root).unwrap();
+    let operations: Vec<&str> = listed.iter().map(|s| s.operation.as_str()).collect();
+    assert_eq!(operations, ["upgrade", "migrate-edition"]);
+    assert_eq!(listed[0].files, 2);
+
+    let opened = Snapshot::open(&root, Some(first.id())).unwrap();
+    opened.restore(&app).unwrap();
+    assert_eq!(
+        fs::read_to_string(&app).unwrap(),
+        "[dependencies]\nserde = \"1.0\"\n"
+    );
+    assert!(opened.contents(&work.path().join("other.toml")).is_err());
+}
+
+#[test]
+fn test_pruned_snapshots_take_their_contents_along() {
+    let work = tempfile::tempdir().unwrap();
+    let root = work.path().join("snapshots");
+    let manifest = work.path().join("Cargo.toml");
+
+    let mut ids = Vec::new();
+    for version in 0..=KEEP_SNAPSHOTS {
+        fs::write(
+            &manifest,
+            format!("[package]\nversion = \"0.{}.0\"\n", version),
+        )
+        .unwrap();
+        let mut snapshot = Snapshot::create_in(&root, "upgrade");
+        snapshot.record(&manifest).unwrap();
+        ids.push(snapshot.id().to_string());
+    }
+
+    let listed: Vec<String> = Snapshot::list(&root)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    assert_eq!(listed, ids[1..]);
+    assert_eq!(objects(&root), KEEP_SNAPSHOTS);
+    assert!(Snapshot::open(&root, Some(&ids[0])).is_err());
+    assert_eq!(
+        Snapshot::open(&root, None).unwrap().id(),
+        ids[KEEP_SNAPSHOTS]
+    );
+}
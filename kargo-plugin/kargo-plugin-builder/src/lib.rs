@@ -1,5 +1,5 @@
 use clap::{Arg, Command};
-use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginArgs, PluginCommand};
 use std::io::Read;
 use std::sync::Arc;
 
@@ -83,12 +83,19 @@ impl PluginBuilder {
             fn clap(&self) -> Command {
                 self.clap.clone()
             }
-            fn run(&self, ctx: ExecutionContext) -> BoxFuture {
+            fn run(&self, mut ctx: ExecutionContext) -> BoxFuture {
                 let run_closure = Arc::clone(&self.run);
                 let set = self.set.clone();
                 let regs = self.regs.clone();
                 let cbs = self.cbs.clone();
+                // Hosts that only pass the raw arguments get them parsed here,
+                // so handlers can always read `ctx.args`
+                let parsed = (ctx.args == PluginArgs::default() && !ctx.matched_args.is_empty())
+                    .then(|| PluginArgs::parse(self.clap.clone(), &ctx.matched_args));
                 Box::pin(async move {
+                    if let Some(parsed) = parsed {
+                        ctx.args = parsed?;
+                    }
                     // capture stdout while running
                     let mut stdout_buf = BufferRedirect::stdout()?;
                     let result = run_closure(ctx.clone()).await;
@@ -1,24 +1,329 @@
+//! `#[plugin]`, declaring a kargo plugin
+//!
+//! On a struct, the struct's fields become the plugin's arguments and the
+//! struct implements `kargo_plugin_api::Plugin` to run it. Each field is an
+//! argument with the field's doc comment as its help:
+//!
+//! - `bool` is a switch, `--field-name`
+//! - `Option<T>` is an optional `--field-name <FIELD_NAME>`
+//! - `Vec<T>` takes the option any number of times
+//! - any other `T` is a required option, unless it has a default
+//!
+//! `#[arg(...)]` on a field adjusts it: `short = 'x'`, `long = "name"`,
+//! `default = "value"`, and `positional` to take it without an option name.
+//! The command is named after the crate without its `kargo-` prefix and
+//! described by the struct's doc comment, or the crate's description; its
+//! version and authors are the crate's.
+//!
+//! On a function returning a `kargo_plugin_builder::PluginBuilder`, the
+//! plugin is the one the builder builds.
+//!
+//! Either way the plugin is exported for the host to load, along with the
+//! API version it was built against. The attribute takes `name = "..."` and
+//! `category = "..."` for struct plugins, and `kargo = "<version req>"` to
+//! declare which kargo versions the plugin supports.
+
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
-use syn::{ItemFn, parse_macro_input};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Attribute, Expr, ExprLit, Fields, GenericArgument, Item, ItemFn, ItemStruct, Lit, LitChar,
+    LitStr, Meta, PathArguments, Type, parse_macro_input,
+};
 
 #[proc_macro_attribute]
-pub fn plugin(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let func = parse_macro_input!(item as ItemFn);
+pub fn plugin(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut options = PluginOptions::default();
+    let parser = syn::meta::parser(|meta| {
+        let value = || meta.value()?.parse::<LitStr>();
+        if meta.path.is_ident("name") {
+            options.name = Some(value()?);
+        } else if meta.path.is_ident("category") {
+            options.category = Some(value()?);
+        } else if meta.path.is_ident("kargo") {
+            options.kargo = Some(value()?);
+        } else {
+            return Err(meta.error("expected `name`, `category` or `kargo`"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+
+    let expanded = match parse_macro_input!(item as Item) {
+        Item::Struct(item) => plugin_struct(item, &options),
+        Item::Fn(item) => plugin_fn(item, &options),
+        item => Err(syn::Error::new_spanned(
+            item,
+            "#[plugin] applies to a struct or a function returning a PluginBuilder",
+        )),
+    };
+    expanded
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct PluginOptions {
+    name: Option<LitStr>,
+    category: Option<LitStr>,
+    kargo: Option<LitStr>,
+}
+
+/// How a field is taken on the command line
+enum Kind {
+    Switch,
+    Optional(Type),
+    Many(Type),
+    Required(Type),
+}
+
+#[derive(Default)]
+struct ArgOptions {
+    short: Option<LitChar>,
+    long: Option<LitStr>,
+    default: Option<LitStr>,
+    positional: bool,
+}
+
+fn plugin_fn(func: ItemFn, options: &PluginOptions) -> syn::Result<TokenStream2> {
+    if let Some(option) = options.name.as_ref().or(options.category.as_ref()) {
+        return Err(syn::Error::new_spanned(
+            option,
+            "the builder names and categorizes function plugins",
+        ));
+    }
     let ident = &func.sig.ident;
-    let wrapper = format_ident!("__{}_wrapper", ident);
-    let vis = &func.vis;
+    let exports = exports(quote!(#ident().build_or_panic()), options);
 
-    TokenStream::from(quote! {
+    Ok(quote! {
         #func
 
-        #vis fn #wrapper() -> Box<dyn ::kargo_plugin_api::PluginCommand> {
-            #ident().build_or_panic()
+        #exports
+    })
+}
+
+fn plugin_struct(mut item: ItemStruct, options: &PluginOptions) -> syn::Result<TokenStream2> {
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "plugin structs cannot be generic",
+        ));
+    }
+    let fields = match &mut item.fields {
+        Fields::Named(fields) => fields.named.iter_mut().collect(),
+        Fields::Unit => Vec::new(),
+        Fields::Unnamed(fields) => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "the fields of a plugin struct need names",
+            ));
         }
+    };
+
+    let mut args = Vec::new();
+    let mut reads = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().expect("named field");
+        let arg_options = arg_options(&field.attrs)?;
+        field.attrs.retain(|attr| !attr.path().is_ident("arg"));
+
+        let id = ident.to_string().trim_start_matches("r#").to_string();
+        let long = match &arg_options.long {
+            Some(long) => long.value(),
+            None => id.replace('_', "-"),
+        };
+        let value_name = id.to_uppercase();
+        let help = doc(&field.attrs).map(|doc| quote!(.help(#doc)));
+        let short = arg_options
+            .short
+            .as_ref()
+            .map(|short| quote!(.short(#short)));
+        let default = arg_options
+            .default
+            .as_ref()
+            .map(|default| quote!(.default_value(#default)));
+        let named = (!arg_options.positional).then(|| quote!(.long(#long) #short));
+
+        let (arg, read) = match kind(&field.ty) {
+            Kind::Switch => {
+                if arg_options.positional {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "a switch cannot be positional",
+                    ));
+                }
+                (
+                    quote!(.action(__clap::ArgAction::SetTrue)),
+                    quote!(args.flag(#id)),
+                )
+            }
+            Kind::Optional(ty) => (
+                quote!(.value_name(#value_name)),
+                quote!(args.get::<#ty>(#id)?),
+            ),
+            Kind::Many(ty) if arg_options.positional => (
+                quote!(.value_name(#value_name).num_args(0..)),
+                quote!(args.get_many::<#ty>(#id)?),
+            ),
+            Kind::Many(ty) => (
+                quote!(.value_name(#value_name).action(__clap::ArgAction::Append)),
+                quote!(args.get_many::<#ty>(#id)?),
+            ),
+            Kind::Required(ty) => {
+                let required = arg_options
+                    .default
+                    .is_none()
+                    .then(|| quote!(.required(true)));
+                (
+                    quote!(.value_name(#value_name) #required),
+                    quote!(args.require::<#ty>(#id)?),
+                )
+            }
+        };
+        args.push(quote! {
+            .arg(__clap::Arg::new(#id) #named #arg #default #help)
+        });
+        reads.push(quote!(#ident: #read));
+    }
 
-        #[no_mangle]
-        pub extern "C" fn _kargo_plugin_create() -> Box<dyn ::kargo_plugin_api::PluginCommand> {
-            #wrapper()
+    let ident = &item.ident;
+    let name = match &options.name {
+        Some(name) => quote!(#name),
+        None => quote!(env!("CARGO_PKG_NAME").trim_start_matches("kargo-")),
+    };
+    let about = match doc(&item.attrs) {
+        Some(doc) => quote!(#doc),
+        None => quote!(env!("CARGO_PKG_DESCRIPTION")),
+    };
+    let construct = match item.fields {
+        Fields::Unit => quote!(Self),
+        _ => quote!(Self { #(#reads,)* }),
+    };
+    let help_info = options.category.as_ref().map(|category| {
+        quote! {
+            fn help_info() -> ::std::option::Option<::kargo_plugin_api::HelpInfo> {
+                ::std::option::Option::Some(::kargo_plugin_api::HelpInfo {
+                    category: ::std::option::Option::Some(#category.to_string()),
+                    ..::std::default::Default::default()
+                })
+            }
         }
+    });
+    let exports = exports(
+        quote!(::std::boxed::Box::new(
+            ::kargo_plugin_api::StructPlugin::<#ident>::new()
+        )),
+        options,
+    );
+
+    Ok(quote! {
+        #item
+
+        impl ::kargo_plugin_api::PluginDefinition for #ident {
+            fn command() -> ::kargo_plugin_api::__private::clap::Command {
+                use ::kargo_plugin_api::__private::clap as __clap;
+                __clap::Command::new(#name)
+                    .about(#about)
+                    .version(env!("CARGO_PKG_VERSION"))
+                    .author(env!("CARGO_PKG_AUTHORS"))
+                    #(#args)*
+            }
+
+            #[allow(unused_variables)]
+            fn from_args(
+                args: &::kargo_plugin_api::PluginArgs,
+            ) -> ::kargo_plugin_api::__private::anyhow::Result<Self> {
+                ::std::result::Result::Ok(#construct)
+            }
+
+            #help_info
+        }
+
+        #exports
     })
 }
+
+/// `kargo_plugin_create`, returning `create` with its panics caught, and the
+/// API version
+fn exports(create: TokenStream2, options: &PluginOptions) -> TokenStream2 {
+    let compat = match &options.kargo {
+        Some(requires) => quote!(::kargo_plugin_api::export_plugin_compat!(kargo = #requires);),
+        None => quote!(::kargo_plugin_api::export_plugin_compat!();),
+    };
+    quote! {
+        #[unsafe(no_mangle)]
+        #[allow(improper_ctypes_definitions)]
+        #[allow(unsafe_code)]
+        pub extern "C" fn kargo_plugin_create() -> ::kargo_plugin_api::__private::anyhow::Result<
+            ::std::boxed::Box<dyn ::kargo_plugin_api::PluginCommand>,
+        > {
+            ::kargo_plugin_api::isolation::create(|| #create)
+        }
+
+        #compat
+    }
+}
+
+fn arg_options(attrs: &[Attribute]) -> syn::Result<ArgOptions> {
+    let mut options = ArgOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("arg")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("short") {
+                options.short = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("long") {
+                options.long = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("default") {
+                options.default = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("positional") {
+                options.positional = true;
+            } else {
+                return Err(meta.error("expected `short`, `long`, `default` or `positional`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+/// The doc comment in `attrs`, one line per line
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(line),
+                    ..
+                }) => Some(line.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+fn kind(ty: &Type) -> Kind {
+    let Type::Path(path) = ty else {
+        return Kind::Required(ty.clone());
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return Kind::Required(ty.clone());
+    };
+    if segment.ident == "bool" {
+        return Kind::Switch;
+    }
+    let inner = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    match inner {
+        Some(inner) if segment.ident == "Option" => Kind::Optional(inner),
+        Some(inner) if segment.ident == "Vec" => Kind::Many(inner),
+        _ => Kind::Required(ty.clone()),
+    }
+}
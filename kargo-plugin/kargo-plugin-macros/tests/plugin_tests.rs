@@ -0,0 +1,92 @@
+use kargo_plugin_api::args::PluginArgs;
+use kargo_plugin_api::compat::API_VERSION;
+use kargo_plugin_api::{ExecutionContext, Plugin, PluginDefinition};
+use kargo_plugin_macros::plugin;
+
+/// Greet whoever is named
+#[plugin(name = "greet", category = "docs")]
+#[derive(Debug, PartialEq)]
+pub struct Greet {
+    /// Who to greet
+    #[arg(short = 'n', default = "world")]
+    name: String,
+    /// Greet in capitals
+    loud: bool,
+    /// Greet this many times
+    times: Option<u32>,
+    /// Languages to greet in
+    #[arg(long = "lang")]
+    languages: Vec<String>,
+    /// Files to greet too
+    #[arg(positional)]
+    files: Vec<String>,
+}
+
+impl Plugin for Greet {
+    async fn run(self, _ctx: ExecutionContext) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn parse(args: &[&str]) -> anyhow::Result<Greet> {
+    Greet::from_args(&PluginArgs::parse(Greet::command(), args)?)
+}
+
+#[test]
+fn test_fields_become_arguments() {
+    assert_eq!(
+        parse(&[
+            "greet", "-n", "kargo", "--loud", "--times", "3", "--lang", "en", "--lang", "fr",
+            "a.md", "b.md"
+        ])
+        .unwrap(),
+        Greet {
+            name: "kargo".to_string(),
+            loud: true,
+            times: Some(3),
+            languages: vec!["en".to_string(), "fr".to_string()],
+            files: vec!["a.md".to_string(), "b.md".to_string()],
+        }
+    );
+    assert_eq!(
+        parse(&["greet"]).unwrap(),
+        Greet {
+            name: "world".to_string(),
+            loud: false,
+            times: None,
+            languages: Vec::new(),
+            files: Vec::new(),
+        }
+    );
+    assert!(parse(&["greet", "--times", "often"]).is_err());
+}
+
+#[test]
+fn test_command_is_documented() {
+    let mut command = Greet::command();
+    assert_eq!(command.get_name(), "greet");
+    assert_eq!(command.get_version(), Some(env!("CARGO_PKG_VERSION")));
+    assert_eq!(
+        command.get_about().map(ToString::to_string).as_deref(),
+        Some("Greet whoever is named")
+    );
+    let help = command.render_help().to_string();
+    assert!(help.contains("-n, --name <NAME>"), "{}", help);
+    assert!(help.contains("Greet in capitals"), "{}", help);
+    assert!(help.contains("--lang <LANGUAGES>"), "{}", help);
+    assert!(help.contains("[FILES]..."), "{}", help);
+}
+
+#[test]
+fn test_plugin_is_exported() {
+    let command = kargo_plugin_create().unwrap();
+    assert_eq!(command.clap().get_name(), "greet");
+    assert_eq!(
+        command
+            .help_info()
+            .and_then(|info| info.category)
+            .as_deref(),
+        Some("docs")
+    );
+    assert_eq!(kargo_plugin_api_version(), API_VERSION);
+}
@@ -28,7 +28,7 @@ Example usage:
 
 import json
 from abc import ABC, abstractmethod
-from dataclasses import dataclass, asdict
+from dataclasses import dataclass, asdict, field
 from typing import List, Optional, Dict, Any
 
 @dataclass
@@ -39,12 +39,50 @@ class ArgDefinition:
     takes_value: bool = False
     short: Optional[str] = None
     long: Optional[str] = None
+    multiple: bool = False
+    last: bool = False
+    value_name: Optional[str] = None
+    # One of "string", "integer", "unsigned", "float", "bool", "path"
+    value_type: Optional[str] = None
+    possible_values: List[str] = field(default_factory=list)
+    default_values: List[str] = field(default_factory=list)
+    value_delimiter: Optional[str] = None
+    conflicts_with: List[str] = field(default_factory=list)
+    requires: List[str] = field(default_factory=list)
+    required_unless_present: List[str] = field(default_factory=list)
+    global_: bool = False
+    hide: bool = False
+
+@dataclass
+class ArgGroupDefinition:
+    name: str
+    args: List[str]
+    required: bool = False
+    multiple: bool = False
 
 @dataclass
 class CommandDefinition:
     name: str
     about: str
     args: List[ArgDefinition]
+    subcommands: List["CommandDefinition"] = field(default_factory=list)
+    groups: List[ArgGroupDefinition] = field(default_factory=list)
+    aliases: List[str] = field(default_factory=list)
+    subcommand_required: bool = False
+    arg_required_else_help: bool = False
+
+def _command_json(cmd: CommandDefinition) -> Dict[str, Any]:
+    data = asdict(cmd)
+
+    def rename(command: Dict[str, Any]) -> None:
+        # `global` is a Python keyword
+        for arg in command["args"]:
+            arg["global"] = arg.pop("global_")
+        for sub in command["subcommands"]:
+            rename(sub)
+
+    rename(data)
+    return data
 
 @dataclass
 class PluginMetadata:
@@ -71,7 +109,7 @@ class KargoPlugin(ABC):
 def get_command() -> str:
     plugin = _get_plugin_instance()
     cmd = plugin.get_command()
-    return json.dumps(asdict(cmd))
+    return json.dumps(_command_json(cmd))
 
 def execute(args_json: str) -> str:
     plugin = _get_plugin_instance()
@@ -25,23 +25,96 @@ pub trait WasmPlugin {
     fn get_metadata() -> String;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A plugin's command line, which the host turns into a clap command
+///
+/// Fields added after the first release are optional in the JSON, so
+/// plugins built against older versions of this crate keep working.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CommandDefinition {
     pub name: String,
     pub about: String,
+    #[serde(default)]
     pub args: Vec<ArgDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subcommands: Vec<CommandDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<ArgGroupDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub subcommand_required: bool,
+    /// Show the help instead of running when no arguments are given
+    #[serde(default)]
+    pub arg_required_else_help: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One argument. Arguments with neither `short` nor `long` are positional,
+/// in the order they are defined.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ArgDefinition {
     pub name: String,
     pub short: Option<char>,
     pub long: Option<String>,
     pub help: String,
     pub required: bool,
+    /// Without a value, an option is a flag
     pub takes_value: bool,
+    /// Accept the option repeatedly, or any number of positional values;
+    /// repeated flags are counted
+    #[serde(default)]
+    pub multiple: bool,
+    /// Positional that only takes the values after `--`
+    #[serde(default)]
+    pub last: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_name: Option<String>,
+    /// Type the values are validated as; anything is accepted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<ValueType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub possible_values: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub default_values: Vec<String>,
+    /// Split each value on this character
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_delimiter: Option<char>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_unless_present: Vec<String>,
+    /// Also accepted after the subcommands
+    #[serde(default)]
+    pub global: bool,
+    #[serde(default)]
+    pub hide: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    String,
+    Integer,
+    Unsigned,
+    Float,
+    Bool,
+    Path,
+}
+
+/// Arguments that belong together, e.g. alternatives of which one is required
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArgGroupDefinition {
+    pub name: String,
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// Allow more than one of the arguments at once
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+/// What the plugin is, returned as JSON from `_kargo_plugin_metadata`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginMetadata {
     pub name: String,
@@ -49,6 +122,39 @@ pub struct PluginMetadata {
     pub description: String,
     pub author: String,
     pub language: String, // "rust", "python", "typescript", "go", etc.
+    /// What the plugin needs from outside its sandbox; the user approves it
+    /// the first time the plugin loads
+    #[serde(default, skip_serializing_if = "Capabilities::is_empty")]
+    pub capabilities: Capabilities,
+    /// The kargo versions the plugin works with, as a semver requirement
+    /// such as `>=0.4, <0.6`; others skip it when loading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_kargo: Option<String>,
+}
+
+/// Access a plugin asks the host for. Anything not declared here, or not
+/// approved by the user, is refused: host functions fail and HTTP requests
+/// to other hosts are blocked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    /// Files and directories read with `read_file`, everything below a
+    /// directory included; relative paths are relative to where kargo runs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fs_read: Vec<String>,
+    /// Hosts reached with Extism's `http_request`; `*` wildcards allowed, as
+    /// in `*.github.com`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<String>,
+    /// Environment variables read with `get_env`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+}
+
+impl Capabilities {
+    pub fn is_empty(&self) -> bool {
+        self.fs_read.is_empty() && self.network.is_empty() && self.env.is_empty()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,56 @@
+//! Criterion benchmarks for markdown rendering throughput on large crates.
+//!
+//! Uses the same `MDDOC_BENCH_FIXTURES` directory of rustdoc JSON as the
+//! `streaming` bench (see there for how to produce tokio/windows-sys fixtures).
+//! To compare against an older renderer, run
+//! `cargo bench -p kargo-mddoc --bench render -- --save-baseline before` on the
+//! old revision and `-- --baseline before` on the new one.
+
+use std::io;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use kargo_mddoc::markdown::{load_crate, rustdoc_json_to_markdown, write_markdown};
+
+fn fixtures() -> Vec<PathBuf> {
+    let Some(dir) = std::env::var_os("MDDOC_BENCH_FIXTURES") else {
+        eprintln!("MDDOC_BENCH_FIXTURES not set; no fixtures to benchmark");
+        return Vec::new();
+    };
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(dir)
+        .expect("Failed to read fixture directory")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+
+    for fixture in fixtures() {
+        let name = fixture
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let data = load_crate(&fixture).expect("Failed to load fixture");
+        let rendered = rustdoc_json_to_markdown(&data);
+        group.throughput(Throughput::Bytes(rendered.len() as u64));
+        drop(rendered);
+
+        group.bench_with_input(BenchmarkId::new("to_string", &name), &data, |b, data| {
+            b.iter(|| rustdoc_json_to_markdown(data))
+        });
+        group.bench_with_input(BenchmarkId::new("to_writer", &name), &data, |b, data| {
+            b.iter(|| write_markdown(data, &mut io::sink()).expect("sink cannot fail"))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
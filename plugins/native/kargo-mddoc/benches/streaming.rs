@@ -0,0 +1,115 @@
+//! Peak-memory and wall-time comparison of buffered vs streaming markdown rendering.
+//!
+//! Fixtures are real rustdoc JSON files, which are too large to check in. Generate
+//! tokio- and windows-sys-sized ones with:
+//!
+//! ```sh
+//! cargo +nightly rustdoc -p tokio --lib --all-features -- -Z unstable-options --output-format json
+//! cargo +nightly rustdoc -p windows-sys --lib --all-features -- -Z unstable-options --output-format json
+//! ```
+//!
+//! copy the resulting `target/doc/*.json` files into one directory and run
+//! `MDDOC_BENCH_FIXTURES=<dir> cargo bench -p kargo-mddoc --bench streaming`.
+//!
+//! Each mode runs in a child process so the reported high-water mark (VmHWM)
+//! belongs to that mode alone.
+
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use kargo_mddoc::markdown::{load_crate, rustdoc_json_to_markdown, write_markdown};
+use rustdoc_types::Crate;
+
+const MODES: [&str; 2] = ["buffered", "streaming"];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let (Some(mode), Some(fixture)) = (args.get(2), args.get(3)) {
+        if args[1] == "--child" {
+            run_child(mode, Path::new(fixture));
+            return;
+        }
+    }
+
+    let Some(dir) = std::env::var_os("MDDOC_BENCH_FIXTURES").map(PathBuf::from) else {
+        eprintln!("MDDOC_BENCH_FIXTURES not set; skipping (see benches/streaming.rs for setup)");
+        return;
+    };
+
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("Failed to read fixture directory")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    fixtures.sort();
+
+    println!(
+        "{:<24} {:>10} {:>10} {:>12} {:>14}",
+        "fixture", "json MiB", "mode", "time", "peak RSS MiB"
+    );
+    let exe = std::env::current_exe().expect("Failed to locate bench binary");
+    for fixture in &fixtures {
+        let size = fs::metadata(fixture).map(|m| m.len()).unwrap_or(0);
+        for mode in MODES {
+            let output = Command::new(&exe)
+                .arg("--child")
+                .arg(mode)
+                .arg(fixture)
+                .output()
+                .expect("Failed to run bench child");
+            let report = String::from_utf8_lossy(&output.stdout);
+            let mut fields = report.split_whitespace();
+            let (elapsed, peak) = (fields.next().unwrap_or("?"), fields.next().unwrap_or("?"));
+            println!(
+                "{:<24} {:>10.1} {:>10} {:>12} {:>14}",
+                fixture.file_name().unwrap_or_default().to_string_lossy(),
+                size as f64 / (1024.0 * 1024.0),
+                mode,
+                elapsed,
+                peak
+            );
+        }
+    }
+}
+
+fn run_child(mode: &str, fixture: &Path) {
+    let out_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let out_path = out_dir.path().join("out.md");
+    let start = Instant::now();
+
+    match mode {
+        // The pre-streaming pipeline: whole JSON text, whole Crate, whole String
+        "buffered" => {
+            let json = fs::read_to_string(fixture).expect("Failed to read fixture");
+            let data: Crate = serde_json::from_str(&json).expect("Failed to parse fixture");
+            let markdown = rustdoc_json_to_markdown(&data);
+            fs::write(&out_path, markdown).expect("Failed to write markdown");
+        }
+        "streaming" => {
+            let data = load_crate(fixture).expect("Failed to load fixture");
+            let mut file =
+                BufWriter::new(fs::File::create(&out_path).expect("Failed to create output"));
+            write_markdown(&data, &mut file).expect("Failed to write markdown");
+        }
+        other => panic!("unknown mode {}", other),
+    }
+
+    println!("{:.2?} {}", start.elapsed(), peak_rss_mib());
+}
+
+/// High-water resident set size from /proc (Linux only)
+fn peak_rss_mib() -> String {
+    fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("VmHWM:"))
+                .and_then(|kb| kb.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        })
+        .map(|kb| format!("{:.1}", kb as f64 / 1024.0))
+        .unwrap_or_else(|| "n/a".to_string())
+}
@@ -0,0 +1,121 @@
+//! `#[doc(hidden)]` and `cfg` attributes of documented items
+//!
+//! rustdoc leaves `#[doc(hidden)]` items out of its JSON unless asked for
+//! them with `--document-hidden-items`, which mddoc passes for
+//! `--include-hidden`; the items are then marked as hidden where they're
+//! rendered. Items behind a `cfg` say which configuration they need, like the
+//! "Available on ... only" banner of rustdoc's HTML. Both are taken out of
+//! the plain attribute list.
+
+use rustdoc_types::Item;
+
+/// Whether `item` is marked `#[doc(hidden)]`
+pub fn is_hidden(item: &Item) -> bool {
+    item.attrs.iter().any(|attr| hides(attr))
+}
+
+/// Whether `attr` is a `#[doc(hidden)]`, possibly among other `doc` arguments
+pub fn hides(attr: &str) -> bool {
+    doc_arguments(attr).is_some_and(|args| split_top_level(args).any(|arg| arg == "hidden"))
+}
+
+/// The configuration `item` needs, combined with `all(..)` when it has
+/// several `cfg` or `doc(cfg)` attributes
+pub fn required_cfg(item: &Item) -> Option<String> {
+    let predicates: Vec<&str> = item.attrs.iter().filter_map(|a| cfg_predicate(a)).collect();
+    match predicates.as_slice() {
+        [] => None,
+        [predicate] => Some(predicate.to_string()),
+        _ => Some(format!("all({})", predicates.join(", "))),
+    }
+}
+
+/// Attributes of `item` other than `doc(hidden)` and `cfg`, for the plain
+/// attribute list
+pub fn other_attributes(item: &Item) -> impl Iterator<Item = &String> {
+    item.attrs
+        .iter()
+        .filter(|attr| cfg_predicate(attr).is_none() && !hides(attr))
+}
+
+/// Markdown notes on `item` being hidden or behind a `cfg`, each its own
+/// paragraph; empty for most items
+pub fn notes(item: &Item) -> String {
+    let mut notes = String::new();
+    if is_hidden(item) {
+        notes
+            .push_str("**Hidden:** marked `#[doc(hidden)]`, so not part of the documented API\n\n");
+    }
+    if let Some(cfg) = required_cfg(item) {
+        notes.push_str(&format!("**Available on {} only**\n\n", describe_cfg(&cfg)));
+    }
+    notes
+}
+
+/// `cfg` predicate in readable form: `crate feature `std`` for a single
+/// feature, the predicate in backticks otherwise
+pub fn describe_cfg(predicate: &str) -> String {
+    let feature = predicate
+        .strip_prefix("feature")
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix('='))
+        .map(str::trim)
+        .and_then(|value| value.strip_prefix('"')?.strip_suffix('"'));
+    match feature {
+        Some(feature) if !feature.contains('"') => format!("crate feature `{}`", feature),
+        _ => format!("`{}`", predicate),
+    }
+}
+
+/// The contents of a `#[...]` attribute
+fn attribute_body(attr: &str) -> Option<&str> {
+    attr.trim()
+        .strip_prefix("#[")
+        .or_else(|| attr.trim().strip_prefix("#!["))?
+        .strip_suffix(']')
+        .map(str::trim)
+}
+
+/// The arguments of a `#[doc(...)]` attribute
+fn doc_arguments(attr: &str) -> Option<&str> {
+    call_arguments(attribute_body(attr)?, "doc")
+}
+
+/// The predicate of a `#[cfg(...)]` or `#[doc(cfg(...))]` attribute
+pub fn cfg_predicate(attr: &str) -> Option<&str> {
+    let body = attribute_body(attr)?;
+    call_arguments(body, "cfg").or_else(|| {
+        let args = call_arguments(body, "doc")?;
+        split_top_level(args).find_map(|arg| call_arguments(arg, "cfg"))
+    })
+}
+
+/// `args` of `name(args)`
+fn call_arguments<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+        .map(str::trim)
+}
+
+/// The comma separated parts of `args`, leaving commas inside parentheses
+/// and strings alone
+fn split_top_level(args: &str) -> impl Iterator<Item = &str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0usize, false, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.into_iter().filter(|part| !part.is_empty())
+}
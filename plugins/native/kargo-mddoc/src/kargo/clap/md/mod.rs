@@ -45,6 +45,10 @@ struct Subcommand {
     /// Include private items in documentation
     #[clap(long)]
     document_private_items: bool,
+
+    /// Include `#[doc(hidden)]` items in documentation
+    #[clap(long)]
+    include_hidden: bool,
 }
 
 #[allow(dead_code)]
@@ -77,6 +81,8 @@ fn main() -> anyhow::Result<()> {
         skip_component_check: cli.skip_component_check,
         verbose: cli.verbose,
         document_private_items: cli.document_private_items,
+        include_hidden: cli.include_hidden,
+        target_dir: None,
     };
 
     // Generate the documentation
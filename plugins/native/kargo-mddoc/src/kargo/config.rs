@@ -23,6 +23,12 @@ pub struct Config {
 
     /// Include private items in documentation
     pub document_private_items: bool,
+
+    /// Include `#[doc(hidden)]` items in documentation
+    pub include_hidden: bool,
+
+    /// Cargo target directory, instead of one inside the temporary project
+    pub target_dir: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -35,6 +41,8 @@ impl Default for Config {
             skip_component_check: false,
             verbose: false,
             document_private_items: false,
+            include_hidden: false,
+            target_dir: None,
         }
     }
 }
@@ -0,0 +1,267 @@
+//! Documentation coverage of a crate's public API
+//!
+//! [`Coverage::of`] walks rustdoc JSON from the crate root through its public
+//! modules and checks every public item for docs: the items of each module,
+//! fields and variants, trait items and the methods and constants of
+//! inherent impls. Trait impls are left out, since their items are
+//! documented by the trait. Items re-exported from private modules count
+//! where they're re-exported, and `#[doc(hidden)]` items not at all.
+
+use crate::attributes::is_hidden;
+use rustdoc_types::{Crate, Id, Item, ItemEnum, StructKind, VariantKind, Visibility};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Documented and undocumented public items of one module
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleCoverage {
+    pub documented: usize,
+    /// Paths of the undocumented items, relative to the module
+    pub missing: Vec<String>,
+}
+
+impl ModuleCoverage {
+    pub fn total(&self) -> usize {
+        self.documented + self.missing.len()
+    }
+}
+
+/// Documentation coverage of a crate, by module path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    pub crate_name: String,
+    pub modules: BTreeMap<String, ModuleCoverage>,
+}
+
+impl Coverage {
+    /// Check the public items of `data` for docs
+    pub fn of(data: &Crate) -> Self {
+        let crate_name = data
+            .index
+            .get(&data.root)
+            .and_then(|root| root.name.clone())
+            .unwrap_or_default();
+        let mut walk = Walk {
+            data,
+            seen: HashSet::new(),
+            globbed: HashSet::new(),
+            coverage: Coverage {
+                crate_name: crate_name.clone(),
+                modules: BTreeMap::new(),
+            },
+        };
+        if let Some(root) = data.index.get(&data.root) {
+            walk.item(root, "", None);
+        }
+        walk.coverage
+    }
+
+    pub fn documented(&self) -> usize {
+        self.modules.values().map(|m| m.documented).sum()
+    }
+
+    pub fn total(&self) -> usize {
+        self.modules.values().map(ModuleCoverage::total).sum()
+    }
+
+    /// Percentage of public items with docs; 100 for a crate without any
+    pub fn percent(&self) -> f64 {
+        match self.total() {
+            0 => 100.0,
+            total => self.documented() as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for Coverage {
+    /// Summary line, then the undocumented items of each module lacking docs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Documentation coverage of {}: {:.1}% ({}/{} public items)",
+            self.crate_name,
+            self.percent(),
+            self.documented(),
+            self.total()
+        )?;
+        for (path, module) in &self.modules {
+            if module.missing.is_empty() {
+                continue;
+            }
+            writeln!(
+                f,
+                "\n{}: {}/{} documented",
+                path,
+                module.documented,
+                module.total()
+            )?;
+            for item in &module.missing {
+                writeln!(f, "  - {}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Walk<'a> {
+    data: &'a Crate,
+    seen: HashSet<&'a Id>,
+    /// Modules whose items were glob re-exported, against cycles
+    globbed: HashSet<&'a Id>,
+    coverage: Coverage,
+}
+
+impl<'a> Walk<'a> {
+    /// Count `item` in `module` under `name`, defaulting to its own, then
+    /// the items below it
+    fn item(&mut self, item: &'a Item, module: &str, name: Option<&str>) {
+        // Hidden items are deliberately left out of the documented API
+        if !self.seen.insert(&item.id) || is_hidden(item) {
+            return;
+        }
+        let data = self.data;
+        let name = name.or(item.name.as_deref()).unwrap_or_default();
+        match &item.inner {
+            ItemEnum::Module(inner) => {
+                let path = match module {
+                    "" => name.to_string(),
+                    _ => format!("{}::{}", module, name),
+                };
+                // Module docs count towards the module itself
+                self.record(item, &path, "(module docs)".to_string());
+                for id in &inner.items {
+                    self.member(id, &path);
+                }
+            }
+            ItemEnum::Struct(struct_) => {
+                self.record(item, module, name.to_string());
+                let fields: Vec<&Id> = match &struct_.kind {
+                    StructKind::Unit => Vec::new(),
+                    StructKind::Tuple(ids) => ids.iter().flatten().collect(),
+                    StructKind::Plain { fields, .. } => fields.iter().collect(),
+                };
+                self.fields(&fields, module, name);
+                self.inherent(&struct_.impls, module, name);
+            }
+            ItemEnum::Union(union_) => {
+                self.record(item, module, name.to_string());
+                let fields: Vec<&Id> = union_.fields.iter().collect();
+                self.fields(&fields, module, name);
+                self.inherent(&union_.impls, module, name);
+            }
+            ItemEnum::Enum(enum_) => {
+                self.record(item, module, name.to_string());
+                for variant in enum_.variants.iter().filter_map(|id| data.index.get(id)) {
+                    let variant_name = variant.name.as_deref().unwrap_or_default();
+                    self.record(variant, module, format!("{}::{}", name, variant_name));
+                    if let ItemEnum::Variant(inner) = &variant.inner {
+                        // Variant fields are public along with the enum
+                        let fields: Vec<&Id> = match &inner.kind {
+                            VariantKind::Plain => Vec::new(),
+                            VariantKind::Tuple(ids) => ids.iter().flatten().collect(),
+                            VariantKind::Struct { fields, .. } => fields.iter().collect(),
+                        };
+                        let owner = format!("{}::{}", name, variant_name);
+                        for field in fields.iter().filter_map(|id| data.index.get(*id)) {
+                            self.named(field, module, &owner);
+                        }
+                    }
+                }
+                self.inherent(&enum_.impls, module, name);
+            }
+            ItemEnum::Trait(trait_) => {
+                self.record(item, module, name.to_string());
+                for member in trait_.items.iter().filter_map(|id| data.index.get(id)) {
+                    self.named(member, module, name);
+                }
+            }
+            _ => self.record(item, module, name.to_string()),
+        }
+    }
+
+    /// Count the public member `id` of `module`, following re-exports of
+    /// items defined in this crate
+    fn member(&mut self, id: &'a Id, module: &str) {
+        let data = self.data;
+        let Some(item) = data.index.get(id) else {
+            return;
+        };
+        if !matches!(item.visibility, Visibility::Public) {
+            return;
+        }
+        match &item.inner {
+            ItemEnum::Use(use_) => {
+                let Some(target) = use_.id.as_ref().and_then(|id| data.index.get(id)) else {
+                    // Re-exports of other crates are documented there
+                    return;
+                };
+                match (&target.inner, use_.is_glob) {
+                    (ItemEnum::Module(inner), true) => {
+                        if self.globbed.insert(&target.id) {
+                            for id in &inner.items {
+                                self.member(id, module);
+                            }
+                        }
+                    }
+                    // Variants glob imported from an enum count with the enum
+                    (_, true) => {}
+                    _ => self.item(target, module, Some(&use_.name)),
+                }
+            }
+            ItemEnum::Impl(_) | ItemEnum::ExternCrate { .. } => {}
+            _ => self.item(item, module, None),
+        }
+    }
+
+    /// Count the public fields among `ids` of the type `owner`
+    fn fields(&mut self, ids: &[&'a Id], module: &str, owner: &str) {
+        let data = self.data;
+        for field in ids.iter().filter_map(|id| data.index.get(*id)) {
+            if matches!(field.visibility, Visibility::Public) {
+                self.named(field, module, owner);
+            }
+        }
+    }
+
+    /// Count the public items of the inherent impls among `impls`
+    fn inherent(&mut self, impls: &'a [Id], module: &str, owner: &str) {
+        let data = self.data;
+        for impl_ in impls.iter().filter_map(|id| data.index.get(id)) {
+            let ItemEnum::Impl(inner) = &impl_.inner else {
+                continue;
+            };
+            if inner.trait_.is_some() || inner.is_synthetic || inner.blanket_impl.is_some() {
+                continue;
+            }
+            for member in inner.items.iter().filter_map(|id| data.index.get(id)) {
+                if matches!(member.visibility, Visibility::Public) {
+                    self.named(member, module, owner);
+                }
+            }
+        }
+    }
+
+    /// Count `item` as `owner::name`
+    fn named(&mut self, item: &'a Item, module: &str, owner: &str) {
+        if self.seen.insert(&item.id) {
+            let name = item.name.as_deref().unwrap_or_default();
+            self.record(item, module, format!("{}::{}", owner, name));
+        }
+    }
+
+    fn record(&mut self, item: &Item, module: &str, name: String) {
+        if is_hidden(item) {
+            return;
+        }
+        let coverage = self.coverage.modules.entry(module.to_string()).or_default();
+        if item
+            .docs
+            .as_deref()
+            .is_some_and(|docs| !docs.trim().is_empty())
+        {
+            coverage.documented += 1;
+        } else {
+            coverage.missing.push(name);
+        }
+    }
+}
@@ -50,6 +50,12 @@ pub enum Error {
     #[error("Failed to convert JSON to Markdown: {0}")]
     MarkdownConversionFailed(String),
 
+    #[error("Template error: {0}")]
+    Template(String),
+
     #[error("Other error: {0}")]
     Other(String),
+
+    #[error(transparent)]
+    ReadOnly(#[from] kargo_plugin_api::ReadOnlyError),
 }
@@ -1,11 +1,14 @@
 use crate::config::Config;
 use crate::error::Error;
+use crate::links;
 use crate::package::PackageSpec;
 use crate::toolchain::Toolchain;
 use crate::utils;
 use indicatif::{ProgressBar, ProgressStyle};
+use kargo_plugin_api::cargo_runner::CargoCommand;
 use log::{debug, info, warn};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -84,6 +87,19 @@ impl DocGenerator {
         Ok(output_file)
     }
 
+    /// Versions locked for the documented package and its dependencies,
+    /// for linking to their docs.rs pages. Empty before [`run`](Self::run).
+    pub fn locked_versions(&self) -> HashMap<String, String> {
+        let lockfile = self.project_dir.join("Cargo.lock");
+        match std::fs::read_to_string(&lockfile) {
+            Ok(content) => links::locked_versions(&content),
+            Err(e) => {
+                debug!("Cannot read {}: {}", lockfile.display(), e);
+                HashMap::new()
+            }
+        }
+    }
+
     /// Set up progress bar for visual feedback
     fn setup_progress_bar(&self) -> ProgressBar {
         let pb = ProgressBar::new(5);
@@ -178,10 +194,8 @@ edition = "2021"
     fn fetch_dependencies(&self) -> Result<(), Error> {
         debug!("Fetching dependencies");
 
-        let output = Toolchain::run_command(
-            "cargo",
-            &["fetch"],
-            Some(&self.project_dir),
+        let output = Toolchain::run_cargo(
+            &CargoCommand::new("fetch").current_dir(&self.project_dir),
             self.config.verbose,
         )?;
 
@@ -197,28 +211,40 @@ edition = "2021"
     fn generate_documentation(&self) -> Result<(), Error> {
         debug!("Generating JSON documentation for {}", self.package_spec);
 
-        // Prepare rustdoc arguments
-        let mut args = vec![
-            "+nightly",
+        let mut command = CargoCommand::new("rustdoc").toolchain("nightly").args([
             "-Zunstable-options",
-            "rustdoc",
             "--output-format",
             "json",
             "--package",
-            &self.package_spec.name,
-        ];
+            self.package_spec.name.as_str(),
+        ]);
 
         // Add option for private items if requested
         if self.config.document_private_items {
-            args.push("--document-private-items");
+            command = command.arg("--document-private-items");
+        }
+        if self.config.include_hidden {
+            command = command.arg("--document-hidden-items");
         }
 
         // Note: Standard rustdoc JSON generation includes all public items by default
         // No additional flags needed for public API documentation
 
+        // A shared target directory may still hold the JSON of another
+        // version of this package
+        let json = self.json_path();
+        if json.exists() {
+            std::fs::remove_file(&json)?;
+        }
+
         // Run cargo with rustdoc
-        let output =
-            Toolchain::run_command("cargo", &args, Some(&self.project_dir), self.config.verbose)?;
+        let target_dir = self.target_dir();
+        let output = Toolchain::run_cargo(
+            &command
+                .target_dir(Some(&target_dir))
+                .current_dir(&self.project_dir),
+            self.config.verbose,
+        )?;
 
         debug!(
             "Documentation generated successfully: {}",
@@ -228,21 +254,32 @@ edition = "2021"
         Ok(())
     }
 
+    /// The configured target directory, or the temporary project's own
+    fn target_dir(&self) -> PathBuf {
+        self.config
+            .target_dir
+            .clone()
+            .unwrap_or_else(|| self.project_dir.join("target"))
+    }
+
+    /// Where rustdoc writes the JSON, named after the crate rather than the
+    /// package
+    fn json_path(&self) -> PathBuf {
+        self.target_dir()
+            .join("doc")
+            .join(format!("{}.json", self.package_spec.name.replace('-', "_")))
+    }
+
     /// Find and copy the generated documentation
     fn process_documentation(&self) -> Result<PathBuf, Error> {
         debug!("Looking for generated documentation files");
 
-        // Find the generated JSON file in target/doc
-        let doc_dir = self.project_dir.join("target").join("doc");
-        let pattern = format!("{}.json", self.package_spec.name);
-        let files = utils::find_files(&doc_dir, &pattern)?;
-
-        if files.is_empty() {
+        // The shared target directory holds other crates' JSON too, so look
+        // for this crate's file only
+        let source_file = &self.json_path();
+        if !utils::file_exists(source_file) {
             return Err(Error::DocNotFound);
         }
-
-        // Use the first matching file
-        let source_file = &files[0];
         debug!("Found documentation file: {}", source_file.display());
 
         // Make sure output directory exists
@@ -258,6 +295,53 @@ edition = "2021"
     }
 }
 
+/// Generate rustdoc JSON for the library of the local package in `dir`,
+/// honoring the toolchain and target directory settings of `config`, and
+/// return where rustdoc wrote it
+pub fn generate_local(dir: &Path, config: &Config) -> Result<PathBuf, Error> {
+    let manifest = utils::read_file(&dir.join("Cargo.toml"))?;
+    let doc = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| Error::TomlParse(e.to_string()))?;
+    let library = doc
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .or_else(|| doc.get("package").and_then(|package| package.get("name")))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| Error::PackageNotFound(format!("{} has no [package] name", dir.display())))?
+        .replace('-', "_");
+
+    Toolchain::check_cargo()?;
+    if !config.skip_component_check {
+        Toolchain::check_rustup()?;
+        Toolchain::ensure_nightly_toolchain()?;
+        Toolchain::ensure_rustdoc_component()?;
+    }
+
+    let target_dir = config
+        .target_dir
+        .clone()
+        .unwrap_or_else(|| dir.join("target"));
+    let mut command = CargoCommand::new("rustdoc")
+        .toolchain("nightly")
+        .args(["-Zunstable-options", "--lib", "--output-format", "json"])
+        .target_dir(Some(&target_dir))
+        .current_dir(dir);
+    if config.document_private_items {
+        command = command.arg("--document-private-items");
+    }
+    if config.include_hidden {
+        command = command.arg("--document-hidden-items");
+    }
+    Toolchain::run_cargo(&command, config.verbose)?;
+
+    let json = target_dir.join("doc").join(format!("{}.json", library));
+    if !utils::file_exists(&json) {
+        return Err(Error::DocNotFound);
+    }
+    Ok(json)
+}
+
 impl Drop for DocGenerator {
     fn drop(&mut self) {
         // Clean up temporary directory if needed
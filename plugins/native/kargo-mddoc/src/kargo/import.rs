@@ -0,0 +1,599 @@
+//! Documentation for crates that can't be built here.
+//!
+//! `mddoc` normally builds a crate's rustdoc JSON itself, which takes the
+//! crate's toolchain and all of its build dependencies. [`import`] writes the
+//! same Markdown from documentation built elsewhere instead:
+//!
+//! - the rustdoc JSON docs.rs built, in the format version this mddoc reads
+//!   (see [`docs_rs_json_url`])
+//! - a rustdoc JSON file, plain or gzipped, from `cargo rustdoc` on a machine
+//!   that can build the crate
+//! - a directory of rustdoc HTML, like `target/doc/<crate>` or a saved copy of
+//!   the crate's pages on docs.rs
+//!
+//! JSON goes through the renderers a local build uses, so the pages are the
+//! same. HTML has less structure to go on: the single page it becomes keeps
+//! the module tree, each item's declaration and its documentation, under the
+//! same headings.
+
+use crate::links::LinkConfig;
+use crate::markdown::convert_to_markdown_with;
+use crate::multipage_markdown::{convert_to_multipage_markdown, MultipageConfig};
+use crate::package::PackageSpec;
+use crate::templates::{CrateContext, GeneratorContext, PageContext, Templates};
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use kargo_plugin_api::{HttpClient, WriteGuard};
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Where the documentation comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportSource {
+    /// Download the rustdoc JSON docs.rs built
+    DocsRs,
+    /// A rustdoc JSON file; gzipped when it ends in `.gz`
+    Json(PathBuf),
+    /// A directory of rustdoc HTML, the one with the crate's `index.html`
+    Html(PathBuf),
+}
+
+impl ImportSource {
+    /// What `--from` points at: a directory is rustdoc HTML, a file rustdoc
+    /// JSON; docs.rs without it
+    pub fn from_path(path: Option<&Path>) -> Self {
+        match path {
+            Some(path) if path.is_dir() => Self::Html(path.to_path_buf()),
+            Some(path) => Self::Json(path.to_path_buf()),
+            None => Self::DocsRs,
+        }
+    }
+}
+
+/// What to import and how to lay it out
+#[derive(Debug, Clone)]
+pub struct ImportConfig {
+    pub package: PackageSpec,
+    pub source: ImportSource,
+    pub output_dir: PathBuf,
+    /// One page per item, as `--multipage`; needs JSON
+    pub multipage: bool,
+    pub base_url: String,
+    pub templates: Templates,
+    pub links: LinkConfig,
+}
+
+/// Import the documentation `config` describes, returning the pages written
+pub async fn import(config: &ImportConfig) -> Result<Vec<PathBuf>> {
+    WriteGuard::current().create_dir_all(&config.output_dir)?;
+    let json = match &config.source {
+        ImportSource::Html(dir) => return Ok(vec![import_html(dir, config)?]),
+        ImportSource::Json(path) => std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .and_then(|bytes| match path.extension().and_then(|e| e.to_str()) {
+                Some("gz") => gunzip(&bytes),
+                _ => Ok(bytes),
+            })?,
+        ImportSource::DocsRs => download(&config.package).await?,
+    };
+    let json_path = config.output_dir.join(config.package.json_filename());
+    WriteGuard::current().write(&json_path, &json)?;
+    import_json(&json_path, config)
+}
+
+/// Render the rustdoc JSON at `json_path` as a local build would
+pub fn import_json(json_path: &Path, config: &ImportConfig) -> Result<Vec<PathBuf>> {
+    let rendered = if config.multipage {
+        convert_to_multipage_markdown(
+            json_path,
+            MultipageConfig {
+                output_dir: config.output_dir.clone(),
+                base_url: config.base_url.clone(),
+                generate_index: true,
+                max_items_per_page: 50,
+                templates: config.templates.clone(),
+                links: config.links.clone(),
+            },
+        )
+    } else {
+        convert_to_markdown_with(json_path, &config.templates, &config.links).map(|page| vec![page])
+    };
+    rendered.map_err(|e| match format_version(json_path) {
+        Some(version) if version != rustdoc_types::FORMAT_VERSION => anyhow!(
+            "{} is rustdoc JSON format {}, this mddoc reads format {}",
+            json_path.display(),
+            version,
+            rustdoc_types::FORMAT_VERSION
+        ),
+        _ => e.into(),
+    })
+}
+
+/// Render the rustdoc HTML in `dir` as the single page, returning its path
+pub fn import_html(dir: &Path, config: &ImportConfig) -> Result<PathBuf> {
+    if config.multipage {
+        bail!("--multipage needs rustdoc JSON; rustdoc HTML becomes a single page");
+    }
+    let index = read_page(&dir.join("index.html"))?;
+    let file = config.package.markdown_filename();
+    let version = find_element(&index, "<span class=\"version\"")
+        .map(plain_text)
+        .or_else(|| {
+            config
+                .package
+                .version
+                .as_deref()
+                .map(|v| v.trim_start_matches('=').to_string())
+        });
+    let page = PageContext {
+        kind: "crate".to_string(),
+        title: "Crate Documentation".to_string(),
+        file: file.clone(),
+        krate: CrateContext {
+            name: config.package.name.clone(),
+            version: version.clone(),
+        },
+        item: None,
+        items: Vec::new(),
+        generator: GeneratorContext::default(),
+    };
+
+    let mut output = config.templates.page_start(&page)?;
+    if let Some(version) = &version {
+        let _ = write!(output, "**Version:** {}\n\n", version);
+    }
+    let _ = write!(output, "# Module `{}`\n\n", config.package.name);
+    push_docs(&mut output, &index);
+    module_html(dir, 2, &mut output)?;
+    output.push_str(&config.templates.page_end(&page)?);
+
+    let path = config.output_dir.join(file);
+    WriteGuard::current().write(&path, output)?;
+    log::info!("Markdown documentation imported at: {}", path.display());
+    Ok(path)
+}
+
+/// URL of the rustdoc JSON docs.rs built for `package`, gzipped, in the
+/// format version of the `rustdoc-types` mddoc is built with. docs.rs has
+/// JSON for releases built since May 2025.
+pub fn docs_rs_json_url(package: &PackageSpec) -> String {
+    let version = package
+        .version
+        .as_deref()
+        .map(|v| v.trim_start_matches('='))
+        .unwrap_or("latest");
+    format!(
+        "https://docs.rs/crate/{}/{}/json/{}.gz",
+        package.name,
+        version,
+        rustdoc_types::FORMAT_VERSION
+    )
+}
+
+async fn download(package: &PackageSpec) -> Result<Vec<u8>> {
+    let url = docs_rs_json_url(package);
+    log::info!("Downloading rustdoc JSON from {}", url);
+    let client = HttpClient::new(concat!("kargo-mddoc/", env!("CARGO_PKG_VERSION")))?;
+    let response = client.send(client.get(&url)).await?;
+    if response.status().as_u16() == 404 {
+        bail!(
+            "docs.rs has no rustdoc JSON (format {}) for {}; import a JSON file or HTML \
+             directory built elsewhere with --from",
+            rustdoc_types::FORMAT_VERSION,
+            package.name
+        );
+    }
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("Failed to download {}", url))?;
+    gunzip(&response.bytes().await?)
+}
+
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut json = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut json)
+        .context("Invalid gzip data")?;
+    Ok(json)
+}
+
+/// The `format_version` of a rustdoc JSON file, whatever else it holds
+fn format_version(json_path: &Path) -> Option<u32> {
+    #[derive(serde::Deserialize)]
+    struct Format {
+        format_version: u32,
+    }
+    let file = std::fs::File::open(json_path).ok()?;
+    let format: Format = serde_json::from_reader(std::io::BufReader::new(file)).ok()?;
+    Some(format.format_version)
+}
+
+/// Item pages rustdoc writes as `<prefix>.<name>.html`, in the order they
+/// are rendered, with the heading for each
+const ITEM_KINDS: &[(&str, &str)] = &[
+    ("macro", "Macro"),
+    ("attr", "Procedural Macro"),
+    ("derive", "Procedural Macro"),
+    ("struct", "Struct"),
+    ("enum", "Enum"),
+    ("union", "Union"),
+    ("trait", "Trait"),
+    ("traitalias", "Trait Alias"),
+    ("type", "Type Alias"),
+    ("fn", "Function"),
+    ("constant", "Constant"),
+    ("static", "Static"),
+];
+
+/// Render the submodules and items of the module whose pages are in `dir`
+fn module_html(dir: &Path, level: usize, output: &mut String) -> Result<()> {
+    let mut modules = Vec::new();
+    let mut items = Vec::new();
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if path.join("index.html").is_file() {
+            modules.push((name, path));
+        } else if let Some((prefix, item)) = name
+            .strip_suffix(".html")
+            .and_then(|stem| stem.split_once('.'))
+        {
+            if let Some(order) = ITEM_KINDS.iter().position(|(kind, _)| *kind == prefix) {
+                items.push((order, item.to_string(), path));
+            }
+        }
+    }
+    modules.sort();
+    items.sort();
+
+    let heading = "#".repeat(level);
+    for (name, path) in modules {
+        let html = read_page(&path.join("index.html"))?;
+        if is_redirect(&html) {
+            continue;
+        }
+        let _ = write!(output, "{} Module `{}`\n\n", heading, name);
+        push_docs(output, &html);
+        module_html(&path, level + 1, output)?;
+    }
+    for (order, name, path) in items {
+        let html = read_page(&path)?;
+        // Re-exports leave pages that only redirect to the item's own
+        if is_redirect(&html) {
+            continue;
+        }
+        let _ = write!(output, "{} {} `{}`\n\n", heading, ITEM_KINDS[order].1, name);
+        if let Some(declaration) = find_element(&html, "<pre class=\"rust item-decl\"") {
+            let _ = write!(
+                output,
+                "```rust\n{}\n```\n\n",
+                plain_text(declaration).trim()
+            );
+        }
+        push_docs(output, &html);
+    }
+    Ok(())
+}
+
+fn read_page(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn is_redirect(html: &str) -> bool {
+    html.contains("http-equiv=\"refresh\"")
+}
+
+/// Append the top documentation block of the page `html`
+fn push_docs(output: &mut String, html: &str) {
+    let top = html
+        .find("toggle top-doc")
+        .map_or(html, |start| &html[start..]);
+    if let Some(docs) = find_element(top, "<div class=\"docblock\"") {
+        let docs = html_to_markdown(docs);
+        if !docs.is_empty() {
+            output.push_str(&docs);
+            output.push_str("\n\n");
+        }
+    }
+}
+
+/// Contents of the first element of `html` starting with `open`, such as
+/// `<div class="docblock"`, up to its matching closing tag
+fn find_element<'a>(html: &'a str, open: &str) -> Option<&'a str> {
+    let start = html.find(open)?;
+    let name: String = open[1..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    let inner = start + html[start..].find('>')? + 1;
+    let (opening, closing) = (format!("<{}", name), format!("</{}>", name));
+    let mut depth = 1;
+    let mut at = inner;
+    while depth > 0 {
+        let next_close = at + html[at..].find(&closing)?;
+        match html[at..next_close].find(&opening) {
+            // A nested element opens before this one closes
+            Some(offset) if is_tag_start(&html[at + offset + opening.len()..]) => {
+                depth += 1;
+                at += offset + opening.len();
+            }
+            Some(offset) => at += offset + opening.len(),
+            None => {
+                depth -= 1;
+                at = next_close + closing.len();
+            }
+        }
+    }
+    Some(&html[inner..at - closing.len()])
+}
+
+/// Whether a tag name ends where `rest` starts, so `<div` isn't `<divider`
+fn is_tag_start(rest: &str) -> bool {
+    rest.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace())
+}
+
+/// Text of an HTML fragment with the tags dropped, whitespace kept
+pub fn plain_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..open]));
+        rest = rest[open..]
+            .find('>')
+            .map_or("", |close| &rest[open + close + 1..]);
+    }
+    text.push_str(&decode_entities(rest));
+    text
+}
+
+/// Markdown for a fragment of rustdoc's documentation HTML
+pub fn html_to_markdown(html: &str) -> String {
+    let mut out = String::new();
+    // Code blocks keep their whitespace
+    let mut pre = false;
+    // Element whose contents are left out, and how deep inside it we are
+    let mut skipping: Option<(String, usize)> = None;
+    // Open lists, with the next number for ordered ones
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    // Targets of the open links, for those written as links
+    let mut links: Vec<Option<String>> = Vec::new();
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        if skipping.is_none() {
+            push_text(&mut out, &decode_entities(&rest[..text_end]), pre);
+        }
+        rest = &rest[text_end..];
+        let Some(close) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if let Some((skipped, depth)) = &mut skipping {
+            if *skipped == name && !tag.ends_with('/') {
+                if closing {
+                    *depth -= 1;
+                } else {
+                    *depth += 1;
+                }
+            }
+            if *depth == 0 {
+                skipping = None;
+            }
+            continue;
+        }
+        let class = attribute(tag, "class").unwrap_or_default();
+        if !closing
+            && (matches!(name.as_str(), "button" | "script" | "style" | "summary")
+                || class.contains("doc-anchor")
+                || class.contains("tooltip"))
+        {
+            skipping = Some((name, 1));
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("p" | "div" | "details" | "section" | "blockquote" | "table", _) => block(&mut out),
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                block(&mut out);
+                let level = name[1..].parse().unwrap_or(2);
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => block(&mut out),
+            ("pre", false) => {
+                block(&mut out);
+                let language = class
+                    .split_whitespace()
+                    .find_map(|c| c.strip_prefix("language-"))
+                    .unwrap_or("rust");
+                let _ = writeln!(out, "```{}", language);
+                pre = true;
+            }
+            ("pre", true) => {
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str("```");
+                block(&mut out);
+                pre = false;
+            }
+            ("code", _) if !pre => out.push('`'),
+            ("em" | "i", _) => out.push('*'),
+            ("strong" | "b", _) => out.push_str("**"),
+            ("a", false) => {
+                // Links within the HTML docs have no page to go to here
+                let href = attribute(tag, "href").filter(|href| href.starts_with("http"));
+                if href.is_some() {
+                    out.push('[');
+                }
+                links.push(href);
+            }
+            ("a", true) => {
+                if let Some(Some(href)) = links.pop() {
+                    let _ = write!(out, "]({})", href);
+                }
+            }
+            ("ul" | "ol", false) => {
+                // A nested list goes on with its item's lines
+                if lists.is_empty() {
+                    block(&mut out);
+                }
+                lists.push((name == "ol").then(|| {
+                    attribute(tag, "start")
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(1)
+                }));
+            }
+            ("ul" | "ol", true) => {
+                lists.pop();
+                if lists.is_empty() {
+                    block(&mut out);
+                }
+            }
+            ("li", false) => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        let _ = write!(out, "{}. ", number);
+                        *number += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            ("br", _) => out.push('\n'),
+            _ => {}
+        }
+    }
+
+    // Tidy up outside code blocks: no trailing spaces, at most one blank
+    // line in a row
+    let mut tidy = String::new();
+    let mut blank = 0;
+    let mut code = false;
+    for line in out.lines() {
+        if line.starts_with("```") {
+            code = !code;
+        }
+        if code {
+            tidy.push_str(line);
+            tidy.push('\n');
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank += 1;
+            if blank > 1 {
+                continue;
+            }
+        } else {
+            blank = 0;
+        }
+        tidy.push_str(line);
+        tidy.push('\n');
+    }
+    tidy.trim().to_string()
+}
+
+/// Start a new block: end what's there with a blank line
+fn block(out: &mut String) {
+    let trimmed = out.trim_end_matches([' ', '\n']).len();
+    out.truncate(trimmed);
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+}
+
+fn push_text(out: &mut String, text: &str, pre: bool) {
+    if pre {
+        out.push_str(text);
+        return;
+    }
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                collapsed.push(' ');
+            }
+            space = false;
+            collapsed.push(c);
+        }
+    }
+    if space {
+        collapsed.push(' ');
+    }
+    // No leading space at the start of a line
+    if out.is_empty() || out.ends_with('\n') || out.ends_with(' ') {
+        out.push_str(collapsed.trim_start());
+    } else {
+        out.push_str(&collapsed);
+    }
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = start + tag[start..].find('"')?;
+    Some(decode_entities(&tag[start..end]))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (character, entity) {
+            (Some(character), Some(entity)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
@@ -0,0 +1,328 @@
+//! Links to the rustdoc pages of items from other crates.
+//!
+//! rustdoc JSON refers to an item of another crate by an ID that is missing
+//! from `index` but present in `paths`, together with the crate it comes from
+//! in `external_crates`. That is enough to build the URL of the item's page:
+//! under the crate's `html_root_url` when rustdoc recorded one (the standard
+//! library does), on docs.rs otherwise. docs.rs links use the version locked
+//! in the documented project's `Cargo.lock` where it is known, and the latest
+//! release where it is not.
+
+use rustdoc_types::{AssocItemConstraintKind, GenericParamDefKind};
+use rustdoc_types::{
+    Crate, GenericArg, GenericArgs, GenericBound, Generics, Item, ItemEnum, ItemKind, ItemSummary,
+    Path, StructKind, Term, Type, VariantKind, WherePredicate,
+};
+use std::collections::HashMap;
+
+/// How items of other crates are linked
+#[derive(Debug, Clone, Default)]
+pub struct LinkConfig {
+    /// Follow each item signature with links to the external types it names
+    pub signatures: bool,
+    /// Locked version of each dependency, by crate name
+    pub versions: HashMap<String, String>,
+}
+
+/// Versions locked in the contents of a `Cargo.lock`, by crate name with `-`
+/// replaced by `_`. Crates locked at more than one version are left out.
+pub fn locked_versions(lockfile: &str) -> HashMap<String, String> {
+    let Ok(doc) = lockfile.parse::<toml_edit::DocumentMut>() else {
+        return HashMap::new();
+    };
+    let Some(packages) = doc.get("package").and_then(|p| p.as_array_of_tables()) else {
+        return HashMap::new();
+    };
+
+    let mut versions: HashMap<String, Option<String>> = HashMap::new();
+    for package in packages.iter() {
+        let (Some(name), Some(version)) = (
+            package.get("name").and_then(|n| n.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        versions
+            .entry(name.replace('-', "_"))
+            .and_modify(|locked| {
+                if locked.as_deref() != Some(version) {
+                    *locked = None;
+                }
+            })
+            .or_insert_with(|| Some(version.to_string()));
+    }
+    versions
+        .into_iter()
+        .filter_map(|(name, version)| Some((name, version?)))
+        .collect()
+}
+
+/// A type from another crate named in a signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalType {
+    /// Full path, e.g. `std::sync::Arc`
+    pub path: String,
+    pub url: String,
+}
+
+/// URL lookup for items of other crates
+pub struct ExternalLinks<'a> {
+    data: &'a Crate,
+    config: &'a LinkConfig,
+}
+
+impl<'a> ExternalLinks<'a> {
+    pub fn new(data: &'a Crate, config: &'a LinkConfig) -> Self {
+        Self { data, config }
+    }
+
+    /// rustdoc page of an item from another crate
+    pub fn url(&self, summary: &ItemSummary) -> Option<String> {
+        let (name, parents) = summary.path.split_last()?;
+        let page = match summary.kind {
+            ItemKind::Module => format!("{}/index.html", name),
+            ItemKind::Struct => format!("struct.{}.html", name),
+            ItemKind::Enum => format!("enum.{}.html", name),
+            ItemKind::Union => format!("union.{}.html", name),
+            ItemKind::Trait => format!("trait.{}.html", name),
+            ItemKind::TraitAlias => format!("traitalias.{}.html", name),
+            ItemKind::Function => format!("fn.{}.html", name),
+            ItemKind::TypeAlias => format!("type.{}.html", name),
+            ItemKind::Constant => format!("constant.{}.html", name),
+            ItemKind::Static => format!("static.{}.html", name),
+            ItemKind::Macro => format!("macro.{}.html", name),
+            ItemKind::ProcAttribute => format!("attr.{}.html", name),
+            ItemKind::ProcDerive => format!("derive.{}.html", name),
+            _ => return None,
+        };
+
+        let krate = self.data.external_crates.get(&summary.crate_id);
+        let krate_name = match krate {
+            Some(krate) => krate.name.as_str(),
+            None => summary.path.first()?.as_str(),
+        };
+        let mut url = match krate.and_then(|k| k.html_root_url.as_deref()) {
+            Some(root) => root.trim_end_matches('/').to_string(),
+            None => format!(
+                "https://docs.rs/{}/{}",
+                krate_name,
+                self.config
+                    .versions
+                    .get(krate_name)
+                    .map(String::as_str)
+                    .unwrap_or("latest")
+            ),
+        };
+        for parent in parents {
+            url.push('/');
+            url.push_str(parent);
+        }
+        url.push('/');
+        url.push_str(&page);
+        Some(url)
+    }
+
+    /// External types named in the signature of `item`, in order of first
+    /// appearance. Empty unless signature links are enabled.
+    pub fn in_signature(&self, item: &Item) -> Vec<ExternalType> {
+        let mut found = Vec::new();
+        if !self.config.signatures {
+            return found;
+        }
+
+        let mut fields = Vec::new();
+        match &item.inner {
+            ItemEnum::Function(function) => {
+                self.generics(&function.generics, &mut found);
+                for (_, ty) in &function.sig.inputs {
+                    self.ty(ty, &mut found);
+                }
+                if let Some(output) = &function.sig.output {
+                    self.ty(output, &mut found);
+                }
+            }
+            ItemEnum::Struct(struct_) => {
+                self.generics(&struct_.generics, &mut found);
+                match &struct_.kind {
+                    StructKind::Unit => {}
+                    StructKind::Tuple(ids) => fields.extend(ids.iter().flatten()),
+                    StructKind::Plain { fields: ids, .. } => fields.extend(ids),
+                }
+            }
+            ItemEnum::Union(union_) => {
+                self.generics(&union_.generics, &mut found);
+                fields.extend(&union_.fields);
+            }
+            ItemEnum::Enum(enum_) => {
+                self.generics(&enum_.generics, &mut found);
+                for id in &enum_.variants {
+                    if let Some(ItemEnum::Variant(variant)) =
+                        self.data.index.get(id).map(|v| &v.inner)
+                    {
+                        match &variant.kind {
+                            VariantKind::Plain => {}
+                            VariantKind::Tuple(ids) => fields.extend(ids.iter().flatten()),
+                            VariantKind::Struct { fields: ids, .. } => fields.extend(ids),
+                        }
+                    }
+                }
+            }
+            ItemEnum::Trait(trait_) => {
+                self.generics(&trait_.generics, &mut found);
+                self.bounds(&trait_.bounds, &mut found);
+            }
+            ItemEnum::TypeAlias(alias) => {
+                self.generics(&alias.generics, &mut found);
+                self.ty(&alias.type_, &mut found);
+            }
+            ItemEnum::Constant { type_, .. } => self.ty(type_, &mut found),
+            ItemEnum::Static(static_) => self.ty(&static_.type_, &mut found),
+            ItemEnum::Impl(impl_) => {
+                self.generics(&impl_.generics, &mut found);
+                if let Some(trait_) = &impl_.trait_ {
+                    self.path(trait_, &mut found);
+                }
+                self.ty(&impl_.for_, &mut found);
+            }
+            _ => {}
+        }
+
+        for id in fields {
+            if let Some(ItemEnum::StructField(ty)) = self.data.index.get(id).map(|f| &f.inner) {
+                self.ty(ty, &mut found);
+            }
+        }
+        found
+    }
+
+    fn ty(&self, ty: &Type, found: &mut Vec<ExternalType>) {
+        match ty {
+            Type::ResolvedPath(path) => self.path(path, found),
+            Type::DynTrait(dyn_trait) => {
+                for poly in &dyn_trait.traits {
+                    self.path(&poly.trait_, found);
+                }
+            }
+            Type::FunctionPointer(fn_ptr) => {
+                for (_, input) in &fn_ptr.sig.inputs {
+                    self.ty(input, found);
+                }
+                if let Some(output) = &fn_ptr.sig.output {
+                    self.ty(output, found);
+                }
+            }
+            Type::Tuple(types) => {
+                for ty in types {
+                    self.ty(ty, found);
+                }
+            }
+            Type::Slice(ty) => self.ty(ty, found),
+            Type::Array { type_, .. }
+            | Type::RawPointer { type_, .. }
+            | Type::BorrowedRef { type_, .. } => self.ty(type_, found),
+            Type::ImplTrait(bounds) => self.bounds(bounds, found),
+            Type::QualifiedPath {
+                args,
+                self_type,
+                trait_,
+                ..
+            } => {
+                self.ty(self_type, found);
+                if let Some(trait_) = trait_ {
+                    self.path(trait_, found);
+                }
+                self.args(args, found);
+            }
+            _ => {}
+        }
+    }
+
+    fn path(&self, path: &Path, found: &mut Vec<ExternalType>) {
+        if !self.data.index.contains_key(&path.id) {
+            if let Some(summary) = self.data.paths.get(&path.id) {
+                if summary.crate_id != 0 {
+                    self.push(summary, found);
+                }
+            }
+        }
+        if let Some(args) = &path.args {
+            self.args(args, found);
+        }
+    }
+
+    fn push(&self, summary: &ItemSummary, found: &mut Vec<ExternalType>) {
+        let path = summary.path.join("::");
+        if found.iter().any(|t| t.path == path) {
+            return;
+        }
+        if let Some(url) = self.url(summary) {
+            found.push(ExternalType { path, url });
+        }
+    }
+
+    fn args(&self, args: &GenericArgs, found: &mut Vec<ExternalType>) {
+        match args {
+            GenericArgs::AngleBracketed { args, constraints } => {
+                for arg in args {
+                    if let GenericArg::Type(ty) = arg {
+                        self.ty(ty, found);
+                    }
+                }
+                for constraint in constraints {
+                    self.args(&constraint.args, found);
+                    match &constraint.binding {
+                        AssocItemConstraintKind::Equality(Term::Type(ty)) => self.ty(ty, found),
+                        AssocItemConstraintKind::Constraint(bounds) => self.bounds(bounds, found),
+                        _ => {}
+                    }
+                }
+            }
+            GenericArgs::Parenthesized { inputs, output } => {
+                for input in inputs {
+                    self.ty(input, found);
+                }
+                if let Some(output) = output {
+                    self.ty(output, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn bounds(&self, bounds: &[GenericBound], found: &mut Vec<ExternalType>) {
+        for bound in bounds {
+            if let GenericBound::TraitBound { trait_, .. } = bound {
+                self.path(trait_, found);
+            }
+        }
+    }
+
+    fn generics(&self, generics: &Generics, found: &mut Vec<ExternalType>) {
+        for param in &generics.params {
+            if let GenericParamDefKind::Type {
+                bounds, default, ..
+            } = &param.kind
+            {
+                self.bounds(bounds, found);
+                if let Some(default) = default {
+                    self.ty(default, found);
+                }
+            }
+        }
+        for predicate in &generics.where_predicates {
+            if let WherePredicate::BoundPredicate { type_, bounds, .. } = predicate {
+                self.ty(type_, found);
+                self.bounds(bounds, found);
+            }
+        }
+    }
+}
+
+/// Markdown list of links to the external types in `types`
+pub fn link_list(types: &[ExternalType]) -> String {
+    types
+        .iter()
+        .map(|t| format!("[`{}`]({})", t.path, t.url))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
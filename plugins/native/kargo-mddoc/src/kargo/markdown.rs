@@ -1,4 +1,9 @@
+use crate::attributes;
 use crate::error::Error;
+use crate::links::{self, ExternalLinks, LinkConfig};
+use crate::output::{hashes, MarkdownOutput, MarkdownWrite};
+use crate::reexports::{self, Link, Member, Reexports, Target};
+use crate::templates::{CrateContext, GeneratorContext, PageContext, Templates};
 use crate::utils;
 use log::{debug, info};
 use rustdoc_types::{AssocItemConstraintKind, Term};
@@ -6,31 +11,60 @@ use rustdoc_types::{Crate, Id, Item, ItemEnum};
 use rustdoc_types::{Enum, Struct, Union};
 use rustdoc_types::{Function, Impl, StructKind, Trait, VariantKind, Visibility};
 use rustdoc_types::{GenericArg, GenericArgs, Generics, Type};
+use std::collections::HashSet;
+use std::io;
 use std::path::{Path, PathBuf};
 
+/// Load rustdoc JSON into memory.
+///
+/// The document is deserialized straight from a buffered reader so the raw
+/// JSON text is never held alongside the parsed crate, and the `paths` table
+/// is trimmed right after parsing to the entries rendering consults: items of
+/// other crates, for links, and re-export targets.
+pub fn load_crate(json_path: &Path) -> Result<Crate, Error> {
+    let mut data: Crate = utils::read_json(json_path)?;
+    let targets: HashSet<Id> = data
+        .index
+        .values()
+        .filter_map(|item| match &item.inner {
+            ItemEnum::Use(use_) => use_.id,
+            _ => None,
+        })
+        .collect();
+    data.paths
+        .retain(|id, summary| summary.crate_id != 0 || targets.contains(id));
+    data.paths.shrink_to_fit();
+    Ok(data)
+}
+
 /// Convert JSON documentation to Markdown
 pub fn convert_to_markdown(json_path: &Path) -> Result<PathBuf, Error> {
+    convert_to_markdown_with(json_path, &Templates::builtin(), &LinkConfig::default())
+}
+
+/// Convert JSON documentation to Markdown, laid out with `templates` and
+/// linking items of other crates as configured by `links`
+pub fn convert_to_markdown_with(
+    json_path: &Path,
+    templates: &Templates,
+    links: &LinkConfig,
+) -> Result<PathBuf, Error> {
     debug!(
         "Converting JSON documentation to Markdown: {}",
         json_path.display()
     );
 
-    // Load the JSON data
-    let json_content = utils::read_file(json_path)?;
-
-    // Parse the JSON into the rustdoc structure
-    let data: Crate = serde_json::from_str(&json_content).map_err(|e| Error::JsonParse(e))?;
-
-    // Generate Markdown content
-    debug!("Generating Markdown content");
-    let markdown = rustdoc_json_to_markdown(&data);
+    // Load and parse the JSON data
+    let data = load_crate(json_path)?;
 
     // Determine output path
     let output_path = derive_markdown_path(json_path);
-    debug!("Writing Markdown to: {}", output_path.display());
+    debug!("Streaming Markdown to: {}", output_path.display());
 
-    // Write Markdown to file
-    utils::write_file(&output_path, &markdown)?;
+    // Render straight into the file instead of building one giant String
+    let mut file = utils::create_file(&output_path)?;
+    let page = page_context(&data, &output_path);
+    write_markdown_with(&data, &mut file, templates, &page, links)?;
 
     info!(
         "Markdown documentation created at: {}",
@@ -41,16 +75,97 @@ pub fn convert_to_markdown(json_path: &Path) -> Result<PathBuf, Error> {
 
 /// Convert a rustdoc JSON structure to Markdown
 pub fn rustdoc_json_to_markdown(data: &Crate) -> String {
-    let mut output = String::new();
+    let mut output = MarkdownOutput::buffered();
+    render_crate(
+        &mut output,
+        data,
+        BUILTIN_HEADING,
+        "",
+        &LinkConfig::default(),
+    );
+    output
+        .finish()
+        .expect("in-memory markdown output cannot fail")
+}
 
+/// Render a rustdoc JSON structure to Markdown, writing incrementally to `writer`
+pub fn write_markdown(data: &Crate, writer: &mut dyn io::Write) -> io::Result<()> {
+    let mut output = MarkdownOutput::streaming(writer);
+    render_crate(
+        &mut output,
+        data,
+        BUILTIN_HEADING,
+        "",
+        &LinkConfig::default(),
+    );
+    output.finish().map(|_| ())
+}
+
+/// Render to `writer` like [`write_markdown`], with the page start and end
+/// from `templates` and external links configured by `links`
+pub fn write_markdown_with(
+    data: &Crate,
+    writer: &mut dyn io::Write,
+    templates: &Templates,
+    page: &PageContext,
+    links: &LinkConfig,
+) -> Result<(), Error> {
+    let start = templates.page_start(page)?;
+    let end = templates.page_end(page)?;
+    let mut output = MarkdownOutput::streaming(writer);
+    render_crate(&mut output, data, &start, &end, links);
+    output.finish()?;
+    Ok(())
+}
+
+/// Template context for the single page documenting `data`
+pub fn page_context(data: &Crate, output_path: &Path) -> PageContext {
+    PageContext {
+        kind: "crate".to_string(),
+        title: "Crate Documentation".to_string(),
+        file: output_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        krate: CrateContext {
+            name: data
+                .index
+                .get(&data.root)
+                .and_then(|item| item.name.clone())
+                .unwrap_or_else(|| "Crate".to_string()),
+            version: data.crate_version.clone(),
+        },
+        item: None,
+        items: Vec::new(),
+        generator: GeneratorContext::default(),
+    }
+}
+
+/// Lookups over the whole crate, shared by the renderers
+struct Context<'a> {
+    reexports: Reexports<'a>,
+    links: ExternalLinks<'a>,
+}
+
+/// What the built-in templates render for the single page, so the untemplated
+/// entry points skip the template engine
+const BUILTIN_HEADING: &str = "# Crate Documentation\n\n";
+
+fn render_crate(
+    output: &mut MarkdownOutput,
+    data: &Crate,
+    start: &str,
+    end: &str,
+    links: &LinkConfig,
+) {
     // Add crate header and basic info
-    output.push_str("# Crate Documentation\n\n");
+    output.push_str(start);
 
     if let Some(version) = &data.crate_version {
-        output.push_str(&format!("**Version:** {}\n\n", version));
+        write!(output, "**Version:** {}\n\n", version);
     }
 
-    output.push_str(&format!("**Format Version:** {}\n\n", data.format_version));
+    write!(output, "**Format Version:** {}\n\n", data.format_version);
 
     // Debug: Log total items in the crate
     log::info!("Total items in crate index: {}", data.index.len());
@@ -63,146 +178,191 @@ pub fn rustdoc_json_to_markdown(data: &Crate) -> String {
             log::info!("Root module has {} direct items", module.items.len());
 
             if let Some(name) = &root_item.name {
-                output.push_str(&format!("# Module `{}`\n\n", name));
+                write!(output, "# Module `{}`\n\n", name);
             } else if module.is_crate {
                 output.push_str("# Crate Root\n\n");
             }
 
             // Add root documentation if available
             if let Some(docs) = &root_item.docs {
-                output.push_str(&format!("{}\n\n", docs));
+                write!(output, "{}\n\n", docs);
             }
 
             // Process all items in the module with consistent heading levels
             // starting at level 2 for top-level categories
-            process_items(&mut output, &module.items, data, 2);
+            let ctx = Context {
+                reexports: Reexports::new(data),
+                links: ExternalLinks::new(data, links),
+            };
+            process_items(output, &module.items, data, &ctx, 2);
         }
     }
 
-    output
+    output.push_str(end);
 }
 
 /// Process items within a module
-fn process_items(output: &mut String, item_ids: &[Id], data: &Crate, level: usize) {
+fn process_items(
+    output: &mut MarkdownOutput,
+    item_ids: &[Id],
+    data: &Crate,
+    ctx: &Context,
+    level: usize,
+) {
     // No capping - we want ALL the docs recursively
     let heading_level = level;
 
-    // Group items by kind for better organization
+    // Group items by kind for better organization. Re-exports of items that
+    // have no page of their own are resolved and grouped with their target.
     let mut modules = Vec::new();
     let mut types = Vec::new();
     let mut traits = Vec::new();
     let mut functions = Vec::new();
     let mut constants = Vec::new();
     let mut macros = Vec::new();
-    let mut reexports = Vec::new(); // New category for re-exports
-    let mut _use_items: Vec<Id> = Vec::new(); // Separate category for use statements
+    let mut extern_crates = Vec::new();
+    let mut links = Vec::new();
     let mut other_items = Vec::new();
 
-    for id in item_ids.iter() {
-        if let Some(item) = data.index.get(id) {
-            match &item.inner {
-                ItemEnum::Module(_) => modules.push(id.clone()),
-                ItemEnum::Struct(_)
-                | ItemEnum::Enum(_)
-                | ItemEnum::Union(_)
-                | ItemEnum::TypeAlias(_) => types.push(id.clone()),
-                ItemEnum::Trait(_) | ItemEnum::TraitAlias(_) => traits.push(id.clone()),
-                ItemEnum::Function(_) => functions.push(id.clone()),
-                ItemEnum::Constant { .. } | ItemEnum::Static(_) => constants.push(id.clone()),
-                ItemEnum::Macro(_) | ItemEnum::ProcMacro(_) => macros.push(id.clone()),
-                ItemEnum::ExternCrate { .. } => reexports.push(id.clone()),
-                ItemEnum::Use(_) => reexports.push(id.clone()),
-                _ => {
-                    // Put all unrecognized items in other_items
-                    other_items.push(id.clone());
-                }
+    for member in ctx.reexports.members(item_ids) {
+        let item = match member {
+            Member::Inline(item) => *item,
+            Member::Link(link) => {
+                links.push(link);
+                continue;
+            }
+        };
+        match &item.inner {
+            ItemEnum::Module(_) => modules.push(item),
+            ItemEnum::Struct(_)
+            | ItemEnum::Enum(_)
+            | ItemEnum::Union(_)
+            | ItemEnum::TypeAlias(_) => types.push(item),
+            ItemEnum::Trait(_) | ItemEnum::TraitAlias(_) => traits.push(item),
+            ItemEnum::Function(_) => functions.push(item),
+            ItemEnum::Constant { .. } | ItemEnum::Static(_) => constants.push(item),
+            ItemEnum::Macro(_) | ItemEnum::ProcMacro(_) => macros.push(item),
+            ItemEnum::ExternCrate { .. } => extern_crates.push(item),
+            _ => {
+                // Put all unrecognized items in other_items
+                other_items.push(item);
             }
         }
     }
 
-    // TODO: Show re-exports prominently once we figure out the correct rustdoc-types variant
-    // The JSON shows these as "use" items but the enum variant name varies by version
-
     // Process each group in order
     if !modules.is_empty() {
-        output.push_str(&format!("{} Modules\n\n", "#".repeat(heading_level)));
-        for id in modules {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
-            }
+        write!(output, "{} Modules\n\n", hashes(heading_level));
+        for item in modules {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
     if !types.is_empty() {
-        output.push_str(&format!("{} Types\n\n", "#".repeat(heading_level)));
-        for id in types {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
-            }
+        write!(output, "{} Types\n\n", hashes(heading_level));
+        for item in types {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
     if !traits.is_empty() {
-        output.push_str(&format!("{} Traits\n\n", "#".repeat(heading_level)));
-        for id in traits {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
-            }
+        write!(output, "{} Traits\n\n", hashes(heading_level));
+        for item in traits {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
     if !functions.is_empty() {
-        output.push_str(&format!("{} Functions\n\n", "#".repeat(heading_level)));
+        write!(output, "{} Functions\n\n", hashes(heading_level));
         log::debug!(
             "Processing {} functions at level {}",
             functions.len(),
             level
         );
-        for id in functions {
-            if let Some(item) = data.index.get(&id) {
-                log::debug!("Processing function: {:?}", item.name);
-                process_item(output, item, data, level + 1);
-            }
+        for item in functions {
+            log::debug!("Processing function: {:?}", item.name);
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
     if !constants.is_empty() {
-        output.push_str(&format!(
+        write!(
+            output,
             "{} Constants and Statics\n\n",
-            "#".repeat(heading_level)
-        ));
-        for id in constants {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
-            }
+            hashes(heading_level)
+        );
+        for item in constants {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
     if !macros.is_empty() {
-        output.push_str(&format!("{} Macros\n\n", "#".repeat(heading_level)));
-        for id in macros {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
-            }
+        write!(output, "{} Macros\n\n", hashes(heading_level));
+        for item in macros {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
-    if !reexports.is_empty() {
-        output.push_str(&format!("{} Re-exports\n\n", "#".repeat(heading_level)));
-        for id in reexports {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
-            }
+    if !links.is_empty() || !extern_crates.is_empty() {
+        write!(output, "{} Re-exports\n\n", hashes(heading_level));
+        for link in &links {
+            process_reexport(output, link, ctx, level + 1);
+        }
+        for item in extern_crates {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
         }
     }
 
     if !other_items.is_empty() {
-        output.push_str(&format!("{} Other Items\n\n", "#".repeat(heading_level)));
-        for id in other_items {
-            if let Some(item) = data.index.get(&id) {
-                process_item(output, item, data, level + 1);
+        write!(output, "{} Other Items\n\n", hashes(heading_level));
+        for item in other_items {
+            process_item(output, &item, data, ctx, level + 1);
+            output.flush_if_full();
+        }
+    }
+}
+
+/// Process a re-export of an item documented elsewhere
+fn process_reexport(output: &mut MarkdownOutput, link: &Link, ctx: &Context, level: usize) {
+    let use_ = link.use_;
+    if use_.is_glob {
+        write!(
+            output,
+            "{} Re-export `{}::*`\n\n",
+            hashes(level),
+            use_.source
+        );
+    } else {
+        write!(output, "{} Re-export `{}`\n\n", hashes(level), use_.name);
+    }
+
+    write!(
+        output,
+        "```rust\npub {}\n```\n\n",
+        reexports::use_declaration(use_)
+    );
+
+    match &link.target {
+        Target::Local(_) => {
+            if let Some(path) = &link.location {
+                write!(output, "Documented as `{}`.\n\n", path);
             }
         }
+        Target::External(summary) => {
+            let path = summary.path.join("::");
+            match ctx.links.url(summary) {
+                Some(url) => write!(output, "See [`{}`]({}).\n\n", path, url),
+                None => write!(output, "Defined in `{}`.\n\n", path),
+            }
+        }
+        Target::Unresolved => {}
     }
 }
 
@@ -214,9 +374,15 @@ fn derive_markdown_path(json_path: &Path) -> PathBuf {
 }
 
 /// Process a single item
-fn process_item(output: &mut String, item: &Item, data: &Crate, level: usize) {
+fn process_item(
+    output: &mut MarkdownOutput,
+    item: &Item,
+    data: &Crate,
+    ctx: &Context,
+    level: usize,
+) {
     // No capping - we want ALL the docs
-    let heading = "#".repeat(level);
+    let heading = hashes(level);
     let _heading_level = level;
 
     // Add item heading with name and kind
@@ -227,50 +393,56 @@ fn process_item(output: &mut String, item: &Item, data: &Crate, level: usize) {
                 Some(r) => r,
                 None => name,
             };
-            output.push_str(&format!("{} Extern Crate `{}`\n\n", heading, display_name));
+            write!(output, "{} Extern Crate `{}`\n\n", heading, display_name);
         }
         _ => {
             // Handle all other items as before
             if let Some(name) = &item.name {
                 match &item.inner {
                     ItemEnum::Module(_) => {
-                        output.push_str(&format!("{} Module `{}`\n\n", heading, name))
+                        write!(output, "{} Module `{}`\n\n", heading, name)
                     }
                     ItemEnum::Struct(_) => {
-                        output.push_str(&format!("{} Struct `{}`\n\n", heading, name))
+                        write!(output, "{} Struct `{}`\n\n", heading, name)
                     }
                     ItemEnum::Enum(_) => {
-                        output.push_str(&format!("{} Enum `{}`\n\n", heading, name))
+                        write!(output, "{} Enum `{}`\n\n", heading, name)
                     }
                     ItemEnum::Union(_) => {
-                        output.push_str(&format!("{} Union `{}`\n\n", heading, name))
+                        write!(output, "{} Union `{}`\n\n", heading, name)
                     }
                     ItemEnum::Trait(_) => {
-                        output.push_str(&format!("{} Trait `{}`\n\n", heading, name))
+                        write!(output, "{} Trait `{}`\n\n", heading, name)
                     }
                     ItemEnum::TraitAlias(_) => {
-                        output.push_str(&format!("{} Trait Alias `{}`\n\n", heading, name))
+                        write!(output, "{} Trait Alias `{}`\n\n", heading, name)
                     }
                     ItemEnum::Function(_) => {
                         log::debug!("Formatting function {} with heading level {}", name, level);
-                        output.push_str(&format!("{} Function `{}`\n\n", heading, name))
+                        write!(output, "{} Function `{}`\n\n", heading, name)
                     }
                     ItemEnum::TypeAlias(_) => {
-                        output.push_str(&format!("{} Type Alias `{}`\n\n", heading, name))
+                        write!(output, "{} Type Alias `{}`\n\n", heading, name)
                     }
                     ItemEnum::Constant { .. } => {
-                        output.push_str(&format!("{} Constant `{}`\n\n", heading, name))
+                        write!(output, "{} Constant `{}`\n\n", heading, name)
                     }
                     ItemEnum::Static(_) => {
-                        output.push_str(&format!("{} Static `{}`\n\n", heading, name))
+                        write!(output, "{} Static `{}`\n\n", heading, name)
                     }
                     ItemEnum::Macro(_) => {
-                        output.push_str(&format!("{} Macro `{}`\n\n", heading, name))
+                        write!(output, "{} Macro `{}`\n\n", heading, name)
                     }
                     ItemEnum::ProcMacro(_) => {
-                        output.push_str(&format!("{} Procedural Macro `{}`\n\n", heading, name))
+                        write!(output, "{} Procedural Macro `{}`\n\n", heading, name)
+                    }
+                    ItemEnum::AssocType { .. } => {
+                        write!(output, "{} Associated Type `{}`\n\n", heading, name)
+                    }
+                    ItemEnum::AssocConst { .. } => {
+                        write!(output, "{} Associated Constant `{}`\n\n", heading, name)
                     }
-                    _ => output.push_str(&format!("{} `{}`\n\n", heading, name)),
+                    _ => write!(output, "{} `{}`\n\n", heading, name),
                 }
             } else {
                 // Special case for impl blocks and other nameless items
@@ -278,35 +450,39 @@ fn process_item(output: &mut String, item: &Item, data: &Crate, level: usize) {
                     ItemEnum::Impl(impl_) => {
                         if let Some(trait_) = &impl_.trait_ {
                             // For trait impls, show "Implementation of TraitName for Type"
-                            output.push_str(&format!(
+                            write!(
+                                output,
                                 "{} Implementation of `{}` for `{}`\n\n",
                                 heading,
                                 trait_.path,
                                 format_type(&impl_.for_, data)
-                            ));
+                            );
                         } else {
                             // For inherent impls, show "Implementation for Type"
-                            output.push_str(&format!(
+                            write!(
+                                output,
                                 "{} Implementation for `{}`\n\n",
                                 heading,
                                 format_type(&impl_.for_, data)
-                            ));
+                            );
                         }
                     }
                     _ => {
                         // For other items without names
-                        output.push_str(&format!("{} Unnamed Item\n\n", heading));
+                        write!(output, "{} Unnamed Item\n\n", heading);
                     }
                 }
             }
         }
     }
 
-    // Add item attributes if present
-    if !item.attrs.is_empty() {
+    // Hidden and cfg-gated items say so; other attributes are listed
+    output.push_str(&attributes::notes(item));
+    let mut other = attributes::other_attributes(item).peekable();
+    if other.peek().is_some() {
         output.push_str("**Attributes:**\n\n");
-        for attr in &item.attrs {
-            output.push_str(&format!("- `{}`\n", attr));
+        for attr in other {
+            writeln!(output, "- `{}`", attr);
         }
         output.push('\n');
     }
@@ -315,19 +491,19 @@ fn process_item(output: &mut String, item: &Item, data: &Crate, level: usize) {
     if let Some(deprecation) = &item.deprecation {
         output.push_str("**⚠️ Deprecated");
         if let Some(since) = &deprecation.since {
-            output.push_str(&format!(" since {}", since));
+            write!(output, " since {}", since);
         }
         output.push_str("**");
 
         if let Some(note) = &deprecation.note {
-            output.push_str(&format!(": {}", note));
+            write!(output, ": {}", note);
         }
         output.push_str("\n\n");
     }
 
     // Add documentation if available
     if let Some(docs) = &item.docs {
-        output.push_str(&format!("{}\n\n", docs));
+        write!(output, "{}\n\n", docs);
     }
 
     // Add code block with item signature
@@ -335,25 +511,35 @@ fn process_item(output: &mut String, item: &Item, data: &Crate, level: usize) {
     format_item_signature(output, item, data);
     output.push_str("\n```\n\n");
 
+    // Code blocks cannot hold links, so external types are listed below
+    let external = ctx.links.in_signature(item);
+    if !external.is_empty() {
+        write!(
+            output,
+            "**External types:** {}\n\n",
+            links::link_list(&external)
+        );
+    }
+
     // Process additional details based on item kind
     match &item.inner {
-        ItemEnum::Module(module) => process_module_details(output, module, data, level + 1),
+        ItemEnum::Module(module) => process_module_details(output, module, data, ctx, level + 1),
         ItemEnum::Struct(struct_) => process_struct_details(output, struct_, item, data, level + 1),
         ItemEnum::Enum(enum_) => process_enum_details(output, enum_, item, data, level + 1),
         ItemEnum::Union(union_) => process_union_details(output, union_, item, data, level + 1),
         ItemEnum::Trait(trait_) => process_trait_details(output, trait_, item, data, level + 1),
-        ItemEnum::Impl(impl_) => process_impl_details(output, impl_, item, data, level + 1),
+        ItemEnum::Impl(impl_) => process_impl_details(output, impl_, item, data, ctx, level + 1),
         _ => {}
     }
 }
 
 /// Format an item's signature
-fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
+pub(crate) fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
     // Format visibility
     match &item.visibility {
         Visibility::Public => output.push_str("pub "),
         Visibility::Crate => output.push_str("pub(crate) "),
-        Visibility::Restricted { path, .. } => output.push_str(&format!("pub(in {}) ", path)),
+        Visibility::Restricted { path, .. } => write!(output, "pub(in {}) ", path),
         Visibility::Default => {}
     }
 
@@ -361,12 +547,12 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
     match &item.inner {
         ItemEnum::Module(_) => {
             if let Some(name) = &item.name {
-                output.push_str(&format!("mod {} {{ /* ... */ }}", name));
+                write!(output, "mod {} {{ /* ... */ }}", name);
             }
         }
         ItemEnum::Struct(struct_) => {
             if let Some(name) = &item.name {
-                output.push_str(&format!("struct {}", name));
+                write!(output, "struct {}", name);
                 format_generics(output, &struct_.generics, data);
 
                 match &struct_.kind {
@@ -382,11 +568,11 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
                                             Visibility::Public => output.push_str("pub "),
                                             Visibility::Crate => output.push_str("pub(crate) "),
                                             Visibility::Restricted { path, .. } => {
-                                                output.push_str(&format!("pub(in {}) ", path))
+                                                write!(output, "pub(in {}) ", path)
                                             }
                                             Visibility::Default => {}
                                         }
-                                        output.push_str(&format_type(field_type, data));
+                                        write_type(output, field_type, data);
                                     }
                                 }
                                 if i < fields.len() - 1 {
@@ -416,15 +602,16 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
                                             Visibility::Public => output.push_str("    pub "),
                                             Visibility::Crate => output.push_str("    pub(crate) "),
                                             Visibility::Restricted { path, .. } => {
-                                                output.push_str(&format!("    pub(in {}) ", path))
+                                                write!(output, "    pub(in {}) ", path)
                                             }
                                             Visibility::Default => output.push_str("    "),
                                         }
-                                        output.push_str(&format!(
-                                            "{}: {},\n",
+                                        writeln!(
+                                            output,
+                                            "{}: {},",
                                             field_name,
                                             format_type(field_type, data)
-                                        ));
+                                        );
                                     }
                                 }
                             }
@@ -442,12 +629,13 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
         }
         ItemEnum::Constant { type_, const_ } => {
             if let Some(name) = &item.name {
-                output.push_str(&format!(
+                write!(
+                    output,
                     "const {}: {} = {};",
                     name,
                     format_type(type_, data),
                     const_.expr
-                ));
+                );
             }
         }
         ItemEnum::Static(static_) => {
@@ -456,12 +644,13 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
                 if static_.is_mutable {
                     output.push_str("mut ");
                 }
-                output.push_str(&format!(
+                write!(
+                    output,
                     "{}: {} = {};",
                     name,
                     format_type(&static_.type_, data),
                     static_.expr
-                ));
+                );
             }
         }
         ItemEnum::Enum(enum_) => {
@@ -469,7 +658,7 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
         }
         ItemEnum::Union(union_) => {
             if let Some(name) = &item.name {
-                output.push_str(&format!("union {}", name));
+                write!(output, "union {}", name);
                 format_generics(output, &union_.generics, data);
                 output.push_str(" {\n");
 
@@ -481,15 +670,16 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
                                     Visibility::Public => output.push_str("    pub "),
                                     Visibility::Crate => output.push_str("    pub(crate) "),
                                     Visibility::Restricted { path, .. } => {
-                                        output.push_str(&format!("    pub(in {}) ", path))
+                                        write!(output, "    pub(in {}) ", path)
                                     }
                                     Visibility::Default => output.push_str("    "),
                                 }
-                                output.push_str(&format!(
-                                    "{}: {},\n",
+                                writeln!(
+                                    output,
+                                    "{}: {},",
                                     field_name,
                                     format_type(field_type, data)
-                                ));
+                                );
                             }
                         }
                     }
@@ -507,7 +697,7 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
         }
         ItemEnum::TraitAlias(trait_alias) => {
             if let Some(name) = &item.name {
-                output.push_str(&format!("trait {}", name));
+                write!(output, "trait {}", name);
                 format_generics(output, &trait_alias.generics, data);
                 output.push_str(" = ");
                 format_trait_bounds(output, &trait_alias.params, data);
@@ -520,25 +710,54 @@ fn format_item_signature(output: &mut String, item: &Item, data: &Crate) {
         }
         ItemEnum::TypeAlias(type_alias) => {
             if let Some(name) = &item.name {
-                output.push_str(&format!("type {}", name));
+                write!(output, "type {}", name);
                 format_generics(output, &type_alias.generics, data);
                 format_where_clause(output, &type_alias.generics.where_predicates, data);
-                output.push_str(&format!(" = {};", format_type(&type_alias.type_, data)));
+                write!(output, " = {};", format_type(&type_alias.type_, data));
             }
         }
         ItemEnum::Macro(macro_body) => {
             if let Some(name) = &item.name {
-                output.push_str(&format!(
+                write!(
+                    output,
                     "macro_rules! {} {{\n    /* {} */\n}}",
                     name, macro_body
-                ));
+                );
+            }
+        }
+        ItemEnum::AssocConst { type_, value } => {
+            if let Some(name) = &item.name {
+                write!(output, "const {}: {}", name, format_type(type_, data));
+                if let Some(value) = value {
+                    write!(output, " = {}", value);
+                }
+                output.push(';');
+            }
+        }
+        ItemEnum::AssocType {
+            generics,
+            bounds,
+            type_,
+        } => {
+            if let Some(name) = &item.name {
+                write!(output, "type {}", name);
+                format_generics(output, generics, data);
+                if !bounds.is_empty() {
+                    output.push_str(": ");
+                    format_trait_bounds(output, bounds, data);
+                }
+                if let Some(default_type) = type_ {
+                    write!(output, " = {}", format_type(default_type, data));
+                }
+                format_where_clause(output, &generics.where_predicates, data);
+                output.push(';');
             }
         }
         // Add more cases as needed for other item kinds
         _ => {
             // Default case for other item kinds
             if let Some(name) = &item.name {
-                output.push_str(&format!("/* {} */", name));
+                write!(output, "/* {} */", name);
             } else {
                 output.push_str("/* unnamed item */");
             }
@@ -556,11 +775,11 @@ fn format_generics(output: &mut String, generics: &Generics, data: &Crate) {
     for (i, param) in generics.params.iter().enumerate() {
         match &param.kind {
             rustdoc_types::GenericParamDefKind::Lifetime { outlives } => {
-                output.push_str(&format!("'{}", param.name));
+                write!(output, "'{}", param.name);
                 if !outlives.is_empty() {
                     output.push_str(": ");
                     for (j, lifetime) in outlives.iter().enumerate() {
-                        output.push_str(&format!("'{}", lifetime));
+                        write!(output, "'{}", lifetime);
                         if j < outlives.len() - 1 {
                             output.push_str(" + ");
                         }
@@ -583,17 +802,13 @@ fn format_generics(output: &mut String, generics: &Generics, data: &Crate) {
                     format_trait_bounds(output, bounds, data);
                 }
                 if let Some(default_type) = default {
-                    output.push_str(&format!(" = {}", format_type(default_type, data)));
+                    write!(output, " = {}", format_type(default_type, data));
                 }
             }
             rustdoc_types::GenericParamDefKind::Const { type_, default } => {
-                output.push_str(&format!(
-                    "const {}: {}",
-                    param.name,
-                    format_type(type_, data)
-                ));
+                write!(output, "const {}: {}", param.name, format_type(type_, data));
                 if let Some(default_value) = default {
-                    output.push_str(&format!(" = {}", default_value));
+                    write!(output, " = {}", default_value);
                 }
             }
         }
@@ -628,7 +843,7 @@ fn format_where_clause(
                     for (j, param) in generic_params.iter().enumerate() {
                         match &param.kind {
                             rustdoc_types::GenericParamDefKind::Lifetime { .. } => {
-                                output.push_str(&format!("'{}", param.name));
+                                write!(output, "'{}", param.name);
                             }
                             _ => output.push_str(&param.name),
                         }
@@ -640,7 +855,7 @@ fn format_where_clause(
                     output.push_str("> ");
                 }
 
-                output.push_str(&format_type(type_, data));
+                write_type(output, type_, data);
 
                 if !bounds.is_empty() {
                     output.push_str(": ");
@@ -648,11 +863,11 @@ fn format_where_clause(
                 }
             }
             rustdoc_types::WherePredicate::LifetimePredicate { lifetime, outlives } => {
-                output.push_str(&format!("'{}", lifetime));
+                write!(output, "'{}", lifetime);
                 if !outlives.is_empty() {
                     output.push_str(": ");
                     for (j, lt) in outlives.iter().enumerate() {
-                        output.push_str(&format!("'{}", lt));
+                        write!(output, "'{}", lt);
                         if j < outlives.len() - 1 {
                             output.push_str(" + ");
                         }
@@ -660,7 +875,7 @@ fn format_where_clause(
                 }
             }
             rustdoc_types::WherePredicate::EqPredicate { lhs, rhs } => {
-                output.push_str(&format_type(lhs, data));
+                write_type(output, lhs, data);
                 output.push_str(" = ");
                 match rhs {
                     rustdoc_types::Term::Type(type_) => output.push_str(&format_type(&type_, data)),
@@ -695,7 +910,7 @@ fn format_trait_bounds(output: &mut String, bounds: &[rustdoc_types::GenericBoun
                     for (j, param) in generic_params.iter().enumerate() {
                         match &param.kind {
                             rustdoc_types::GenericParamDefKind::Lifetime { .. } => {
-                                output.push_str(&format!("'{}", param.name));
+                                write!(output, "'{}", param.name);
                             }
                             _ => output.push_str(&param.name),
                         }
@@ -709,13 +924,11 @@ fn format_trait_bounds(output: &mut String, bounds: &[rustdoc_types::GenericBoun
 
                 output.push_str(&trait_.path);
                 if let Some(args) = &trait_.args {
-                    let mut args_str = String::new();
-                    format_generic_args(&mut args_str, args, data);
-                    output.push_str(&args_str);
+                    format_generic_args(output, args, data);
                 }
             }
             rustdoc_types::GenericBound::Outlives(lifetime) => {
-                output.push_str(&format!("'{}", lifetime));
+                write!(output, "'{}", lifetime);
             }
             // Handle other bound types if needed
             _ => output.push_str("/* unsupported bound */"),
@@ -740,7 +953,7 @@ fn format_generic_args(output: &mut String, args: &GenericArgs, data: &Crate) {
             // Format args
             for (i, arg) in args.iter().enumerate() {
                 match arg {
-                    GenericArg::Lifetime(lifetime) => output.push_str(&format!("'{}", lifetime)),
+                    GenericArg::Lifetime(lifetime) => write!(output, "'{}", lifetime),
                     GenericArg::Type(type_) => output.push_str(&format_type(type_, data)),
                     GenericArg::Const(constant) => output.push_str(&constant.expr),
                     GenericArg::Infer => output.push('_'),
@@ -756,10 +969,10 @@ fn format_generic_args(output: &mut String, args: &GenericArgs, data: &Crate) {
                 output.push_str(&constraint.name);
 
                 // Format constraint args if present
-                let mut args_str = String::new();
-                format_generic_args(&mut args_str, &constraint.args, data);
-                if !args_str.is_empty() && args_str != "<>" {
-                    output.push_str(&args_str);
+                let start = output.len();
+                format_generic_args(output, &constraint.args, data);
+                if &output[start..] == "<>" {
+                    output.truncate(start);
                 }
 
                 // In newer rustdoc-types, AssocItemConstraint has name, args, and binding
@@ -792,7 +1005,7 @@ fn format_generic_args(output: &mut String, args: &GenericArgs, data: &Crate) {
             output.push('(');
 
             for (i, input) in inputs.iter().enumerate() {
-                output.push_str(&format_type(input, data));
+                write_type(output, input, data);
                 if i < inputs.len() - 1 {
                     output.push_str(", ");
                 }
@@ -801,7 +1014,7 @@ fn format_generic_args(output: &mut String, args: &GenericArgs, data: &Crate) {
             output.push(')');
 
             if let Some(output_ty) = output_type {
-                output.push_str(&format!(" -> {}", format_type(output_ty, data)));
+                write!(output, " -> {}", format_type(output_ty, data));
             }
         }
         _ => {
@@ -813,14 +1026,17 @@ fn format_generic_args(output: &mut String, args: &GenericArgs, data: &Crate) {
 /// Format a type for display
 fn format_type(ty: &Type, data: &Crate) -> String {
     let mut output = String::new();
+    write_type(&mut output, ty, data);
+    output
+}
 
+/// Write a type directly into `output`, avoiding a temporary String per nested type
+fn write_type(output: &mut String, ty: &Type, data: &Crate) {
     match ty {
         Type::ResolvedPath(path) => {
             output.push_str(&path.path);
             if let Some(args) = &path.args {
-                let mut args_str = String::new();
-                format_generic_args(&mut args_str, args, data);
-                output.push_str(&args_str);
+                format_generic_args(output, args, data);
             }
         }
         Type::DynTrait(dyn_trait) => {
@@ -833,7 +1049,7 @@ fn format_type(ty: &Type, data: &Crate) -> String {
                     for (j, param) in trait_.generic_params.iter().enumerate() {
                         match &param.kind {
                             rustdoc_types::GenericParamDefKind::Lifetime { .. } => {
-                                output.push_str(&format!("'{}", param.name));
+                                write!(output, "'{}", param.name);
                             }
                             _ => output.push_str(&param.name),
                         }
@@ -847,9 +1063,7 @@ fn format_type(ty: &Type, data: &Crate) -> String {
 
                 output.push_str(&trait_.trait_.path);
                 if let Some(args) = &trait_.trait_.args {
-                    let mut args_str = String::new();
-                    format_generic_args(&mut args_str, args, data);
-                    output.push_str(&args_str);
+                    format_generic_args(output, args, data);
                 }
 
                 if i < dyn_trait.traits.len() - 1 {
@@ -859,7 +1073,7 @@ fn format_type(ty: &Type, data: &Crate) -> String {
 
             // Lifetime bound if present
             if let Some(lifetime) = &dyn_trait.lifetime {
-                output.push_str(&format!(" + '{}", lifetime));
+                write!(output, " + '{}", lifetime);
             }
         }
         Type::Generic(name) => {
@@ -875,7 +1089,7 @@ fn format_type(ty: &Type, data: &Crate) -> String {
                 for (j, param) in fn_ptr.generic_params.iter().enumerate() {
                     match &param.kind {
                         rustdoc_types::GenericParamDefKind::Lifetime { .. } => {
-                            output.push_str(&format!("'{}", param.name));
+                            write!(output, "'{}", param.name);
                         }
                         _ => output.push_str(&param.name),
                     }
@@ -896,13 +1110,13 @@ fn format_type(ty: &Type, data: &Crate) -> String {
             }
 
             // ABI
-            format_abi(&mut output, &fn_ptr.header.abi);
+            format_abi(output, &fn_ptr.header.abi);
 
             output.push_str("fn(");
 
             // Parameters
             for (i, (_, param_type)) in fn_ptr.sig.inputs.iter().enumerate() {
-                output.push_str(&format_type(param_type, data));
+                write_type(output, param_type, data);
                 if i < fn_ptr.sig.inputs.len() - 1 || fn_ptr.sig.is_c_variadic {
                     output.push_str(", ");
                 }
@@ -917,7 +1131,8 @@ fn format_type(ty: &Type, data: &Crate) -> String {
 
             // Return type
             if let Some(return_type) = &fn_ptr.sig.output {
-                output.push_str(&format!(" -> {}", format_type(return_type, data)));
+                output.push_str(" -> ");
+                write_type(output, return_type, data);
             }
         }
         Type::Tuple(types) => {
@@ -926,7 +1141,7 @@ fn format_type(ty: &Type, data: &Crate) -> String {
             } else {
                 output.push('(');
                 for (i, ty) in types.iter().enumerate() {
-                    output.push_str(&format_type(ty, data));
+                    write_type(output, ty, data);
                     if i < types.len() - 1 {
                         output.push_str(", ");
                     }
@@ -935,17 +1150,19 @@ fn format_type(ty: &Type, data: &Crate) -> String {
             }
         }
         Type::Slice(ty) => {
-            output.push_str(&format!("[{}]", format_type(ty, data)));
+            output.push('[');
+            write_type(output, ty, data);
+            output.push(']');
         }
         Type::Array { type_, len } => {
-            output.push_str(&format!("[{}; {}]", format_type(type_, data), len));
+            output.push('[');
+            write_type(output, type_, data);
+            write!(output, "; {}]", len);
         }
         Type::ImplTrait(bounds) => {
             output.push_str("impl ");
 
-            let mut bounds_str = String::new();
-            format_trait_bounds(&mut bounds_str, bounds, data);
-            output.push_str(&bounds_str);
+            format_trait_bounds(output, bounds, data);
         }
         Type::Infer => {
             output.push('_');
@@ -956,7 +1173,7 @@ fn format_type(ty: &Type, data: &Crate) -> String {
             } else {
                 output.push_str("*const ");
             }
-            output.push_str(&format_type(type_, data));
+            write_type(output, type_, data);
         }
         Type::BorrowedRef {
             lifetime,
@@ -965,12 +1182,12 @@ fn format_type(ty: &Type, data: &Crate) -> String {
         } => {
             output.push('&');
             if let Some(lt) = lifetime {
-                output.push_str(&format!("'{} ", lt));
+                write!(output, "'{} ", lt);
             }
             if *is_mutable {
                 output.push_str("mut ");
             }
-            output.push_str(&format_type(type_, data));
+            write_type(output, type_, data);
         }
         Type::QualifiedPath {
             name,
@@ -979,23 +1196,22 @@ fn format_type(ty: &Type, data: &Crate) -> String {
             trait_,
         } => {
             output.push('<');
-            output.push_str(&format_type(self_type, data));
+            write_type(output, self_type, data);
 
             if let Some(trait_path) = trait_ {
-                output.push_str(&format!(" as {}", trait_path.path));
+                write!(output, " as {}", trait_path.path);
                 if let Some(trait_args) = &trait_path.args {
-                    let mut args_str = String::new();
-                    format_generic_args(&mut args_str, trait_args, data);
-                    output.push_str(&args_str);
+                    format_generic_args(output, trait_args, data);
                 }
             }
 
-            output.push_str(&format!(">::{}", name));
+            write!(output, ">::{}", name);
 
-            let mut args_str = String::new();
-            format_generic_args(&mut args_str, args, data);
-            if args_str != "<>" && !args_str.is_empty() {
-                output.push_str(&args_str);
+            // Write in place and drop an empty `<>` rather than staging in a temporary
+            let start = output.len();
+            format_generic_args(output, args, data);
+            if &output[start..] == "<>" {
+                output.truncate(start);
             }
         }
         // Handle other types as needed
@@ -1003,8 +1219,6 @@ fn format_type(ty: &Type, data: &Crate) -> String {
             output.push_str("/* unsupported type */");
         }
     }
-
-    output
 }
 
 /// Format ABI for functions
@@ -1068,7 +1282,7 @@ fn format_abi(output: &mut String, abi: &rustdoc_types::Abi) {
             }
         }
         rustdoc_types::Abi::Other(abi) => {
-            output.push_str(&format!("extern \"{}\" ", abi));
+            write!(output, "extern \"{}\" ", abi);
         }
     }
 }
@@ -1091,7 +1305,7 @@ fn format_function_signature(output: &mut String, item: &Item, function: &Functi
 
     // Function name
     if let Some(name) = &item.name {
-        output.push_str(&format!("fn {}", name));
+        write!(output, "fn {}", name);
 
         // Generic parameters
         format_generics(output, &function.generics, data);
@@ -1099,11 +1313,7 @@ fn format_function_signature(output: &mut String, item: &Item, function: &Functi
         // Parameters
         output.push('(');
         for (i, (param_name, param_type)) in function.sig.inputs.iter().enumerate() {
-            output.push_str(&format!(
-                "{}: {}",
-                param_name,
-                format_type(param_type, data)
-            ));
+            write!(output, "{}: {}", param_name, format_type(param_type, data));
             if i < function.sig.inputs.len() - 1 || function.sig.is_c_variadic {
                 output.push_str(", ");
             }
@@ -1118,7 +1328,7 @@ fn format_function_signature(output: &mut String, item: &Item, function: &Functi
 
         // Return type
         if let Some(return_type) = &function.sig.output {
-            output.push_str(&format!(" -> {}", format_type(return_type, data)));
+            write!(output, " -> {}", format_type(return_type, data));
         }
 
         // Where clause
@@ -1136,14 +1346,14 @@ fn format_function_signature(output: &mut String, item: &Item, function: &Functi
 /// Format an enum signature
 fn format_enum_signature(output: &mut String, item: &Item, enum_: &Enum, data: &Crate) {
     if let Some(name) = &item.name {
-        output.push_str(&format!("enum {}", name));
+        write!(output, "enum {}", name);
         format_generics(output, &enum_.generics, data);
         output.push_str(" {\n");
 
         for variant_id in &enum_.variants {
             if let Some(variant_item) = data.index.get(&variant_id) {
                 if let Some(variant_name) = &variant_item.name {
-                    output.push_str(&format!("    {}", variant_name));
+                    write!(output, "    {}", variant_name);
 
                     if let ItemEnum::Variant(variant) = &variant_item.inner {
                         match &variant.kind {
@@ -1156,7 +1366,7 @@ fn format_enum_signature(output: &mut String, item: &Item, enum_: &Enum, data: &
                                             if let ItemEnum::StructField(field_type) =
                                                 &field_item.inner
                                             {
-                                                output.push_str(&format_type(field_type, data));
+                                                write_type(output, field_type, data);
                                             }
                                         }
                                         if i < fields.len() - 1 {
@@ -1183,11 +1393,12 @@ fn format_enum_signature(output: &mut String, item: &Item, enum_: &Enum, data: &
                                             if let ItemEnum::StructField(field_type) =
                                                 &field_item.inner
                                             {
-                                                output.push_str(&format!(
-                                                    "        {}: {},\n",
+                                                writeln!(
+                                                    output,
+                                                    "        {}: {},",
                                                     field_name,
                                                     format_type(field_type, data)
-                                                ));
+                                                );
                                             }
                                         }
                                     }
@@ -1200,7 +1411,7 @@ fn format_enum_signature(output: &mut String, item: &Item, enum_: &Enum, data: &
                         }
 
                         if let Some(discriminant) = &variant.discriminant {
-                            output.push_str(&format!(" = {}", discriminant.expr));
+                            write!(output, " = {}", discriminant.expr);
                         }
                     }
 
@@ -1229,7 +1440,7 @@ fn format_trait_signature(output: &mut String, item: &Item, trait_: &Trait, data
 
     // Trait definition
     if let Some(name) = &item.name {
-        output.push_str(&format!("trait {}", name));
+        write!(output, "trait {}", name);
         format_generics(output, &trait_.generics, data);
 
         // Trait bounds
@@ -1267,16 +1478,14 @@ fn format_impl_signature(output: &mut String, impl_: &Impl, data: &Crate) {
 
         output.push_str(&trait_.path);
         if let Some(args) = &trait_.args {
-            let mut args_str = String::new();
-            format_generic_args(&mut args_str, args, data);
-            output.push_str(&args_str);
+            format_generic_args(output, args, data);
         }
 
         output.push_str(" for ");
     }
 
     // For type
-    output.push_str(&format_type(&impl_.for_, data));
+    write_type(output, &impl_.for_, data);
 
     // Where clause
     format_where_clause(output, &impl_.generics.where_predicates, data);
@@ -1291,9 +1500,10 @@ fn format_impl_signature(output: &mut String, impl_: &Impl, data: &Crate) {
 
 /// Process module details
 fn process_module_details(
-    output: &mut String,
+    output: &mut MarkdownOutput,
     module: &rustdoc_types::Module,
     data: &Crate,
+    ctx: &Context,
     level: usize,
 ) {
     if module.is_stripped {
@@ -1303,7 +1513,7 @@ fn process_module_details(
     }
 
     // Continue processing items at the next level - fully recursive, no capping
-    process_items(output, &module.items, data, level);
+    process_items(output, &module.items, data, ctx, level);
 }
 
 /// Process struct details
@@ -1314,6 +1524,9 @@ fn process_struct_details(
     data: &Crate,
     level: usize,
 ) {
+    // Reused across methods to avoid a fresh allocation per signature
+    let mut method_signature = String::new();
+
     // Cap heading level at 6 (maximum valid Markdown heading level)
     let heading_level = std::cmp::min(level, 6);
 
@@ -1324,7 +1537,7 @@ fn process_struct_details(
         }
         StructKind::Tuple(fields) => {
             // Use heading_level for Fields section (since level is already incremented in process_item)
-            output.push_str(&format!("{} Fields\n\n", "#".repeat(heading_level)));
+            write!(output, "{} Fields\n\n", hashes(heading_level));
             output.push_str("| Index | Type | Documentation |\n");
             output.push_str("|-------|------|---------------|\n");
 
@@ -1336,16 +1549,17 @@ fn process_struct_details(
                                 Some(d) => d.replace('\n', "<br>"),
                                 None => String::new(),
                             };
-                            output.push_str(&format!(
-                                "| {} | `{}` | {} |\n",
+                            writeln!(
+                                output,
+                                "| {} | `{}` | {} |",
                                 i,
                                 format_type(field_type, data),
                                 docs
-                            ));
+                            );
                         }
                     }
                 } else {
-                    output.push_str(&format!("| {} | `private` | *Private field* |\n", i));
+                    writeln!(output, "| {} | `private` | *Private field* |", i);
                 }
             }
             output.push('\n');
@@ -1355,7 +1569,7 @@ fn process_struct_details(
             has_stripped_fields,
         } => {
             // Use heading_level for Fields section
-            output.push_str(&format!("{} Fields\n\n", "#".repeat(heading_level)));
+            write!(output, "{} Fields\n\n", hashes(heading_level));
             output.push_str("| Name | Type | Documentation |\n");
             output.push_str("|------|------|---------------|\n");
 
@@ -1367,12 +1581,13 @@ fn process_struct_details(
                                 Some(d) => d.replace('\n', "<br>"),
                                 None => String::new(),
                             };
-                            output.push_str(&format!(
-                                "| `{}` | `{}` | {} |\n",
+                            writeln!(
+                                output,
+                                "| `{}` | `{}` | {} |",
                                 field_name,
                                 format_type(field_type, data),
                                 docs
-                            ));
+                            );
                         }
                     }
                 }
@@ -1389,10 +1604,7 @@ fn process_struct_details(
     // Process impls
     if !struct_.impls.is_empty() {
         // Use heading_level for Implementations section
-        output.push_str(&format!(
-            "{} Implementations\n\n",
-            "#".repeat(heading_level)
-        ));
+        write!(output, "{} Implementations\n\n", hashes(heading_level));
 
         // Group impls by trait
         let mut trait_impls = std::collections::HashMap::new();
@@ -1418,10 +1630,11 @@ fn process_struct_details(
         // First list inherent impls
         if !inherent_impls.is_empty() {
             // Use level+1 for Methods (one level deeper than Implementations)
-            output.push_str(&format!(
+            write!(
+                output,
                 "{} Methods\n\n",
-                "#".repeat(std::cmp::min(heading_level + 1, 6))
-            ));
+                hashes(std::cmp::min(heading_level + 1, 6))
+            );
             for impl_id in &inherent_impls {
                 if let Some(impl_item) = data.index.get(&impl_id) {
                     if let ItemEnum::Impl(impl_) = &impl_item.inner {
@@ -1429,7 +1642,7 @@ fn process_struct_details(
                             if let Some(method_item) = data.index.get(&item_id) {
                                 if let ItemEnum::Function(_) = &method_item.inner {
                                     // Format method signature
-                                    let mut method_signature = String::new();
+                                    method_signature.clear();
                                     format_item_signature(&mut method_signature, method_item, data);
 
                                     // Output with proper code block formatting
@@ -1441,7 +1654,7 @@ fn process_struct_details(
                                     if let Some(docs) = &method_item.docs {
                                         if let Some(first_line) = docs.lines().next() {
                                             if !first_line.trim().is_empty() {
-                                                output.push_str(&format!("\n  {}", first_line));
+                                                write!(output, "\n  {}", first_line);
                                             }
                                         }
                                     }
@@ -1457,12 +1670,13 @@ fn process_struct_details(
         // Then list trait impls
         if !trait_impls.is_empty() {
             // Use level+1 for Trait Implementations (one level deeper than Implementations)
-            output.push_str(&format!(
+            write!(
+                output,
                 "{} Trait Implementations\n\n",
-                "#".repeat(std::cmp::min(heading_level + 1, 6))
-            ));
+                hashes(std::cmp::min(heading_level + 1, 6))
+            );
             for (trait_name, impls) in trait_impls {
-                output.push_str(&format!("- **{}**\n", trait_name));
+                writeln!(output, "- **{}**", trait_name);
                 for impl_id in &impls {
                     if let Some(impl_item) = data.index.get(&impl_id) {
                         if let ItemEnum::Impl(impl_) = &impl_item.inner {
@@ -1470,7 +1684,7 @@ fn process_struct_details(
                                 if let Some(method_item) = data.index.get(&item_id) {
                                     if let ItemEnum::Function(_) = &method_item.inner {
                                         // Format method signature
-                                        let mut method_signature = String::new();
+                                        method_signature.clear();
                                         format_item_signature(
                                             &mut method_signature,
                                             method_item,
@@ -1486,8 +1700,7 @@ fn process_struct_details(
                                         if let Some(docs) = &method_item.docs {
                                             if let Some(first_line) = docs.lines().next() {
                                                 if !first_line.trim().is_empty() {
-                                                    output
-                                                        .push_str(&format!("\n    {}", first_line));
+                                                    write!(output, "\n    {}", first_line);
                                                 }
                                             }
                                         }
@@ -1511,26 +1724,30 @@ fn process_enum_details(
     data: &Crate,
     level: usize,
 ) {
+    // Reused across methods to avoid a fresh allocation per signature
+    let mut method_signature = String::new();
+
     // Cap heading level at 6 (maximum valid Markdown heading level)
     let heading_level = std::cmp::min(level, 6);
 
     // Detail variants with proper nesting
-    output.push_str(&format!("{} Variants\n\n", "#".repeat(heading_level)));
+    write!(output, "{} Variants\n\n", hashes(heading_level));
 
     for variant_id in &enum_.variants {
         if let Some(variant_item) = data.index.get(&variant_id) {
             if let Some(variant_name) = &variant_item.name {
                 // Use heading_level + 1 for individual variants (capped at 6)
                 let variant_heading_level = std::cmp::min(heading_level + 1, 6);
-                output.push_str(&format!(
+                write!(
+                    output,
                     "{} `{}`\n\n",
-                    "#".repeat(variant_heading_level),
+                    hashes(variant_heading_level),
                     variant_name
-                ));
+                );
 
                 // Add variant docs if available
                 if let Some(docs) = &variant_item.docs {
-                    output.push_str(&format!("{}\n\n", docs));
+                    write!(output, "{}\n\n", docs);
                 }
 
                 if let ItemEnum::Variant(variant) = &variant_item.inner {
@@ -1538,10 +1755,7 @@ fn process_enum_details(
                         VariantKind::Plain => {
                             // Nothing additional to display for plain variants
                             if let Some(discriminant) = &variant.discriminant {
-                                output.push_str(&format!(
-                                    "Discriminant: `{}`\n\n",
-                                    discriminant.expr
-                                ));
+                                write!(output, "Discriminant: `{}`\n\n", discriminant.expr);
                             }
                         }
                         VariantKind::Tuple(fields) => {
@@ -1558,19 +1772,17 @@ fn process_enum_details(
                                                 Some(d) => d.replace('\n', "<br>"),
                                                 None => String::new(),
                                             };
-                                            output.push_str(&format!(
-                                                "| {} | `{}` | {} |\n",
+                                            writeln!(
+                                                output,
+                                                "| {} | `{}` | {} |",
                                                 i,
                                                 format_type(field_type, data),
                                                 docs
-                                            ));
+                                            );
                                         }
                                     }
                                 } else {
-                                    output.push_str(&format!(
-                                        "| {} | `private` | *Private field* |\n",
-                                        i
-                                    ));
+                                    writeln!(output, "| {} | `private` | *Private field* |", i);
                                 }
                             }
                             output.push('\n');
@@ -1592,12 +1804,13 @@ fn process_enum_details(
                                                 Some(d) => d.replace('\n', "<br>"),
                                                 None => String::new(),
                                             };
-                                            output.push_str(&format!(
-                                                "| `{}` | `{}` | {} |\n",
+                                            writeln!(
+                                                output,
+                                                "| `{}` | `{}` | {} |",
                                                 field_name,
                                                 format_type(field_type, data),
                                                 docs
-                                            ));
+                                            );
                                         }
                                     }
                                 }
@@ -1612,8 +1825,7 @@ fn process_enum_details(
                     }
 
                     if let Some(discriminant) = &variant.discriminant {
-                        output
-                            .push_str(&format!("Discriminant value: `{}`\n\n", discriminant.value));
+                        write!(output, "Discriminant value: `{}`\n\n", discriminant.value);
                     }
                 }
             }
@@ -1628,10 +1840,7 @@ fn process_enum_details(
 
     // Process impls (same as for struct)
     if !enum_.impls.is_empty() {
-        output.push_str(&format!(
-            "{} Implementations\n\n",
-            "#".repeat(heading_level)
-        ));
+        write!(output, "{} Implementations\n\n", hashes(heading_level));
 
         // Group impls by trait
         let mut trait_impls = std::collections::HashMap::new();
@@ -1657,7 +1866,7 @@ fn process_enum_details(
         // First list inherent impls
         if !inherent_impls.is_empty() {
             let methods_level = std::cmp::min(heading_level + 1, 6);
-            output.push_str(&format!("{} Methods\n\n", "#".repeat(methods_level)));
+            write!(output, "{} Methods\n\n", hashes(methods_level));
             for impl_id in &inherent_impls {
                 if let Some(impl_item) = data.index.get(&impl_id) {
                     if let ItemEnum::Impl(impl_) = &impl_item.inner {
@@ -1665,7 +1874,7 @@ fn process_enum_details(
                             if let Some(method_item) = data.index.get(&item_id) {
                                 if let ItemEnum::Function(_) = &method_item.inner {
                                     // Format method signature
-                                    let mut method_signature = String::new();
+                                    method_signature.clear();
                                     format_item_signature(&mut method_signature, method_item, data);
 
                                     // Output with proper code block formatting
@@ -1677,7 +1886,7 @@ fn process_enum_details(
                                     if let Some(docs) = &method_item.docs {
                                         if let Some(first_line) = docs.lines().next() {
                                             if !first_line.trim().is_empty() {
-                                                output.push_str(&format!("\n  {}", first_line));
+                                                write!(output, "\n  {}", first_line);
                                             }
                                         }
                                     }
@@ -1693,12 +1902,13 @@ fn process_enum_details(
         // Then list trait impls
         if !trait_impls.is_empty() {
             let trait_impl_level = std::cmp::min(heading_level + 1, 6);
-            output.push_str(&format!(
+            write!(
+                output,
                 "{} Trait Implementations\n\n",
-                "#".repeat(trait_impl_level)
-            ));
+                hashes(trait_impl_level)
+            );
             for (trait_name, impls) in trait_impls {
-                output.push_str(&format!("- **{}**\n", trait_name));
+                writeln!(output, "- **{}**", trait_name);
                 for impl_id in &impls {
                     if let Some(impl_item) = data.index.get(&impl_id) {
                         if let ItemEnum::Impl(impl_) = &impl_item.inner {
@@ -1706,7 +1916,7 @@ fn process_enum_details(
                                 if let Some(method_item) = data.index.get(&item_id) {
                                     if let ItemEnum::Function(_) = &method_item.inner {
                                         // Format method signature
-                                        let mut method_signature = String::new();
+                                        method_signature.clear();
                                         format_item_signature(
                                             &mut method_signature,
                                             method_item,
@@ -1722,8 +1932,7 @@ fn process_enum_details(
                                         if let Some(docs) = &method_item.docs {
                                             if let Some(first_line) = docs.lines().next() {
                                                 if !first_line.trim().is_empty() {
-                                                    output
-                                                        .push_str(&format!("\n    {}", first_line));
+                                                    write!(output, "\n    {}", first_line);
                                                 }
                                             }
                                         }
@@ -1751,7 +1960,7 @@ fn process_union_details(
     let heading_level = std::cmp::min(level, 6);
 
     // Detail fields
-    output.push_str(&format!("{} Fields\n\n", "#".repeat(heading_level)));
+    write!(output, "{} Fields\n\n", hashes(heading_level));
     output.push_str("| Name | Type | Documentation |\n");
     output.push_str("|------|------|---------------|\n");
 
@@ -1763,12 +1972,13 @@ fn process_union_details(
                         Some(d) => d.replace('\n', "<br>"),
                         None => String::new(),
                     };
-                    output.push_str(&format!(
-                        "| `{}` | `{}` | {} |\n",
+                    writeln!(
+                        output,
+                        "| `{}` | `{}` | {} |",
                         field_name,
                         format_type(field_type, data),
                         docs
-                    ));
+                    );
                 }
             }
         }
@@ -1782,10 +1992,7 @@ fn process_union_details(
 
     // Process impls
     if !union_.impls.is_empty() {
-        output.push_str(&format!(
-            "{} Implementations\n\n",
-            "#".repeat(heading_level)
-        ));
+        write!(output, "{} Implementations\n\n", hashes(heading_level));
 
         // Group impls by trait
         let mut trait_impls = std::collections::HashMap::new();
@@ -1811,7 +2018,7 @@ fn process_union_details(
         // First list inherent impls
         if !inherent_impls.is_empty() {
             let methods_level = std::cmp::min(heading_level + 1, 6);
-            output.push_str(&format!("{} Methods\n\n", "#".repeat(methods_level)));
+            write!(output, "{} Methods\n\n", hashes(methods_level));
             for impl_id in &inherent_impls {
                 process_impl_methods(output, (*impl_id).clone(), data, heading_level + 2);
             }
@@ -1820,19 +2027,20 @@ fn process_union_details(
         // Then list trait impls
         if !trait_impls.is_empty() {
             let trait_impl_level = std::cmp::min(heading_level + 1, 6);
-            output.push_str(&format!(
+            write!(
+                output,
                 "{} Trait Implementations\n\n",
-                "#".repeat(trait_impl_level)
-            ));
+                hashes(trait_impl_level)
+            );
             for (trait_name, impls) in trait_impls {
-                output.push_str(&format!("- **{}**\n", trait_name));
+                writeln!(output, "- **{}**", trait_name);
                 for impl_id in &impls {
                     if let Some(impl_item) = data.index.get(&impl_id) {
                         if let ItemEnum::Impl(impl_) = &impl_item.inner {
                             for method_id in &impl_.items {
                                 if let Some(method_item) = data.index.get(&method_id) {
                                     if let Some(name) = &method_item.name {
-                                        output.push_str(&format!("  - `{}`: ", name));
+                                        write!(output, "  - `{}`: ", name);
                                         if let Some(docs) = &method_item.docs {
                                             let first_line = match docs.lines().next() {
                                                 Some(line) => line,
@@ -1860,6 +2068,9 @@ fn process_trait_details(
     data: &Crate,
     level: usize,
 ) {
+    // Reused across methods to avoid a fresh allocation per signature
+    let mut method_signature = String::new();
+
     // Cap heading level at 6 (maximum valid Markdown heading level)
     let heading_level = std::cmp::min(level, 6);
 
@@ -1883,24 +2094,31 @@ fn process_trait_details(
         let mut provided_methods = Vec::new();
         let mut assoc_types = Vec::new();
         let mut assoc_consts = Vec::new();
+        // Associated types and constants with defaults
+        let mut provided_assoc = Vec::new();
 
         for item_id in &trait_.items {
             if let Some(item) = data.index.get(&item_id) {
                 match &item.inner {
                     ItemEnum::Function(function) => {
                         if function.has_body {
-                            provided_methods.push(item_id);
+                            provided_methods.push(item);
                         } else {
-                            required_methods.push(item_id);
+                            required_methods.push(item);
                         }
                     }
-                    ItemEnum::AssocType { .. } => assoc_types.push(item_id),
-                    ItemEnum::AssocConst { type_: _, value } => {
+                    ItemEnum::AssocType { type_, .. } => {
+                        if type_.is_some() {
+                            provided_assoc.push(item);
+                        } else {
+                            assoc_types.push(item);
+                        }
+                    }
+                    ItemEnum::AssocConst { value, .. } => {
                         if value.is_some() {
-                            // Has a default value
-                            provided_methods.push(item_id);
+                            provided_assoc.push(item);
                         } else {
-                            assoc_consts.push(item_id);
+                            assoc_consts.push(item);
                         }
                     }
                     _ => {}
@@ -1910,72 +2128,39 @@ fn process_trait_details(
 
         // Required items
         if !required_methods.is_empty() || !assoc_types.is_empty() || !assoc_consts.is_empty() {
-            output.push_str(&format!("{} Required Items\n\n", "#".repeat(heading_level)));
+            write!(output, "{} Required Items\n\n", hashes(heading_level));
 
             if !assoc_types.is_empty() {
-                output.push_str(&format!(
-                    "{} Associated Types\n\n",
-                    "#".repeat(heading_level + 1)
-                ));
-                for type_id in &assoc_types {
-                    if let Some(type_item) = data.index.get(&type_id) {
-                        if let Some(name) = &type_item.name {
-                            output.push_str(&format!("- `{}`", name));
-                            if let Some(docs) = &type_item.docs {
-                                if let Some(first_line) = docs.lines().next() {
-                                    if !first_line.trim().is_empty() {
-                                        output.push_str(&format!(": {}", first_line));
-                                    }
-                                }
-                            }
-                            output.push('\n');
-                        }
-                    }
+                write!(output, "{} Associated Types\n\n", hashes(heading_level + 1));
+                for type_item in &assoc_types {
+                    write_signature_entry(output, &mut method_signature, type_item, data);
                 }
-                output.push('\n');
             }
 
             if !assoc_consts.is_empty() {
-                output.push_str(&format!(
+                write!(
+                    output,
                     "{} Associated Constants\n\n",
-                    "#".repeat(heading_level + 1)
-                ));
-                for const_id in &assoc_consts {
-                    if let Some(const_item) = data.index.get(&const_id) {
-                        if let Some(name) = &const_item.name {
-                            output.push_str(&format!("- `{}`", name));
-                            if let Some(docs) = &const_item.docs {
-                                if let Some(first_line) = docs.lines().next() {
-                                    if !first_line.trim().is_empty() {
-                                        output.push_str(&format!(": {}", first_line));
-                                    }
-                                }
-                            }
-                            output.push('\n');
-                        }
-                    }
+                    hashes(heading_level + 1)
+                );
+                for const_item in &assoc_consts {
+                    write_signature_entry(output, &mut method_signature, const_item, data);
                 }
-                output.push('\n');
             }
 
             if !required_methods.is_empty() {
-                output.push_str(&format!(
-                    "{} Required Methods\n\n",
-                    "#".repeat(heading_level + 1)
-                ));
-                for method_id in &required_methods {
-                    if let Some(method_item) = data.index.get(&method_id) {
-                        if let Some(name) = &method_item.name {
-                            output.push_str(&format!("- `{}`", name));
-                            if let Some(docs) = &method_item.docs {
-                                if let Some(first_line) = docs.lines().next() {
-                                    if !first_line.trim().is_empty() {
-                                        output.push_str(&format!(": {}", first_line));
-                                    }
+                write!(output, "{} Required Methods\n\n", hashes(heading_level + 1));
+                for method_item in &required_methods {
+                    if let Some(name) = &method_item.name {
+                        write!(output, "- `{}`", name);
+                        if let Some(docs) = &method_item.docs {
+                            if let Some(first_line) = docs.lines().next() {
+                                if !first_line.trim().is_empty() {
+                                    write!(output, ": {}", first_line);
                                 }
                             }
-                            output.push('\n');
                         }
+                        output.push('\n');
                     }
                 }
                 output.push('\n');
@@ -1983,50 +2168,34 @@ fn process_trait_details(
         }
 
         // Provided items
-        if !provided_methods.is_empty() {
-            output.push_str(&format!(
-                "{} Provided Methods\n\n",
-                "#".repeat(heading_level)
-            ));
-            for method_id in &provided_methods {
-                if let Some(method_item) = data.index.get(&method_id) {
-                    if let ItemEnum::Function(_) = &method_item.inner {
-                        // Format method signature
-                        let mut method_signature = String::new();
-                        format_item_signature(&mut method_signature, method_item, data);
-
-                        // Output with proper code block formatting
-                        output.push_str("- ```rust\n  ");
-                        output.push_str(&method_signature.trim());
-                        output.push_str("\n  ```");
+        if !provided_assoc.is_empty() {
+            write!(
+                output,
+                "{} Provided Associated Items\n\n",
+                hashes(heading_level)
+            );
+            for assoc_item in &provided_assoc {
+                write_signature_entry(output, &mut method_signature, assoc_item, data);
+            }
+        }
 
-                        // Add documentation if available
-                        if let Some(docs) = &method_item.docs {
-                            if let Some(first_line) = docs.lines().next() {
-                                if !first_line.trim().is_empty() {
-                                    output.push_str(&format!("\n  {}", first_line));
-                                }
-                            }
-                        }
-                        output.push_str("\n\n");
-                    }
-                }
+        if !provided_methods.is_empty() {
+            write!(output, "{} Provided Methods\n\n", hashes(heading_level));
+            for method_item in &provided_methods {
+                write_signature_entry(output, &mut method_signature, method_item, data);
             }
         }
     }
 
     // Implementations
     if !trait_.implementations.is_empty() {
-        output.push_str(&format!(
-            "{} Implementations\n\n",
-            "#".repeat(heading_level)
-        ));
+        write!(output, "{} Implementations\n\n", hashes(heading_level));
         output.push_str("This trait is implemented for the following types:\n\n");
 
         for impl_id in &trait_.implementations {
             if let Some(impl_item) = data.index.get(&impl_id) {
                 if let ItemEnum::Impl(impl_) = &impl_item.inner {
-                    output.push_str(&format!("- `{}`", format_type(&impl_.for_, data)));
+                    write!(output, "- `{}`", format_type(&impl_.for_, data));
                     // Add generics if present
                     if !impl_.generics.params.is_empty() {
                         let mut generics_str = String::new();
@@ -2044,12 +2213,34 @@ fn process_trait_details(
     }
 }
 
+/// Write `item`'s signature as a list entry, followed by the first line of
+/// its docs. `signature` is scratch space reused between entries.
+fn write_signature_entry(output: &mut String, signature: &mut String, item: &Item, data: &Crate) {
+    signature.clear();
+    format_item_signature(signature, item, data);
+
+    // Continuation lines (where clauses) stay inside the list item
+    output.push_str("- ```rust\n  ");
+    output.push_str(&signature.trim().replace('\n', "\n  "));
+    output.push_str("\n  ```");
+
+    if let Some(docs) = &item.docs {
+        if let Some(first_line) = docs.lines().next() {
+            if !first_line.trim().is_empty() {
+                write!(output, "\n  {}", first_line);
+            }
+        }
+    }
+    output.push_str("\n\n");
+}
+
 /// Process impl details
 fn process_impl_details(
-    output: &mut String,
+    output: &mut MarkdownOutput,
     impl_: &Impl,
     _item: &Item,
     data: &Crate,
+    ctx: &Context,
     level: usize,
 ) {
     // Cap heading level at 6 (maximum valid Markdown heading level)
@@ -2057,10 +2248,7 @@ fn process_impl_details(
 
     // List all items in the impl
     if !impl_.items.is_empty() {
-        output.push_str(&format!(
-            "{} Associated Items\n\n",
-            "#".repeat(heading_level)
-        ));
+        write!(output, "{} Associated Items\n\n", hashes(heading_level));
 
         // Group by kind
         let mut methods = Vec::new();
@@ -2079,34 +2267,32 @@ fn process_impl_details(
         }
 
         if !assoc_types.is_empty() {
-            output.push_str(&format!(
-                "{} Associated Types\n\n",
-                "#".repeat(heading_level + 1)
-            ));
+            write!(output, "{} Associated Types\n\n", hashes(heading_level + 1));
             for type_id in &assoc_types {
                 if let Some(assoc_item) = data.index.get(&type_id) {
-                    process_item(output, assoc_item, data, level + 2);
+                    process_item(output, assoc_item, data, ctx, level + 2);
                 }
             }
         }
 
         if !assoc_consts.is_empty() {
-            output.push_str(&format!(
+            write!(
+                output,
                 "{} Associated Constants\n\n",
-                "#".repeat(heading_level + 1)
-            ));
+                hashes(heading_level + 1)
+            );
             for const_id in &assoc_consts {
                 if let Some(assoc_item) = data.index.get(&const_id) {
-                    process_item(output, assoc_item, data, level + 2);
+                    process_item(output, assoc_item, data, ctx, level + 2);
                 }
             }
         }
 
         if !methods.is_empty() {
-            output.push_str(&format!("{} Methods\n\n", "#".repeat(heading_level + 1)));
+            write!(output, "{} Methods\n\n", hashes(heading_level + 1));
             for method_id in &methods {
                 if let Some(method_item) = data.index.get(&method_id) {
-                    process_item(output, method_item, data, level + 2);
+                    process_item(output, method_item, data, ctx, level + 2);
                 }
             }
         }
@@ -2114,14 +2300,15 @@ fn process_impl_details(
 
     // If this is a trait impl, list the provided trait methods that aren't overridden
     if impl_.trait_.is_some() && !impl_.provided_trait_methods.is_empty() {
-        output.push_str(&format!(
+        write!(
+            output,
             "{} Provided Trait Methods\n\n",
-            "#".repeat(heading_level)
-        ));
+            hashes(heading_level)
+        );
         output.push_str("The following methods are available through the trait but not explicitly implemented:\n\n");
 
         for provided_method in &impl_.provided_trait_methods {
-            output.push_str(&format!("- `{}`\n", provided_method));
+            writeln!(output, "- `{}`", provided_method);
         }
 
         output.push('\n');
@@ -2129,15 +2316,19 @@ fn process_impl_details(
 
     // If this is a blanket impl, mention it
     if let Some(blanket_type) = &impl_.blanket_impl {
-        output.push_str(&format!(
+        write!(
+            output,
             "This is a blanket implementation for all types that match: `{}`\n\n",
             format_type(blanket_type, data)
-        ));
+        );
     }
 }
 
 /// Process all the methods of an impl
 fn process_impl_methods(output: &mut String, impl_id: Id, data: &Crate, _level: usize) {
+    // Reused across methods to avoid a fresh allocation per signature
+    let mut method_signature = String::new();
+
     if let Some(impl_item) = data.index.get(&impl_id) {
         if let ItemEnum::Impl(impl_) = &impl_item.inner {
             for item_id in &impl_.items {
@@ -2145,7 +2336,7 @@ fn process_impl_methods(output: &mut String, impl_id: Id, data: &Crate, _level:
                     if let ItemEnum::Function(_) = &method_item.inner {
                         if let Some(_name) = &method_item.name {
                             // Format method signature
-                            let mut method_signature = String::new();
+                            method_signature.clear();
                             format_item_signature(&mut method_signature, method_item, data);
 
                             // Output with proper code block formatting
@@ -2157,7 +2348,7 @@ fn process_impl_methods(output: &mut String, impl_id: Id, data: &Crate, _level:
                             if let Some(docs) = &method_item.docs {
                                 if let Some(first_line) = docs.lines().next() {
                                     if !first_line.trim().is_empty() {
-                                        output.push_str(&format!("\n  {}", first_line));
+                                        write!(output, "\n  {}", first_line);
                                     }
                                 }
                             }
@@ -1,11 +1,19 @@
+pub mod attributes;
 pub mod clap;
 pub mod config;
+pub mod coverage;
 pub mod error;
 pub mod generator;
+pub mod import;
+pub mod links;
 pub mod markdown;
 pub mod multipage_markdown;
+pub mod output;
 pub mod package;
+pub mod reexports;
 pub mod rust2md;
+pub mod serve;
+pub mod templates;
 pub mod toolchain;
 pub mod utils;
 
@@ -17,6 +25,7 @@ pub use error::Error;
 pub use generator::DocGenerator;
 pub use package::PackageSpec;
 pub use rust2md::*;
+pub use templates::Templates;
 
 // Version of rustdoc-md for programmatic access
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
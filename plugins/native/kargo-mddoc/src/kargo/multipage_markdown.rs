@@ -1,6 +1,11 @@
 //! Multi-page markdown generator with proper interlinking and lint-valid output.
 
+use crate::attributes;
 use crate::error::Error;
+use crate::links::{ExternalLinks, LinkConfig};
+use crate::output::MarkdownWrite;
+use crate::reexports::{self, Link, Member, Reexports, Target};
+use crate::templates::{CrateContext, GeneratorContext, ItemContext, PageContext, Templates};
 use crate::utils;
 use log::{debug, info};
 use rustdoc_types::{Crate, Enum, Item, ItemEnum, Module, Struct, Trait};
@@ -18,6 +23,10 @@ pub struct MultipageConfig {
     pub generate_index: bool,
     /// Maximum items per page before splitting
     pub max_items_per_page: usize,
+    /// Layout templates for every page
+    pub templates: Templates,
+    /// Linking of items from other crates
+    pub links: LinkConfig,
 }
 
 impl Default for MultipageConfig {
@@ -27,6 +36,8 @@ impl Default for MultipageConfig {
             base_url: String::new(),
             generate_index: true,
             max_items_per_page: 50,
+            templates: Templates::builtin(),
+            links: LinkConfig::default(),
         }
     }
 }
@@ -83,25 +94,14 @@ impl MultipageGenerator {
 
         let mut content = String::new();
 
-        // Lint-valid markdown header
-        let crate_name = self
-            .crate_data
-            .index
-            .get(&self.crate_data.root)
-            .and_then(|item| item.name.as_ref())
-            .map(|s| s.as_str())
-            .unwrap_or("Crate");
-
-        content.push_str(&format!("# {} Documentation\n\n", crate_name));
-
         if let Some(version) = &self.crate_data.crate_version {
-            content.push_str(&format!("**Version:** {}\n\n", version));
+            write!(content, "**Version:** {}\n\n", version);
         }
 
         // Add crate-level documentation
         if let Some(root_item) = self.crate_data.index.get(&self.crate_data.root) {
             if let Some(docs) = &root_item.docs {
-                content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+                write!(content, "{}\n\n", self.clean_docs(docs));
             }
         }
 
@@ -125,48 +125,53 @@ impl MultipageGenerator {
 
         // Generate TOC with links to category pages
         if counts.get("Modules").unwrap_or(&0) > &0 {
-            content.push_str(&format!(
-                "- [Modules](modules.md) ({} items)\n",
+            writeln!(
+                content,
+                "- [Modules](modules.md) ({} items)",
                 counts.get("Modules").unwrap_or(&0)
-            ));
+            );
         }
         if counts.get("Structs").unwrap_or(&0) > &0 {
-            content.push_str(&format!(
-                "- [Structs](structs.md) ({} items)\n",
+            writeln!(
+                content,
+                "- [Structs](structs.md) ({} items)",
                 counts.get("Structs").unwrap_or(&0)
-            ));
+            );
         }
         if counts.get("Traits").unwrap_or(&0) > &0 {
-            content.push_str(&format!(
-                "- [Traits](traits.md) ({} items)\n",
+            writeln!(
+                content,
+                "- [Traits](traits.md) ({} items)",
                 counts.get("Traits").unwrap_or(&0)
-            ));
+            );
         }
         if counts.get("Enums").unwrap_or(&0) > &0 {
-            content.push_str(&format!(
-                "- [Enums](enums.md) ({} items)\n",
+            writeln!(
+                content,
+                "- [Enums](enums.md) ({} items)",
                 counts.get("Enums").unwrap_or(&0)
-            ));
+            );
         }
         if counts.get("Functions").unwrap_or(&0) > &0 {
-            content.push_str(&format!(
-                "- [Functions](functions.md) ({} items)\n",
+            writeln!(
+                content,
+                "- [Functions](functions.md) ({} items)",
                 counts.get("Functions").unwrap_or(&0)
-            ));
+            );
         }
 
         content.push('\n');
 
-        let index_path = self.config.output_dir.join("README.md");
-        utils::write_file(&index_path, &content)?;
-
-        Ok(index_path)
+        let title = format!("{} Documentation", self.crate_name());
+        let page = self.page("index", title, "README.md".to_string());
+        self.write_page(&page, &content)
     }
 
     /// Generate modules page
     fn generate_modules_page(&self) -> Result<Vec<PathBuf>, Error> {
+        let reexports = Reexports::new(&self.crate_data);
         let mut content = String::new();
-        content.push_str("# Modules\n\n");
+        let mut entries = Vec::new();
 
         let mut modules = Vec::new();
         for (id, item) in &self.crate_data.index {
@@ -180,36 +185,38 @@ impl MultipageGenerator {
         modules.sort_by(|a, b| a.2.cmp(b.2));
 
         for (_id, item, name) in modules {
-            content.push_str(&format!("## `{}`\n\n", name));
+            // Generate link to detailed page
+            let detailed_link = format!("module_{}.md", self.sanitize_filename(name));
+            let entry = self.item_context(item, name, "module", Some(detailed_link.clone()));
+            content.push_str(&self.config.templates.item_heading(&entry)?);
 
             if let Some(docs) = &item.docs {
                 let brief = self.extract_brief_docs(docs);
-                content.push_str(&format!("{}\n\n", brief));
+                write!(content, "{}\n\n", brief);
             }
 
-            // Generate link to detailed page
-            let detailed_link = format!("module_{}.md", self.sanitize_filename(name));
-            content.push_str(&format!(
+            write!(
+                content,
                 "[View detailed documentation]({})\n\n",
                 detailed_link
-            ));
+            );
 
             // Generate detailed page for this module
             if let ItemEnum::Module(module) = &item.inner {
-                self.generate_detailed_module_page(module, name)?;
+                self.generate_detailed_module_page(module, name, item, &reexports)?;
             }
+            entries.push(entry);
         }
 
-        let modules_path = self.config.output_dir.join("modules.md");
-        utils::write_file(&modules_path, &content)?;
-
-        Ok(vec![modules_path])
+        let mut page = self.page("category", "Modules".to_string(), "modules.md".to_string());
+        page.items = entries;
+        Ok(vec![self.write_page(&page, &content)?])
     }
 
     /// Generate structs page
     fn generate_structs_page(&self) -> Result<Vec<PathBuf>, Error> {
         let mut content = String::new();
-        content.push_str("# Structs\n\n");
+        let mut entries = Vec::new();
 
         let mut structs = Vec::new();
         for (id, item) in &self.crate_data.index {
@@ -223,36 +230,38 @@ impl MultipageGenerator {
         structs.sort_by(|a, b| a.2.cmp(b.2));
 
         for (_id, item, name) in structs {
-            content.push_str(&format!("## `{}`\n\n", name));
+            // Generate link to detailed page
+            let detailed_link = format!("struct_{}.md", self.sanitize_filename(name));
+            let entry = self.item_context(item, name, "struct", Some(detailed_link.clone()));
+            content.push_str(&self.config.templates.item_heading(&entry)?);
 
             if let Some(docs) = &item.docs {
                 let brief = self.extract_brief_docs(docs);
-                content.push_str(&format!("{}\n\n", brief));
+                write!(content, "{}\n\n", brief);
             }
 
-            // Generate link to detailed page
-            let detailed_link = format!("struct_{}.md", self.sanitize_filename(name));
-            content.push_str(&format!(
+            write!(
+                content,
                 "[View detailed documentation]({})\n\n",
                 detailed_link
-            ));
+            );
 
             // Generate detailed page for this struct
             if let ItemEnum::Struct(struct_item) = &item.inner {
                 self.generate_detailed_struct_page(struct_item, name, item)?;
             }
+            entries.push(entry);
         }
 
-        let structs_path = self.config.output_dir.join("structs.md");
-        utils::write_file(&structs_path, &content)?;
-
-        Ok(vec![structs_path])
+        let mut page = self.page("category", "Structs".to_string(), "structs.md".to_string());
+        page.items = entries;
+        Ok(vec![self.write_page(&page, &content)?])
     }
 
     /// Generate traits page
     fn generate_traits_page(&self) -> Result<Vec<PathBuf>, Error> {
         let mut content = String::new();
-        content.push_str("# Traits\n\n");
+        let mut entries = Vec::new();
 
         let mut traits = Vec::new();
         for (id, item) in &self.crate_data.index {
@@ -266,36 +275,38 @@ impl MultipageGenerator {
         traits.sort_by(|a, b| a.2.cmp(b.2));
 
         for (_id, item, name) in traits {
-            content.push_str(&format!("## `{}`\n\n", name));
+            // Generate link to detailed page
+            let detailed_link = format!("trait_{}.md", self.sanitize_filename(name));
+            let entry = self.item_context(item, name, "trait", Some(detailed_link.clone()));
+            content.push_str(&self.config.templates.item_heading(&entry)?);
 
             if let Some(docs) = &item.docs {
                 let brief = self.extract_brief_docs(docs);
-                content.push_str(&format!("{}\n\n", brief));
+                write!(content, "{}\n\n", brief);
             }
 
-            // Generate link to detailed page
-            let detailed_link = format!("trait_{}.md", self.sanitize_filename(name));
-            content.push_str(&format!(
+            write!(
+                content,
                 "[View detailed documentation]({})\n\n",
                 detailed_link
-            ));
+            );
 
             // Generate detailed page for this trait
             if let ItemEnum::Trait(trait_item) = &item.inner {
                 self.generate_detailed_trait_page(trait_item, name, item)?;
             }
+            entries.push(entry);
         }
 
-        let traits_path = self.config.output_dir.join("traits.md");
-        utils::write_file(&traits_path, &content)?;
-
-        Ok(vec![traits_path])
+        let mut page = self.page("category", "Traits".to_string(), "traits.md".to_string());
+        page.items = entries;
+        Ok(vec![self.write_page(&page, &content)?])
     }
 
     /// Generate enums page
     fn generate_enums_page(&self) -> Result<Vec<PathBuf>, Error> {
         let mut content = String::new();
-        content.push_str("# Enums\n\n");
+        let mut entries = Vec::new();
 
         let mut enums = Vec::new();
         for (id, item) in &self.crate_data.index {
@@ -309,36 +320,38 @@ impl MultipageGenerator {
         enums.sort_by(|a, b| a.2.cmp(b.2));
 
         for (_id, item, name) in enums {
-            content.push_str(&format!("## `{}`\n\n", name));
+            // Generate link to detailed page
+            let detailed_link = format!("enum_{}.md", self.sanitize_filename(name));
+            let entry = self.item_context(item, name, "enum", Some(detailed_link.clone()));
+            content.push_str(&self.config.templates.item_heading(&entry)?);
 
             if let Some(docs) = &item.docs {
                 let brief = self.extract_brief_docs(docs);
-                content.push_str(&format!("{}\n\n", brief));
+                write!(content, "{}\n\n", brief);
             }
 
-            // Generate link to detailed page
-            let detailed_link = format!("enum_{}.md", self.sanitize_filename(name));
-            content.push_str(&format!(
+            write!(
+                content,
                 "[View detailed documentation]({})\n\n",
                 detailed_link
-            ));
+            );
 
             // Generate detailed page for this enum
             if let ItemEnum::Enum(enum_item) = &item.inner {
                 self.generate_detailed_enum_page(enum_item, name, item)?;
             }
+            entries.push(entry);
         }
 
-        let enums_path = self.config.output_dir.join("enums.md");
-        utils::write_file(&enums_path, &content)?;
-
-        Ok(vec![enums_path])
+        let mut page = self.page("category", "Enums".to_string(), "enums.md".to_string());
+        page.items = entries;
+        Ok(vec![self.write_page(&page, &content)?])
     }
 
     /// Generate functions page
     fn generate_functions_page(&self) -> Result<Vec<PathBuf>, Error> {
         let mut content = String::new();
-        content.push_str("# Functions\n\n");
+        let mut entries = Vec::new();
 
         let mut functions = Vec::new();
         for (id, item) in &self.crate_data.index {
@@ -352,44 +365,59 @@ impl MultipageGenerator {
         functions.sort_by(|a, b| a.2.cmp(b.2));
 
         for (_id, item, name) in functions {
-            content.push_str(&format!("## `{}`\n\n", name));
+            let entry = self.item_context(item, name, "function", None);
+            content.push_str(&self.config.templates.item_heading(&entry)?);
 
             if let Some(docs) = &item.docs {
                 let brief = self.extract_brief_docs(docs);
-                content.push_str(&format!("{}\n\n", brief));
+                write!(content, "{}\n\n", brief);
             }
+            entries.push(entry);
         }
 
-        let functions_path = self.config.output_dir.join("functions.md");
-        utils::write_file(&functions_path, &content)?;
-
-        Ok(vec![functions_path])
+        let mut page = self.page(
+            "category",
+            "Functions".to_string(),
+            "functions.md".to_string(),
+        );
+        page.items = entries;
+        Ok(vec![self.write_page(&page, &content)?])
     }
 
     /// Generate detailed module page
-    fn generate_detailed_module_page(&self, module: &Module, name: &str) -> Result<(), Error> {
+    fn generate_detailed_module_page(
+        &self,
+        module: &Module,
+        name: &str,
+        module_item: &Item,
+        reexports: &Reexports,
+    ) -> Result<(), Error> {
         let mut content = String::new();
-        content.push_str(&format!("# Module `{}`\n\n", name));
+        let mut contents = Vec::new();
+        content.push_str(&attributes::notes(module_item));
 
         // Find the module item for documentation
         for item in self.crate_data.index.values() {
             if let Some(item_name) = &item.name {
                 if item_name == name {
                     if let Some(docs) = &item.docs {
-                        content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+                        write!(content, "{}\n\n", self.clean_docs(docs));
                     }
                     break;
                 }
             }
         }
 
-        // List module contents
+        // List module contents, with re-exports resolved
         if !module.items.is_empty() {
             content.push_str("## Contents\n\n");
 
-            for item_id in &module.items {
-                if let Some(item) = self.crate_data.index.get(item_id) {
-                    if let Some(item_name) = &item.name {
+            for member in reexports.members(&module.items) {
+                match member {
+                    Member::Inline(item) => {
+                        let Some(item_name) = &item.name else {
+                            continue;
+                        };
                         let item_type = match &item.inner {
                             ItemEnum::Module(_) => "Module",
                             ItemEnum::Struct(_) => "Struct",
@@ -400,26 +428,36 @@ impl MultipageGenerator {
                             _ => "Item",
                         };
 
-                        content.push_str(&format!("* **{}** `{}`", item_type, item_name));
+                        write!(content, "* **{}** `{}`", item_type, item_name);
+                        contents.push(self.item_context(
+                            &item,
+                            item_name,
+                            &item_type.to_lowercase(),
+                            None,
+                        ));
 
                         if let Some(docs) = &item.docs {
                             let brief = self.extract_brief_docs(docs);
                             if !brief.is_empty() {
-                                content.push_str(&format!(" - {}", brief));
+                                write!(content, " - {}", brief);
                             }
                         }
                         content.push('\n');
                     }
+                    Member::Link(link) => self.write_reexport(&mut content, &link),
                 }
             }
             content.push('\n');
         }
 
-        let file_path = self
-            .config
-            .output_dir
-            .join(format!("module_{}.md", self.sanitize_filename(name)));
-        utils::write_file(&file_path, &content)?;
+        let mut page = self.page(
+            "module",
+            format!("Module `{}`", name),
+            format!("module_{}.md", self.sanitize_filename(name)),
+        );
+        page.item = Some(self.item_context(module_item, name, "module", None));
+        page.items = contents;
+        self.write_page(&page, &content)?;
 
         Ok(())
     }
@@ -432,21 +470,23 @@ impl MultipageGenerator {
         item: &Item,
     ) -> Result<(), Error> {
         let mut content = String::new();
-        content.push_str(&format!("# Struct `{}`\n\n", name));
 
+        content.push_str(&attributes::notes(item));
         if let Some(docs) = &item.docs {
-            content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+            write!(content, "{}\n\n", self.clean_docs(docs));
         }
 
         // TODO: Add fields documentation when we have better type handling
         content.push_str("## Fields\n\n");
         content.push_str("Field information will be available in a future version.\n\n");
 
-        let file_path = self
-            .config
-            .output_dir
-            .join(format!("struct_{}.md", self.sanitize_filename(name)));
-        utils::write_file(&file_path, &content)?;
+        let mut page = self.page(
+            "struct",
+            format!("Struct `{}`", name),
+            format!("struct_{}.md", self.sanitize_filename(name)),
+        );
+        page.item = Some(self.item_context(item, name, "struct", None));
+        self.write_page(&page, &content)?;
 
         Ok(())
     }
@@ -459,10 +499,11 @@ impl MultipageGenerator {
         item: &Item,
     ) -> Result<(), Error> {
         let mut content = String::new();
-        content.push_str(&format!("# Trait `{}`\n\n", name));
+        let mut associated = Vec::new();
 
+        content.push_str(&attributes::notes(item));
         if let Some(docs) = &item.docs {
-            content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+            write!(content, "{}\n\n", self.clean_docs(docs));
         }
 
         if !trait_item.items.is_empty() {
@@ -478,21 +519,43 @@ impl MultipageGenerator {
                             _ => "Item",
                         };
 
-                        content.push_str(&format!("### {} `{}`\n\n", item_type, assoc_name));
+                        write!(content, "### {} `{}`\n\n", item_type, assoc_name);
+                        associated.push(self.item_context(
+                            assoc_item,
+                            assoc_name,
+                            &item_type.to_lowercase().replace(' ', "_"),
+                            None,
+                        ));
 
+                        content.push_str(&attributes::notes(assoc_item));
                         if let Some(docs) = &assoc_item.docs {
-                            content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+                            write!(content, "{}\n\n", self.clean_docs(docs));
+                        }
+
+                        if let ItemEnum::AssocType { .. } | ItemEnum::AssocConst { .. } =
+                            &assoc_item.inner
+                        {
+                            content.push_str("```rust\n");
+                            crate::markdown::format_item_signature(
+                                &mut content,
+                                assoc_item,
+                                &self.crate_data,
+                            );
+                            content.push_str("\n```\n\n");
                         }
                     }
                 }
             }
         }
 
-        let file_path = self
-            .config
-            .output_dir
-            .join(format!("trait_{}.md", self.sanitize_filename(name)));
-        utils::write_file(&file_path, &content)?;
+        let mut page = self.page(
+            "trait",
+            format!("Trait `{}`", name),
+            format!("trait_{}.md", self.sanitize_filename(name)),
+        );
+        page.item = Some(self.item_context(item, name, "trait", None));
+        page.items = associated;
+        self.write_page(&page, &content)?;
 
         Ok(())
     }
@@ -505,10 +568,11 @@ impl MultipageGenerator {
         item: &Item,
     ) -> Result<(), Error> {
         let mut content = String::new();
-        content.push_str(&format!("# Enum `{}`\n\n", name));
+        let mut variants = Vec::new();
 
+        content.push_str(&attributes::notes(item));
         if let Some(docs) = &item.docs {
-            content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+            write!(content, "{}\n\n", self.clean_docs(docs));
         }
 
         content.push_str("## Variants\n\n");
@@ -516,24 +580,126 @@ impl MultipageGenerator {
         for variant_id in &enum_item.variants {
             if let Some(variant) = self.crate_data.index.get(variant_id) {
                 if let Some(variant_name) = &variant.name {
-                    content.push_str(&format!("### `{}`\n\n", variant_name));
+                    write!(content, "### `{}`\n\n", variant_name);
+                    variants.push(self.item_context(variant, variant_name, "variant", None));
 
+                    content.push_str(&attributes::notes(variant));
                     if let Some(docs) = &variant.docs {
-                        content.push_str(&format!("{}\n\n", self.clean_docs(docs)));
+                        write!(content, "{}\n\n", self.clean_docs(docs));
                     }
                 }
             }
         }
 
-        let file_path = self
-            .config
-            .output_dir
-            .join(format!("enum_{}.md", self.sanitize_filename(name)));
-        utils::write_file(&file_path, &content)?;
+        let mut page = self.page(
+            "enum",
+            format!("Enum `{}`", name),
+            format!("enum_{}.md", self.sanitize_filename(name)),
+        );
+        page.item = Some(self.item_context(item, name, "enum", None));
+        page.items = variants;
+        self.write_page(&page, &content)?;
 
         Ok(())
     }
 
+    /// List a re-export of an item documented elsewhere, linking to its page
+    fn write_reexport(&self, content: &mut String, link: &Link) {
+        let use_ = link.use_;
+        if use_.is_glob {
+            write!(content, "* **Re-export** `{}::*`", use_.source);
+        } else {
+            write!(content, "* **Re-export** `{}`", use_.name);
+        }
+
+        match (&link.target, &link.location) {
+            (Target::Local(target), Some(path)) => match self.detail_page(target) {
+                Some(page) => write!(content, " - see [`{}`]({})", path, page),
+                None => write!(content, " - documented as `{}`", path),
+            },
+            (Target::External(summary), _) => {
+                let path = summary.path.join("::");
+                match ExternalLinks::new(&self.crate_data, &self.config.links).url(summary) {
+                    Some(url) => write!(content, " - see [`{}`]({})", path, url),
+                    None => write!(content, " - defined in `{}`", path),
+                }
+            }
+            _ => write!(content, " - `pub {}`", reexports::use_declaration(use_)),
+        }
+        content.push('\n');
+    }
+
+    /// Page documenting `item` in detail, for the kinds that have one
+    fn detail_page(&self, item: &Item) -> Option<String> {
+        let prefix = match &item.inner {
+            ItemEnum::Module(_) => "module",
+            ItemEnum::Struct(_) => "struct",
+            ItemEnum::Trait(_) => "trait",
+            ItemEnum::Enum(_) => "enum",
+            _ => return None,
+        };
+        let name = item.name.as_deref()?;
+        Some(format!("{}_{}.md", prefix, self.sanitize_filename(name)))
+    }
+
+    /// Name of the crate being documented
+    fn crate_name(&self) -> &str {
+        self.crate_data
+            .index
+            .get(&self.crate_data.root)
+            .and_then(|item| item.name.as_ref())
+            .map(|s| s.as_str())
+            .unwrap_or("Crate")
+    }
+
+    /// Template context for a page with no item or item list yet
+    fn page(&self, kind: &str, title: String, file: String) -> PageContext {
+        PageContext {
+            kind: kind.to_string(),
+            title,
+            file,
+            krate: CrateContext {
+                name: self.crate_name().to_string(),
+                version: self.crate_data.crate_version.clone(),
+            },
+            item: None,
+            items: Vec::new(),
+            generator: GeneratorContext::default(),
+        }
+    }
+
+    /// Template context for one item
+    fn item_context(
+        &self,
+        item: &Item,
+        name: &str,
+        kind: &str,
+        link: Option<String>,
+    ) -> ItemContext {
+        ItemContext {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            docs: item.docs.clone(),
+            summary: item
+                .docs
+                .as_deref()
+                .map(|docs| self.extract_brief_docs(docs)),
+            link,
+        }
+    }
+
+    /// Wrap `body` in the page layout and write it to the page's file
+    fn write_page(&self, page: &PageContext, body: &str) -> Result<PathBuf, Error> {
+        let templates = &self.config.templates;
+        let mut content = templates.page_start(page)?;
+        content.push_str(body);
+        content.push_str(&templates.page_end(page)?);
+
+        let path = self.config.output_dir.join(&page.file);
+        utils::write_file(&path, &content)?;
+        Ok(path)
+    }
+
     /// Sanitize filename for filesystem safety
     fn sanitize_filename(&self, name: &str) -> String {
         name.chars()
@@ -584,8 +750,7 @@ pub fn convert_to_multipage_markdown(
     );
 
     // Load the JSON data
-    let json_content = utils::read_file(json_path)?;
-    let data: Crate = serde_json::from_str(&json_content).map_err(|e| Error::JsonParse(e))?;
+    let data = crate::markdown::load_crate(json_path)?;
 
     // Generate multi-page markdown
     let mut generator = MultipageGenerator::new(data, config);
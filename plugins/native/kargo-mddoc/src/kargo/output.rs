@@ -0,0 +1,138 @@
+//! Output writers for markdown rendering.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+
+/// Size at which buffered markdown is drained into the sink
+const FLUSH_THRESHOLD: usize = 64 * 1024;
+
+/// Deep enough for any heading the renderers emit; deeper levels are clamped
+const HASHES: &str = "################################";
+
+/// Heading prefix for `level` without allocating a new `"#".repeat(level)`
+pub fn hashes(level: usize) -> &'static str {
+    &HASHES[..level.min(HASHES.len())]
+}
+
+/// Infallible text sink shared by the markdown renderers.
+///
+/// Writing to memory cannot fail, so `write!`/`writeln!` through this trait
+/// return `()` and format directly into the buffer instead of going through
+/// `push_str(&format!(..))` and a temporary String.
+pub trait MarkdownWrite {
+    fn push_str(&mut self, s: &str);
+
+    fn push(&mut self, c: char);
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>);
+}
+
+impl MarkdownWrite for String {
+    fn push_str(&mut self, s: &str) {
+        String::push_str(self, s);
+    }
+
+    fn push(&mut self, c: char) {
+        String::push(self, c);
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) {
+        // Formatting into a String only fails if a Display impl does
+        let _ = fmt::Write::write_fmt(self, args);
+    }
+}
+
+impl MarkdownWrite for MarkdownOutput<'_> {
+    fn push_str(&mut self, s: &str) {
+        self.buf.push_str(s);
+    }
+
+    fn push(&mut self, c: char) {
+        self.buf.push(c);
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) {
+        MarkdownWrite::write_fmt(&mut self.buf, args);
+    }
+}
+
+/// Markdown buffer that is periodically drained into a writer.
+///
+/// Renderers push into it like a `String` (it derefs to one), and call
+/// [`MarkdownOutput::flush_if_full`] between items so peak memory is bounded by
+/// the largest single item rather than the whole crate. Without a sink it
+/// simply accumulates, which keeps the in-memory API working.
+pub struct MarkdownOutput<'w> {
+    buf: String,
+    sink: Option<&'w mut dyn Write>,
+    error: Option<io::Error>,
+}
+
+impl MarkdownOutput<'static> {
+    /// Accumulate everything in memory
+    pub fn buffered() -> Self {
+        Self {
+            buf: String::new(),
+            sink: None,
+            error: None,
+        }
+    }
+}
+
+impl<'w> MarkdownOutput<'w> {
+    /// Stream output into `sink` as it is rendered
+    pub fn streaming(sink: &'w mut dyn Write) -> Self {
+        Self {
+            buf: String::with_capacity(FLUSH_THRESHOLD * 2),
+            sink: Some(sink),
+            error: None,
+        }
+    }
+
+    /// Drain the buffer into the sink once it has grown past the threshold
+    pub fn flush_if_full(&mut self) {
+        if self.buf.len() >= FLUSH_THRESHOLD {
+            self.drain();
+        }
+    }
+
+    fn drain(&mut self) {
+        if let Some(sink) = self.sink.as_mut() {
+            if let Err(e) = sink.write_all(self.buf.as_bytes()) {
+                // Keep the first error and stop writing; rendering carries on
+                // so callers don't need to thread io::Result through every
+                // formatter.
+                self.error = Some(e);
+                self.sink = None;
+            }
+            self.buf.clear();
+        }
+    }
+
+    /// Flush whatever is left, surfacing the first write error
+    pub fn finish(mut self) -> io::Result<String> {
+        self.drain();
+        if let Some(sink) = self.sink.as_mut() {
+            sink.flush()?;
+        }
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(std::mem::take(&mut self.buf)),
+        }
+    }
+}
+
+impl Deref for MarkdownOutput<'_> {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.buf
+    }
+}
+
+impl DerefMut for MarkdownOutput<'_> {
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.buf
+    }
+}
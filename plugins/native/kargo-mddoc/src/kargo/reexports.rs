@@ -0,0 +1,202 @@
+//! Resolution of `pub use` re-exports.
+//!
+//! rustdoc JSON records a re-export as a `Use` item holding the source path and
+//! the ID of the item it names. Facade crates define most of their API in
+//! private modules and re-export it, so listing the `Use` items alone leaves
+//! the documentation nearly empty. [`Reexports`] follows each one to its
+//! target and decides how it should be rendered:
+//!
+//! - items that are not documented anywhere else (they live in a private
+//!   module) are rendered inline, under the re-exported name;
+//! - items that already have a home in the public module tree, and items from
+//!   other crates, are rendered as a link to that home (see [`crate::links`]
+//!   for the latter).
+//!
+//! Glob re-exports of a module are expanded to the module's members.
+
+use rustdoc_types::{Crate, Id, Item, ItemEnum, ItemSummary, Use};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Re-exports of re-exports are followed at most this deep
+const MAX_CHAIN: usize = 16;
+
+/// What a `Use` item refers to
+#[derive(Debug, Clone, Copy)]
+pub enum Target<'a> {
+    /// An item in this crate's index
+    Local(&'a Item),
+    /// An item from another crate
+    External(&'a ItemSummary),
+    /// The target is not in the JSON (a primitive, or a hidden item)
+    Unresolved,
+}
+
+/// A module member after re-exports have been resolved
+#[derive(Debug, Clone)]
+pub enum Member<'a> {
+    /// Render the item in place. Inlined re-exports carry the re-exported
+    /// name.
+    Inline(Box<Cow<'a, Item>>),
+    /// A re-export of an item documented elsewhere
+    Link(Link<'a>),
+}
+
+/// A re-export rendered as a reference to its target
+#[derive(Debug, Clone)]
+pub struct Link<'a> {
+    /// The `Use` item as written
+    pub use_: &'a Use,
+    pub target: Target<'a>,
+    /// Public path of a target in this crate
+    pub location: Option<String>,
+}
+
+/// Re-export resolution for one crate
+pub struct Reexports<'a> {
+    data: &'a Crate,
+    /// Path of every item documented where it is defined, keyed by ID
+    paths: HashMap<&'a Id, String>,
+}
+
+impl<'a> Reexports<'a> {
+    pub fn new(data: &'a Crate) -> Self {
+        let mut reexports = Self {
+            data,
+            paths: HashMap::new(),
+        };
+        if let Some(root) = data.index.get(&data.root) {
+            let name = root.name.clone().unwrap_or_else(|| "crate".to_string());
+            reexports.walk(root, &name);
+        }
+        reexports
+    }
+
+    /// Record the paths of the public module tree below `module`
+    fn walk(&mut self, module: &'a Item, path: &str) {
+        let ItemEnum::Module(inner) = &module.inner else {
+            return;
+        };
+        for id in &inner.items {
+            let Some(item) = self.data.index.get(id) else {
+                continue;
+            };
+            let Some(name) = &item.name else {
+                continue;
+            };
+            if matches!(&item.inner, ItemEnum::Use(_) | ItemEnum::ExternCrate { .. }) {
+                continue;
+            }
+            let item_path = format!("{}::{}", path, name);
+            if let ItemEnum::Module(child) = &item.inner {
+                if child.is_stripped {
+                    continue;
+                }
+                self.paths.insert(id, item_path.clone());
+                self.walk(item, &item_path);
+            } else {
+                self.paths.insert(id, item_path);
+            }
+        }
+    }
+
+    /// Whether `id` is documented where it is defined
+    pub fn is_documented(&self, id: &Id) -> bool {
+        self.paths.contains_key(id)
+    }
+
+    /// Follow `use_` to the item it names
+    pub fn resolve(&self, use_: &'a Use) -> Target<'a> {
+        let Some(id) = self.target_id(use_) else {
+            return Target::Unresolved;
+        };
+        if let Some(item) = self.data.index.get(id) {
+            return Target::Local(item);
+        }
+        match self.data.paths.get(id) {
+            Some(summary) if summary.crate_id != 0 => Target::External(summary),
+            _ => Target::Unresolved,
+        }
+    }
+
+    /// The members of a module with every re-export resolved
+    pub fn members(&self, ids: &'a [Id]) -> Vec<Member<'a>> {
+        let mut members = Vec::new();
+        self.collect(ids, &mut members, &mut HashSet::new());
+        members
+    }
+
+    fn collect(&self, ids: &'a [Id], members: &mut Vec<Member<'a>>, globbed: &mut HashSet<Id>) {
+        for id in ids {
+            let Some(item) = self.data.index.get(id) else {
+                continue;
+            };
+            let ItemEnum::Use(use_) = &item.inner else {
+                members.push(Member::Inline(Box::new(Cow::Borrowed(item))));
+                continue;
+            };
+
+            let target = self.resolve(use_);
+            let target_id = self.target_id(use_);
+            let documented = target_id.is_some_and(|id| self.is_documented(id));
+            match target {
+                Target::Local(target_item) if use_.is_glob && !documented => {
+                    match &target_item.inner {
+                        ItemEnum::Module(module) => {
+                            if target_id.is_some_and(|id| globbed.insert(*id)) {
+                                self.collect(&module.items, members, globbed);
+                            }
+                        }
+                        _ => members.push(self.link(use_, target, target_id)),
+                    }
+                }
+                Target::Local(target_item) if !use_.is_glob && !documented => {
+                    let mut inlined = Cow::Borrowed(target_item);
+                    if target_item.name.as_deref() != Some(use_.name.as_str()) {
+                        inlined.to_mut().name = Some(use_.name.clone());
+                    }
+                    members.push(Member::Inline(Box::new(inlined)));
+                }
+                _ => members.push(self.link(use_, target, target_id)),
+            }
+        }
+    }
+
+    /// ID of the item at the end of a chain of re-exports
+    fn target_id(&self, use_: &'a Use) -> Option<&'a Id> {
+        let mut id = use_.id.as_ref()?;
+        for _ in 0..MAX_CHAIN {
+            match self.data.index.get(id) {
+                Some(Item {
+                    inner: ItemEnum::Use(next),
+                    ..
+                }) => id = next.id.as_ref()?,
+                _ => return Some(id),
+            }
+        }
+        None
+    }
+
+    fn link(&self, use_: &'a Use, target: Target<'a>, target_id: Option<&'a Id>) -> Member<'a> {
+        let location = match target {
+            Target::Local(_) => target_id.and_then(|id| self.paths.get(id)).cloned(),
+            Target::External(_) | Target::Unresolved => None,
+        };
+        Member::Link(Link {
+            use_,
+            target,
+            location,
+        })
+    }
+}
+
+/// The `use` declaration a re-export was written as
+pub fn use_declaration(use_: &Use) -> String {
+    if use_.is_glob {
+        format!("use {}::*;", use_.source)
+    } else if use_.source.rsplit("::").next() == Some(use_.name.as_str()) {
+        format!("use {};", use_.source)
+    } else {
+        format!("use {} as {};", use_.source, use_.name)
+    }
+}
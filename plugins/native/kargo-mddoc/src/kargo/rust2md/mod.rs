@@ -3,11 +3,11 @@ mod types;
 
 use crate::config::Config;
 use anyhow::{Context, Result};
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
 use log::{info, warn};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use toml_edit;
 
 pub use markdown::rustdoc_json_to_markdown;
@@ -90,24 +90,21 @@ impl DocGenerator {
     /// Run cargo rustdoc with nightly to generate JSON documentation
     fn run_cargo_doc(&self, package_name: &str, crate_path: &Path) -> Result<PathBuf> {
         // Run the cargo +nightly command to generate JSON documentation
-        let output = Command::new("cargo")
-            .current_dir(crate_path)
-            .arg("+nightly")
-            .arg("-Zunstable-options")
-            .arg("rustdoc")
-            .arg("--output-format")
-            .arg("json")
-            .arg("--manifest-path")
-            .arg("./Cargo.toml")
-            .arg("--package")
-            .arg(package_name)
-            .output()
-            .context("Failed to execute cargo +nightly rustdoc command")?;
-
-        if !output.status.success() {
-            let error_message = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("cargo rustdoc failed: {}", error_message));
-        }
+        let command = CargoCommand::new("rustdoc")
+            .toolchain("nightly")
+            .args([
+                "-Zunstable-options",
+                "--output-format",
+                "json",
+                "--manifest-path",
+                "./Cargo.toml",
+                "--package",
+                package_name,
+            ])
+            .current_dir(crate_path);
+        cargo_runner::run(&command)
+            .context("Failed to execute cargo +nightly rustdoc command")?
+            .ensure_success(&command)?;
 
         // The JSON file is generated at target/doc/{package_name}.json
         let json_path = crate_path
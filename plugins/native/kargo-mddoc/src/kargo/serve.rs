@@ -0,0 +1,873 @@
+//! Local documentation browser for `kargo mddoc serve`.
+//!
+//! Serves a directory of generated Markdown with one subdirectory per crate
+//! (`docs/tokio`), or per crate and version (`docs/tokio/1.45.0`) to switch
+//! between releases. Pages are rendered to HTML when requested, so
+//! regenerated docs show up on reload; the full-text index over every page is
+//! built once at startup.
+//!
+//! | Path                        | Serves                                   |
+//! |-----------------------------|------------------------------------------|
+//! | `/`                         | The crates and versions in the directory |
+//! | `/search?q=..&crate=..`     | Search results, optionally for one crate |
+//! | `/search.json?q=..`         | The same results as JSON                 |
+//! | `/<crate>/[<version>/]...`  | Pages rendered to HTML, other files as-is |
+
+use crate::error::Error;
+use pulldown_cmark::{html, Options, Parser};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most results a search returns
+const MAX_HITS: usize = 50;
+/// Characters of context around the first match in a result
+const SNIPPET_LEN: usize = 160;
+/// Pages tried, in order, when a directory is requested
+const ENTRY_PAGES: [&str; 2] = ["README.md", "index.md"];
+
+lazy_static::lazy_static! {
+    static ref LINK: Regex = Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap();
+}
+
+/// Generated docs of one crate, or one version of a crate
+#[derive(Debug, Clone)]
+pub struct Book {
+    pub name: String,
+    pub version: Option<semver::Version>,
+    /// Relative to the served directory
+    pub dir: PathBuf,
+    /// Markdown pages, relative to `dir`
+    pub pages: Vec<PathBuf>,
+}
+
+impl Book {
+    fn url(&self) -> String {
+        format!("/{}/", url_path(&self.dir))
+    }
+
+    fn page_url(&self, page: &Path) -> String {
+        format!("{}{}", self.url(), url_path(page))
+    }
+
+    /// The page a directory of the book opens on
+    fn entry_page(&self, dir: &Path) -> Option<PathBuf> {
+        let in_dir = |name: &str| {
+            let page = dir.join(name);
+            self.pages.contains(&page).then_some(page)
+        };
+        ENTRY_PAGES
+            .iter()
+            .find_map(|name| in_dir(name))
+            // Single-page output is named after the crate
+            .or_else(|| in_dir(&format!("{}.md", self.name.replace('-', "_"))))
+            .or_else(|| {
+                self.pages
+                    .iter()
+                    .find(|page| page.parent() == Some(dir))
+                    .cloned()
+            })
+    }
+}
+
+/// A search result
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub version: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+#[derive(Debug)]
+struct IndexedPage {
+    book: usize,
+    page: PathBuf,
+    title: String,
+    /// Plain text of the page, for snippets
+    text: String,
+}
+
+/// Inverted index from lowercased words to the pages containing them
+#[derive(Debug, Default)]
+struct SearchIndex {
+    pages: Vec<IndexedPage>,
+    /// Word to `(page, occurrences)`
+    terms: HashMap<String, Vec<(usize, u32)>>,
+}
+
+impl SearchIndex {
+    fn add(&mut self, book: usize, page: PathBuf, markdown: &str) {
+        let id = self.pages.len();
+        let title = markdown
+            .lines()
+            .find_map(|line| line.strip_prefix("# "))
+            .map(|title| title.trim().trim_matches('`').to_string())
+            .unwrap_or_else(|| {
+                page.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+        let text = plain_text(markdown);
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for word in words(&text) {
+            *counts.entry(word).or_default() += 1;
+        }
+        for (word, count) in counts {
+            self.terms.entry(word).or_default().push((id, count));
+        }
+        self.pages.push(IndexedPage {
+            book,
+            page,
+            title,
+            text,
+        });
+    }
+
+    /// Pages containing every word of `query`, best first. Title matches
+    /// outrank any number of mentions in the text.
+    fn search(&self, query: &str, books: impl Fn(usize) -> bool) -> Vec<(usize, u32)> {
+        let query: Vec<String> = words(query).collect();
+        let Some((first, rest)) = query.split_first() else {
+            return Vec::new();
+        };
+        let Some(postings) = self.terms.get(first) else {
+            return Vec::new();
+        };
+        let mut scores: HashMap<usize, u32> = postings
+            .iter()
+            .filter(|(page, _)| books(self.pages[*page].book))
+            .copied()
+            .collect();
+        for word in rest {
+            let postings: HashMap<usize, u32> = self
+                .terms
+                .get(word)
+                .map(|postings| postings.iter().copied().collect())
+                .unwrap_or_default();
+            scores.retain(|page, score| match postings.get(page) {
+                Some(count) => {
+                    *score += count;
+                    true
+                }
+                None => false,
+            });
+        }
+
+        let mut ranked: Vec<(usize, u32)> = scores
+            .into_iter()
+            .map(|(page, score)| {
+                let title = self.pages[page].title.to_ascii_lowercase();
+                let in_title = query.iter().filter(|w| title.contains(w.as_str())).count();
+                (page, score + 1000 * in_title as u32)
+            })
+            .collect();
+        ranked.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| self.pages[*a].title.cmp(&self.pages[*b].title))
+        });
+        ranked.truncate(MAX_HITS);
+        ranked
+    }
+}
+
+/// The crates in a directory of generated docs, and the index over them
+#[derive(Debug)]
+pub struct Library {
+    root: PathBuf,
+    books: Vec<Book>,
+    index: SearchIndex,
+}
+
+impl Library {
+    /// Find the crates in `root` and index their pages.
+    ///
+    /// A subdirectory whose own subdirectories are named by versions holds
+    /// one book per version; any other subdirectory with Markdown in it is
+    /// the docs of a single version.
+    pub fn scan(root: &Path) -> Result<Self, Error> {
+        let mut books = Vec::new();
+        for dir in subdirs(root)? {
+            let name = file_name(&dir);
+            if name.starts_with('.') {
+                continue;
+            }
+            let mut versions: Vec<(semver::Version, PathBuf)> = subdirs(&dir)?
+                .into_iter()
+                .filter_map(|sub| Some((semver::Version::parse(&file_name(&sub)).ok()?, sub)))
+                .collect();
+            versions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let candidates = if versions.is_empty() {
+                vec![(None, dir)]
+            } else {
+                versions
+                    .into_iter()
+                    .map(|(version, sub)| (Some(version), sub))
+                    .collect()
+            };
+            for (version, book_dir) in candidates {
+                let mut pages = Vec::new();
+                markdown_pages(&book_dir, Path::new(""), &mut pages)?;
+                if pages.is_empty() {
+                    continue;
+                }
+                pages.sort();
+                books.push(Book {
+                    name: name.clone(),
+                    version,
+                    dir: book_dir
+                        .strip_prefix(root)
+                        .unwrap_or(&book_dir)
+                        .to_path_buf(),
+                    pages,
+                });
+            }
+        }
+
+        let mut index = SearchIndex::default();
+        for (id, book) in books.iter().enumerate() {
+            for page in &book.pages {
+                let markdown = std::fs::read_to_string(root.join(&book.dir).join(page))?;
+                index.add(id, page.clone(), &markdown);
+            }
+        }
+        log::debug!(
+            "Indexed {} pages of {} crates",
+            index.pages.len(),
+            books.len()
+        );
+        Ok(Self {
+            root: root.to_path_buf(),
+            books,
+            index,
+        })
+    }
+
+    pub fn books(&self) -> &[Book] {
+        &self.books
+    }
+
+    /// Pages matching `query`, optionally only those of crate `crate_name`
+    pub fn search(&self, query: &str, crate_name: Option<&str>) -> Vec<SearchHit> {
+        let in_scope = |book: usize| crate_name.is_none_or(|name| self.books[book].name == name);
+        self.index
+            .search(query, in_scope)
+            .into_iter()
+            .map(|(id, _)| {
+                let page = &self.index.pages[id];
+                let book = &self.books[page.book];
+                SearchHit {
+                    crate_name: book.name.clone(),
+                    version: book.version.as_ref().map(ToString::to_string),
+                    title: page.title.clone(),
+                    url: book.page_url(&page.page),
+                    snippet: snippet(&page.text, query),
+                }
+            })
+            .collect()
+    }
+
+    /// Answer a GET request for `target`, the path and query of the URL
+    pub fn respond(&self, target: &str) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params = parse_query(query);
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .filter(|v| !v.is_empty())
+        };
+
+        let path = percent_decode(path);
+        match path.as_str() {
+            "/" => Response::html(200, self.layout("Documentation", None, "", &self.home())),
+            "/search" => {
+                let query = param("q").unwrap_or_default();
+                let body = self.results(query, param("crate"));
+                let current = param("crate").and_then(|name| self.latest(name));
+                Response::html(200, self.layout("Search", current, query, &body))
+            }
+            "/search.json" => {
+                let hits = self.search(param("q").unwrap_or_default(), param("crate"));
+                Response {
+                    status: 200,
+                    content_type: "application/json",
+                    location: None,
+                    body: serde_json::to_vec(&hits).unwrap_or_default(),
+                }
+            }
+            _ => self.file(&path),
+        }
+    }
+
+    fn file(&self, url: &str) -> Response {
+        let mut relative = PathBuf::new();
+        for segment in url.split('/').filter(|s| !s.is_empty()) {
+            // Keep requests inside the served directory
+            if segment == ".." || segment.contains('\\') || segment.contains(':') {
+                return self.not_found();
+            }
+            relative.push(segment);
+        }
+        let path = self.root.join(&relative);
+
+        if path.is_dir() {
+            if !url.ends_with('/') {
+                return Response::redirect(format!("{}/", url));
+            }
+            // A versioned crate opens on its newest version
+            if let Some(book) = self.books.iter().rev().find(|book| {
+                book.version.is_some() && book.dir.parent() == Some(relative.as_path())
+            }) {
+                return Response::redirect(book.url());
+            }
+            return match self.owner(&relative) {
+                Some((id, dir)) => match self.books[id].entry_page(&dir) {
+                    Some(page) => Response::redirect(self.books[id].page_url(&page)),
+                    None => self.not_found(),
+                },
+                None => self.not_found(),
+            };
+        }
+
+        let Ok(content) = std::fs::read(&path) else {
+            return self.not_found();
+        };
+        if path.extension().is_some_and(|ext| ext == "md") {
+            if let Some((id, page)) = self.owner(&relative) {
+                let markdown = String::from_utf8_lossy(&content);
+                let title = self
+                    .index
+                    .pages
+                    .iter()
+                    .find(|p| p.book == id && p.page == page)
+                    .map(|p| p.title.clone())
+                    .unwrap_or_else(|| self.books[id].name.clone());
+                let body = render_markdown(&markdown);
+                return Response::html(200, self.layout(&title, Some((id, &page)), "", &body));
+            }
+        }
+        Response {
+            status: 200,
+            content_type: content_type(&path),
+            location: None,
+            body: content,
+        }
+    }
+
+    /// The book `relative` belongs to, and the path within it
+    fn owner(&self, relative: &Path) -> Option<(usize, PathBuf)> {
+        self.books
+            .iter()
+            .enumerate()
+            .filter_map(|(id, book)| Some((id, relative.strip_prefix(&book.dir).ok()?)))
+            .max_by_key(|(id, _)| self.books[*id].dir.components().count())
+            .map(|(id, rest)| (id, rest.to_path_buf()))
+    }
+
+    fn latest(&self, crate_name: &str) -> Option<(usize, &Path)> {
+        let id = self
+            .books
+            .iter()
+            .rposition(|book| book.name == crate_name)?;
+        Some((id, Path::new("")))
+    }
+
+    fn home(&self) -> String {
+        let mut body = String::from("<h1>Documentation</h1>\n");
+        if self.books.is_empty() {
+            let _ = write!(
+                body,
+                "<p>No generated docs in <code>{}</code>. Generate some with \
+                 <code>kargo mddoc &lt;crate&gt; -o {}/&lt;crate&gt;</code>.</p>",
+                escape_html(&self.root.display().to_string()),
+                escape_html(&self.root.display().to_string())
+            );
+            return body;
+        }
+        body.push_str("<ul class=\"crates\">\n");
+        let mut books = self.books.iter().peekable();
+        while let Some(book) = books.next() {
+            let mut versions = vec![book];
+            while let Some(next) = books.next_if(|next| next.name == book.name) {
+                versions.push(next);
+            }
+            let latest = versions[versions.len() - 1];
+            let _ = write!(
+                body,
+                "<li><a href=\"{}\">{}</a>",
+                escape_html(&latest.url()),
+                escape_html(&book.name)
+            );
+            for version in versions
+                .iter()
+                .rev()
+                .filter_map(|b| Some((b, b.version.as_ref()?)))
+            {
+                let _ = write!(
+                    body,
+                    " <a class=\"version\" href=\"{}\">{}</a>",
+                    escape_html(&version.0.url()),
+                    version.1
+                );
+            }
+            body.push_str("</li>\n");
+        }
+        body.push_str("</ul>\n");
+        body
+    }
+
+    fn results(&self, query: &str, crate_name: Option<&str>) -> String {
+        let mut body = String::new();
+        if query.is_empty() {
+            body.push_str("<h1>Search</h1>\n");
+            return body;
+        }
+        let hits = self.search(query, crate_name);
+        let _ = writeln!(
+            body,
+            "<h1>{} result{} for “{}”</h1>",
+            hits.len(),
+            if hits.len() == 1 { "" } else { "s" },
+            escape_html(query)
+        );
+        body.push_str("<ol class=\"results\">\n");
+        for hit in &hits {
+            let version = hit
+                .version
+                .as_deref()
+                .map(|v| format!(" {}", v))
+                .unwrap_or_default();
+            let _ = writeln!(
+                body,
+                "<li><a href=\"{}\">{}</a> <span class=\"crate\">{}{}</span><p>{}</p></li>",
+                escape_html(&hit.url),
+                escape_html(&hit.title),
+                escape_html(&hit.crate_name),
+                escape_html(&version),
+                escape_html(&hit.snippet)
+            );
+        }
+        body.push_str("</ol>\n");
+        body
+    }
+
+    /// Wrap `body` in the page chrome: crate and version switchers, and the
+    /// search box. `current` is the book being viewed and the page in it.
+    fn layout(
+        &self,
+        title: &str,
+        current: Option<(usize, &Path)>,
+        query: &str,
+        body: &str,
+    ) -> String {
+        let current_book = current.map(|(id, _)| &self.books[id]);
+        let mut nav = String::from("<a class=\"home\" href=\"/\">docs</a>\n");
+
+        nav.push_str("<select onchange=\"location = this.value\" aria-label=\"Crate\">\n");
+        if current_book.is_none() {
+            nav.push_str("<option selected disabled>crate</option>\n");
+        }
+        let mut names: Vec<&str> = self.books.iter().map(|b| b.name.as_str()).collect();
+        names.dedup();
+        for name in names {
+            let selected = current_book.is_some_and(|book| book.name == name);
+            let _ = writeln!(
+                nav,
+                "<option value=\"/{}/\"{}>{}</option>",
+                escape_html(name),
+                if selected { " selected" } else { "" },
+                escape_html(name)
+            );
+        }
+        nav.push_str("</select>\n");
+
+        if let Some((book, page)) = current.map(|(id, page)| (&self.books[id], page)) {
+            let versions: Vec<&Book> = self
+                .books
+                .iter()
+                .filter(|b| b.name == book.name && b.version.is_some())
+                .collect();
+            if versions.len() > 1 {
+                nav.push_str(
+                    "<select onchange=\"location = this.value\" aria-label=\"Version\">\n",
+                );
+                for other in versions.iter().rev() {
+                    // Stay on the same page when the other version has it
+                    let url = if other.pages.iter().any(|p| p == page) {
+                        other.page_url(page)
+                    } else {
+                        other.url()
+                    };
+                    let _ = writeln!(
+                        nav,
+                        "<option value=\"{}\"{}>{}</option>",
+                        escape_html(&url),
+                        if other.dir == book.dir {
+                            " selected"
+                        } else {
+                            ""
+                        },
+                        other
+                            .version
+                            .as_ref()
+                            .map(ToString::to_string)
+                            .unwrap_or_default()
+                    );
+                }
+                nav.push_str("</select>\n");
+            }
+        }
+
+        let scope = current_book
+            .map(|book| {
+                format!(
+                    "<input type=\"hidden\" name=\"crate\" value=\"{}\">",
+                    escape_html(&book.name)
+                )
+            })
+            .unwrap_or_default();
+        let placeholder = current_book
+            .map(|book| format!("Search {}", book.name))
+            .unwrap_or_else(|| "Search all crates".to_string());
+        let _ = write!(
+            nav,
+            "<form action=\"/search\"><input type=\"search\" name=\"q\" value=\"{}\" placeholder=\"{}\">{}</form>",
+            escape_html(query),
+            escape_html(&placeholder),
+            scope
+        );
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<nav>{}</nav>\n\
+             <main>\n{}</main>\n</body>\n</html>\n",
+            escape_html(title),
+            STYLE,
+            nav,
+            body
+        )
+    }
+
+    fn not_found(&self) -> Response {
+        let body = "<h1>Not found</h1>\n<p><a href=\"/\">Back to the crates</a></p>\n";
+        Response::html(404, self.layout("Not found", None, "", body))
+    }
+}
+
+/// An HTTP response
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    /// Target of a redirect
+    pub location: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn html(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "text/html; charset=utf-8",
+            location: None,
+            body: body.into_bytes(),
+        }
+    }
+
+    fn redirect(location: String) -> Self {
+        Self {
+            status: 302,
+            content_type: "text/plain; charset=utf-8",
+            location: Some(location),
+            body: Vec::new(),
+        }
+    }
+
+    fn write_to(&self, stream: &mut impl Write, head_only: bool) -> std::io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            302 => "Found",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "",
+        };
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        );
+        if let Some(location) = &self.location {
+            let _ = write!(head, "Location: {}\r\n", location);
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        if !head_only {
+            stream.write_all(&self.body)?;
+        }
+        stream.flush()
+    }
+}
+
+/// Serve `library` on `addr` until the process is stopped
+pub fn serve(library: Library, addr: impl ToSocketAddrs) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    log::info!(
+        "Serving docs of {} crate versions at http://{}/ (Ctrl-C to stop)",
+        library.books.len(),
+        local
+    );
+    let library = Arc::new(library);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::debug!("Connection failed: {}", e);
+                continue;
+            }
+        };
+        let library = Arc::clone(&library);
+        std::thread::spawn(move || {
+            if let Err(e) = handle(&library, stream) {
+                log::debug!("Request failed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(library: &Library, mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers aren't needed, but have to be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or("/");
+    let response = match method {
+        "GET" | "HEAD" => library.respond(target),
+        _ => Response {
+            status: 405,
+            content_type: "text/plain; charset=utf-8",
+            location: None,
+            body: b"Only GET is supported\n".to_vec(),
+        },
+    };
+    log::debug!("{} {} -> {}", method, target, response.status);
+    response.write_to(&mut stream, method == "HEAD")
+}
+
+/// Render a page to HTML; front matter from custom templates is left out
+pub fn render_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, Parser::new_ext(markdown, options));
+    out
+}
+
+fn subdirs(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn markdown_pages(dir: &Path, relative: &Path, pages: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = file_name(&path);
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            markdown_pages(&path, &relative.join(&name), pages)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            pages.push(relative.join(name));
+        }
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn url_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Markdown reduced to its words, for indexing and snippets
+fn plain_text(markdown: &str) -> String {
+    let text = LINK.replace_all(markdown, "$1");
+    let mut plain = String::with_capacity(text.len());
+    for line in text.lines() {
+        let line = line.trim_start_matches(['#', '>', ' ']).trim();
+        let table_rule = line.starts_with('|') && line.contains("---");
+        if line.is_empty() || line.starts_with("```") || table_rule {
+            continue;
+        }
+        for c in line.chars().filter(|c| !matches!(c, '`' | '*' | '|')) {
+            plain.push(c);
+        }
+        plain.push(' ');
+    }
+    plain
+}
+
+/// Lowercased words of `text`. `snake_case` words are indexed whole and by
+/// their parts, so both `spawn_blocking` and `blocking` find them.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .flat_map(|word| {
+            let parts = word
+                .contains('_')
+                .then(|| word.split('_'))
+                .into_iter()
+                .flatten();
+            std::iter::once(word).chain(parts)
+        })
+        .filter(|word| word.chars().count() > 1)
+        .map(|word| word.to_ascii_lowercase())
+}
+
+/// The text around the first word of `query` in `text`
+fn snippet(text: &str, query: &str) -> String {
+    let lowercase = text.to_ascii_lowercase();
+    let at = words(query)
+        .next()
+        .and_then(|word| lowercase.find(&word))
+        .unwrap_or(0);
+    let mut start = at.saturating_sub(SNIPPET_LEN / 3);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + SNIPPET_LEN).min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut snippet = text[start..end].trim().to_string();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("md" | "txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+const STYLE: &str = "\
+body { margin: 0; font: 16px/1.5 system-ui, sans-serif; color: #1f2328; }
+nav { position: sticky; top: 0; display: flex; gap: .75rem; align-items: center; padding: .5rem 1.5rem; background: #f6f8fa; border-bottom: 1px solid #d0d7de; }
+nav .home { font-weight: 600; text-decoration: none; color: inherit; }
+nav form { margin-left: auto; }
+nav input[type=search] { width: 18rem; padding: .25rem .5rem; }
+main { max-width: 60rem; margin: 0 auto; padding: 1rem 1.5rem 3rem; }
+code, pre { font-family: ui-monospace, monospace; font-size: 90%; }
+pre { background: #f6f8fa; padding: .75rem; overflow-x: auto; border-radius: 6px; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: .25rem .5rem; }
+a { color: #0969da; }
+.crates .version, .results .crate { color: #59636e; font-size: 85%; margin-left: .5rem; }
+.results p { margin: .25rem 0 1rem; color: #59636e; }
+";
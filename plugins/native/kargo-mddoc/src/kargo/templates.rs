@@ -0,0 +1,179 @@
+//! Handlebars templates for page layout.
+//!
+//! The renderers produce the body of each page; the parts around it come from
+//! four templates that users can replace with their own:
+//!
+//! | Template       | Renders                                     | Context         |
+//! |----------------|---------------------------------------------|-----------------|
+//! | `front_matter` | Start of every page (empty by default)      | [`PageContext`] |
+//! | `heading`      | Page title, `# {{title}}`                   | [`PageContext`] |
+//! | `item_heading` | Entry heading on category pages             | [`ItemContext`] |
+//! | `footer`       | End of every page (empty by default)        | [`PageContext`] |
+//!
+//! [`Templates::load`] reads `<name>.hbs` files from a directory; missing ones
+//! keep the built-in version, so the defaults reproduce the plain output.
+//! Every other `.hbs` file in the directory is registered as a partial. Output
+//! is Markdown, so nothing is HTML-escaped.
+//!
+//! A Hugo front matter, for example:
+//!
+//! ```text
+//! ---
+//! title: "{{title}}"
+//! weight: {{#if item}}20{{else}}10{{/if}}
+//! ---
+//!
+//! ```
+//!
+//! The context types are the stable interface for templates: fields are only
+//! ever added, never renamed or removed.
+
+use crate::error::Error;
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::path::Path;
+
+const FRONT_MATTER: &str = "front_matter";
+const HEADING: &str = "heading";
+const ITEM_HEADING: &str = "item_heading";
+const FOOTER: &str = "footer";
+
+const BUILTIN: [(&str, &str); 4] = [
+    (FRONT_MATTER, ""),
+    (HEADING, "# {{title}}\n\n"),
+    (ITEM_HEADING, "## `{{name}}`\n\n"),
+    (FOOTER, ""),
+];
+
+/// The crate being documented
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateContext {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+/// Tool that generated the page, for attribution footers
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratorContext {
+    pub name: &'static str,
+    pub version: &'static str,
+}
+
+impl Default for GeneratorContext {
+    fn default() -> Self {
+        Self {
+            name: "kargo-mddoc",
+            version: crate::VERSION,
+        }
+    }
+}
+
+/// An item shown on a page
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemContext {
+    pub name: String,
+    /// `module`, `struct`, `trait`, `enum`, `function`, `constant`, ...
+    pub kind: String,
+    /// Full documentation, as written
+    pub docs: Option<String>,
+    /// First paragraph of the documentation, shortened
+    pub summary: Option<String>,
+    /// Page documenting the item in detail, relative to the output directory
+    pub link: Option<String>,
+}
+
+/// One output page
+#[derive(Debug, Clone, Serialize)]
+pub struct PageContext {
+    /// `crate` (single-page output), `index`, `category`, or the item kind for
+    /// item pages
+    pub kind: String,
+    /// Page title without Markdown heading markers
+    pub title: String,
+    /// Output file, relative to the output directory
+    pub file: String,
+    #[serde(rename = "crate")]
+    pub krate: CrateContext,
+    /// The item the page documents, on item pages
+    pub item: Option<ItemContext>,
+    /// Items listed on the page: category entries or module contents
+    pub items: Vec<ItemContext>,
+    pub generator: GeneratorContext,
+}
+
+/// The layout templates used for rendering
+#[derive(Debug, Clone)]
+pub struct Templates {
+    registry: Handlebars<'static>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Templates {
+    /// Templates reproducing the plain output
+    pub fn builtin() -> Self {
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(handlebars::no_escape);
+        for (name, source) in BUILTIN {
+            registry
+                .register_template_string(name, source)
+                .expect("built-in templates are valid");
+        }
+        Self { registry }
+    }
+
+    /// Built-in templates overridden by the `.hbs` files in `dir`
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        let mut templates = Self::builtin();
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            Error::Template(format!(
+                "Cannot read template directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("hbs") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let source = std::fs::read_to_string(&path)?;
+            templates
+                .registry
+                .register_template_string(name, source)
+                .map_err(|e| Error::Template(format!("{}: {}", path.display(), e)))?;
+            log::debug!("Loaded template {} from {}", name, path.display());
+        }
+        Ok(templates)
+    }
+
+    /// Front matter and title heading of `page`
+    pub fn page_start(&self, page: &PageContext) -> Result<String, Error> {
+        let mut start = self.render(FRONT_MATTER, page)?;
+        start.push_str(&self.render(HEADING, page)?);
+        Ok(start)
+    }
+
+    /// Footer of `page`
+    pub fn page_end(&self, page: &PageContext) -> Result<String, Error> {
+        self.render(FOOTER, page)
+    }
+
+    /// Heading of an entry on a category page
+    pub fn item_heading(&self, item: &ItemContext) -> Result<String, Error> {
+        self.render(ITEM_HEADING, item)
+    }
+
+    fn render<T: Serialize>(&self, name: &str, context: &T) -> Result<String, Error> {
+        self.registry
+            .render(name, context)
+            .map_err(|e| Error::Template(format!("{}: {}", name, e)))
+    }
+}
@@ -1,8 +1,9 @@
 use crate::error::Error;
+use kargo_plugin_api::cargo_runner::{self, CargoCommand, CargoOutput};
 use log::{debug, info, warn};
 use std::fs;
 use std::path::PathBuf;
-use std::process::{Command, Output};
+use std::process::Command;
 use std::time::{Duration, SystemTime};
 
 pub struct Toolchain;
@@ -193,41 +194,20 @@ impl Toolchain {
         Ok(())
     }
 
-    /// Run a command and return its output, with error handling
-    pub fn run_command(
-        command: &str,
-        args: &[&str],
-        current_dir: Option<&std::path::Path>,
-        verbose: bool,
-    ) -> Result<Output, Error> {
-        let mut cmd = Command::new(command);
-        cmd.args(args);
-
-        if let Some(dir) = current_dir {
-            cmd.current_dir(dir);
-        }
-
-        debug!("Running command: {:?} {:?}", command, args);
-
-        let output = cmd
-            .output()
+    /// Run a cargo command and return its output, with error handling
+    pub fn run_cargo(command: &CargoCommand, verbose: bool) -> Result<CargoOutput, Error> {
+        let output = cargo_runner::run(command)
             .map_err(|e| Error::CommandFailed(format!("Failed to execute {}: {}", command, e)))?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-
+        if let Err(e) = output.ensure_success(command) {
             if verbose {
-                eprintln!("Command failed: {} {:?}", command, args);
-                eprintln!("Status: {}", output.status);
-                eprintln!("Stdout: {}", stdout);
-                eprintln!("Stderr: {}", stderr);
+                eprintln!("Command failed: {}", command);
+                eprintln!("Status: {:?}", output.status);
+                eprintln!("Stdout: {}", String::from_utf8_lossy(&output.stdout));
+                eprintln!("Stderr: {}", output.stderr);
             }
 
-            return Err(Error::CommandFailed(format!(
-                "Command failed with status {}: {}",
-                output.status, stderr
-            )));
+            return Err(Error::CommandFailed(e.to_string()));
         }
 
         Ok(output)
@@ -1,11 +1,17 @@
 use crate::error::Error;
+use kargo_plugin_api::WriteGuard;
 use log::{debug, warn};
-use std::fs;
+use serde::de::DeserializeOwned;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 
 /// Create a directory and all parent directories
 pub fn create_dir_all(path: &Path) -> Result<(), Error> {
     debug!("Creating directory: {}", path.display());
+    if !path.is_dir() {
+        WriteGuard::current().check(path)?;
+    }
     fs::create_dir_all(path).map_err(|e| Error::Io(e))
 }
 
@@ -55,6 +61,7 @@ pub fn copy_file(src: &Path, dst: &Path) -> Result<(), Error> {
         create_dir_all(parent)?;
     }
 
+    WriteGuard::current().check(dst)?;
     fs::copy(src, dst)
         .map(|_| ())
         .map_err(|e| Error::DocCopyFailed(format!("Failed to copy file: {}", e)))
@@ -74,6 +81,7 @@ pub fn write_file(path: &Path, content: &str) -> Result<(), Error> {
         create_dir_all(parent)?;
     }
 
+    WriteGuard::current().check(path)?;
     fs::write(path, content).map_err(|e| Error::Io(e))
 }
 
@@ -82,3 +90,22 @@ pub fn read_file(path: &Path) -> Result<String, Error> {
     debug!("Reading file: {}", path.display());
     fs::read_to_string(path).map_err(|e| Error::Io(e))
 }
+
+/// Deserialize a JSON file from a buffered reader without loading it into a String
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    debug!("Reading JSON file: {}", path.display());
+    let file = File::open(path).map_err(Error::Io)?;
+    serde_json::from_reader(BufReader::with_capacity(1 << 20, file)).map_err(Error::JsonParse)
+}
+
+/// Create a file for buffered writing, making sure its parent directory exists
+pub fn create_file(path: &Path) -> Result<BufWriter<File>, Error> {
+    debug!("Creating file: {}", path.display());
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    WriteGuard::current().check(path)?;
+    File::create(path).map(BufWriter::new).map_err(Error::Io)
+}
@@ -1,9 +1,18 @@
 #![allow(unsafe_code)]
-use crate::{Config, DocGenerator};
+use crate::coverage::Coverage;
+use crate::generator::generate_local;
+use crate::import::{import, ImportConfig, ImportSource};
+use crate::links::LinkConfig;
+use crate::markdown::load_crate;
+use crate::serve::{serve, Library};
+use crate::{Config, DocGenerator, PackageSpec, Templates};
 use anyhow::anyhow;
 use clap::{Arg, Command};
-use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
-use std::path::PathBuf;
+use kargo_plugin_api::{
+    BoxFuture, CacheSpec, Diagnostic, DiagnosticKind, DiagnosticsSchema, Example, ExecutionContext,
+    HelpInfo, PluginCommand, Severity,
+};
+use std::path::{Path, PathBuf};
 
 pub struct MddocPlugin;
 
@@ -12,6 +21,108 @@ impl PluginCommand for MddocPlugin {
         Command::new("mddoc")
             .about("Generate Markdown documentation for Rust packages")
             .long_about("Creates Markdown documentation from any Rust crate's API by leveraging rustdoc's JSON output format")
+            .subcommand_negates_reqs(true)
+            .subcommand(
+                Command::new("serve")
+                    .about("Browse generated documentation in a web browser, with search")
+                    .long_about("Serves a directory of generated docs as HTML. Each subdirectory is a crate; generate into <dir>/<crate>/<version> to switch between versions.")
+                    .arg(
+                        Arg::new("dir")
+                            .long("dir")
+                            .help("Directory of generated documentation, one subdirectory per crate")
+                            .value_name("DIR")
+                            .default_value("./docs")
+                    )
+                    .arg(
+                        Arg::new("port")
+                            .short('p')
+                            .long("port")
+                            .help("Port to listen on")
+                            .value_name("PORT")
+                            .value_parser(clap::value_parser!(u16))
+                            .default_value("4000")
+                    )
+                    .arg(
+                        Arg::new("host")
+                            .long("host")
+                            .help("Address to listen on; the default only accepts local connections")
+                            .value_name("ADDR")
+                            .default_value("127.0.0.1")
+                    )
+            )
+            .subcommand(
+                Command::new("coverage")
+                    .about("Report public items lacking documentation, by module")
+                    .arg(
+                        Arg::new("target")
+                            .help("Package name with optional version, a local package directory or a rustdoc JSON file")
+                            .value_name("CRATE|PATH")
+                            .required(true)
+                            .index(1)
+                    )
+                    .arg(
+                        Arg::new("threshold")
+                            .long("threshold")
+                            .help("Fail when less than PERCENT of the public items are documented")
+                            .value_name("PERCENT")
+                            .value_parser(clap::value_parser!(f64))
+                    )
+                    .arg(
+                        Arg::new("skip-component-check")
+                            .long("skip-component-check")
+                            .help("Skip checking/installing rustup components")
+                            .action(clap::ArgAction::SetTrue)
+                    )
+            )
+            .subcommand(
+                Command::new("import")
+                    .about("Document a crate from docs.rs or prebuilt rustdoc output, without building it")
+                    .long_about("Writes the same Markdown as a local build from the rustdoc JSON docs.rs built, or from rustdoc JSON or HTML built elsewhere, for crates that don't build here.")
+                    .arg(
+                        Arg::new("package")
+                            .help("Package name with optional version (e.g., 'tokio' or 'tokio@1.28.0')")
+                            .required(true)
+                            .index(1)
+                    )
+                    .arg(
+                        Arg::new("from")
+                            .long("from")
+                            .help("Rustdoc JSON file (optionally .gz) or rustdoc HTML directory to import instead of downloading from docs.rs")
+                            .value_name("PATH")
+                    )
+                    .arg(
+                        Arg::new("output")
+                            .short('o')
+                            .long("output")
+                            .help("Output directory for documentation (default: ./docs/{package_name})")
+                            .value_name("DIR")
+                    )
+                    .arg(
+                        Arg::new("multipage")
+                            .short('m')
+                            .long("multipage")
+                            .help("Generate multi-page Markdown output; needs rustdoc JSON")
+                            .action(clap::ArgAction::SetTrue)
+                    )
+                    .arg(
+                        Arg::new("base-url")
+                            .long("base-url")
+                            .help("Base URL for cross-references in multi-page output")
+                            .value_name("URL")
+                    )
+                    .arg(
+                        Arg::new("link-external")
+                            .long("link-external")
+                            .help("Link types from dependencies to their docs.rs pages")
+                            .action(clap::ArgAction::SetTrue)
+                    )
+                    .arg(
+                        Arg::new("templates")
+                            .long("templates")
+                            .help("Directory of Handlebars layout templates")
+                            .value_name("DIR")
+                    )
+            )
             .arg(
                 Arg::new("package")
                     .help("Package name with optional version (e.g., 'tokio' or 'tokio@1.28.0')")
@@ -63,6 +174,12 @@ impl PluginCommand for MddocPlugin {
                     .help("Include private items in documentation")
                     .action(clap::ArgAction::SetTrue)
             )
+            .arg(
+                Arg::new("include-hidden")
+                    .long("include-hidden")
+                    .help("Include #[doc(hidden)] items, marked as hidden")
+                    .action(clap::ArgAction::SetTrue)
+            )
             .arg(
                 Arg::new("verbose")
                     .short('v')
@@ -84,6 +201,74 @@ impl PluginCommand for MddocPlugin {
                     .value_name("URL")
                     .default_value("")
             )
+            .arg(
+                Arg::new("link-external")
+                    .long("link-external")
+                    .help("Link types from other crates in signatures to their docs.rs pages")
+                    .action(clap::ArgAction::SetTrue)
+            )
+            .arg(
+                Arg::new("templates")
+                    .long("templates")
+                    .help("Directory of Handlebars layout templates (front_matter.hbs, heading.hbs, item_heading.hbs, footer.hbs)")
+                    .value_name("DIR")
+            )
+    }
+
+    fn diagnostics_schema(&self) -> Option<DiagnosticsSchema> {
+        Some(DiagnosticsSchema {
+            kinds: vec![DiagnosticKind {
+                code: "mddoc::coverage".to_string(),
+                severity: Severity::Note,
+                description: "Share of the public API with documentation".to_string(),
+            }],
+            output_patterns: Vec::new(),
+        })
+    }
+
+    fn help_info(&self) -> Option<HelpInfo> {
+        Some(HelpInfo {
+            category: Some("docs".to_string()),
+            examples: vec![
+                Example::new("kargo mddoc tokio", "Document the latest tokio release in ./docs/tokio"),
+                Example::new(
+                    "kargo mddoc serde@=1.0.219 --multipage -o docs/serde",
+                    "Document a pinned version, one page per module",
+                ),
+                Example::new(
+                    "kargo mddoc serve --dir docs",
+                    "Browse and search the generated docs at http://127.0.0.1:4000",
+                ),
+                Example::new(
+                    "kargo mddoc import vendor-sdk@=2.1.0 --from target/doc/vendor_sdk",
+                    "Document a crate that can't be built here from its rustdoc HTML",
+                ),
+                Example::new(
+                    "kargo mddoc coverage . --threshold 80",
+                    "Fail CI when less than 80% of this crate's public API is documented",
+                ),
+            ],
+        })
+    }
+
+    /// Runs are pure when the package version is pinned exactly
+    /// (`tokio@=1.45.0`); a looser requirement may resolve to a new release.
+    fn cache_spec(&self, ctx: &ExecutionContext) -> Option<CacheSpec> {
+        let matches = self.clap().try_get_matches_from(&ctx.matched_args).ok()?;
+        let package_spec = matches.get_one::<String>("package")?;
+        let (package_name, version) = package_spec.split_once('@')?;
+        if !version.starts_with('=') {
+            return None;
+        }
+        let output_dir = matches
+            .get_one::<String>("output")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("./docs").join(package_name));
+        Some(CacheSpec {
+            version: crate::VERSION.to_string(),
+            inputs: matches.get_one::<String>("templates").map(PathBuf::from).into_iter().collect(),
+            outputs: vec![output_dir],
+        })
     }
 
     fn run(&self, ctx: ExecutionContext) -> BoxFuture {
@@ -93,14 +278,103 @@ impl PluginCommand for MddocPlugin {
             let matches = cmd.get_matches_from(&ctx.matched_args);
 
             let verbose = matches.get_flag("verbose");
-            if verbose {
-                env_logger::Builder::new()
-                    .filter_level(log::LevelFilter::Debug)
-                    .init();
+            let level = if verbose {
+                log::LevelFilter::Debug
             } else {
-                env_logger::Builder::new()
-                    .filter_level(log::LevelFilter::Info)
-                    .init();
+                log::LevelFilter::Info
+            };
+            // The host's logger is already installed when loaded as a plugin;
+            // `--verbose` then only raises the level it is fed at
+            if env_logger::Builder::new()
+                .filter_level(level)
+                .try_init()
+                .is_err()
+                && verbose
+            {
+                log::set_max_level(level);
+            }
+
+            if let Some(("serve", sub)) = matches.subcommand() {
+                let dir = ctx.current_dir.join(sub.get_one::<String>("dir").map(String::as_str).unwrap_or("./docs"));
+                let host = sub.get_one::<String>("host").map(String::as_str).unwrap_or("127.0.0.1");
+                let port = sub.get_one::<u16>("port").copied().unwrap_or(4000);
+                let library = Library::scan(&dir)
+                    .map_err(|e| anyhow!("Failed to read docs in {}: {}", dir.display(), e))?;
+                serve(library, (host, port))?;
+                return Ok(());
+            }
+
+            if let Some(("import", sub)) = matches.subcommand() {
+                ctx.network.clone().install();
+                let mut package = PackageSpec::parse(
+                    sub.get_one::<String>("package")
+                        .ok_or_else(|| anyhow!("Package argument is required"))?,
+                )?;
+                // Pages are named after the version, not the requirement
+                package.version = package.version.map(|v| v.trim_start_matches('=').to_string());
+                let output_dir = sub
+                    .get_one::<String>("output")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("./docs").join(&package.name));
+                let config = ImportConfig {
+                    source: ImportSource::from_path(
+                        sub.get_one::<String>("from").map(|from| ctx.current_dir.join(from)).as_deref(),
+                    ),
+                    output_dir: ctx.current_dir.join(output_dir),
+                    multipage: sub.get_flag("multipage"),
+                    base_url: sub.get_one::<String>("base-url").cloned().unwrap_or_default(),
+                    templates: match sub.get_one::<String>("templates") {
+                        Some(dir) => Templates::load(&PathBuf::from(dir))?,
+                        None => Templates::builtin(),
+                    },
+                    links: LinkConfig {
+                        signatures: sub.get_flag("link-external"),
+                        ..LinkConfig::default()
+                    },
+                    package,
+                };
+                let pages = import(&config).await?;
+                log::info!(
+                    "Imported documentation for {}: {} files in {}",
+                    config.package.display_name(),
+                    pages.len(),
+                    config.output_dir.display()
+                );
+                return Ok(());
+            }
+
+            if let Some(("coverage", sub)) = matches.subcommand() {
+                let target = sub
+                    .get_one::<String>("target")
+                    .ok_or_else(|| anyhow!("Crate or path argument is required"))?;
+                let config = Config {
+                    package_spec: target.clone(),
+                    skip_component_check: sub.get_flag("skip-component-check"),
+                    verbose,
+                    target_dir: ctx.target_dir.clone(),
+                    ..Config::default()
+                };
+                let coverage = coverage_of(&ctx.current_dir.join(target), config)?;
+                print!("{}", coverage);
+                ctx.diagnostics.report(Diagnostic::new(
+                    "mddoc::coverage",
+                    Severity::Note,
+                    format!(
+                        "{:.1}% of {} public items documented",
+                        coverage.percent(),
+                        coverage.total()
+                    ),
+                ));
+                if let Some(threshold) = sub.get_one::<f64>("threshold") {
+                    if coverage.percent() < *threshold {
+                        return Err(anyhow!(
+                            "Documentation coverage {:.1}% is below the threshold of {}%",
+                            coverage.percent(),
+                            threshold
+                        ));
+                    }
+                }
+                return Ok(());
             }
 
             // Build configuration from arguments
@@ -119,14 +393,22 @@ impl PluginCommand for MddocPlugin {
             let keep_temp = matches.get_flag("keep-temp");
             let skip_component_check = matches.get_flag("skip-component-check");
             let document_private_items = matches.get_flag("document-private-items");
+            let include_hidden = matches.get_flag("include-hidden");
             let _keep_json = matches.get_flag("keep-json");
             let json_only = matches.get_flag("json-only");
             let multipage = matches.get_flag("multipage");
+            let link_external = matches.get_flag("link-external");
             let base_url = matches
                 .get_one::<String>("base-url")
                 .unwrap_or(&String::new())
                 .clone();
 
+            // Load templates up front so a broken one fails before rustdoc runs
+            let templates = match matches.get_one::<String>("templates") {
+                Some(dir) => Templates::load(&PathBuf::from(dir))?,
+                None => Templates::builtin(),
+            };
+
             // Create output directory if it doesn't exist
             if !output_dir.exists() {
                 std::fs::create_dir_all(&output_dir)?;
@@ -140,11 +422,17 @@ impl PluginCommand for MddocPlugin {
                 skip_component_check,
                 verbose,
                 document_private_items,
+                include_hidden,
+                target_dir: ctx.target_dir.clone(),
             };
 
             // Generate the documentation
             let mut generator = DocGenerator::new(config)?;
             let json_path = generator.run()?;
+            let links = LinkConfig {
+                signatures: link_external,
+                versions: generator.locked_versions(),
+            };
 
             // By default, we generate Markdown unless json_only is specified
             if !json_only {
@@ -155,6 +443,8 @@ impl PluginCommand for MddocPlugin {
                         base_url,
                         generate_index: true,
                         max_items_per_page: 50,
+                        templates,
+                        links,
                     };
                     let generated_files = crate::multipage_markdown::convert_to_multipage_markdown(
                         &json_path,
@@ -167,7 +457,7 @@ impl PluginCommand for MddocPlugin {
                     );
                 } else {
                     log::debug!("Converting JSON to single-page Markdown");
-                    let markdown_path = crate::markdown::convert_to_markdown(&json_path)?;
+                    let markdown_path = crate::markdown::convert_to_markdown_with(&json_path, &templates, &links)?;
                     log::info!(
                         "Markdown documentation generated at: {}",
                         markdown_path.display()
@@ -191,9 +481,30 @@ impl PluginCommand for MddocPlugin {
     }
 }
 
-#[no_mangle]
-#[allow(improper_ctypes_definitions)]
-#[allow(unsafe_code)]
-pub extern "C" fn kargo_plugin_create() -> Box<dyn PluginCommand> {
-    Box::new(MddocPlugin)
+/// Coverage of the rustdoc JSON file or local package at `path`, or else
+/// of the published package `config` names
+fn coverage_of(path: &Path, config: Config) -> anyhow::Result<Coverage> {
+    let data = if path.is_file() {
+        load_crate(path)?
+    } else if path.join("Cargo.toml").is_file() {
+        load_crate(&generate_local(path, &config)?)?
+    } else {
+        // The generator copies the JSON to its output directory
+        let output = tempfile::tempdir()?;
+        let mut generator = DocGenerator::new(Config {
+            output_dir: output.path().to_path_buf(),
+            ..config
+        })?;
+        load_crate(&generator.run()?)?
+    };
+    Ok(Coverage::of(&data))
 }
+
+// Let the host create this plugin, with its panics caught inside it
+kargo_plugin_api::export_plugin!(MddocPlugin);
+
+// Route this plugin's `log` records through the host's subscriber
+kargo_plugin_api::export_host_logger!();
+
+// Let the host check this plugin was built against an API it loads
+kargo_plugin_api::export_plugin_compat!();
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{TestCrate, assoc_const, assoc_type, trait_};
+use kargo_mddoc::markdown::rustdoc_json_to_markdown;
+use kargo_mddoc::multipage_markdown::{MultipageConfig, MultipageGenerator};
+
+/// `trait Codec` with required and defaulted associated types and constants
+fn codec() -> TestCrate {
+    let mut krate = TestCrate::new("demo");
+    let root = krate.root();
+    let codec = krate.add(root, "Codec", trait_());
+    let item = krate.add(codec, "Item", assoc_type(&["Clone", "Send"], None));
+    krate.get(item).docs = Some("What is decoded.\nMore details.".to_string());
+    krate.add(codec, "Error", assoc_type(&[], Some("str")));
+    krate.add(codec, "ID", assoc_const("u32", None));
+    krate.add(codec, "MAX", assoc_const("usize", Some("10")));
+    krate
+}
+
+#[test]
+fn test_associated_items_are_rendered_with_their_signatures() {
+    let markdown = rustdoc_json_to_markdown(&codec().data);
+
+    let required = markdown
+        .split_once("#### Required Items\n\n")
+        .expect("no required items")
+        .1;
+    let (required, provided) = required
+        .split_once("#### Provided Associated Items\n\n")
+        .expect("no provided items");
+    assert_eq!(
+        required,
+        "##### Associated Types\n\n\
+         - ```rust\n  type Item: Clone + Send;\n  ```\n  What is decoded.\n\n\
+         ##### Associated Constants\n\n\
+         - ```rust\n  const ID: u32;\n  ```\n\n"
+    );
+    // Defaults are shown with the value they provide
+    assert!(provided.starts_with(
+        "- ```rust\n  type Error = str;\n  ```\n\n\
+         - ```rust\n  const MAX: usize = 10;\n  ```\n\n"
+    ));
+}
+
+#[test]
+fn test_trait_pages_show_associated_item_signatures() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = MultipageConfig {
+        output_dir: dir.path().to_path_buf(),
+        ..MultipageConfig::default()
+    };
+    MultipageGenerator::new(codec().data, config)
+        .generate_all()
+        .unwrap();
+
+    let page = std::fs::read_to_string(dir.path().join("trait_codec.md")).unwrap();
+    for expected in [
+        "### Associated Type `Item`\n\nWhat is decoded.\nMore details.\n\n```rust\ntype Item: Clone + Send;\n```\n",
+        "### Associated Type `Error`\n\n```rust\ntype Error = str;\n```\n",
+        "### Associated Constant `ID`\n\n```rust\nconst ID: u32;\n```\n",
+        "### Associated Constant `MAX`\n\n```rust\nconst MAX: usize = 10;\n```\n",
+    ] {
+        assert!(page.contains(expected), "{:?} not in\n{}", expected, page);
+    }
+}
@@ -0,0 +1,24 @@
+use kargo_mddoc::attributes::{cfg_predicate, describe_cfg, hides};
+
+#[test]
+fn test_doc_hidden_among_other_doc_arguments() {
+    assert!(hides("#[doc(hidden)]"));
+    assert!(hides("#[doc(hidden, alias = \"x\")]"));
+    assert!(!hides("#[doc(alias = \"hidden\")]"));
+    assert!(!hides("#[inline]"));
+}
+
+#[test]
+fn test_cfg_predicates_are_described() {
+    assert_eq!(
+        cfg_predicate("#[cfg(feature = \"std\")]"),
+        Some("feature = \"std\"")
+    );
+    assert_eq!(
+        cfg_predicate("#[doc(cfg(all(unix, feature = \"rt\")))]"),
+        Some("all(unix, feature = \"rt\")")
+    );
+    assert_eq!(cfg_predicate("#[cfg_attr(docsrs, doc(cfg(unix)))]"), None);
+    assert_eq!(describe_cfg("feature = \"std\""), "crate feature `std`");
+    assert_eq!(describe_cfg("all(unix, windows)"), "`all(unix, windows)`");
+}
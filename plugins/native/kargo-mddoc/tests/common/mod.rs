@@ -0,0 +1,229 @@
+//! A small rustdoc crate built by hand, for tests that render or resolve
+//! items without running rustdoc
+#![allow(dead_code)]
+
+use rustdoc_types::{
+    Constant, Crate, ExternalCrate, GenericBound, Generics, Id, Item, ItemEnum, ItemKind,
+    ItemSummary, Module, Path, Struct, StructKind, Target, Trait, TraitBoundModifier, Type, Use,
+    Visibility,
+};
+use std::collections::HashMap;
+
+pub struct TestCrate {
+    pub data: Crate,
+    next: u32,
+}
+
+impl TestCrate {
+    /// A crate called `name` with an empty root module
+    pub fn new(name: &str) -> Self {
+        let root = Id(0);
+        let mut data = Crate {
+            root,
+            crate_version: Some("0.1.0".to_string()),
+            includes_private: false,
+            index: HashMap::new(),
+            paths: HashMap::new(),
+            external_crates: HashMap::new(),
+            target: Target {
+                triple: "x86_64-unknown-linux-gnu".to_string(),
+                target_features: Vec::new(),
+            },
+            format_version: rustdoc_types::FORMAT_VERSION,
+        };
+        data.index.insert(root, item(root, Some(name), module()));
+        Self { data, next: 1 }
+    }
+
+    pub fn root(&self) -> Id {
+        self.data.root
+    }
+
+    /// Add an item called `name` to the module, trait or impl `parent`
+    pub fn add(&mut self, parent: Id, name: &str, inner: ItemEnum) -> Id {
+        let id = self.next_id();
+        let name = match inner {
+            ItemEnum::Use(_) | ItemEnum::Impl(_) => None,
+            _ => Some(name),
+        };
+        let mut new = item(id, name, inner);
+        match &mut self.get(parent).inner {
+            ItemEnum::Module(module) => module.items.push(id),
+            ItemEnum::Trait(trait_) => {
+                // Trait members have no visibility of their own
+                new.visibility = Visibility::Default;
+                trait_.items.push(id);
+            }
+            ItemEnum::Impl(impl_) => impl_.items.push(id),
+            other => panic!("{:?} can't have members", other),
+        }
+        self.data.index.insert(id, new);
+        id
+    }
+
+    /// Record an item of the external crate `krate` at `path`
+    pub fn external(&mut self, krate: &str, path: &[&str], kind: ItemKind) -> Id {
+        let crate_id = match self
+            .data
+            .external_crates
+            .iter()
+            .find(|(_, external)| external.name == krate)
+        {
+            Some((crate_id, _)) => *crate_id,
+            None => {
+                let crate_id = self.data.external_crates.len() as u32 + 1;
+                self.data.external_crates.insert(
+                    crate_id,
+                    ExternalCrate {
+                        name: krate.to_string(),
+                        html_root_url: None,
+                    },
+                );
+                crate_id
+            }
+        };
+        let id = self.next_id();
+        self.data.paths.insert(
+            id,
+            ItemSummary {
+                crate_id,
+                path: std::iter::once(krate)
+                    .chain(path.iter().copied())
+                    .map(String::from)
+                    .collect(),
+                kind,
+            },
+        );
+        id
+    }
+
+    pub fn get(&mut self, id: Id) -> &mut Item {
+        self.data.index.get_mut(&id).expect("no such item")
+    }
+
+    fn next_id(&mut self) -> Id {
+        let id = Id(self.next);
+        self.next += 1;
+        id
+    }
+}
+
+fn item(id: Id, name: Option<&str>, inner: ItemEnum) -> Item {
+    Item {
+        id,
+        crate_id: 0,
+        name: name.map(String::from),
+        span: None,
+        visibility: Visibility::Public,
+        docs: None,
+        links: HashMap::new(),
+        attrs: Vec::new(),
+        deprecation: None,
+        inner,
+    }
+}
+
+pub fn module() -> ItemEnum {
+    ItemEnum::Module(Module {
+        is_crate: false,
+        items: Vec::new(),
+        is_stripped: false,
+    })
+}
+
+/// A module rustdoc leaves out of the docs, like a private one
+pub fn stripped_module() -> ItemEnum {
+    ItemEnum::Module(Module {
+        is_crate: false,
+        items: Vec::new(),
+        is_stripped: true,
+    })
+}
+
+pub fn unit_struct() -> ItemEnum {
+    ItemEnum::Struct(Struct {
+        kind: StructKind::Unit,
+        generics: generics(),
+        impls: Vec::new(),
+    })
+}
+
+pub fn constant(type_: &str, expr: &str) -> ItemEnum {
+    ItemEnum::Constant {
+        type_: Type::Primitive(type_.to_string()),
+        const_: Constant {
+            expr: expr.to_string(),
+            value: None,
+            is_literal: true,
+        },
+    }
+}
+
+pub fn trait_() -> ItemEnum {
+    ItemEnum::Trait(Trait {
+        is_auto: false,
+        is_unsafe: false,
+        is_dyn_compatible: true,
+        items: Vec::new(),
+        generics: generics(),
+        bounds: Vec::new(),
+        implementations: Vec::new(),
+    })
+}
+
+/// `type Name: bounds = default;` in a trait
+pub fn assoc_type(bounds: &[&str], default: Option<&str>) -> ItemEnum {
+    ItemEnum::AssocType {
+        generics: generics(),
+        bounds: bounds.iter().map(|bound| trait_bound(bound)).collect(),
+        type_: default.map(|type_| Type::Primitive(type_.to_string())),
+    }
+}
+
+/// `const NAME: type_ = value;` in a trait
+pub fn assoc_const(type_: &str, value: Option<&str>) -> ItemEnum {
+    ItemEnum::AssocConst {
+        type_: Type::Primitive(type_.to_string()),
+        value: value.map(String::from),
+    }
+}
+
+/// A bound on the trait at `path`, which is not in the crate
+pub fn trait_bound(path: &str) -> GenericBound {
+    GenericBound::TraitBound {
+        trait_: Path {
+            path: path.to_string(),
+            id: Id(u32::MAX),
+            args: None,
+        },
+        generic_params: Vec::new(),
+        modifier: TraitBoundModifier::None,
+    }
+}
+
+/// `pub use source as name;` of the item `id`
+pub fn reexport(source: &str, name: &str, id: Option<Id>) -> ItemEnum {
+    ItemEnum::Use(Use {
+        source: source.to_string(),
+        name: name.to_string(),
+        id,
+        is_glob: false,
+    })
+}
+
+/// `pub use source::*;` of the module `id`
+pub fn glob(source: &str, id: Id) -> ItemEnum {
+    ItemEnum::Use(Use {
+        source: source.to_string(),
+        name: source.rsplit("::").next().unwrap_or(source).to_string(),
+        id: Some(id),
+        is_glob: true,
+    })
+}
+
+pub fn generics() -> Generics {
+    Generics {
+        params: Vec::new(),
+        where_predicates: Vec::new(),
+    }
+}
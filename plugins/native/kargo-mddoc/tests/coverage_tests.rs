@@ -0,0 +1,106 @@
+mod common;
+
+use common::{
+    TestCrate, assoc_type, constant, glob, module, reexport, stripped_module, trait_, unit_struct,
+};
+use kargo_mddoc::coverage::{Coverage, ModuleCoverage};
+use rustdoc_types::Visibility;
+
+fn documented(krate: &mut TestCrate, id: rustdoc_types::Id) {
+    krate.get(id).docs = Some("Docs.".to_string());
+}
+
+#[test]
+fn test_undocumented_public_items_are_reported_by_module() {
+    let mut krate = TestCrate::new("demo");
+    let root = krate.root();
+    documented(&mut krate, root);
+    let config = krate.add(root, "Config", unit_struct());
+    documented(&mut krate, config);
+    krate.add(root, "LIMIT", constant("u32", "1"));
+    // Blank docs are as good as none
+    let blank = krate.add(root, "Blank", unit_struct());
+    krate.get(blank).docs = Some("  \n".to_string());
+
+    let net = krate.add(root, "net", module());
+    let transport = krate.add(net, "Transport", trait_());
+    documented(&mut krate, transport);
+    krate.add(transport, "Error", assoc_type(&[], None));
+    let private = krate.add(net, "Private", unit_struct());
+    krate.get(private).visibility = Visibility::Crate;
+    let hidden = krate.add(net, "Hidden", unit_struct());
+    krate.get(hidden).attrs.push("#[doc(hidden)]".to_string());
+
+    let coverage = Coverage::of(&krate.data);
+    assert_eq!(coverage.crate_name, "demo");
+    assert_eq!(
+        coverage.modules["demo"],
+        ModuleCoverage {
+            documented: 2,
+            missing: vec!["LIMIT".to_string(), "Blank".to_string()],
+        }
+    );
+    assert_eq!(
+        coverage.modules["demo::net"],
+        ModuleCoverage {
+            documented: 1,
+            missing: vec!["(module docs)".to_string(), "Transport::Error".to_string()],
+        }
+    );
+    assert_eq!((coverage.documented(), coverage.total()), (3, 7));
+    assert_eq!(
+        coverage.to_string(),
+        "Documentation coverage of demo: 42.9% (3/7 public items)\n\
+         \n\
+         demo: 2/4 documented\n  - LIMIT\n  - Blank\n\
+         \n\
+         demo::net: 1/3 documented\n  - (module docs)\n  - Transport::Error\n"
+    );
+}
+
+#[test]
+fn test_reexported_items_count_once_where_they_are_reexported() {
+    let mut krate = TestCrate::new("facade");
+    let root = krate.root();
+    documented(&mut krate, root);
+    let imp = krate.add(root, "imp", stripped_module());
+    krate.get(imp).visibility = Visibility::Crate;
+    let engine = krate.add(imp, "Engine", unit_struct());
+    let helper = krate.add(imp, "Helper", unit_struct());
+    documented(&mut krate, helper);
+
+    krate.add(
+        root,
+        "Motor",
+        reexport("crate::imp::Engine", "Motor", Some(engine)),
+    );
+    krate.add(
+        root,
+        "Engine",
+        reexport("crate::imp::Engine", "Engine", Some(engine)),
+    );
+    krate.add(root, "imp", glob("crate::imp", imp));
+    // Other crates' items are covered by their own docs
+    krate.add(root, "Value", reexport("serde_json::Value", "Value", None));
+
+    let coverage = Coverage::of(&krate.data);
+    assert_eq!(
+        coverage.modules["facade"],
+        ModuleCoverage {
+            documented: 2,
+            missing: vec!["Motor".to_string()],
+        }
+    );
+    assert_eq!(coverage.modules.len(), 1);
+}
+
+#[test]
+fn test_crates_without_public_items_are_fully_covered() {
+    let mut krate = TestCrate::new("empty");
+    let root = krate.root();
+    krate.get(root).attrs.push("#[doc(hidden)]".to_string());
+
+    let coverage = Coverage::of(&krate.data);
+    assert_eq!(coverage.total(), 0);
+    assert_eq!(coverage.percent(), 100.0);
+}
@@ -0,0 +1,147 @@
+use kargo_mddoc::import::{
+    docs_rs_json_url, html_to_markdown, import_html, ImportConfig, ImportSource,
+};
+use kargo_mddoc::links::LinkConfig;
+use kargo_mddoc::{PackageSpec, Templates};
+use std::path::Path;
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn page(declaration: &str, docs: &str) -> String {
+    format!(
+        "<html><body><nav class=\"sidebar\"><span class=\"version\">2.1.0</span></nav>\
+         <main><pre class=\"rust item-decl\"><code>{}</code></pre>\
+         <details class=\"toggle top-doc\" open><summary>Expand description</summary>\
+         <div class=\"docblock\">{}</div></details>\
+         <div class=\"docblock\"><p>Trait implementation docs.</p></div></main></body></html>",
+        declaration, docs
+    )
+}
+
+fn config(output_dir: &Path, source: ImportSource) -> ImportConfig {
+    ImportConfig {
+        package: PackageSpec::parse("vendor-sdk@2.1.0").unwrap(),
+        source,
+        output_dir: output_dir.to_path_buf(),
+        multipage: false,
+        base_url: String::new(),
+        templates: Templates::builtin(),
+        links: LinkConfig::default(),
+    }
+}
+
+#[test]
+fn test_html_import_lays_out_modules_and_items() {
+    let docs = tempfile::tempdir().unwrap();
+    let root = docs.path().join("vendor_sdk");
+    write(
+        &root,
+        "index.html",
+        &page("", "<p>Client for the <em>vendor</em> API.</p>"),
+    );
+    write(
+        &root,
+        "struct.Client.html",
+        &page(
+            "pub struct Client { <span class=\"comment\">/* private fields */</span> }",
+            "<p>A connection, see <a href=\"https://vendor.example.com\">the manual</a>.</p>",
+        ),
+    );
+    write(
+        &root,
+        "fn.connect.html",
+        &page(
+            "pub fn connect(url: &amp;<a class=\"primitive\" href=\"#\">str</a>) -&gt; Client",
+            "<p>Connects to <code>url</code>.</p>",
+        ),
+    );
+    write(&root, "auth/index.html", &page("", "<p>Credentials.</p>"));
+    write(
+        &root,
+        "auth/enum.Token.html",
+        &page("pub enum Token {}", ""),
+    );
+    // A re-export's redirect page
+    write(
+        &root,
+        "auth/struct.Client.html",
+        "<html><head><meta http-equiv=\"refresh\" content=\"0;URL=../struct.Client.html\"></head></html>",
+    );
+
+    let output = docs.path().join("out");
+    std::fs::create_dir_all(&output).unwrap();
+    let path = import_html(&root, &config(&output, ImportSource::Html(root.clone()))).unwrap();
+    assert_eq!(path, output.join("vendor-sdk-2.1.0.md"));
+
+    let markdown = std::fs::read_to_string(path).unwrap();
+    let expected = [
+        "**Version:** 2.1.0",
+        "# Module `vendor-sdk`\n\nClient for the *vendor* API.",
+        "## Module `auth`\n\nCredentials.",
+        "### Enum `Token`\n\n```rust\npub enum Token {}\n```",
+        "## Struct `Client`\n\n```rust\npub struct Client { /* private fields */ }\n```\n\n\
+         A connection, see [the manual](https://vendor.example.com).",
+        "## Function `connect`\n\n```rust\npub fn connect(url: &str) -> Client\n```\n\n\
+         Connects to `url`.",
+    ];
+    let mut at = 0;
+    for part in expected {
+        let found = markdown[at..]
+            .find(part)
+            .unwrap_or_else(|| panic!("{:?} not in order in:\n{}", part, markdown));
+        at += found + part.len();
+    }
+    assert!(!markdown.contains("Trait implementation docs"));
+    assert!(!markdown.contains("Expand description"));
+    assert_eq!(markdown.matches("`Client`").count(), 1);
+}
+
+#[test]
+fn test_html_import_has_no_multipage_layout() {
+    let docs = tempfile::tempdir().unwrap();
+    let mut config = config(docs.path(), ImportSource::Html(docs.path().to_path_buf()));
+    config.multipage = true;
+    let error = import_html(docs.path(), &config).unwrap_err();
+    assert!(error.to_string().contains("--multipage"));
+}
+
+#[test]
+fn test_html_to_markdown_converts_rustdoc_docblocks() {
+    let html = "<h2 id=\"examples\"><a class=\"doc-anchor\" href=\"#examples\">§</a>Examples</h2>\
+        <div class=\"example-wrap\"><pre class=\"rust rust-example-rendered\"><code>\
+        <span class=\"kw\">let </span>x = <span class=\"number\">1</span>;\n\
+        <span class=\"macro\">assert!</span>(x &lt; <span class=\"number\">2</span>);</code></pre></div>\
+        <ul><li>One <strong>bold</strong></li><li>Two<ol><li>Nested</li></ol></li></ul>\
+        <p>See <a href=\"struct.Client.html\"><code>Client</code></a>.</p>";
+    assert_eq!(
+        html_to_markdown(html),
+        "## Examples\n\n\
+         ```rust\nlet x = 1;\nassert!(x < 2);\n```\n\n\
+         - One **bold**\n- Two\n  1. Nested\n\n\
+         See `Client`."
+    );
+}
+
+#[test]
+fn test_sources_and_docs_rs_url() {
+    let package = PackageSpec::parse("serde@=1.0.219").unwrap();
+    assert!(docs_rs_json_url(&package).starts_with("https://docs.rs/crate/serde/1.0.219/json/"));
+    assert!(docs_rs_json_url(&PackageSpec::parse("serde").unwrap())
+        .starts_with("https://docs.rs/crate/serde/latest/json/"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let json = dir.path().join("serde.json.gz");
+    assert_eq!(ImportSource::from_path(None), ImportSource::DocsRs);
+    assert_eq!(
+        ImportSource::from_path(Some(dir.path())),
+        ImportSource::Html(dir.path().to_path_buf())
+    );
+    assert_eq!(
+        ImportSource::from_path(Some(&json)),
+        ImportSource::Json(json.clone())
+    );
+}
@@ -0,0 +1,34 @@
+use kargo_mddoc::links::locked_versions;
+
+#[test]
+fn test_locked_versions_skip_crates_locked_twice() {
+    let lockfile = r#"
+version = 4
+
+[[package]]
+name = "doc-generator"
+version = "0.1.0"
+dependencies = ["tokio-util"]
+
+[[package]]
+name = "tokio-util"
+version = "0.7.15"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.101"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    let versions = locked_versions(lockfile);
+    assert_eq!(versions.get("tokio_util").map(String::as_str), Some("0.7.15"));
+    assert_eq!(versions.get("doc_generator").map(String::as_str), Some("0.1.0"));
+    assert!(!versions.contains_key("syn"));
+    assert!(locked_versions("not a lockfile [").is_empty());
+}
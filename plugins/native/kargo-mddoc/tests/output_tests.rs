@@ -0,0 +1,113 @@
+mod common;
+
+use common::{TestCrate, constant, module, unit_struct};
+use kargo_mddoc::markdown::{rustdoc_json_to_markdown, write_markdown};
+use kargo_mddoc::output::{MarkdownOutput, MarkdownWrite, hashes};
+use std::io::{self, Write};
+
+/// Records every write it gets
+#[derive(Default)]
+struct Recorder {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl Write for Recorder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        self.writes += 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_streaming_drains_once_the_buffer_is_full() {
+    let mut sink = Recorder::default();
+    let mut output = MarkdownOutput::streaming(&mut sink);
+    output.push_str("small");
+    output.flush_if_full();
+    assert_eq!(output.as_str(), "small");
+
+    let big = "x".repeat(100 * 1024);
+    output.push_str(&big);
+    output.flush_if_full();
+    assert!(output.is_empty());
+    write!(output, "tail-{}", 1);
+    assert_eq!(output.finish().unwrap(), "");
+    assert_eq!(sink.data, format!("small{}tail-1", big).into_bytes());
+
+    // Without a sink everything stays in memory
+    let mut output = MarkdownOutput::buffered();
+    output.push_str(&big);
+    output.flush_if_full();
+    output.push('!');
+    assert_eq!(output.finish().unwrap(), format!("{}!", big));
+}
+
+#[test]
+fn test_write_errors_surface_when_finishing() {
+    let mut sink = Broken;
+    let mut output = MarkdownOutput::streaming(&mut sink);
+    output.push_str(&"x".repeat(100 * 1024));
+    output.flush_if_full();
+    // Rendering carries on after the failed write
+    output.push_str("more");
+    let error = output.finish().unwrap_err();
+    assert_eq!(error.to_string(), "disk full");
+}
+
+#[test]
+fn test_heading_prefixes() {
+    assert_eq!(hashes(0), "");
+    assert_eq!(hashes(3), "###");
+    assert_eq!(hashes(100).len(), 32);
+}
+
+#[test]
+fn test_streamed_markdown_matches_the_buffered_rendering() {
+    let mut krate = TestCrate::new("demo");
+    let root = krate.root();
+    let runtime = krate.add(root, "Runtime", unit_struct());
+    krate.get(runtime).docs = Some("The runtime.".to_string());
+    let limits = krate.add(root, "limits", module());
+    // Enough documentation to be written out in several pieces
+    for i in 0..200 {
+        let id = krate.add(limits, &format!("LIMIT_{}", i), constant("u32", "1"));
+        krate.get(id).docs = Some(format!("Limit number {}. {}", i, "Details. ".repeat(100)));
+    }
+
+    let buffered = rustdoc_json_to_markdown(&krate.data);
+    assert!(buffered.contains("Runtime"));
+    assert!(buffered.contains("The runtime."));
+    assert!(buffered.contains("LIMIT_199"));
+
+    let mut sink = Recorder::default();
+    write_markdown(&krate.data, &mut sink).unwrap();
+    assert!(sink.writes > 1, "written in {} piece(s)", sink.writes);
+    assert_eq!(String::from_utf8(sink.data).unwrap(), buffered);
+}
+
+#[test]
+fn test_markdown_write_formats_into_strings() {
+    let mut output = String::from("# Title\n\n");
+    MarkdownWrite::push_str(&mut output, "Text");
+    MarkdownWrite::push(&mut output, '\n');
+    write!(output, "{} Section {}\n\n", hashes(2), 1);
+    assert_eq!(output, "# Title\n\nText\n## Section 1\n\n");
+}
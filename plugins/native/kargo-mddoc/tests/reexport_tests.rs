@@ -0,0 +1,208 @@
+mod common;
+
+use common::{TestCrate, glob, module, reexport, stripped_module, unit_struct};
+use kargo_mddoc::links::{ExternalLinks, LinkConfig};
+use kargo_mddoc::reexports::{Member, Reexports, Target, use_declaration};
+use rustdoc_types::{ItemEnum, ItemKind, Use};
+
+/// Each member as `inline <name>` or `link <name> -> <target>`
+fn describe(members: Vec<Member>) -> Vec<String> {
+    members
+        .into_iter()
+        .map(|member| match member {
+            Member::Inline(item) => format!("inline {}", item.name.as_deref().unwrap_or("?")),
+            Member::Link(link) => {
+                let target = match link.target {
+                    Target::Local(_) => link.location.unwrap_or_else(|| "?".to_string()),
+                    Target::External(summary) => summary.path.join("::"),
+                    Target::Unresolved => "unresolved".to_string(),
+                };
+                format!("link {} -> {}", link.use_.name, target)
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_reexports_of_undocumented_items_are_inlined() {
+    let mut krate = TestCrate::new("facade");
+    let root = krate.root();
+    let imp = krate.add(root, "imp", stripped_module());
+    let engine = krate.add(imp, "Engine", unit_struct());
+    let helper = krate.add(imp, "Helper", unit_struct());
+    let public = krate.add(root, "public", module());
+    let config = krate.add(public, "Config", unit_struct());
+
+    let facade = krate.add(root, "facade", module());
+    krate.add(
+        facade,
+        "Engine",
+        reexport("crate::imp::Engine", "Engine", Some(engine)),
+    );
+    krate.add(
+        facade,
+        "Assist",
+        reexport("crate::imp::Helper", "Assist", Some(helper)),
+    );
+    krate.add(
+        facade,
+        "Config",
+        reexport("crate::public::Config", "Config", Some(config)),
+    );
+
+    let data = &krate.data;
+    let reexports = Reexports::new(data);
+    assert!(!reexports.is_documented(&engine));
+    assert!(reexports.is_documented(&config));
+    let ItemEnum::Module(facade) = &data.index[&facade].inner else {
+        unreachable!()
+    };
+    assert_eq!(
+        describe(reexports.members(&facade.items)),
+        [
+            "inline Engine",
+            // Inlined under the name it's re-exported as
+            "inline Assist",
+            "link Config -> facade::public::Config",
+        ]
+    );
+}
+
+#[test]
+fn test_glob_reexports_expand_once() {
+    let mut krate = TestCrate::new("facade");
+    let root = krate.root();
+    let imp = krate.add(root, "imp", stripped_module());
+    krate.add(imp, "One", unit_struct());
+    krate.add(imp, "Two", unit_struct());
+    let public = krate.add(root, "public", module());
+    krate.add(public, "Three", unit_struct());
+
+    let facade = krate.add(root, "facade", module());
+    krate.add(facade, "imp", glob("crate::imp", imp));
+    krate.add(facade, "imp", glob("crate::imp", imp));
+    // A module with its own page is linked rather than expanded
+    krate.add(facade, "public", glob("crate::public", public));
+
+    let data = &krate.data;
+    let reexports = Reexports::new(data);
+    let ItemEnum::Module(facade) = &data.index[&facade].inner else {
+        unreachable!()
+    };
+    assert_eq!(
+        describe(reexports.members(&facade.items)),
+        ["inline One", "inline Two", "link public -> facade::public"]
+    );
+}
+
+#[test]
+fn test_chains_of_reexports_are_followed() {
+    let mut krate = TestCrate::new("facade");
+    let root = krate.root();
+    let imp = krate.add(root, "imp", stripped_module());
+    let engine = krate.add(imp, "Engine", unit_struct());
+    let inner = krate.add(
+        imp,
+        "Engine",
+        reexport("crate::imp::Engine", "Engine", Some(engine)),
+    );
+    let middle = krate.add(
+        imp,
+        "Motor",
+        reexport("crate::imp::Engine", "Motor", Some(inner)),
+    );
+    let facade = krate.add(root, "facade", module());
+    krate.add(
+        facade,
+        "Motor",
+        reexport("crate::imp::Motor", "Motor", Some(middle)),
+    );
+    // A cycle ends after MAX_CHAIN steps instead of looping
+    let looped = krate.add(imp, "Loop", reexport("crate::imp::Loop", "Loop", None));
+    if let ItemEnum::Use(use_) = &mut krate.get(looped).inner {
+        use_.id = Some(looped);
+    }
+    krate.add(
+        facade,
+        "Loop",
+        reexport("crate::imp::Loop", "Loop", Some(looped)),
+    );
+
+    let data = &krate.data;
+    let reexports = Reexports::new(data);
+    let ItemEnum::Module(facade) = &data.index[&facade].inner else {
+        unreachable!()
+    };
+    assert_eq!(
+        describe(reexports.members(&facade.items)),
+        ["inline Motor", "link Loop -> unresolved"]
+    );
+}
+
+#[test]
+fn test_reexports_from_other_crates_link_to_their_docs() {
+    let mut krate = TestCrate::new("facade");
+    let root = krate.root();
+    let serialize = krate.external("serde", &["ser", "Serialize"], ItemKind::Trait);
+    krate.add(
+        root,
+        "Serialize",
+        reexport("serde::Serialize", "Serialize", Some(serialize)),
+    );
+    krate.add(root, "u8", reexport("core::primitive::u8", "u8", None));
+
+    let data = &krate.data;
+    let reexports = Reexports::new(data);
+    let ItemEnum::Module(module) = &data.index[&root].inner else {
+        unreachable!()
+    };
+    let members = reexports.members(&module.items);
+    let Some(Member::Link(link)) = members.first() else {
+        panic!("not a link: {:?}", members);
+    };
+    let Target::External(summary) = link.target else {
+        panic!("not external: {:?}", link.target);
+    };
+
+    let mut config = LinkConfig::default();
+    assert_eq!(
+        ExternalLinks::new(data, &config).url(summary).as_deref(),
+        Some("https://docs.rs/serde/latest/serde/ser/trait.Serialize.html")
+    );
+    config
+        .versions
+        .insert("serde".to_string(), "1.0.219".to_string());
+    assert_eq!(
+        ExternalLinks::new(data, &config).url(summary).as_deref(),
+        Some("https://docs.rs/serde/1.0.219/serde/ser/trait.Serialize.html")
+    );
+    assert_eq!(
+        describe(members),
+        [
+            "link Serialize -> serde::ser::Serialize",
+            "link u8 -> unresolved"
+        ]
+    );
+}
+
+#[test]
+fn test_use_declarations_are_written_as_in_source() {
+    let use_ = |source: &str, name: &str, is_glob| Use {
+        source: source.to_string(),
+        name: name.to_string(),
+        id: None,
+        is_glob,
+    };
+    assert_eq!(
+        use_declaration(&use_("crate::imp::Engine", "Engine", false)),
+        "use crate::imp::Engine;"
+    );
+    assert_eq!(
+        use_declaration(&use_("crate::imp::Helper", "Assist", false)),
+        "use crate::imp::Helper as Assist;"
+    );
+    assert_eq!(
+        use_declaration(&use_("crate::imp", "imp", true)),
+        "use crate::imp::*;"
+    );
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{TestCrate, constant, module, trait_, unit_struct};
+use kargo_mddoc::markdown::rustdoc_json_to_markdown;
+
+#[test]
+fn test_nested_modules_render_one_heading_level_deeper() {
+    let mut krate = TestCrate::new("demo");
+    let root = krate.root();
+    let net = krate.add(root, "net", module());
+    let tcp = krate.add(net, "tcp", module());
+    krate.add(tcp, "Stream", unit_struct());
+    krate.add(net, "Transport", trait_());
+    krate.add(root, "LIMIT", constant("u32", "1"));
+
+    assert_eq!(
+        rustdoc_json_to_markdown(&krate.data),
+        "# Crate Documentation\n\n\
+         **Version:** 0.1.0\n\n\
+         **Format Version:** 46\n\n\
+         # Module `demo`\n\n\
+         ## Modules\n\n\
+         ### Module `net`\n\n\
+         ```rust\npub mod net { /* ... */ }\n```\n\n\
+         #### Modules\n\n\
+         ##### Module `tcp`\n\n\
+         ```rust\npub mod tcp { /* ... */ }\n```\n\n\
+         ###### Types\n\n\
+         ####### Struct `Stream`\n\n\
+         ```rust\npub struct Stream;\n```\n\n\
+         #### Traits\n\n\
+         ##### Trait `Transport`\n\n\
+         ```rust\npub trait Transport {\n    /* Associated items */\n}\n```\n\n\
+         ## Constants and Statics\n\n\
+         ### Constant `LIMIT`\n\n\
+         ```rust\npub const LIMIT: u32 = 1;\n```\n\n"
+    );
+}
+
+#[test]
+fn test_crates_without_a_version_skip_the_version_line() {
+    let mut krate = TestCrate::new("demo");
+    krate.data.crate_version = None;
+
+    let markdown = rustdoc_json_to_markdown(&krate.data);
+    assert!(markdown.starts_with("# Crate Documentation\n\n**Format Version:** 46\n\n"));
+    assert!(!markdown.contains("**Version:**"));
+}
@@ -0,0 +1,88 @@
+use kargo_mddoc::serve::Library;
+use std::path::Path;
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn body(library: &Library, target: &str) -> String {
+    let response = library.respond(target);
+    assert_eq!(response.status, 200, "{}", target);
+    String::from_utf8(response.body).unwrap()
+}
+
+#[test]
+fn test_library_serves_versions_and_searches_pages() {
+    let docs = tempfile::tempdir().unwrap();
+    let root = docs.path();
+    write(
+        root,
+        "tokio/1.44.0/README.md",
+        "# tokio\n\nAn async runtime.\n",
+    );
+    write(
+        root,
+        "tokio/1.45.0/README.md",
+        "# tokio\n\nAn async runtime.\n",
+    );
+    write(
+        root,
+        "tokio/1.45.0/task/fn.spawn_blocking.md",
+        "# Function `spawn_blocking`\n\nRuns the closure on a thread where blocking is acceptable.\n",
+    );
+    write(
+        root,
+        "serde/serde.md",
+        "# serde\n\nA serialization framework, not a runtime.\n",
+    );
+    write(root, "empty/notes.txt", "no markdown here");
+
+    let library = Library::scan(root).unwrap();
+    let books: Vec<(String, Option<String>)> = library
+        .books()
+        .iter()
+        .map(|book| {
+            (
+                book.name.clone(),
+                book.version.as_ref().map(|v| v.to_string()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        books,
+        [
+            ("serde".to_string(), None),
+            ("tokio".to_string(), Some("1.44.0".to_string())),
+            ("tokio".to_string(), Some("1.45.0".to_string())),
+        ]
+    );
+
+    let hits = library.search("blocking", None);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].url, "/tokio/1.45.0/task/fn.spawn_blocking.md");
+    assert!(hits[0].snippet.contains("blocking is acceptable"));
+    assert_eq!(library.search("runtime", None).len(), 3);
+    assert_eq!(library.search("runtime", Some("serde")).len(), 1);
+    assert!(library.search("async runtime", Some("serde")).is_empty());
+
+    let redirect = |target: &str| library.respond(target).location.unwrap();
+    assert_eq!(redirect("/tokio"), "/tokio/");
+    assert_eq!(redirect("/tokio/"), "/tokio/1.45.0/");
+    assert_eq!(redirect("/tokio/1.45.0/"), "/tokio/1.45.0/README.md");
+    assert_eq!(redirect("/serde/"), "/serde/serde.md");
+
+    let page = body(&library, "/tokio/1.44.0/README.md");
+    assert!(page.contains("<option value=\"/tokio/1.45.0/README.md\">1.45.0</option>"));
+    assert!(page.contains("<option value=\"/tokio/1.44.0/README.md\" selected>1.44.0</option>"));
+    let page = body(&library, "/tokio/1.45.0/task/fn.spawn_blocking.md");
+    assert!(page.contains("<option value=\"/tokio/1.44.0/\">1.44.0</option>"));
+
+    assert!(body(&library, "/search?q=spawn+blocking").contains("fn.spawn_blocking.md"));
+    let json: serde_json::Value =
+        serde_json::from_str(&body(&library, "/search.json?q=serialization")).unwrap();
+    assert_eq!(json[0]["crate"], "serde");
+    assert_eq!(library.respond("/tokio/../../etc/passwd").status, 404);
+    assert_eq!(library.respond("/empty/").status, 404);
+}
@@ -0,0 +1,66 @@
+use kargo_mddoc::templates::{CrateContext, GeneratorContext, ItemContext, PageContext, Templates};
+
+fn struct_page() -> PageContext {
+    PageContext {
+        kind: "struct".to_string(),
+        title: "Struct `Runtime`".to_string(),
+        file: "struct_runtime.md".to_string(),
+        krate: CrateContext {
+            name: "tokio".to_string(),
+            version: Some("1.45.0".to_string()),
+        },
+        item: Some(ItemContext {
+            name: "Runtime".to_string(),
+            kind: "struct".to_string(),
+            docs: None,
+            summary: None,
+            link: None,
+        }),
+        items: Vec::new(),
+        generator: GeneratorContext::default(),
+    }
+}
+
+#[test]
+fn test_builtin_templates_match_plain_output() {
+    let templates = Templates::builtin();
+    let page = struct_page();
+
+    assert_eq!(
+        templates.page_start(&page).unwrap(),
+        "# Struct `Runtime`\n\n"
+    );
+    assert_eq!(templates.page_end(&page).unwrap(), "");
+    assert_eq!(
+        templates.item_heading(page.item.as_ref().unwrap()).unwrap(),
+        "## `Runtime`\n\n"
+    );
+}
+
+#[test]
+fn test_user_templates_add_front_matter_and_footer() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("front_matter.hbs"),
+        "---\ntitle: \"{{title}}\"\nweight: {{#if item}}20{{else}}10{{/if}}\n---\n\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("footer.hbs"), "\n{{> attribution}}").unwrap();
+    std::fs::write(
+        dir.path().join("attribution.hbs"),
+        "_Generated by {{generator.name}} for {{crate.name}} {{crate.version}}_\n",
+    )
+    .unwrap();
+
+    let templates = Templates::load(dir.path()).unwrap();
+    let page = struct_page();
+
+    assert_eq!(
+        templates.page_start(&page).unwrap(),
+        "---\ntitle: \"Struct `Runtime`\"\nweight: 20\n---\n\n# Struct `Runtime`\n\n"
+    );
+    assert_eq!(
+        templates.page_end(&page).unwrap(),
+        "\n_Generated by kargo-mddoc for tokio 1.45.0_\n"
+    );
+}
@@ -3,7 +3,7 @@ use anyhow::Result;
 use clap::{Arg, Command, CommandFactory};
 use clap_complete::Shell;
 use globset::Glob;
-use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+use kargo_plugin_api::{BoxFuture, Example, ExecutionContext, HelpInfo, PluginCommand};
 use std::path::PathBuf;
 
 pub struct MdlintPlugin;
@@ -56,14 +56,26 @@ impl PluginCommand for MdlintPlugin {
             )
     }
 
+    fn help_info(&self) -> Option<HelpInfo> {
+        Some(HelpInfo {
+            category: Some("docs".to_string()),
+            examples: vec![
+                Example::new("kargo mdlint", "Lint every markdown file below the current directory"),
+                Example::new(
+                    "kargo mdlint docs --exclude 'docs/generated/**'",
+                    "Lint docs, skipping generated pages",
+                ),
+            ],
+        })
+    }
+
     fn run(&self, ctx: ExecutionContext) -> BoxFuture {
-        let cmd = self.clap();
         Box::pin(async move {
-            let matches = cmd.get_matches_from(&ctx.matched_args);
+            let args = &ctx.args;
 
             // Check if shell completion was requested
-            if let Some(shell_str) = matches.get_one::<String>("shell") {
-                let shell = match shell_str.as_str() {
+            if let Some(shell_str) = args.value("shell") {
+                let shell = match shell_str {
                     "bash" => Shell::Bash,
                     "fish" => Shell::Fish,
                     "zsh" => Shell::Zsh,
@@ -79,22 +91,19 @@ impl PluginCommand for MdlintPlugin {
             }
 
             // Collect files
-            let files: Vec<PathBuf> = matches
-                .get_many::<String>("files")
-                .unwrap_or_default()
-                .map(PathBuf::from)
-                .collect();
+            let files: Vec<PathBuf> = args.values("files").iter().map(PathBuf::from).collect();
 
             // Parse output format - let mado handle the format parsing
             let output_format = None; // Will use mado's default format handling
 
-            let quiet = matches.get_flag("quiet");
+            let quiet = args.flag("quiet");
 
             // Parse exclude patterns
-            let exclude = matches
-                .get_many::<String>("exclude")
-                .map(|values| {
-                    values
+            let exclude = args
+                .contains("exclude")
+                .then(|| {
+                    args.values("exclude")
+                        .iter()
                         .map(|pattern| {
                             Glob::new(pattern)
                                 .map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", pattern, e))
@@ -103,7 +112,7 @@ impl PluginCommand for MdlintPlugin {
                 })
                 .transpose()?;
 
-            let config_path = matches.get_one::<String>("config").map(PathBuf::from);
+            let config_path = args.value("config").map(PathBuf::from);
 
             // Create mado options
             let options = mado::command::check::Options {
@@ -131,9 +140,8 @@ impl PluginCommand for MdlintPlugin {
     }
 }
 
-#[no_mangle]
-#[allow(improper_ctypes_definitions)]
-#[allow(unsafe_code)]
-pub extern "C" fn kargo_plugin_create() -> Box<dyn PluginCommand> {
-    Box::new(MdlintPlugin)
-}
\ No newline at end of file
+// Let the host create this plugin, with its panics caught inside it
+kargo_plugin_api::export_plugin!(MdlintPlugin);
+
+// Let the host check this plugin was built against an API it loads
+kargo_plugin_api::export_plugin_compat!();
@@ -0,0 +1,363 @@
+//! Semantic relevance through embeddings
+//!
+//! When `<kargo config dir>/sap.yaml` configures an embedding model, entries
+//! are ranked by the cosine similarity between the embedding of the
+//! objective (and context) and that of a short summary of each entry, instead
+//! of being filtered by name alone:
+//!
+//! ```yaml
+//! embeddings:
+//!   # A local program: reads text on stdin, prints a JSON array of numbers
+//!   command: ["my-embedder", "--model", "nomic-embed-text"]
+//!   # ... or an OpenAI-compatible endpoint (OpenAI, Ollama, llama.cpp, ...)
+//!   url: http://localhost:11434/v1/embeddings
+//!   model: nomic-embed-text
+//!   api_key_env: OPENAI_API_KEY
+//!   # ... or keep the key in the OS keychain (`kargo secret set openai`)
+//!   # and list the host under `network.tokens` as `keyring:openai`
+//!   # Entries scoring below this are not listed (0 lists everything)
+//!   min_score: 0.3
+//! ```
+//!
+//! Summary vectors are kept in `<plugin cache dir>/embeddings.json` and only
+//! recomputed for entries whose size or modification time changed. Switching
+//! to another model discards them.
+
+use anyhow::{Context, Result, anyhow, bail};
+use kargo_plugin_api::{HttpClient, WriteGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::relevance::LlmConfig;
+use crate::session::Seen;
+
+/// Bytes of a file's contents included in its summary
+const SUMMARY_BYTES: usize = 2048;
+/// Names of a directory's children included in its summary
+const SUMMARY_CHILDREN: usize = 50;
+const DEFAULT_MIN_SCORE: f32 = 0.3;
+
+/// `<kargo config dir>/sap.yaml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SapConfig {
+    pub embeddings: Option<EmbeddingConfig>,
+    /// Chat model ranking entries, see [`relevance`](crate::relevance)
+    pub relevance: Option<LlmConfig>,
+    /// Fleet index to consult, see [`inventory`](crate::inventory)
+    pub inventory: Option<PathBuf>,
+}
+
+impl SapConfig {
+    /// Load the config from `config_dir`; the defaults if there is none
+    pub fn load(config_dir: &Path) -> Result<Self> {
+        let path = config_dir.join("sap.yaml");
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    /// Program that reads text on stdin and prints its embedding as a JSON
+    /// array of numbers
+    pub command: Option<Vec<String>>,
+    /// OpenAI-compatible embeddings endpoint
+    pub url: Option<String>,
+    pub model: Option<String>,
+    /// Environment variable holding the API key sent as a bearer token
+    pub api_key_env: Option<String>,
+    pub min_score: Option<f32>,
+}
+
+/// Where embeddings come from
+pub enum Embedder {
+    Command(Vec<String>),
+    Http {
+        client: Box<HttpClient>,
+        url: String,
+        model: String,
+        api_key: Option<String>,
+    },
+}
+
+impl Embedder {
+    pub fn from_config(config: &EmbeddingConfig) -> Result<Self> {
+        match (&config.command, &config.url) {
+            (Some(command), None) if !command.is_empty() => Ok(Self::Command(command.clone())),
+            (None, Some(url)) => Ok(Self::Http {
+                client: Box::new(HttpClient::new(concat!(
+                    "kargo-sap/",
+                    env!("CARGO_PKG_VERSION")
+                ))?),
+                url: url.clone(),
+                model: config
+                    .model
+                    .clone()
+                    .context("embeddings.model is required with embeddings.url")?,
+                api_key: config
+                    .api_key_env
+                    .as_ref()
+                    .and_then(|var| std::env::var(var).ok()),
+            }),
+            _ => bail!("configure exactly one of embeddings.command and embeddings.url"),
+        }
+    }
+
+    /// Identifies the model; vectors from different models don't compare
+    pub fn fingerprint(&self) -> String {
+        match self {
+            Self::Command(command) => format!("command:{}", command.join(" ")),
+            Self::Http { url, model, .. } => format!("http:{}#{}", url, model),
+        }
+    }
+
+    /// One embedding per text, in order
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self {
+            Self::Command(command) => {
+                let mut vectors = Vec::with_capacity(texts.len());
+                for text in texts {
+                    vectors.push(embed_with_command(command, text).await?);
+                }
+                Ok(vectors)
+            }
+            Self::Http {
+                client,
+                url,
+                model,
+                api_key,
+            } => {
+                let mut request = client
+                    .post(url)
+                    .json(&serde_json::json!({ "model": model, "input": texts }));
+                if let Some(key) = api_key {
+                    request = request.bearer_auth(key);
+                }
+                let response: EmbeddingResponse = client
+                    .send(request)
+                    .await
+                    .and_then(|r| r.error_for_status().map_err(Into::into))
+                    .with_context(|| format!("Embedding request to {} failed", url))?
+                    .json()
+                    .await
+                    .with_context(|| format!("Unexpected embedding response from {}", url))?;
+                let mut data = response.data;
+                data.sort_by_key(|d| d.index);
+                if data.len() != texts.len() {
+                    bail!(
+                        "{} returned {} embeddings for {} texts",
+                        url,
+                        data.len(),
+                        texts.len()
+                    );
+                }
+                Ok(data.into_iter().map(|d| d.embedding).collect())
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+async fn embed_with_command(command: &[String], text: &str) -> Result<Vec<f32>> {
+    let mut child = tokio::process::Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", command[0]))?;
+    let mut stdin = child.stdin.take().context("stdin is piped")?;
+    stdin.write_all(text.as_bytes()).await?;
+    drop(stdin);
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        bail!("{} exited with {}", command[0], output.status);
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| {
+        anyhow!(
+            "{} did not print a JSON array of numbers: {}",
+            command[0],
+            e
+        )
+    })
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VectorStore {
+    fingerprint: String,
+    entries: BTreeMap<PathBuf, StoredVector>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredVector {
+    seen: Seen,
+    vector: Vec<f32>,
+}
+
+/// An entry to score
+pub struct Candidate<'a> {
+    pub path: &'a Path,
+    pub is_dir: bool,
+    pub seen: Seen,
+}
+
+/// Summary vectors of listed entries, refreshed as they change
+pub struct SemanticIndex {
+    embedder: Embedder,
+    store: VectorStore,
+    file: PathBuf,
+    min_score: f32,
+}
+
+impl SemanticIndex {
+    /// Open the index kept in `cache_dir` for the model in `config`
+    pub fn open(config: &EmbeddingConfig, cache_dir: &Path) -> Result<Self> {
+        let embedder = Embedder::from_config(config)?;
+        let file = cache_dir.join("embeddings.json");
+        let store = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str::<VectorStore>(&content).ok())
+            .filter(|store| store.fingerprint == embedder.fingerprint())
+            .unwrap_or_else(|| VectorStore {
+                fingerprint: embedder.fingerprint(),
+                entries: BTreeMap::new(),
+            });
+        Ok(Self {
+            embedder,
+            store,
+            file,
+            min_score: config.min_score.unwrap_or(DEFAULT_MIN_SCORE),
+        })
+    }
+
+    pub fn min_score(&self) -> f32 {
+        self.min_score
+    }
+
+    /// Similarity of each candidate to `query`, in order. Candidates without
+    /// an up-to-date vector are summarized and embedded first.
+    pub async fn scores(&mut self, candidates: &[Candidate<'_>], query: &str) -> Result<Vec<f32>> {
+        let stale: Vec<&Candidate> = candidates
+            .iter()
+            .filter(|c| {
+                self.store
+                    .entries
+                    .get(c.path)
+                    .is_none_or(|stored| stored.seen != c.seen)
+            })
+            .collect();
+        if !stale.is_empty() {
+            log::debug!("Embedding {} changed entries", stale.len());
+            let summaries: Vec<String> =
+                stale.iter().map(|c| summarize(c.path, c.is_dir)).collect();
+            let vectors = self.embedder.embed(&summaries).await?;
+            for (candidate, vector) in stale.iter().zip(vectors) {
+                self.store.entries.insert(
+                    candidate.path.to_path_buf(),
+                    StoredVector {
+                        seen: candidate.seen,
+                        vector,
+                    },
+                );
+            }
+        }
+
+        let query = self
+            .embedder
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .context("no embedding for the query")?;
+        Ok(candidates
+            .iter()
+            .map(|c| {
+                self.store
+                    .entries
+                    .get(c.path)
+                    .map_or(0.0, |stored| cosine(&query, &stored.vector))
+            })
+            .collect())
+    }
+
+    /// Save the vectors, dropping those of entries that no longer exist
+    pub fn save(&mut self) -> Result<()> {
+        self.store.entries.retain(|path, _| path.exists());
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.file.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        guard.write(&self.file, serde_json::to_string(&self.store)?)
+    }
+}
+
+/// The text embedded for an entry: its name and parent, then the start of a
+/// file's contents or the names in a directory
+pub fn summarize(path: &Path, is_dir: bool) -> String {
+    let mut summary = path
+        .iter()
+        .rev()
+        .take(2)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<PathBuf>()
+        .display()
+        .to_string();
+    if is_dir {
+        let mut names: Vec<String> = std::fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        names.sort();
+        names.truncate(SUMMARY_CHILDREN);
+        summary.push_str("\ncontains: ");
+        summary.push_str(&names.join(", "));
+    } else if let Ok(bytes) = std::fs::read(path) {
+        let head = &bytes[..bytes.len().min(SUMMARY_BYTES)];
+        if !head.contains(&0) {
+            summary.push('\n');
+            summary.push_str(&String::from_utf8_lossy(head));
+        }
+    }
+    summary
+}
+
+/// Cosine similarity; 0 for vectors of different lengths or zero vectors
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
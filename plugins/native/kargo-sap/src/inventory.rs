@@ -0,0 +1,209 @@
+//! What the fleet inventory knows about the listed directories
+//!
+//! `kargo-walk` indexes the Rust projects below a directory into an
+//! `index.yaml`: where each project lives, whether it builds and what it
+//! depends on. When the listed roots are part of such a fleet, sap reads the
+//! index given with `--inventory`, else the one named in `sap.yaml`:
+//!
+//! ```yaml
+//! inventory: /srv/fleet/index.yaml
+//! ```
+//!
+//! else the nearest `index.yaml` in a root or above it. Indexed projects are
+//! then marked in the listing, broken ones as such, the language model ranker
+//! is told the same, and projects the objective mentions, by name or by a
+//! crate they depend on, are listed first.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::embeddings::Candidate;
+use crate::relevance::{Query, Ranked};
+use crate::roots::Root;
+
+/// Index file `kargo-walk` writes by default
+pub const INDEX_FILE: &str = "index.yaml";
+
+/// Dependencies named per project when telling the model about it
+const PROMPT_DEPENDENCIES: usize = 20;
+
+/// Whether an indexed project built when it was indexed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum ProjectStatus {
+    Working,
+    Broken,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+/// A project of the index
+#[derive(Debug, Clone, Deserialize)]
+pub struct Project {
+    pub path: PathBuf,
+    pub name: String,
+    #[serde(default)]
+    pub status: ProjectStatus,
+    /// Names of the crates it depends on
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Names of the indexed projects depending on it
+    #[serde(default)]
+    pub dependents: Vec<String>,
+}
+
+/// How an objective refers to a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mention {
+    /// By the project's name
+    Name,
+    /// By the name of a crate it depends on
+    Dependency,
+}
+
+impl Project {
+    /// How the words of an objective refer to the project, if they do
+    pub fn mention(&self, words: &HashSet<String>) -> Option<Mention> {
+        if words.contains(&crate_key(&self.name)) {
+            Some(Mention::Name)
+        } else if self
+            .dependencies
+            .iter()
+            .any(|dependency| words.contains(&crate_key(dependency)))
+        {
+            Some(Mention::Dependency)
+        } else {
+            None
+        }
+    }
+
+    /// Note shown next to the project's directory in the listing
+    pub fn note(&self) -> String {
+        match self.status {
+            ProjectStatus::Broken => format!("crate {}, broken", self.name),
+            _ => format!("crate {}", self.name),
+        }
+    }
+}
+
+/// The projects of one index
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    pub projects: Vec<Project>,
+}
+
+impl Inventory {
+    /// Read the index at `path`. Project paths are taken relative to the
+    /// directory of the index, where `kargo-walk` runs by default.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read inventory {}", path.display()))?;
+        let mut projects: Vec<Project> = serde_yaml::from_str(&content)
+            .with_context(|| format!("Invalid inventory {}", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for project in &mut projects {
+            let joined = base.join(&project.path);
+            project.path = joined.canonicalize().unwrap_or(joined);
+        }
+        Ok(Self { projects })
+    }
+
+    /// The nearest [`INDEX_FILE`] in one of `roots` or a directory above it
+    pub fn find(roots: &[Root]) -> Option<PathBuf> {
+        roots.iter().find_map(|root| {
+            root.path
+                .ancestors()
+                .map(|dir| dir.join(INDEX_FILE))
+                .find(|index| index.is_file())
+        })
+    }
+
+    /// The project whose directory is `path`
+    pub fn project_at(&self, path: &Path) -> Option<&Project> {
+        self.projects.iter().find(|project| project.path == path)
+    }
+
+    /// `ranked` with the projects among `candidates` that the query mentions
+    /// moved to the front: those it names first, then those depending on a
+    /// crate it names. Mentioned projects the provider left out are added.
+    pub fn boost(
+        &self,
+        candidates: &[Candidate<'_>],
+        query: Query<'_>,
+        ranked: Vec<Ranked>,
+    ) -> Vec<Ranked> {
+        let words = words(&query.text());
+        let position = |index: usize| ranked.iter().position(|r| r.index == index);
+        let mut mentioned: Vec<(Mention, usize, usize)> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| candidate.is_dir)
+            .filter_map(|(index, candidate)| {
+                let mention = self.project_at(candidate.path)?.mention(&words)?;
+                Some((mention, position(index).unwrap_or(usize::MAX), index))
+            })
+            .collect();
+        if mentioned.is_empty() {
+            return ranked;
+        }
+        mentioned.sort();
+
+        let mut boosted: Vec<Ranked> = mentioned
+            .iter()
+            .map(|&(_, position, index)| Ranked {
+                index,
+                score: ranked.get(position).and_then(|r| r.score),
+            })
+            .collect();
+        boosted.extend(
+            ranked
+                .into_iter()
+                .filter(|r| !mentioned.iter().any(|&(_, _, index)| index == r.index)),
+        );
+        boosted
+    }
+
+    /// What the index says about the numbered `candidates`, for the
+    /// language model's prompt; `None` when none of them is indexed
+    pub fn prompt_notes(&self, candidates: &[Candidate<'_>]) -> Option<String> {
+        let mut notes = String::new();
+        for (index, candidate) in candidates.iter().enumerate() {
+            let Some(project) = self.project_at(candidate.path) else {
+                continue;
+            };
+            let status = match project.status {
+                ProjectStatus::Working => "builds",
+                ProjectStatus::Broken => "broken, does not build",
+                ProjectStatus::Unknown => "build status unknown",
+            };
+            let _ = write!(notes, "{}. Rust crate {} ({})", index, project.name, status);
+            if !project.dependencies.is_empty() {
+                let shown =
+                    &project.dependencies[..project.dependencies.len().min(PROMPT_DEPENDENCIES)];
+                let _ = write!(notes, "; depends on {}", shown.join(", "));
+            }
+            if !project.dependents.is_empty() {
+                let _ = write!(notes, "; used by {}", project.dependents.join(", "));
+            }
+            notes.push('\n');
+        }
+        (!notes.is_empty())
+            .then(|| format!("\nIndexed Rust projects among the entries:\n{}", notes))
+    }
+}
+
+/// Crate names compare with `-` and `_` as the same character, ignoring case
+fn crate_key(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+/// The words of `text` as [`crate_key`]s
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(crate_key)
+        .collect()
+}
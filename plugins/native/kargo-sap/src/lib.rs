@@ -1,8 +1,30 @@
 use anyhow::Result;
 use clap::{Arg, Command};
-use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+use kargo_plugin_api::profile::{Phase, Profiler};
+use kargo_plugin_api::{
+    BoxFuture, Example, ExecutionContext, HelpInfo, PluginCommand, ReadOnlyError,
+};
 use jwalk::WalkDir;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+pub mod embeddings;
+pub mod inventory;
+pub mod relevance;
+pub mod roots;
+pub mod schema;
+pub mod session;
+pub mod stats;
+
+use embeddings::{Candidate, SapConfig, SemanticIndex};
+use inventory::Inventory;
+use relevance::{LlmRanker, Query, RelevanceProvider};
+use roots::Root;
+use schema::SchemaFormat;
+use session::{Seen, Session, Status};
+use stats::StatsIndex;
 
 pub struct SapCommand;
 
@@ -18,9 +40,17 @@ impl PluginCommand for SapCommand {
             .about("Smart Agent Protocol - AI-enhanced directory listing for LLM agents")
             .arg(
                 Arg::new("path")
-                    .help("Path to list (defaults to current directory)")
+                    .help("Paths to list as one ranked listing (defaults to the repository root or current directory)")
                     .value_name("PATH")
                     .index(1)
+                    .num_args(1..)
+                    .action(clap::ArgAction::Append)
+            )
+            .arg(
+                Arg::new("repo-root")
+                    .long("repo-root")
+                    .help("Repository the paths belong to; labels them relative to it")
+                    .value_name("DIR")
             )
             .arg(
                 Arg::new("objective")
@@ -43,6 +73,94 @@ impl PluginCommand for SapCommand {
                     .help("Show all files (including hidden)")
                     .action(clap::ArgAction::SetTrue)
             )
+            .arg(
+                Arg::new("no-embeddings")
+                    .long("no-embeddings")
+                    .help("Don't rank by embeddings even when an embedding model is configured")
+                    .action(clap::ArgAction::SetTrue)
+            )
+            .arg(
+                Arg::new("no-llm")
+                    .long("no-llm")
+                    .help("Don't ask the configured language model to rank entries")
+                    .action(clap::ArgAction::SetTrue)
+            )
+            .arg(
+                Arg::new("inventory")
+                    .long("inventory")
+                    .help("Fleet index written by kargo-walk (defaults to the nearest index.yaml)")
+                    .value_name("FILE")
+            )
+            .arg(
+                Arg::new("no-inventory")
+                    .long("no-inventory")
+                    .help("Don't consult a fleet index")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("inventory")
+            )
+            .arg(
+                Arg::new("session")
+                    .long("session")
+                    .help("Remember the objective, context and shown files across calls under this id")
+                    .value_name("ID")
+            )
+            .arg(
+                Arg::new("show-seen")
+                    .long("show-seen")
+                    .help("Also list files the session has already shown unchanged")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("session")
+            )
+            .arg(
+                Arg::new("reset-session")
+                    .long("reset-session")
+                    .help("Start the session over, forgetting what it has shown")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("session")
+            )
+            .arg(
+                Arg::new("emit")
+                    .long("emit")
+                    .help("Print a tool schema describing sap's parameters instead of listing")
+                    .value_name("WHAT")
+                    .value_parser(["schema"])
+            )
+            .arg(
+                Arg::new("schema-format")
+                    .long("schema-format")
+                    .help("Only print the schema for Claude tool use or OpenAI function calling")
+                    .value_name("FORMAT")
+                    .value_parser(["claude", "openai"])
+                    .requires("emit")
+            )
+    }
+
+    fn help_info(&self) -> Option<HelpInfo> {
+        Some(HelpInfo {
+            category: Some("agents".to_string()),
+            examples: vec![
+                Example::new(
+                    "kargo sap src --objective \"fix the config loader\"",
+                    "List the entries below src relevant to a task",
+                ),
+                Example::new(
+                    "kargo sap --repo-root . crates/api ../shared-lib --objective \"auth\"",
+                    "Rank entries of a monorepo crate and a sibling checkout together",
+                ),
+                Example::new(
+                    "kargo sap ~/src --inventory ~/src/index.yaml --objective \"move off openssl\"",
+                    "List the fleet's crates that depend on openssl first, marking broken ones",
+                ),
+                Example::new(
+                    "kargo sap --session task-42 --objective \"add retries\"",
+                    "Start a session; later calls with --session task-42 only list new or changed files",
+                ),
+                Example::new(
+                    "kargo sap --emit schema --schema-format openai",
+                    "Print the function schema to register sap with an OpenAI agent",
+                ),
+            ],
+        })
     }
 
     fn run(&self, ctx: ExecutionContext) -> BoxFuture {
@@ -55,33 +173,164 @@ impl PluginCommand for SapCommand {
 
 impl SapCommand {
     async fn run_async(&self, ctx: ExecutionContext) -> Result<()> {
+        ctx.network.clone().install();
+        ctx.profiler.clone().install();
         // Parse arguments from the execution context
         let args: Vec<&str> = ctx.matched_args.iter().map(|s| s.as_str()).collect();
         let matches = self.clap().try_get_matches_from(args)?;
+        if matches.contains_id("emit") {
+            self.print_schema(matches.get_one::<String>("schema-format").map(String::as_str))?;
+            return Ok(());
+        }
         
-        let path = matches.get_one::<String>("path")
-            .map(|s| s.as_str())
-            .unwrap_or(".");
+        let paths: Vec<PathBuf> = matches.get_many::<String>("path")
+            .into_iter()
+            .flatten()
+            .map(PathBuf::from)
+            .collect();
+        let repo_root = matches.get_one::<String>("repo-root").map(PathBuf::from);
+        let roots = roots::resolve(&paths, repo_root.as_deref())?;
             
-        let objective = matches.get_one::<String>("objective");
-        let context = matches.get_one::<String>("context");
         let show_all = matches.get_flag("all");
-        
+        let mut session = match matches.get_one::<String>("session") {
+            Some(id) => Some(Session::open(&ctx.data_dir, id)?),
+            None => None,
+        };
+        if matches.get_flag("reset-session")
+            && let Some(session) = &mut session
+        {
+            session.reset();
+        }
+
+        // Without new ones, the session's objective and context still apply
+        let objective = matches.get_one::<String>("objective").cloned()
+            .or_else(|| session.as_ref().and_then(|s| s.objective.clone()));
+        let context = matches.get_one::<String>("context").cloned()
+            .or_else(|| session.as_ref().and_then(|s| s.context.clone()));
+        if let Some(session) = &mut session {
+            session.objective = objective.clone();
+            session.context = context.clone();
+        }
+
+        // Providers in the order they're tried; filtering by name is the
+        // last resort
+        let config = SapConfig::load(&ctx.config_dir)?;
+        let inventory = if matches.get_flag("no-inventory") {
+            None
+        } else {
+            self.inventory(
+                matches.get_one::<String>("inventory").map(PathBuf::from),
+                config.inventory.clone(),
+                &roots,
+            )?
+        };
+        let mut providers: Vec<Box<dyn RelevanceProvider>> = Vec::new();
+        if objective.is_some() {
+            if let Some(config) = config.relevance.filter(|_| !matches.get_flag("no-llm")) {
+                let mut ranker = LlmRanker::from_config(&config)?;
+                if let Some(inventory) = &inventory {
+                    ranker = ranker.with_inventory(inventory.clone());
+                }
+                providers.push(Box::new(ranker));
+            }
+            if let Some(config) = config.embeddings.filter(|_| !matches.get_flag("no-embeddings")) {
+                providers.push(Box::new(SemanticIndex::open(&config, &ctx.cache_dir)?));
+            }
+        }
+
         // Run the smart listing
-        self.smart_list(path, objective, context, show_all)?;
-        
+        let mut entries = self
+            .smart_list(
+                &roots,
+                objective.as_ref(),
+                context.as_ref(),
+                show_all,
+                &mut providers,
+                inventory.as_deref(),
+            )
+            .await?;
+        if let Some(inventory) = &inventory {
+            for entry in entries.iter_mut().filter(|e| e.is_dir) {
+                entry.project = inventory.project_at(&entry.path).map(|p| p.note());
+            }
+        }
+
+        // Display results
+        let mut stats = StatsIndex::open(&ctx.cache_dir);
+        match &mut session {
+            Some(session) => self.display_session(&entries, session, &mut stats, matches.get_flag("show-seen")),
+            None => self.display_entries(&entries.iter().map(|e| (e, "")).collect::<Vec<_>>(), &mut stats),
+        }
+        // Counting again next time is all a lost index costs
+        if let Err(e) = stats.save() {
+            log::warn!("Failed to save file stats: {:#}", e);
+        }
+
+        // A read-only run shows what is new without marking it seen
+        if let Some(session) = &session {
+            match session.save() {
+                Err(e) if e.is::<ReadOnlyError>() => log::warn!("{}", e),
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The inventory from `--inventory`, else from `sap.yaml`, else the
+    /// nearest index above `roots`. One that was asked for must load; a
+    /// found one that doesn't is some other `index.yaml`.
+    fn inventory(
+        &self,
+        requested: Option<PathBuf>,
+        configured: Option<PathBuf>,
+        roots: &[Root],
+    ) -> Result<Option<Arc<Inventory>>> {
+        if let Some(path) = requested.or(configured) {
+            return Ok(Some(Arc::new(Inventory::load(&path)?)));
+        }
+        let Some(path) = Inventory::find(roots) else {
+            return Ok(None);
+        };
+        match Inventory::load(&path) {
+            Ok(inventory) => Ok(Some(Arc::new(inventory))),
+            Err(e) => {
+                log::debug!("Not using {} as the fleet inventory: {:#}", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Print the tool schema in `format`, or in every format keyed by name
+    fn print_schema(&self, format: Option<&str>) -> Result<()> {
+        let cmd = self.clap();
+        let schema = match format.and_then(SchemaFormat::parse) {
+            Some(format) => schema::tool_schema(&cmd, format),
+            None => serde_json::json!({
+                "claude": schema::tool_schema(&cmd, SchemaFormat::Claude),
+                "openai": schema::tool_schema(&cmd, SchemaFormat::OpenAi),
+            }),
+        };
+        println!("{}", serde_json::to_string_pretty(&schema)?);
         Ok(())
     }
     
-    fn smart_list(
+    async fn smart_list(
         &self,
-        path: &str,
+        roots: &[Root],
         objective: Option<&String>,
         context: Option<&String>,
         show_all: bool,
-    ) -> Result<()> {
-        let path = Path::new(path);
-        
+        providers: &mut [Box<dyn RelevanceProvider>],
+        inventory: Option<&Inventory>,
+    ) -> Result<Vec<FileEntry>> {
+        if roots.len() > 1 {
+            println!("🗂️  Roots:");
+            for root in roots {
+                println!("  [{}] {}", root.label, root.path.display());
+            }
+            println!();
+        }
+
         // Print header with context if provided
         if objective.is_some() || context.is_some() {
             println!("🤖 Smart Agent Protocol - Focused Directory Listing");
@@ -94,17 +343,59 @@ impl SapCommand {
             println!();
         }
         
-        // For now, implement a basic smart filtering
-        // In a full implementation, this would use an LLM to analyze relevance
-        let entries = self.collect_entries(path, show_all)?;
-        let filtered = self.filter_entries(entries, objective, context);
-        
-        // Display results
-        self.display_entries(&filtered);
-        
-        Ok(())
+        let entries = {
+            let _span = Profiler::current().span(Phase::Discovery, "entries");
+            self.collect_roots(roots, show_all)?
+        };
+        let Some(objective) = objective else {
+            return Ok(entries);
+        };
+        let entries: Vec<FileEntry> = entries.into_iter().filter(|e| !is_noise(e)).collect();
+        let candidates: Vec<Candidate> = entries
+            .iter()
+            .map(|e| Candidate {
+                path: &e.path,
+                is_dir: e.is_dir,
+                seen: e.seen(),
+            })
+            .collect();
+        let query = Query {
+            objective,
+            context: context.map(String::as_str),
+        };
+        let mut ranked = relevance::rank(providers, &candidates, query).await;
+        if let Some(inventory) = inventory {
+            ranked = inventory.boost(&candidates, query, ranked);
+        }
+        Ok(ranked
+            .into_iter()
+            .map(|r| FileEntry {
+                score: r.score,
+                ..entries[r.index].clone()
+            })
+            .collect())
     }
     
+    /// The entries of every root, each listed once, labelled with their
+    /// root when there are several
+    fn collect_roots(&self, roots: &[Root], show_all: bool) -> Result<Vec<FileEntry>> {
+        let mut listed = HashSet::new();
+        let mut entries = Vec::new();
+        for root in roots {
+            for mut entry in self.collect_entries(&root.path, show_all)? {
+                // A root inside another root is listed on its own
+                if roots.iter().any(|r| r.path == entry.path) || !listed.insert(entry.path.clone()) {
+                    continue;
+                }
+                if roots.len() > 1 {
+                    entry.root = Some(root.label.clone());
+                }
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
     fn collect_entries(&self, path: &Path, show_all: bool) -> Result<Vec<FileEntry>> {
         let mut entries = Vec::new();
         
@@ -125,11 +416,18 @@ impl SapCommand {
             }
             
             let metadata = entry.metadata()?;
+            let modified = metadata.modified().ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs());
             entries.push(FileEntry {
                 name: name.to_string(),
-                path: path.to_path_buf(),
+                path: std::fs::canonicalize(&path).unwrap_or(path),
                 is_dir: metadata.is_dir(),
                 size: metadata.len(),
+                modified,
+                score: None,
+                root: None,
+                project: None,
             });
         }
         
@@ -142,51 +440,49 @@ impl SapCommand {
         Ok(entries)
     }
     
-    fn filter_entries(
+    /// List what the session hasn't shown yet, then remember all of `entries`
+    fn display_session(
         &self,
-        entries: Vec<FileEntry>,
-        objective: Option<&String>,
-        _context: Option<&String>,
-    ) -> Vec<FileEntry> {
-        // Basic smart filtering without LLM
-        // In production, this would call an LLM API to analyze relevance
-        
-        if objective.is_none() {
-            return entries;
+        entries: &[FileEntry],
+        session: &mut Session,
+        stats: &mut StatsIndex,
+        show_seen: bool,
+    ) {
+        let first = session.listings == 0;
+        let mut shown = Vec::new();
+        let mut unchanged = 0;
+        for entry in entries {
+            let note = match session.status(&entry.path, entry.seen()) {
+                Status::New if first => "",
+                Status::New => " [new]",
+                Status::Changed => " [changed]",
+                Status::Unchanged if show_seen => " [seen]",
+                Status::Unchanged => {
+                    unchanged += 1;
+                    continue;
+                }
+            };
+            shown.push((entry, note));
         }
-        
-        let _objective = objective.unwrap().to_lowercase();
-        
-        entries.into_iter().filter(|entry| {
-            let name_lower = entry.name.to_lowercase();
-            
-            // Filter out common build/cache directories
-            if entry.is_dir && matches!(name_lower.as_str(), "target" | "node_modules" | ".git" | ".cache") {
-                return false;
-            }
-            
-            // Filter out OS-specific files
-            if matches!(name_lower.as_str(), ".ds_store" | "thumbs.db") {
-                return false;
-            }
-            
-            // Basic relevance check - in real implementation, LLM would determine this
-            // For now, show source files and important configs
-            if !entry.is_dir {
-                let is_source = name_lower.ends_with(".rs") || 
-                               name_lower.ends_with(".toml") ||
-                               name_lower.ends_with(".md");
-                               
-                let is_config = matches!(name_lower.as_str(), "cargo.toml" | "config.toml" | ".env");
-                
-                return is_source || is_config;
-            }
-            
-            true
-        }).collect()
+
+        if shown.is_empty() && unchanged > 0 {
+            println!("Nothing new since the last listing.");
+        } else {
+            self.display_entries(&shown, stats);
+        }
+        if unchanged > 0 {
+            println!("{} item(s) unchanged since the last listing (--show-seen to list them)", unchanged);
+        }
+
+        for entry in entries {
+            session.record(entry.path.clone(), entry.seen());
+        }
+        session.listings += 1;
     }
-    
-    fn display_entries(&self, entries: &[FileEntry]) {
+
+    /// Files are annotated with their size, line count and estimated
+    /// tokens, so an agent can budget what it reads
+    fn display_entries(&self, entries: &[(&FileEntry, &str)], stats: &mut StatsIndex) {
         if entries.is_empty() {
             println!("No relevant files found for the given objective.");
             return;
@@ -195,28 +491,79 @@ impl SapCommand {
         println!("📁 Relevant files and directories:");
         println!();
         
-        for entry in entries {
+        let mut tokens = 0;
+        for (entry, note) in entries {
             let icon = if entry.is_dir { "📂" } else { "📄" };
             let size_str = if entry.is_dir {
                 String::new()
             } else {
-                format!(" ({})", format_size(entry.size))
+                match stats.stats(&entry.path, entry.seen()) {
+                    Some(counts) => {
+                        tokens += counts.tokens;
+                        format!(
+                            " ({}, {} lines, ~{} tokens)",
+                            format_size(entry.size),
+                            counts.lines,
+                            counts.tokens
+                        )
+                    }
+                    None => format!(" ({})", format_size(entry.size)),
+                }
             };
+            let score_str = entry.score
+                .map(|score| format!(" ~{:.0}%", score * 100.0))
+                .unwrap_or_default();
+            
+            let root_str = entry.root.as_ref()
+                .map(|root| format!("[{}] ", root))
+                .unwrap_or_default();
             
-            println!("{} {}{}", icon, entry.name, size_str);
+            let project_str = entry.project.as_ref()
+                .map(|project| format!(" [{}]", project))
+                .unwrap_or_default();
+            
+            println!("{} {}{}{}{}{}{}", icon, root_str, entry.name, project_str, size_str, score_str, note);
         }
         
         println!();
-        println!("Total: {} items", entries.len());
+        if tokens > 0 {
+            println!("Total: {} items, ~{} tokens to read every file", entries.len(), tokens);
+        } else {
+            println!("Total: {} items", entries.len());
+        }
     }
 }
 
+#[derive(Clone)]
 struct FileEntry {
     name: String,
-    #[allow(dead_code)]
-    path: std::path::PathBuf,
+    /// Absolute, so sessions recognize it from any working directory
+    path: PathBuf,
     is_dir: bool,
     size: u64,
+    modified: Option<u64>,
+    /// Relevance to the objective, when the provider scores entries
+    score: Option<f32>,
+    /// Label of the root listing it, when several were given
+    root: Option<String>,
+    /// What the fleet inventory says about it, for indexed projects
+    project: Option<String>,
+}
+
+impl FileEntry {
+    fn seen(&self) -> Seen {
+        Seen {
+            size: self.size,
+            modified: self.modified,
+        }
+    }
+}
+
+/// Build output, caches and OS clutter, never relevant
+fn is_noise(entry: &FileEntry) -> bool {
+    let name = entry.name.to_lowercase();
+    (entry.is_dir && matches!(name.as_str(), "target" | "node_modules" | ".git" | ".cache"))
+        || matches!(name.as_str(), ".ds_store" | "thumbs.db")
 }
 
 fn format_size(size: u64) -> String {
@@ -236,10 +583,11 @@ fn format_size(size: u64) -> String {
     }
 }
 
-// Plugin registration
-#[unsafe(no_mangle)]
-#[allow(improper_ctypes_definitions)]
-#[allow(unsafe_code)]
-pub extern "C" fn kargo_plugin_create() -> Box<dyn PluginCommand> {
-    Box::new(SapCommand::new())
-}
\ No newline at end of file
+// Plugin registration, with its panics caught inside the plugin
+kargo_plugin_api::export_plugin!(SapCommand::new());
+
+// Route this plugin's `log` records through the host's subscriber
+kargo_plugin_api::export_host_logger!();
+
+// Let the host check this plugin was built against an API it loads
+kargo_plugin_api::export_plugin_compat!();
@@ -0,0 +1,329 @@
+//! Deciding which entries matter for an objective
+//!
+//! A [`RelevanceProvider`] judges the listed entries against the objective
+//! and context. `sap` tries the configured providers in turn and falls back
+//! to the next one when a provider fails:
+//!
+//! 1. [`LlmRanker`], when `<kargo config dir>/sap.yaml` configures a chat
+//!    model behind an OpenAI-compatible API:
+//!
+//!    ```yaml
+//!    relevance:
+//!      url: http://localhost:11434/v1/chat/completions
+//!      model: qwen2.5-coder
+//!      api_key_env: OPENAI_API_KEY
+//!      # Entries the model scores below this are not listed
+//!      min_score: 0.5
+//!    ```
+//!
+//! 2. [`SemanticIndex`], when embeddings are configured, see
+//!    [`embeddings`](crate::embeddings)
+//! 3. [`RuleBased`], which keeps directories, sources and configs by name
+//!    and never fails
+//!
+//! When a fleet [`inventory`](crate::inventory) covers the listing, the
+//! projects the objective mentions are then moved to the front.
+
+use anyhow::{Context, Result, bail};
+use kargo_plugin_api::HttpClient;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::embeddings::{Candidate, SemanticIndex, summarize};
+use crate::inventory::Inventory;
+
+const DEFAULT_MIN_SCORE: f32 = 0.5;
+/// Characters of an entry's summary shown to the model
+const EXCERPT_CHARS: usize = 200;
+
+/// What the listing is for
+#[derive(Debug, Clone, Copy)]
+pub struct Query<'a> {
+    pub objective: &'a str,
+    pub context: Option<&'a str>,
+}
+
+impl Query<'_> {
+    /// Objective and context as one text
+    pub fn text(&self) -> String {
+        match self.context {
+            Some(context) => format!("{}\n{}", self.objective, context),
+            None => self.objective.to_string(),
+        }
+    }
+}
+
+/// A candidate judged relevant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ranked {
+    /// Position among the candidates
+    pub index: usize,
+    /// Relevance between 0 and 1, for providers that score
+    pub score: Option<f32>,
+}
+
+pub type RankFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Ranked>>> + Send + 'a>>;
+
+/// Judges which candidates matter for a query
+pub trait RelevanceProvider: Send {
+    /// Named in warnings when the provider fails
+    fn name(&self) -> &'static str;
+
+    /// The relevant candidates, most relevant first
+    fn rank<'a>(&'a mut self, candidates: &'a [Candidate<'_>], query: Query<'a>) -> RankFuture<'a>;
+}
+
+/// Rank with the first of `providers` that succeeds; [`RuleBased`] when
+/// they all fail
+pub async fn rank(
+    providers: &mut [Box<dyn RelevanceProvider>],
+    candidates: &[Candidate<'_>],
+    query: Query<'_>,
+) -> Vec<Ranked> {
+    for provider in providers.iter_mut() {
+        match provider.rank(candidates, query).await {
+            Ok(ranked) => return ranked,
+            Err(e) => log::warn!("{} unavailable, falling back: {:#}", provider.name(), e),
+        }
+    }
+    RuleBased.judge(candidates)
+}
+
+/// Keeps directories and source, manifest and documentation files
+pub struct RuleBased;
+
+impl RuleBased {
+    fn judge(&self, candidates: &[Candidate<'_>]) -> Vec<Ranked> {
+        candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| {
+                let name = candidate
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                candidate.is_dir
+                    || name.ends_with(".rs")
+                    || name.ends_with(".toml")
+                    || name.ends_with(".md")
+                    || name == ".env"
+            })
+            .map(|(index, _)| Ranked { index, score: None })
+            .collect()
+    }
+}
+
+impl RelevanceProvider for RuleBased {
+    fn name(&self) -> &'static str {
+        "Name-based filtering"
+    }
+
+    fn rank<'a>(
+        &'a mut self,
+        candidates: &'a [Candidate<'_>],
+        _query: Query<'a>,
+    ) -> RankFuture<'a> {
+        let ranked = self.judge(candidates);
+        Box::pin(async move { Ok(ranked) })
+    }
+}
+
+impl RelevanceProvider for SemanticIndex {
+    fn name(&self) -> &'static str {
+        "Semantic ranking"
+    }
+
+    fn rank<'a>(&'a mut self, candidates: &'a [Candidate<'_>], query: Query<'a>) -> RankFuture<'a> {
+        Box::pin(async move {
+            let scores = self.scores(candidates, &query.text()).await?;
+            if let Err(e) = self.save() {
+                log::warn!("Failed to save the embedding index: {:#}", e);
+            }
+            Ok(by_score(scores, self.min_score()))
+        })
+    }
+}
+
+/// `relevance:` in `sap.yaml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LlmConfig {
+    /// OpenAI-compatible chat completions endpoint
+    pub url: Option<String>,
+    pub model: Option<String>,
+    /// Environment variable holding the API key sent as a bearer token
+    pub api_key_env: Option<String>,
+    pub min_score: Option<f32>,
+}
+
+/// Asks a chat model to score every candidate in one request
+pub struct LlmRanker {
+    client: HttpClient,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    min_score: f32,
+    inventory: Option<Arc<Inventory>>,
+}
+
+impl LlmRanker {
+    pub fn from_config(config: &LlmConfig) -> Result<Self> {
+        Ok(Self {
+            client: HttpClient::new(concat!("kargo-sap/", env!("CARGO_PKG_VERSION")))?,
+            url: config.url.clone().context("relevance.url is required")?,
+            model: config
+                .model
+                .clone()
+                .context("relevance.model is required")?,
+            api_key: config
+                .api_key_env
+                .as_ref()
+                .and_then(|var| std::env::var(var).ok()),
+            min_score: config.min_score.unwrap_or(DEFAULT_MIN_SCORE),
+            inventory: None,
+        })
+    }
+
+    /// Tell the model what `inventory` knows about the entries
+    pub fn with_inventory(mut self, inventory: Arc<Inventory>) -> Self {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    async fn scores(&self, candidates: &[Candidate<'_>], query: Query<'_>) -> Result<Vec<f32>> {
+        let mut prompt = prompt(candidates, query);
+        if let Some(notes) = self
+            .inventory
+            .as_ref()
+            .and_then(|inventory| inventory.prompt_notes(candidates))
+        {
+            prompt.push_str(&notes);
+        }
+        let mut request = self.client.post(&self.url).json(&serde_json::json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": SYSTEM_PROMPT },
+                { "role": "user", "content": prompt },
+            ],
+        }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response: ChatResponse = self
+            .client
+            .send(request)
+            .await
+            .and_then(|r| r.error_for_status().map_err(Into::into))
+            .with_context(|| format!("Relevance request to {} failed", self.url))?
+            .json()
+            .await
+            .with_context(|| format!("Unexpected chat response from {}", self.url))?;
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .with_context(|| format!("{} returned no choices", self.url))?;
+        parse_scores(&content, candidates.len())
+    }
+}
+
+impl RelevanceProvider for LlmRanker {
+    fn name(&self) -> &'static str {
+        "Language model ranking"
+    }
+
+    fn rank<'a>(&'a mut self, candidates: &'a [Candidate<'_>], query: Query<'a>) -> RankFuture<'a> {
+        Box::pin(async move {
+            if candidates.is_empty() {
+                return Ok(Vec::new());
+            }
+            let scores = self.scores(candidates, query).await?;
+            Ok(by_score(scores, self.min_score))
+        })
+    }
+}
+
+const SYSTEM_PROMPT: &str = "You help a coding agent decide which files and directories to look \
+at. Score how relevant each numbered entry is to the agent's objective, from 0 (unrelated) to 1 \
+(certainly needed). Reply with only a JSON object mapping entry numbers to scores, like \
+{\"0\": 0.9, \"1\": 0.1}.";
+
+/// The objective, context and a numbered excerpt of every candidate
+pub fn prompt(candidates: &[Candidate<'_>], query: Query<'_>) -> String {
+    let mut prompt = format!("Objective: {}\n", query.objective);
+    if let Some(context) = query.context {
+        prompt.push_str(&format!("Context: {}\n", context));
+    }
+    prompt.push_str("\nEntries:\n");
+    for (index, candidate) in candidates.iter().enumerate() {
+        let excerpt: String = summarize(candidate.path, candidate.is_dir)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(EXCERPT_CHARS)
+            .collect();
+        let kind = if candidate.is_dir { "dir" } else { "file" };
+        prompt.push_str(&format!("{}. [{}] {}\n", index, kind, excerpt));
+    }
+    prompt
+}
+
+/// Scores of `count` entries from the model's reply; entries it left out
+/// score 0
+pub fn parse_scores(reply: &str, count: usize) -> Result<Vec<f32>> {
+    // Models like to wrap JSON in prose or code fences
+    let (Some(start), Some(end)) = (reply.find('{'), reply.rfind('}')) else {
+        bail!("the model did not reply with JSON: {}", reply);
+    };
+    let object: HashMap<String, f32> = serde_json::from_str(&reply[start..=end])
+        .with_context(|| format!("the model did not reply with scores: {}", reply))?;
+    let mut scores = vec![0.0; count];
+    for (key, score) in object {
+        if let Some(slot) = key
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| scores.get_mut(i))
+        {
+            *slot = score.clamp(0.0, 1.0);
+        }
+    }
+    Ok(scores)
+}
+
+/// Candidates scoring at least `min_score`, highest first
+fn by_score(scores: Vec<f32>, min_score: f32) -> Vec<Ranked> {
+    let mut ranked: Vec<Ranked> = scores
+        .into_iter()
+        .enumerate()
+        .filter(|(_, score)| *score >= min_score)
+        .map(|(index, score)| Ranked {
+            index,
+            score: Some(score),
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)));
+    ranked
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
@@ -0,0 +1,84 @@
+//! The directories one sap call lists
+//!
+//! An agent working across a monorepo and a sibling library checkout passes
+//! both, and gets one listing ranked across them. Each root is labelled so
+//! the agent can tell entries apart: relative to `--repo-root` for roots
+//! inside the repository, by directory name otherwise. The same directory
+//! given twice, or through a symlink, is listed once.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Root {
+    /// Canonical, so roots reached through different spellings compare equal
+    pub path: PathBuf,
+    pub label: String,
+}
+
+/// The roots for `paths`, in the order given; the repository root, or the
+/// current directory, when there are none
+pub fn resolve(paths: &[PathBuf], repo_root: Option<&Path>) -> Result<Vec<Root>> {
+    let repo_root = repo_root
+        .map(|root| {
+            root.canonicalize()
+                .with_context(|| format!("No repository root at {}", root.display()))
+        })
+        .transpose()?;
+    let defaults = [repo_root.clone().unwrap_or_else(|| PathBuf::from("."))];
+    let paths = if paths.is_empty() {
+        &defaults[..]
+    } else {
+        paths
+    };
+
+    let mut canonical: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("No directory at {}", path.display()))?;
+        if !canonical.contains(&path) {
+            canonical.push(path);
+        }
+    }
+
+    let labels: Vec<String> = canonical
+        .iter()
+        .map(|path| label(path, repo_root.as_deref()))
+        .collect();
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for label in &labels {
+        *uses.entry(label.as_str()).or_default() += 1;
+    }
+    // Two checkouts with the same directory name need their full paths
+    let labels: Vec<String> = labels
+        .iter()
+        .zip(&canonical)
+        .map(|(label, path)| {
+            if uses[label.as_str()] > 1 {
+                path.display().to_string()
+            } else {
+                label.clone()
+            }
+        })
+        .collect();
+
+    Ok(canonical
+        .into_iter()
+        .zip(labels)
+        .map(|(path, label)| Root { path, label })
+        .collect())
+}
+
+fn label(path: &Path, repo_root: Option<&Path>) -> String {
+    let name = || {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    };
+    match repo_root.and_then(|root| path.strip_prefix(root).ok()) {
+        Some(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+        _ => name(),
+    }
+}
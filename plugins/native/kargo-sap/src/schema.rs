@@ -0,0 +1,101 @@
+//! Tool schemas for agent frameworks
+//!
+//! `kargo sap --emit schema` describes sap as a tool an agent can call, in
+//! the format Claude tool use or OpenAI function calling expects. The
+//! parameters are generated from the clap definition, so the schema stays in
+//! step with the flags: every parameter is the flag of the same name with
+//! `-` written as `_`, and `path` is the positional argument. Repeatable
+//! arguments take arrays.
+
+use clap::{ArgAction, Command};
+use serde_json::{Map, Value, json};
+
+/// Flags that control sap's own output rather than the listing
+const META_ARGS: [&str; 4] = ["emit", "schema-format", "help", "version"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    Claude,
+    OpenAi,
+}
+
+impl SchemaFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "claude" => Some(Self::Claude),
+            "openai" => Some(Self::OpenAi),
+            _ => None,
+        }
+    }
+}
+
+/// The tool definition for `cmd` in `format`
+pub fn tool_schema(cmd: &Command, format: SchemaFormat) -> Value {
+    let name = cmd.get_name();
+    let description = cmd
+        .get_about()
+        .map(|about| about.to_string())
+        .unwrap_or_default();
+    let parameters = parameters(cmd);
+    match format {
+        SchemaFormat::Claude => json!({
+            "name": name,
+            "description": description,
+            "input_schema": parameters,
+        }),
+        SchemaFormat::OpenAi => json!({
+            "type": "function",
+            "function": {
+                "name": name,
+                "description": description,
+                "parameters": parameters,
+            },
+        }),
+    }
+}
+
+/// JSON Schema of the arguments of `cmd`
+pub fn parameters(cmd: &Command) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for arg in cmd.get_arguments() {
+        let id = arg.get_id().as_str();
+        if META_ARGS.contains(&id) || arg.is_hide_set() {
+            continue;
+        }
+        let mut property = Map::new();
+        match arg.get_action() {
+            ArgAction::SetTrue | ArgAction::SetFalse => {
+                property.insert("type".to_string(), json!("boolean"));
+            }
+            ArgAction::Append => {
+                property.insert("type".to_string(), json!("array"));
+                property.insert("items".to_string(), json!({ "type": "string" }));
+            }
+            _ => {
+                property.insert("type".to_string(), json!("string"));
+                let values: Vec<String> = arg
+                    .get_possible_values()
+                    .iter()
+                    .map(|value| value.get_name().to_string())
+                    .collect();
+                if !values.is_empty() {
+                    property.insert("enum".to_string(), json!(values));
+                }
+            }
+        }
+        if let Some(help) = arg.get_help() {
+            property.insert("description".to_string(), json!(help.to_string()));
+        }
+        let name = id.replace('-', "_");
+        if arg.is_required_set() {
+            required.push(name.clone());
+        }
+        properties.insert(name, Value::Object(property));
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
@@ -0,0 +1,94 @@
+//! Memory of a sap session across invocations
+//!
+//! With `--session <id>`, the objective, the context and every entry shown are
+//! kept in `<plugin data dir>/sessions/<id>.json`. Later calls in the same
+//! session reuse the objective and context when they aren't given again, and
+//! only list the entries that are new or changed since they were last shown.
+
+use anyhow::{Context, Result, bail};
+use kargo_plugin_api::WriteGuard;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What an entry looked like when it was shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seen {
+    pub size: u64,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: Option<u64>,
+}
+
+/// How an entry compares to what the session has already shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    New,
+    Changed,
+    Unchanged,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(skip)]
+    file: PathBuf,
+    pub objective: Option<String>,
+    pub context: Option<String>,
+    /// Listings made so far
+    pub listings: u32,
+    /// Entries shown, by absolute path
+    pub seen: BTreeMap<PathBuf, Seen>,
+}
+
+impl Session {
+    /// Load session `id` from `data_dir`, or start it
+    pub fn open(data_dir: &Path, id: &str) -> Result<Self> {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            || id.starts_with('.')
+        {
+            bail!(
+                "invalid session id '{}': use letters, digits, '-', '_' and '.'",
+                id
+            );
+        }
+        let file = data_dir.join("sessions").join(format!("{}.json", id));
+        let mut session = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Corrupt session {}", file.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", file.display())),
+        };
+        session.file = file;
+        Ok(session)
+    }
+
+    /// Forget everything but where the session is stored
+    pub fn reset(&mut self) {
+        *self = Self {
+            file: std::mem::take(&mut self.file),
+            ..Self::default()
+        };
+    }
+
+    pub fn status(&self, path: &Path, now: Seen) -> Status {
+        match self.seen.get(path) {
+            None => Status::New,
+            Some(seen) if *seen == now => Status::Unchanged,
+            Some(_) => Status::Changed,
+        }
+    }
+
+    pub fn record(&mut self, path: PathBuf, seen: Seen) {
+        self.seen.insert(path, seen);
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.file.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        guard.write(&self.file, serde_json::to_string_pretty(self)?)
+    }
+}
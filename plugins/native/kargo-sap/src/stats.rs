@@ -0,0 +1,130 @@
+//! Read budgets for listed files
+//!
+//! Every file listed is annotated with its size, its line count and an
+//! estimate of the tokens reading it costs an agent. Counting needs the
+//! contents, so it's only done for the files actually listed, and the counts
+//! are kept in `<plugin cache dir>/stats.json`: a file is only read again
+//! once its size or modification time changes.
+
+use anyhow::Result;
+use kargo_plugin_api::WriteGuard;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::session::Seen;
+
+/// Files larger than this are annotated with their size only
+const MAX_COUNTED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// What a text file costs to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStats {
+    pub lines: u64,
+    /// Estimated with [`estimate_tokens`]
+    pub tokens: u64,
+}
+
+impl FileStats {
+    /// Counts for `content`; `None` when it isn't text
+    pub fn of(content: &[u8]) -> Option<Self> {
+        if content.contains(&0) {
+            return None;
+        }
+        let text = String::from_utf8_lossy(content);
+        let newlines = content.iter().filter(|&&b| b == b'\n').count() as u64;
+        // A last line without a newline counts too
+        let partial = !content.is_empty() && !content.ends_with(b"\n");
+        Some(Self {
+            lines: newlines + u64::from(partial),
+            tokens: estimate_tokens(&text),
+        })
+    }
+}
+
+/// Tokens a language model's tokenizer splits `text` into, roughly: runs of
+/// letters and digits take a token per four characters, other symbols one
+/// each, and whitespace is folded into the tokens around it. Close to what
+/// BPE tokenizers produce for source code and prose, without shipping one.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let mut tokens = 0;
+    let mut word = 0u64;
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word += 1;
+            continue;
+        }
+        tokens += word.div_ceil(4);
+        word = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word.div_ceil(4)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredStats {
+    seen: Seen,
+    /// `None` for files that aren't text or are too large to count
+    stats: Option<FileStats>,
+}
+
+/// Counts of listed files, refreshed as they change
+#[derive(Debug, Default)]
+pub struct StatsIndex {
+    file: PathBuf,
+    entries: BTreeMap<PathBuf, StoredStats>,
+    changed: bool,
+}
+
+impl StatsIndex {
+    /// Open the index kept in `cache_dir`; an unreadable one starts over
+    pub fn open(cache_dir: &Path) -> Self {
+        let file = cache_dir.join("stats.json");
+        let entries = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            file,
+            entries,
+            changed: false,
+        }
+    }
+
+    /// Counts for the file at `path`, which looks like `seen` now; counted
+    /// unless the index has them for that version of the file
+    pub fn stats(&mut self, path: &Path, seen: Seen) -> Option<FileStats> {
+        if let Some(stored) = self.entries.get(path).filter(|s| s.seen == seen) {
+            return stored.stats;
+        }
+        let stats = if seen.size > MAX_COUNTED_BYTES {
+            None
+        } else {
+            std::fs::read(path)
+                .ok()
+                .and_then(|content| FileStats::of(&content))
+        };
+        self.entries
+            .insert(path.to_path_buf(), StoredStats { seen, stats });
+        self.changed = true;
+        stats
+    }
+
+    /// Save the counts if any were added, dropping those of files that no
+    /// longer exist
+    pub fn save(&mut self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        self.entries.retain(|path, _| path.exists());
+        let guard = WriteGuard::current();
+        if let Some(dir) = self.file.parent() {
+            guard.create_dir_all(dir)?;
+        }
+        guard.write(&self.file, serde_json::to_string(&self.entries)?)?;
+        self.changed = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use kargo_sap::embeddings::{Candidate, EmbeddingConfig, SemanticIndex, cosine};
+use kargo_sap::session::Seen;
+
+/// Embeds text mentioning "parser" as [1, 0] and anything else as [0, 1],
+/// counting its runs in `calls`
+fn config(dir: &std::path::Path) -> EmbeddingConfig {
+    let script = format!(
+        "echo run >> {}; if grep -q parser; then echo '[1, 0]'; else echo '[0, 1]'; fi",
+        dir.join("calls").display()
+    );
+    EmbeddingConfig {
+        command: Some(vec!["sh".to_string(), "-c".to_string(), script]),
+        ..Default::default()
+    }
+}
+
+fn calls(dir: &std::path::Path) -> usize {
+    std::fs::read_to_string(dir.join("calls"))
+        .unwrap_or_default()
+        .lines()
+        .count()
+}
+
+#[tokio::test]
+async fn test_entries_are_ranked_and_embedded_incrementally() {
+    let dir = tempfile::tempdir().unwrap();
+    let parser = dir.path().join("parser.rs");
+    let readme = dir.path().join("README.md");
+    std::fs::write(&parser, "// The parser").unwrap();
+    std::fs::write(&readme, "# Overview").unwrap();
+    let seen = Seen {
+        size: 13,
+        modified: Some(1),
+    };
+    let candidates = [
+        Candidate {
+            path: &parser,
+            is_dir: false,
+            seen,
+        },
+        Candidate {
+            path: &readme,
+            is_dir: false,
+            seen,
+        },
+    ];
+
+    let mut index = SemanticIndex::open(&config(dir.path()), dir.path()).unwrap();
+    let scores = index.scores(&candidates, "fix the parser").await.unwrap();
+    assert_eq!(scores, [1.0, 0.0]);
+    index.save().unwrap();
+    assert_eq!(calls(dir.path()), 3);
+
+    // Only the query is embedded again while the entries are unchanged
+    let mut index = SemanticIndex::open(&config(dir.path()), dir.path()).unwrap();
+    index.scores(&candidates, "docs").await.unwrap();
+    assert_eq!(calls(dir.path()), 4);
+    assert!((cosine(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-6);
+}
@@ -0,0 +1,141 @@
+use kargo_sap::embeddings::Candidate;
+use kargo_sap::inventory::{Inventory, ProjectStatus};
+use kargo_sap::relevance::{Query, Ranked};
+use kargo_sap::roots::Root;
+use kargo_sap::session::Seen;
+use std::path::{Path, PathBuf};
+
+const INDEX: &str = r#"- path: ./api
+  name: api-server
+  version: 0.3.0
+  project_type: Binary
+  status: Broken
+  dependencies: [axum, tokio, openssl]
+  tags: [service]
+  dependents: []
+- path: ./store
+  name: store
+  version: 1.0.0
+  project_type: Library
+  status: Working
+  dependencies: [rusqlite]
+  tags: []
+  dependents: [api-server]
+- path: ./tools
+  name: tools
+  status: Unknown
+  dependencies: [openssl]
+"#;
+
+/// A fleet with an index at its top and `docs` left out of it
+fn fleet() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let top = dir.path().canonicalize().unwrap();
+    for name in ["api", "docs", "store", "tools"] {
+        std::fs::create_dir(top.join(name)).unwrap();
+    }
+    std::fs::write(top.join("index.yaml"), INDEX).unwrap();
+    (dir, top)
+}
+
+fn candidates(paths: &[PathBuf]) -> Vec<Candidate<'_>> {
+    paths
+        .iter()
+        .map(|path| Candidate {
+            path,
+            is_dir: true,
+            seen: Seen {
+                size: 0,
+                modified: None,
+            },
+        })
+        .collect()
+}
+
+fn order(ranked: &[Ranked]) -> Vec<usize> {
+    ranked.iter().map(|r| r.index).collect()
+}
+
+#[test]
+fn test_index_is_found_above_the_roots_and_resolved_against_its_directory() {
+    let (_dir, top) = fleet();
+    let root = Root {
+        path: top.join("api"),
+        label: "api".to_string(),
+    };
+    let index = Inventory::find(&[root]).unwrap();
+    assert_eq!(index, top.join("index.yaml"));
+
+    let inventory = Inventory::load(&index).unwrap();
+    let api = inventory.project_at(&top.join("api")).unwrap();
+    assert_eq!(api.name, "api-server");
+    assert_eq!(api.status, ProjectStatus::Broken);
+    assert_eq!(api.note(), "crate api-server, broken");
+    assert_eq!(
+        inventory.project_at(&top.join("tools")).unwrap().note(),
+        "crate tools"
+    );
+    assert!(inventory.project_at(&top.join("docs")).is_none());
+
+    assert!(
+        Inventory::find(&[Root {
+            path: Path::new("/").to_path_buf(),
+            label: "/".to_string(),
+        }])
+        .is_none()
+    );
+}
+
+#[test]
+fn test_mentioned_projects_are_listed_first() {
+    let (_dir, top) = fleet();
+    let inventory = Inventory::load(&top.join("index.yaml")).unwrap();
+    let paths: Vec<PathBuf> = ["api", "docs", "store", "tools"]
+        .iter()
+        .map(|name| top.join(name))
+        .collect();
+    let candidates = candidates(&paths);
+    let ranked = vec![
+        Ranked {
+            index: 1,
+            score: Some(0.9),
+        },
+        Ranked {
+            index: 0,
+            score: Some(0.6),
+        },
+    ];
+
+    // The store by name, then the crates depending on openssl, the tools
+    // added although the provider left them out
+    let query = Query {
+        objective: "Move the Store and everything on OpenSSL to rustls",
+        context: None,
+    };
+    let boosted = inventory.boost(&candidates, query, ranked.clone());
+    assert_eq!(order(&boosted), [2, 0, 3, 1]);
+    assert_eq!(boosted[1].score, Some(0.6));
+    assert_eq!(boosted[2].score, None);
+
+    let query = Query {
+        objective: "write the release notes",
+        context: None,
+    };
+    assert_eq!(inventory.boost(&candidates, query, ranked.clone()), ranked);
+}
+
+#[test]
+fn test_the_model_is_told_what_the_index_knows() {
+    let (_dir, top) = fleet();
+    let inventory = Inventory::load(&top.join("index.yaml")).unwrap();
+    let paths = vec![top.join("docs"), top.join("api"), top.join("store")];
+
+    let notes = inventory.prompt_notes(&candidates(&paths)).unwrap();
+    assert_eq!(
+        notes,
+        "\nIndexed Rust projects among the entries:\n\
+         1. Rust crate api-server (broken, does not build); depends on axum, tokio, openssl\n\
+         2. Rust crate store (builds); depends on rusqlite; used by api-server\n"
+    );
+    assert_eq!(inventory.prompt_notes(&candidates(&paths[..1])), None);
+}
@@ -0,0 +1,153 @@
+use kargo_sap::embeddings::Candidate;
+use kargo_sap::relevance::{
+    self, LlmConfig, LlmRanker, Query, RankFuture, Ranked, RelevanceProvider, parse_scores,
+};
+use kargo_sap::session::Seen;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+/// Answers one chat completion with `reply` and returns the request body
+fn serve_chat(listener: TcpListener, reply: &'static str) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        // Read the headers, then as much body as they announce
+        let body_start = loop {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let length: usize = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .map_or(0, |value| value.trim().parse().unwrap());
+        while request.len() < body_start + length {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        let body = serde_json::json!({
+            "choices": [{ "message": { "role": "assistant", "content": reply } }]
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&request[body_start..]).into_owned()
+    })
+}
+
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let paths: Vec<PathBuf> = ["parser.rs", "logo.png", "README.md"]
+        .iter()
+        .map(|name| dir.join(name))
+        .collect();
+    for path in &paths {
+        std::fs::write(path, "// contents").unwrap();
+    }
+    paths
+}
+
+fn candidates(paths: &[PathBuf]) -> Vec<Candidate<'_>> {
+    paths
+        .iter()
+        .map(|path| Candidate {
+            path,
+            is_dir: false,
+            seen: Seen {
+                size: 11,
+                modified: Some(1),
+            },
+        })
+        .collect()
+}
+
+const QUERY: Query<'static> = Query {
+    objective: "fix the parser",
+    context: Some("tokens are dropped"),
+};
+
+#[tokio::test]
+async fn test_language_model_scores_rank_the_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let paths = files(dir.path());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = LlmConfig {
+        url: Some(format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        )),
+        model: Some("test-model".to_string()),
+        ..Default::default()
+    };
+    let server = serve_chat(
+        listener,
+        "Here you go:\n```json\n{\"0\": 0.9, \"2\": 0.6, \"1\": 0.1}\n```",
+    );
+
+    let mut ranker = LlmRanker::from_config(&config).unwrap();
+    let ranked = ranker.rank(&candidates(&paths), QUERY).await.unwrap();
+    assert_eq!(
+        ranked,
+        [
+            Ranked {
+                index: 0,
+                score: Some(0.9)
+            },
+            Ranked {
+                index: 2,
+                score: Some(0.6)
+            },
+        ]
+    );
+
+    let request: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+    assert_eq!(request["model"], "test-model");
+    let prompt = request["messages"][1]["content"].as_str().unwrap();
+    assert!(prompt.contains("Objective: fix the parser"));
+    assert!(prompt.contains("Context: tokens are dropped"));
+    assert!(prompt.contains("1. [file] "));
+}
+
+/// Fails every time, like an unreachable endpoint
+struct Unreachable;
+
+impl RelevanceProvider for Unreachable {
+    fn name(&self) -> &'static str {
+        "Unreachable"
+    }
+
+    fn rank<'a>(
+        &'a mut self,
+        _candidates: &'a [Candidate<'_>],
+        _query: Query<'a>,
+    ) -> RankFuture<'a> {
+        Box::pin(async { anyhow::bail!("connection refused") })
+    }
+}
+
+#[tokio::test]
+async fn test_failing_providers_fall_back_to_file_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let paths = files(dir.path());
+    let mut providers: Vec<Box<dyn RelevanceProvider>> = vec![Box::new(Unreachable)];
+    let ranked = relevance::rank(&mut providers, &candidates(&paths), QUERY).await;
+    let kept: Vec<usize> = ranked.iter().map(|r| r.index).collect();
+    assert_eq!(kept, [0, 2]);
+    assert!(ranked.iter().all(|r| r.score.is_none()));
+}
+
+#[test]
+fn test_scores_are_parsed_leniently() {
+    let scores = parse_scores("{\"1\": 1.5, \" 0 \": 0.25, \"7\": 0.9}", 3).unwrap();
+    assert_eq!(scores, [0.25, 1.0, 0.0]);
+    assert!(parse_scores("I can't tell", 3).is_err());
+}
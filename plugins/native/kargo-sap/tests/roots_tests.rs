@@ -0,0 +1,40 @@
+use kargo_sap::roots;
+use std::path::PathBuf;
+
+#[test]
+fn test_roots_are_labelled_and_deduplicated() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().canonicalize().unwrap();
+    let mono = base.join("mono");
+    for path in [
+        mono.join("crates/api"),
+        base.join("shared-lib"),
+        base.join("a/util"),
+        base.join("b/util"),
+    ] {
+        std::fs::create_dir_all(path).unwrap();
+    }
+
+    let roots = roots::resolve(
+        &[
+            mono.join("crates/api"),
+            mono.join("crates/../crates/api"),
+            base.join("shared-lib"),
+        ],
+        Some(&mono),
+    )
+    .unwrap();
+    let labels: Vec<&str> = roots.iter().map(|root| root.label.as_str()).collect();
+    assert_eq!(labels, ["crates/api", "shared-lib"]);
+    assert_eq!(roots[0].path, mono.join("crates/api"));
+
+    // The repository root stands in for missing paths
+    let roots = roots::resolve(&[], Some(&mono)).unwrap();
+    assert_eq!(roots[0].label, "mono");
+
+    // Checkouts sharing a directory name are told apart by full path
+    let roots = roots::resolve(&[base.join("a/util"), base.join("b/util")], None).unwrap();
+    assert_eq!(roots[1].label, base.join("b/util").display().to_string());
+
+    assert!(roots::resolve(&[PathBuf::from("/no/such/root")], None).is_err());
+}
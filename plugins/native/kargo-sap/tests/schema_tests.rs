@@ -0,0 +1,24 @@
+use kargo_plugin_api::PluginCommand;
+use kargo_sap::SapCommand;
+use kargo_sap::schema::{self, SchemaFormat};
+
+#[test]
+fn test_schemas_follow_the_clap_definition() {
+    let cmd = SapCommand::new().clap();
+
+    let claude = schema::tool_schema(&cmd, SchemaFormat::Claude);
+    assert_eq!(claude["name"], "sap");
+    let properties = &claude["input_schema"]["properties"];
+    assert_eq!(properties["objective"]["type"], "string");
+    assert_eq!(properties["no_embeddings"]["type"], "boolean");
+    assert!(properties["path"]["description"].is_string());
+    assert_eq!(properties["path"]["type"], "array");
+    assert_eq!(properties["repo_root"]["type"], "string");
+    assert!(properties.get("emit").is_none());
+    assert!(properties.get("schema_format").is_none());
+
+    let openai = schema::tool_schema(&cmd, SchemaFormat::OpenAi);
+    assert_eq!(openai["type"], "function");
+    assert_eq!(openai["function"]["name"], "sap");
+    assert_eq!(openai["function"]["parameters"], claude["input_schema"]);
+}
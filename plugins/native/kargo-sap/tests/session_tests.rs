@@ -0,0 +1,28 @@
+use kargo_sap::session::{Seen, Session, Status};
+use std::path::Path;
+
+#[test]
+fn test_session_remembers_shown_entries_across_opens() {
+    let data = tempfile::tempdir().unwrap();
+    let lib = Path::new("/work/src/lib.rs");
+    let seen = Seen {
+        size: 120,
+        modified: Some(1_700_000_000),
+    };
+
+    let mut session = Session::open(data.path(), "agent-1").unwrap();
+    assert_eq!(session.status(lib, seen), Status::New);
+    session.objective = Some("fix the parser".to_string());
+    session.record(lib.to_path_buf(), seen);
+    session.save().unwrap();
+
+    let mut session = Session::open(data.path(), "agent-1").unwrap();
+    assert_eq!(session.objective.as_deref(), Some("fix the parser"));
+    assert_eq!(session.status(lib, seen), Status::Unchanged);
+    let edited = Seen { size: 240, ..seen };
+    assert_eq!(session.status(lib, edited), Status::Changed);
+
+    session.reset();
+    assert_eq!(session.status(lib, seen), Status::New);
+    assert!(Session::open(data.path(), "../escape").is_err());
+}
@@ -0,0 +1,50 @@
+use kargo_sap::session::Seen;
+use kargo_sap::stats::{FileStats, StatsIndex, estimate_tokens};
+
+#[test]
+fn test_counts_lines_and_estimates_tokens() {
+    assert_eq!(estimate_tokens(""), 0);
+    // `fn`, `main`, `(`, `)`, `{`, `}`
+    assert_eq!(estimate_tokens("fn main() {}"), 6);
+    // 11 characters take three tokens, whitespace none
+    assert_eq!(estimate_tokens("  hello_world\n"), 3);
+    assert_eq!(
+        FileStats::of(b"one\ntwo\nthree"),
+        Some(FileStats {
+            lines: 3,
+            tokens: 4
+        })
+    );
+    assert_eq!(FileStats::of(b"one\n").unwrap().lines, 1);
+    assert_eq!(FileStats::of(b"\x7fELF\0\0"), None);
+}
+
+#[test]
+fn test_counts_are_cached_until_the_file_changes() {
+    let cache = tempfile::tempdir().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("lib.rs");
+    std::fs::write(&file, "pub fn parse() {}\n").unwrap();
+    let seen = Seen {
+        size: 18,
+        modified: Some(1_700_000_000),
+    };
+
+    let mut index = StatsIndex::open(cache.path());
+    let counted = index.stats(&file, seen).unwrap();
+    assert_eq!(counted.lines, 1);
+    index.save().unwrap();
+
+    // Not read again while it looks the same
+    std::fs::write(&file, "pub fn parse() {}\npub fn print() {}\n").unwrap();
+    let mut index = StatsIndex::open(cache.path());
+    assert_eq!(index.stats(&file, seen), Some(counted));
+    let edited = Seen { size: 36, ..seen };
+    assert_eq!(index.stats(&file, edited).unwrap().lines, 2);
+
+    // Files that are gone are dropped
+    std::fs::remove_file(&file).unwrap();
+    index.save().unwrap();
+    let saved = std::fs::read_to_string(cache.path().join("stats.json")).unwrap();
+    assert_eq!(saved, "{}");
+}
@@ -0,0 +1,62 @@
+//! End-to-end planning benchmark: full TOML parse of every manifest vs the
+//! byte-level pre-filter, over a synthetic fleet where few manifests mention
+//! the crate being upgraded.
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use kargo_upgrade::prefilter::{collect_candidates, ManifestPrefilter};
+
+const FLEET_SIZE: usize = 2_000;
+/// One in this many manifests depends on the target crate
+const HIT_EVERY: usize = 50;
+
+fn write_fleet(root: &std::path::Path) -> Vec<PathBuf> {
+    (0..FLEET_SIZE)
+        .map(|i| {
+            let dir = root.join(format!("crate-{i}"));
+            std::fs::create_dir_all(&dir).expect("Failed to create crate dir");
+            let mut manifest = format!(
+                "[package]\nname = \"crate-{i}\"\nversion = \"0.1.{i}\"\nedition = \"2021\"\n\n[dependencies]\n"
+            );
+            for dep in 0..40 {
+                manifest.push_str(&format!(
+                    "dep-{dep} = {{ version = \"1.{dep}\", features = [\"std\"] }}\n"
+                ));
+            }
+            if i % HIT_EVERY == 0 {
+                manifest.push_str("tokio = { version = \"1.40\", features = [\"full\"] }\n");
+            }
+            manifest.push_str("\n[dev-dependencies]\nassert_fs = \"1.1\"\n");
+            let path = dir.join("Cargo.toml");
+            std::fs::write(&path, manifest).expect("Failed to write manifest");
+            path
+        })
+        .collect()
+}
+
+fn planning(c: &mut Criterion) {
+    let fleet = tempfile::tempdir().expect("Failed to create temp dir");
+    let paths = write_fleet(fleet.path());
+
+    let mut group = c.benchmark_group("plan_tokio_upgrade");
+    group.sample_size(20);
+
+    let filters = [
+        ("full_parse", ManifestPrefilter::any()),
+        (
+            "prefiltered",
+            ManifestPrefilter::new(["tokio"]).expect("valid pre-filter"),
+        ),
+    ];
+    for (name, filter) in &filters {
+        group.bench_with_input(BenchmarkId::new(*name, FLEET_SIZE), filter, |b, filter| {
+            b.iter(|| collect_candidates(&paths, filter))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, planning);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+//! File backups used to roll a session's writes back
+//!
+//! The backups are taken into a [`Snapshot`], so besides rolling back a failed
+//! transaction they let `kargo restore` undo a whole run afterwards. Sessions
+//! that aren't given a snapshots directory keep theirs in a temporary one.
+
+use anyhow::{Context, Result};
+use kargo_plugin_api::snapshot::Snapshot;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Operation the upgrade plugin's snapshots are named after
+pub const OPERATION: &str = "upgrade";
+
+/// Records files before they are modified so they can be restored
+pub struct BackupManager {
+    snapshot: Snapshot,
+    /// Holds the snapshots directory when none was given
+    _scratch: Option<TempDir>,
+    /// Files backed up since the last commit, oldest first
+    pending: Vec<PathBuf>,
+}
+
+impl BackupManager {
+    /// Back files up into a new snapshot under `root`, or a temporary one
+    pub fn new(root: Option<&Path>) -> Result<Self> {
+        let (snapshot, scratch) = match root {
+            Some(root) => (Snapshot::create_in(root, OPERATION), None),
+            None => {
+                let scratch = TempDir::new().context("Failed to create backup directory")?;
+                (
+                    Snapshot::create_in(scratch.path(), OPERATION),
+                    Some(scratch),
+                )
+            }
+        };
+        Ok(Self {
+            snapshot,
+            _scratch: scratch,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// Back up `path` before it is modified
+    pub fn backup_file(&mut self, path: &Path) -> Result<()> {
+        self.snapshot.record(path)?;
+        self.pending.push(path.to_owned());
+        Ok(())
+    }
+
+    /// Keep the files backed up so far as they are; a later rollback leaves
+    /// them alone
+    pub fn commit(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Restore every file backed up since the last commit, newest first.
+    ///
+    /// Keeps going after a failed restore so one bad file doesn't leave the
+    /// others modified; the first error is returned.
+    pub fn rollback(&mut self) -> Result<()> {
+        let mut first_error = None;
+        for path in self.pending.drain(..).rev() {
+            if let Err(e) = self.snapshot.restore(&path) {
+                log::error!("Failed to restore {}: {:#}", path.display(), e);
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Picking updates by hand before they're written
+//!
+//! `kargo upgrade --interactive` plans the updates like a dry run, then lists
+//! them in a terminal checklist grouped by manifest, every update checked.
+//! The user unchecks what they don't want, one update, a whole manifest or a
+//! crate in every manifest at a time, and only the updates still checked are
+//! applied, as an [`UpgradePlan`] made of them would be.
+
+use anyhow::{bail, Result};
+use console::{style, Key, Term};
+use std::io::IsTerminal;
+
+use crate::plan::{PlannedUpdate, UpgradePlan};
+use crate::types::UpdateResult;
+
+/// Rows shown at once; the list scrolls to keep the cursor visible
+const VISIBLE: usize = 20;
+
+/// A line of the checklist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    /// The manifest at this index of the plan, standing for all its updates
+    Manifest(usize),
+    /// An update, by manifest and update index
+    Update(usize, usize),
+}
+
+/// Which updates of a plan are picked
+#[derive(Debug, Clone)]
+pub struct Checklist {
+    plan: UpgradePlan,
+    /// Parallel to the plan's manifests and their updates
+    checked: Vec<Vec<bool>>,
+}
+
+impl Checklist {
+    /// A checklist of `plan` with every update checked
+    pub fn new(plan: UpgradePlan) -> Self {
+        let checked = plan
+            .manifests
+            .iter()
+            .map(|manifest| vec![true; manifest.updates.len()])
+            .collect();
+        Self { plan, checked }
+    }
+
+    /// Each manifest followed by its updates
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (m, manifest) in self.plan.manifests.iter().enumerate() {
+            rows.push(Row::Manifest(m));
+            rows.extend((0..manifest.updates.len()).map(|u| Row::Update(m, u)));
+        }
+        rows
+    }
+
+    /// Whether the update `row` is checked, or for a manifest, all of its
+    pub fn is_checked(&self, row: Row) -> bool {
+        match row {
+            Row::Manifest(m) => self.checked[m].iter().all(|&checked| checked),
+            Row::Update(m, u) => self.checked[m][u],
+        }
+    }
+
+    /// Flip an update; a manifest is unchecked if it's all checked and
+    /// checked all over otherwise
+    pub fn toggle(&mut self, row: Row) {
+        let check = !self.is_checked(row);
+        match row {
+            Row::Manifest(m) => self.checked[m].fill(check),
+            Row::Update(m, u) => self.checked[m][u] = check,
+        }
+    }
+
+    /// Flip the crate updated at `row` in every manifest, following the row
+    /// like [`Checklist::toggle`]; does nothing on a manifest row
+    pub fn toggle_crate(&mut self, row: Row) {
+        let Row::Update(m, u) = row else {
+            return;
+        };
+        let name = crate_name(&self.plan.manifests[m].updates[u]).to_string();
+        let check = !self.checked[m][u];
+        for (manifest, checked) in self.plan.manifests.iter().zip(&mut self.checked) {
+            for (update, checked) in manifest.updates.iter().zip(checked.iter_mut()) {
+                if crate_name(update) == name {
+                    *checked = check;
+                }
+            }
+        }
+    }
+
+    /// Uncheck everything if everything is checked, check everything
+    /// otherwise
+    pub fn toggle_all(&mut self) {
+        let check = !self.checked.iter().flatten().all(|&checked| checked);
+        for checked in &mut self.checked {
+            checked.fill(check);
+        }
+    }
+
+    pub fn checked_count(&self) -> usize {
+        self.checked
+            .iter()
+            .flatten()
+            .filter(|&&checked| checked)
+            .count()
+    }
+
+    /// The plan with only the checked updates, without manifests left with
+    /// none
+    pub fn selected(&self) -> UpgradePlan {
+        let mut plan = self.plan.clone();
+        for (manifest, checked) in plan.manifests.iter_mut().zip(&self.checked) {
+            let mut checked = checked.iter();
+            manifest
+                .updates
+                .retain(|_| checked.next().copied().unwrap_or(false));
+        }
+        plan.manifests
+            .retain(|manifest| !manifest.updates.is_empty());
+        plan
+    }
+
+    /// The text of `row`, without its checkbox
+    fn label(&self, row: Row) -> String {
+        match row {
+            Row::Manifest(m) => {
+                let manifest = &self.plan.manifests[m];
+                let checked = self.checked[m].iter().filter(|&&c| c).count();
+                format!(
+                    "{} ({}/{})",
+                    manifest.path.display(),
+                    checked,
+                    manifest.updates.len()
+                )
+            }
+            Row::Update(m, u) => {
+                let update = &self.plan.manifests[m].updates[u];
+                format!("    {} {} -> {}", update.name, update.from, update.to)
+            }
+        }
+    }
+}
+
+fn crate_name(update: &PlannedUpdate) -> &str {
+    update.package.as_deref().unwrap_or(&update.name)
+}
+
+/// Let the user pick the updates of `plan` to apply. Returns the plan of the
+/// checked ones, or `None` if the user cancelled.
+pub fn pick(plan: UpgradePlan) -> Result<Option<UpgradePlan>> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        bail!("--interactive needs a terminal; use --plan-out and --apply-plan to pick updates in a file instead");
+    }
+    let term = Term::stderr();
+    let columns = usize::from(term.size().1);
+    let mut checklist = Checklist::new(plan);
+    let rows = checklist.rows();
+    let mut cursor: usize = 0;
+    let mut drawn = 0;
+    term.hide_cursor()?;
+    let picked = loop {
+        let first = cursor.saturating_sub(VISIBLE - 1);
+
+        term.clear_last_lines(drawn)?;
+        let mut lines = vec![format!(
+            "{} {}",
+            style(format!(
+                "Pick the updates to apply ({} of {})",
+                checklist.checked_count(),
+                checklist.plan.update_count()
+            ))
+            .bold(),
+            style("(Space toggles, c the crate everywhere, a all, Enter applies, Esc cancels)")
+                .dim()
+        )];
+        for (index, &row) in rows.iter().enumerate().skip(first).take(VISIBLE) {
+            let mark = if checklist.is_checked(row) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            let line = format!("{} {}", mark, checklist.label(row));
+            let line = console::truncate_str(&line, columns.saturating_sub(4), "…");
+            lines.push(match (index == cursor, row) {
+                (true, _) => format!("{} {}", style("›").cyan(), style(line).cyan().bold()),
+                (false, Row::Manifest(_)) => format!("  {}", style(line).bold()),
+                (false, Row::Update(..)) => format!("  {}", line),
+            });
+        }
+        for line in &lines {
+            term.write_line(line)?;
+        }
+        drawn = lines.len();
+
+        match term.read_key()? {
+            Key::ArrowUp | Key::Char('k') => cursor = cursor.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => cursor = (cursor + 1).min(rows.len() - 1),
+            Key::Char(' ') => checklist.toggle(rows[cursor]),
+            Key::Char('c') => checklist.toggle_crate(rows[cursor]),
+            Key::Char('a') => checklist.toggle_all(),
+            Key::Enter => break Some(checklist.selected()),
+            Key::Escape | Key::CtrlC | Key::Char('q') => break None,
+            _ => {}
+        }
+    };
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    Ok(picked)
+}
+
+/// The results of applying the picked updates, completed with what only the
+/// planning run knows about: manifests that failed to plan, and updates left
+/// out because their crate is patched or vendored
+pub fn merge(planned: Vec<UpdateResult>, mut applied: Vec<UpdateResult>) -> Vec<UpdateResult> {
+    for mut result in planned {
+        match applied.iter_mut().find(|a| a.path == result.path) {
+            Some(applied) => applied.skipped = result.skipped,
+            None if result.error.is_some() || !result.skipped.is_empty() => {
+                // Only what's reported of it; its updates weren't written
+                result.updates.clear();
+                result.patches.clear();
+                result.diff = None;
+                applied.push(result);
+            }
+            None => {}
+        }
+    }
+    applied
+}
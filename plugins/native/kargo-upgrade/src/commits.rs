@@ -0,0 +1,557 @@
+//! Git commits of the manifests an upgrade wrote
+//!
+//! [`UpgradeCommits`] commits the results of a session in batches: one
+//! commit per upgraded crate, per project or for the whole session, in each
+//! git repository the manifests belong to. Subjects come from a template
+//! that follows Conventional Commits by default, so changelog tooling picks
+//! the bumps up, and the body lists every update in the commit.
+//!
+//! Per-crate commits stage each manifest as it was after that crate's
+//! updates, without touching the working tree: a manifest bumping `serde`
+//! and `tokio` is committed with only `serde` bumped first, then with both.
+//! Moved `[patch]` entries land with the last commit of their manifest.
+//!
+//! Manifests inside a git submodule are committed in the submodule. Its
+//! superproject then gets a commit moving the submodule pointer to the new
+//! commits, unless [`UpgradeCommits::leave_superprojects`] is set.
+
+use anyhow::{anyhow, bail, Context, Result};
+use kargo_plugin_api::WriteGuard;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::models::{DependencySource, DependencyUpdate, DependencyWriter};
+use crate::session::workspace_root;
+use crate::types::UpdateResult;
+use crate::writers::CargoWriter;
+
+/// How the manifests an upgrade wrote are grouped into commits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitBatching {
+    /// One commit per upgraded crate, across every manifest depending on it
+    Crate,
+    /// One commit per workspace, or per package outside a workspace
+    Project,
+    /// One commit for the whole upgrade
+    #[default]
+    Session,
+}
+
+impl FromStr for CommitBatching {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "crate" => Ok(Self::Crate),
+            "project" => Ok(Self::Project),
+            "session" => Ok(Self::Session),
+            other => bail!("expected crate, project or session, got '{}'", other),
+        }
+    }
+}
+
+impl CommitBatching {
+    /// Subject template used when none is configured
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            Self::Crate => "chore(deps): bump {crate} from {from} to {to}",
+            Self::Project => "chore(deps): update {count} dependencies in {project}",
+            Self::Session => "chore(deps): update {count} dependencies",
+        }
+    }
+}
+
+/// A commit made for an upgrade
+#[derive(Debug, Clone)]
+pub struct UpgradeCommit {
+    pub repository: PathBuf,
+    /// Abbreviated commit hash
+    pub id: String,
+    pub message: String,
+}
+
+impl UpgradeCommit {
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Commits the manifests an upgrade session wrote
+#[derive(Debug, Clone, Default)]
+pub struct UpgradeCommits {
+    batching: CommitBatching,
+    template: Option<String>,
+    leave_superprojects: bool,
+}
+
+/// A manifest that was written, with where it belongs
+struct Written<'a> {
+    result: &'a UpdateResult,
+    repository: PathBuf,
+    /// Path relative to the repository
+    relative: PathBuf,
+    project: PathBuf,
+}
+
+/// What a commit stages for one manifest
+enum Staged {
+    /// The manifest as it is on disk
+    Final(PathBuf),
+    /// Intermediate content, at a path relative to the repository
+    Content(PathBuf, String),
+}
+
+#[derive(Default)]
+struct Batch<'a> {
+    crate_name: Option<String>,
+    project: PathBuf,
+    updates: Vec<(&'a Written<'a>, &'a DependencyUpdate)>,
+    files: Vec<Staged>,
+}
+
+impl UpgradeCommits {
+    pub fn new(batching: CommitBatching) -> Self {
+        Self {
+            batching,
+            template: None,
+            leave_superprojects: false,
+        }
+    }
+
+    /// Build subjects from `template` instead of the batching's default.
+    /// `{crate}`, `{from}` and `{to}` name the bumped crate (per-crate
+    /// batching only), `{project}` the project and `{count}` the number of
+    /// updates in the commit.
+    pub fn with_template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    /// Don't commit the moved pointers of submodules that got commits in
+    /// their superprojects, leaving them for the user
+    pub fn leave_superprojects(mut self, leave: bool) -> Self {
+        self.leave_superprojects = leave;
+        self
+    }
+
+    /// Fail unless the repositories holding `manifests` have nothing staged
+    /// and the manifests themselves no uncommitted changes, so the upgrade
+    /// commits contain the upgrade only
+    pub async fn check_clean(manifests: &[PathBuf]) -> Result<()> {
+        let mut repositories: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        let mut known = HashMap::new();
+        for manifest in manifests {
+            if let Some((repository, relative)) = locate(manifest, &mut known).await {
+                repositories.entry(repository).or_default().push(relative);
+            }
+        }
+        // Superprojects get commits of the moved submodule pointers
+        let mut staged_checked: Vec<PathBuf> = repositories.keys().cloned().collect();
+        let mut pending = staged_checked.clone();
+        while let Some(repository) = pending.pop() {
+            if let Some(superproject) = superproject(&repository).await {
+                if !staged_checked.contains(&superproject) {
+                    staged_checked.push(superproject.clone());
+                    pending.push(superproject);
+                }
+            }
+        }
+        for repository in &staged_checked {
+            if !git(repository, &["diff", "--cached", "--name-only"], None)
+                .await?
+                .trim()
+                .is_empty()
+            {
+                bail!(
+                    "{} has staged changes; commit or unstage them before committing an upgrade",
+                    repository.display()
+                );
+            }
+        }
+        for (repository, manifests) in repositories {
+            let mut args = vec!["status", "--porcelain", "--untracked-files=no", "--"];
+            let paths: Vec<String> = manifests
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect();
+            args.extend(paths.iter().map(String::as_str));
+            let dirty = git(&repository, &args, None).await?;
+            if !dirty.trim().is_empty() {
+                let files: Vec<&str> = dirty
+                    .lines()
+                    .map(|l| l.get(3..).unwrap_or(l).trim())
+                    .collect();
+                bail!(
+                    "Uncommitted changes to {} in {}; commit or stash them before committing an upgrade",
+                    files.join(", "),
+                    repository.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Commit the manifests written in `results`, skipping failed ones and
+    /// those outside a git repository
+    pub async fn commit(&self, results: &[UpdateResult]) -> Result<Vec<UpgradeCommit>> {
+        let mut known = HashMap::new();
+        let mut workspaces = HashMap::new();
+        let mut written = Vec::new();
+        for result in results {
+            if result.error.is_some() || (result.updates.is_empty() && result.patches.is_empty()) {
+                continue;
+            }
+            let Some((repository, relative)) = locate(&result.path, &mut known).await else {
+                log::warn!(
+                    "{} is not in a git repository; not committing it",
+                    result.path.display()
+                );
+                continue;
+            };
+            let project = workspace_root(&result.path, &mut workspaces)
+                .or_else(|| result.path.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            // Canonical, like the repository it's shown relative to
+            let project = project.canonicalize().unwrap_or(project);
+            written.push(Written {
+                result,
+                repository,
+                relative,
+                project,
+            });
+        }
+
+        let mut batches = self.batches(&written).await;
+        let mut repositories: Vec<PathBuf> = batches.keys().cloned().collect();
+        let mut superprojects: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+        if !self.leave_superprojects {
+            let mut pending = repositories.clone();
+            while let Some(repository) = pending.pop() {
+                if superprojects.contains_key(&repository) {
+                    continue;
+                }
+                let superproject = superproject(&repository).await;
+                if let Some(superproject) = &superproject {
+                    if !repositories.contains(superproject) {
+                        repositories.push(superproject.clone());
+                        pending.push(superproject.clone());
+                    }
+                }
+                superprojects.insert(repository, superproject);
+            }
+        }
+        // Submodules first, so their superprojects can commit the new pointers
+        repositories.sort_by_key(|repository| Reverse(repository.components().count()));
+
+        let mut commits = Vec::new();
+        let mut moved: HashMap<PathBuf, Vec<(PathBuf, Vec<UpgradeCommit>)>> = HashMap::new();
+        for repository in repositories {
+            WriteGuard::current().check(&repository)?;
+            let mut made = Vec::new();
+            if let Some(submodules) = moved.remove(&repository) {
+                made.push(commit_pointers(&repository, &submodules).await?);
+            }
+            for batch in batches.remove(&repository).unwrap_or_default() {
+                let message = self.message(&batch, &repository);
+                made.push(commit(&repository, &batch.files, message).await?);
+            }
+            if let Some(Some(superproject)) = superprojects.get(&repository) {
+                if !made.is_empty() {
+                    moved
+                        .entry(superproject.clone())
+                        .or_default()
+                        .push((repository.clone(), made.clone()));
+                }
+            }
+            commits.extend(made);
+        }
+        Ok(commits)
+    }
+
+    /// The commits to make in each repository, in order
+    async fn batches<'a>(&self, written: &'a [Written<'a>]) -> BTreeMap<PathBuf, Vec<Batch<'a>>> {
+        let mut batches: BTreeMap<PathBuf, BTreeMap<String, Batch<'a>>> = BTreeMap::new();
+        for manifest in written {
+            let repository = batches.entry(manifest.repository.clone()).or_default();
+            match self.batching {
+                CommitBatching::Session | CommitBatching::Project => {
+                    let key = match self.batching {
+                        CommitBatching::Project => manifest.project.to_string_lossy().into_owned(),
+                        _ => String::new(),
+                    };
+                    let batch = repository.entry(key).or_insert_with(|| Batch {
+                        project: manifest.project.clone(),
+                        ..Batch::default()
+                    });
+                    batch
+                        .updates
+                        .extend(manifest.result.updates.iter().map(|u| (manifest, u)));
+                    batch
+                        .files
+                        .push(Staged::Final(manifest.result.path.clone()));
+                }
+                CommitBatching::Crate => {
+                    let crates = touched_crates(manifest.result);
+                    let base = match git(
+                        &manifest.repository,
+                        &["show", &format!("HEAD:{}", manifest.relative.display())],
+                        None,
+                    )
+                    .await
+                    {
+                        Ok(base) => Some(base),
+                        Err(e) => {
+                            log::debug!("No committed {}: {:#}", manifest.relative.display(), e);
+                            None
+                        }
+                    };
+                    for (i, name) in crates.iter().enumerate() {
+                        let batch = repository.entry(name.clone()).or_insert_with(|| Batch {
+                            crate_name: Some(name.clone()),
+                            project: manifest.project.clone(),
+                            ..Batch::default()
+                        });
+                        batch.updates.extend(
+                            manifest
+                                .result
+                                .updates
+                                .iter()
+                                .filter(|u| u.dependency.package_name() == name)
+                                .map(|u| (manifest, u)),
+                        );
+                        if i + 1 == crates.len() {
+                            batch
+                                .files
+                                .push(Staged::Final(manifest.result.path.clone()));
+                        } else if let Some(base) = &base {
+                            match intermediate(manifest, base, &crates[..=i]) {
+                                Ok(content) => batch
+                                    .files
+                                    .push(Staged::Content(manifest.relative.clone(), content)),
+                                Err(e) => log::debug!(
+                                    "Committing {} with its last crate: {:#}",
+                                    manifest.relative.display(),
+                                    e
+                                ),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        batches
+            .into_iter()
+            .map(|(repository, batches)| (repository, batches.into_values().collect()))
+            .collect()
+    }
+
+    fn message(&self, batch: &Batch, repository: &Path) -> String {
+        let distinct = |version: fn(&DependencyUpdate) -> &str| {
+            let versions: BTreeSet<&str> = batch.updates.iter().map(|(_, u)| version(u)).collect();
+            versions.into_iter().collect::<Vec<_>>().join(", ")
+        };
+        let project = match batch.project.strip_prefix(repository) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+            _ => repository
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        let template = self
+            .template
+            .as_deref()
+            .unwrap_or(self.batching.default_template());
+        let subject = template
+            .replace("{crate}", batch.crate_name.as_deref().unwrap_or_default())
+            .replace("{from}", &distinct(|u| &u.from_version))
+            .replace("{to}", &distinct(|u| &u.to_version))
+            .replace("{project}", &project)
+            .replace("{count}", &batch.updates.len().to_string());
+
+        let mut body = Vec::new();
+        for (manifest, update) in &batch.updates {
+            body.push(format!(
+                "- {} {} -> {} ({})",
+                update.dependency.package_name(),
+                update.from_version,
+                update.to_version,
+                manifest.relative.display()
+            ));
+        }
+        if body.is_empty() {
+            return subject;
+        }
+        format!("{}\n\n{}\n", subject, body.join("\n"))
+    }
+}
+
+/// The repository enclosing `manifest` and the manifest's path inside it
+async fn locate(
+    manifest: &Path,
+    known: &mut HashMap<PathBuf, Option<PathBuf>>,
+) -> Option<(PathBuf, PathBuf)> {
+    let manifest = manifest.canonicalize().ok()?;
+    let dir = manifest.parent()?.to_path_buf();
+    if !known.contains_key(&dir) {
+        let toplevel = git(&dir, &["rev-parse", "--show-toplevel"], None)
+            .await
+            .ok()
+            .map(|out| PathBuf::from(out.trim()));
+        known.insert(dir.clone(), toplevel);
+    }
+    let repository = known.get(&dir)?.clone()?;
+    let relative = manifest.strip_prefix(&repository).ok()?.to_path_buf();
+    Some((repository, relative))
+}
+
+/// The superproject of `repository` when it is a submodule checkout
+async fn superproject(repository: &Path) -> Option<PathBuf> {
+    let out = git(
+        repository,
+        &["rev-parse", "--show-superproject-working-tree"],
+        None,
+    )
+    .await
+    .ok()?;
+    let out = out.trim();
+    (!out.is_empty()).then(|| PathBuf::from(out))
+}
+
+/// Crates updated or patched in a manifest, in commit order
+fn touched_crates(result: &UpdateResult) -> Vec<String> {
+    let crates: BTreeSet<String> = result
+        .updates
+        .iter()
+        .map(|u| u.dependency.package_name().to_string())
+        .chain(result.patches.iter().map(|p| p.patch.name.clone()))
+        .collect();
+    crates.into_iter().collect()
+}
+
+/// The committed `base` of a manifest with the updates of `crates` applied
+fn intermediate(manifest: &Written, base: &str, crates: &[String]) -> Result<String> {
+    let path = manifest.result.path.clone();
+    let content = base.to_string();
+    let mut source = if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+        DependencySource::CargoToml {
+            path,
+            is_workspace: content.contains("[workspace]"),
+            content,
+        }
+    } else {
+        DependencySource::RustScript { path, content }
+    };
+    let updates: Vec<DependencyUpdate> = manifest
+        .result
+        .updates
+        .iter()
+        .filter(|u| crates.iter().any(|c| c == u.dependency.package_name()))
+        .cloned()
+        .collect();
+    CargoWriter.apply_updates(&mut source, &updates)?;
+    Ok(source.content().to_string())
+}
+
+/// Stage `files` in `repository` and commit them with `message`
+async fn commit(repository: &Path, files: &[Staged], message: String) -> Result<UpgradeCommit> {
+    for file in files {
+        match file {
+            Staged::Final(path) => {
+                let path = path.to_string_lossy();
+                git(repository, &["add", "--", &path], None).await?;
+            }
+            Staged::Content(relative, content) => {
+                let blob =
+                    git(repository, &["hash-object", "-w", "--stdin"], Some(content)).await?;
+                let info = format!("100644,{},{}", blob.trim(), relative.display());
+                git(
+                    repository,
+                    &["update-index", "--add", "--cacheinfo", &info],
+                    None,
+                )
+                .await?;
+            }
+        }
+    }
+    git(
+        repository,
+        &["commit", "--quiet", "--file", "-"],
+        Some(&message),
+    )
+    .await?;
+    let id = git(repository, &["rev-parse", "--short", "HEAD"], None).await?;
+    Ok(UpgradeCommit {
+        repository: repository.to_path_buf(),
+        id: id.trim().to_string(),
+        message,
+    })
+}
+
+/// Commit the pointers of `submodules`, moved by the commits made in them,
+/// in their `superproject`
+async fn commit_pointers(
+    superproject: &Path,
+    submodules: &[(PathBuf, Vec<UpgradeCommit>)],
+) -> Result<UpgradeCommit> {
+    let mut files = Vec::new();
+    let mut names = Vec::new();
+    let mut body = Vec::new();
+    for (submodule, commits) in submodules {
+        let relative = submodule.strip_prefix(superproject).unwrap_or(submodule);
+        files.push(Staged::Final(submodule.clone()));
+        names.push(relative.display().to_string());
+        for commit in commits {
+            body.push(format!(
+                "- {}: {} {}",
+                relative.display(),
+                commit.id,
+                commit.subject()
+            ));
+        }
+    }
+    names.sort();
+    let subject = match names.as_slice() {
+        [name] => format!("chore(deps): update submodule {}", name),
+        _ => format!("chore(deps): update submodules {}", names.join(", ")),
+    };
+    commit(
+        superproject,
+        &files,
+        format!("{}\n\n{}\n", subject, body.join("\n")),
+    )
+    .await
+}
+
+/// Run git in `dir`, feeding it `input`, and return its output
+async fn git(dir: &Path, args: &[&str], input: Option<&str>) -> Result<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run git {}", args.join(" ")))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
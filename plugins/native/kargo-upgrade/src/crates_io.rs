@@ -1,20 +1,15 @@
-//! Crates.io API client for querying the latest versions of crates
+//! Crates.io client for querying the latest versions of crates
+//!
+//! Lookups go through the sparse registry index (see [`crate::registry`]),
+//! which is cached and shared across the whole run.
 
-use anyhow::{anyhow, Result};
-use once_cell::sync::Lazy;
-use reqwest::Client;
-use serde_json::Value;
+use anyhow::Result;
+use kargo_plugin_api::HttpClient;
+use serde::Deserialize;
 
-/// Shared HTTP client for crates.io API requests
-static CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .user_agent("krater/version-up2date")
-        .build()
-        .unwrap_or_else(|e| {
-            log::error!("Failed to create HTTP client: {}", e);
-            panic!("Critical error: Failed to create HTTP client: {}", e);
-        })
-});
+use std::sync::Arc;
+
+use crate::registry::{crates_io_index, IndexVersion};
 
 /// Get the latest version of a crate from crates.io
 /// Returns a Future that resolves to the latest version
@@ -25,6 +20,39 @@ pub async fn get_latest_version(crate_name: &str) -> Result<Option<String>> {
     future.fetch().await
 }
 
+/// All published versions of a crate, or `None` if the registry doesn't know it
+pub async fn get_versions(crate_name: &str) -> Result<Option<Arc<Vec<IndexVersion>>>> {
+    crates_io_index().versions(crate_name).await
+}
+
+/// Warm the index cache for every crate in `crate_names` concurrently
+pub async fn prefetch_versions<I, S>(crate_names: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    crates_io_index().prefetch(crate_names).await
+}
+
+/// Repository URL a crate declares on crates.io, if any
+pub async fn get_repository(client: &HttpClient, crate_name: &str) -> Result<Option<String>> {
+    #[derive(Deserialize)]
+    struct CrateResponse {
+        #[serde(rename = "crate")]
+        krate: CrateInfo,
+    }
+    #[derive(Deserialize)]
+    struct CrateInfo {
+        repository: Option<String>,
+    }
+
+    let response = client
+        .send(client.get(format!("https://crates.io/api/v1/crates/{}", crate_name)))
+        .await?
+        .error_for_status()?;
+    Ok(response.json::<CrateResponse>().await?.krate.repository)
+}
+
 /// Domain-specific type for fetching a crate version
 pub struct VersionFuture {
     crate_name: String,
@@ -33,32 +61,6 @@ pub struct VersionFuture {
 impl VersionFuture {
     /// Internal method that performs the actual async work
     pub fn fetch(self) -> impl std::future::Future<Output = Result<Option<String>>> + Send {
-        async move {
-            // Query crates.io API
-            let url = format!("https://crates.io/api/v1/crates/{}", self.crate_name);
-
-            match CLIENT.get(&url).send().await {
-                Ok(response) => {
-                    if !response.status().is_success() {
-                        return Ok(None);
-                    }
-
-                    match response.json::<Value>().await {
-                        Ok(data) => {
-                            // Extract the latest version
-                            let version = data
-                                .get("crate")
-                                .and_then(|c| c.get("max_version"))
-                                .and_then(|v| v.as_str())
-                                .map(|s| s.to_string());
-
-                            Ok(version)
-                        }
-                        Err(_) => Ok(None),
-                    }
-                }
-                Err(e) => Err(anyhow!("Failed to query crates.io: {}", e)),
-            }
-        }
+        async move { crates_io_index().latest_version(&self.crate_name).await }
     }
 }
@@ -0,0 +1,96 @@
+//! Unified diffs of the manifests an upgrade rewrites
+//!
+//! `kargo upgrade --diff` shows exactly what each manifest write changes, as
+//! a unified diff of the planned content against the file as it was read.
+//! Diffs are made before anything is written, so a dry run shows them too.
+//! They go to stdout (`--diff` or `--diff -`), into the `--pr-body-out`
+//! description (`--diff report`), or into one `.patch` file per manifest
+//! below a directory (`--diff DIR`), mirroring the manifest's path relative
+//! to the session root. Paths in the headers are relative to the root as
+//! well, so every diff applies with `git apply` or `patch -p1` from there.
+
+use anyhow::Result;
+use kargo_plugin_api::WriteGuard;
+use similar::TextDiff;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// Where `--diff` sends the diffs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffOutput {
+    Stdout,
+    /// Into the pull request description
+    Report,
+    /// One `.patch` file per manifest below the directory
+    Dir(PathBuf),
+}
+
+impl FromStr for DiffOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("expected -, report or a directory".to_string()),
+            "-" | "stdout" => Ok(Self::Stdout),
+            "report" => Ok(Self::Report),
+            dir => Ok(Self::Dir(PathBuf::from(dir))),
+        }
+    }
+}
+
+/// A manifest as read and as it is (or would be) written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestDiff {
+    pub path: PathBuf,
+    pub before: String,
+    pub after: String,
+}
+
+impl ManifestDiff {
+    /// The change as a unified diff, with paths relative to `root`; empty if
+    /// nothing changed
+    pub fn unified(&self, root: &Path) -> String {
+        let path = self.relative(root);
+        TextDiff::from_lines(&self.before, &self.after)
+            .unified_diff()
+            .context_radius(3)
+            .header(
+                &format!("a/{}", path.display()),
+                &format!("b/{}", path.display()),
+            )
+            .to_string()
+    }
+
+    /// The manifest's path relative to `root`; a manifest outside it keeps
+    /// its path without the root or prefix
+    pub fn relative(&self, root: &Path) -> PathBuf {
+        self.path
+            .strip_prefix(root)
+            .unwrap_or(&self.path)
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect()
+    }
+
+    /// `<dir>/<path relative to root>.patch`
+    pub fn patch_path(&self, root: &Path, dir: &Path) -> PathBuf {
+        let mut name = self.relative(root).into_os_string();
+        name.push(".patch");
+        dir.join(name)
+    }
+}
+
+/// Write one `.patch` file per diff below `dir`, returning the files written
+pub fn write_patches(diffs: &[&ManifestDiff], root: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let guard = WriteGuard::current();
+    let mut written = Vec::new();
+    for diff in diffs {
+        let path = diff.patch_path(root, dir);
+        if let Some(parent) = path.parent() {
+            guard.create_dir_all(parent)?;
+        }
+        guard.write(&path, diff.unified(root))?;
+        written.push(path);
+    }
+    Ok(written)
+}
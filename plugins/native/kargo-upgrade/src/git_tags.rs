@@ -0,0 +1,159 @@
+//! Version tags of git dependencies
+//!
+//! A dependency taken from git with `tag = "v1.2.0"`, or pinned to the commit
+//! of a release with `rev = "..."`, is brought up to date by moving the pin to
+//! the newest version tag of its repository. The tags are listed with
+//! `git ls-remote`, once per repository and run, so the user's git
+//! credentials and URL rewrites apply as they do for cargo.
+
+use anyhow::{bail, Context, Result};
+use kargo_plugin_api::{NetworkConfig, OfflineError};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use semver::Version;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::registry::IndexVersion;
+use crate::update_policy::UpdatePolicy;
+
+/// A version embedded in a git tag, e.g. `v1.2.3` or `serde-1.2.3`
+pub(crate) static TAG_VERSION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?").unwrap());
+
+/// A repository's tags, listed once by whichever caller gets there first
+type Listing = Arc<tokio::sync::OnceCell<Vec<GitTag>>>;
+
+/// Tags listed in this run, by repository URL
+static LISTED: Lazy<Mutex<HashMap<String, Listing>>> = Lazy::new(Default::default);
+
+/// A tag of a remote repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitTag {
+    pub name: String,
+    /// The commit the tag points to, through annotated tags
+    pub commit: String,
+}
+
+/// The crate a tag names, empty when it names none, and the version it marks:
+/// `("", 1.2.3)` for `v1.2.3` or `1.2.3`, `("serde", 1.2.3)` for
+/// `serde-v1.2.3` or `serde/1.2.3`
+pub fn parse_tag(tag: &str) -> Option<(&str, Version)> {
+    let found = TAG_VERSION
+        .find(tag)
+        .filter(|found| found.end() == tag.len())?;
+    let version = Version::parse(found.as_str()).ok()?;
+    let prefix = &tag[..found.start()];
+    let prefix = prefix
+        .strip_suffix('v')
+        .unwrap_or(prefix)
+        .trim_end_matches(['-', '_', '@', '/']);
+    Some((prefix, version))
+}
+
+/// The tags of `url`, listed once per run
+pub async fn list_tags(url: &str) -> Result<Vec<GitTag>> {
+    let listing = LISTED
+        .lock()
+        .expect("tag listing memo poisoned")
+        .entry(url.to_string())
+        .or_default()
+        .clone();
+    listing.get_or_try_init(|| ls_remote(url)).await.cloned()
+}
+
+async fn ls_remote(url: &str) -> Result<Vec<GitTag>> {
+    if NetworkConfig::current().offline {
+        return Err(OfflineError {
+            url: url.to_string(),
+        }
+        .into());
+    }
+    let output = tokio::process::Command::new("git")
+        .args(["ls-remote", "--tags", url])
+        // Fail instead of waiting for a password nobody is there to type
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .with_context(|| format!("Failed to run git ls-remote {}", url))?;
+    if !output.status.success() {
+        bail!(
+            "git ls-remote {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_ls_remote(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The tags in the output of `git ls-remote --tags`, each with the commit it
+/// points to: annotated tags are listed twice, the second time peeled
+/// (`refs/tags/v1.0.0^{}`) to their commit
+pub fn parse_ls_remote(output: &str) -> Vec<GitTag> {
+    let mut tags: Vec<GitTag> = Vec::new();
+    for line in output.lines() {
+        let Some((object, reference)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some(name) = reference.strip_prefix("refs/tags/") else {
+            continue;
+        };
+        match name.strip_suffix("^{}") {
+            Some(name) => match tags.iter_mut().find(|tag| tag.name == name) {
+                Some(tag) => tag.commit = object.to_string(),
+                None => tags.push(GitTag {
+                    name: name.to_string(),
+                    commit: object.to_string(),
+                }),
+            },
+            None => tags.push(GitTag {
+                name: name.to_string(),
+                commit: object.to_string(),
+            }),
+        }
+    }
+    tags
+}
+
+/// The tag `policy` moves `package` to from the tag or commit `current`, if
+/// it moves at all. Only the tags naming `package` count, or those naming no
+/// crate when there are none, and a commit no tag points to is left alone:
+/// it can't be told whether it's ahead of the newest release.
+pub fn select_tag<'a>(
+    tags: &'a [GitTag],
+    package: &str,
+    current: &str,
+    policy: UpdatePolicy,
+) -> Option<&'a GitTag> {
+    let parsed: Vec<(&str, Version, &GitTag)> = tags
+        .iter()
+        .filter_map(|tag| {
+            let (prefix, version) = parse_tag(&tag.name)?;
+            Some((prefix, version, tag))
+        })
+        .collect();
+    // Repositories releasing several crates tag each with its name
+    let named = parsed.iter().any(|(prefix, _, _)| *prefix == package);
+    let versions: Vec<(Version, &GitTag)> = parsed
+        .into_iter()
+        .filter(|(prefix, _, _)| *prefix == if named { package } else { "" })
+        .map(|(_, version, tag)| (version, tag))
+        .collect();
+    let (base, _) = versions.iter().find(|(_, tag)| {
+        tag.name == current || (current.len() >= 7 && tag.commit.starts_with(current))
+    })?;
+    let published: Vec<IndexVersion> = versions
+        .iter()
+        .map(|(version, _)| IndexVersion {
+            name: package.to_string(),
+            vers: version.to_string(),
+            yanked: false,
+            rust_version: None,
+        })
+        .collect();
+    let selected = Version::parse(&policy.select(&base.to_string(), &published)?).ok()?;
+    versions
+        .iter()
+        .find(|(version, _)| *version == selected)
+        .map(|(_, tag)| *tag)
+}
@@ -1,8 +1,25 @@
+pub mod backup;
+pub mod checklist;
+pub mod commits;
 pub mod crates_io;
+pub mod diff;
 pub mod finder;
+pub mod git_tags;
+pub mod migrate;
 pub mod models;
+pub mod overrides;
 pub mod parsers;
+pub mod plan;
+pub mod plugin;
+pub mod pr_body;
+pub mod prefilter;
+pub mod registry;
+pub mod release_notes;
+pub mod session;
+pub mod submodules;
 pub mod types;
+pub mod update_policy;
 pub mod updater;
 pub mod updaters;
+pub mod version_bump;
 pub mod writers;
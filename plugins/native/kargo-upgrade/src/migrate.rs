@@ -0,0 +1,492 @@
+//! Migration assistant for breaking dependency bumps
+//!
+//! After a session applies major bumps, the assistant runs `cargo check` in
+//! every affected project, keeps the compiler errors that mention a bumped
+//! crate and writes them to a per-project breakage report together with the
+//! crate's published migration notes; everything cargo printed is kept in the
+//! project's `.kargo/runs/` and referenced from the report. Known breakages
+//! can be fixed automatically with configured rewrite rules:
+//!
+//! ```yaml
+//! rules:
+//!   - crate: rand
+//!     from: "0.8"
+//!     to: "0.9"
+//!     find: 'rand::thread_rng\(\)'
+//!     replace: "rand::rng()"
+//!     description: thread_rng was renamed to rng
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use kargo_plugin_api::artifacts::{CapturedOutput, RunArtifacts};
+use kargo_plugin_api::cargo_runner::{CargoCommand, CargoRunner, ProcessRunner};
+use kargo_plugin_api::{HttpClient, WriteGuard};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::crates_io::get_repository;
+use crate::release_notes::{github_raw_base, sections_between};
+use crate::session::workspace_root;
+use crate::types::UpdateResult;
+
+/// Report file written into each project's target directory
+pub const BREAKAGE_REPORT_PATH: &str = "target/kargo/breakage-report.json";
+
+/// Changelog files tried, in order, when looking for migration notes
+const NOTES_FILES: [&str; 3] = ["MIGRATION.md", "UPGRADING.md", "CHANGELOG.md"];
+
+/// A configured source rewrite for a known breaking change
+#[derive(Debug, Clone, Deserialize)]
+pub struct MigrationRule {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Only apply when upgrading from a version starting with this prefix
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Only apply when upgrading to a version starting with this prefix
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Regular expression matched against affected source files
+    pub find: String,
+    /// Replacement, which may refer to capture groups as `$1`
+    pub replace: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl MigrationRule {
+    fn applies_to(&self, bump: &BumpedCrate) -> bool {
+        self.crate_name == bump.name
+            && self
+                .from
+                .as_deref()
+                .is_none_or(|prefix| version_has_prefix(&bump.from, prefix))
+            && self
+                .to
+                .as_deref()
+                .is_none_or(|prefix| version_has_prefix(&bump.to, prefix))
+    }
+
+    fn label(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| self.find.clone())
+    }
+}
+
+/// Rewrite rules loaded from one or more YAML files
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MigrationRules {
+    #[serde(default)]
+    pub rules: Vec<MigrationRule>,
+}
+
+impl MigrationRules {
+    /// Merge the rules from every file in `paths` that exists
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        let mut merged = Self::default();
+        for path in paths.iter().filter(|p| p.is_file()) {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let rules: Self = serde_yaml_ok::from_str(&content)
+                .with_context(|| format!("Invalid migration rules in {}", path.display()))?;
+            merged.rules.extend(rules.rules);
+        }
+        Ok(merged)
+    }
+}
+
+/// A dependency whose bump crossed a semver-incompatible boundary
+#[derive(Debug, Clone, Serialize)]
+pub struct BumpedCrate {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub notes: Option<MigrationNotes>,
+}
+
+/// Migration notes published by the crate
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationNotes {
+    pub url: String,
+    /// Sections covering the versions between `from` and `to`, if they could
+    /// be picked out of the file
+    pub excerpt: Option<String>,
+}
+
+/// A compiler error caused by a bumped crate
+#[derive(Debug, Clone, Serialize)]
+pub struct Breakage {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// A rewrite rule applied to a source file
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedFix {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub file: PathBuf,
+    pub rule: String,
+    pub replacements: usize,
+}
+
+/// Breakage found in one project after its dependencies were bumped
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakageReport {
+    pub project: PathBuf,
+    pub bumped: Vec<BumpedCrate>,
+    pub breakages: Vec<Breakage>,
+    pub fixes: Vec<AppliedFix>,
+    /// Errors still referencing a bumped crate after fixes were applied
+    pub remaining: Option<usize>,
+    /// Where the output of each `cargo check` was saved
+    pub outputs: Vec<CapturedOutput>,
+}
+
+impl BreakageReport {
+    /// Write the report to [`BREAKAGE_REPORT_PATH`] inside the project
+    pub fn write(&self) -> Result<PathBuf> {
+        let path = self.project.join(BREAKAGE_REPORT_PATH);
+        let guard = WriteGuard::current();
+        if let Some(parent) = path.parent() {
+            guard.create_dir_all(parent)?;
+        }
+        guard.write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Runs the migration pass over the results of an upgrade session
+pub struct MigrationAssistant {
+    rules: MigrationRules,
+    fetch_notes: bool,
+    apply_fixes: bool,
+    target_dir: Option<PathBuf>,
+    runner: Arc<dyn CargoRunner>,
+    client: HttpClient,
+}
+
+impl MigrationAssistant {
+    pub fn new(rules: MigrationRules) -> Self {
+        let client = HttpClient::new(concat!("kargo-upgrade/", env!("CARGO_PKG_VERSION")))
+            .unwrap_or_else(|e| {
+                log::error!("Failed to create HTTP client: {}", e);
+                panic!("Critical error: Failed to create HTTP client: {}", e);
+            });
+        Self {
+            rules,
+            fetch_notes: true,
+            apply_fixes: true,
+            target_dir: None,
+            runner: Arc::new(ProcessRunner::default()),
+            client,
+        }
+    }
+
+    /// Whether to download each bumped crate's migration notes
+    pub fn fetch_notes(mut self, fetch_notes: bool) -> Self {
+        self.fetch_notes = fetch_notes;
+        self
+    }
+
+    /// Whether to apply matching rewrite rules, or only report
+    pub fn apply_fixes(mut self, apply_fixes: bool) -> Self {
+        self.apply_fixes = apply_fixes;
+        self
+    }
+
+    /// Target directory for the checks, instead of each project's own
+    pub fn target_dir(mut self, target_dir: Option<PathBuf>) -> Self {
+        self.target_dir = target_dir;
+        self
+    }
+
+    /// Run the checks through `runner` instead of spawning cargo
+    pub fn runner(mut self, runner: Arc<dyn CargoRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Check every project that received a breaking bump and build its report
+    pub async fn run(&self, results: &[UpdateResult]) -> Result<Vec<BreakageReport>> {
+        let mut reports = Vec::new();
+        for (project, mut bumped) in breaking_bumps(results) {
+            if self.fetch_notes {
+                for bump in &mut bumped {
+                    bump.notes = self.migration_notes(bump).await;
+                }
+            }
+            reports.push(self.check_project(project, bumped).await?);
+        }
+        Ok(reports)
+    }
+
+    /// Run `cargo check` in `project` and keep the errors mentioning a
+    /// bumped crate. What cargo printed is saved to the project's run
+    /// artifacts and added to `outputs`.
+    async fn cargo_check(
+        &self,
+        project: &Path,
+        bumped: &[BumpedCrate],
+        outputs: &mut Vec<CapturedOutput>,
+    ) -> Result<Vec<Breakage>> {
+        let command = CargoCommand::new("check")
+            .arg("--all-targets")
+            .json_messages()
+            .current_dir(project)
+            .target_dir(self.target_dir.as_deref());
+        let runner = Arc::clone(&self.runner);
+        let (command, output) = tokio::task::spawn_blocking(move || {
+            let output = runner.run(&command);
+            (command, output)
+        })
+        .await?;
+        let output = output?;
+        match RunArtifacts::for_project(project).capture_cargo(&command, &output) {
+            Ok(captured) => outputs.push(captured),
+            Err(e) => log::warn!("{:#}", e),
+        }
+
+        let mut breakages = Vec::new();
+        for message in output.errors() {
+            let (Some(file), Some(line), Some(column)) =
+                (&message.file, message.line, message.column)
+            else {
+                continue;
+            };
+            let Some(bump) = bumped
+                .iter()
+                .find(|b| mentions_crate(message.text(), &b.name))
+            else {
+                continue;
+            };
+            breakages.push(Breakage {
+                crate_name: bump.name.clone(),
+                file: project.join(file),
+                line,
+                column,
+                code: message.code.clone(),
+                message: message.message.clone(),
+            });
+        }
+        Ok(breakages)
+    }
+
+    async fn check_project(
+        &self,
+        project: PathBuf,
+        bumped: Vec<BumpedCrate>,
+    ) -> Result<BreakageReport> {
+        log::info!("Checking {} for breakage", project.display());
+        let mut outputs = Vec::new();
+        let breakages = self.cargo_check(&project, &bumped, &mut outputs).await?;
+
+        let mut fixes = Vec::new();
+        if self.apply_fixes {
+            for bump in &bumped {
+                let rules: Vec<&MigrationRule> = self
+                    .rules
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.applies_to(bump))
+                    .collect();
+                if !rules.is_empty() {
+                    fixes.extend(apply_rules(&rules, bump, &breakages)?);
+                }
+            }
+        }
+
+        let remaining = if fixes.is_empty() {
+            None
+        } else {
+            Some(
+                self.cargo_check(&project, &bumped, &mut outputs)
+                    .await?
+                    .len(),
+            )
+        };
+
+        Ok(BreakageReport {
+            project,
+            bumped,
+            breakages,
+            fixes,
+            remaining,
+            outputs,
+        })
+    }
+
+    /// Best-effort lookup of the crate's migration notes in its repository
+    async fn migration_notes(&self, bump: &BumpedCrate) -> Option<MigrationNotes> {
+        let repository = match get_repository(&self.client, &bump.name).await {
+            Ok(repository) => repository?,
+            Err(e) => {
+                log::warn!("Failed to look up {} on crates.io: {:#}", bump.name, e);
+                return None;
+            }
+        };
+
+        let raw_base = github_raw_base(&repository)?;
+        for file in NOTES_FILES {
+            let url = format!("{}/{}", raw_base, file);
+            let response = match self.client.send(self.client.get(&url)).await {
+                Ok(response) if response.status().is_success() => response,
+                _ => continue,
+            };
+            let Ok(body) = response.text().await else {
+                continue;
+            };
+            return Some(MigrationNotes {
+                url: format!("{}/blob/HEAD/{}", repository.trim_end_matches('/'), file),
+                excerpt: notes_excerpt(&body, &bump.from, &bump.to),
+            });
+        }
+        None
+    }
+}
+
+/// Breaking bumps from successful results, grouped by the project to check
+fn breaking_bumps(results: &[UpdateResult]) -> BTreeMap<PathBuf, Vec<BumpedCrate>> {
+    let mut workspaces = HashMap::new();
+    let mut projects: BTreeMap<PathBuf, Vec<BumpedCrate>> = BTreeMap::new();
+    for result in results.iter().filter(|r| r.error.is_none()) {
+        for update in &result.updates {
+            if !is_breaking(&update.from_version, &update.to_version) {
+                continue;
+            }
+            let project = workspace_root(&result.path, &mut workspaces)
+                .or_else(|| result.path.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let bumped = projects.entry(project).or_default();
+            if !bumped.iter().any(|b| b.name == update.name) {
+                bumped.push(BumpedCrate {
+                    name: update.name.clone(),
+                    from: update.from_version.clone(),
+                    to: update.to_version.clone(),
+                    notes: None,
+                });
+            }
+        }
+    }
+    projects
+}
+
+/// Whether moving from requirement `from` to version `to` is
+/// semver-incompatible: a new major, a new minor while still on 0.x, or a
+/// new patch while still on 0.0.x
+pub fn is_breaking(from: &str, to: &str) -> bool {
+    let (Some(from), Some(to)) = (version_parts(from), version_parts(to)) else {
+        return false;
+    };
+    let part = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+    match (from.first(), to.first()) {
+        // `0.0` alone still allows any 0.0.x
+        (Some(0), Some(0)) if part(&from, 1) == 0 && from.len() > 2 => {
+            part(&to, 1) != 0 || part(&from, 2) != part(&to, 2)
+        }
+        (Some(0), Some(0)) => part(&from, 1) != part(&to, 1),
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    }
+}
+
+/// Numeric components of a version or simple requirement (`^1.2`, `=0.3.1`)
+fn version_parts(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches(['^', '=', '~', 'v', ' ']);
+    let parts: Vec<u64> = version
+        .split(['.', '-', '+'])
+        .take(3)
+        .map_while(|part| part.parse().ok())
+        .collect();
+    (!parts.is_empty()).then_some(parts)
+}
+
+/// `major.minor.patch` with missing components as zero, for ordering
+pub(crate) fn padded(version: &str) -> Option<[u64; 3]> {
+    let parts = version_parts(version)?;
+    Some([0, 1, 2].map(|i| parts.get(i).copied().unwrap_or(0)))
+}
+
+/// Component-wise prefix match, so `1` matches `1.4.0` but not `10.0.0`
+fn version_has_prefix(version: &str, prefix: &str) -> bool {
+    match (version_parts(version), version_parts(prefix)) {
+        (Some(version), Some(prefix)) => version.starts_with(&prefix),
+        _ => false,
+    }
+}
+
+/// Whether compiler output refers to items of `crate_name`
+fn mentions_crate(text: &str, crate_name: &str) -> bool {
+    let ident = crate_name.replace('-', "_");
+    text.contains(&format!("{}::", ident)) || text.contains(&format!("`{}`", ident))
+}
+
+/// Apply `rules` to every file with a breakage caused by `bump`
+fn apply_rules(
+    rules: &[&MigrationRule],
+    bump: &BumpedCrate,
+    breakages: &[Breakage],
+) -> Result<Vec<AppliedFix>> {
+    let mut files: Vec<&Path> = breakages
+        .iter()
+        .filter(|b| b.crate_name == bump.name)
+        .map(|b| b.file.as_path())
+        .collect();
+    files.sort();
+    files.dedup();
+
+    let compiled = rules
+        .iter()
+        .map(|rule| {
+            Regex::new(&rule.find)
+                .map(|re| (re, *rule))
+                .map_err(|e| anyhow!("Invalid rule for {}: {}", rule.crate_name, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut fixes = Vec::new();
+    for file in files {
+        let mut content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let mut changed = false;
+        for (re, rule) in &compiled {
+            let replacements = re.find_iter(&content).count();
+            if replacements == 0 {
+                continue;
+            }
+            content = re.replace_all(&content, rule.replace.as_str()).into_owned();
+            changed = true;
+            fixes.push(AppliedFix {
+                crate_name: bump.name.clone(),
+                file: file.to_path_buf(),
+                rule: rule.label(),
+                replacements,
+            });
+        }
+        if changed {
+            WriteGuard::current().write(file, content)?;
+        }
+    }
+    Ok(fixes)
+}
+
+/// Sections of a changelog whose heading names a release that breaks
+/// compatibility with `from`, up to and including `to`
+pub fn notes_excerpt(notes: &str, from: &str, to: &str) -> Option<String> {
+    let (from, to) = (padded(from)?, padded(to)?);
+    // Releases still compatible with `from` hold nothing to migrate
+    let first = match from {
+        [0, minor, _] => [0, minor + 1, 0],
+        [major, _, _] => [major + 1, 0, 0],
+    };
+    sections_between(notes, first, to)
+}
@@ -1,6 +1,7 @@
 //! Domain models for the dependency up2date
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
@@ -9,16 +10,66 @@ use crate::types::PendingWrite;
 /// Represents a parsed dependency with its metadata
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Dependency {
-    /// The name of the dependency
+    /// The name of the dependency, as keyed in the manifest
     pub name: String,
+    /// Registry package name when `name` is a rename (`foo = { package = "bar" }`)
+    pub package: Option<String>,
     /// The current version string
     pub version: String,
     /// The location of this dependency in the source
     pub location: DependencyLocation,
+    /// Where the dependency comes from
+    pub origin: Origin,
+}
+
+impl Dependency {
+    /// Name to look the dependency up under in the registry
+    pub fn package_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Where a dependency is fetched from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    /// The default registry: crates.io, or the profile's `registry`
+    #[default]
+    Default,
+    /// A registry named with `registry = "..."`; its version is looked up in
+    /// that registry's index
+    Registry(String),
+    /// A git repository; the dependency's version is its `tag` or `rev`
+    Git { url: String, pin: GitPin },
+}
+
+impl Origin {
+    pub fn is_default(&self) -> bool {
+        *self == Self::Default
+    }
+}
+
+/// The key a git dependency is pinned with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitPin {
+    Tag,
+    Rev,
+}
+
+impl GitPin {
+    /// The manifest key
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Tag => "tag",
+            Self::Rev => "rev",
+        }
+    }
 }
 
 /// Specifies where a dependency is located within a source
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DependencyLocation {
     /// In a Cargo.toml [dependencies] section
     CargoTomlDirect,
@@ -133,7 +184,8 @@ pub trait DependencyParser {
     fn parse(&self, source: &DependencySource) -> Result<Vec<Dependency>>;
 }
 
-use crate::types::{BatchUpdateOperation, PendingDependencyUpdate};
+use crate::types::{BatchUpdateOperation, PendingDependencyUpdate, SendFuture};
+use crate::update_policy::UpdatePolicies;
 
 /// Updater trait for updating dependencies to their latest versions
 pub trait DependencyUpdater: Clone + Send + Sync + 'static {
@@ -141,6 +193,23 @@ pub trait DependencyUpdater: Clone + Send + Sync + 'static {
     /// Returns a PendingDependencyUpdate that can be awaited
     fn update(&self, dependency: &Dependency) -> PendingDependencyUpdate;
 
+    /// Warm any caches for a batch before the individual updates run.
+    /// The default does nothing.
+    fn prefetch(&self, _dependencies: &[Dependency]) -> SendFuture<()> {
+        SendFuture(Box::pin(async {}))
+    }
+
+    /// Name of the rule this updater picks new versions by, recorded in the
+    /// upgrade history
+    fn policy(&self) -> &str {
+        "latest"
+    }
+
+    /// This updater, picking versions by `policies`. The default ignores them.
+    fn with_policies(&self, _policies: UpdatePolicies) -> Self {
+        self.clone()
+    }
+
     /// Update a list of dependencies
     /// Returns a BatchUpdateOperation that provides a stream of updates
     fn update_all(&self, dependencies: &[Dependency]) -> BatchUpdateOperation {
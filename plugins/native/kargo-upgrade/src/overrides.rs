@@ -0,0 +1,299 @@
+//! `[patch]` entries and source replacement that override where crates come from
+//!
+//! Bumping the requirement of a patched crate past the patched version makes
+//! cargo ignore the patch, or fail to resolve it; bumping anything in a
+//! vendored workspace asks for a version the vendor directory doesn't have.
+//! Either way the manifest looks fine and the build breaks. [`Overrides`]
+//! collects both for a workspace so the session can skip those crates, or
+//! move the patch and vendor copy along with the requirement.
+
+use anyhow::{bail, Context, Result};
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item};
+
+use crate::git_tags::TAG_VERSION;
+
+/// Where a patch takes a crate from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchSource {
+    /// A local checkout
+    Path(PathBuf),
+    Git {
+        url: String,
+        /// `tag`, `branch` or `rev`, and its value
+        reference: Option<(String, String)>,
+    },
+    /// Another registry, at a pinned version
+    Registry { version: String },
+}
+
+/// One `[patch.<source>]` or `[replace]` entry of a workspace root manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// Key of the entry
+    pub name: String,
+    /// Package the entry replaces, when it's renamed with `package = ".."`
+    pub package: Option<String>,
+    /// The patch table's key (`crates-io` or a URL), or `replace`
+    pub table: String,
+    pub source: PatchSource,
+}
+
+impl Patch {
+    pub fn package_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The `(key, value)` to set in the entry so it provides version `to`,
+    /// for patches pinned to a registry version or a version tag
+    pub fn retarget(&self, to: &str) -> Option<(&'static str, String)> {
+        match &self.source {
+            PatchSource::Registry { version } => {
+                // Keep the requirement's operator, e.g. `=1.2.3`
+                let op_len = version.len() - version.trim_start_matches(['=', '^', '~', ' ']).len();
+                Some(("version", format!("{}{}", &version[..op_len], to)))
+            }
+            PatchSource::Git {
+                reference: Some((kind, tag)),
+                ..
+            } if kind == "tag" => {
+                let found = TAG_VERSION.find(tag)?;
+                let retagged = format!("{}{}{}", &tag[..found.start()], to, &tag[found.end()..]);
+                Some(("tag", retagged))
+            }
+            _ => None,
+        }
+    }
+
+    /// Why the crate can't be bumped without touching the patch
+    pub fn describe(&self) -> String {
+        let table = if self.table == "replace" {
+            "[replace]".to_string()
+        } else {
+            format!("[patch.{}]", self.table)
+        };
+        match &self.source {
+            PatchSource::Path(path) => format!("patched by {} to {}", table, path.display()),
+            PatchSource::Git { url, reference } => match reference {
+                Some((kind, value)) => {
+                    format!("patched by {} to {} ({} {})", table, url, kind, value)
+                }
+                None => format!("patched by {} to {}", table, url),
+            },
+            PatchSource::Registry { version } => {
+                format!("patched by {} to version {}", table, version)
+            }
+        }
+    }
+}
+
+/// A patch entry moved to a new tag or version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchUpdate {
+    pub patch: Patch,
+    /// Directory of the manifest declaring the patch
+    pub root: PathBuf,
+    /// `tag` or `version`
+    pub key: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+/// The patches and vendored sources that apply to a workspace
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    /// Directory of the manifest the patches are declared in
+    pub root: PathBuf,
+    pub patches: Vec<Patch>,
+    /// Directory crates.io is replaced with, by `cargo vendor` or a local
+    /// registry
+    pub vendor_dir: Option<PathBuf>,
+}
+
+impl Overrides {
+    /// The overrides of the workspace in `root`: the patches of its manifest
+    /// and the source replacement of the cargo config files that apply there
+    pub fn load(root: &Path) -> Result<Self> {
+        let manifest = root.join("Cargo.toml");
+        let patches = match std::fs::read_to_string(&manifest) {
+            Ok(content) => parse_patches(&content)
+                .with_context(|| format!("Invalid [patch] in {}", manifest.display()))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            root: root.to_path_buf(),
+            patches,
+            vendor_dir: vendor_dir(root)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty() && self.vendor_dir.is_none()
+    }
+
+    /// The patch of registry package `package`, if it's patched
+    pub fn patch(&self, package: &str) -> Option<&Patch> {
+        self.patches.iter().find(|p| p.package_name() == package)
+    }
+}
+
+/// The patch entries of a manifest
+pub fn parse_patches(content: &str) -> Result<Vec<Patch>> {
+    let doc: DocumentMut = content.parse()?;
+    let mut patches = Vec::new();
+    let mut collect = |table: &str, entries: &Item| -> Result<()> {
+        let Some(entries) = entries.as_table_like() else {
+            return Ok(());
+        };
+        for (name, entry) in entries.iter() {
+            let Some(entry) = entry.as_table_like() else {
+                bail!("`{}` is not a table", name);
+            };
+            let field = |key: &str| entry.get(key).and_then(Item::as_str).map(str::to_string);
+            let source = if let Some(path) = field("path") {
+                PatchSource::Path(PathBuf::from(path))
+            } else if let Some(url) = field("git") {
+                let reference = ["tag", "branch", "rev"]
+                    .into_iter()
+                    .find_map(|kind| Some((kind.to_string(), field(kind)?)));
+                PatchSource::Git { url, reference }
+            } else if let Some(version) = field("version") {
+                PatchSource::Registry { version }
+            } else {
+                bail!("`{}` has no path, git or version", name);
+            };
+            // `[replace]` keys are package IDs, `name:version`
+            let key = name.split(':').next().unwrap_or(name);
+            patches.push(Patch {
+                name: key.to_string(),
+                package: field("package"),
+                table: table.to_string(),
+                source,
+            });
+        }
+        Ok(())
+    };
+
+    if let Some(tables) = doc.get("patch").and_then(Item::as_table_like) {
+        for (table, entries) in tables.iter() {
+            collect(table, entries)?;
+        }
+    }
+    if let Some(entries) = doc.get("replace") {
+        collect("replace", entries)?;
+    }
+    Ok(patches)
+}
+
+/// Set `key` of the patch entry `patch` to `value` in `content`, keeping
+/// the rest of the manifest as written
+pub fn set_patch_field(content: &str, patch: &Patch, key: &str, value: &str) -> Result<String> {
+    let mut doc: DocumentMut = content.parse()?;
+    let table = if patch.table == "replace" {
+        doc.get_mut("replace")
+    } else {
+        doc.get_mut("patch")
+            .and_then(|patches| patches.get_mut(&patch.table))
+    };
+    let entry = table
+        .and_then(|table| table.as_table_like_mut())
+        .and_then(|table| {
+            let name = table
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .find(|name| name.split(':').next() == Some(patch.name.as_str()))?;
+            table.get_mut(&name)
+        })
+        .and_then(|entry| entry.as_table_like_mut())
+        .with_context(|| format!("No patch entry for {}", patch.name))?;
+    let field = entry
+        .get_mut(key)
+        .and_then(Item::as_value_mut)
+        .with_context(|| format!("The patch of {} has no {}", patch.name, key))?;
+    let decor = field.decor().clone();
+    *field = value.into();
+    *field.decor_mut() = decor;
+    Ok(doc.to_string())
+}
+
+/// Directory crates.io is replaced with in the cargo config that applies to
+/// `root`, if it's a `cargo vendor` directory or local registry
+fn vendor_dir(root: &Path) -> Result<Option<PathBuf>> {
+    // Closest config first; CARGO_HOME's applies last
+    let mut configs: Vec<PathBuf> = root
+        .ancestors()
+        .flat_map(|dir| {
+            let cargo = dir.join(".cargo");
+            [cargo.join("config.toml"), cargo.join("config")]
+        })
+        .collect();
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(".cargo")));
+    if let Some(home) = cargo_home {
+        configs.push(home.join("config.toml"));
+        configs.push(home.join("config"));
+    }
+
+    let mut docs = Vec::new();
+    for path in configs {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let doc: DocumentMut = content
+            .parse()
+            .with_context(|| format!("Invalid cargo config {}", path.display()))?;
+        // Relative paths in a config are relative to the directory holding
+        // its `.cargo` directory
+        let base = path
+            .parent()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        docs.push((doc, base));
+    }
+    let source = |name: &str, key: &str| {
+        docs.iter().find_map(|(doc, base)| {
+            let value = doc.get("source")?.get(name)?.get(key)?.as_str()?;
+            Some((value.to_string(), base.clone()))
+        })
+    };
+
+    let Some((replacement, _)) = source("crates-io", "replace-with") else {
+        return Ok(None);
+    };
+    let dir = source(&replacement, "directory").or_else(|| source(&replacement, "local-registry"));
+    Ok(dir.map(|(dir, base)| base.join(dir)))
+}
+
+/// Whether `tag` exists in the git repository at `url`
+pub async fn tag_exists(url: &str, tag: &str) -> Result<bool> {
+    let output = tokio::process::Command::new("git")
+        .args(["ls-remote", "--tags", url, &format!("refs/tags/{}", tag)])
+        .output()
+        .await
+        .with_context(|| format!("Failed to run git ls-remote {}", url))?;
+    if !output.status.success() {
+        bail!(
+            "git ls-remote {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(!output.stdout.is_empty())
+}
+
+/// Refresh the vendored copies of the workspace in `root` after its
+/// requirements changed. `cargo vendor` ignores the source replacement by
+/// default, so it downloads the new versions from crates.io.
+pub async fn revendor(root: &Path, vendor_dir: &Path) -> Result<()> {
+    let command = CargoCommand::new("vendor")
+        .arg(vendor_dir.to_string_lossy())
+        .current_dir(root);
+    let output = {
+        let command = command.clone();
+        tokio::task::spawn_blocking(move || cargo_runner::run(&command)).await??
+    };
+    output.ensure_success(&command)
+}
@@ -3,7 +3,9 @@
 use anyhow::{anyhow, Result};
 use toml_edit::{DocumentMut as Document, Item, Table};
 
-use crate::models::{Dependency, DependencyLocation, DependencyParser, DependencySource};
+use crate::models::{
+    Dependency, DependencyLocation, DependencyParser, DependencySource, GitPin, Origin,
+};
 
 /// Parser for Cargo.toml files
 #[derive(Clone)]
@@ -88,11 +90,16 @@ impl CargoParser {
                 continue;
             }
 
-            if let Some(version) = self.extract_version(value) {
+            if let Some((version, origin)) = self.extract_version(value) {
                 dependencies.push(Dependency {
                     name: name.to_string(),
+                    package: value
+                        .get("package")
+                        .and_then(|package| package.as_str())
+                        .map(str::to_string),
                     version,
                     location: location.clone(),
+                    origin,
                 });
             }
         }
@@ -100,38 +107,46 @@ impl CargoParser {
         Ok(())
     }
 
-    /// Extract the version from a dependency item
-    fn extract_version(&self, item: &Item) -> Option<String> {
-        match item {
-            // Simple string version like version = "1.0.0"
-            Item::Value(value) => {
-                if let Some(version) = value.as_str() {
-                    Some(version.to_string())
-                } else {
-                    None
-                }
-            }
+    /// Extract the version from a dependency item, with where it comes from
+    fn extract_version(&self, item: &Item) -> Option<(String, Origin)> {
+        // Simple string version like version = "1.0.0"
+        if let Some(version) = item.as_str() {
+            return Some((version.to_string(), Origin::Default));
+        }
 
-            // Table specification like { version = "1.0.0", features = ["..."] }
-            Item::Table(table) => {
-                // Skip workspace dependencies
-                if table.contains_key("workspace") {
-                    return None;
-                }
+        // Table specification like { version = "1.0.0", features = ["..."] },
+        // inline or as a section of its own
+        let table = item.as_table_like()?;
+        let key = |key: &str| table.get(key).and_then(Item::as_str);
 
-                if let Some(version) = table.get("version") {
-                    if let Some(version_str) = version.as_str() {
-                        Some(version_str.to_string())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            }
+        // Skip workspace dependencies
+        if table.contains_key("workspace") {
+            return None;
+        }
 
-            // Other formats not supported
-            _ => None,
+        // Path dependencies build from local sources; their version
+        // only matters when publishing
+        if table.contains_key("path") {
+            return None;
         }
+
+        // Git dependencies are versioned by the tag or commit they are pinned
+        // to; those following a branch are always current
+        if let Some(url) = key("git") {
+            let (pin, version) = [GitPin::Tag, GitPin::Rev]
+                .into_iter()
+                .find_map(|pin| Some((pin, key(pin.key())?)))?;
+            let origin = Origin::Git {
+                url: url.to_string(),
+                pin,
+            };
+            return Some((version.to_string(), origin));
+        }
+
+        let origin = match key("registry") {
+            Some(registry) => Origin::Registry(registry.to_string()),
+            None => Origin::Default,
+        };
+        Some((key("version")?.to_string(), origin))
     }
 }
@@ -2,7 +2,7 @@ use anyhow::Result;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-use crate::models::{Dependency, DependencyLocation, DependencyParser, DependencySource};
+use crate::models::{Dependency, DependencyLocation, DependencyParser, DependencySource, Origin};
 
 // Regular expressions for parsing rust-script files
 static CARGO_SECTION_REGEX: Lazy<Regex> =
@@ -79,10 +79,12 @@ impl RustScriptParser {
 
                         dependencies.push(Dependency {
                             name: name.to_string(),
+                            package: None,
                             version: version.to_string(),
                             location: DependencyLocation::RustScriptCargo {
                                 section_range: (cargo_content.start(), cargo_content.end()),
                             },
+                            origin: Origin::Default,
                         });
                     }
 
@@ -99,10 +101,12 @@ impl RustScriptParser {
 
                         dependencies.push(Dependency {
                             name: name.to_string(),
+                            package: None,
                             version: version.to_string(),
                             location: DependencyLocation::RustScriptCargo {
                                 section_range: (cargo_content.start(), cargo_content.end()),
                             },
+                            origin: Origin::Default,
                         });
                     }
                 }
@@ -148,10 +152,12 @@ impl RustScriptParser {
 
                         dependencies.push(Dependency {
                             name: name.to_string(),
+                            package: None,
                             version: version.to_string(),
                             location: DependencyLocation::RustScriptDeps {
                                 line_range: (line_start, line_end),
                             },
+                            origin: Origin::Default,
                         });
                     }
                 }
@@ -172,10 +178,12 @@ impl RustScriptParser {
 
                         dependencies.push(Dependency {
                             name: name.to_string(),
+                            package: None,
                             version: version.to_string(),
                             location: DependencyLocation::RustScriptDeps {
                                 line_range: (line_start, line_end),
                             },
+                            origin: Origin::Default,
                         });
                     }
                 }
@@ -197,10 +205,12 @@ impl RustScriptParser {
 
                         dependencies.push(Dependency {
                             name: name.to_string(),
+                            package: None,
                             version: version.to_string(),
                             location: DependencyLocation::RustScriptDeps {
                                 line_range: (line_start, line_end),
                             },
+                            origin: Origin::Default,
                         });
                     }
                 }
@@ -219,10 +229,12 @@ impl RustScriptParser {
 
                     dependencies.push(Dependency {
                         name: name.to_string(),
+                        package: None,
                         version: "*".to_string(),
                         location: DependencyLocation::RustScriptDeps {
                             line_range: (line_start, line_end),
                         },
+                        origin: Origin::Default,
                     });
                 }
             }
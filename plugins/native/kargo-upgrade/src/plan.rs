@@ -0,0 +1,169 @@
+//! Upgrade plans: updates decided now and applied later
+//!
+//! `kargo upgrade --plan-out plan.json` writes the updates it would make as an
+//! [`UpgradePlan`] instead of applying them, so they can be reviewed and
+//! diffed like any other file. `--apply-plan plan.json` applies exactly those
+//! updates. The plan records a checksum of every manifest it covers, and
+//! applying it fails before anything is written if one of them has changed.
+
+use anyhow::{bail, Context, Result};
+use kargo_plugin_api::WriteGuard;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::models::{Dependency, DependencyLocation, DependencyUpdate, Origin};
+use crate::types::UpdateResult;
+
+/// Version of the plan format, bumped on incompatible changes
+pub const PLAN_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradePlan {
+    pub version: u32,
+    pub kargo_version: String,
+    /// Rule the new versions were picked by
+    pub policy: String,
+    pub manifests: Vec<PlannedManifest>,
+}
+
+/// The updates planned for one manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedManifest {
+    /// Relative to the directory the plan was made for
+    pub path: PathBuf,
+    /// [`checksum`] of the manifest the updates were planned against
+    pub checksum: String,
+    pub updates: Vec<PlannedUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedUpdate {
+    /// Name as keyed in the manifest
+    pub name: String,
+    /// Registry name, for renamed dependencies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    pub location: DependencyLocation,
+    /// Registry or git repository, unless the default registry
+    #[serde(default, skip_serializing_if = "Origin::is_default")]
+    pub origin: Origin,
+    pub from: String,
+    pub to: String,
+}
+
+impl UpgradePlan {
+    /// Plan the updates of a dry run below `root`. Results with errors are
+    /// left out, so callers should only plan runs that fully succeeded.
+    pub fn from_results(
+        results: &[UpdateResult],
+        root: &Path,
+        policy: impl Into<String>,
+        kargo_version: impl Into<String>,
+    ) -> Result<Self> {
+        let mut manifests = Vec::new();
+        for result in results {
+            if result.error.is_some() || result.updates.is_empty() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&result.path)
+                .with_context(|| format!("Failed to read {}", result.path.display()))?;
+            manifests.push(PlannedManifest {
+                path: result
+                    .path
+                    .strip_prefix(root)
+                    .unwrap_or(&result.path)
+                    .to_path_buf(),
+                checksum: checksum(&content),
+                updates: result
+                    .updates
+                    .iter()
+                    .map(|update| PlannedUpdate {
+                        name: update.name.clone(),
+                        package: update.dependency.package.clone(),
+                        location: update.dependency.location.clone(),
+                        origin: update.dependency.origin.clone(),
+                        from: update.from_version.clone(),
+                        to: update.to_version.clone(),
+                    })
+                    .collect(),
+            });
+        }
+        manifests.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Self {
+            version: PLAN_VERSION,
+            kargo_version: kargo_version.into(),
+            policy: policy.into(),
+            manifests,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read plan {}", path.display()))?;
+        let plan: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid plan {}", path.display()))?;
+        if plan.version != PLAN_VERSION {
+            bail!(
+                "{} is a version {} plan; this kargo reads version {}",
+                path.display(),
+                plan.version,
+                PLAN_VERSION
+            );
+        }
+        Ok(plan)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        WriteGuard::current().write(path, json)
+    }
+
+    /// Manifests below `root` that are missing or changed since the plan was
+    /// made
+    pub fn drifted(&self, root: &Path) -> Vec<PathBuf> {
+        self.manifests
+            .iter()
+            .filter(|manifest| {
+                std::fs::read_to_string(root.join(&manifest.path))
+                    .map(|content| checksum(&content) != manifest.checksum)
+                    .unwrap_or(true)
+            })
+            .map(|manifest| manifest.path.clone())
+            .collect()
+    }
+
+    pub fn update_count(&self) -> usize {
+        self.manifests.iter().map(|m| m.updates.len()).sum()
+    }
+}
+
+impl PlannedManifest {
+    /// The planned updates, in the form the writers apply
+    pub fn dependency_updates(&self) -> Vec<DependencyUpdate> {
+        self.updates
+            .iter()
+            .map(|update| DependencyUpdate {
+                name: update.name.clone(),
+                from_version: update.from.clone(),
+                to_version: update.to.clone(),
+                dependency: Dependency {
+                    name: update.name.clone(),
+                    package: update.package.clone(),
+                    version: update.from.clone(),
+                    location: update.location.clone(),
+                    origin: update.origin.clone(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// FNV-1a (64-bit) of a manifest's contents; detects drift, not tampering
+pub fn checksum(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("fnv1a64:{:016x}", hash)
+}
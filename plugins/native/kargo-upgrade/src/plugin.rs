@@ -0,0 +1,787 @@
+#![allow(unsafe_code)]
+use anyhow::bail;
+use clap::{Arg, ArgAction, Command};
+use kargo_plugin_api::policy::{self, Policy, POLICY_FILE};
+use kargo_plugin_api::profile::{Phase, Profiler};
+use kargo_plugin_api::resume::Journal;
+use kargo_plugin_api::snapshot::Snapshot;
+use kargo_plugin_api::{
+    BoxFuture, Diagnostic, DiagnosticKind, DiagnosticsSchema, Example, ExecutionContext, HelpInfo,
+    HostEvent, PluginCommand, Severity,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::checklist;
+use crate::commits::{CommitBatching, UpgradeCommits};
+use crate::diff::{write_patches, DiffOutput};
+use crate::finder::find_cargo_toml_files;
+use crate::migrate::{MigrationAssistant, MigrationRules};
+use crate::models::DependencyUpdater;
+use crate::plan::UpgradePlan;
+use crate::pr_body::PrBody;
+use crate::prefilter::ManifestPrefilter;
+use crate::registry::{set_index_ttl, use_index, use_registries};
+use crate::session::{Attribution, UpgradeSession, WriteMode};
+use crate::submodules::{find_submodules, SubmoduleSelection};
+use crate::types::UpdateOptions;
+use crate::update_policy::{UpdatePolicy, UpdatePolicyConfig};
+use crate::updater::CratesIoUpdater;
+use crate::version_bump::{VersionBump, VersionBumpPlan, VersionTarget};
+
+pub struct UpgradePlugin;
+
+/// The plugin's section of the kargo config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct UpgradeSettings {
+    /// Sparse index URLs of the registries dependencies name with
+    /// `registry = "..."`, by name
+    pub registries: BTreeMap<String, String>,
+}
+
+impl PluginCommand for UpgradePlugin {
+    fn clap(&self) -> Command {
+        Command::new("upgrade")
+            .about("Upgrade dependencies in every Cargo.toml below a directory")
+            .arg(
+                Arg::new("path")
+                    .help("Directory to scan (defaults to the current directory)")
+                    .value_name("PATH")
+                    .index(1),
+            )
+            .arg(
+                Arg::new("per-file")
+                    .long("per-file")
+                    .help("Commit each manifest on its own instead of all-or-nothing per workspace")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("dry-run")
+                    .long("dry-run")
+                    .help("Show the updates without writing any manifest; exits with status 2 when there are any")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("plan-out")
+                    .long("plan-out")
+                    .help("Write the planned updates to FILE for review instead of applying them")
+                    .value_name("FILE")
+                    .conflicts_with_all(["dry-run", "apply-plan"]),
+            )
+            .arg(
+                Arg::new("apply-plan")
+                    .long("apply-plan")
+                    .help("Apply exactly the updates in a --plan-out FILE; fails if a manifest changed since")
+                    .value_name("FILE")
+                    .conflicts_with("dry-run"),
+            )
+            .arg(
+                Arg::new("interactive")
+                    .long("interactive")
+                    .short('i')
+                    .help("Pick the updates to apply from a checklist, per manifest or per crate")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["dry-run", "plan-out", "apply-plan", "update-patches"]),
+            )
+            .arg(
+                Arg::new("update-policy")
+                    .long("update-policy")
+                    .help("How far every dependency may move this run: patch, minor, compatible, major (latest) or pinned; crate policies in update-policy.yaml still apply")
+                    .value_name("LEVEL")
+                    .value_parser(clap::value_parser!(UpdatePolicy))
+                    .conflicts_with("apply-plan"),
+            )
+            .arg(
+                Arg::new("update-patches")
+                    .long("update-patches")
+                    .help("Bump patched and vendored crates too, moving [patch] tags/versions and re-running cargo vendor")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("pr-body-out")
+                    .long("pr-body-out")
+                    .help("Write a markdown pull request description of the updates to FILE (- for stdout)")
+                    .value_name("FILE"),
+            )
+            .arg(
+                Arg::new("diff")
+                    .long("diff")
+                    .help("Show a unified diff of each manifest changed: on stdout (-, the default), in the --pr-body-out description (report) or as .patch files below a DIR")
+                    .value_name("TARGET")
+                    .num_args(0..=1)
+                    .default_missing_value("-")
+                    .value_parser(clap::value_parser!(DiffOutput))
+                    .requires_if("report", "pr-body-out")
+                    .conflicts_with("bump-version"),
+            )
+            .arg(
+                Arg::new("release-notes")
+                    .long("release-notes")
+                    .help("Fetch the release notes between the old and new version of each updated crate and show them, also in the --pr-body-out description")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("bump-version"),
+            )
+            .arg(
+                Arg::new("migrate")
+                    .long("migrate")
+                    .help("After major bumps, run cargo check and write a breakage report per project")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["dry-run", "plan-out"]),
+            )
+            .arg(
+                Arg::new("no-fix")
+                    .long("no-fix")
+                    .help("Only report breakage; don't apply migration rewrite rules")
+                    .action(ArgAction::SetTrue)
+                    .requires("migrate"),
+            )
+            .arg(
+                Arg::new("no-notes")
+                    .long("no-notes")
+                    .help("Don't download migration notes for bumped crates")
+                    .action(ArgAction::SetTrue)
+                    .requires("migrate"),
+            )
+            .arg(
+                Arg::new("rules")
+                    .long("rules")
+                    .help("Extra migration rules file (in addition to <config>/migrations.yaml and .kargo/migrations.yaml)")
+                    .value_name("FILE")
+                    .action(ArgAction::Append)
+                    .requires("migrate"),
+            )
+            .arg(
+                Arg::new("index-ttl")
+                    .long("index-ttl")
+                    .help("Use registry index entries cached less than SECONDS ago without asking the registry (0 always revalidates)")
+                    .value_name("SECONDS")
+                    .value_parser(clap::value_parser!(u64)),
+            )
+            .arg(
+                Arg::new("policy")
+                    .long("policy")
+                    .help("Extra policy file (in addition to <config>/policy.toml and .kargo/policy.toml)")
+                    .value_name("FILE")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("bump-version")
+                    .long("bump-version")
+                    .help("Bump package versions instead of dependencies: major, minor, patch or an exact version. Members inheriting the workspace version follow its single bump")
+                    .value_name("LEVEL")
+                    .value_parser(clap::value_parser!(VersionBump))
+                    .conflicts_with_all([
+                        "plan-out",
+                        "apply-plan",
+                        "interactive",
+                        "migrate",
+                        "update-patches",
+                    ]),
+            )
+            .arg(
+                Arg::new("inherit-version")
+                    .long("inherit-version")
+                    .help("With --bump-version, switch members repeating the workspace version to version.workspace = true")
+                    .action(ArgAction::SetTrue)
+                    .requires("bump-version"),
+            )
+            .arg(
+                Arg::new("git-commit")
+                    .long("git-commit")
+                    .help("Commit the updated manifests with git: one commit per crate, per project or for the whole session (the default)")
+                    .value_name("BATCHING")
+                    .num_args(0..=1)
+                    .default_missing_value("session")
+                    .value_parser(clap::value_parser!(CommitBatching))
+                    .conflicts_with_all(["dry-run", "plan-out", "bump-version"]),
+            )
+            .arg(
+                Arg::new("commit-template")
+                    .long("commit-template")
+                    .help("Subject of the --git-commit messages; {crate}, {from}, {to}, {project} and {count} are filled in")
+                    .value_name("TEMPLATE")
+                    .requires("git-commit"),
+            )
+            .arg(
+                Arg::new("no-submodules")
+                    .long("no-submodules")
+                    .help("Leave the manifests inside git submodules alone")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("submodule"),
+            )
+            .arg(
+                Arg::new("submodule")
+                    .long("submodule")
+                    .help("Only upgrade inside this git submodule, by path below PATH (repeatable); projects outside submodules are always upgraded")
+                    .value_name("DIR")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("skip-submodule")
+                    .long("skip-submodule")
+                    .help("Leave this git submodule alone, by path below PATH (repeatable)")
+                    .value_name("DIR")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("leave-superprojects")
+                    .long("leave-superprojects")
+                    .help("With --git-commit, commit inside submodules but leave their moved pointers uncommitted in the superproject")
+                    .action(ArgAction::SetTrue)
+                    .requires("git-commit"),
+            )
+            .arg(
+                Arg::new("package")
+                    .short('p')
+                    .long("package")
+                    .help("Only upgrade this crate (repeatable); manifests that don't depend on it are skipped")
+                    .value_name("CRATE")
+                    .action(ArgAction::Append)
+                    .conflicts_with("apply-plan"),
+            )
+            .arg(
+                Arg::new("enforce")
+                    .long("enforce")
+                    .help("Fail if any manifest violates the dependency policy")
+                    .action(ArgAction::SetTrue),
+            )
+    }
+
+    fn diagnostics_schema(&self) -> Option<DiagnosticsSchema> {
+        Some(DiagnosticsSchema {
+            kinds: [
+                DiagnosticKind {
+                    code: "upgrade::failed".to_string(),
+                    severity: Severity::Error,
+                    description: "A manifest could not be upgraded".to_string(),
+                },
+                DiagnosticKind {
+                    code: "upgrade::breakage".to_string(),
+                    severity: Severity::Error,
+                    description: "Code no longer compiles after a major bump".to_string(),
+                },
+                DiagnosticKind {
+                    code: "upgrade::skipped".to_string(),
+                    severity: Severity::Warning,
+                    description: "A patched or vendored crate was left at its version".to_string(),
+                },
+            ]
+            .into_iter()
+            .chain(policy::diagnostic_kinds())
+            .collect(),
+            output_patterns: Vec::new(),
+        })
+    }
+
+    fn help_info(&self) -> Option<HelpInfo> {
+        Some(HelpInfo {
+            category: Some("deps".to_string()),
+            examples: vec![
+                Example::new(
+                    "kargo upgrade --dry-run",
+                    "Show the available updates without changing any manifest",
+                ),
+                Example::new(
+                    "kargo upgrade --plan-out plan.json",
+                    "Write the updates to a plan for review instead of applying them",
+                ),
+                Example::new(
+                    "kargo upgrade --apply-plan plan.json",
+                    "Apply a reviewed plan, failing if a manifest changed since",
+                ),
+                Example::new(
+                    "kargo upgrade --interactive",
+                    "Pick which updates to apply from a checklist",
+                ),
+                Example::new(
+                    "kargo upgrade --update-policy minor",
+                    "Only move dependencies within their major version",
+                ),
+                Example::new(
+                    "kargo upgrade -p tokio -p serde",
+                    "Only upgrade tokio and serde, wherever they're used",
+                ),
+                Example::new(
+                    "kargo upgrade --pr-body-out pr.md",
+                    "Upgrade and describe the changes for a pull request",
+                ),
+                Example::new(
+                    "kargo upgrade --dry-run --release-notes",
+                    "Read what changed in each crate before accepting its update",
+                ),
+                Example::new(
+                    "kargo upgrade --dry-run --diff patches",
+                    "Write the manifest changes as .patch files below patches/ without applying them",
+                ),
+                Example::new(
+                    "kargo upgrade --git-commit crate",
+                    "Upgrade and commit each crate's bump on its own",
+                ),
+                Example::new(
+                    "kargo upgrade --git-commit --skip-submodule vendor/legacy",
+                    "Commit inside each submodule but one, then the moved pointers in the superproject",
+                ),
+                Example::new(
+                    "kargo upgrade --migrate",
+                    "Upgrade across major versions and fix the code that breaks",
+                ),
+                Example::new(
+                    "kargo upgrade --bump-version minor --inherit-version",
+                    "Bump the workspace version once and have every member inherit it",
+                ),
+            ],
+        })
+    }
+
+    fn run(&self, ctx: ExecutionContext) -> BoxFuture {
+        let cmd = self.clap();
+        Box::pin(async move {
+            ctx.network.clone().install();
+            ctx.profiler.clone().install();
+            let matches = cmd.try_get_matches_from(&ctx.matched_args)?;
+            let root = matches
+                .get_one::<String>("path")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| ctx.current_dir.clone());
+            let mode = if matches.get_flag("per-file") {
+                WriteMode::PerFile
+            } else {
+                WriteMode::Transactional
+            };
+            let plan_out = matches.get_one::<String>("plan-out").map(PathBuf::from);
+            let dry_run = matches.get_flag("dry-run") || plan_out.is_some();
+            if let Some(registry) = &ctx.registry {
+                if !use_index(registry) {
+                    log::warn!("Registry index already in use; ignoring {}", registry);
+                }
+            }
+            use_registries(ctx.settings.get::<UpgradeSettings>()?.registries);
+            if let Some(ttl) = matches.get_one::<u64>("index-ttl") {
+                set_index_ttl(Duration::from_secs(*ttl));
+            }
+
+            let manifests = {
+                let _span = Profiler::current().span(Phase::Discovery, "manifests");
+                find_cargo_toml_files(&root)?
+            };
+            let paths = |id: &str| -> Vec<PathBuf> {
+                matches
+                    .get_many::<String>(id)
+                    .into_iter()
+                    .flatten()
+                    .map(PathBuf::from)
+                    .collect()
+            };
+            let selection = SubmoduleSelection {
+                exclude_all: matches.get_flag("no-submodules"),
+                only: paths("submodule"),
+                skip: paths("skip-submodule"),
+            };
+            let submodules = find_submodules(&root)?;
+            for path in selection.unknown(&submodules) {
+                log::warn!(
+                    "{} is not a git submodule below {}",
+                    path.display(),
+                    root.display()
+                );
+            }
+            let (manifests, excluded) = selection.filter(manifests, &submodules);
+            for (submodule, count) in &excluded {
+                println!(
+                    "Skipping {} manifest(s) in submodule {}",
+                    count,
+                    submodule.relative.display()
+                );
+            }
+            if let Some(bump) = matches.get_one::<VersionBump>("bump-version") {
+                let plan =
+                    VersionBumpPlan::new(&manifests, bump, matches.get_flag("inherit-version"))?;
+                return bump_versions(&ctx, &plan, dry_run);
+            }
+            let commits = matches
+                .get_one::<CommitBatching>("git-commit")
+                .map(|batching| {
+                    let commits = UpgradeCommits::new(*batching)
+                        .leave_superprojects(matches.get_flag("leave-superprojects"));
+                    match matches.get_one::<String>("commit-template") {
+                        Some(template) => commits.with_template(template),
+                        None => commits,
+                    }
+                });
+            if commits.is_some() {
+                UpgradeCommits::check_clean(&manifests).await?;
+            }
+            let mut policies =
+                UpdatePolicyConfig::load(&ctx.config_dir.join("update-policy.yaml"))?;
+            if let Some(policy) = matches.get_one::<UpdatePolicy>("update-policy") {
+                policies = policies.with_kinds(*policy);
+            }
+            let updater =
+                CratesIoUpdater::new(UpdateOptions::default()).with_policies(policies.global());
+            let policy_name = updater.policy().to_string();
+            let packages = ManifestPrefilter::new(
+                matches.get_many::<String>("package").into_iter().flatten(),
+            )?;
+            let session = |dry_run| {
+                UpgradeSession::new(updater.clone())
+                    .with_policies(policies.clone())
+                    .with_mode(mode)
+                    .dry_run(dry_run)
+                    .update_patches(matches.get_flag("update-patches"))
+                    .with_snapshots(Snapshot::root(&ctx.config_dir))
+                    .record_history(Attribution::new(ctx.host_version.clone()))
+                    .only_packages(packages.clone())
+            };
+            let results = match matches.get_one::<String>("apply-plan") {
+                Some(file) => {
+                    let plan = UpgradePlan::load(Path::new(file))?;
+                    println!(
+                        "Applying {} update(s) to {} manifest(s) from {}",
+                        plan.update_count(),
+                        plan.manifests.len(),
+                        file
+                    );
+                    session(dry_run).apply(&plan, &root).await?
+                }
+                None if matches.get_flag("interactive") => {
+                    let planned = session(true).run(&manifests).await;
+                    let plan = UpgradePlan::from_results(
+                        &planned,
+                        &root,
+                        policy_name.clone(),
+                        &ctx.host_version,
+                    )?;
+                    if plan.manifests.is_empty() {
+                        println!("No updates to pick from");
+                        planned
+                    } else {
+                        match checklist::pick(plan)? {
+                            Some(plan) if !plan.manifests.is_empty() => {
+                                let applied = session(false).apply(&plan, &root).await?;
+                                checklist::merge(planned, applied)
+                            }
+                            _ => {
+                                println!("No updates picked: no manifests were written");
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+                None => session(dry_run).run(&manifests).await,
+            };
+
+            let mut failed = 0;
+            for result in &results {
+                match &result.error {
+                    Some(error) => {
+                        failed += 1;
+                        eprintln!("✗ {}: {}", result.path.display(), error);
+                        ctx.diagnostics.report(
+                            Diagnostic::new("upgrade::failed", Severity::Error, error.clone()).at(
+                                &result.path,
+                                None,
+                                None,
+                            ),
+                        );
+                    }
+                    None => {
+                        let mark = if result.updates.is_empty() {
+                            "!"
+                        } else {
+                            "✓"
+                        };
+                        println!("{} {}", mark, result.path.display());
+                        for update in &result.updates {
+                            println!(
+                                "    {} {} -> {}",
+                                update.name, update.from_version, update.to_version
+                            );
+                            ctx.events.publish(if dry_run {
+                                // Counted by `--ci` and the exit status
+                                HostEvent::DependencyOutdated {
+                                    manifest: result.path.clone(),
+                                    name: update.name.clone(),
+                                    current: update.from_version.clone(),
+                                    latest: update.to_version.clone(),
+                                }
+                            } else {
+                                HostEvent::DependencyUpdated {
+                                    manifest: result.path.clone(),
+                                    name: update.name.clone(),
+                                    from: update.from_version.clone(),
+                                    to: update.to_version.clone(),
+                                }
+                            });
+                        }
+                        if !dry_run && (!result.updates.is_empty() || !result.patches.is_empty()) {
+                            ctx.events.publish(HostEvent::FileModified {
+                                path: result.path.clone(),
+                            });
+                        }
+                        for moved in &result.patches {
+                            println!(
+                                "    {} {} {} -> {}",
+                                moved.patch.describe(),
+                                moved.key,
+                                moved.from,
+                                moved.to
+                            );
+                        }
+                    }
+                }
+                for skipped in &result.skipped {
+                    let update = &skipped.update;
+                    println!(
+                        "    ⚠ {} {} -> {} skipped: {}",
+                        update.name, update.from_version, update.to_version, skipped.reason
+                    );
+                    ctx.diagnostics.report(
+                        Diagnostic::new(
+                            "upgrade::skipped",
+                            Severity::Warning,
+                            format!(
+                                "{} not bumped to {}: {}",
+                                update.name, update.to_version, skipped.reason
+                            ),
+                        )
+                        .at(&result.path, None, None),
+                    );
+                }
+            }
+
+            let diffs: Vec<_> = results
+                .iter()
+                .filter(|result| result.error.is_none())
+                .filter_map(|result| result.diff.as_ref())
+                .collect();
+            match matches.get_one::<DiffOutput>("diff") {
+                Some(DiffOutput::Stdout) => {
+                    for diff in &diffs {
+                        print!("{}", diff.unified(&root));
+                    }
+                }
+                Some(DiffOutput::Dir(dir)) => {
+                    let written = write_patches(&diffs, &root, dir)?;
+                    println!("Wrote {} patch file(s) to {}", written.len(), dir.display());
+                }
+                Some(DiffOutput::Report) | None => {}
+            }
+
+            if let Some(commits) = &commits {
+                for commit in commits.commit(&results).await? {
+                    println!(
+                        "Committed {} {} in {}",
+                        commit.id,
+                        commit.subject(),
+                        commit.repository.display()
+                    );
+                }
+            }
+
+            let pr_body_out = matches.get_one::<String>("pr-body-out");
+            let release_notes = matches.get_flag("release-notes");
+            if pr_body_out.is_some() || release_notes {
+                let mut body = PrBody::from_results(&results, &root);
+                if matches.get_one::<DiffOutput>("diff") == Some(&DiffOutput::Report) {
+                    body = body.with_diffs(&results, &root);
+                }
+                // Lookups are kept until the body is complete, so a rerun
+                // after an interruption doesn't repeat them
+                let mut journal = Journal::open(&ctx.cache_dir, "pr-body");
+                body.fetch_repositories(&mut journal).await;
+                if release_notes {
+                    body.fetch_release_notes(&mut journal).await;
+                    if pr_body_out.map(String::as_str) != Some("-") {
+                        print_release_notes(&body);
+                    }
+                }
+                if let Some(file) = pr_body_out {
+                    body.write(Path::new(file))?;
+                }
+                journal.finish();
+            }
+
+            if let Some(file) = &plan_out {
+                if failed > 0 {
+                    bail!(
+                        "{} of {} manifests could not be planned; no plan was written",
+                        failed,
+                        results.len()
+                    );
+                }
+                let plan =
+                    UpgradePlan::from_results(&results, &root, policy_name, &ctx.host_version)?;
+                plan.write(file)?;
+                println!(
+                    "Plan written to {}: {} update(s) in {} manifest(s); apply it with --apply-plan",
+                    file.display(),
+                    plan.update_count(),
+                    plan.manifests.len()
+                );
+            }
+
+            let mut policy_files = vec![ctx.config_dir.join("policy.toml"), root.join(POLICY_FILE)];
+            if let Some(files) = matches.get_many::<String>("policy") {
+                policy_files.extend(files.map(PathBuf::from));
+            }
+            let policy = Policy::load(&policy_files)?;
+            let mut violations = 0;
+            if !policy.is_empty() {
+                for manifest in &manifests {
+                    let found = match policy.check_manifest(manifest) {
+                        Ok(found) => found,
+                        Err(e) => {
+                            log::warn!("Policy not checked for {}: {:#}", manifest.display(), e);
+                            continue;
+                        }
+                    };
+                    for diagnostic in found {
+                        violations += 1;
+                        eprintln!(
+                            "✗ {}: {} [{}]",
+                            manifest.display(),
+                            diagnostic.message,
+                            diagnostic.code
+                        );
+                        ctx.diagnostics.report(diagnostic);
+                    }
+                }
+            }
+
+            if matches.get_flag("migrate") {
+                let mut rule_files = vec![
+                    ctx.config_dir.join("migrations.yaml"),
+                    root.join(".kargo").join("migrations.yaml"),
+                ];
+                if let Some(files) = matches.get_many::<String>("rules") {
+                    rule_files.extend(files.map(PathBuf::from));
+                }
+                let assistant = MigrationAssistant::new(MigrationRules::load(&rule_files)?)
+                    .fetch_notes(!matches.get_flag("no-notes"))
+                    .apply_fixes(!matches.get_flag("no-fix"))
+                    .target_dir(ctx.target_dir.clone());
+                for report in assistant.run(&results).await? {
+                    let path = report.write()?;
+                    for breakage in &report.breakages {
+                        ctx.diagnostics.report(
+                            Diagnostic::new(
+                                "upgrade::breakage",
+                                Severity::Error,
+                                format!("{}: {}", breakage.crate_name, breakage.message),
+                            )
+                            .at(
+                                &breakage.file,
+                                Some(breakage.line),
+                                Some(breakage.column),
+                            ),
+                        );
+                    }
+                    let remaining = report.remaining.unwrap_or(report.breakages.len());
+                    println!(
+                        "{}: {} breakage(s), {} fix(es) applied, {} remaining — {}",
+                        report.project.display(),
+                        report.breakages.len(),
+                        report.fixes.len(),
+                        remaining,
+                        path.display()
+                    );
+                }
+            }
+
+            if failed > 0 {
+                bail!("{} of {} manifests were not updated", failed, results.len());
+            }
+            if violations > 0 && matches.get_flag("enforce") {
+                bail!("{} dependency policy violation(s)", violations);
+            }
+            if dry_run && plan_out.is_none() {
+                println!("Dry run: no manifests were written");
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Print the release notes fetched for each update, indented below it
+fn print_release_notes(body: &PrBody) {
+    for dependency in &body.dependencies {
+        match &dependency.notes {
+            Some(notes) => {
+                println!(
+                    "\nRelease notes for {} {} -> {} ({}, {}):",
+                    dependency.package, dependency.from, dependency.to, notes.source, notes.url
+                );
+                for line in notes.excerpt.lines() {
+                    println!("    {}", line);
+                }
+            }
+            None => println!(
+                "\nNo release notes found for {} {} -> {}; see {}",
+                dependency.package,
+                dependency.from,
+                dependency.to,
+                dependency.changelog_url()
+            ),
+        }
+    }
+}
+
+/// Print and, unless `dry_run`, write a package version bump
+fn bump_versions(
+    ctx: &ExecutionContext,
+    plan: &VersionBumpPlan,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    for change in &plan.changes {
+        let what = match &change.target {
+            VersionTarget::Workspace => "[workspace.package]".to_string(),
+            VersionTarget::Package { name } => name.clone(),
+            VersionTarget::Inherit { name } => format!("{} (now inherited)", name),
+        };
+        println!(
+            "✓ {} {}: {} -> {}",
+            change.manifest.display(),
+            what,
+            change.from,
+            change.to
+        );
+    }
+    for (manifest, name) in &plan.inherited {
+        println!(
+            "  {} {}: follows the workspace version",
+            manifest.display(),
+            name
+        );
+    }
+    for (manifest, name) in &plan.could_inherit {
+        println!(
+            "! {} {}: same version as its workspace; --inherit-version switches it to version.workspace = true",
+            manifest.display(),
+            name
+        );
+    }
+    if plan.changes.is_empty() {
+        println!("No package versions to bump");
+        return Ok(());
+    }
+    if dry_run {
+        println!("Dry run: no manifests were written");
+        return Ok(());
+    }
+    for path in plan.apply()? {
+        ctx.events.publish(HostEvent::FileModified { path });
+    }
+    Ok(())
+}
+
+// Let the host create this plugin, with its panics caught inside it
+kargo_plugin_api::export_plugin!(UpgradePlugin);
+
+// Route this plugin's `log` records through the host's subscriber
+kargo_plugin_api::export_host_logger!();
+
+// Let the host check this plugin was built against an API it loads
+kargo_plugin_api::export_plugin_compat!();
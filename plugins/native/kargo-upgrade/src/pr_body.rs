@@ -0,0 +1,332 @@
+//! Pull request descriptions for upgrade sessions
+//!
+//! `kargo upgrade --pr-body-out FILE` (`-` for stdout) writes a markdown
+//! summary of the session, ready to paste into a pull request: one table per
+//! kind of bump (major, minor, patch) listing each crate with its old and new
+//! version, the manifests it changed in, and links to its changelog and to a
+//! diff of the two releases. With `--diff report`, the unified diff of each
+//! manifest follows the tables, and with `--release-notes` so do the notes of
+//! the releases each crate moves across.
+
+use anyhow::Result;
+use kargo_plugin_api::resume::Journal;
+use kargo_plugin_api::{HttpClient, WriteGuard};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::crates_io::get_repository;
+use crate::migrate::{is_breaking, padded};
+use crate::models::Origin;
+use crate::release_notes::{ReleaseNotes, ReleaseNotesFetcher};
+use crate::types::UpdateResult;
+
+/// How far an update moves a dependency, under Cargo's semver rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    /// Breaking: a new major version, or a new minor version of a `0.x` crate
+    Major,
+    Minor,
+    Patch,
+}
+
+impl Bump {
+    pub fn classify(from: &str, to: &str) -> Self {
+        if is_breaking(from, to) {
+            return Self::Major;
+        }
+        match (padded(from), padded(to)) {
+            (Some(from), Some(to)) if from[..2] != to[..2] => Self::Minor,
+            _ => Self::Patch,
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Self::Major => "Major (breaking)",
+            Self::Minor => "Minor",
+            Self::Patch => "Patch",
+        }
+    }
+}
+
+/// One row of the summary: a crate moved between two versions
+#[derive(Debug, Clone)]
+pub struct BumpedDependency {
+    /// Registry name, which the links point at
+    pub package: String,
+    pub from: String,
+    pub to: String,
+    pub bump: Bump,
+    /// Manifests the update was made in, relative to the session root
+    pub manifests: Vec<PathBuf>,
+    /// Repository declared on crates.io, once looked up
+    pub repository: Option<String>,
+    /// Notes of the releases between `from` and `to`, once fetched
+    pub notes: Option<ReleaseNotes>,
+}
+
+impl BumpedDependency {
+    /// Where the crate's release notes live: its changelog on GitHub, or its
+    /// version list on crates.io
+    pub fn changelog_url(&self) -> String {
+        match self
+            .repository
+            .as_deref()
+            .map(|repo| repo.trim_end_matches('/').trim_end_matches(".git"))
+            .filter(|repo| repo.starts_with("https://github.com/"))
+        {
+            Some(repo) => format!("{}/blob/HEAD/CHANGELOG.md", repo),
+            None => format!("https://crates.io/crates/{}/versions", self.package),
+        }
+    }
+
+    /// Source diff between the two releases
+    pub fn diff_url(&self) -> String {
+        let exact = |version: &str| {
+            padded(version)
+                .map(|[major, minor, patch]| format!("{}.{}.{}", major, minor, patch))
+                .unwrap_or_else(|| version.to_string())
+        };
+        format!(
+            "https://diff.rs/{}/{}/{}",
+            self.package,
+            exact(&self.from),
+            exact(&self.to)
+        )
+    }
+}
+
+/// Summary of the updates of a session
+#[derive(Debug, Clone, Default)]
+pub struct PrBody {
+    pub dependencies: Vec<BumpedDependency>,
+    pub manifests: usize,
+    /// Unified diffs of the written manifests, by path relative to the root
+    pub diffs: Vec<(PathBuf, String)>,
+}
+
+impl PrBody {
+    /// Collect the updates of successful results below `root`. The same crate
+    /// moved between the same versions in several manifests is one row.
+    pub fn from_results(results: &[UpdateResult], root: &Path) -> Self {
+        let mut rows: BTreeMap<(String, String, String), BumpedDependency> = BTreeMap::new();
+        let mut manifests = 0;
+        for result in results {
+            if result.error.is_some() || result.updates.is_empty() {
+                continue;
+            }
+            manifests += 1;
+            let path = result
+                .path
+                .strip_prefix(root)
+                .unwrap_or(&result.path)
+                .to_path_buf();
+            for update in &result.updates {
+                let package = update.dependency.package_name().to_string();
+                let key = (
+                    package.clone(),
+                    update.from_version.clone(),
+                    update.to_version.clone(),
+                );
+                rows.entry(key)
+                    .or_insert_with(|| BumpedDependency {
+                        bump: Bump::classify(&update.from_version, &update.to_version),
+                        package,
+                        from: update.from_version.clone(),
+                        to: update.to_version.clone(),
+                        manifests: Vec::new(),
+                        // Git dependencies name theirs; the others' is
+                        // looked up by `fetch_repositories`
+                        repository: match &update.dependency.origin {
+                            Origin::Git { url, .. } => Some(url.clone()),
+                            _ => None,
+                        },
+                        notes: None,
+                    })
+                    .manifests
+                    .push(path.clone());
+            }
+        }
+        Self {
+            dependencies: rows.into_values().collect(),
+            manifests,
+            diffs: Vec::new(),
+        }
+    }
+
+    /// Add the diffs of the successful results below `root` to the body
+    pub fn with_diffs(mut self, results: &[UpdateResult], root: &Path) -> Self {
+        self.diffs = results
+            .iter()
+            .filter(|result| result.error.is_none())
+            .filter_map(|result| result.diff.as_ref())
+            .map(|diff| (diff.relative(root), diff.unified(root)))
+            .collect();
+        self
+    }
+
+    /// Look up each crate's repository on crates.io for its changelog link,
+    /// taking those `journal` kept from an interrupted run. Failures only
+    /// cost the better link.
+    pub async fn fetch_repositories(&mut self, journal: &mut Journal) {
+        let client = match HttpClient::new(concat!("kargo-upgrade/", env!("CARGO_PKG_VERSION"))) {
+            Ok(client) => client,
+            Err(e) => {
+                log::warn!("No changelog links: {}", e);
+                return;
+            }
+        };
+        let mut repositories: BTreeMap<String, Option<String>> = BTreeMap::new();
+        for dependency in &mut self.dependencies {
+            if dependency.repository.is_some() {
+                continue;
+            }
+            if !repositories.contains_key(&dependency.package) {
+                let key = format!("repository:{}", dependency.package);
+                let repository = match journal.get(&key) {
+                    Some(repository) => repository,
+                    None => match get_repository(&client, &dependency.package).await {
+                        Ok(repository) => {
+                            journal.record(&key, &repository);
+                            repository
+                        }
+                        Err(e) => {
+                            log::debug!("No repository for {}: {:#}", dependency.package, e);
+                            None
+                        }
+                    },
+                };
+                repositories.insert(dependency.package.clone(), repository);
+            }
+            dependency.repository = repositories[&dependency.package].clone();
+        }
+    }
+
+    /// Fetch the release notes of each crate with a known repository, so
+    /// call [`Self::fetch_repositories`] first, taking those `journal` kept
+    /// from an interrupted run. Crates whose notes can't be found are left
+    /// without.
+    pub async fn fetch_release_notes(&mut self, journal: &mut Journal) {
+        let client = match HttpClient::new(concat!("kargo-upgrade/", env!("CARGO_PKG_VERSION"))) {
+            Ok(client) => client,
+            Err(e) => {
+                log::warn!("No release notes: {}", e);
+                return;
+            }
+        };
+        let fetcher = ReleaseNotesFetcher::new(client);
+        for dependency in &mut self.dependencies {
+            let Some(repository) = &dependency.repository else {
+                continue;
+            };
+            let key = format!(
+                "notes:{}:{}:{}",
+                dependency.package, dependency.from, dependency.to
+            );
+            if let Some(notes) = journal.get(&key) {
+                dependency.notes = Some(notes);
+                continue;
+            }
+            dependency.notes = fetcher
+                .fetch(
+                    &dependency.package,
+                    repository,
+                    &dependency.from,
+                    &dependency.to,
+                )
+                .await;
+            if let Some(notes) = &dependency.notes {
+                journal.record(&key, notes);
+            }
+        }
+    }
+
+    /// The markdown body
+    pub fn render(&self) -> String {
+        let mut body = String::from("## Dependency updates\n\n");
+        if self.dependencies.is_empty() {
+            body.push_str("All dependencies are up to date.\n");
+            return body;
+        }
+        let count = |bump| self.dependencies.iter().filter(|d| d.bump == bump).count();
+        let _ = writeln!(
+            body,
+            "{} dependency update(s) in {} manifest(s): {} major, {} minor, {} patch.",
+            self.dependencies.len(),
+            self.manifests,
+            count(Bump::Major),
+            count(Bump::Minor),
+            count(Bump::Patch)
+        );
+
+        for bump in [Bump::Major, Bump::Minor, Bump::Patch] {
+            let rows: Vec<&BumpedDependency> = self
+                .dependencies
+                .iter()
+                .filter(|d| d.bump == bump)
+                .collect();
+            if rows.is_empty() {
+                continue;
+            }
+            let _ = write!(
+                body,
+                "\n### {}\n\n| Crate | From | To | Manifests | Links |\n|---|---|---|---|---|\n",
+                bump.heading()
+            );
+            for row in rows {
+                let manifests: Vec<String> = row
+                    .manifests
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .collect();
+                let _ = writeln!(
+                    body,
+                    "| {} | {} | {} | {} | [changelog]({}) · [diff]({}) |",
+                    row.package,
+                    row.from,
+                    row.to,
+                    manifests.join(", "),
+                    row.changelog_url(),
+                    row.diff_url()
+                );
+            }
+        }
+        let noted: Vec<(&BumpedDependency, &ReleaseNotes)> = self
+            .dependencies
+            .iter()
+            .filter_map(|d| d.notes.as_ref().map(|notes| (d, notes)))
+            .collect();
+        if !noted.is_empty() {
+            body.push_str("\n### Release notes\n");
+            for (row, notes) in noted {
+                let _ = write!(
+                    body,
+                    "\n<details><summary>{} {} → {} (<a href=\"{}\">{}</a>)</summary>\n\n{}\n\n</details>\n",
+                    row.package, row.from, row.to, notes.url, notes.source, notes.excerpt
+                );
+            }
+        }
+        if !self.diffs.is_empty() {
+            body.push_str("\n### Manifest changes\n");
+            for (path, diff) in &self.diffs {
+                let _ = write!(
+                    body,
+                    "\n<details><summary><code>{}</code></summary>\n\n```diff\n{}```\n\n</details>\n",
+                    path.display(),
+                    diff
+                );
+            }
+        }
+        body
+    }
+
+    /// Write the body to `path`, or to stdout for `-`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if path == Path::new("-") {
+            print!("{}", self.render());
+            return Ok(());
+        }
+        WriteGuard::current().write(path, self.render())
+    }
+}
@@ -0,0 +1,287 @@
+//! Cheap content pre-filter that avoids full TOML parses for irrelevant manifests
+//!
+//! Parsing a manifest into a `DocumentMut` dominates planning time across large
+//! fleets, yet most manifests don't mention the crates being upgraded. The
+//! pre-filter scans raw bytes for candidate dependency names first and only
+//! parses manifests that could possibly contain one of them. It errs on the side
+//! of false positives: a hit still goes through the real parser.
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use regex::bytes::Regex;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Value};
+
+use kargo_plugin_api::metadata::{DepKind, MetadataMode, ProjectMetadata};
+
+use crate::models::{Dependency, DependencyLocation, DependencyParser, DependencySource};
+use crate::parsers::CargoParser;
+
+/// Byte-level matcher for a set of dependency names
+#[derive(Debug, Clone)]
+pub struct ManifestPrefilter {
+    /// `None` accepts every manifest (no names to narrow by)
+    pattern: Option<Regex>,
+    /// The names, with `_` spelled `-`
+    names: BTreeSet<String>,
+}
+
+impl ManifestPrefilter {
+    /// Build a pre-filter matching any of `names`.
+    ///
+    /// Names match on identifier boundaries and treat `-` and `_` as equivalent,
+    /// so `serde-json` in a manifest is still a candidate for `serde_json`.
+    pub fn new<I, S>(names: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names: BTreeSet<String> = names
+            .into_iter()
+            .map(|name| normalize(name.as_ref()))
+            .filter(|name| !name.is_empty())
+            .collect();
+        let alternatives: Vec<String> = names
+            .iter()
+            .map(|name| {
+                name.split('-')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join("[-_]")
+            })
+            .collect();
+
+        if alternatives.is_empty() {
+            return Ok(Self::any());
+        }
+
+        let pattern = format!(
+            r"(?:^|[^A-Za-z0-9_-])(?:{})(?:[^A-Za-z0-9_-]|$)",
+            alternatives.join("|")
+        );
+        let pattern = Regex::new(&pattern).context("Failed to build manifest pre-filter")?;
+        Ok(Self {
+            pattern: Some(pattern),
+            names,
+        })
+    }
+
+    /// A pre-filter that accepts every manifest
+    pub fn any() -> Self {
+        Self {
+            pattern: None,
+            names: BTreeSet::new(),
+        }
+    }
+
+    /// Whether the crate `name` is one the pre-filter was built for
+    pub fn wants(&self, name: &str) -> bool {
+        self.names.is_empty() || self.names.contains(&normalize(name))
+    }
+
+    /// Whether `content` could declare one of the candidate dependencies
+    pub fn may_contain(&self, content: &[u8]) -> bool {
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(content),
+            None => true,
+        }
+    }
+}
+
+/// A manifest that passed the pre-filter, together with its parsed dependencies
+#[derive(Debug, Clone)]
+pub struct CandidateManifest {
+    /// The parsed source, ready to hand to a writer
+    pub source: DependencySource,
+    /// Dependencies declared by the manifest
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Read and parse the manifests that may reference a candidate dependency.
+///
+/// Files are read as raw bytes and only converted to a `String` (without
+/// copying) once they pass the pre-filter. Manifests that fail to read or parse
+/// are logged and skipped so one broken file doesn't stop a fleet-wide plan.
+pub fn collect_candidates(
+    paths: &[PathBuf],
+    prefilter: &ManifestPrefilter,
+) -> Vec<CandidateManifest> {
+    let parser = CargoParser;
+    paths
+        .par_iter()
+        .filter_map(|path| match load_candidate(path, prefilter, &parser) {
+            Ok(candidate) => candidate,
+            Err(e) => {
+                log::warn!("Skipping {}: {:#}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn load_candidate(
+    path: &Path,
+    prefilter: &ManifestPrefilter,
+    parser: &CargoParser,
+) -> Result<Option<CandidateManifest>> {
+    let bytes = std::fs::read(path)?;
+    if !prefilter.may_contain(&bytes) {
+        return Ok(None);
+    }
+
+    let content = String::from_utf8(bytes).context("Manifest is not valid UTF-8")?;
+    let source = DependencySource::CargoToml {
+        path: path.to_path_buf(),
+        is_workspace: content.contains("[workspace]"),
+        content,
+    };
+    let dependencies = parser.parse(&source)?;
+    Ok(Some(CandidateManifest {
+        source,
+        dependencies,
+    }))
+}
+
+/// Keep only the dependencies `prefilter` was built for, dropping manifests
+/// that are left without any.
+///
+/// The byte scan also hits manifests that merely mention a name, in a
+/// comment or as their own package name, and parses every dependency of the
+/// manifests that do declare one; this narrows candidates down to the named
+/// crates. A pre-filter without names keeps everything.
+pub fn retain_named(candidates: &mut Vec<CandidateManifest>, prefilter: &ManifestPrefilter) {
+    if prefilter.names.is_empty() {
+        return;
+    }
+    candidates.retain_mut(|candidate| {
+        candidate
+            .dependencies
+            .retain(|dependency| prefilter.wants(dependency.package_name()));
+        !candidate.dependencies.is_empty()
+    });
+}
+
+/// Check parsed dependencies against `cargo metadata`.
+///
+/// Cargo's view settles what the TOML alone can't: renamed dependencies are
+/// looked up under their registry package name, and path and git dependencies
+/// that also carry a `version` are dropped since there's no registry release to
+/// move them to. Manifests cargo can't load (or virtual workspace roots) keep
+/// what the parser found. Metadata is loaded once per workspace.
+pub fn refine_with_metadata(candidates: &mut [CandidateManifest]) {
+    let mut loaded: Vec<ProjectMetadata> = Vec::new();
+    for candidate in candidates {
+        let path = candidate.source.path().to_path_buf();
+        if let DependencySource::RustScript { .. } = candidate.source {
+            continue;
+        }
+        if !loaded.iter().any(|m| m.member_at(&path).is_some()) {
+            match ProjectMetadata::load(&path, MetadataMode::Declared) {
+                Ok(metadata) => loaded.push(metadata),
+                Err(e) => {
+                    log::debug!("Using manifest contents only: {:#}", e);
+                    continue;
+                }
+            }
+        }
+        let Some(package) = loaded.iter().find_map(|m| m.member_at(&path)) else {
+            continue;
+        };
+
+        candidate.dependencies.retain_mut(|dependency| {
+            let kind = match dependency.location {
+                DependencyLocation::CargoTomlDirect => DepKind::Normal,
+                DependencyLocation::CargoTomlDev => DepKind::Dev,
+                DependencyLocation::CargoTomlBuild => DepKind::Build,
+                _ => return true,
+            };
+            let declared = package
+                .dependencies
+                .iter()
+                .find(|d| d.key() == dependency.name && d.kind == kind && d.target.is_none());
+            match declared {
+                Some(declared) if declared.is_registry() => {
+                    if declared.rename.is_some() {
+                        dependency.package = Some(declared.package.clone());
+                    }
+                    true
+                }
+                Some(_) => false,
+                // e.g. `[workspace.dependencies]` of a root package
+                None => true,
+            }
+        });
+    }
+}
+
+/// Drop dependencies on crates that are never published, returning their
+/// names.
+///
+/// Packages among the candidates with `publish = false` (or `publish = []`,
+/// set directly or inherited from `[workspace.package]`) have no registry
+/// release: looking them up is wasted work, and when a public crate happens
+/// to have the same name it gets proposed as an update. Only manifests that
+/// mention `publish` are parsed again.
+pub fn exclude_private(candidates: &mut [CandidateManifest]) -> BTreeSet<String> {
+    let manifests: Vec<(&Path, DocumentMut)> = candidates
+        .iter()
+        .filter(|candidate| candidate.source.content().contains("publish"))
+        .filter_map(|candidate| {
+            let document = candidate.source.content().parse().ok()?;
+            Some((candidate.source.path(), document))
+        })
+        .collect();
+    // Workspace roots whose members inherit `publish = false`
+    let private_roots: Vec<&Path> = manifests
+        .iter()
+        .filter(|(_, document)| {
+            document
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package"))
+                .and_then(|package| package.get("publish"))
+                .is_some_and(is_private)
+        })
+        .filter_map(|(path, _)| path.parent())
+        .collect();
+    let private: BTreeSet<String> = manifests
+        .iter()
+        .filter_map(|(path, document)| {
+            let package = document.get("package")?;
+            let publish = package.get("publish")?;
+            let inherited = publish
+                .get("workspace")
+                .and_then(Item::as_bool)
+                .unwrap_or(false);
+            let private = if inherited {
+                private_roots.iter().any(|root| path.starts_with(root))
+            } else {
+                is_private(publish)
+            };
+            private.then(|| package.get("name")?.as_str().map(str::to_string))?
+        })
+        .collect();
+
+    if !private.is_empty() {
+        for candidate in candidates.iter_mut() {
+            candidate
+                .dependencies
+                .retain(|dependency| !private.contains(dependency.package_name()));
+        }
+    }
+    private
+}
+
+/// `name` with `_` spelled `-`, as cargo treats them alike
+fn normalize(name: &str) -> String {
+    name.replace('_', "-")
+}
+
+/// Whether a `publish` field keeps the package off every registry
+fn is_private(publish: &Item) -> bool {
+    match publish.as_value() {
+        Some(Value::Boolean(publish)) => !*publish.value(),
+        Some(Value::Array(registries)) => registries.is_empty(),
+        _ => false,
+    }
+}
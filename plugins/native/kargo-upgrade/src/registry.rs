@@ -0,0 +1,456 @@
+//! Sparse registry index client
+//!
+//! Looks crates up through the sparse index protocol
+//! (<https://doc.rust-lang.org/cargo/reference/registry-index.html#sparse-protocol>)
+//! rather than the crates.io web API. Index files are cached on disk together
+//! with their `ETag`/`Last-Modified` validators and the time they were
+//! fetched: files younger than the index's time to live are used as they are,
+//! older ones are revalidated with a conditional request. Concurrent lookups
+//! of the same crate share one request, and all lookups share one pooled HTTP
+//! client. When kargo is offline, lookups are answered from the cache alone.
+//!
+//! Dependencies with `registry = "name"` are looked up in that registry's
+//! sparse index, configured in the upgrade plugin's settings:
+//!
+//! ```toml
+//! [plugin_settings.upgrade.registries]
+//! my-company = "sparse+https://cargo.example.com/index/"
+//! ```
+//!
+//! or, as for cargo, with `CARGO_REGISTRIES_MY_COMPANY_INDEX`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::stream::{self, StreamExt};
+use kargo_plugin_api::{HttpClient, OfflineError, WriteGuard};
+use once_cell::sync::{Lazy, OnceCell};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Sparse index for crates.io
+pub const CRATES_IO_SPARSE_INDEX: &str = "https://index.crates.io";
+
+/// Number of index files fetched concurrently during prefetch
+const PREFETCH_CONCURRENCY: usize = 32;
+
+/// How long the shared index uses a cached index file without revalidating it
+pub const DEFAULT_INDEX_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Shared index used by the version lookups
+static DEFAULT_INDEX: OnceCell<SparseIndex> = OnceCell::new();
+
+/// Index URLs of the registries named in the settings
+static REGISTRIES: Lazy<Mutex<BTreeMap<String, String>>> = Lazy::new(Default::default);
+
+/// Indexes of the named registries looked up so far. They live as long as
+/// the process, like [`DEFAULT_INDEX`]; there are only ever a few.
+static NAMED_INDEXES: Lazy<Mutex<HashMap<String, &'static SparseIndex>>> =
+    Lazy::new(Default::default);
+
+/// Time to live set with [`set_index_ttl`], for every index
+static INDEX_TTL: AtomicU64 = AtomicU64::new(DEFAULT_INDEX_TTL.as_secs());
+
+fn cached_index(base_url: &str) -> SparseIndex {
+    // Accept cargo's `sparse+https://...` spelling as well as a plain URL
+    let base_url = base_url.trim_start_matches("sparse+");
+    let host = base_url
+        .split("://")
+        .last()
+        .unwrap_or(base_url)
+        .trim_end_matches('/')
+        .replace(['/', ':'], "_");
+    let cache_dir = directories::ProjectDirs::from("rs", "", "kargo")
+        .map(|dirs| dirs.cache_dir().join("index").join(host));
+    let index = SparseIndex::new(base_url).with_cache_dir(cache_dir);
+    index.set_ttl(Duration::from_secs(INDEX_TTL.load(Ordering::Relaxed)));
+    index
+}
+
+/// Point the shared index at `base_url` instead of crates.io.
+///
+/// Only takes effect before the first lookup; returns whether the shared
+/// index now uses `base_url`.
+pub fn use_index(base_url: &str) -> bool {
+    let index = cached_index(base_url);
+    let wanted = index.base_url.clone();
+    DEFAULT_INDEX.set(index).is_ok() || crates_io_index().base_url == wanted
+}
+
+/// The process-wide sparse index client: crates.io unless [`use_index`]
+/// selected another registry
+pub fn crates_io_index() -> &'static SparseIndex {
+    DEFAULT_INDEX.get_or_init(|| cached_index(CRATES_IO_SPARSE_INDEX))
+}
+
+/// Use cached index files younger than `ttl` without asking the registry,
+/// in the shared index and those of named registries
+pub fn set_index_ttl(ttl: Duration) {
+    INDEX_TTL.store(ttl.as_secs(), Ordering::Relaxed);
+    crates_io_index().set_ttl(ttl);
+    for index in NAMED_INDEXES.lock().expect("index map poisoned").values() {
+        index.set_ttl(ttl);
+    }
+}
+
+/// Index URLs of the registries dependencies name with `registry = "..."`,
+/// by name; replaces those configured before
+pub fn use_registries(registries: BTreeMap<String, String>) {
+    *REGISTRIES.lock().expect("registry map poisoned") = registries;
+}
+
+/// The index of the registry named `registry`: the shared one for
+/// `crates-io`, else the URL given with [`use_registries`] or in
+/// `CARGO_REGISTRIES_<NAME>_INDEX`
+pub fn registry_index(registry: &str) -> Result<&'static SparseIndex> {
+    if registry == "crates-io" {
+        return Ok(crates_io_index());
+    }
+    let mut indexes = NAMED_INDEXES.lock().expect("index map poisoned");
+    if let Some(index) = indexes.get(registry) {
+        return Ok(index);
+    }
+
+    let configured = REGISTRIES
+        .lock()
+        .expect("registry map poisoned")
+        .get(registry)
+        .cloned();
+    let url = match configured {
+        Some(url) => url,
+        None => {
+            let var = format!(
+                "CARGO_REGISTRIES_{}_INDEX",
+                registry.to_uppercase().replace('-', "_")
+            );
+            let url = std::env::var(&var).map_err(|_| {
+                anyhow!(
+                    "No index URL for registry '{}'; set plugin_settings.upgrade.registries.{} or {}",
+                    registry,
+                    registry,
+                    var
+                )
+            })?;
+            // Cargo reads a URL without the prefix as a git index
+            if !url.starts_with("sparse+") {
+                bail!(
+                    "Registry '{}' has a git index ({}); only sparse indexes are supported",
+                    registry,
+                    url
+                );
+            }
+            url
+        }
+    };
+    let index: &'static SparseIndex = Box::leak(Box::new(cached_index(&url)));
+    indexes.insert(registry.to_string(), index);
+    Ok(index)
+}
+
+/// One published version, as recorded in an index file
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexVersion {
+    /// Crate name with its published casing
+    pub name: String,
+    /// Version string
+    pub vers: String,
+    /// Whether this version has been yanked
+    #[serde(default)]
+    pub yanked: bool,
+    /// Minimum supported Rust version, if declared
+    #[serde(default)]
+    pub rust_version: Option<String>,
+}
+
+/// An index file cached on disk with its HTTP validators
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedIndexFile {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+    /// When the registry last sent or confirmed the body, in seconds since
+    /// the epoch; files cached before this was recorded count as stale
+    #[serde(default)]
+    fetched_at: u64,
+}
+
+type Versions = Option<Arc<Vec<IndexVersion>>>;
+
+/// A lookup of this run: resolved once, by whichever caller gets there first
+type Lookup = Arc<tokio::sync::OnceCell<Versions>>;
+
+/// Client for a sparse registry index
+pub struct SparseIndex {
+    client: HttpClient,
+    base_url: String,
+    cache_dir: Option<PathBuf>,
+    /// Seconds a cached index file is used without revalidating it
+    ttl: AtomicU64,
+    /// Lookups of this run, resolved or in flight, including misses
+    resolved: Mutex<HashMap<String, Lookup>>,
+}
+
+impl SparseIndex {
+    /// Create a client for the index rooted at `base_url`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let client = HttpClient::from_builder(
+            Client::builder()
+                .user_agent(concat!("kargo-upgrade/", env!("CARGO_PKG_VERSION")))
+                .pool_max_idle_per_host(PREFETCH_CONCURRENCY),
+        )
+        .unwrap_or_else(|e| {
+            log::error!("Failed to create HTTP client: {}", e);
+            panic!("Critical error: Failed to create HTTP client: {}", e);
+        });
+
+        Self {
+            client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            cache_dir: None,
+            ttl: AtomicU64::new(0),
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Persist index files and their validators under `cache_dir`
+    pub fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Use cached index files younger than `ttl` without asking the
+    /// registry; zero, the default, revalidates every one
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.ttl.store(ttl.as_secs(), Ordering::Relaxed);
+    }
+
+    /// All published versions of a crate, or `None` if the index doesn't know it
+    pub async fn versions(&self, name: &str) -> Result<Versions> {
+        let key = name.to_lowercase();
+        let lookup = self
+            .resolved
+            .lock()
+            .expect("index memo poisoned")
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        // Callers arriving while the request is in flight wait for it; a
+        // failed lookup is left unresolved for the next caller to retry
+        lookup
+            .get_or_try_init(|| async { Ok(self.fetch(&key).await?.map(Arc::new)) })
+            .await
+            .cloned()
+    }
+
+    /// Latest stable, non-yanked version of a crate
+    pub async fn latest_version(&self, name: &str) -> Result<Option<String>> {
+        Ok(self
+            .versions(name)
+            .await?
+            .and_then(|versions| latest_version(&versions)))
+    }
+
+    /// Resolve many crates concurrently so later lookups are served from memory.
+    ///
+    /// Failures are logged rather than returned; the individual lookup will
+    /// retry and surface the error where it matters.
+    pub async fn prefetch<I, S>(&self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let pending: HashSet<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().to_lowercase())
+            .filter(|name| self.resolved_entry(name).is_none())
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+
+        log::debug!("Prefetching {} index entries", pending.len());
+        stream::iter(pending)
+            .map(|name| async move {
+                if let Err(e) = self.versions(&name).await {
+                    log::warn!("Failed to prefetch {}: {:#}", name, e);
+                }
+            })
+            .buffer_unordered(PREFETCH_CONCURRENCY)
+            .collect::<()>()
+            .await;
+    }
+
+    fn resolved_entry(&self, key: &str) -> Option<Versions> {
+        self.resolved
+            .lock()
+            .expect("index memo poisoned")
+            .get(key)
+            .and_then(|lookup| lookup.get().cloned())
+    }
+
+    async fn fetch(&self, name: &str) -> Result<Option<Vec<IndexVersion>>> {
+        let path = index_path(name);
+        let cached = self.read_cache(&path);
+        let url = format!("{}/{}", self.base_url, path);
+        if self.client.config().offline {
+            return match cached {
+                Some(cached) => Ok(Some(parse_index_file(&cached.body))),
+                None => Err(OfflineError { url }.into()),
+            };
+        }
+        if let Some(cached) = cached.as_ref().filter(|cached| self.is_fresh(cached)) {
+            log::debug!("Index entry for {} is fresh in the cache", name);
+            return Ok(Some(parse_index_file(&cached.body)));
+        }
+
+        let mut request = self.client.get(url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            } else if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| anyhow!("Failed to query registry index for {}: {:#}", name, e))?;
+
+        let body = match response.status() {
+            StatusCode::NOT_MODIFIED => {
+                log::debug!("Index entry for {} not modified", name);
+                let cached = cached.ok_or_else(|| {
+                    anyhow!("Registry returned 304 for {} without a cached copy", name)
+                })?;
+                // Confirmed current: fresh for another time to live
+                self.write_cache(
+                    &path,
+                    &CachedIndexFile {
+                        fetched_at: now(),
+                        ..cached
+                    },
+                )
+            }
+            // Registries answer 403/410 as well as 404 for unknown crates
+            StatusCode::NOT_FOUND | StatusCode::GONE | StatusCode::FORBIDDEN => return Ok(None),
+            status if status.is_success() => {
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                let etag = header(ETAG);
+                let last_modified = header(LAST_MODIFIED);
+                let body = response
+                    .text()
+                    .await
+                    .with_context(|| format!("Failed to read index entry for {}", name))?;
+                self.write_cache(
+                    &path,
+                    &CachedIndexFile {
+                        etag,
+                        last_modified,
+                        body,
+                        fetched_at: now(),
+                    },
+                )
+            }
+            status => bail!("Registry index returned {} for {}", status, name),
+        };
+
+        Ok(Some(parse_index_file(&body)))
+    }
+
+    fn is_fresh(&self, cached: &CachedIndexFile) -> bool {
+        now().saturating_sub(cached.fetched_at) < self.ttl.load(Ordering::Relaxed)
+    }
+
+    fn cache_path(&self, index_path: &str) -> Option<PathBuf> {
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", index_path)))
+    }
+
+    fn read_cache(&self, index_path: &str) -> Option<CachedIndexFile> {
+        let path = self.cache_path(index_path)?;
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Store an index file, returning its body. Cache failures only cost a
+    /// conditional request next time, so they are logged and ignored.
+    fn write_cache(&self, index_path: &str, file: &CachedIndexFile) -> String {
+        if let Some(path) = self
+            .cache_path(index_path)
+            .filter(|_| !WriteGuard::current().is_read_only())
+        {
+            let result = path
+                .parent()
+                .map(std::fs::create_dir_all)
+                .transpose()
+                .and_then(|_| {
+                    let json = serde_json::to_string(file).map_err(std::io::Error::from)?;
+                    std::fs::write(&path, json)
+                });
+            if let Err(e) = result {
+                log::warn!("Failed to cache index entry {}: {}", path.display(), e);
+            }
+        }
+        file.body.clone()
+    }
+}
+
+/// Seconds since the epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Path of a crate's file within a sparse index, following cargo's layout
+pub fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Parse the newline-delimited JSON records of an index file, skipping any
+/// lines this client doesn't understand
+pub fn parse_index_file(body: &str) -> Vec<IndexVersion> {
+    body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Highest non-yanked version, preferring stable releases over pre-releases
+pub fn latest_version(versions: &[IndexVersion]) -> Option<String> {
+    let parsed: Vec<(semver::Version, &IndexVersion)> = versions
+        .iter()
+        .filter(|v| !v.yanked)
+        .filter_map(|v| {
+            semver::Version::parse(&v.vers)
+                .ok()
+                .map(|parsed| (parsed, v))
+        })
+        .collect();
+
+    parsed
+        .iter()
+        .filter(|(version, _)| version.pre.is_empty())
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .or_else(|| parsed.iter().max_by(|a, b| a.0.cmp(&b.0)))
+        .map(|(_, v)| v.vers.clone())
+}
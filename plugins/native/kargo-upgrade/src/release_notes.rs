@@ -0,0 +1,254 @@
+//! Release notes for proposed updates
+//!
+//! With `--release-notes`, every update is reported together with what the
+//! crate's authors wrote about the releases it skips over, so breaking changes
+//! can be assessed before the update is accepted. The crate's repository is
+//! looked up on crates.io; for GitHub repositories the notes come from the
+//! GitHub releases between the two versions, or failing that from the
+//! matching sections of the changelog file at the head of the repository.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use kargo_plugin_api::HttpClient;
+
+use crate::migrate::padded;
+
+/// Changelog files tried, in order, when a crate has no GitHub releases
+const CHANGELOG_FILES: [&str; 4] = ["CHANGELOG.md", "CHANGES.md", "RELEASES.md", "HISTORY.md"];
+
+/// Upper bound on the lines kept in an excerpt
+pub const MAX_EXCERPT_LINES: usize = 200;
+
+/// Where a crate's release notes were found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotesSource {
+    GithubReleases,
+    Changelog,
+}
+
+impl fmt::Display for NotesSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::GithubReleases => "GitHub releases",
+            Self::Changelog => "changelog",
+        })
+    }
+}
+
+/// The notes of the releases an update moves across
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    pub source: NotesSource,
+    /// Page the excerpt was taken from
+    pub url: String,
+    /// Notes of the releases after `from`, up to and including `to`
+    pub excerpt: String,
+}
+
+/// A release as listed by the GitHub API
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubRelease {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// Fetches release notes, best effort: a crate without findable notes is
+/// reported without them
+pub struct ReleaseNotesFetcher {
+    client: HttpClient,
+}
+
+impl ReleaseNotesFetcher {
+    pub fn new(client: HttpClient) -> Self {
+        Self { client }
+    }
+
+    /// Notes for moving `package` from `from` to `to`, published in its
+    /// `repository`
+    pub async fn fetch(
+        &self,
+        package: &str,
+        repository: &str,
+        from: &str,
+        to: &str,
+    ) -> Option<ReleaseNotes> {
+        let (owner, repo) = github_repo(repository)?;
+        match self.github_releases(package, owner, repo, from, to).await {
+            Some(notes) => Some(notes),
+            None => self.changelog(repository, from, to).await,
+        }
+    }
+
+    async fn github_releases(
+        &self,
+        package: &str,
+        owner: &str,
+        repo: &str,
+        from: &str,
+        to: &str,
+    ) -> Option<ReleaseNotes> {
+        let url = format!(
+            "https://api.github.com/repos/{}/{}/releases?per_page=100",
+            owner, repo
+        );
+        let response = match self.client.send(self.client.get(&url)).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                log::debug!("No GitHub releases for {}: {}", package, response.status());
+                return None;
+            }
+            Err(e) => {
+                log::debug!("No GitHub releases for {}: {:#}", package, e);
+                return None;
+            }
+        };
+        let releases: Vec<GithubRelease> = response.json().await.ok()?;
+        Some(ReleaseNotes {
+            source: NotesSource::GithubReleases,
+            url: format!("https://github.com/{}/{}/releases", owner, repo),
+            excerpt: releases_excerpt(&releases, package, from, to)?,
+        })
+    }
+
+    async fn changelog(&self, repository: &str, from: &str, to: &str) -> Option<ReleaseNotes> {
+        let raw_base = github_raw_base(repository)?;
+        for file in CHANGELOG_FILES {
+            let url = format!("{}/{}", raw_base, file);
+            let response = match self.client.send(self.client.get(&url)).await {
+                Ok(response) if response.status().is_success() => response,
+                _ => continue,
+            };
+            let Ok(body) = response.text().await else {
+                continue;
+            };
+            let Some(excerpt) = changelog_excerpt(&body, from, to) else {
+                continue;
+            };
+            return Some(ReleaseNotes {
+                source: NotesSource::Changelog,
+                url: format!("{}/blob/HEAD/{}", repository.trim_end_matches('/'), file),
+                excerpt,
+            });
+        }
+        None
+    }
+}
+
+/// Sections of a changelog whose heading names a release after `from`, up to
+/// and including `to`
+pub fn changelog_excerpt(notes: &str, from: &str, to: &str) -> Option<String> {
+    let [major, minor, patch] = padded(from)?;
+    sections_between(notes, [major, minor, patch + 1], padded(to)?)
+}
+
+/// Sections of a changelog whose heading names a release in `first..=last`,
+/// cut off after [`MAX_EXCERPT_LINES`] lines
+pub(crate) fn sections_between(notes: &str, first: [u64; 3], last: [u64; 3]) -> Option<String> {
+    let heading_version = Regex::new(r"\d+\.\d+(?:\.\d+)?").expect("Invalid version regex");
+
+    let mut excerpt = Vec::new();
+    let mut in_range = false;
+    for line in notes.lines() {
+        if line.starts_with('#') {
+            if let Some(version) = heading_version.find(line).and_then(|m| padded(m.as_str())) {
+                in_range = version >= first && version <= last;
+            }
+        }
+        if in_range {
+            excerpt.push(line);
+            if excerpt.len() >= MAX_EXCERPT_LINES {
+                break;
+            }
+        }
+    }
+    (!excerpt.is_empty()).then(|| excerpt.join("\n"))
+}
+
+/// Notes of the published releases of `package` after `from`, up to and
+/// including `to`, oldest first. Tags of other crates released from the same
+/// repository (`other-crate-v1.2.0`) are left out.
+pub fn releases_excerpt(
+    releases: &[GithubRelease],
+    package: &str,
+    from: &str,
+    to: &str,
+) -> Option<String> {
+    let (from, to) = (padded(from)?, padded(to)?);
+    let tag_version = Regex::new(r"^(.*?)v?(\d+\.\d+\.\d+)$").expect("Invalid tag regex");
+
+    let mut picked: Vec<([u64; 3], &GithubRelease)> = releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| {
+            let captures = tag_version.captures(&release.tag_name)?;
+            let prefix = captures[1].trim_end_matches(['-', '_', '@', '/']);
+            if !prefix.is_empty() && prefix != package {
+                return None;
+            }
+            let version = padded(&captures[2])?;
+            (version > from && version <= to).then_some((version, release))
+        })
+        .filter(|(_, release)| {
+            release
+                .body
+                .as_deref()
+                .is_some_and(|body| !body.trim().is_empty())
+        })
+        .collect();
+    picked.sort_by_key(|(version, _)| *version);
+
+    let mut excerpt = Vec::new();
+    for (_, release) in picked {
+        if !excerpt.is_empty() {
+            excerpt.push(String::new());
+        }
+        let title = release
+            .name
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(&release.tag_name);
+        excerpt.push(format!("## {}", title.trim()));
+        excerpt.push(String::new());
+        excerpt.extend(
+            release
+                .body
+                .as_deref()
+                .unwrap_or_default()
+                .trim()
+                .lines()
+                .map(|line| line.trim_end().to_string()),
+        );
+    }
+    if excerpt.is_empty() {
+        return None;
+    }
+    excerpt.truncate(MAX_EXCERPT_LINES);
+    Some(excerpt.join("\n"))
+}
+
+/// `(owner, repo)` of a GitHub repository URL
+fn github_repo(repository: &str) -> Option<(&str, &str)> {
+    let path = repository
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .strip_prefix("https://github.com/")?;
+    let mut segments = path.split('/');
+    Some((segments.next()?, segments.next()?))
+}
+
+/// `https://raw.githubusercontent.com/<owner>/<repo>/HEAD` for a GitHub repository URL
+pub(crate) fn github_raw_base(repository: &str) -> Option<String> {
+    let (owner, repo) = github_repo(repository)?;
+    Some(format!(
+        "https://raw.githubusercontent.com/{}/{}/HEAD",
+        owner, repo
+    ))
+}
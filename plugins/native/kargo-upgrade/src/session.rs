@@ -0,0 +1,685 @@
+//! Upgrade sessions that write manifests transactionally
+//!
+//! By default every manifest of a workspace is planned before anything touches
+//! disk and the writes are committed together: if a version lookup or a write
+//! fails, manifests already written are restored from their backups, so a
+//! workspace never ends up with only some of its members bumped.
+//! [`WriteMode::PerFile`] commits each manifest on its own instead. With
+//! [`UpgradeSession::with_snapshots`] the backups go into a snapshot of the
+//! run that `kargo restore` can roll back to later.
+//!
+//! With [`UpgradeSession::record_history`], every committed update is also
+//! appended to the project's `.kargo/history.jsonl`. [`UpgradeSession::apply`]
+//! writes the updates of an [`UpgradePlan`] instead of looking new versions up.
+//! [`UpgradeSession::with_policies`] lets each manifest's update policies (see
+//! [`crate::update_policy`]) decide how far its dependencies move.
+//!
+//! [`UpgradeSession::only_packages`] narrows a run to some crates, skipping
+//! manifests that don't depend on them before they're parsed (see
+//! [`crate::prefilter`]).
+//!
+//! Crates that are patched or vendored (see [`crate::overrides`]) are skipped,
+//! since bumping their requirement breaks the build. With
+//! [`UpgradeSession::update_patches`] they're updated anyway: `[patch]`
+//! entries pinned to a version tag or registry version move to the new
+//! version in the same transaction, and vendored workspaces are re-vendored
+//! after they're written.
+
+use anyhow::{bail, Context, Result};
+use kargo_plugin_api::history::{History, UpgradeRecord};
+use kargo_plugin_api::profile::{Phase, Profiler};
+use kargo_plugin_api::WriteGuard;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::backup::BackupManager;
+use crate::diff::ManifestDiff;
+use crate::models::{DependencySource, DependencyUpdate, DependencyUpdater, DependencyWriter};
+use crate::overrides::{
+    revendor, set_patch_field, tag_exists, Overrides, Patch, PatchSource, PatchUpdate,
+};
+use crate::plan::UpgradePlan;
+use crate::prefilter::{
+    collect_candidates, exclude_private, refine_with_metadata, retain_named, CandidateManifest,
+    ManifestPrefilter,
+};
+use crate::types::{CrateType, SkippedUpdate, UpdateResult};
+use crate::update_policy::{UpdatePolicies, UpdatePolicyConfig};
+use crate::writers::CargoWriter;
+
+/// How a session groups its writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WriteMode {
+    /// All manifests of a workspace are updated, or none are
+    #[default]
+    Transactional,
+    /// Each manifest is updated independently of the others
+    PerFile,
+}
+
+/// Who and what committed updates are attributed to in the project history
+#[derive(Debug, Clone)]
+pub struct Attribution {
+    /// Shared by every record of one run
+    pub session: String,
+    pub kargo_version: String,
+    pub user: Option<String>,
+}
+
+impl Attribution {
+    /// Attribute updates to a new session of `kargo_version`, run by the
+    /// current user
+    pub fn new(kargo_version: impl Into<String>) -> Self {
+        Self {
+            session: format!("{}-{}", unix_now(), std::process::id()),
+            kargo_version: kargo_version.into(),
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok(),
+        }
+    }
+}
+
+/// A manifest with its updates applied in memory, ready to be written
+struct PlannedChange {
+    source: DependencySource,
+    /// Content of the manifest as it was read
+    original: String,
+    updates: Vec<DependencyUpdate>,
+    /// Policies the updates were picked by, when planned by this session
+    policies: Option<UpdatePolicies>,
+    skipped: Vec<SkippedUpdate>,
+    /// Patch entries moved in this manifest
+    patches: Vec<PatchUpdate>,
+}
+
+impl PlannedChange {
+    /// Whether the manifest has to be written
+    fn changed(&self) -> bool {
+        !self.updates.is_empty() || !self.patches.is_empty()
+    }
+
+    /// The planned write, made before anything touches disk
+    fn diff(&self) -> Option<ManifestDiff> {
+        (self.changed() && self.source.content() != self.original).then(|| ManifestDiff {
+            path: self.source.path().to_path_buf(),
+            before: self.original.clone(),
+            after: self.source.content().to_string(),
+        })
+    }
+}
+
+/// Plans and writes dependency updates for a set of manifests
+pub struct UpgradeSession<U> {
+    updater: U,
+    mode: WriteMode,
+    dry_run: bool,
+    history: Option<Attribution>,
+    policies: Option<UpdatePolicyConfig>,
+    update_patches: bool,
+    snapshots: Option<PathBuf>,
+    prefilter: ManifestPrefilter,
+    /// The run's backups, taken on the first write
+    backups: tokio::sync::Mutex<Option<BackupManager>>,
+}
+
+impl<U: DependencyUpdater> UpgradeSession<U> {
+    /// Create a transactional session resolving versions with `updater`
+    pub fn new(updater: U) -> Self {
+        Self {
+            updater,
+            mode: WriteMode::default(),
+            dry_run: false,
+            history: None,
+            policies: None,
+            update_patches: false,
+            snapshots: None,
+            prefilter: ManifestPrefilter::any(),
+            backups: Default::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: WriteMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Plan updates without writing anything
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Append committed updates to each project's history, attributed to
+    /// `attribution`
+    pub fn record_history(mut self, attribution: Attribution) -> Self {
+        self.history = Some(attribution);
+        self
+    }
+
+    /// Pick each manifest's versions by its update policies instead of the
+    /// updater's own
+    pub fn with_policies(mut self, policies: UpdatePolicyConfig) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Update patched and vendored crates too, moving their `[patch]` entries
+    /// and vendored copies along instead of skipping them
+    pub fn update_patches(mut self, update_patches: bool) -> Self {
+        self.update_patches = update_patches;
+        self
+    }
+
+    /// Back manifests up into a snapshot under `root` (see
+    /// [`kargo_plugin_api::snapshot`]) rather than a temporary directory
+    pub fn with_snapshots(mut self, root: impl Into<PathBuf>) -> Self {
+        self.snapshots = Some(root.into());
+        self
+    }
+
+    /// Only upgrade the crates `prefilter` was built for; manifests that don't
+    /// mention them are never parsed
+    pub fn only_packages(mut self, prefilter: ManifestPrefilter) -> Self {
+        self.prefilter = prefilter;
+        self
+    }
+
+    /// Upgrade `manifests`, returning one result per manifest that had updates
+    /// or failed. Failures are reported in the results rather than as an error
+    /// so one broken workspace doesn't stop the others.
+    pub async fn run(&self, manifests: &[PathBuf]) -> Vec<UpdateResult> {
+        let parsing = Profiler::current().span(Phase::Parsing, "manifests");
+        let mut candidates = collect_candidates(manifests, &self.prefilter);
+        refine_with_metadata(&mut candidates);
+        retain_named(&mut candidates, &self.prefilter);
+        let private = exclude_private(&mut candidates);
+        if !private.is_empty() {
+            let names: Vec<&str> = private.iter().map(String::as_str).collect();
+            log::info!("Not looking up unpublished crates: {}", names.join(", "));
+        }
+        drop(parsing);
+        let mut results = Vec::new();
+        for group in self.group(candidates, |c| c.source.path()) {
+            results.extend(self.run_group(group).await);
+        }
+        results
+    }
+
+    /// Apply the updates of `plan` to the manifests below `root`, exactly as
+    /// planned and committed like [`UpgradeSession::run`] would. Fails without
+    /// writing anything if a manifest changed since the plan was made.
+    pub async fn apply(&self, plan: &UpgradePlan, root: &Path) -> Result<Vec<UpdateResult>> {
+        let drifted = plan.drifted(root);
+        if !drifted.is_empty() {
+            let list: Vec<String> = drifted.iter().map(|p| p.display().to_string()).collect();
+            bail!(
+                "{} manifest(s) changed since the plan was made: {}; create a new plan",
+                drifted.len(),
+                list.join(", ")
+            );
+        }
+
+        let mut planned = Vec::new();
+        for manifest in &plan.manifests {
+            let mut source = DependencySource::from_path(root.join(&manifest.path)).await?;
+            let original = source.content().to_string();
+            let updates = manifest.dependency_updates();
+            CargoWriter.apply_updates(&mut source, &updates)?;
+            planned.push(PlannedChange {
+                source,
+                original,
+                updates,
+                policies: None,
+                skipped: Vec::new(),
+                patches: Vec::new(),
+            });
+        }
+
+        let mut results = Vec::new();
+        for group in self.group(planned, |c| c.source.path()) {
+            results.extend(self.write_group(group, &plan.policy).await);
+        }
+        Ok(results)
+    }
+
+    /// Split manifests into the units that are committed together
+    fn group<T>(&self, items: Vec<T>, path: impl Fn(&T) -> &Path) -> Vec<Vec<T>> {
+        if self.mode == WriteMode::PerFile {
+            return items.into_iter().map(|item| vec![item]).collect();
+        }
+
+        let mut workspaces = HashMap::new();
+        let mut groups: BTreeMap<PathBuf, Vec<T>> = BTreeMap::new();
+        for item in items {
+            let path = path(&item);
+            let key = workspace_root(path, &mut workspaces).unwrap_or_else(|| path.to_path_buf());
+            groups.entry(key).or_default().push(item);
+        }
+        groups.into_values().collect()
+    }
+
+    async fn run_group(&self, group: Vec<CandidateManifest>) -> Vec<UpdateResult> {
+        let mut workspaces = HashMap::new();
+        let mut overrides: BTreeMap<PathBuf, Overrides> = BTreeMap::new();
+        let mut planned = Vec::new();
+        for candidate in group {
+            let path = candidate.source.path().to_path_buf();
+            let root = workspace_root(&path, &mut workspaces)
+                .or_else(|| path.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let change = match overrides.get(&root) {
+                Some(found) => self.plan(candidate, found).await,
+                None => match Overrides::load(&root) {
+                    Ok(found) => {
+                        let found = overrides.entry(root).or_insert(found);
+                        self.plan(candidate, found).await
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+            match change {
+                Ok(Some(change)) => planned.push(change),
+                Ok(None) => {}
+                Err(e) => {
+                    // Nothing has been written yet, so aborting is enough
+                    let error = format!("{:#}", e);
+                    log::error!("Failed to plan {}: {}", path.display(), error);
+                    let mut results = abandoned(planned, &path);
+                    results.push(UpdateResult {
+                        path,
+                        updates: Vec::new(),
+                        crate_type: CrateType::Unknown,
+                        error: Some(error),
+                        skipped: Vec::new(),
+                        patches: Vec::new(),
+                        diff: None,
+                    });
+                    return results;
+                }
+            }
+        }
+        if let Err(e) = self.move_patches(&mut planned).await {
+            let error = format!("{:#}", e);
+            return planned
+                .into_iter()
+                .map(|c| result(c, Some(error.clone())))
+                .collect();
+        }
+
+        let mut results = self.write_group(planned, self.updater.policy()).await;
+        if self.update_patches && !self.dry_run {
+            self.revendor(&overrides, &mut results).await;
+        }
+        results
+    }
+
+    /// Move the patch entries the planned updates need to the workspace root
+    /// manifests that declare them, planning those manifests too if nothing
+    /// else changed in them
+    async fn move_patches(&self, planned: &mut Vec<PlannedChange>) -> Result<()> {
+        let mut moves: BTreeMap<PathBuf, Vec<PatchUpdate>> = BTreeMap::new();
+        for change in planned.iter_mut() {
+            for update in std::mem::take(&mut change.patches) {
+                let manifest = update.root.join("Cargo.toml");
+                let moves = moves.entry(manifest).or_default();
+                match moves.iter().find(|m| m.patch == update.patch) {
+                    Some(existing) if existing.to != update.to => log::warn!(
+                        "{} is bumped to different versions; moving its patch to {} only",
+                        update.patch.name,
+                        existing.to
+                    ),
+                    Some(_) => {}
+                    None => moves.push(update),
+                }
+            }
+        }
+
+        for (manifest, updates) in moves {
+            let index = match planned.iter().position(|c| c.source.path() == manifest) {
+                Some(index) => index,
+                None => {
+                    let source = DependencySource::from_path(&manifest).await?;
+                    planned.push(PlannedChange {
+                        original: source.content().to_string(),
+                        source,
+                        updates: Vec::new(),
+                        policies: None,
+                        skipped: Vec::new(),
+                        patches: Vec::new(),
+                    });
+                    planned.len() - 1
+                }
+            };
+            let change = &mut planned[index];
+            for update in updates {
+                let content = set_patch_field(
+                    change.source.content(),
+                    &update.patch,
+                    update.key,
+                    &update.to,
+                )
+                .with_context(|| format!("Failed to update {}", manifest.display()))?;
+                change.source.update_content(content);
+                change.patches.push(update);
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-vendor the written workspaces that replace crates.io with a vendor
+    /// directory. The manifests are already on disk, so a failure is added
+    /// to their results rather than rolled back.
+    async fn revendor(
+        &self,
+        overrides: &BTreeMap<PathBuf, Overrides>,
+        results: &mut [UpdateResult],
+    ) {
+        for (root, found) in overrides {
+            let Some(vendor_dir) = &found.vendor_dir else {
+                continue;
+            };
+            let written: Vec<&mut UpdateResult> = results
+                .iter_mut()
+                .filter(|r| r.path.starts_with(root) && r.error.is_none() && !r.updates.is_empty())
+                .collect();
+            if written.is_empty() {
+                continue;
+            }
+            log::info!(
+                "Re-vendoring {} into {}",
+                root.display(),
+                vendor_dir.display()
+            );
+            if let Err(e) = revendor(root, vendor_dir).await {
+                let error = format!("Written, but the vendor directory is stale: {:#}", e);
+                for result in written {
+                    result.error = Some(error.clone());
+                }
+            }
+        }
+    }
+
+    /// Commit the planned changes of one group, unless this is a dry run
+    async fn write_group(&self, planned: Vec<PlannedChange>, policy: &str) -> Vec<UpdateResult> {
+        if self.dry_run || planned.is_empty() {
+            return planned.into_iter().map(|c| result(c, None)).collect();
+        }
+
+        let writes = Profiler::current().span(Phase::Writes, "manifests");
+        let mut backups = self.backups.lock().await;
+        let committed = match &mut *backups {
+            Some(backup) => commit(&planned, backup).await,
+            None => match BackupManager::new(self.snapshots.as_deref()) {
+                Ok(backup) => {
+                    log::info!(
+                        "Backing up manifests to snapshot {}",
+                        backup.snapshot().id()
+                    );
+                    commit(&planned, backups.insert(backup)).await
+                }
+                Err(e) => Err(e),
+            },
+        };
+        drop(backups);
+        drop(writes);
+        match committed {
+            Ok(()) => {
+                self.record(&planned, policy);
+                planned.into_iter().map(|c| result(c, None)).collect()
+            }
+            Err(e) => {
+                let error = format!("Rolled back: {:#}", e);
+                planned
+                    .into_iter()
+                    .map(|c| result(c, Some(error.clone())))
+                    .collect()
+            }
+        }
+    }
+
+    /// Append the updates of written manifests to their projects' histories.
+    /// The manifests are already on disk, so failures are only logged.
+    fn record(&self, planned: &[PlannedChange], policy: &str) {
+        let Some(attribution) = &self.history else {
+            return;
+        };
+        let timestamp = unix_now();
+        let mut workspaces = HashMap::new();
+        let mut projects: BTreeMap<PathBuf, Vec<UpgradeRecord>> = BTreeMap::new();
+        for change in planned {
+            let path = change.source.path();
+            let root = workspace_root(path, &mut workspaces)
+                .or_else(|| path.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let manifest = path.strip_prefix(&root).unwrap_or(path).to_path_buf();
+            let records = projects.entry(root).or_default();
+            for update in &change.updates {
+                records.push(UpgradeRecord {
+                    crate_name: update.dependency.package_name().to_string(),
+                    from: update.from_version.clone(),
+                    to: update.to_version.clone(),
+                    manifest: manifest.clone(),
+                    timestamp,
+                    kargo_version: attribution.kargo_version.clone(),
+                    session: attribution.session.clone(),
+                    policy: change
+                        .policies
+                        .as_ref()
+                        .map(|p| p.for_dependency(&update.dependency).to_string())
+                        .unwrap_or_else(|| policy.to_string()),
+                    user: attribution.user.clone(),
+                });
+            }
+        }
+
+        for (root, records) in projects {
+            let history = History::for_project(&root);
+            if let Err(e) = history.append(&records) {
+                log::warn!("Failed to record upgrade history: {:#}", e);
+            }
+        }
+    }
+
+    async fn plan(
+        &self,
+        candidate: CandidateManifest,
+        overrides: &Overrides,
+    ) -> Result<Option<PlannedChange>> {
+        let policies = match &self.policies {
+            Some(config) => Some(config.for_manifest(candidate.source.path())?),
+            None => None,
+        };
+        let updater = match &policies {
+            Some(policies) => self.updater.with_policies(policies.clone()),
+            None => self.updater.clone(),
+        };
+        let updates = updater
+            .update_all(&candidate.dependencies)
+            .collect()
+            .await?;
+
+        let mut kept = Vec::new();
+        let mut skipped = Vec::new();
+        let mut patches = Vec::new();
+        for update in updates {
+            let skip = match overrides.patch(update.dependency.package_name()) {
+                Some(patch) => match self.move_patch(patch, overrides, &update.to_version).await {
+                    Ok(moved) => {
+                        patches.push(moved);
+                        None
+                    }
+                    Err(reason) => Some(reason),
+                },
+                None => match &overrides.vendor_dir {
+                    Some(dir) if !self.update_patches => Some(format!(
+                        "vendored in {}; pass --update-patches to re-vendor",
+                        dir.display()
+                    )),
+                    _ => None,
+                },
+            };
+            match skip {
+                Some(reason) => {
+                    log::warn!(
+                        "Not updating {} in {}: {}",
+                        update.name,
+                        candidate.source.path().display(),
+                        reason
+                    );
+                    skipped.push(SkippedUpdate { update, reason });
+                }
+                None => kept.push(update),
+            }
+        }
+        if kept.is_empty() && skipped.is_empty() {
+            return Ok(None);
+        }
+
+        let mut source = candidate.source;
+        let original = source.content().to_string();
+        if !kept.is_empty() {
+            CargoWriter.apply_updates(&mut source, &kept)?;
+        }
+        Ok(Some(PlannedChange {
+            source,
+            original,
+            updates: kept,
+            policies,
+            skipped,
+            patches,
+        }))
+    }
+
+    /// The change to `patch`, declared in the workspace of `overrides`, that
+    /// lets its crate move to version `to`, or why the crate has to stay
+    /// where it is
+    async fn move_patch(
+        &self,
+        patch: &Patch,
+        overrides: &Overrides,
+        to: &str,
+    ) -> Result<PatchUpdate, String> {
+        if !self.update_patches {
+            return Err(format!(
+                "{}; pass --update-patches to move the patch",
+                patch.describe()
+            ));
+        }
+        let Some((key, value)) = patch.retarget(to) else {
+            return Err(format!(
+                "{}, which can't be moved to {} automatically",
+                patch.describe(),
+                to
+            ));
+        };
+        let from = match &patch.source {
+            PatchSource::Git {
+                url,
+                reference: Some((_, tag)),
+            } => {
+                match tag_exists(url, &value).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return Err(format!(
+                            "{}; {} has no tag {}",
+                            patch.describe(),
+                            url,
+                            value
+                        ))
+                    }
+                    Err(e) => return Err(format!("{}; {:#}", patch.describe(), e)),
+                }
+                tag.clone()
+            }
+            PatchSource::Registry { version } => version.clone(),
+            _ => unreachable!("only tags and versions are retargeted"),
+        };
+        Ok(PatchUpdate {
+            patch: patch.clone(),
+            root: overrides.root.clone(),
+            key,
+            from,
+            to: value,
+        })
+    }
+}
+
+/// Write every planned manifest, restoring all of them if any write fails
+async fn commit(planned: &[PlannedChange], backup: &mut BackupManager) -> Result<()> {
+    // Refuse before anything is backed up, so a read-only run leaves no trace
+    let guard = WriteGuard::current();
+    for change in planned.iter().filter(|c| c.changed()) {
+        guard.check(change.source.path())?;
+    }
+    for change in planned.iter().filter(|c| c.changed()) {
+        let path = change.source.path();
+        let write = async {
+            backup.backup_file(path)?;
+            CargoWriter.write(&change.source)?.await
+        };
+        if let Err(e) = write.await {
+            log::error!("Failed to write {}: {:#}", path.display(), e);
+            if let Err(rollback) = backup.rollback() {
+                return Err(e.context(format!("rollback also failed: {:#}", rollback)));
+            }
+            return Err(e.context(format!("Failed to write {}", path.display())));
+        }
+    }
+    backup.commit();
+    Ok(())
+}
+
+/// Results for manifests that were planned but not written because another
+/// manifest in the same transaction failed
+fn abandoned(planned: Vec<PlannedChange>, failed: &Path) -> Vec<UpdateResult> {
+    let error = format!("Not written: {} failed", failed.display());
+    planned
+        .into_iter()
+        .map(|c| result(c, Some(error.clone())))
+        .collect()
+}
+
+fn result(change: PlannedChange, error: Option<String>) -> UpdateResult {
+    let crate_type = if change.source.is_workspace() {
+        CrateType::Workspace
+    } else {
+        CrateType::Standard
+    };
+    let diff = change.diff();
+    UpdateResult {
+        path: change.source.path().to_path_buf(),
+        updates: change.updates,
+        crate_type,
+        error,
+        skipped: change.skipped,
+        patches: change.patches,
+        diff,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Directory of the nearest workspace manifest enclosing `manifest`, if any
+pub(crate) fn workspace_root(
+    manifest: &Path,
+    known: &mut HashMap<PathBuf, bool>,
+) -> Option<PathBuf> {
+    manifest
+        .parent()?
+        .ancestors()
+        .find(|dir| {
+            *known.entry(dir.to_path_buf()).or_insert_with(|| {
+                std::fs::read_to_string(dir.join("Cargo.toml"))
+                    .map(|content| content.contains("[workspace]"))
+                    .unwrap_or(false)
+            })
+        })
+        .map(Path::to_path_buf)
+}
@@ -0,0 +1,128 @@
+//! Git submodules below the scanned directory
+//!
+//! A submodule checkout has a `.git` file pointing into its superproject's
+//! git directory instead of a `.git` directory of its own, which is how
+//! [`find_submodules`] tells the boundaries apart without running git. Each
+//! manifest belongs to the innermost submodule containing it, and
+//! [`SubmoduleSelection`] decides which submodules take part in an upgrade.
+//!
+//! With `--git-commit`, the updates are committed inside each submodule and
+//! the superproject then commits the moved submodule pointers, see
+//! [`UpgradeCommits`](crate::commits::UpgradeCommits).
+
+use anyhow::Result;
+use jwalk::WalkDir;
+use std::path::{Path, PathBuf};
+
+/// A submodule checkout found while scanning
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    /// The submodule's working tree
+    pub path: PathBuf,
+    /// Path relative to the scanned directory, used to select it
+    pub relative: PathBuf,
+}
+
+/// Submodule checkouts below `root`, outermost first
+pub fn find_submodules(root: &Path) -> Result<Vec<Submodule>> {
+    let mut submodules: Vec<Submodule> = WalkDir::new(root)
+        .skip_hidden(false)
+        .process_read_dir(|_, _, _, children| {
+            // Nothing to find inside build output or git directories
+            children.retain(|child| {
+                child.as_ref().map_or(true, |child| {
+                    !(child.file_type().is_dir()
+                        && matches!(child.file_name().to_str(), Some("target" | ".git")))
+                })
+            });
+        })
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name() == ".git" && entry.file_type().is_file())
+        .filter_map(|entry| {
+            let path = entry.parent_path().to_path_buf();
+            let relative = path.strip_prefix(root).ok()?.to_path_buf();
+            // The scanned directory may itself be a submodule
+            (!relative.as_os_str().is_empty()).then_some(Submodule { path, relative })
+        })
+        .collect();
+    submodules
+        .sort_by_key(|submodule| (submodule.path.components().count(), submodule.path.clone()));
+    Ok(submodules)
+}
+
+/// Which submodules an upgrade covers
+#[derive(Debug, Clone, Default)]
+pub struct SubmoduleSelection {
+    /// Leave every submodule alone
+    pub exclude_all: bool,
+    /// Only these submodules, by path relative to the scanned directory;
+    /// every one when empty
+    pub only: Vec<PathBuf>,
+    /// Never these, by path relative to the scanned directory
+    pub skip: Vec<PathBuf>,
+}
+
+impl SubmoduleSelection {
+    /// `manifests` without those in excluded submodules, and how many were
+    /// left out of each, counted against the innermost submodule
+    pub fn filter(
+        &self,
+        manifests: Vec<PathBuf>,
+        submodules: &[Submodule],
+    ) -> (Vec<PathBuf>, Vec<(Submodule, usize)>) {
+        let mut excluded: Vec<(Submodule, usize)> = Vec::new();
+        let mut kept = Vec::new();
+        for manifest in manifests {
+            let containing: Vec<&Submodule> = submodules
+                .iter()
+                .filter(|submodule| manifest.starts_with(&submodule.path))
+                .collect();
+            match innermost(&manifest, submodules) {
+                Some(submodule) if self.excludes(&containing) => {
+                    match excluded.iter_mut().find(|(s, _)| s == submodule) {
+                        Some((_, count)) => *count += 1,
+                        None => excluded.push((submodule.clone(), 1)),
+                    }
+                }
+                _ => kept.push(manifest),
+            }
+        }
+        (kept, excluded)
+    }
+
+    /// Submodules named by the selection that aren't among `submodules`
+    pub fn unknown<'a>(&'a self, submodules: &[Submodule]) -> Vec<&'a PathBuf> {
+        self.only
+            .iter()
+            .chain(&self.skip)
+            .filter(|path| !submodules.iter().any(|s| s.relative == normalize(path)))
+            .collect()
+    }
+
+    /// Whether a manifest inside the `containing` submodules is left out;
+    /// skipping a submodule skips the ones nested in it too
+    fn excludes(&self, containing: &[&Submodule]) -> bool {
+        let named = |paths: &[PathBuf]| {
+            containing
+                .iter()
+                .any(|submodule| paths.iter().any(|p| normalize(p) == submodule.relative))
+        };
+        self.exclude_all || named(&self.skip) || (!self.only.is_empty() && !named(&self.only))
+    }
+}
+
+/// The innermost submodule holding `manifest`
+pub fn innermost<'a>(manifest: &Path, submodules: &'a [Submodule]) -> Option<&'a Submodule> {
+    submodules
+        .iter()
+        .filter(|submodule| manifest.starts_with(&submodule.path))
+        .max_by_key(|submodule| submodule.path.components().count())
+}
+
+/// `path` as given on the command line, without `./` or a trailing `/`
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .collect()
+}
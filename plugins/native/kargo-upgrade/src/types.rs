@@ -11,7 +11,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use crate::diff::ManifestDiff;
 use crate::models::{Dependency, DependencyUpdater};
+use crate::overrides::PatchUpdate;
 // Re-export DependencyUpdate from models for public use
 pub use crate::models::DependencyUpdate;
 
@@ -101,6 +103,8 @@ impl BatchUpdateOperation {
 
         // Spawn a task to process updates and send them to the channel
         tokio::spawn(async move {
+            updater.prefetch(&deps).await;
+
             for dep in deps {
                 // Get update for each dependency
                 let update_result = updater.update(&dep).await;
@@ -214,6 +218,19 @@ pub struct UpdateResult {
     pub crate_type: CrateType,
     /// Any errors that occurred during the update
     pub error: Option<String>,
+    /// Updates left out because the crate is patched or vendored
+    pub skipped: Vec<SkippedUpdate>,
+    /// `[patch]` entries moved along with the updates
+    pub patches: Vec<PatchUpdate>,
+    /// The manifest before and after, when it was planned to change
+    pub diff: Option<ManifestDiff>,
+}
+
+/// An available update that was not applied, and why
+#[derive(Debug, Clone)]
+pub struct SkippedUpdate {
+    pub update: DependencyUpdate,
+    pub reason: String,
 }
 
 /// A session for tracking dependency update operations
@@ -0,0 +1,375 @@
+//! How far dependencies may move, per dependency kind
+//!
+//! Runtime dependencies ship with the project and are often worth keeping on
+//! a conservative track, while dev- and build-dependencies can usually follow
+//! the newest releases. Policies are set per kind in YAML, in the kargo config
+//! directory for every project and in a project's `.kargo/update-policy.yaml`
+//! for its own:
+//!
+//! ```yaml
+//! normal: compatible
+//! dev: latest
+//! build: patch
+//! crates:
+//!   openssl: pinned
+//!   "aws-*": patch
+//! ```
+//!
+//! `crates` sets the policy of single crates, whatever their kind, by
+//! registry name; `*` matches any run of characters. An exact name wins over
+//! a pattern, and a longer pattern over a shorter one.
+//!
+//! A project file only overrides the kinds and crates it names. In a
+//! workspace, a member's file takes precedence over the workspace root's.
+//! Dependencies of Rust scripts count as normal dependencies.
+//! `kargo upgrade --update-policy` sets every kind's policy for one run;
+//! crate policies still apply.
+//!
+//! How far a policy reaches depends on the current requirement (see
+//! [`Requirement`]): caret and tilde requirements have their minimum raised,
+//! an exact pin (`=1.2.3`) is left alone, and wildcards and other ranges only
+//! move to versions they don't already accept. Requirements keep their
+//! operator when moved (see [`retarget`]).
+
+use anyhow::{bail, Context, Result};
+use semver::{Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::models::{Dependency, DependencyLocation};
+use crate::registry::{latest_version, IndexVersion};
+use crate::session::workspace_root;
+
+/// Location of a project's update policy relative to its root
+pub const UPDATE_POLICY_FILE: &str = ".kargo/update-policy.yaml";
+
+/// Which published versions a dependency may move to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdatePolicy {
+    /// The newest release, across major versions
+    #[default]
+    #[serde(alias = "major")]
+    Latest,
+    /// The newest release with the same major version
+    Minor,
+    /// The newest release the current requirement accepts
+    Compatible,
+    /// The newest release with the same major and minor version
+    Patch,
+    /// Never updated
+    Pinned,
+}
+
+impl UpdatePolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Latest => "latest",
+            Self::Minor => "minor",
+            Self::Compatible => "compatible",
+            Self::Patch => "patch",
+            Self::Pinned => "pinned",
+        }
+    }
+
+    /// The version a dependency required as `current` moves to, out of
+    /// the published `versions`; `None` if the policy allows nothing newer
+    pub fn select(self, current: &str, versions: &[IndexVersion]) -> Option<String> {
+        if self == Self::Pinned {
+            return None;
+        }
+        let (req, base, range) = match Requirement::parse(current)? {
+            // Nothing to stay compatible with, e.g. a bare `cargo-deps` entry
+            Requirement::Unset => return latest_version(versions),
+            Requirement::Exact => return None,
+            Requirement::Minimum { req, base } => (req, base, false),
+            Requirement::Range { req, base } => (req, base, true),
+        };
+        let allowed = |version: &Version| match self {
+            Self::Latest => true,
+            Self::Minor => version.major == base.major,
+            Self::Compatible => req.matches(version),
+            Self::Patch => version.major == base.major && version.minor == base.minor,
+            Self::Pinned => false,
+        };
+        let candidates: Vec<IndexVersion> = versions
+            .iter()
+            .filter(|v| {
+                Version::parse(&v.vers).is_ok_and(|version| {
+                    // Ranges don't accept pre-releases either, but shouldn't
+                    // move to one
+                    let beyond = !range || (!req.matches(&version) && version.pre.is_empty());
+                    version > base && allowed(&version) && beyond
+                })
+            })
+            .cloned()
+            .collect();
+        latest_version(&candidates)
+    }
+}
+
+impl FromStr for UpdatePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "latest" | "major" => Ok(Self::Latest),
+            "minor" => Ok(Self::Minor),
+            "compatible" => Ok(Self::Compatible),
+            "patch" => Ok(Self::Patch),
+            "pinned" => Ok(Self::Pinned),
+            other => bail!(
+                "expected patch, minor, compatible, major, latest or pinned, got '{}'",
+                other
+            ),
+        }
+    }
+}
+
+/// A dependency's version requirement, as far as update policies care
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    /// No requirement, e.g. a bare `cargo-deps` entry
+    Unset,
+    /// `=1.2.3`: a pin, which no policy moves
+    Exact,
+    /// `1.2`, `^1.2` or `~1.2`: moved by raising its minimum, `base`
+    Minimum { req: VersionReq, base: Version },
+    /// `*`, `1.*` or a range like `>=1.2, <1.5`: only moved past the versions
+    /// it already accepts, which `cargo update` picks up by itself
+    Range { req: VersionReq, base: Version },
+}
+
+impl Requirement {
+    /// `None` if `requirement` isn't one `semver` reads
+    pub fn parse(requirement: &str) -> Option<Self> {
+        let requirement = requirement.trim();
+        if requirement.is_empty() {
+            return Some(Self::Unset);
+        }
+        let req = VersionReq::parse(requirement).ok()?;
+        let base = req
+            .comparators
+            .first()
+            .map(|c| Version {
+                major: c.major,
+                minor: c.minor.unwrap_or(0),
+                patch: c.patch.unwrap_or(0),
+                pre: c.pre.clone(),
+                build: Default::default(),
+            })
+            .unwrap_or_else(|| Version::new(0, 0, 0));
+        Some(match req.comparators.as_slice() {
+            [c] if c.op == Op::Exact && c.patch.is_some() => Self::Exact,
+            [c] if matches!(c.op, Op::Caret | Op::Tilde) => Self::Minimum { req, base },
+            // `=1.2` is `1.2.*` to cargo
+            _ => Self::Range { req, base },
+        })
+    }
+}
+
+/// `current` moved to `version`, keeping its operator: `~1.2` moved to
+/// 1.3.4 is `~1.3.4`, `1.*` moved to 2.0.1 is `2.*`, and anything else
+/// becomes `version` itself
+pub fn retarget(current: &str, version: &str) -> String {
+    let current = current.trim();
+    if let Some(operator) = ['^', '~'].into_iter().find(|&op| current.starts_with(op)) {
+        return format!("{}{}", operator, version);
+    }
+    if let Some(fixed) = current.strip_suffix(".*") {
+        if fixed.split('.').all(|part| part.parse::<u64>().is_ok()) {
+            let release = version.split(['-', '+']).next().unwrap_or(version);
+            let kept: Vec<&str> = release.split('.').take(fixed.split('.').count()).collect();
+            return format!("{}.*", kept.join("."));
+        }
+    }
+    version.to_string()
+}
+
+impl std::fmt::Display for UpdatePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The policy of each dependency kind, and of single crates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdatePolicies {
+    pub normal: UpdatePolicy,
+    pub dev: UpdatePolicy,
+    pub build: UpdatePolicy,
+    /// Policies by crate name or `*` pattern, over the kind's
+    pub crates: BTreeMap<String, UpdatePolicy>,
+}
+
+impl UpdatePolicies {
+    /// The policy for a dependency declared at `location`
+    pub fn for_location(&self, location: &DependencyLocation) -> UpdatePolicy {
+        match location {
+            DependencyLocation::CargoTomlDev => self.dev,
+            DependencyLocation::CargoTomlBuild => self.build,
+            _ => self.normal,
+        }
+    }
+
+    /// The policy for `dependency`: its crate's, if one matches, otherwise
+    /// its kind's
+    pub fn for_dependency(&self, dependency: &Dependency) -> UpdatePolicy {
+        self.for_crate(dependency.package_name())
+            .unwrap_or_else(|| self.for_location(&dependency.location))
+    }
+
+    /// The policy `crates` sets for `name`, by exact name or else the longest
+    /// matching pattern
+    pub fn for_crate(&self, name: &str) -> Option<UpdatePolicy> {
+        if let Some(policy) = self.crates.get(name) {
+            return Some(*policy);
+        }
+        self.crates
+            .iter()
+            .filter(|(pattern, _)| pattern.contains('*') && matches_pattern(pattern, name))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, policy)| *policy)
+    }
+
+    /// The shared policy when every kind has the same one, otherwise each
+    /// kind's; crate policies are counted
+    pub fn name(&self) -> String {
+        let mut name = if self.normal == self.dev && self.normal == self.build {
+            self.normal.to_string()
+        } else {
+            format!(
+                "normal={},dev={},build={}",
+                self.normal, self.dev, self.build
+            )
+        };
+        if !self.crates.is_empty() {
+            name.push_str(&format!(",crates={}", self.crates.len()));
+        }
+        name
+    }
+
+    fn merge(mut self, file: &PolicyFile) -> Self {
+        self.normal = file.normal.unwrap_or(self.normal);
+        self.dev = file.dev.unwrap_or(self.dev);
+        self.build = file.build.unwrap_or(self.build);
+        self.crates.extend(
+            file.crates
+                .iter()
+                .map(|(name, policy)| (name.clone(), *policy)),
+        );
+        self
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// One policy file; kinds it leaves out keep the policy from the level above
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    normal: Option<UpdatePolicy>,
+    dev: Option<UpdatePolicy>,
+    build: Option<UpdatePolicy>,
+    #[serde(default)]
+    crates: BTreeMap<String, UpdatePolicy>,
+}
+
+impl PolicyFile {
+    fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let file = serde_yaml_ok::from_str(&content)
+            .with_context(|| format!("Invalid update policy in {}", path.display()))?;
+        Ok(Some(file))
+    }
+}
+
+/// The global update policies, with the project files layered on top per
+/// manifest
+#[derive(Debug, Clone, Default)]
+pub struct UpdatePolicyConfig {
+    global: UpdatePolicies,
+    /// Policy of every kind, over the files'
+    kinds: Option<UpdatePolicy>,
+}
+
+impl UpdatePolicyConfig {
+    /// Read the global policies from `path`, if it exists
+    pub fn load(path: &Path) -> Result<Self> {
+        let global = match PolicyFile::load(path)? {
+            Some(file) => UpdatePolicies::default().merge(&file),
+            None => UpdatePolicies::default(),
+        };
+        Ok(Self {
+            global,
+            kinds: None,
+        })
+    }
+
+    /// Move every kind of dependency by `policy`, whatever the files say;
+    /// crate policies still apply
+    pub fn with_kinds(mut self, policy: UpdatePolicy) -> Self {
+        self.kinds = Some(policy);
+        self
+    }
+
+    pub fn global(&self) -> UpdatePolicies {
+        self.overridden(self.global.clone())
+    }
+
+    fn overridden(&self, mut policies: UpdatePolicies) -> UpdatePolicies {
+        if let Some(policy) = self.kinds {
+            policies.normal = policy;
+            policies.dev = policy;
+            policies.build = policy;
+        }
+        policies
+    }
+
+    /// The policies for the dependencies of `manifest`
+    pub fn for_manifest(&self, manifest: &Path) -> Result<UpdatePolicies> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        if let Some(root) = workspace_root(manifest, &mut HashMap::new()) {
+            dirs.push(root);
+        }
+        if let Some(dir) = manifest.parent() {
+            if !dirs.iter().any(|d| d == dir) {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+
+        let mut policies = self.global.clone();
+        for dir in dirs {
+            if let Some(file) = PolicyFile::load(&dir.join(UPDATE_POLICY_FILE))? {
+                policies = policies.merge(&file);
+            }
+        }
+        Ok(self.overridden(policies))
+    }
+}
@@ -1,21 +1,34 @@
 //! Module for updating dependencies to their latest versions
 
+use std::collections::BTreeMap;
+
 use crate::{
-    crates_io::get_latest_version,
-    models::{Dependency, DependencyUpdate, DependencyUpdater},
-    types::{PendingDependencyUpdate, UpdateOptions},
+    crates_io::{get_latest_version, get_versions, prefetch_versions},
+    git_tags::{list_tags, select_tag},
+    models::{Dependency, DependencyUpdate, DependencyUpdater, GitPin, Origin},
+    registry::registry_index,
+    types::{PendingDependencyUpdate, SendFuture, UpdateOptions},
+    update_policy::{Requirement, UpdatePolicies, UpdatePolicy},
 };
 
-/// Updates dependencies to their latest versions from crates.io
+/// Updates dependencies to newer versions from crates.io, the registry they
+/// name, or the tags of their git repository, as far as their update policy
+/// allows
 #[derive(Clone)]
 pub struct CratesIoUpdater {
     options: UpdateOptions,
+    policies: UpdatePolicies,
+    policy_name: String,
 }
 
 impl CratesIoUpdater {
     /// Create a new updater with the given options
     pub fn new(options: UpdateOptions) -> Self {
-        Self { options }
+        Self {
+            options,
+            policies: UpdatePolicies::default(),
+            policy_name: UpdatePolicies::default().name(),
+        }
     }
 }
 
@@ -24,6 +37,7 @@ impl DependencyUpdater for CratesIoUpdater {
         // Clone what we need for the async task
         let dependency = dependency.clone();
         let _options = self.options.clone(); // Unused for now but may be needed later
+        let policy = self.policies.for_dependency(&dependency);
 
         // Create a future that will be performed asynchronously
         let update_future = async move {
@@ -34,8 +48,38 @@ impl DependencyUpdater for CratesIoUpdater {
                 dependency.version.clone()
             };
 
-            // Get the latest version from crates.io
-            let to_version = get_latest_version(&dependency.name).await?;
+            let to_version = match (&dependency.origin, policy) {
+                (_, UpdatePolicy::Pinned) => return Ok(None),
+                (Origin::Git { url, pin }, policy) => {
+                    let tags = list_tags(url).await?;
+                    select_tag(
+                        &tags,
+                        dependency.package_name(),
+                        &dependency.version,
+                        policy,
+                    )
+                    .map(|tag| match pin {
+                        GitPin::Tag => tag.name.clone(),
+                        GitPin::Rev => tag.commit.clone(),
+                    })
+                }
+                (Origin::Registry(registry), policy) => registry_index(registry)?
+                    .versions(dependency.package_name())
+                    .await?
+                    .and_then(|versions| policy.select(&dependency.version, &versions)),
+                // Pins and ranges need the published versions to move past
+                (Origin::Default, UpdatePolicy::Latest)
+                    if matches!(
+                        Requirement::parse(&dependency.version),
+                        Some(Requirement::Unset | Requirement::Minimum { .. })
+                    ) =>
+                {
+                    get_latest_version(dependency.package_name()).await?
+                }
+                (Origin::Default, policy) => get_versions(dependency.package_name())
+                    .await?
+                    .and_then(|versions| policy.select(&dependency.version, &versions)),
+            };
 
             if let Some(to_version) = to_version {
                 // Skip if already at latest version
@@ -58,4 +102,46 @@ impl DependencyUpdater for CratesIoUpdater {
         // Return a domain-specific type that will resolve to the update result
         PendingDependencyUpdate::new(update_future)
     }
+
+    fn policy(&self) -> &str {
+        &self.policy_name
+    }
+
+    fn with_policies(&self, policies: UpdatePolicies) -> Self {
+        Self {
+            options: self.options.clone(),
+            policy_name: policies.name(),
+            policies,
+        }
+    }
+
+    fn prefetch(&self, dependencies: &[Dependency]) -> SendFuture<()> {
+        let mut names: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+        for dependency in dependencies {
+            let registry = match &dependency.origin {
+                Origin::Default => None,
+                Origin::Registry(registry) => Some(registry.clone()),
+                // Listed once per repository by the update itself
+                Origin::Git { .. } => continue,
+            };
+            names
+                .entry(registry)
+                .or_default()
+                .push(dependency.package_name().to_string());
+        }
+        SendFuture(Box::pin(async move {
+            for (registry, names) in names {
+                match registry {
+                    None => prefetch_versions(names).await,
+                    // A registry without an index is reported by the updates
+                    // of its crates
+                    Some(registry) => {
+                        if let Ok(index) = registry_index(&registry) {
+                            index.prefetch(names).await;
+                        }
+                    }
+                }
+            }
+        }))
+    }
 }
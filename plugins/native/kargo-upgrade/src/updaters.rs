@@ -1,11 +1,12 @@
 use anyhow::{Context, Result};
+use kargo_plugin_api::WriteGuard;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::path::Path;
 use tokio::fs;
 
 use crate::crates_io::get_latest_version;
-use crate::models::{Dependency, DependencyLocation, DependencyUpdate};
+use crate::models::{Dependency, DependencyLocation, DependencyUpdate, Origin};
 use crate::types::UpdateOptions;
 
 // Pre-compile regex patterns
@@ -55,6 +56,7 @@ pub async fn update_cargo_toml(
     }
 
     // Write the updated content back
+    WriteGuard::current().check(path)?;
     fs::write(path, document.to_string()).await?;
     Ok(())
 }
@@ -115,6 +117,7 @@ pub async fn update_cargo_manifest_in_rust(
     }
 
     // Write the updated content back
+    WriteGuard::current().check(path)?;
     fs::write(path, updated_content).await?;
     Ok(())
 }
@@ -166,6 +169,7 @@ pub async fn update_rust_script(
     }
 
     // Write the updated content back
+    WriteGuard::current().check(path)?;
     fs::write(path, updated_content).await?;
     Ok(())
 }
@@ -334,8 +338,10 @@ pub async fn update_markdown(
                             if version != latest {
                                 let dummy_dep = Dependency {
                                     name: name.to_string(),
+                                    package: None,
                                     version: version.to_string(),
                                     location: DependencyLocation::CargoTomlDirect,
+                                    origin: Origin::Default,
                                 };
 
                                 section_updates.push(DependencyUpdate {
@@ -366,8 +372,10 @@ pub async fn update_markdown(
                             if version != latest {
                                 let dummy_dep = Dependency {
                                     name: name.to_string(),
+                                    package: None,
                                     version: version.to_string(),
                                     location: DependencyLocation::CargoTomlDirect,
+                                    origin: Origin::Default,
                                 };
 
                                 section_updates.push(DependencyUpdate {
@@ -572,6 +580,7 @@ pub async fn update_markdown(
     }
 
     // Write the updated content back
+    WriteGuard::current().check(path)?;
     fs::write(path, updated_content).await?;
     Ok(())
 }
@@ -0,0 +1,254 @@
+//! Package version bumps that respect `[workspace.package]` inheritance
+//!
+//! `kargo upgrade --bump-version minor` bumps the version of every package
+//! below a directory. Members declaring `version.workspace = true` are left
+//! alone: their workspace's `[workspace.package]` version is bumped once
+//! instead. Members that spell out the same version as their workspace are
+//! reported, since they would follow it by inheriting; with
+//! `--inherit-version` they are switched to `version.workspace = true`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use kargo_plugin_api::WriteGuard;
+use semver::Version;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml_edit::{DocumentMut as Document, InlineTable, Item, Value};
+
+use crate::session::workspace_root;
+
+/// How to change a version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionBump {
+    Major,
+    Minor,
+    Patch,
+    /// Set this exact version
+    Exact(Version),
+}
+
+impl FromStr for VersionBump {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "major" => Ok(Self::Major),
+            "minor" => Ok(Self::Minor),
+            "patch" => Ok(Self::Patch),
+            version => Version::parse(version).map(Self::Exact).map_err(|e| {
+                anyhow!(
+                    "expected major, minor, patch or a version, got '{}': {}",
+                    version,
+                    e
+                )
+            }),
+        }
+    }
+}
+
+impl VersionBump {
+    /// The version after bumping `version`; pre-release and build metadata
+    /// are dropped
+    pub fn apply(&self, version: &Version) -> Version {
+        match self {
+            Self::Major => Version::new(version.major + 1, 0, 0),
+            Self::Minor => Version::new(version.major, version.minor + 1, 0),
+            Self::Patch => Version::new(version.major, version.minor, version.patch + 1),
+            Self::Exact(version) => version.clone(),
+        }
+    }
+}
+
+/// What a version change is written to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionTarget {
+    /// `[workspace.package] version`, inherited by the members
+    Workspace,
+    /// `[package] version` of the package `name`
+    Package { name: String },
+    /// The package `name` switches to `version.workspace = true`
+    Inherit { name: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChange {
+    pub manifest: PathBuf,
+    pub target: VersionTarget,
+    pub from: String,
+    pub to: String,
+}
+
+/// The version changes for a set of manifests
+#[derive(Debug, Default)]
+pub struct VersionBumpPlan {
+    pub changes: Vec<VersionChange>,
+    /// Packages already inheriting their workspace's version, which change
+    /// along with it
+    pub inherited: Vec<(PathBuf, String)>,
+    /// Packages repeating their workspace's version explicitly that could
+    /// inherit it instead; empty when they were switched
+    pub could_inherit: Vec<(PathBuf, String)>,
+}
+
+impl VersionBumpPlan {
+    /// Plan bumping every package in `manifests`. With `inherit`, members
+    /// that repeat their workspace's version switch to inheriting it.
+    pub fn new(manifests: &[PathBuf], bump: &VersionBump, inherit: bool) -> Result<Self> {
+        // The workspace roots count too, even when outside the scanned
+        // directory, since their version is what members inherit
+        let mut workspaces = HashMap::new();
+        let mut manifests = manifests.to_vec();
+        for manifest in manifests.clone() {
+            if let Some(root) = workspace_root(&manifest, &mut workspaces) {
+                let root = root.join("Cargo.toml");
+                if !manifests.contains(&root) {
+                    manifests.push(root);
+                }
+            }
+        }
+
+        let mut documents = BTreeMap::new();
+        for manifest in manifests {
+            let content = std::fs::read_to_string(&manifest)
+                .with_context(|| format!("Failed to read {}", manifest.display()))?;
+            let doc: Document = content
+                .parse()
+                .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+            documents.insert(manifest, doc);
+        }
+
+        let mut plan = Self::default();
+        // Workspace version before the bump, by workspace root directory
+        let mut workspace_versions = HashMap::new();
+        for (manifest, doc) in &documents {
+            let Some(version) = doc
+                .get("workspace")
+                .and_then(|w| w.get("package"))
+                .and_then(|p| p.get("version"))
+                .and_then(Item::as_str)
+            else {
+                continue;
+            };
+            let to = bump_str(bump, version, manifest)?;
+            let root = manifest.parent().unwrap_or(Path::new("")).to_path_buf();
+            workspace_versions.insert(root, version.to_string());
+            plan.changes.push(VersionChange {
+                manifest: manifest.clone(),
+                target: VersionTarget::Workspace,
+                from: version.to_string(),
+                to,
+            });
+        }
+
+        for (manifest, doc) in &documents {
+            let Some(package) = doc.get("package") else {
+                continue;
+            };
+            let name = package
+                .get("name")
+                .and_then(Item::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let workspace_version = workspace_root(manifest, &mut workspaces)
+                .and_then(|root| workspace_versions.get(&root));
+            let Some(version) = package.get("version") else {
+                // No version means 0.0.0, and the package isn't published
+                continue;
+            };
+            if version
+                .get("workspace")
+                .and_then(Item::as_bool)
+                .unwrap_or(false)
+            {
+                if workspace_version.is_none() {
+                    bail!(
+                        "{} inherits the workspace version, but no [workspace.package] version was found for it",
+                        manifest.display()
+                    );
+                }
+                plan.inherited.push((manifest.clone(), name));
+                continue;
+            }
+            let version = version.as_str().ok_or_else(|| {
+                anyhow!("{}: package.version is not a string", manifest.display())
+            })?;
+            match workspace_version {
+                Some(shared) if shared == version && inherit => {
+                    let to = bump_str(bump, version, manifest)?;
+                    plan.changes.push(VersionChange {
+                        manifest: manifest.clone(),
+                        target: VersionTarget::Inherit { name },
+                        from: version.to_string(),
+                        to,
+                    });
+                }
+                _ => {
+                    if workspace_version.is_some_and(|shared| shared == version) {
+                        plan.could_inherit.push((manifest.clone(), name.clone()));
+                    }
+                    let to = bump_str(bump, version, manifest)?;
+                    plan.changes.push(VersionChange {
+                        manifest: manifest.clone(),
+                        target: VersionTarget::Package { name },
+                        from: version.to_string(),
+                        to,
+                    });
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Write the changes, each manifest once. Returns the manifests written.
+    pub fn apply(&self) -> Result<Vec<PathBuf>> {
+        let mut by_manifest: BTreeMap<&Path, Vec<&VersionChange>> = BTreeMap::new();
+        for change in &self.changes {
+            by_manifest
+                .entry(&change.manifest)
+                .or_default()
+                .push(change);
+        }
+        let guard = WriteGuard::current();
+        for (manifest, changes) in &by_manifest {
+            guard.check(manifest)?;
+            let content = std::fs::read_to_string(manifest)
+                .with_context(|| format!("Failed to read {}", manifest.display()))?;
+            let mut doc: Document = content
+                .parse()
+                .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+            for change in changes {
+                match &change.target {
+                    VersionTarget::Workspace => {
+                        set_version(&mut doc["workspace"]["package"]["version"], &change.to);
+                    }
+                    VersionTarget::Package { .. } => {
+                        set_version(&mut doc["package"]["version"], &change.to);
+                    }
+                    VersionTarget::Inherit { .. } => {
+                        let mut inherited = InlineTable::new();
+                        inherited.insert("workspace", Value::from(true));
+                        inherited.set_dotted(true);
+                        doc["package"]["version"] = Item::Value(Value::InlineTable(inherited));
+                    }
+                }
+            }
+            guard.write(manifest, doc.to_string())?;
+        }
+        Ok(by_manifest.keys().map(|m| m.to_path_buf()).collect())
+    }
+}
+
+/// Replace the version string, keeping its comments and spacing
+fn set_version(item: &mut Item, version: &str) {
+    let decor = item.as_value().map(|value| value.decor().clone());
+    *item = toml_edit::value(version);
+    if let (Some(decor), Some(value)) = (decor, item.as_value_mut()) {
+        *value.decor_mut() = decor;
+    }
+}
+
+fn bump_str(bump: &VersionBump, version: &str, manifest: &Path) -> Result<String> {
+    let parsed = Version::parse(version)
+        .with_context(|| format!("{}: invalid version '{}'", manifest.display(), version))?;
+    Ok(bump.apply(&parsed).to_string())
+}
@@ -1,12 +1,17 @@
 //! Writer for Cargo.toml files
 
 use anyhow::{anyhow, Result};
+use kargo_plugin_api::WriteGuard;
 use std::collections::HashMap;
 use tokio::fs;
-use toml_edit::{DocumentMut as Document, Item, Value};
+use toml_edit::{DocumentMut as Document, Item, TableLike, Value};
 
-use crate::models::{DependencyLocation, DependencySource, DependencyUpdate, DependencyWriter};
+use crate::git_tags::parse_tag;
+use crate::models::{
+    DependencyLocation, DependencySource, DependencyUpdate, DependencyWriter, GitPin, Origin,
+};
 use crate::types::PendingWrite;
+use crate::update_policy::retarget;
 
 /// Writer for Cargo.toml files
 #[derive(Clone)]
@@ -32,13 +37,13 @@ impl DependencyWriter for CargoWriter {
                 for update in updates {
                     match update.dependency.location {
                         DependencyLocation::CargoTomlDirect => {
-                            direct_updates.insert(update.name.clone(), update.to_version.clone());
+                            direct_updates.insert(update.name.clone(), update);
                         }
                         DependencyLocation::CargoTomlDev => {
-                            dev_updates.insert(update.name.clone(), update.to_version.clone());
+                            dev_updates.insert(update.name.clone(), update);
                         }
                         DependencyLocation::CargoTomlBuild => {
-                            build_updates.insert(update.name.clone(), update.to_version.clone());
+                            build_updates.insert(update.name.clone(), update);
                         }
                         _ => {} // Ignore other location types
                     }
@@ -49,8 +54,8 @@ impl DependencyWriter for CargoWriter {
                     if let Some(workspace_table) = workspace.as_table_mut() {
                         if let Some(deps) = workspace_table.get_mut("dependencies") {
                             if let Some(deps_table) = deps.as_table_mut() {
-                                for (name, version) in &direct_updates {
-                                    self.update_dependency_in_table(deps_table, name, version)?;
+                                for (name, update) in &direct_updates {
+                                    self.update_dependency_in_table(deps_table, name, update)?;
                                 }
                             }
                         }
@@ -60,8 +65,8 @@ impl DependencyWriter for CargoWriter {
                 // Update regular dependencies
                 if let Some(deps) = document.get_mut("dependencies") {
                     if let Some(deps_table) = deps.as_table_mut() {
-                        for (name, version) in &direct_updates {
-                            self.update_dependency_in_table(deps_table, name, version)?;
+                        for (name, update) in &direct_updates {
+                            self.update_dependency_in_table(deps_table, name, update)?;
                         }
                     }
                 }
@@ -69,8 +74,8 @@ impl DependencyWriter for CargoWriter {
                 // Update dev-dependencies
                 if let Some(deps) = document.get_mut("dev-dependencies") {
                     if let Some(deps_table) = deps.as_table_mut() {
-                        for (name, version) in &dev_updates {
-                            self.update_dependency_in_table(deps_table, name, version)?;
+                        for (name, update) in &dev_updates {
+                            self.update_dependency_in_table(deps_table, name, update)?;
                         }
                     }
                 }
@@ -78,8 +83,8 @@ impl DependencyWriter for CargoWriter {
                 // Update build-dependencies
                 if let Some(deps) = document.get_mut("build-dependencies") {
                     if let Some(deps_table) = deps.as_table_mut() {
-                        for (name, version) in &build_updates {
-                            self.update_dependency_in_table(deps_table, name, version)?;
+                        for (name, update) in &build_updates {
+                            self.update_dependency_in_table(deps_table, name, update)?;
                         }
                     }
                 }
@@ -96,6 +101,7 @@ impl DependencyWriter for CargoWriter {
     fn write(&self, source: &DependencySource) -> Result<PendingWrite> {
         match source {
             DependencySource::CargoToml { path, content, .. } => {
+                WriteGuard::current().check(path)?;
                 let path = path.clone();
                 let content = content.clone();
 
@@ -119,39 +125,58 @@ impl CargoWriter {
         &self,
         table: &mut toml_edit::Table,
         name: &str,
-        version: &str,
+        update: &DependencyUpdate,
     ) -> Result<()> {
-        if let Some(item) = table.get_mut(name) {
-            match item {
-                // Simple string version
-                Item::Value(value) => {
-                    if value.is_str() {
-                        *value = Value::String(toml_edit::Formatted::new(version.to_string()));
-                    }
-                }
+        let version = update.to_version.as_str();
+        let Some(item) = table.get_mut(name) else {
+            return Ok(());
+        };
+
+        // Simple string version
+        if let Some(value) = item.as_value_mut().filter(|value| value.is_str()) {
+            if let Some(current) = value.as_str() {
+                let requirement = retarget(current, version);
+                *value = Value::String(toml_edit::Formatted::new(requirement));
+            }
+            return Ok(());
+        }
 
-                // Table format
-                Item::Table(dep_table) => {
-                    // Skip if it's a workspace dependency
-                    if dep_table.contains_key("workspace") {
-                        return Ok(());
-                    }
+        // Table format, inline or as a section of its own
+        let Some(dep_table) = item.as_table_like_mut() else {
+            return Ok(());
+        };
 
-                    if let Some(ver_item) = dep_table.get_mut("version") {
-                        if let Some(ver_value) = ver_item.as_value_mut() {
-                            if ver_value.is_str() {
-                                *ver_value =
-                                    Value::String(toml_edit::Formatted::new(version.to_string()));
-                            }
-                        }
-                    }
-                }
+        // Skip if it's a workspace dependency
+        if dep_table.contains_key("workspace") {
+            return Ok(());
+        }
 
-                // Other formats not supported
-                _ => {}
+        let version = match &update.dependency.origin {
+            Origin::Git { pin, .. } => {
+                set_str(dep_table, pin.key(), |_| version.to_string());
+                // A version next to the pin has to match the package at the
+                // new tag; a bare commit says nothing about it
+                match parse_tag(version) {
+                    Some((_, tagged)) if *pin == GitPin::Tag => tagged.to_string(),
+                    _ => return Ok(()),
+                }
             }
-        }
+            _ => version.to_string(),
+        };
+        set_str(dep_table, "version", |current| retarget(current, &version));
 
         Ok(())
     }
 }
+
+/// Replace the string at `key` with what `update` makes of it, keeping its
+/// decoration
+fn set_str(table: &mut dyn TableLike, key: &str, update: impl FnOnce(&str) -> String) {
+    if let Some(value) = table.get_mut(key).and_then(Item::as_value_mut) {
+        if let Some(current) = value.as_str() {
+            let decor = value.decor().clone();
+            *value = Value::String(toml_edit::Formatted::new(update(current)));
+            *value.decor_mut() = decor;
+        }
+    }
+}
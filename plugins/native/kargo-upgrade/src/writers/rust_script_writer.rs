@@ -1,6 +1,7 @@
 //! Writer for Rust script files
 
 use anyhow::{anyhow, Result};
+use kargo_plugin_api::WriteGuard;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use tokio::fs;
@@ -90,6 +91,7 @@ impl DependencyWriter for RustScriptWriter {
     fn write(&self, source: &DependencySource) -> Result<PendingWrite> {
         match source {
             DependencySource::RustScript { path, content, .. } => {
+                WriteGuard::current().check(path)?;
                 let path = path.clone();
                 let content = content.clone();
 
@@ -0,0 +1,89 @@
+use kargo_upgrade::checklist::{self, Checklist, Row};
+use kargo_upgrade::models::{Dependency, DependencyUpdate, DependencyUpdater};
+use kargo_upgrade::plan::UpgradePlan;
+use kargo_upgrade::session::UpgradeSession;
+use kargo_upgrade::types::PendingDependencyUpdate;
+
+/// Bumps everything to 2.0.0
+#[derive(Clone)]
+struct StubUpdater;
+
+impl DependencyUpdater for StubUpdater {
+    fn update(&self, dependency: &Dependency) -> PendingDependencyUpdate {
+        let dependency = dependency.clone();
+        PendingDependencyUpdate::new(async move {
+            Ok(Some(DependencyUpdate {
+                name: dependency.name.clone(),
+                from_version: dependency.version.clone(),
+                to_version: "2.0.0".to_string(),
+                dependency,
+            }))
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_only_checked_updates_are_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app/Cargo.toml");
+    let cli = dir.path().join("cli/Cargo.toml");
+    for (manifest, name) in [(&app, "app"), (&cli, "cli")] {
+        std::fs::create_dir_all(manifest.parent().unwrap()).unwrap();
+        std::fs::write(
+            manifest,
+            format!(
+                "[package]\nname = \"{}\"\n\n[dependencies]\nserde = \"1.0.0\"\nlog = \"0.4\"\n",
+                name
+            ),
+        )
+        .unwrap();
+    }
+
+    let planned = UpgradeSession::new(StubUpdater)
+        .dry_run(true)
+        .run(&[app.clone(), cli.clone()])
+        .await;
+    let plan = UpgradePlan::from_results(&planned, dir.path(), "latest", "0.1.0").unwrap();
+    let log_index = plan.manifests[0]
+        .updates
+        .iter()
+        .position(|u| u.name == "log")
+        .unwrap();
+    let mut checklist = Checklist::new(plan);
+    let rows = checklist.rows();
+    assert_eq!(rows.len(), 6);
+    assert_eq!(checklist.checked_count(), 4);
+
+    // Unchecking a manifest unchecks its updates; toggling it again checks
+    // them all
+    checklist.toggle(Row::Manifest(1));
+    assert!(!checklist.is_checked(Row::Update(1, 0)));
+    checklist.toggle(Row::Update(1, 0));
+    assert!(!checklist.is_checked(Row::Manifest(1)));
+    checklist.toggle(Row::Manifest(1));
+    assert_eq!(checklist.checked_count(), 4);
+
+    checklist.toggle_all();
+    assert_eq!(checklist.checked_count(), 0);
+    checklist.toggle_all();
+    assert_eq!(checklist.checked_count(), 4);
+
+    // log is left alone in every manifest
+    checklist.toggle_crate(Row::Update(0, log_index));
+    assert_eq!(checklist.checked_count(), 2);
+
+    let plan = checklist.selected();
+    assert_eq!(plan.update_count(), 2);
+    let applied = UpgradeSession::new(StubUpdater)
+        .apply(&plan, dir.path())
+        .await
+        .unwrap();
+    let results = checklist::merge(planned, applied);
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|r| r.updates.len() == 1));
+    for manifest in [&app, &cli] {
+        let content = std::fs::read_to_string(manifest).unwrap();
+        assert!(content.contains("serde = \"2.0.0\""));
+        assert!(content.contains("log = \"0.4\""));
+    }
+}
@@ -0,0 +1,112 @@
+use kargo_upgrade::commits::{CommitBatching, UpgradeCommits};
+use kargo_upgrade::models::{Dependency, DependencyUpdate, DependencyUpdater};
+use kargo_upgrade::session::UpgradeSession;
+use kargo_upgrade::types::PendingDependencyUpdate;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Bumps everything to 2.0.0
+#[derive(Clone)]
+struct StubUpdater;
+
+impl DependencyUpdater for StubUpdater {
+    fn update(&self, dependency: &Dependency) -> PendingDependencyUpdate {
+        let dependency = dependency.clone();
+        PendingDependencyUpdate::new(async move {
+            Ok(Some(DependencyUpdate {
+                name: dependency.name.clone(),
+                from_version: dependency.version.clone(),
+                to_version: "2.0.0".to_string(),
+                dependency,
+            }))
+        })
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// A committed workspace whose members depend on `serde` and `tokio`
+fn repository(root: &Path) -> Vec<PathBuf> {
+    let manifests = [
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        (
+            "a/Cargo.toml",
+            "[dependencies]\nserde = \"1.0.0\"\ntokio = \"1.0.0\"\n",
+        ),
+        ("b/Cargo.toml", "[dependencies]\nserde = \"1.0.0\"\n"),
+    ];
+    let paths = manifests
+        .iter()
+        .map(|(path, content)| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            path
+        })
+        .collect();
+    git(root, &["init", "--quiet"]);
+    git(root, &["config", "user.name", "Test"]);
+    git(root, &["config", "user.email", "test@example.com"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "--quiet", "-m", "initial"]);
+    paths
+}
+
+async fn upgrade_and_commit(commits: UpgradeCommits) -> (tempfile::TempDir, Vec<String>) {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = repository(dir.path());
+    UpgradeCommits::check_clean(&manifests).await.unwrap();
+    let results = UpgradeSession::new(StubUpdater).run(&manifests).await;
+    commits.commit(&results).await.unwrap();
+    let log = git(dir.path(), &["log", "--format=%s", "--reverse"]);
+    let subjects = log.lines().skip(1).map(str::to_string).collect();
+    (dir, subjects)
+}
+
+#[tokio::test]
+async fn test_crate_batching_commits_each_crate_in_turn() {
+    let (dir, subjects) = upgrade_and_commit(UpgradeCommits::new(CommitBatching::Crate)).await;
+
+    assert_eq!(
+        subjects,
+        [
+            "chore(deps): bump serde from 1.0.0 to 2.0.0",
+            "chore(deps): bump tokio from 1.0.0 to 2.0.0",
+        ]
+    );
+    let first = git(dir.path(), &["show", "HEAD~1:a/Cargo.toml"]);
+    assert!(first.contains("serde = \"2.0.0\"") && first.contains("tokio = \"1.0.0\""));
+    assert!(git(dir.path(), &["status", "--porcelain"]).is_empty());
+}
+
+#[tokio::test]
+async fn test_session_batching_uses_template_and_lists_updates() {
+    let commits = UpgradeCommits::new(CommitBatching::Session)
+        .with_template("deps: {count} bumps in {project}");
+    let (dir, subjects) = upgrade_and_commit(commits).await;
+
+    let project = dir.path().canonicalize().unwrap();
+    let name = project.file_name().unwrap().to_string_lossy();
+    assert_eq!(subjects, [format!("deps: 3 bumps in {}", name)]);
+    let body = git(dir.path(), &["log", "-1", "--format=%b"]);
+    assert!(body.contains("- tokio 1.0.0 -> 2.0.0 (a/Cargo.toml)"));
+}
+
+#[tokio::test]
+async fn test_dirty_manifest_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = repository(dir.path());
+    std::fs::write(&manifests[2], "[dependencies]\n").unwrap();
+
+    let error = UpgradeCommits::check_clean(&manifests).await.unwrap_err();
+
+    assert!(error.to_string().contains("b/Cargo.toml"));
+}
@@ -0,0 +1,23 @@
+use kargo_upgrade::migrate::{is_breaking, notes_excerpt};
+
+#[test]
+fn test_breaking_bumps_follow_cargo_semver_rules() {
+    assert!(is_breaking("1.0", "2.0.0"));
+    assert!(is_breaking("^0.8.5", "0.9.0"));
+    assert!(!is_breaking("1.2", "1.9.3"));
+    assert!(!is_breaking("0.3.1", "0.3.9"));
+    assert!(is_breaking("0.0.3", "0.0.4"));
+    assert!(!is_breaking("0.1.2", "0.1.3"));
+    assert!(!is_breaking("0.0", "0.0.7"));
+    assert!(!is_breaking("*", "1.0.0"));
+}
+
+#[test]
+fn test_notes_excerpt_keeps_sections_in_range() {
+    let changelog = "# Changelog\n\n## 0.10.0\n- newer\n\n## 0.9.0\n- renamed thread_rng\n\n## 0.8.5\n- older\n";
+    let excerpt = notes_excerpt(changelog, "0.8", "0.9.0").unwrap();
+
+    assert!(excerpt.contains("renamed thread_rng"));
+    assert!(!excerpt.contains("newer"));
+    assert!(!excerpt.contains("older"));
+}
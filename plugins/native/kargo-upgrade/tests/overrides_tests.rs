@@ -0,0 +1,52 @@
+use kargo_upgrade::overrides::{parse_patches, Overrides, PatchSource};
+
+#[test]
+fn test_patches_parse_and_retarget() {
+    let patches = parse_patches(
+        r#"[patch.crates-io]
+serde = { git = "https://github.com/serde-rs/serde", tag = "v1.0.200" }
+json = { package = "serde_json", path = "vendor/json" }
+
+[replace]
+"log:0.4.20" = { version = "0.4.20", registry = "mirror" }
+"#,
+    )
+    .unwrap();
+    assert_eq!(patches.len(), 3);
+
+    let serde = &patches[0];
+    assert_eq!(serde.table, "crates-io");
+    assert_eq!(
+        serde.retarget("1.0.210"),
+        Some(("tag", "v1.0.210".to_string()))
+    );
+    assert_eq!(patches[1].package_name(), "serde_json");
+    assert_eq!(patches[1].retarget("2.0.0"), None);
+    assert_eq!(patches[1].source, PatchSource::Path("vendor/json".into()));
+    assert_eq!(patches[2].name, "log");
+    assert_eq!(
+        patches[2].describe(),
+        "patched by [replace] to version 0.4.20"
+    );
+}
+
+#[test]
+fn test_vendor_directory_is_found_in_cargo_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = dir.path().join("ws");
+    std::fs::create_dir_all(workspace.join(".cargo")).unwrap();
+    std::fs::write(
+        workspace.join(".cargo/config.toml"),
+        r#"[source.crates-io]
+replace-with = "vendored-sources"
+
+[source.vendored-sources]
+directory = "vendor"
+"#,
+    )
+    .unwrap();
+
+    let overrides = Overrides::load(&workspace.join("member")).unwrap();
+    assert_eq!(overrides.vendor_dir, Some(workspace.join("vendor")));
+    assert!(overrides.patches.is_empty());
+}
@@ -0,0 +1,81 @@
+use kargo_upgrade::models::{Dependency, DependencyUpdate, DependencyUpdater};
+use kargo_upgrade::plan::UpgradePlan;
+use kargo_upgrade::session::UpgradeSession;
+use kargo_upgrade::types::PendingDependencyUpdate;
+
+/// Bumps everything to 2.0.0
+#[derive(Clone)]
+struct StubUpdater;
+
+impl DependencyUpdater for StubUpdater {
+    fn update(&self, dependency: &Dependency) -> PendingDependencyUpdate {
+        let dependency = dependency.clone();
+        PendingDependencyUpdate::new(async move {
+            Ok(Some(DependencyUpdate {
+                name: dependency.name.clone(),
+                from_version: dependency.version.clone(),
+                to_version: "2.0.0".to_string(),
+                dependency,
+            }))
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_plan_round_trips_and_applies_exactly() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    std::fs::write(
+        &manifest,
+        "[package]\nname = \"app\"\n\n[dependencies]\nserde = \"1.0.0\"\nlog = \"0.4\"\n",
+    )
+    .unwrap();
+
+    let results = UpgradeSession::new(StubUpdater)
+        .dry_run(true)
+        .run(std::slice::from_ref(&manifest))
+        .await;
+    let mut plan = UpgradePlan::from_results(&results, dir.path(), "latest", "0.1.0").unwrap();
+    assert_eq!(plan.manifests[0].path.to_str(), Some("Cargo.toml"));
+    assert_eq!(plan.update_count(), 2);
+
+    // A reviewer drops the log bump before the plan is applied
+    plan.manifests[0].updates.retain(|u| u.name == "serde");
+    let file = dir.path().join("plan.json");
+    plan.write(&file).unwrap();
+    let plan = UpgradePlan::load(&file).unwrap();
+
+    let applied = UpgradeSession::new(StubUpdater)
+        .apply(&plan, dir.path())
+        .await
+        .unwrap();
+    assert!(applied.iter().all(|r| r.error.is_none()));
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    assert!(content.contains("serde = \"2.0.0\""));
+    assert!(content.contains("log = \"0.4\""));
+}
+
+#[tokio::test]
+async fn test_drifted_manifest_fails_without_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    std::fs::write(&manifest, "[dependencies]\nserde = \"1.0.0\"\n").unwrap();
+
+    let results = UpgradeSession::new(StubUpdater)
+        .dry_run(true)
+        .run(std::slice::from_ref(&manifest))
+        .await;
+    let plan = UpgradePlan::from_results(&results, dir.path(), "latest", "0.1.0").unwrap();
+
+    let drifted = "[dependencies]\nserde = \"1.0.100\"\n";
+    std::fs::write(&manifest, drifted).unwrap();
+    let error = UpgradeSession::new(StubUpdater)
+        .apply(&plan, dir.path())
+        .await
+        .unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("changed since the plan was made"));
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), drifted);
+}
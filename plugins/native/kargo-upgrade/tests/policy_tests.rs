@@ -0,0 +1,72 @@
+use kargo_plugin_api::policy::Policy;
+use std::path::Path;
+
+const POLICY: &str = r#"
+max_dependencies = 3
+
+[[ban]]
+crate = "openssl"
+reason = "use rustls"
+
+[minimum]
+tokio = "1.38"
+
+[git]
+forbid_branches = ["main"]
+"#;
+
+#[test]
+fn test_policy_reports_each_rule_violation() {
+    let policy = Policy::parse(POLICY).unwrap();
+    let manifest = r#"
+[package]
+name = "app"
+
+[dependencies]
+tls = { package = "openssl", version = "0.10" }
+tokio = "1.20"
+serde = "1.0.200"
+pinned = { git = "https://example.com/pinned", tag = "v1" }
+fork = { git = "https://example.com/fork", branch = "main" }
+
+[dev-dependencies]
+tempfile = "3"
+"#;
+
+    let violations = policy.check(Path::new("Cargo.toml"), manifest).unwrap();
+    let codes: Vec<&str> = violations.iter().map(|d| d.code.as_str()).collect();
+
+    assert_eq!(
+        codes,
+        [
+            "policy::banned",
+            "policy::minimum-version",
+            "policy::git-branch",
+            "policy::dependency-count",
+        ]
+    );
+    assert!(violations[0].message.contains("use rustls"));
+}
+
+#[test]
+fn test_merged_policies_keep_the_stricter_rule() {
+    let dir = tempfile::tempdir().unwrap();
+    let org = dir.path().join("org.toml");
+    let project = dir.path().join("project.toml");
+    std::fs::write(
+        &org,
+        "max_dependencies = 10\n\n[minimum]\ntokio = \"1.38\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        &project,
+        "max_dependencies = 40\n\n[minimum]\ntokio = \"1.20\"\n",
+    )
+    .unwrap();
+
+    let policy = Policy::load(&[org, project, dir.path().join("missing.toml")]).unwrap();
+
+    assert_eq!(policy.max_dependencies, Some(10));
+    assert_eq!(policy.minimum["tokio"].to_string(), "1.38.0");
+    assert!(Policy::parse("[unknown]\n").is_err());
+}
@@ -0,0 +1,89 @@
+use kargo_upgrade::models::{Dependency, DependencyLocation, DependencyUpdate, Origin};
+use kargo_upgrade::pr_body::{Bump, PrBody};
+use kargo_upgrade::release_notes::{NotesSource, ReleaseNotes};
+use kargo_upgrade::types::{CrateType, UpdateResult};
+use std::path::{Path, PathBuf};
+
+fn result(path: &str, updates: &[(&str, &str, &str)]) -> UpdateResult {
+    UpdateResult {
+        path: PathBuf::from(path),
+        updates: updates
+            .iter()
+            .map(|(name, from, to)| DependencyUpdate {
+                name: name.to_string(),
+                from_version: from.to_string(),
+                to_version: to.to_string(),
+                dependency: Dependency {
+                    name: name.to_string(),
+                    package: None,
+                    version: from.to_string(),
+                    location: DependencyLocation::CargoTomlDirect,
+                    origin: Origin::Default,
+                },
+            })
+            .collect(),
+        crate_type: CrateType::Standard,
+        error: None,
+        skipped: Vec::new(),
+        patches: Vec::new(),
+        diff: None,
+    }
+}
+
+#[test]
+fn test_bumps_are_classified_by_semver_rules() {
+    assert_eq!(Bump::classify("1.0", "2.0.0"), Bump::Major);
+    assert_eq!(Bump::classify("0.8.5", "0.9.0"), Bump::Major);
+    assert_eq!(Bump::classify("1.2", "1.4.0"), Bump::Minor);
+    assert_eq!(Bump::classify("1.2.3", "1.2.9"), Bump::Patch);
+}
+
+#[test]
+fn test_body_groups_rows_by_bump_and_merges_manifests() {
+    let mut failed = result("/ws/broken/Cargo.toml", &[("rand", "0.8", "0.9.0")]);
+    failed.error = Some("boom".to_string());
+    let results = [
+        result(
+            "/ws/Cargo.toml",
+            &[("serde", "1.0.100", "1.0.219"), ("tokio", "0.2", "1.45.0")],
+        ),
+        result("/ws/app/Cargo.toml", &[("serde", "1.0.100", "1.0.219")]),
+        failed,
+    ];
+
+    let body = PrBody::from_results(&results, Path::new("/ws"));
+    assert_eq!(body.manifests, 2);
+    let markdown = body.render();
+
+    assert!(
+        markdown.contains("2 dependency update(s) in 2 manifest(s): 1 major, 0 minor, 1 patch.")
+    );
+    assert!(markdown.find("### Major").unwrap() < markdown.find("### Patch").unwrap());
+    assert!(!markdown.contains("### Minor"));
+    assert!(!markdown.contains("rand"));
+    assert!(markdown.contains(
+        "| serde | 1.0.100 | 1.0.219 | `Cargo.toml`, `app/Cargo.toml` | \
+         [changelog](https://crates.io/crates/serde/versions) · \
+         [diff](https://diff.rs/serde/1.0.100/1.0.219) |"
+    ));
+    assert!(markdown.contains("[diff](https://diff.rs/tokio/0.2.0/1.45.0)"));
+}
+
+#[test]
+fn test_release_notes_follow_the_tables() {
+    let results = [result("/ws/Cargo.toml", &[("tokio", "1.1", "1.3.0")])];
+    let mut body = PrBody::from_results(&results, Path::new("/ws"));
+    assert!(!body.render().contains("### Release notes"));
+
+    body.dependencies[0].notes = Some(ReleaseNotes {
+        source: NotesSource::GithubReleases,
+        url: "https://github.com/tokio-rs/tokio/releases".to_string(),
+        excerpt: "## v1.3.0\n\n- faster timers".to_string(),
+    });
+    let markdown = body.render();
+    assert!(markdown.contains(
+        "\n### Release notes\n\n<details><summary>tokio 1.1 → 1.3.0 \
+         (<a href=\"https://github.com/tokio-rs/tokio/releases\">GitHub releases</a>)</summary>\n\n\
+         ## v1.3.0\n\n- faster timers\n\n</details>\n"
+    ));
+}
@@ -0,0 +1,76 @@
+use kargo_upgrade::prefilter::{collect_candidates, exclude_private, ManifestPrefilter};
+
+#[test]
+fn test_prefilter_matches_on_name_boundaries() {
+    let filter = ManifestPrefilter::new(["serde_json"]).unwrap();
+
+    assert!(filter.may_contain(b"[dependencies]\nserde_json = \"1\"\n"));
+    assert!(filter.may_contain(b"[dependencies]\nserde-json = \"1\"\n"));
+    assert!(filter.may_contain(b"[dependencies.serde_json]\nversion = \"1\"\n"));
+    assert!(filter.may_contain(b"json = { package = \"serde_json\", version = \"1\" }"));
+
+    assert!(!filter.may_contain(b"[dependencies]\nserde = \"1\"\n"));
+    assert!(!filter.may_contain(b"[dependencies]\nserde_json5 = \"0.1\"\n"));
+}
+
+#[test]
+fn test_collect_candidates_skips_unrelated_manifests() {
+    let dir = tempfile::tempdir().unwrap();
+    let hit = dir.path().join("hit.toml");
+    let miss = dir.path().join("miss.toml");
+    std::fs::write(&hit, "[dependencies]\ntokio = \"1.0\"\nserde = \"1.0\"\n").unwrap();
+    std::fs::write(&miss, "[dependencies]\nserde = \"1.0\"\n").unwrap();
+
+    let filter = ManifestPrefilter::new(["tokio"]).unwrap();
+    let candidates = collect_candidates(&[hit.clone(), miss], &filter);
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].source.path(), hit.as_path());
+    assert_eq!(candidates[0].dependencies.len(), 2);
+}
+
+#[test]
+fn test_unpublished_workspace_crates_are_not_looked_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    };
+    let root = write(
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"*\"]\n\n[workspace.package]\npublish = false\n",
+    );
+    let inherited = write(
+        "config/Cargo.toml",
+        "[package]\nname = \"config\"\npublish.workspace = true\n",
+    );
+    let private = write(
+        "tools/Cargo.toml",
+        "[package]\nname = \"xtask-tools\"\npublish = []\n",
+    );
+    let app = write(
+        "app/Cargo.toml",
+        "[package]\nname = \"app\"\n\n\
+         [dependencies]\nconfig = \"0.1\"\nserde = \"1.0\"\n\n\
+         [dependencies.tools]\npackage = \"xtask-tools\"\nversion = \"0.1\"\n\n\
+         [dependencies.shared]\npath = \"../shared\"\nversion = \"0.1\"\n",
+    );
+
+    let mut candidates = collect_candidates(
+        &[root, inherited, private, app.clone()],
+        &ManifestPrefilter::any(),
+    );
+    let excluded = exclude_private(&mut candidates);
+    assert_eq!(
+        excluded.into_iter().collect::<Vec<_>>(),
+        ["config", "xtask-tools"]
+    );
+    let app = candidates
+        .iter()
+        .find(|candidate| candidate.source.path() == app.as_path())
+        .unwrap();
+    let names: Vec<&str> = app.dependencies.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["serde"]);
+}
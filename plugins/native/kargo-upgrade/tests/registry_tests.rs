@@ -0,0 +1,180 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use kargo_plugin_api::{HttpClient, NetworkConfig, OfflineError};
+use kargo_upgrade::registry::{index_path, latest_version, parse_index_file, SparseIndex};
+
+const INDEX_FILE: &str = concat!(
+    r#"{"name":"demo","vers":"1.0.0","yanked":false}"#,
+    "\n",
+    r#"{"name":"demo","vers":"1.2.0","yanked":true}"#,
+    "\n",
+    r#"{"name":"demo","vers":"1.1.0","yanked":false}"#,
+    "\n",
+    r#"{"name":"demo","vers":"2.0.0-rc.1","yanked":false}"#,
+    "\n",
+);
+
+#[test]
+fn test_index_path_layout() {
+    assert_eq!(index_path("a"), "1/a");
+    assert_eq!(index_path("ab"), "2/ab");
+    assert_eq!(index_path("abc"), "3/a/abc");
+    assert_eq!(index_path("Serde"), "se/rd/serde");
+}
+
+#[test]
+fn test_latest_version_skips_yanked_and_prereleases() {
+    let versions = parse_index_file(INDEX_FILE);
+    assert_eq!(versions.len(), 4);
+    assert_eq!(latest_version(&versions).as_deref(), Some("1.1.0"));
+}
+
+/// Serve `responses` in order, recording the `If-None-Match` header of each request
+fn serve(responses: Vec<String>) -> (String, thread::JoinHandle<Vec<Option<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut seen = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut if_none_match = None;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("if-none-match") {
+                        if_none_match = Some(value.trim().to_string());
+                    }
+                }
+            }
+            seen.push(if_none_match);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        seen
+    });
+    (url, handle)
+}
+
+#[tokio::test]
+async fn test_conditional_request_reuses_cached_index_file() {
+    let cache = tempfile::tempdir().unwrap();
+    let (url, server) = serve(vec![
+        format!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            INDEX_FILE.len(),
+            INDEX_FILE
+        ),
+        "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+    ]);
+
+    // Separate clients share the on-disk cache, like two runs would
+    let first = SparseIndex::new(&url).with_cache_dir(Some(cache.path().to_path_buf()));
+    assert_eq!(
+        first.latest_version("demo").await.unwrap().as_deref(),
+        Some("1.1.0")
+    );
+
+    let second = SparseIndex::new(&url).with_cache_dir(Some(cache.path().to_path_buf()));
+    assert_eq!(
+        second.latest_version("demo").await.unwrap().as_deref(),
+        Some("1.1.0")
+    );
+
+    let seen = server.join().unwrap();
+    assert_eq!(seen, vec![None, Some("\"v1\"".to_string())]);
+}
+
+#[tokio::test]
+async fn test_fresh_cache_entries_skip_the_registry() {
+    let cache = tempfile::tempdir().unwrap();
+    let (url, server) = serve(vec![
+        format!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            INDEX_FILE.len(),
+            INDEX_FILE
+        ),
+        "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+    ]);
+    let run = |ttl| {
+        let index = SparseIndex::new(&url).with_cache_dir(Some(cache.path().to_path_buf()));
+        index.set_ttl(Duration::from_secs(ttl));
+        index
+    };
+
+    assert!(run(600).versions("demo").await.unwrap().is_some());
+    // Fetched moments ago: answered from the cache without a request
+    assert_eq!(
+        run(600).latest_version("demo").await.unwrap().as_deref(),
+        Some("1.1.0")
+    );
+    // Without a time to live, every run revalidates
+    assert_eq!(
+        run(0).latest_version("demo").await.unwrap().as_deref(),
+        Some("1.1.0")
+    );
+
+    let seen = server.join().unwrap();
+    assert_eq!(seen, vec![None, Some("\"v1\"".to_string())]);
+}
+
+#[tokio::test]
+async fn test_concurrent_lookups_share_one_request() {
+    let (url, server) = serve(vec![format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        INDEX_FILE.len(),
+        INDEX_FILE
+    )]);
+    let index = SparseIndex::new(&url);
+
+    let lookups = (0..8).map(|i| {
+        let index = &index;
+        async move {
+            let name = if i % 2 == 0 { "demo" } else { "Demo" };
+            index.latest_version(name).await
+        }
+    });
+    for latest in futures::future::join_all(lookups).await {
+        assert_eq!(latest.unwrap().as_deref(), Some("1.1.0"));
+    }
+    assert_eq!(server.join().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_transient_failures_are_retried_and_offline_requests_refused() {
+    let (url, server) = serve(vec![
+        "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string(),
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            INDEX_FILE.len(),
+            INDEX_FILE
+        ),
+    ]);
+    let index = SparseIndex::new(&url);
+    assert_eq!(
+        index.latest_version("demo").await.unwrap().as_deref(),
+        Some("1.1.0")
+    );
+    assert_eq!(server.join().unwrap().len(), 2);
+
+    let offline = NetworkConfig {
+        offline: true,
+        ..Default::default()
+    };
+    let client = HttpClient::with_config(reqwest::Client::builder(), offline).unwrap();
+    let error = client
+        .send(client.get("https://index.crates.io/de/mo/demo"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<OfflineError>().unwrap().url,
+        "https://index.crates.io/de/mo/demo"
+    );
+}
@@ -0,0 +1,46 @@
+use kargo_upgrade::release_notes::{changelog_excerpt, releases_excerpt, GithubRelease};
+
+fn release(tag: &str, body: &str) -> GithubRelease {
+    GithubRelease {
+        tag_name: tag.to_string(),
+        name: None,
+        body: Some(body.to_string()),
+        draft: false,
+    }
+}
+
+#[test]
+fn test_changelog_excerpt_covers_releases_after_the_old_version() {
+    let changelog = "# Changelog\n\n## [1.3.0]\n- newest\n\n## [1.2.1] - 2024-05-01\n- fixed parsing\n\n## [1.2.0]\n- added streams\n\n## [1.1.0]\n- older\n";
+    let excerpt = changelog_excerpt(changelog, "1.1", "1.2.1").unwrap();
+
+    assert!(excerpt.starts_with("## [1.2.1]"));
+    assert!(excerpt.contains("fixed parsing"));
+    assert!(excerpt.contains("added streams"));
+    assert!(!excerpt.contains("newest"));
+    assert!(!excerpt.contains("older"));
+    assert_eq!(changelog_excerpt(changelog, "1.3", "1.3.0"), None);
+}
+
+#[test]
+fn test_releases_excerpt_picks_this_crates_tags_in_range() {
+    let mut draft = release("v1.4.0", "- unpublished");
+    draft.draft = true;
+    let mut named = release("tokio-1.2.0", "- **breaking:** removed `spawn_local`\n");
+    named.name = Some("Tokio v1.2.0".to_string());
+    let releases = [
+        draft,
+        release("v1.3.0", "- faster timers"),
+        release("tokio-macros-v1.2.5", "- macro fix"),
+        named,
+        release("v1.1.0", "- older"),
+        release("v1.2.1", "  "),
+    ];
+
+    let excerpt = releases_excerpt(&releases, "tokio", "1.1", "1.3.0").unwrap();
+    assert_eq!(
+        excerpt,
+        "## Tokio v1.2.0\n\n- **breaking:** removed `spawn_local`\n\n## v1.3.0\n\n- faster timers"
+    );
+    assert_eq!(releases_excerpt(&releases, "tokio", "1.3.0", "1.3.0"), None);
+}
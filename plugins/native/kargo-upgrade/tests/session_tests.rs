@@ -0,0 +1,253 @@
+use anyhow::anyhow;
+use kargo_plugin_api::history::History;
+use kargo_upgrade::diff::write_patches;
+use kargo_upgrade::models::{Dependency, DependencyUpdate, DependencyUpdater};
+use kargo_upgrade::prefilter::ManifestPrefilter;
+use kargo_upgrade::session::{Attribution, UpgradeSession, WriteMode};
+use kargo_upgrade::types::PendingDependencyUpdate;
+use std::path::{Path, PathBuf};
+
+/// Bumps everything to 2.0.0 except `broken`, whose lookup fails
+#[derive(Clone)]
+struct StubUpdater;
+
+impl DependencyUpdater for StubUpdater {
+    fn update(&self, dependency: &Dependency) -> PendingDependencyUpdate {
+        let dependency = dependency.clone();
+        PendingDependencyUpdate::new(async move {
+            if dependency.name == "broken" {
+                return Err(anyhow!("lookup failed"));
+            }
+            Ok(Some(DependencyUpdate {
+                name: dependency.name.clone(),
+                from_version: dependency.version.clone(),
+                to_version: "2.0.0".to_string(),
+                dependency,
+            }))
+        })
+    }
+}
+
+fn workspace(root: &Path) -> Vec<PathBuf> {
+    let manifests = [
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        ("a/Cargo.toml", "[dependencies]\nserde = \"1.0.0\"\n"),
+        ("b/Cargo.toml", "[dependencies]\nbroken = \"1.0.0\"\n"),
+    ];
+    manifests
+        .iter()
+        .map(|(path, content)| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            path
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_failure_leaves_whole_workspace_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = workspace(dir.path());
+
+    let results = UpgradeSession::new(StubUpdater).run(&manifests).await;
+
+    assert!(results.iter().all(|r| r.error.is_some()));
+    let member = std::fs::read_to_string(dir.path().join("a/Cargo.toml")).unwrap();
+    assert!(member.contains("serde = \"1.0.0\""));
+}
+
+#[tokio::test]
+async fn test_per_file_mode_commits_independently() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = workspace(dir.path());
+
+    let results = UpgradeSession::new(StubUpdater)
+        .with_mode(WriteMode::PerFile)
+        .run(&manifests)
+        .await;
+
+    assert_eq!(results.iter().filter(|r| r.error.is_some()).count(), 1);
+    let member = std::fs::read_to_string(dir.path().join("a/Cargo.toml")).unwrap();
+    assert!(member.contains("serde = \"2.0.0\""));
+}
+
+#[tokio::test]
+async fn test_cargo_metadata_settles_renames_and_path_dependencies() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    std::fs::write(
+        &manifest,
+        r#"[package]
+name = "app"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies.json]
+package = "serde_json"
+version = "1.0.0"
+
+[dependencies.util]
+path = "util"
+version = "1.0.0"
+"#,
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+    std::fs::create_dir_all(dir.path().join("util/src")).unwrap();
+    std::fs::write(
+        dir.path().join("util/Cargo.toml"),
+        "[package]\nname = \"util\"\nversion = \"1.0.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("util/src/lib.rs"), "").unwrap();
+
+    let results = UpgradeSession::new(StubUpdater)
+        .run(std::slice::from_ref(&manifest))
+        .await;
+
+    assert_eq!(results.len(), 1);
+    let updates = &results[0].updates;
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].dependency.package_name(), "serde_json");
+}
+
+#[tokio::test]
+async fn test_committed_updates_are_recorded_in_project_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = workspace(dir.path());
+
+    let results = UpgradeSession::new(StubUpdater)
+        .with_mode(WriteMode::PerFile)
+        .record_history(Attribution::new("9.9.9"))
+        .run(&manifests)
+        .await;
+    assert_eq!(results.iter().filter(|r| r.error.is_none()).count(), 1);
+
+    // Only the committed manifest is recorded, relative to the workspace root
+    let records = History::for_project(dir.path()).read().unwrap();
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record.crate_name, "serde");
+    assert_eq!(
+        (record.from.as_str(), record.to.as_str()),
+        ("1.0.0", "2.0.0")
+    );
+    assert_eq!(record.manifest, Path::new("a/Cargo.toml"));
+    assert_eq!(record.kargo_version, "9.9.9");
+    assert_eq!(record.policy, "latest");
+    assert!(History::find(&dir.path().join("a")).is_some());
+}
+
+#[tokio::test]
+async fn test_patched_crates_are_skipped_unless_patches_are_moved() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("Cargo.toml");
+    std::fs::write(
+        &root,
+        r#"[workspace]
+members = ["a"]
+
+[patch.crates-io]
+serde = { path = "../serde" }
+tokio = { version = "=1.0.0", registry = "mirror" } # pinned mirror
+"#,
+    )
+    .unwrap();
+    let member = dir.path().join("a/Cargo.toml");
+    std::fs::create_dir_all(member.parent().unwrap()).unwrap();
+    std::fs::write(
+        &member,
+        "[dependencies]\nserde = \"1.0.0\"\ntokio = \"1.0.0\"\n",
+    )
+    .unwrap();
+    let manifests = vec![root.clone(), member.clone()];
+
+    let results = UpgradeSession::new(StubUpdater).run(&manifests).await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].updates.is_empty());
+    assert_eq!(results[0].skipped.len(), 2);
+    assert!(results[0].skipped[0].reason.contains("--update-patches"));
+    assert!(std::fs::read_to_string(&member)
+        .unwrap()
+        .contains("tokio = \"1.0.0\""));
+
+    let results = UpgradeSession::new(StubUpdater)
+        .update_patches(true)
+        .run(&manifests)
+        .await;
+    assert!(results.iter().all(|r| r.error.is_none()));
+    let member_result = results.iter().find(|r| r.path == member).unwrap();
+    assert_eq!(member_result.updates.len(), 1);
+    assert_eq!(member_result.skipped[0].update.name, "serde");
+    let root_result = results.iter().find(|r| r.path == root).unwrap();
+    assert_eq!(root_result.patches[0].to, "=2.0.0");
+
+    assert!(std::fs::read_to_string(&member)
+        .unwrap()
+        .contains("tokio = \"2.0.0\""));
+    assert!(std::fs::read_to_string(&root)
+        .unwrap()
+        .contains("tokio = { version = \"=2.0.0\", registry = \"mirror\" } # pinned mirror"));
+}
+
+#[tokio::test]
+async fn test_dry_run_diffs_the_planned_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifests = workspace(dir.path());
+
+    let results = UpgradeSession::new(StubUpdater)
+        .with_mode(WriteMode::PerFile)
+        .dry_run(true)
+        .run(&manifests)
+        .await;
+
+    let member = results
+        .iter()
+        .find(|r| r.path.ends_with("a/Cargo.toml"))
+        .unwrap();
+    let diff = member.diff.as_ref().unwrap();
+    assert_eq!(
+        diff.unified(dir.path()),
+        "--- a/a/Cargo.toml\n+++ b/a/Cargo.toml\n@@ -1,2 +1,2 @@\n \
+         [dependencies]\n-serde = \"1.0.0\"\n+serde = \"2.0.0\"\n"
+    );
+    let on_disk = std::fs::read_to_string(dir.path().join("a/Cargo.toml")).unwrap();
+    assert!(on_disk.contains("serde = \"1.0.0\""));
+
+    let patches = dir.path().join("patches");
+    let written = write_patches(&[diff], dir.path(), &patches).unwrap();
+    assert_eq!(written, [patches.join("a/Cargo.toml.patch")]);
+}
+
+#[tokio::test]
+async fn test_package_filter_skips_unrelated_manifests() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut manifests = workspace(dir.path());
+    let tools = dir.path().join("tools/Cargo.toml");
+    std::fs::create_dir_all(tools.parent().unwrap()).unwrap();
+    std::fs::write(
+        &tools,
+        "[dependencies]\nserde-json = \"1.0.0\"\nregex = \"1.0.0\"\n",
+    )
+    .unwrap();
+    manifests.push(tools.clone());
+
+    let results = UpgradeSession::new(StubUpdater)
+        .only_packages(ManifestPrefilter::new(["serde_json"]).unwrap())
+        .run(&manifests)
+        .await;
+
+    // `broken` is never looked up, so nothing fails
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, tools);
+    assert!(results[0].error.is_none());
+    let names: Vec<&str> = results[0].updates.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, ["serde-json"]);
+    let written = std::fs::read_to_string(&tools).unwrap();
+    assert!(written.contains("serde-json = \"2.0.0\""));
+    assert!(written.contains("regex = \"1.0.0\""));
+    let member = std::fs::read_to_string(dir.path().join("a/Cargo.toml")).unwrap();
+    assert!(member.contains("serde = \"1.0.0\""));
+}
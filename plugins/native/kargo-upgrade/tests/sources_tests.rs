@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use kargo_upgrade::git_tags::{parse_ls_remote, parse_tag, select_tag};
+use kargo_upgrade::models::{
+    DependencyParser, DependencySource, DependencyUpdate, DependencyWriter, GitPin, Origin,
+};
+use kargo_upgrade::parsers::CargoParser;
+use kargo_upgrade::update_policy::UpdatePolicy;
+use kargo_upgrade::writers::CargoWriter;
+
+const MANIFEST: &str = r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+billing = { version = "2.1", registry = "my-company" }
+tower = { git = "https://github.com/tower-rs/tower", tag = "tower-0.4.13", version = "0.4" }
+hyper = { git = "https://github.com/hyperium/hyper", rev = "a1b2c3d4e5" }
+tokio = { git = "https://github.com/tokio-rs/tokio", branch = "master" }
+"#;
+
+const LS_REMOTE: &str = "\
+1111111111111111111111111111111111111111\trefs/tags/tower-0.4.13
+2222222222222222222222222222222222222222\trefs/tags/tower-0.5.0
+3333333333333333333333333333333333333333\trefs/tags/tower-0.5.0^{}
+4444444444444444444444444444444444444444\trefs/tags/tower-http-0.6.0
+5555555555555555555555555555555555555555\trefs/tags/v1.0.0-rc.1
+a1b2c3d4e5f60000000000000000000000000000\trefs/tags/v0.14.2
+6666666666666666666666666666666666666666\trefs/tags/v1.4.0
+7777777777777777777777777777777777777777\trefs/heads/master
+";
+
+fn source() -> DependencySource {
+    DependencySource::CargoToml {
+        path: PathBuf::from("Cargo.toml"),
+        content: MANIFEST.to_string(),
+        is_workspace: false,
+    }
+}
+
+#[test]
+fn test_registry_and_git_dependencies_are_parsed_with_their_origin() {
+    let dependencies = CargoParser.parse(&source()).unwrap();
+    let parsed: Vec<(&str, &str, &Origin)> = dependencies
+        .iter()
+        .map(|d| (d.name.as_str(), d.version.as_str(), &d.origin))
+        .collect();
+
+    let git = |url: &str, pin| Origin::Git {
+        url: url.to_string(),
+        pin,
+    };
+    assert_eq!(
+        parsed,
+        [
+            ("serde", "1.0", &Origin::Default),
+            (
+                "billing",
+                "2.1",
+                &Origin::Registry("my-company".to_string())
+            ),
+            (
+                "tower",
+                "tower-0.4.13",
+                &git("https://github.com/tower-rs/tower", GitPin::Tag)
+            ),
+            (
+                "hyper",
+                "a1b2c3d4e5",
+                &git("https://github.com/hyperium/hyper", GitPin::Rev)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_git_pins_move_to_the_newest_tag_of_their_crate() {
+    let tags = parse_ls_remote(LS_REMOTE);
+    assert_eq!(tags.len(), 6);
+    assert_eq!(
+        parse_tag("tower-http-v0.6.0").map(|(name, v)| (name, v.to_string())),
+        Some(("tower-http", "0.6.0".to_string()))
+    );
+
+    // Annotated tags resolve to their commit; other crates' tags are skipped
+    let tower = select_tag(&tags, "tower", "tower-0.4.13", UpdatePolicy::Latest).unwrap();
+    assert_eq!(tower.name, "tower-0.5.0");
+    assert_eq!(tower.commit, "3".repeat(40));
+    assert!(select_tag(&tags, "tower", "tower-0.4.13", UpdatePolicy::Compatible).is_none());
+
+    // A commit is placed by the tag pointing to it, and stays put otherwise
+    let hyper = select_tag(&tags, "hyper", "a1b2c3d4e5", UpdatePolicy::Latest).unwrap();
+    assert_eq!(hyper.name, "v1.4.0");
+    assert!(select_tag(&tags, "hyper", "0123456789", UpdatePolicy::Latest).is_none());
+    assert!(select_tag(&tags, "hyper", "v1.4.0", UpdatePolicy::Latest).is_none());
+}
+
+#[test]
+fn test_git_updates_rewrite_the_pin_and_its_version() {
+    let mut source = source();
+    let updates: Vec<DependencyUpdate> = CargoParser
+        .parse(&source)
+        .unwrap()
+        .into_iter()
+        .filter_map(|dependency| {
+            let to = match dependency.name.as_str() {
+                "billing" => "2.3.0",
+                "tower" => "tower-0.5.0",
+                "hyper" => "6666666666666666666666666666666666666666",
+                _ => return None,
+            };
+            Some(DependencyUpdate {
+                name: dependency.name.clone(),
+                from_version: dependency.version.clone(),
+                to_version: to.to_string(),
+                dependency,
+            })
+        })
+        .collect();
+    CargoWriter.apply_updates(&mut source, &updates).unwrap();
+
+    let content = source.content();
+    assert!(content.contains(r#"billing = { version = "2.3.0", registry = "my-company" }"#));
+    assert!(content.contains(
+        r#"tower = { git = "https://github.com/tower-rs/tower", tag = "tower-0.5.0", version = "0.5.0" }"#
+    ));
+    assert!(content.contains(
+        r#"hyper = { git = "https://github.com/hyperium/hyper", rev = "6666666666666666666666666666666666666666" }"#
+    ));
+    assert!(content.contains(r#"serde = { version = "1.0", features = ["derive"] }"#));
+}
@@ -0,0 +1,188 @@
+use kargo_upgrade::commits::{CommitBatching, UpgradeCommits};
+use kargo_upgrade::models::{Dependency, DependencyUpdate, DependencyUpdater};
+use kargo_upgrade::session::UpgradeSession;
+use kargo_upgrade::submodules::{find_submodules, SubmoduleSelection};
+use kargo_upgrade::types::PendingDependencyUpdate;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Bumps everything to 2.0.0
+#[derive(Clone)]
+struct StubUpdater;
+
+impl DependencyUpdater for StubUpdater {
+    fn update(&self, dependency: &Dependency) -> PendingDependencyUpdate {
+        let dependency = dependency.clone();
+        PendingDependencyUpdate::new(async move {
+            Ok(Some(DependencyUpdate {
+                name: dependency.name.clone(),
+                from_version: dependency.version.clone(),
+                to_version: "2.0.0".to_string(),
+                dependency,
+            }))
+        })
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn init(dir: &Path) {
+    git(dir, &["init", "--quiet"]);
+    git(dir, &["config", "user.name", "Test"]);
+    git(dir, &["config", "user.email", "test@example.com"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "--quiet", "-m", "initial"]);
+}
+
+#[test]
+fn test_selection_follows_submodule_boundaries() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    for manifest in [
+        "Cargo.toml",
+        "libs/a/Cargo.toml",
+        "libs/a/nested/Cargo.toml",
+        "libs/b/Cargo.toml",
+    ] {
+        write(&root.join(manifest), "[package]\nname = \"x\"\n");
+    }
+    std::fs::create_dir(root.join(".git")).unwrap();
+    for submodule in ["libs/a", "libs/a/nested", "libs/b"] {
+        write(
+            &root.join(submodule).join(".git"),
+            "gitdir: ../.git/modules/x\n",
+        );
+    }
+
+    let submodules = find_submodules(root).unwrap();
+    let found: Vec<&Path> = submodules.iter().map(|s| s.relative.as_path()).collect();
+    assert_eq!(
+        found,
+        [
+            Path::new("libs/a"),
+            Path::new("libs/b"),
+            Path::new("libs/a/nested")
+        ]
+    );
+
+    let manifests = || -> Vec<PathBuf> {
+        [
+            "Cargo.toml",
+            "libs/a/Cargo.toml",
+            "libs/a/nested/Cargo.toml",
+            "libs/b/Cargo.toml",
+        ]
+        .iter()
+        .map(|m| root.join(m))
+        .collect()
+    };
+
+    // Skipping a submodule skips the ones nested in it
+    let skip = SubmoduleSelection {
+        skip: vec![PathBuf::from("./libs/a/")],
+        ..Default::default()
+    };
+    let (kept, excluded) = skip.filter(manifests(), &submodules);
+    assert_eq!(
+        kept,
+        [root.join("Cargo.toml"), root.join("libs/b/Cargo.toml")]
+    );
+    assert_eq!(excluded.len(), 2);
+
+    let only = SubmoduleSelection {
+        only: vec![PathBuf::from("libs/b")],
+        ..Default::default()
+    };
+    let (kept, _) = only.filter(manifests(), &submodules);
+    assert_eq!(
+        kept,
+        [root.join("Cargo.toml"), root.join("libs/b/Cargo.toml")]
+    );
+    assert_eq!(only.unknown(&submodules).len(), 0);
+
+    let none = SubmoduleSelection {
+        exclude_all: true,
+        skip: vec![PathBuf::from("libs/c")],
+        ..Default::default()
+    };
+    let (kept, _) = none.filter(manifests(), &submodules);
+    assert_eq!(kept, [root.join("Cargo.toml")]);
+    assert_eq!(none.unknown(&submodules), [&PathBuf::from("libs/c")]);
+}
+
+#[tokio::test]
+async fn test_commits_in_submodule_then_moves_its_pointer() {
+    let dir = tempfile::tempdir().unwrap();
+    let origin = dir.path().join("lib-origin");
+    write(
+        &origin.join("Cargo.toml"),
+        "[package]\nname = \"lib\"\n\n[dependencies]\nserde = \"1.0.0\"\n",
+    );
+    init(&origin);
+
+    let superproject = dir.path().join("app");
+    write(
+        &superproject.join("Cargo.toml"),
+        "[package]\nname = \"app\"\n\n[dependencies]\ntokio = \"1.0.0\"\n",
+    );
+    init(&superproject);
+    git(
+        &superproject,
+        &[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "--quiet",
+            origin.to_str().unwrap(),
+            "lib",
+        ],
+    );
+    git(&superproject, &["commit", "--quiet", "-m", "add lib"]);
+    let submodule = superproject.join("lib");
+    git(&submodule, &["config", "user.name", "Test"]);
+    git(&submodule, &["config", "user.email", "test@example.com"]);
+
+    let manifests = vec![
+        superproject.join("Cargo.toml"),
+        submodule.join("Cargo.toml"),
+    ];
+    UpgradeCommits::check_clean(&manifests).await.unwrap();
+    let results = UpgradeSession::new(StubUpdater).run(&manifests).await;
+    let commits = UpgradeCommits::new(CommitBatching::Session)
+        .commit(&results)
+        .await
+        .unwrap();
+
+    let subjects: Vec<&str> = commits.iter().map(|c| c.subject()).collect();
+    assert_eq!(
+        subjects,
+        [
+            "chore(deps): update 1 dependencies",
+            "chore(deps): update submodule lib",
+            "chore(deps): update 1 dependencies",
+        ]
+    );
+    assert!(commits[1]
+        .message
+        .contains(&format!("- lib: {}", commits[0].id)));
+    assert_eq!(
+        git(&submodule, &["rev-parse", "--short", "HEAD"]).trim(),
+        commits[0].id
+    );
+    // Nothing left for the user to commit in the superproject
+    assert_eq!(git(&superproject, &["status", "--porcelain"]), "");
+}
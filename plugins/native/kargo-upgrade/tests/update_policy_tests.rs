@@ -0,0 +1,179 @@
+use kargo_upgrade::models::DependencyLocation;
+use kargo_upgrade::registry::parse_index_file;
+use kargo_upgrade::update_policy::{retarget, Requirement, UpdatePolicy, UpdatePolicyConfig};
+
+const INDEX: &str = r#"
+{"name":"serde","vers":"1.0.100"}
+{"name":"serde","vers":"1.0.219"}
+{"name":"serde","vers":"1.1.0"}
+{"name":"serde","vers":"1.2.0","yanked":true}
+{"name":"serde","vers":"2.0.0"}
+{"name":"serde","vers":"2.1.0-rc.1"}
+"#;
+
+#[test]
+fn test_policies_bound_how_far_a_version_moves() {
+    let versions = parse_index_file(INDEX);
+    let select = |policy: UpdatePolicy, current: &str| policy.select(current, &versions);
+
+    assert_eq!(
+        select(UpdatePolicy::Latest, "1.0.100").as_deref(),
+        Some("2.0.0")
+    );
+    assert_eq!(
+        select(UpdatePolicy::Compatible, "1.0.100").as_deref(),
+        Some("1.1.0")
+    );
+    assert_eq!(
+        select(UpdatePolicy::Patch, "1.0").as_deref(),
+        Some("1.0.219")
+    );
+    assert_eq!(select(UpdatePolicy::Patch, "1.0.219"), None);
+    assert_eq!(select(UpdatePolicy::Pinned, "1.0.100"), None);
+    assert_eq!(
+        select(UpdatePolicy::Compatible, "").as_deref(),
+        Some("2.0.0")
+    );
+}
+
+#[test]
+fn test_project_files_override_the_kinds_they_name() {
+    let config_dir = tempfile::tempdir().unwrap();
+    let global = config_dir.path().join("update-policy.yaml");
+    std::fs::write(&global, "normal: compatible\ndev: latest\nbuild: patch\n").unwrap();
+
+    let project = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = project.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    write("Cargo.toml", "[workspace]\nmembers = [\"app\"]\n");
+    write(".kargo/update-policy.yaml", "normal: patch\ndev: pinned\n");
+    write("app/Cargo.toml", "[package]\nname = \"app\"\n");
+    write("app/.kargo/update-policy.yaml", "dev: compatible\n");
+
+    let config = UpdatePolicyConfig::load(&global).unwrap();
+    let root = config
+        .for_manifest(&project.path().join("Cargo.toml"))
+        .unwrap();
+    assert_eq!(
+        (root.normal, root.dev, root.build),
+        (
+            UpdatePolicy::Patch,
+            UpdatePolicy::Pinned,
+            UpdatePolicy::Patch
+        )
+    );
+    let app = config
+        .for_manifest(&project.path().join("app/Cargo.toml"))
+        .unwrap();
+    assert_eq!(
+        app.for_location(&DependencyLocation::CargoTomlDev),
+        UpdatePolicy::Compatible
+    );
+    assert_eq!(
+        app.for_location(&DependencyLocation::CargoTomlDirect),
+        UpdatePolicy::Patch
+    );
+    assert_eq!(app.name(), "normal=patch,dev=compatible,build=patch");
+
+    write(".kargo/update-policy.yaml", "runtime: patch\n");
+    assert!(config
+        .for_manifest(&project.path().join("Cargo.toml"))
+        .is_err());
+}
+
+#[test]
+fn test_crate_policies_override_the_kind() {
+    let config_dir = tempfile::tempdir().unwrap();
+    let global = config_dir.path().join("update-policy.yaml");
+    std::fs::write(
+        &global,
+        "normal: compatible\ncrates:\n  openssl: pinned\n  \"aws-*\": patch\n  \"aws-sdk-*\": latest\n",
+    )
+    .unwrap();
+    let project = tempfile::tempdir().unwrap();
+    std::fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"app\"\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(project.path().join(".kargo")).unwrap();
+    std::fs::write(
+        project.path().join(".kargo/update-policy.yaml"),
+        "crates:\n  openssl: patch\n",
+    )
+    .unwrap();
+
+    let config = UpdatePolicyConfig::load(&global).unwrap();
+    let policies = config
+        .for_manifest(&project.path().join("Cargo.toml"))
+        .unwrap();
+    assert_eq!(policies.for_crate("openssl"), Some(UpdatePolicy::Patch));
+    assert_eq!(policies.for_crate("aws-config"), Some(UpdatePolicy::Patch));
+    assert_eq!(policies.for_crate("aws-sdk-s3"), Some(UpdatePolicy::Latest));
+    assert_eq!(policies.for_crate("serde"), None);
+    assert_eq!(
+        policies.name(),
+        "normal=compatible,dev=latest,build=latest,crates=3"
+    );
+
+    // --update-policy replaces the kinds' policies, not the crates'
+    let policies = config
+        .with_kinds(UpdatePolicy::Minor)
+        .for_manifest(&project.path().join("Cargo.toml"))
+        .unwrap();
+    assert_eq!(policies.name(), "minor,crates=3");
+    assert_eq!(policies.for_crate("openssl"), Some(UpdatePolicy::Patch));
+}
+
+#[test]
+fn test_requirement_operators_bound_the_move() {
+    let versions = parse_index_file(INDEX);
+    let select = |policy: UpdatePolicy, current: &str| policy.select(current, &versions);
+
+    // Caret and tilde requirements have their minimum raised
+    assert_eq!(
+        select(UpdatePolicy::Compatible, "^1.0.100").as_deref(),
+        Some("1.1.0")
+    );
+    assert_eq!(
+        select(UpdatePolicy::Compatible, "~1.0.100").as_deref(),
+        Some("1.0.219")
+    );
+    assert_eq!(
+        select(UpdatePolicy::Minor, "~1.0.100").as_deref(),
+        Some("1.1.0")
+    );
+    assert_eq!(
+        select(UpdatePolicy::Latest, "~1.0.100").as_deref(),
+        Some("2.0.0")
+    );
+    // Exact pins stay where they are
+    assert_eq!(Requirement::parse("=1.0.100"), Some(Requirement::Exact));
+    assert_eq!(select(UpdatePolicy::Latest, "=1.0.100"), None);
+    // Wildcards only move past what they accept
+    assert_eq!(select(UpdatePolicy::Latest, "*"), None);
+    assert_eq!(select(UpdatePolicy::Minor, "1.*"), None);
+    assert_eq!(
+        select(UpdatePolicy::Latest, "1.*").as_deref(),
+        Some("2.0.0")
+    );
+    assert_eq!(
+        select(UpdatePolicy::Minor, "=1.0").as_deref(),
+        Some("1.1.0")
+    );
+    assert_eq!(Requirement::parse("not a version"), None);
+    assert_eq!(
+        "major".parse::<UpdatePolicy>().unwrap(),
+        UpdatePolicy::Latest
+    );
+    assert!("newest".parse::<UpdatePolicy>().is_err());
+
+    assert_eq!(retarget("~1.0.100", "1.0.219"), "~1.0.219");
+    assert_eq!(retarget("^1.0", "1.1.0"), "^1.1.0");
+    assert_eq!(retarget("1.*", "2.0.0"), "2.*");
+    assert_eq!(retarget("1.0.*", "2.1.0-rc.1"), "2.1.*");
+    assert_eq!(retarget("1.0", "1.1.0"), "1.1.0");
+}
@@ -0,0 +1,99 @@
+use kargo_upgrade::version_bump::{VersionBump, VersionBumpPlan, VersionTarget};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn workspace(root: &Path) -> Vec<PathBuf> {
+    let manifests = [
+        (
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"core\", \"cli\", \"tool\"]\n\n[workspace.package]\nversion = \"0.3.1\" # shared\n",
+        ),
+        (
+            "core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion.workspace = true\n",
+        ),
+        (
+            "cli/Cargo.toml",
+            "[package]\nname = \"cli\"\nversion = \"0.3.1\"\n",
+        ),
+        (
+            "tool/Cargo.toml",
+            "[package]\nname = \"tool\"\nversion = \"1.2.0-rc.1\"\n",
+        ),
+    ];
+    manifests
+        .iter()
+        .map(|(path, content)| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            path
+        })
+        .collect()
+}
+
+#[test]
+fn test_workspace_version_is_bumped_once_for_inheriting_members() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let manifests = workspace(root);
+    // Scanning a member alone still finds the workspace it inherits from
+    let plan = VersionBumpPlan::new(&manifests[1..2], &VersionBump::Minor, false).unwrap();
+    assert_eq!(plan.changes.len(), 1);
+    assert_eq!(plan.changes[0].manifest, root.join("Cargo.toml"));
+
+    let plan = VersionBumpPlan::new(&manifests, &"minor".parse().unwrap(), false).unwrap();
+    let changes: Vec<(&VersionTarget, &str, &str)> = plan
+        .changes
+        .iter()
+        .map(|c| (&c.target, c.from.as_str(), c.to.as_str()))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (&VersionTarget::Workspace, "0.3.1", "0.4.0"),
+            (
+                &VersionTarget::Package {
+                    name: "cli".to_string()
+                },
+                "0.3.1",
+                "0.4.0"
+            ),
+            (
+                &VersionTarget::Package {
+                    name: "tool".to_string()
+                },
+                "1.2.0-rc.1",
+                "1.3.0"
+            ),
+        ]
+    );
+    assert_eq!(
+        plan.inherited,
+        [(root.join("core/Cargo.toml"), "core".to_string())]
+    );
+    assert_eq!(
+        plan.could_inherit,
+        [(root.join("cli/Cargo.toml"), "cli".to_string())]
+    );
+
+    let plan = VersionBumpPlan::new(&manifests, &VersionBump::Patch, true).unwrap();
+    assert!(plan.could_inherit.is_empty());
+    plan.apply().unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+        "[workspace]\nmembers = [\"core\", \"cli\", \"tool\"]\n\n[workspace.package]\nversion = \"0.3.2\" # shared\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("core/Cargo.toml")).unwrap(),
+        "[package]\nname = \"core\"\nversion.workspace = true\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("cli/Cargo.toml")).unwrap(),
+        "[package]\nname = \"cli\"\nversion.workspace = true\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("tool/Cargo.toml")).unwrap(),
+        "[package]\nname = \"tool\"\nversion = \"1.2.1\"\n"
+    );
+}
@@ -0,0 +1,85 @@
+//! The project index written to `index.yaml`.
+
+use anyhow::{Context, Result};
+use cargo_toml::Manifest;
+use kargo_plugin_api::owners::Ownership;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectType {
+    Binary,
+    Library,
+    Both,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectStatus {
+    Working,
+    Broken,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    pub path: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub project_type: ProjectType,
+    pub status: ProjectStatus,
+    /// Package names of the dependencies, see [`dependency_packages`]
+    pub dependencies: Vec<String>,
+    pub tags: Vec<String>,
+    /// From CODEOWNERS, the package authors or git history; see
+    /// [`kargo_plugin_api::owners`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owners: Option<Ownership>,
+    /// Commit time of the last commit touching the project, in seconds since
+    /// the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_commit: Option<i64>,
+    /// Indexed projects that depend on this one
+    #[serde(default)]
+    pub dependents: Vec<String>,
+    pub is_workspace: bool,
+    pub workspace_members: Vec<String>,
+    pub indicators: HashMap<String, String>,
+}
+
+/// Package names of the dependencies of `manifest`: a dependency renamed
+/// with `foo = { package = "real-name" }` is listed as `real-name`
+pub fn dependency_packages(manifest: &Manifest) -> Vec<String> {
+    manifest
+        .dependencies
+        .iter()
+        .map(|(key, dependency)| dependency.package().unwrap_or(key).to_string())
+        .collect()
+}
+
+/// Record, for every project, the indexed projects depending on it
+pub fn link_dependents(projects: &mut [ProjectInfo]) {
+    let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+    for project in projects.iter() {
+        for dependency in &project.dependencies {
+            dependents
+                .entry(dependency.clone())
+                .or_default()
+                .push(project.name.clone());
+        }
+    }
+    for project in projects {
+        project.dependents = dependents.remove(&project.name).unwrap_or_default();
+        project.dependents.sort();
+        project.dependents.dedup();
+    }
+}
+
+pub fn read(index: &Path) -> Result<Vec<ProjectInfo>> {
+    let content = std::fs::read_to_string(index)
+        .with_context(|| format!("Failed to read {}", index.display()))?;
+    serde_yaml_ok::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", index.display()))
+}
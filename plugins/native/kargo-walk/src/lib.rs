@@ -0,0 +1,7 @@
+//! Indexing of the Rust projects under a directory; see the `kargo-walk`
+//! binary. `kargo stale` reads the index through [`index`] and [`stale`].
+
+pub mod index;
+pub mod stale;
+pub mod tags;
+pub mod triage;
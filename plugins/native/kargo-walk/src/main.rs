@@ -1,51 +1,77 @@
 use anyhow::{Context, Result, anyhow};
 use cargo_toml::Manifest;
+use clap::Parser;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use jwalk::WalkDir;
+use kargo_plugin_api::artifacts::{CapturedOutput, RunArtifacts};
+use kargo_plugin_api::cargo_runner::{self, CargoCommand};
+use kargo_plugin_api::{WriteGuard, owners};
 use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::Semaphore;
 
-#[derive(Debug, Serialize, Deserialize)]
-enum ProjectType {
-    Binary,
-    Library,
-    Both,
-    Unknown,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-enum ProjectStatus {
-    Working,
-    Broken,
-    Unknown,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ProjectInfo {
-    path: String,
-    name: String,
-    version: String,
-    description: Option<String>,
-    project_type: ProjectType,
-    status: ProjectStatus,
-    dependencies: Vec<String>,
+use kargo_walk::index::{self, ProjectInfo, ProjectStatus, ProjectType};
+use kargo_walk::stale;
+use kargo_walk::tags::{self, Facts, InventoryConfig, TagRule};
+use kargo_walk::triage::Triage;
+
+/// A project whose check runs longer than this is reported as unknown
+const CHECK_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Parser)]
+#[command(
+    name = "kargo-walk",
+    about = "Build an index of the Rust projects under a directory"
+)]
+struct Cli {
+    /// Directory to scan for projects
+    #[arg(long, default_value = "/home/ubuntu/forge")]
+    root: PathBuf,
+    /// Inventory config with tagging rules [default: <config dir>/krater/inventory.yaml]
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// List the indexed projects carrying this tag instead of scanning
+    /// (repeat to require several)
+    #[arg(long = "tag", value_name = "TAG")]
     tags: Vec<String>,
-    is_workspace: bool,
-    workspace_members: Vec<String>,
-    indicators: HashMap<String, String>,
+    /// List the indexed projects owned by this CODEOWNERS owner, author or
+    /// committer instead of scanning, e.g. `@org/platform-team`
+    #[arg(long, value_name = "OWNER")]
+    owner: Option<String>,
+    /// Report the indexed projects that are broken, that no other indexed
+    /// project depends on, and that have had no commits in MONTHS months,
+    /// instead of scanning
+    #[arg(long, value_name = "MONTHS")]
+    stale: Option<u32>,
+    /// Index file to write, or to read with --tag, --owner or --stale
+    #[arg(long, default_value = "index.yaml")]
+    index: PathBuf,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("Forge Inventory Tool - Scanning projects in /home/ubuntu/forge");
+    let cli = Cli::parse();
+    if let Some(months) = cli.stale {
+        return list_stale(&cli.index, months.into());
+    }
+    if !cli.tags.is_empty() || cli.owner.is_some() {
+        return list_matching(&cli.index, &cli.tags, cli.owner.as_deref());
+    }
+
+    let rules = InventoryConfig::load(cli.config.as_deref())?
+        .rules()
+        .context("Invalid tagging rule")?;
+
+    println!(
+        "Forge Inventory Tool - Scanning projects in {}",
+        cli.root.display()
+    );
 
     // Step 1: Find all Cargo.toml files
-    let cargo_toml_paths = find_cargo_toml_files("/home/ubuntu/forge")?;
+    let cargo_toml_paths = find_cargo_toml_files(&cli.root)?;
     println!("Found {} Cargo.toml files", cargo_toml_paths.len());
 
     // Take only the first 10 projects for testing
@@ -54,22 +80,80 @@ async fn main() -> Result<()> {
 
     // Step 2: Extract project information in parallel
     let mp = MultiProgress::new();
-    let projects = extract_project_info(limited_paths, &mp)?;
+    let projects = extract_project_info(limited_paths, &rules, &mp)?;
 
     // Step 3: Check project status concurrently
-    let projects = check_project_status(projects).await?;
+    let mut projects = check_project_status(projects).await?;
 
-    // Step 4: Analyze project relationships (simplified for now)
-    let projects_with_relationships = analyze_relationships(projects);
+    // Step 4: Analyze project relationships
+    println!("Analyzing project relationships...");
+    index::link_dependents(&mut projects);
 
     // Step 5: Generate index.yaml
-    generate_index_yaml(&projects_with_relationships)?;
+    generate_index_yaml(&projects, &cli.index)?;
+
+    println!(
+        "✅ Completed inventory process. Results saved to {}",
+        cli.index.display()
+    );
+    Ok(())
+}
+
+/// Print the projects in `index` that carry every tag in `tags` and, with
+/// `owner`, are owned by it
+fn list_matching(index: &Path, tags: &[String], owner: Option<&str>) -> Result<()> {
+    let projects = index::read(index)?;
+    let wanted: Vec<String> = tags.iter().map(|tag| tag.to_lowercase()).collect();
+    let mut matched = 0;
+    for project in &projects {
+        let owned = owner.is_none_or(|owner| {
+            project
+                .owners
+                .as_ref()
+                .is_some_and(|owners| owners.includes(owner))
+        });
+        if owned && wanted.iter().all(|tag| project.tags.contains(tag)) {
+            let owners = project
+                .owners
+                .as_ref()
+                .map(|owners| format!(" owned by {}", owners.owners.join(", ")))
+                .unwrap_or_default();
+            println!(
+                "{} {} ({}) [{}]{}",
+                project.name,
+                project.version,
+                project.path,
+                project.tags.join(", "),
+                owners
+            );
+            matched += 1;
+        }
+    }
+    let mut criteria = Vec::new();
+    if !wanted.is_empty() {
+        criteria.push(format!("tagged {}", wanted.join(" + ")));
+    }
+    if let Some(owner) = owner {
+        criteria.push(format!("owned by {}", owner));
+    }
+    println!(
+        "{} of {} projects {}",
+        matched,
+        projects.len(),
+        criteria.join(" and ")
+    );
+    Ok(())
+}
 
-    println!("✅ Completed inventory process. Results saved to index.yaml");
+/// Print the candidates for archiving in `index` with the evidence against
+/// each (see [`stale`])
+fn list_stale(index: &Path, months: i64) -> Result<()> {
+    let projects = index::read(index)?;
+    print!("{}", stale::report(&projects, months, stale::now()));
     Ok(())
 }
 
-fn find_cargo_toml_files(root_path: &str) -> Result<Vec<PathBuf>> {
+fn find_cargo_toml_files(root_path: &Path) -> Result<Vec<PathBuf>> {
     let pb = ProgressBar::new_spinner();
     pb.set_message("Scanning for Cargo.toml files...");
     pb.enable_steady_tick(Duration::from_millis(100));
@@ -96,6 +180,7 @@ fn find_cargo_toml_files(root_path: &str) -> Result<Vec<PathBuf>> {
 
 fn extract_project_info(
     cargo_toml_paths: Vec<PathBuf>,
+    rules: &[TagRule],
     mp: &MultiProgress,
 ) -> Result<Vec<ProjectInfo>> {
     println!("Extracting project information...");
@@ -112,12 +197,7 @@ fn extract_project_info(
     let projects = Arc::new(Mutex::new(Vec::new()));
 
     cargo_toml_paths.par_iter().for_each(|path| {
-        let mut project_info = extract_single_project_info(path);
-        if let Ok(info) = &mut project_info {
-            info.project_type = determine_project_type(path);
-        }
-
-        if let Ok(info) = project_info {
+        if let Ok(info) = extract_single_project_info(path, rules) {
             match projects.lock() {
                 Ok(mut proj) => proj.push(info),
                 Err(e) => eprintln!("Failed to lock projects mutex: {}", e),
@@ -142,7 +222,7 @@ fn extract_project_info(
     }
 }
 
-fn extract_single_project_info(path: &Path) -> Result<ProjectInfo> {
+fn extract_single_project_info(path: &Path, rules: &[TagRule]) -> Result<ProjectInfo> {
     let manifest = Manifest::from_path(path)
         .with_context(|| format!("Failed to parse Cargo.toml at {:?}", path))?;
 
@@ -151,11 +231,7 @@ fn extract_single_project_info(path: &Path) -> Result<ProjectInfo> {
         .as_ref()
         .with_context(|| format!("No package section in {:?}", path))?;
 
-    let dependencies = manifest
-        .dependencies
-        .keys()
-        .map(|k| k.to_string())
-        .collect();
+    let dependencies = index::dependency_packages(&manifest);
 
     // Handle workspace members
     let workspace_members = if let Some(workspace) = &manifest.workspace {
@@ -176,6 +252,27 @@ fn extract_single_project_info(path: &Path) -> Result<ProjectInfo> {
         cargo_toml::Inheritable::Inherited => None,
     });
 
+    let categories = match &package.categories {
+        cargo_toml::Inheritable::Set(v) => v.clone(),
+        cargo_toml::Inheritable::Inherited => Vec::new(),
+    };
+    let keywords = match &package.keywords {
+        cargo_toml::Inheritable::Set(v) => v.clone(),
+        cargo_toml::Inheritable::Inherited => Vec::new(),
+    };
+    let project_type = determine_project_type(path);
+    let tags = tags::infer(
+        &Facts {
+            name: &package.name,
+            dependencies: &dependencies,
+            categories: &categories,
+            keywords: &keywords,
+            has_bin: matches!(project_type, ProjectType::Binary | ProjectType::Both),
+            has_lib: matches!(project_type, ProjectType::Library | ProjectType::Both),
+        },
+        rules,
+    );
+
     Ok(ProjectInfo {
         path: match path.parent() {
             Some(p) => p.to_string_lossy().to_string(),
@@ -184,10 +281,13 @@ fn extract_single_project_info(path: &Path) -> Result<ProjectInfo> {
         name: package.name.clone(),
         version,
         description,
-        project_type: ProjectType::Unknown, // Will be set later
-        status: ProjectStatus::Unknown,     // Will be set later
+        project_type,
+        status: ProjectStatus::Unknown, // Will be set later
         dependencies,
-        tags: Vec::new(), // Can be enhanced later
+        tags,
+        owners: owners::project_owners(path.parent().unwrap_or(Path::new("."))),
+        last_commit: stale::last_commit(path.parent().unwrap_or(Path::new("."))),
+        dependents: Vec::new(),
         is_workspace: manifest.workspace.is_some(),
         workspace_members,
         indicators: HashMap::new(),
@@ -234,7 +334,17 @@ async fn check_project_status(projects: Vec<ProjectInfo>) -> Result<Vec<ProjectI
             println!("Checking project: {}", project.name);
 
             let mut updated_project = project;
-            updated_project.status = check_single_project_status(&updated_project.path).await;
+            let (status, triage, output) = check_single_project_status(&updated_project.path).await;
+            updated_project.status = status;
+            if let Some(triage) = triage {
+                triage.record(&mut updated_project.indicators);
+            }
+            if let Some(output) = output {
+                updated_project.indicators.insert(
+                    "check_output".to_string(),
+                    output.stderr.display().to_string(),
+                );
+            }
 
             match updated_projects.lock() {
                 Ok(mut proj) => proj.push(updated_project),
@@ -261,38 +371,53 @@ async fn check_project_status(projects: Vec<ProjectInfo>) -> Result<Vec<ProjectI
     }
 }
 
-async fn check_single_project_status(project_path: &str) -> ProjectStatus {
-    let output = tokio::process::Command::new("cargo")
-        .args(["check", "--quiet"])
+/// Run `cargo check` in the project; a broken project comes with the triage
+/// of its first error. A check that fails or times out has its output saved
+/// to the project's `.kargo/runs/`; projects that check fine are left alone.
+async fn check_single_project_status(
+    project_path: &str,
+) -> (ProjectStatus, Option<Triage>, Option<CapturedOutput>) {
+    let command = CargoCommand::new("check")
+        .args(["--quiet", "--color=never"])
         .current_dir(project_path)
-        .output()
-        .await;
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                ProjectStatus::Working
-            } else {
-                ProjectStatus::Broken
+        .timeout(CHECK_TIMEOUT);
+    let run = command.clone();
+    let output = tokio::task::spawn_blocking(move || cargo_runner::run(&run)).await;
+
+    let Ok(Ok(output)) = output else {
+        return (ProjectStatus::Unknown, None, None);
+    };
+    if output.success() {
+        return (ProjectStatus::Working, None, None);
+    }
+    let captured =
+        match RunArtifacts::for_project(Path::new(project_path)).capture_cargo(&command, &output) {
+            Ok(captured) => Some(captured),
+            Err(e) => {
+                log::warn!("{:#}", e);
+                None
             }
-        }
-        Err(_) => ProjectStatus::Unknown,
+        };
+    if output.timed_out {
+        return (ProjectStatus::Unknown, None, captured);
     }
+    (
+        ProjectStatus::Broken,
+        Triage::from_stderr(&output.stderr, project_path),
+        captured,
+    )
 }
 
-fn analyze_relationships(projects: Vec<ProjectInfo>) -> Vec<ProjectInfo> {
-    println!("Analyzing project relationships...");
-    // For this simple implementation, we'll just return the projects without modification
-    // In a more complex implementation, this could analyze dependencies and workspace relationships
-    projects
-}
-
-fn generate_index_yaml(projects: &[ProjectInfo]) -> Result<()> {
-    println!("Generating index.yaml...");
+fn generate_index_yaml(projects: &[ProjectInfo], index: &Path) -> Result<()> {
+    println!("Generating {}...", index.display());
 
     let yaml = serde_yaml_ok::to_string(projects)?;
-    std::fs::write("index.yaml", yaml)?;
+    WriteGuard::current().write(index, yaml)?;
 
-    println!("✅ index.yaml generated with {} projects", projects.len());
+    println!(
+        "✅ {} generated with {} projects",
+        index.display(),
+        projects.len()
+    );
     Ok(())
 }
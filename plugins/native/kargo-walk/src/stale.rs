@@ -0,0 +1,93 @@
+//! Projects that look abandoned.
+//!
+//! A project is a candidate for archiving when all three hold: nobody has
+//! committed to it for the given number of months, it fails `cargo check`,
+//! and no other indexed project depends on it. A project whose history is
+//! unknown (not in a git repository) is never a candidate.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::index::{ProjectInfo, ProjectStatus};
+
+/// Average length of a Gregorian month
+const SECONDS_PER_MONTH: i64 = 2_629_746;
+
+/// Why a project is a candidate for archiving
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evidence {
+    /// Whole months since the last commit
+    pub months_idle: i64,
+    /// First `cargo check` error recorded in the index, if any
+    pub error: Option<String>,
+}
+
+/// Commit time (seconds since the epoch) of the last commit touching `dir`
+pub fn last_commit(dir: &Path) -> Option<i64> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%ct", "--", "."])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// The evidence against `project` when it is a candidate for archiving, as
+/// of `now`
+pub fn assess(project: &ProjectInfo, months: i64, now: i64) -> Option<Evidence> {
+    let months_idle = (now - project.last_commit?) / SECONDS_PER_MONTH;
+    let broken = matches!(project.status, ProjectStatus::Broken);
+    if months_idle < months || !broken || !project.dependents.is_empty() {
+        return None;
+    }
+    Some(Evidence {
+        months_idle,
+        error: project.indicators.get("first_error").cloned(),
+    })
+}
+
+/// The candidates for archiving among `projects` with the evidence against
+/// each, and how many there are
+pub fn report(projects: &[ProjectInfo], months: i64, now: i64) -> String {
+    let mut report = String::new();
+    let mut candidates = 0;
+    for project in projects {
+        let Some(evidence) = assess(project, months, now) else {
+            continue;
+        };
+        let _ = writeln!(
+            report,
+            "{} {} ({})",
+            project.name, project.version, project.path
+        );
+        let _ = writeln!(report, "  last commit {} months ago", evidence.months_idle);
+        match &evidence.error {
+            Some(error) => {
+                let _ = writeln!(report, "  broken: {}", error);
+            }
+            None => report.push_str("  broken\n"),
+        }
+        report.push_str("  no indexed project depends on it\n");
+        if let Some(owners) = &project.owners {
+            let _ = writeln!(report, "  owned by {}", owners.owners.join(", "));
+        }
+        candidates += 1;
+    }
+    let _ = writeln!(
+        report,
+        "{} of {} projects are candidates for archiving (idle for {}+ months, broken, no dependents)",
+        candidates,
+        projects.len(),
+        months
+    );
+    report
+}
@@ -0,0 +1,205 @@
+//! Project tags.
+//!
+//! Every project is tagged with its manifest categories and keywords, the
+//! frameworks it depends on, and the tags of any user rule it matches. Rules
+//! live in the inventory config as one line each:
+//!
+//! ```yaml
+//! tags:
+//!   - tag "cli" when depends_on clap
+//!   - tag "web-service" when depends_on axum and is bin
+//!   - tag "internal" when name_matches ^acme- or keyword internal
+//! ```
+//!
+//! Conditions are `depends_on CRATE`, `category SLUG`, `keyword WORD`,
+//! `name_matches REGEX` and `is bin|lib`, combined with `and` and `or` (`and`
+//! binds tighter).
+
+use anyhow::{Context, Result, anyhow, bail};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Dependencies that identify a framework, and the tags they imply
+const FRAMEWORKS: &[(&str, &[&str])] = &[
+    ("tokio", &["tokio", "async"]),
+    ("async-std", &["async-std", "async"]),
+    ("axum", &["axum", "web"]),
+    ("actix-web", &["actix-web", "web"]),
+    ("rocket", &["rocket", "web"]),
+    ("warp", &["warp", "web"]),
+    ("leptos", &["leptos", "web"]),
+    ("bevy", &["bevy", "game"]),
+    ("tauri", &["tauri", "desktop"]),
+    ("eframe", &["egui", "gui"]),
+    ("egui", &["egui", "gui"]),
+    ("iced", &["iced", "gui"]),
+    ("diesel", &["diesel", "database"]),
+    ("sqlx", &["sqlx", "database"]),
+    ("tonic", &["tonic", "grpc"]),
+];
+
+/// What tagging looks at in one project
+#[derive(Debug, Clone)]
+pub struct Facts<'a> {
+    pub name: &'a str,
+    pub dependencies: &'a [String],
+    pub categories: &'a [String],
+    pub keywords: &'a [String],
+    pub has_bin: bool,
+    pub has_lib: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    DependsOn(String),
+    Category(String),
+    Keyword(String),
+    NameMatches(Regex),
+    IsBin,
+    IsLib,
+}
+
+impl Condition {
+    fn holds(&self, facts: &Facts<'_>) -> bool {
+        match self {
+            Condition::DependsOn(krate) => facts.dependencies.iter().any(|d| d == krate),
+            Condition::Category(slug) => facts.categories.iter().any(|c| c == slug),
+            Condition::Keyword(word) => facts.keywords.iter().any(|k| k.eq_ignore_ascii_case(word)),
+            Condition::NameMatches(regex) => regex.is_match(facts.name),
+            Condition::IsBin => facts.has_bin,
+            Condition::IsLib => facts.has_lib,
+        }
+    }
+}
+
+/// `tag "NAME" when CONDITION ...`
+#[derive(Debug, Clone)]
+pub struct TagRule {
+    tag: String,
+    /// Alternatives of the `or`, each a conjunction of conditions
+    any_of: Vec<Vec<Condition>>,
+}
+
+impl TagRule {
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn matches(&self, facts: &Facts<'_>) -> bool {
+        self.any_of
+            .iter()
+            .any(|all| all.iter().all(|condition| condition.holds(facts)))
+    }
+}
+
+impl FromStr for TagRule {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        let syntax = || anyhow!("expected `tag \"NAME\" when CONDITION`, got `{}`", rule);
+        let rest = rule
+            .trim()
+            .strip_prefix("tag ")
+            .ok_or_else(syntax)?
+            .trim_start();
+        let rest = rest.strip_prefix('"').ok_or_else(syntax)?;
+        let (tag, rest) = rest.split_once('"').ok_or_else(syntax)?;
+        let rest = rest.trim_start().strip_prefix("when ").ok_or_else(syntax)?;
+        if tag.is_empty() {
+            bail!("empty tag name in `{}`", rule);
+        }
+
+        let mut any_of = vec![Vec::new()];
+        let mut words = rest.split_whitespace();
+        loop {
+            let condition = match (words.next(), words.next()) {
+                (Some("depends_on"), Some(krate)) => Condition::DependsOn(krate.to_string()),
+                (Some("category"), Some(slug)) => Condition::Category(slug.to_string()),
+                (Some("keyword"), Some(word)) => Condition::Keyword(word.to_string()),
+                (Some("name_matches"), Some(pattern)) => Condition::NameMatches(
+                    Regex::new(pattern).with_context(|| format!("invalid regex in `{}`", rule))?,
+                ),
+                (Some("is"), Some("bin")) => Condition::IsBin,
+                (Some("is"), Some("lib")) => Condition::IsLib,
+                (Some(other), _) => bail!("unknown condition `{}` in `{}`", other, rule),
+                (None, _) => bail!("missing condition in `{}`", rule),
+            };
+            if let Some(all) = any_of.last_mut() {
+                all.push(condition);
+            }
+            match words.next() {
+                None => break,
+                Some("and") => {}
+                Some("or") => any_of.push(Vec::new()),
+                Some(other) => bail!("expected `and` or `or`, got `{}` in `{}`", other, rule),
+            }
+        }
+
+        Ok(Self {
+            // Tags are matched case-insensitively, like manifest keywords
+            tag: tag.to_lowercase(),
+            any_of,
+        })
+    }
+}
+
+/// The inventory config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct InventoryConfig {
+    /// Tagging rules, one `tag "NAME" when ...` per entry
+    pub tags: Vec<String>,
+}
+
+impl InventoryConfig {
+    /// `<config dir>/krater/inventory.yaml`, next to the kargo config
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("rs", "", "krater")
+            .map(|dirs| dirs.config_dir().join("inventory.yaml"))
+    }
+
+    /// Load `path`, or the default file if it exists; no rules otherwise
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path().filter(|p| p.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_yaml_ok::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn rules(&self) -> Result<Vec<TagRule>> {
+        self.tags.iter().map(|rule| rule.parse()).collect()
+    }
+}
+
+/// All tags for a project, sorted and without duplicates
+pub fn infer(facts: &Facts<'_>, rules: &[TagRule]) -> Vec<String> {
+    let mut tags: Vec<String> = facts
+        .categories
+        .iter()
+        .chain(facts.keywords)
+        .map(|tag| tag.to_lowercase())
+        .collect();
+    for (dependency, implied) in FRAMEWORKS {
+        if facts.dependencies.iter().any(|d| d == dependency) {
+            tags.extend(implied.iter().map(|tag| tag.to_string()));
+        }
+    }
+    tags.extend(
+        rules
+            .iter()
+            .filter(|rule| rule.matches(facts))
+            .map(|rule| rule.tag().to_string()),
+    );
+    tags.sort();
+    tags.dedup();
+    tags
+}
@@ -0,0 +1,147 @@
+//! Triage of projects that fail `cargo check`.
+//!
+//! The first error cargo prints is classified into a [`Cause`], and where the
+//! cause has a known remedy a command that applies it is suggested. Both end
+//! up in the project's `indicators` next to the error itself.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Error lines are cut to this many characters in the index
+const MAX_ERROR_LEN: usize = 200;
+
+static TOOLCHAIN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"toolchain '([^']+)' is not installed").unwrap());
+static UNDECLARED_CRATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?:use of undeclared crate or module|use of unresolved module or unlinked crate|cannot find module or crate|can't find crate for) `(\w+)`",
+    )
+    .unwrap()
+});
+static UNAVAILABLE_DEPENDENCY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?:no matching package named|failed to load source for dependency|failed to get) `([\w-]+)`",
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    MissingDependency,
+    Edition,
+    ToolchainMissing,
+    YankedCrate,
+    /// Anything else, usually an ordinary compile error
+    Other,
+}
+
+impl Cause {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Cause::MissingDependency => "missing-dependency",
+            Cause::Edition => "edition",
+            Cause::ToolchainMissing => "toolchain-missing",
+            Cause::YankedCrate => "yanked-crate",
+            Cause::Other => "other",
+        }
+    }
+}
+
+/// Why a project is broken, and what might fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Triage {
+    pub cause: Cause,
+    /// First line of the first error
+    pub error: String,
+    pub suggestion: Option<String>,
+}
+
+impl Triage {
+    /// Classify the first error in the `cargo check` output of the project
+    /// in `project_dir`
+    pub fn from_stderr(stderr: &str, project_dir: &str) -> Option<Self> {
+        let block = first_error(stderr)?;
+        let manifest = format!("{}/Cargo.toml", project_dir);
+        let error = block
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(MAX_ERROR_LEN)
+            .collect();
+
+        let (cause, suggestion) = if let Some(toolchain) = TOOLCHAIN.captures(&block) {
+            (
+                Cause::ToolchainMissing,
+                Some(format!("rustup toolchain install {}", &toolchain[1])),
+            )
+        } else if block.contains("rustup could not choose a version") {
+            (
+                Cause::ToolchainMissing,
+                Some("rustup default stable".to_string()),
+            )
+        } else if block.contains("yanked") {
+            (
+                Cause::YankedCrate,
+                Some(format!("kargo upgrade {}", project_dir)),
+            )
+        } else if block.contains("edition20") || block.contains("`edition`") {
+            // The manifest asks for an edition this cargo doesn't know yet
+            (Cause::Edition, Some("rustup update stable".to_string()))
+        } else if block.contains("in Rust 2015") || block.contains("in Rust 2018") {
+            (
+                Cause::Edition,
+                Some(format!(
+                    "kargo cargo fix --edition --allow-dirty --manifest-path {}",
+                    manifest
+                )),
+            )
+        } else if let Some(dependency) = UNAVAILABLE_DEPENDENCY.captures(&block) {
+            (
+                Cause::MissingDependency,
+                Some(format!(
+                    "kargo cargo remove {} --manifest-path {}",
+                    &dependency[1], manifest
+                )),
+            )
+        } else if let Some(krate) = UNDECLARED_CRATE.captures(&block) {
+            (
+                Cause::MissingDependency,
+                Some(format!(
+                    "kargo cargo add {} --manifest-path {}",
+                    &krate[1], manifest
+                )),
+            )
+        } else {
+            (Cause::Other, None)
+        };
+
+        Some(Self {
+            cause,
+            error,
+            suggestion,
+        })
+    }
+
+    /// Record the triage in a project's indicators
+    pub fn record(&self, indicators: &mut HashMap<String, String>) {
+        indicators.insert("broken_reason".to_string(), self.cause.as_str().to_string());
+        indicators.insert("first_error".to_string(), self.error.clone());
+        if let Some(suggestion) = &self.suggestion {
+            indicators.insert("suggested_fix".to_string(), suggestion.clone());
+        }
+    }
+}
+
+/// The first error message of `stderr`, including the `Caused by:` chain and
+/// notes cargo prints below it
+fn first_error(stderr: &str) -> Option<String> {
+    let mut lines = stderr.lines().skip_while(|line| !line.starts_with("error"));
+    let first = lines.next()?;
+    let mut block = vec![first];
+    block.extend(
+        lines.take_while(|line| !line.starts_with("error") && !line.starts_with("warning")),
+    );
+    Some(block.join("\n"))
+}
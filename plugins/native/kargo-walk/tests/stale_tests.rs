@@ -0,0 +1,197 @@
+use cargo_toml::Manifest;
+use kargo_plugin_api::owners::{OwnerSource, Ownership};
+use kargo_walk::index::{
+    ProjectInfo, ProjectStatus, ProjectType, dependency_packages, link_dependents,
+};
+use kargo_walk::stale::{Evidence, assess, last_commit, report};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+const MONTH: i64 = 2_629_746;
+const NOW: i64 = 1_760_000_000;
+
+fn project(name: &str, status: ProjectStatus, months_idle: i64) -> ProjectInfo {
+    ProjectInfo {
+        path: format!("/forge/{}", name),
+        name: name.to_string(),
+        version: "0.1.0".to_string(),
+        description: None,
+        project_type: ProjectType::Library,
+        status,
+        dependencies: Vec::new(),
+        tags: Vec::new(),
+        owners: None,
+        last_commit: Some(NOW - months_idle * MONTH),
+        dependents: Vec::new(),
+        is_workspace: false,
+        workspace_members: Vec::new(),
+        indicators: HashMap::new(),
+    }
+}
+
+#[test]
+fn test_only_idle_broken_unreferenced_projects_are_candidates() {
+    let mut candidate = project("old", ProjectStatus::Broken, 14);
+    candidate
+        .indicators
+        .insert("first_error".to_string(), "error: oops".to_string());
+    assert_eq!(
+        assess(&candidate, 12, NOW),
+        Some(Evidence {
+            months_idle: 14,
+            error: Some("error: oops".to_string()),
+        })
+    );
+    // Exactly the threshold is idle enough
+    assert_eq!(
+        assess(&project("old", ProjectStatus::Broken, 12), 12, NOW),
+        Some(Evidence {
+            months_idle: 12,
+            error: None,
+        })
+    );
+
+    assert_eq!(
+        assess(&project("recent", ProjectStatus::Broken, 11), 12, NOW),
+        None
+    );
+    assert_eq!(
+        assess(&project("working", ProjectStatus::Working, 14), 12, NOW),
+        None
+    );
+    assert_eq!(
+        assess(&project("unchecked", ProjectStatus::Unknown, 14), 12, NOW),
+        None
+    );
+    let mut used = project("used", ProjectStatus::Broken, 14);
+    used.dependents.push("app".to_string());
+    assert_eq!(assess(&used, 12, NOW), None);
+    let mut untracked = project("untracked", ProjectStatus::Broken, 14);
+    untracked.last_commit = None;
+    assert_eq!(assess(&untracked, 12, NOW), None);
+}
+
+#[test]
+fn test_report_lists_the_evidence_against_each_candidate() {
+    let mut old = project("old", ProjectStatus::Broken, 14);
+    old.indicators.insert(
+        "first_error".to_string(),
+        "error[E0308]: mismatched types".to_string(),
+    );
+    old.owners = Some(Ownership {
+        owners: vec!["@acme/platform".to_string()],
+        source: OwnerSource::Codeowners,
+    });
+    let projects = [
+        old,
+        project("older", ProjectStatus::Broken, 30),
+        project("working", ProjectStatus::Working, 30),
+    ];
+
+    assert_eq!(
+        report(&projects, 12, NOW),
+        "old 0.1.0 (/forge/old)\n\
+         \x20 last commit 14 months ago\n\
+         \x20 broken: error[E0308]: mismatched types\n\
+         \x20 no indexed project depends on it\n\
+         \x20 owned by @acme/platform\n\
+         older 0.1.0 (/forge/older)\n\
+         \x20 last commit 30 months ago\n\
+         \x20 broken\n\
+         \x20 no indexed project depends on it\n\
+         2 of 3 projects are candidates for archiving (idle for 12+ months, broken, no dependents)\n"
+    );
+    assert_eq!(
+        report(&projects, 36, NOW),
+        "0 of 3 projects are candidates for archiving (idle for 36+ months, broken, no dependents)\n"
+    );
+}
+
+#[test]
+fn test_renamed_dependencies_link_to_the_real_package() {
+    let manifest = Manifest::from_str(
+        r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+core = { package = "acme-core", path = "../acme-core" }
+serde = "1"
+"#,
+    )
+    .unwrap();
+    let mut dependencies = dependency_packages(&manifest);
+    dependencies.sort();
+    assert_eq!(dependencies, ["acme-core", "serde"]);
+
+    let mut app = project("app", ProjectStatus::Working, 0);
+    app.dependencies = dependencies;
+    let mut tool = project("tool", ProjectStatus::Working, 0);
+    tool.dependencies = vec!["acme-core".to_string(), "acme-core".to_string()];
+    let mut projects = [
+        app,
+        tool,
+        project("acme-core", ProjectStatus::Broken, 20),
+        project("core", ProjectStatus::Broken, 20),
+    ];
+    link_dependents(&mut projects);
+
+    assert_eq!(projects[2].dependents, ["app", "tool"]);
+    // Only the key in the manifest is called `core`
+    assert!(projects[3].dependents.is_empty());
+    assert_eq!(assess(&projects[2], 12, NOW), None);
+    assert!(assess(&projects[3], 12, NOW).is_some());
+}
+
+/// Run git in `dir` as a test user, committing at `date`
+fn git(dir: &Path, date: i64, args: &[&str]) {
+    let date = format!("@{} +0000", date);
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_DATE", &date)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn test_last_commit_is_the_last_one_touching_the_project() {
+    let repo = tempfile::tempdir().unwrap();
+    // Not in a git repository
+    assert_eq!(last_commit(repo.path()), None);
+
+    git(repo.path(), 0, &["init", "--quiet"]);
+    let project = repo.path().join("project");
+    let other = repo.path().join("other");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::create_dir_all(&other).unwrap();
+    std::fs::write(project.join("Cargo.toml"), "[package]\n").unwrap();
+    git(repo.path(), 0, &["add", "project"]);
+    git(
+        repo.path(),
+        1_700_000_000,
+        &["commit", "--quiet", "-m", "project"],
+    );
+    // Nothing committed below `other` yet
+    assert_eq!(last_commit(&other), None);
+
+    std::fs::write(other.join("Cargo.toml"), "[package]\n").unwrap();
+    git(repo.path(), 0, &["add", "other"]);
+    git(
+        repo.path(),
+        1_750_000_000,
+        &["commit", "--quiet", "-m", "other"],
+    );
+
+    assert_eq!(last_commit(&project), Some(1_700_000_000));
+    assert_eq!(last_commit(&other), Some(1_750_000_000));
+    assert_eq!(last_commit(repo.path()), Some(1_750_000_000));
+}
@@ -0,0 +1,119 @@
+use kargo_walk::tags::{Facts, TagRule, infer};
+
+fn facts<'a>(
+    name: &'a str,
+    dependencies: &'a [String],
+    categories: &'a [String],
+    keywords: &'a [String],
+) -> Facts<'a> {
+    Facts {
+        name,
+        dependencies,
+        categories,
+        keywords,
+        has_bin: true,
+        has_lib: false,
+    }
+}
+
+fn strings(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+fn rule(text: &str) -> TagRule {
+    text.parse().unwrap()
+}
+
+#[test]
+fn test_rules_combine_conditions_with_and_before_or() {
+    let dependencies = strings(&["axum", "serde"]);
+    let keywords = strings(&["Internal"]);
+    let service = facts("acme-billing", &dependencies, &[], &keywords);
+    let library = Facts {
+        has_bin: false,
+        has_lib: true,
+        ..facts("parser", &[], &[], &[])
+    };
+
+    let web = rule("tag \"web-service\" when depends_on axum and is bin");
+    assert_eq!(web.tag(), "web-service");
+    assert!(web.matches(&service));
+    assert!(!web.matches(&library));
+
+    let internal = rule("  tag  \"internal\" when name_matches ^acme- or keyword internal");
+    assert!(internal.matches(&service));
+    assert!(!internal.matches(&library));
+
+    let both = rule("tag \"x\" when is lib or depends_on serde and category cli");
+    assert!(both.matches(&library));
+    // `and` binds tighter: serde alone isn't enough
+    assert!(!both.matches(&service));
+}
+
+#[test]
+fn test_rule_tags_are_lowercased() {
+    assert_eq!(rule("tag \"Web-Service\" when is bin").tag(), "web-service");
+}
+
+#[test]
+fn test_malformed_rules_are_rejected() {
+    let error = |text: &str| text.parse::<TagRule>().unwrap_err().to_string();
+    let syntax = "expected `tag \"NAME\" when CONDITION`";
+    assert!(error("label \"cli\" when is bin").starts_with(syntax));
+    assert!(error("tag cli when is bin").starts_with(syntax));
+    assert!(error("tag \"cli when is bin").starts_with(syntax));
+    assert!(error("tag \"cli\" if is bin").starts_with(syntax));
+    assert_eq!(
+        error("tag \"\" when is bin"),
+        "empty tag name in `tag \"\" when is bin`"
+    );
+    assert!(error("tag \"cli\" when").starts_with(syntax));
+    assert_eq!(
+        error("tag \"cli\" when uses clap"),
+        "unknown condition `uses` in `tag \"cli\" when uses clap`"
+    );
+    assert_eq!(
+        error("tag \"cli\" when is test"),
+        "unknown condition `is` in `tag \"cli\" when is test`"
+    );
+    assert_eq!(
+        error("tag \"cli\" when is bin but depends_on clap"),
+        "expected `and` or `or`, got `but` in `tag \"cli\" when is bin but depends_on clap`"
+    );
+    assert_eq!(
+        error("tag \"cli\" when is bin and"),
+        "missing condition in `tag \"cli\" when is bin and`"
+    );
+    assert_eq!(
+        error("tag \"cli\" when name_matches ("),
+        "invalid regex in `tag \"cli\" when name_matches (`"
+    );
+}
+
+#[test]
+fn test_tags_are_inferred_from_categories_keywords_frameworks_and_rules() {
+    let dependencies = strings(&["tokio", "axum", "clap"]);
+    let categories = strings(&["command-line-utilities", "Web-Programming"]);
+    let keywords = strings(&["Async", "cli"]);
+    let project = facts("acme-server", &dependencies, &categories, &keywords);
+    let rules = [
+        rule("tag \"CLI\" when depends_on clap"),
+        rule("tag \"internal\" when name_matches ^acme-"),
+        rule("tag \"gui\" when depends_on egui"),
+    ];
+
+    assert_eq!(
+        infer(&project, &rules),
+        [
+            "async",
+            "axum",
+            "cli",
+            "command-line-utilities",
+            "internal",
+            "tokio",
+            "web",
+            "web-programming",
+        ]
+    );
+    assert!(infer(&facts("plain", &[], &[], &[]), &rules).is_empty());
+}
@@ -0,0 +1,159 @@
+use kargo_walk::triage::{Cause, Triage};
+use std::collections::HashMap;
+
+const PROJECT: &str = "/forge/demo";
+
+/// `cargo check` stderr, the cause and the suggested fix it should triage to
+const SAMPLES: &[(&str, Cause, Option<&str>)] = &[
+    (
+        "error: toolchain 'nightly-2023-01-01-x86_64-unknown-linux-gnu' is not installed\n\
+         help: run `rustup toolchain install nightly-2023-01-01-x86_64-unknown-linux-gnu` to install it\n",
+        Cause::ToolchainMissing,
+        Some("rustup toolchain install nightly-2023-01-01-x86_64-unknown-linux-gnu"),
+    ),
+    (
+        "error: rustup could not choose a version of cargo to run, because one wasn't specified explicitly, and no default is configured.\n\
+         help: run 'rustup default stable' to download the latest stable release of Rust and set it as your default toolchain.\n",
+        Cause::ToolchainMissing,
+        Some("rustup default stable"),
+    ),
+    (
+        "    Updating crates.io index\n\
+         error: failed to select a version for the requirement `time = \"=0.3.20\"`\n\
+         candidate versions found which didn't match: 0.3.36, 0.3.35\n\
+         location searched: crates.io index\n\
+         required by package `demo v0.1.0 (/forge/demo)`\n\
+         version 0.3.20 is yanked\n",
+        Cause::YankedCrate,
+        Some("kargo upgrade /forge/demo"),
+    ),
+    (
+        "error: failed to parse manifest at `/forge/demo/Cargo.toml`\n\
+         \n\
+         Caused by:\n\
+         \x20 feature `edition2024` is required\n\
+         \n\
+         \x20 The package requires the Cargo feature called `edition2024`, but that feature is not stabilized in this version of Cargo (1.80.0).\n",
+        Cause::Edition,
+        Some("rustup update stable"),
+    ),
+    (
+        "    Checking demo v0.1.0 (/forge/demo)\n\
+         error[E0670]: `async fn` is not permitted in Rust 2015\n\
+         \x20--> src/main.rs:1:1\n\
+         \x20 |\n\
+         1 | async fn run() {}\n\
+         \x20 | ^^^^^ to use `async fn`, switch to Rust 2018 or later\n\
+         \x20 |\n\
+         \x20 = help: pass `--edition 2024` to `rustc`\n",
+        Cause::Edition,
+        Some("kargo cargo fix --edition --allow-dirty --manifest-path /forge/demo/Cargo.toml"),
+    ),
+    (
+        "    Updating crates.io index\n\
+         error: no matching package named `serde_jsn` found\n\
+         location searched: crates.io index\n\
+         required by package `demo v0.1.0 (/forge/demo)`\n",
+        Cause::MissingDependency,
+        Some("kargo cargo remove serde_jsn --manifest-path /forge/demo/Cargo.toml"),
+    ),
+    (
+        "error: failed to get `acme-core` as a dependency of package `demo v0.1.0 (/forge/demo)`\n\
+         \n\
+         Caused by:\n\
+         \x20 failed to load source for dependency `acme-core`\n",
+        Cause::MissingDependency,
+        Some("kargo cargo remove acme-core --manifest-path /forge/demo/Cargo.toml"),
+    ),
+    (
+        "    Checking demo v0.1.0 (/forge/demo)\n\
+         error[E0433]: failed to resolve: use of undeclared crate or module `regex`\n\
+         \x20--> src/main.rs:1:5\n\
+         \x20 |\n\
+         1 | use regex::Regex;\n\
+         \x20 |     ^^^^^ use of undeclared crate or module `regex`\n",
+        Cause::MissingDependency,
+        Some("kargo cargo add regex --manifest-path /forge/demo/Cargo.toml"),
+    ),
+    (
+        "error[E0433]: failed to resolve: use of unresolved module or unlinked crate `anyhow`\n\
+         \x20--> src/lib.rs:3:5\n",
+        Cause::MissingDependency,
+        Some("kargo cargo add anyhow --manifest-path /forge/demo/Cargo.toml"),
+    ),
+    (
+        "error[E0463]: can't find crate for `rand`\n\
+         \x20--> src/lib.rs:1:1\n",
+        Cause::MissingDependency,
+        Some("kargo cargo add rand --manifest-path /forge/demo/Cargo.toml"),
+    ),
+    (
+        "    Checking demo v0.1.0 (/forge/demo)\n\
+         warning: unused variable: `x`\n\
+         \x20--> src/main.rs:2:9\n\
+         error[E0308]: mismatched types\n\
+         \x20--> src/main.rs:3:18\n\
+         \x20 |\n\
+         3 |     let n: u32 = \"one\";\n\
+         \x20 |            ---   ^^^^^ expected `u32`, found `&str`\n\
+         error: could not compile `demo` (bin \"demo\") due to 1 previous error; 1 warning emitted\n",
+        Cause::Other,
+        None,
+    ),
+];
+
+#[test]
+fn test_cargo_errors_are_classified() {
+    for (stderr, cause, suggestion) in SAMPLES {
+        let triage = Triage::from_stderr(stderr, PROJECT).expect("no error found");
+        assert_eq!(triage.cause, *cause, "{}", stderr);
+        assert_eq!(triage.suggestion.as_deref(), *suggestion, "{}", stderr);
+    }
+}
+
+#[test]
+fn test_only_the_first_error_is_classified() {
+    // The undeclared crate is named in a later error than the first
+    let stderr = "error[E0308]: mismatched types\n\
+                  \x20--> src/main.rs:3:18\n\
+                  error[E0433]: failed to resolve: use of undeclared crate or module `regex`\n";
+    let triage = Triage::from_stderr(stderr, PROJECT).unwrap();
+    assert_eq!(triage.cause, Cause::Other);
+    assert_eq!(triage.error, "error[E0308]: mismatched types");
+}
+
+#[test]
+fn test_output_without_errors_is_not_triaged() {
+    let stderr = "    Checking demo v0.1.0 (/forge/demo)\n\
+                  warning: unused import: `std::fmt`\n\
+                  \x20   Finished `dev` profile [unoptimized + debuginfo] target(s) in 0.41s\n";
+    assert_eq!(Triage::from_stderr(stderr, PROJECT), None);
+    assert_eq!(Triage::from_stderr("", PROJECT), None);
+}
+
+#[test]
+fn test_triage_is_recorded_in_the_indicators() {
+    let long = format!("error: {}", "x".repeat(300));
+    let triage = Triage::from_stderr(&long, PROJECT).unwrap();
+    // Error lines are cut short for the index
+    assert_eq!(triage.error.chars().count(), 200);
+
+    let mut indicators = HashMap::new();
+    Triage::from_stderr(SAMPLES[0].0, PROJECT)
+        .unwrap()
+        .record(&mut indicators);
+    assert_eq!(indicators["broken_reason"], "toolchain-missing");
+    assert_eq!(
+        indicators["first_error"],
+        "error: toolchain 'nightly-2023-01-01-x86_64-unknown-linux-gnu' is not installed"
+    );
+    assert_eq!(
+        indicators["suggested_fix"],
+        "rustup toolchain install nightly-2023-01-01-x86_64-unknown-linux-gnu"
+    );
+
+    let mut indicators = HashMap::new();
+    triage.record(&mut indicators);
+    assert_eq!(indicators["broken_reason"], "other");
+    assert!(!indicators.contains_key("suggested_fix"));
+}
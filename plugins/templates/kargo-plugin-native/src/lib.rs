@@ -1,4 +1,4 @@
-use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginCommand};
+use kargo_plugin_api::{BoxFuture, ExecutionContext, PluginArgs, PluginCommand};
 use kargo_plugin_native::{kargo_plugin, NativePlugin, PluginMetadata};
 use clap::{Arg, Command};
 use anyhow::Result;
@@ -15,14 +15,14 @@ impl {{plugin_name | pascal_case}}Plugin {
     async fn run_async(&self, ctx: ExecutionContext) -> Result<()> {
         info!("Running {{plugin_name}} plugin");
         
-        // Parse arguments from the matched args
-        let args = ctx.matched_args;
+        // kargo parsed the arguments with `command()` already
+        let example = ctx.args.value("example");
         
         // TODO: Implement your plugin logic here
         println!("Hello from {{plugin_name}}!");
         
-        if args.len() > 1 {
-            println!("Arguments received: {:?}", &args[1..]);
+        if let Some(example) = example {
+            println!("--example {}", example);
         }
         
         Ok(())
@@ -47,11 +47,31 @@ impl NativePlugin for {{plugin_name | pascal_case}}Plugin {
     fn execute(&self, args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
         // Convert sync execute to async
         let ctx = ExecutionContext {
+            args: PluginArgs::parse(self.command(), &args)?,
             matched_args: args,
             current_dir: std::env::current_dir()?,
             config_dir: dirs::config_dir()
                 .unwrap_or_else(|| std::path::PathBuf::from("."))
                 .join("kargo"),
+            data_dir: dirs::data_dir()
+                .unwrap_or_else(|| std::path::PathBuf::from("."))
+                .join("kargo")
+                .join("plugins")
+                .join("{{plugin_name}}"),
+            cache_dir: dirs::cache_dir()
+                .unwrap_or_else(|| std::path::PathBuf::from("."))
+                .join("kargo")
+                .join("plugins")
+                .join("{{plugin_name}}"),
+            diagnostics: Default::default(),
+            registry: None,
+            target_dir: None,
+            host_version: String::new(),
+            write_guard: Default::default(),
+            events: Default::default(),
+            network: Default::default(),
+            profiler: Default::default(),
+            settings: Default::default(),
         };
         
         // Block on async execution
@@ -91,8 +111,10 @@ kargo_plugin! {
     plugin_type: {{plugin_name | pascal_case}}Plugin
 }
 
-// The actual extern "C" function that kargo-cli will look for
-#[no_mangle]
-pub extern "C" fn kargo_plugin_create() -> Box<dyn PluginCommand> {
-    Box::new({{plugin_name | pascal_case}}Plugin::new())
-}
\ No newline at end of file
+// The actual extern "C" function that kargo-cli will look for; it catches
+// the plugin's panics so they don't take kargo down with them
+kargo_plugin_api::export_plugin!({{plugin_name | pascal_case}}Plugin::new());
+
+// Lets kargo check the plugin was built against a plugin API it loads. Add
+// `kargo = "<requirement>"` to only load with those kargo versions.
+kargo_plugin_api::export_plugin_compat!();
@@ -1,6 +1,6 @@
 use kargo_plugin_wasm::{
-    kargo_wasm_plugin, ArgDefinition, CommandDefinition, ExecutionResult, 
-    PluginMetadata, WasmPlugin
+    kargo_wasm_plugin, ArgDefinition, Capabilities, CommandDefinition, ExecutionResult, 
+    PluginMetadata, ValueType, WasmPlugin
 };
 use serde_json::Value;
 
@@ -19,9 +19,19 @@ impl WasmPlugin for {{plugin_name | pascal_case}}Plugin {
                     help: "An example argument".to_string(),
                     required: false,
                     takes_value: true,
+                    ..Default::default()
+                },
+                ArgDefinition {
+                    name: "paths".to_string(),
+                    help: "Files to work on".to_string(),
+                    takes_value: true,
+                    multiple: true,
+                    value_type: Some(ValueType::Path),
+                    ..Default::default()
                 },
                 // TODO: Add more arguments as needed
             ],
+            ..Default::default()
         };
         serde_json::to_string(&cmd).unwrap()
     }
@@ -57,6 +67,10 @@ impl WasmPlugin for {{plugin_name | pascal_case}}Plugin {
             description: "{{plugin_description}}".to_string(),
             author: "{{author_name}}".to_string(),
             language: "rust".to_string(),
+            // TODO: Declare the files, hosts and environment variables the
+            // plugin needs, e.g. `network: vec!["api.github.com".to_string()]`
+            capabilities: Capabilities::default(),
+            requires_kargo: None,
         };
         serde_json::to_string(&metadata).unwrap()
     }